@@ -0,0 +1,136 @@
+//! Throughput and latency of the server loop
+//!
+//! Run with `cargo bench`, and compare against a saved baseline with
+//! `cargo bench -- --save-baseline main` then `--baseline main`.
+
+use bioma_tool::schema::{CallToolResult, Content};
+use bioma_tool::testing::TestServer;
+use bioma_tool::tools::{McpTool, ToolContext, ToolError};
+use bioma_tool::transport::{TransportType, WebSocketTransport};
+use bioma_tool::{Client, Server};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct NoopProperties {}
+
+#[derive(Clone, Serialize, McpTool)]
+#[mcp_tool(name = "noop", description = "Does nothing", properties = NoopProperties)]
+struct Noop;
+
+impl Noop {
+    async fn run(
+        &self,
+        _properties: NoopProperties,
+        _context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        Ok(CallToolResult::success(Vec::new()))
+    }
+}
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+/// Round trip of a request doing no work, through the in-process transport
+fn dispatch(c: &mut Criterion) {
+    let runtime = runtime();
+    let server = runtime.block_on(TestServer::start(Server::builder().build()));
+    c.bench_function("dispatch/ping", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { server.ping().await.unwrap() })
+    });
+    runtime.block_on(server.shutdown());
+}
+
+/// Concurrent calls of a tool doing no work
+fn tool_calls(c: &mut Criterion) {
+    let runtime = runtime();
+    let server = runtime.block_on(TestServer::start(Server::builder().tool(Noop).build()));
+    let mut group = c.benchmark_group("tools/call");
+    for concurrency in [1, 16, 64] {
+        group.throughput(Throughput::Elements(concurrency));
+        group.bench_with_input(
+            BenchmarkId::new("noop", concurrency),
+            &concurrency,
+            |b, &concurrency| {
+                b.to_async(&runtime).iter(|| {
+                    let calls = (0..concurrency).map(|_| server.call_tool("noop", json!({})));
+                    async { futures::future::try_join_all(calls).await.unwrap() }
+                })
+            },
+        );
+    }
+    group.finish();
+    runtime.block_on(server.shutdown());
+}
+
+/// Turning large tool results into the JSON sent to clients
+fn serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize/CallToolResult");
+    let many = CallToolResult::success(
+        (0..1000)
+            .map(|i| Content::text(format!("{} {}", i, "x".repeat(1024))))
+            .collect(),
+    );
+    let image = CallToolResult::image("A".repeat(4 * 1024 * 1024), "image/png");
+    for (name, result) in [("1000 texts", many), ("4MB image", image)] {
+        let bytes = serde_json::to_string(&result).unwrap().len();
+        group.throughput(Throughput::Bytes(bytes as u64));
+        group.bench_with_input(BenchmarkId::new("to_string", name), &result, |b, result| {
+            b.iter(|| serde_json::to_string(result).unwrap())
+        });
+        // What a handler returns goes through a `Value` before it's written
+        group.bench_with_input(BenchmarkId::new("via_value", name), &result, |b, result| {
+            b.iter(|| serde_json::to_value(result).unwrap().to_string())
+        });
+    }
+    group.finish();
+}
+
+/// Round trip of a request over a local WebSocket connection
+fn websocket(c: &mut Criterion) {
+    let runtime = runtime();
+    let addr = runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    });
+    let transport = TransportType::WebSocket(WebSocketTransport::new(addr.clone()));
+    let (handle, client) = runtime.block_on(async {
+        let handle = Server::builder().tool(Noop).build().spawn(transport);
+        let url = format!("ws://{}", addr);
+        let client = loop {
+            match Client::connect_websocket(&url).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        client.initialize().await.unwrap();
+        (handle, client)
+    });
+
+    let mut group = c.benchmark_group("websocket");
+    group.bench_function("ping", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { client.ping().await.unwrap() })
+    });
+    group.bench_function("tools/call", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { client.call_tool("noop", json!({})).await.unwrap() })
+    });
+    group.finish();
+
+    drop(client);
+    handle.shutdown();
+    let _ = runtime.block_on(handle.wait());
+}
+
+criterion_group!(benches, dispatch, tool_calls, serialization, websocket);
+criterion_main!(benches);
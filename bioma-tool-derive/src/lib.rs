@@ -0,0 +1,164 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, Expr, ExprLit, Lit, LitInt, LitStr, Meta, Type};
+
+/// Derives `ToolDef` for a tool, generating its definition from the
+/// `schemars` schema of its properties.
+///
+/// ```ignore
+/// /// Echoes back the input message
+/// #[derive(Serialize, McpTool)]
+/// #[mcp_tool(name = "echo", properties = EchoProperties)]
+/// pub struct Echo;
+///
+/// impl Echo {
+///     async fn run(
+///         &self,
+///         properties: EchoProperties,
+///         context: ToolContext,
+///     ) -> Result<CallToolResult, ToolError> {
+///         // ...
+///     }
+/// }
+/// ```
+///
+/// # Attributes
+/// * `name` - The name of the tool
+/// * `description` - What the tool does, defaults to the struct's doc comment
+/// * `properties` - The type of the tool's input properties
+/// * `output` - The type of the tool's structured output, giving it an output schema
+/// * `timeout_secs` - How long a call may run, overriding the server's default
+///
+/// The tool is called through an inherent `run` method taking the
+/// properties and the `ToolContext`.
+#[proc_macro_derive(McpTool, attributes(mcp_tool))]
+pub fn derive_mcp_tool(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut name: Option<LitStr> = None;
+    let mut description: Option<LitStr> = None;
+    let mut properties: Option<Type> = None;
+    let mut output: Option<Type> = None;
+    let mut timeout_secs: Option<LitInt> = None;
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("mcp_tool")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("description") {
+                description = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("properties") {
+                properties = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("output") {
+                output = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("timeout_secs") {
+                let secs: LitInt = meta.value()?.parse()?;
+                secs.base10_parse::<u64>()?;
+                timeout_secs = Some(secs);
+            } else {
+                return Err(meta.error(
+                    "expected `name`, `description`, `properties`, `output` or `timeout_secs`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    let span = Span::call_site();
+    let name = name.ok_or_else(|| syn::Error::new(span, "missing #[mcp_tool(name = \"...\")]"))?;
+    let properties = properties
+        .ok_or_else(|| syn::Error::new(span, "missing #[mcp_tool(properties = Type)]"))?;
+    let description = match description {
+        Some(description) => description,
+        None => doc_comment(input).ok_or_else(|| {
+            syn::Error::new(
+                span,
+                "missing #[mcp_tool(description = \"...\")] or doc comment",
+            )
+        })?,
+    };
+
+    let timeout = timeout_secs.map(|secs| {
+        quote! {
+            fn timeout(&self) -> ::std::option::Option<::std::time::Duration> {
+                ::std::option::Option::Some(::std::time::Duration::from_secs(#secs))
+            }
+        }
+    });
+
+    let def = match &output {
+        Some(_) => quote! {
+            ::bioma_tool::tools::tool_def_with_output::<Self::Properties, Self::Output>(
+                Self::NAME,
+                Self::DESCRIPTION,
+            )
+        },
+        None => quote! {
+            ::bioma_tool::tools::tool_def::<Self::Properties>(Self::NAME, Self::DESCRIPTION)
+        },
+    };
+    let output = match output {
+        Some(output) => quote! { #output },
+        None => quote! { () },
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::bioma_tool::tools::ToolDef for #ident #type_generics #where_clause {
+            const NAME: &'static str = #name;
+            const DESCRIPTION: &'static str = #description;
+            type Properties = #properties;
+            type Output = #output;
+
+            fn def() -> ::bioma_tool::schema::Tool {
+                #def
+            }
+
+            fn call<'a>(
+                &'a self,
+                properties: Self::Properties,
+                context: ::bioma_tool::tools::ToolContext,
+            ) -> impl ::std::future::Future<
+                Output = ::std::result::Result<
+                    ::bioma_tool::schema::CallToolResult,
+                    ::bioma_tool::tools::ToolError,
+                >,
+            > + ::std::marker::Send + 'a {
+                self.run(properties, context)
+            }
+
+            #timeout
+        }
+    })
+}
+
+/// Joins the lines of the item's doc comment
+fn doc_comment(input: &DeriveInput) -> Option<LitStr> {
+    let lines: Vec<String> = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let doc = lines.join(" ");
+    (!doc.is_empty()).then(|| LitStr::new(&doc, Span::call_site()))
+}
@@ -0,0 +1,242 @@
+//! Serves the tools, resources and prompts of several MCP servers as one
+//!
+//! Each downstream server is mounted under a prefix that namespaces what it
+//! offers, so its `search` tool becomes `github.search`. Tool calls and
+//! resource reads are relayed to the server that offers them through a
+//! `Client` connected to it.
+//!
+//! ```ignore
+//! let (github, _child) = Client::spawn_process(Command::new("github-mcp"))?;
+//! let fs = Client::connect_websocket("ws://127.0.0.1:9090").await?;
+//! let server = Aggregator::new()
+//!     .mount("github", github)
+//!     .mount("fs", fs)
+//!     .add_to(Server::builder())
+//!     .await?
+//!     .build();
+//! ```
+
+use crate::error::{ClientError, ResourceError};
+use crate::resources::ResourceTemplateProvider;
+use crate::schema::{self, CallToolResult, ReadResourceResult, ResourceTemplate};
+use crate::server::ServerBuilder;
+use crate::tools::{ToolCallHandler, ToolContext, ToolError};
+use crate::Client;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use tracing::info;
+
+/// Separates a mounted server's prefix from the names of what it offers
+pub const NAMESPACE_SEPARATOR: char = '.';
+
+/// Mounts downstream servers, each under a prefix of its own
+#[derive(Default)]
+pub struct Aggregator {
+    backends: Vec<(String, Client)>,
+}
+
+impl Aggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mounts the server `client` is connected to under `prefix`
+    pub fn mount(mut self, prefix: impl Into<String>, client: Client) -> Self {
+        self.backends.push((prefix.into(), client));
+        self
+    }
+
+    /// Initializes every mounted server and adds what it offers to `builder`
+    ///
+    /// What each server offers is listed once, so tools it adds later aren't
+    /// served. Static resources are listed but only those matching one of the
+    /// server's templates can be read.
+    pub async fn add_to(self, mut builder: ServerBuilder) -> Result<ServerBuilder, ClientError> {
+        for (prefix, client) in self.backends {
+            let initialized = client.initialize().await?;
+            let capabilities = initialized.capabilities;
+            info!(
+                "Mounting {} {} as {}",
+                initialized.server_info.name, initialized.server_info.version, prefix
+            );
+
+            if capabilities.tools.is_some() {
+                for def in client.list_tools().await? {
+                    builder = builder.tool(RemoteTool {
+                        name: def.name.clone(),
+                        def: schema::Tool {
+                            name: namespaced(&prefix, &def.name),
+                            ..def
+                        },
+                        client: client.clone(),
+                    });
+                }
+            }
+            if capabilities.resources.is_some() {
+                for resource in client.list_resources().await? {
+                    builder = builder.resource(schema::Resource {
+                        name: namespaced(&prefix, &resource.name),
+                        ..resource
+                    });
+                }
+                for template in client.list_resource_templates().await? {
+                    builder = builder.resource_template(RemoteTemplate {
+                        template: ResourceTemplate {
+                            name: namespaced(&prefix, &template.name),
+                            ..template
+                        },
+                        client: client.clone(),
+                    });
+                }
+            }
+            if capabilities.prompts.is_some() {
+                for prompt in client.list_prompts().await? {
+                    builder = builder.prompt(schema::Prompt {
+                        name: namespaced(&prefix, &prompt.name),
+                        ..prompt
+                    });
+                }
+            }
+        }
+        Ok(builder)
+    }
+}
+
+/// `name` in the namespace of the server mounted under `prefix`
+pub fn namespaced(prefix: &str, name: &str) -> String {
+    format!("{}{}{}", prefix, NAMESPACE_SEPARATOR, name)
+}
+
+/// A tool of a mounted server, called through its client
+struct RemoteTool {
+    /// What the mounted server calls the tool
+    name: String,
+    def: schema::Tool,
+    client: Client,
+}
+
+impl ToolCallHandler for RemoteTool {
+    fn call_boxed<'a>(
+        &'a self,
+        args: Option<BTreeMap<String, Value>>,
+        _context: ToolContext,
+    ) -> Pin<Box<dyn Future<Output = Result<CallToolResult, ToolError>> + Send + 'a>> {
+        Box::pin(async move {
+            let arguments = Value::Object(args.unwrap_or_default().into_iter().collect());
+            self.client
+                .call_tool(&self.name, arguments)
+                .await
+                .map_err(|e| ToolError::Execution(format!("{} failed: {}", self.def.name, e)))
+        })
+    }
+
+    fn def(&self) -> schema::Tool {
+        self.def.clone()
+    }
+}
+
+/// A resource template of a mounted server, read through its client
+struct RemoteTemplate {
+    template: ResourceTemplate,
+    client: Client,
+}
+
+impl ResourceTemplateProvider for RemoteTemplate {
+    fn template(&self) -> ResourceTemplate {
+        self.template.clone()
+    }
+
+    fn read<'a>(
+        &'a self,
+        uri: &'a str,
+        _variables: HashMap<String, String>,
+    ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>> {
+        Box::pin(async move {
+            self.client
+                .read_resource(uri)
+                .await
+                .map_err(ResourceError::failed)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_tool_text, TestServer};
+    use crate::tools::echo::Echo;
+    use crate::Server;
+    use serde_json::json;
+
+    struct Notes;
+
+    impl ResourceTemplateProvider for Notes {
+        fn template(&self) -> ResourceTemplate {
+            ResourceTemplate {
+                name: "notes".to_string(),
+                uri_template: "notes://{id}".to_string(),
+                annotations: None,
+                description: None,
+                mime_type: None,
+            }
+        }
+
+        fn read<'a>(
+            &'a self,
+            uri: &'a str,
+            variables: HashMap<String, String>,
+        ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>>
+        {
+            Box::pin(async move {
+                Ok(ReadResourceResult {
+                    contents: vec![
+                        json!({ "uri": uri, "text": format!("note {}", variables["id"]) }),
+                    ],
+                    meta: None,
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aggregated_servers() {
+        let (first, first_client) = Server::builder().tool(Echo).build().spawn_in_process();
+        let (second, second_client) = Server::builder()
+            .tool(Echo)
+            .resource_template(Notes)
+            .build()
+            .spawn_in_process();
+
+        let builder = Aggregator::new()
+            .mount("first", first_client)
+            .mount("second", second_client)
+            .add_to(Server::builder())
+            .await
+            .unwrap();
+        let server = TestServer::start(builder.build()).await;
+
+        let tools: Vec<_> = server
+            .list_tools()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(tools, vec!["first.echo", "second.echo"]);
+        let result = server.call("second.echo", json!({ "message": "hi" })).await;
+        assert_tool_text(&result, "hi");
+        // Arguments are checked against the mounted server's schema before being relayed
+        assert!(server.call_tool("first.echo", json!({})).await.is_err());
+
+        let templates = server.list_resource_templates().await.unwrap();
+        assert_eq!(templates[0].name, "second.notes");
+        let note = server.read_resource("notes://7").await.unwrap();
+        assert_eq!(note.contents[0]["text"], "note 7");
+
+        server.shutdown().await;
+        first.shutdown();
+        second.shutdown();
+    }
+}
@@ -0,0 +1,433 @@
+//! Confirmation of destructive tool calls by a human before they run
+//!
+//! A tool needs approval when its policy sets `require_approval`, or when
+//! unset, when it's annotated as destructive. Approval is asked of the
+//! calling client's user through elicitation when the client supports it,
+//! and otherwise of the server's `Approver`, such as a `WebhookApprover`.
+
+use crate::error::ApprovalError;
+use crate::policy::ToolPolicy;
+use crate::schema::{self, CallToolResult, ElicitRequestParamsRequestedSchema, ElicitResultAction};
+use crate::tools::ToolContext;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+/// Time a call waits for approval before it's refused, unless set otherwise
+pub const DEFAULT_APPROVAL_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A tool call waiting for approval
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ApprovalRequest {
+    pub tool: String,
+    pub arguments: Option<BTreeMap<String, Value>>,
+    /// Session the call was made from
+    pub session: String,
+}
+
+/// What an approver decided about a call
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct ApprovalDecision {
+    pub approved: bool,
+    /// Why, shown to the model when the call is refused
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl ApprovalDecision {
+    pub fn approve() -> Self {
+        Self {
+            approved: true,
+            reason: None,
+        }
+    }
+
+    pub fn deny(reason: impl Into<String>) -> Self {
+        Self {
+            approved: false,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+/// Decides on calls for clients that can't ask their user
+pub trait Approver: fmt::Debug + Send + Sync {
+    fn decide<'a>(
+        &'a self,
+        request: &'a ApprovalRequest,
+    ) -> Pin<Box<dyn Future<Output = Result<ApprovalDecision, ApprovalError>> + Send + 'a>>;
+}
+
+/// Asks a web service to decide, posting it the `ApprovalRequest` as JSON
+///
+/// The service answers with `{"approved": true}`, or `{"approved": false,
+/// "reason": "..."}`, possibly after waiting on a human itself.
+#[derive(Clone, Debug)]
+pub struct WebhookApprover {
+    url: Url,
+    client: reqwest::Client,
+}
+
+impl WebhookApprover {
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl Approver for WebhookApprover {
+    fn decide<'a>(
+        &'a self,
+        request: &'a ApprovalRequest,
+    ) -> Pin<Box<dyn Future<Output = Result<ApprovalDecision, ApprovalError>> + Send + 'a>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(self.url.clone())
+                .json(request)
+                .send()
+                .await
+                .map_err(|source| ApprovalError::Unreachable {
+                    url: self.url.clone(),
+                    source,
+                })?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(ApprovalError::Status {
+                    url: self.url.clone(),
+                    status,
+                });
+            }
+            response
+                .json()
+                .await
+                .map_err(|source| ApprovalError::InvalidDecision {
+                    url: self.url.clone(),
+                    source,
+                })
+        })
+    }
+}
+
+/// How a server gets tool calls approved
+#[derive(Clone, Debug)]
+pub struct Approvals {
+    approver: Option<Arc<dyn Approver>>,
+    timeout: Duration,
+}
+
+impl Default for Approvals {
+    fn default() -> Self {
+        Self {
+            approver: None,
+            timeout: DEFAULT_APPROVAL_TIMEOUT,
+        }
+    }
+}
+
+impl Approvals {
+    /// Asks `approver` about calls from clients without elicitation
+    pub fn with_approver(mut self, approver: impl Approver + 'static) -> Self {
+        self.approver = Some(Arc::new(approver));
+        self
+    }
+
+    /// Refuses calls that aren't approved within `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Gets the call of `tool` approved if it needs to be
+    ///
+    /// Returns the failed result to send instead of running the tool when
+    /// it's denied, the approval times out, or there's no way to ask.
+    pub async fn check(
+        &self,
+        tool: &schema::Tool,
+        arguments: Option<&BTreeMap<String, Value>>,
+        context: &ToolContext,
+        policy: &ToolPolicy,
+    ) -> Result<(), CallToolResult> {
+        if !requires_approval(tool, policy) {
+            return Ok(());
+        }
+        let request = ApprovalRequest {
+            tool: tool.name.clone(),
+            arguments: arguments.cloned(),
+            session: context.session_id().to_string(),
+        };
+        let decision =
+            match tokio::time::timeout(self.timeout, self.decide(&request, context)).await {
+                Ok(Ok(decision)) => decision,
+                Ok(Err(e)) => return Err(refusal(&request.tool, "unavailable", e.to_string())),
+                Err(_) => {
+                    return Err(refusal(
+                        &request.tool,
+                        "timed_out",
+                        format!(
+                            "Approval timed out after {} seconds",
+                            self.timeout.as_secs_f64()
+                        ),
+                    ))
+                }
+            };
+        if decision.approved {
+            return Ok(());
+        }
+        let reason = decision
+            .reason
+            .unwrap_or_else(|| "The call was not approved".to_string());
+        Err(refusal(&request.tool, "denied", reason))
+    }
+
+    async fn decide(
+        &self,
+        request: &ApprovalRequest,
+        context: &ToolContext,
+    ) -> Result<ApprovalDecision, ApprovalError> {
+        let elicits = context
+            .session()
+            .is_some_and(|session| session.client_capabilities.elicitation.is_some());
+        if elicits {
+            return elicit(request, context).await;
+        }
+        match &self.approver {
+            Some(approver) => approver.decide(request).await,
+            None => Err(ApprovalError::NoApprover(request.tool.clone())),
+        }
+    }
+}
+
+/// Whether calls of `tool` wait for approval under `policy`
+pub fn requires_approval(tool: &schema::Tool, policy: &ToolPolicy) -> bool {
+    policy.require_approval.unwrap_or_else(|| {
+        tool.annotations
+            .as_ref()
+            .and_then(|annotations| annotations.destructive_hint)
+            .unwrap_or(false)
+    })
+}
+
+/// Asks the client's user to approve the call
+async fn elicit(
+    request: &ApprovalRequest,
+    context: &ToolContext,
+) -> Result<ApprovalDecision, ApprovalError> {
+    let arguments = serde_json::to_string_pretty(&request.arguments.clone().unwrap_or_default())?;
+    let message = format!(
+        "Allow {} to run with these arguments?\n{}",
+        request.tool, arguments
+    );
+    let schema = ElicitRequestParamsRequestedSchema::object().with_property(
+        "approve",
+        json!({ "type": "boolean", "title": "Approve", "description": "Let the tool run" }),
+        true,
+    );
+    let result = context.elicit(schema, message).await?;
+    let approved = result
+        .content
+        .as_ref()
+        .and_then(|content| content.get("approve"))
+        .and_then(Value::as_bool);
+    Ok(match (result.action, approved) {
+        (ElicitResultAction::Accept, Some(true)) => ApprovalDecision::approve(),
+        (ElicitResultAction::Accept, _) | (ElicitResultAction::Decline, _) => {
+            ApprovalDecision::deny("The user declined the call")
+        }
+        (ElicitResultAction::Cancel, _) => ApprovalDecision::deny("The user cancelled the call"),
+    })
+}
+
+/// A failed result for a call of `tool` that wasn't approved, with the details in `_meta.approval`
+fn refusal(tool: &str, outcome: &str, message: String) -> CallToolResult {
+    let details = json!({
+        "tool": tool,
+        "outcome": outcome,
+        "message": message,
+    });
+    CallToolResult {
+        meta: Some(BTreeMap::from([("approval".to_string(), details)])),
+        ..CallToolResult::error(format!("{} was not run: {}", tool, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roots::RootsState;
+    use crate::router::RequestRouter;
+    use crate::schema::ToolAnnotations;
+    use crate::session::Sessions;
+    use crate::tools::echo::Echo;
+    use crate::tools::ToolDef;
+    use crate::transport::ConnectionId;
+    use tokio::sync::mpsc;
+
+    /// Decides the same way every time, after an optional delay
+    #[derive(Debug)]
+    struct Fixed(ApprovalDecision, Duration);
+
+    impl Approver for Fixed {
+        fn decide<'a>(
+            &'a self,
+            _request: &'a ApprovalRequest,
+        ) -> Pin<Box<dyn Future<Output = Result<ApprovalDecision, ApprovalError>> + Send + 'a>>
+        {
+            Box::pin(async move {
+                tokio::time::sleep(self.1).await;
+                Ok(self.0.clone())
+            })
+        }
+    }
+
+    fn destructive() -> schema::Tool {
+        let mut tool = Echo::def();
+        tool.annotations = Some(ToolAnnotations {
+            destructive_hint: Some(true),
+            ..Default::default()
+        });
+        tool
+    }
+
+    fn outcome(result: CallToolResult) -> String {
+        assert_eq!(result.is_error, Some(true));
+        result.meta.unwrap()["approval"]["outcome"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_requires_approval() {
+        let policy = ToolPolicy::default();
+        assert!(!requires_approval(&Echo::def(), &policy));
+        assert!(requires_approval(&destructive(), &policy));
+        let policy = ToolPolicy {
+            require_approval: Some(false),
+            ..Default::default()
+        };
+        assert!(!requires_approval(&destructive(), &policy));
+        let policy = ToolPolicy {
+            require_approval: Some(true),
+            ..Default::default()
+        };
+        assert!(requires_approval(&Echo::def(), &policy));
+    }
+
+    #[tokio::test]
+    async fn test_approver() {
+        let context = ToolContext::default();
+        let policy = ToolPolicy::default();
+        let tool = destructive();
+        let check = |approvals: Approvals| {
+            let (tool, context, policy) = (tool.clone(), context.clone(), policy.clone());
+            async move { approvals.check(&tool, None, &context, &policy).await }
+        };
+
+        assert!(check(Approvals::default())
+            .await
+            .is_err_and(|result| outcome(result) == "unavailable"));
+        let approved =
+            Approvals::default().with_approver(Fixed(ApprovalDecision::approve(), Duration::ZERO));
+        assert!(check(approved).await.is_ok());
+        let denied = Approvals::default()
+            .with_approver(Fixed(ApprovalDecision::deny("Not today"), Duration::ZERO));
+        let result = check(denied).await.unwrap_err();
+        assert_eq!(
+            result.content[0].as_text(),
+            Some("echo was not run: Not today")
+        );
+        assert_eq!(outcome(result), "denied");
+        let slow = Approvals::default()
+            .with_approver(Fixed(ApprovalDecision::approve(), Duration::from_secs(5)))
+            .with_timeout(Duration::from_millis(10));
+        assert_eq!(outcome(check(slow).await.unwrap_err()), "timed_out");
+
+        // Tools that don't need approval run without asking
+        assert!(Approvals::default()
+            .check(&Echo::def(), None, &context, &policy)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_approver() {
+        let mut server = mockito::Server::new_async().await;
+        let _hook = server
+            .mock("POST", "/approve")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "tool": "echo",
+                "arguments": { "message": "rm -rf" },
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"approved": false, "reason": "Looks dangerous"}"#)
+            .create_async()
+            .await;
+        let url = Url::parse(&format!("{}/approve", server.url())).unwrap();
+        let approvals = Approvals::default().with_approver(WebhookApprover::new(url));
+        let arguments = BTreeMap::from([("message".to_string(), json!("rm -rf"))]);
+
+        let result = approvals
+            .check(
+                &destructive(),
+                Some(&arguments),
+                &ToolContext::default(),
+                &ToolPolicy::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            result.content[0].as_text(),
+            Some("echo was not run: Looks dangerous")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_elicited_approval() {
+        let (outgoing, mut outgoing_rx) = mpsc::channel(8);
+        let router = RequestRouter::new(outgoing);
+        let sessions = Sessions::default();
+        let session_id = ConnectionId::from("client");
+        sessions.update(&session_id, |session| {
+            session.client_capabilities.elicitation = Some(BTreeMap::new());
+        });
+        let context = ToolContext::new(session_id, sessions, router.clone(), RootsState::default());
+
+        // The user approves the first call and declines the second
+        tokio::spawn(async move {
+            for answer in [
+                json!({ "action": "accept", "content": { "approve": true } }),
+                json!({ "action": "decline" }),
+            ] {
+                let sent = outgoing_rx.recv().await.unwrap();
+                let request: Value = serde_json::from_str(&sent.message).unwrap();
+                assert_eq!(request["method"], "elicitation/create");
+                let reply = json!({ "jsonrpc": "2.0", "id": request["id"], "result": answer });
+                router.handle_response(&sent.connection, &reply).unwrap();
+            }
+        });
+
+        // Elicitation is preferred over the approver
+        let approvals = Approvals::default()
+            .with_approver(Fixed(ApprovalDecision::deny("Never"), Duration::ZERO));
+        let policy = ToolPolicy::default();
+        assert!(approvals
+            .check(&destructive(), None, &context, &policy)
+            .await
+            .is_ok());
+        let result = approvals
+            .check(&destructive(), None, &context, &policy)
+            .await
+            .unwrap_err();
+        assert_eq!(outcome(result), "denied");
+    }
+}
@@ -0,0 +1,254 @@
+use crate::client::spawn_stdio;
+use crate::error::ServerError;
+use crate::message::JsonRpcMessage;
+use crate::shutdown::Shutdown;
+use crate::transport::{ConnectionEvent, ConnectionId, Transport, TransportMessage, TransportType};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use tokio::process::{Child, Command};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, info, warn};
+
+/// Messages waiting to reach a client or a child server before senders wait
+const BRIDGE_CAPACITY: usize = 32;
+
+/// Exposes a server that only speaks stdio over any transport
+///
+/// Each client connection gets a child process of its own running the
+/// server, so every client has a session of its own. Messages are relayed
+/// untouched in both directions, one per line on the child's stdio. A child
+/// is killed once its client disconnects, and started again if its client
+/// writes after it exited.
+///
+/// ```ignore
+/// Bridge::new("npx", ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"])
+///     .serve(TransportType::WebSocket(WebSocketTransport::new(addr)))
+///     .await?;
+/// ```
+pub struct Bridge {
+    program: OsString,
+    args: Vec<OsString>,
+}
+
+/// A child server relaying one client's session
+struct Backend {
+    sender: mpsc::Sender<String>,
+    _child: Child,
+}
+
+impl Bridge {
+    pub fn new(
+        program: impl Into<OsString>,
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Relays clients until the transport closes or the process gets SIGINT or SIGTERM
+    pub async fn serve(self, transport: TransportType) -> Result<(), ServerError> {
+        self.serve_with_shutdown(transport, Shutdown::new().on_signals())
+            .await
+    }
+
+    /// Relays clients until the transport closes or `shutdown` is triggered,
+    /// killing every child server then
+    pub async fn serve_with_shutdown(
+        self,
+        mut transport: TransportType,
+        shutdown: Shutdown,
+    ) -> Result<(), ServerError> {
+        let (request_tx, mut request_rx) = mpsc::channel(BRIDGE_CAPACITY);
+        let mut transport_reader = transport.clone();
+        let reader = tokio::spawn(async move { transport_reader.start(request_tx).await });
+
+        let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<TransportMessage>(BRIDGE_CAPACITY);
+        let (exited_tx, mut exited_rx) = mpsc::unbounded_channel::<ConnectionId>();
+        let mut events_rx = transport.connection_events();
+        let mut backends: HashMap<ConnectionId, Backend> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                _ = shutdown.triggered() => {
+                    info!("Shutting down the bridge to {} servers", backends.len());
+                    break;
+                }
+                Some(outgoing) = outgoing_rx.recv() => {
+                    transport
+                        .send_response(outgoing.connection, outgoing.message)
+                        .await
+                        .map_err(|e| {
+                            error!("Failed to send response: {}", e);
+                            ServerError::Send(e)
+                        })?;
+                }
+                Some(connection) = exited_rx.recv() => {
+                    if backends.remove(&connection).is_some() {
+                        info!("Server for {} exited", connection);
+                    }
+                }
+                event = async {
+                    match events_rx.as_mut() {
+                        Some(events) => events.recv().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    match event {
+                        // A suspended client keeps its server until its connection closes
+                        Ok(
+                            ConnectionEvent::Opened(_)
+                            | ConnectionEvent::Suspended(_)
+                            | ConnectionEvent::Resumed(_),
+                        ) => {}
+                        Ok(ConnectionEvent::Closed(connection)) => {
+                            if backends.remove(&connection).is_some() {
+                                debug!("Stopped the server for {}", connection);
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Missed {} connection events", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => events_rx = None,
+                    }
+                }
+                message = request_rx.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    if !backends.contains_key(&message.connection) {
+                        match self.start(&message.connection, &outgoing_tx, &exited_tx) {
+                            Ok(backend) => {
+                                backends.insert(message.connection.clone(), backend);
+                            }
+                            Err(e) => {
+                                error!("Failed to start the server for {}: {}", message.connection, e);
+                                if let Some(error) = start_failed(&message.message) {
+                                    let _ = outgoing_tx
+                                        .send(TransportMessage {
+                                            connection: message.connection,
+                                            message: error,
+                                        })
+                                        .await;
+                                }
+                                continue;
+                            }
+                        }
+                    }
+                    if let Some(backend) = backends.get(&message.connection) {
+                        if backend.sender.send(message.message).await.is_err() {
+                            backends.remove(&message.connection);
+                        }
+                    }
+                }
+            }
+        }
+
+        reader.abort();
+        match reader.await {
+            Ok(Err(e)) => Err(ServerError::Transport(e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Starts a child server for `connection`, relaying what it writes to the client
+    fn start(
+        &self,
+        connection: &ConnectionId,
+        outgoing_tx: &mpsc::Sender<TransportMessage>,
+        exited_tx: &mpsc::UnboundedSender<ConnectionId>,
+    ) -> std::io::Result<Backend> {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        let (mut end, child) = spawn_stdio(command)?;
+        info!("Started {:?} for {}", self.program, connection);
+
+        let connection = connection.clone();
+        let outgoing_tx = outgoing_tx.clone();
+        let exited_tx = exited_tx.clone();
+        tokio::spawn(async move {
+            while let Some(message) = end.receiver.recv().await {
+                let message = TransportMessage {
+                    connection: connection.clone(),
+                    message,
+                };
+                if outgoing_tx.send(message).await.is_err() {
+                    return;
+                }
+            }
+            let _ = exited_tx.send(connection);
+        });
+
+        Ok(Backend {
+            sender: end.sender,
+            _child: child,
+        })
+    }
+}
+
+/// Error response to `message` when its server couldn't be started, if it expects one
+fn start_failed(message: &str) -> Option<String> {
+    let JsonRpcMessage::Request { id, .. } = JsonRpcMessage::parse(message).ok()? else {
+        return None;
+    };
+    let error = jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::InternalError,
+        message: "Failed to start the server".to_string(),
+        data: None,
+    };
+    let response = serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error });
+    Some(response.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::ChannelTransport;
+    use crate::Client;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_bridge_relays_both_ways() {
+        // A stand-in server answering every request with its own ID
+        let server = r#"while read line; do
+            id=$(echo "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+            echo '{"jsonrpc":"2.0","method":"notifications/message"}'
+            echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{}}"
+        done"#;
+        let (transport, end) = ChannelTransport::new();
+        let shutdown = Shutdown::new();
+        let bridge = tokio::spawn(
+            Bridge::new("sh", ["-c", server])
+                .serve_with_shutdown(TransportType::Channel(transport), shutdown.clone()),
+        );
+
+        let client = Client::new(end);
+        client.ping().await.unwrap();
+        client.ping().await.unwrap();
+        let notification = client.next_notification().await.unwrap();
+        assert_eq!(notification["method"], "notifications/message");
+
+        shutdown.trigger();
+        tokio::time::timeout(Duration::from_secs(5), bridge)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bridge_start_failure() {
+        let (transport, end) = ChannelTransport::new();
+        tokio::spawn(
+            Bridge::new("/nonexistent/server", Vec::<OsString>::new())
+                .serve_with_shutdown(TransportType::Channel(transport), Shutdown::new()),
+        );
+
+        let client = Client::new(end);
+        match client.ping().await {
+            Err(crate::ClientError::Rpc(e)) => assert_eq!(e.message, "Failed to start the server"),
+            other => panic!("Expected the request to fail, got {:?}", other),
+        }
+    }
+}
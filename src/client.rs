@@ -0,0 +1,684 @@
+use crate::completion::CompletionRef;
+use crate::error::ClientError;
+use crate::protocol::LATEST_PROTOCOL_VERSION;
+use crate::router::DEFAULT_REQUEST_TIMEOUT;
+use crate::schema::{
+    CallToolResult, ClientCapabilities, CompleteResult, CompleteResultCompletion, GetPromptResult,
+    Implementation, InitializeRequestParams, InitializeResult, ListPromptsResult,
+    ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, Prompt, ReadResourceResult,
+    Resource, ResourceTemplate, Tool,
+};
+use crate::transport::{
+    read_cbor, to_cbor, ChannelEnd, Encoding, Frame, CBOR_MAGIC, DEFAULT_MAX_MESSAGE_SIZE,
+};
+use futures::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+use tracing::{debug, error};
+
+/// Messages relayed to or from a server process or socket before the sender waits
+const RELAY_CAPACITY: usize = 32;
+
+type PendingRequests =
+    Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, jsonrpc_core::Error>>>>>;
+
+/// Talks to an MCP server as a client, with typed requests for its features
+///
+/// Responses are matched to requests by ID, so clones can make requests
+/// concurrently. The server's `ping` requests are answered and its other
+/// requests refused; notifications wait in order for `next_notification`.
+///
+/// ```ignore
+/// let (handle, client) = Server::builder().tool(Echo).build().spawn_in_process();
+/// client.initialize().await?;
+/// let result = client.call_tool("echo", json!({ "message": "hi" })).await?;
+/// ```
+#[derive(Clone)]
+pub struct Client {
+    next_id: Arc<AtomicU64>,
+    pending: PendingRequests,
+    sender: mpsc::Sender<String>,
+    notifications: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<Value>>>,
+    timeout: Duration,
+}
+
+impl Client {
+    /// Starts reading the server's messages from `end`, which must be called
+    /// from within a Tokio runtime
+    pub fn new(end: ChannelEnd) -> Self {
+        let pending = PendingRequests::default();
+        let (notifications_tx, notifications) = mpsc::unbounded_channel();
+        tokio::spawn(read_messages(
+            end.receiver,
+            end.sender.downgrade(),
+            pending.clone(),
+            notifications_tx,
+        ));
+        Self {
+            next_id: Arc::new(AtomicU64::new(0)),
+            pending,
+            sender: end.sender,
+            notifications: Arc::new(tokio::sync::Mutex::new(notifications)),
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+
+    /// Starts `command` as a server speaking over its stdin and stdout, one
+    /// message per line
+    ///
+    /// The process is killed when the returned `Child` is dropped; its
+    /// stderr is left to the caller's `command` settings.
+    pub fn spawn_process(command: Command) -> Result<(Self, Child), ClientError> {
+        let (end, child) = spawn_stdio(command).map_err(ClientError::Connect)?;
+        Ok((Self::new(end), child))
+    }
+
+    /// Connects to a server listening for WebSocket connections at `url`
+    pub async fn connect_websocket(url: &str) -> Result<Self, ClientError> {
+        let (socket, _) = tokio_tungstenite::connect_async(url)
+            .await
+            .map_err(|e| ClientError::Connect(io::Error::other(e)))?;
+        let (end, server) = channel_pair();
+        tokio::spawn(pipe_websocket(socket, server));
+        Ok(Self::new(end))
+    }
+
+    /// Connects to a server listening for TCP connections at `addr`, talking
+    /// in `encoding`
+    ///
+    /// CBOR is asked for when connecting, failing if the server doesn't agree.
+    pub async fn connect_tcp(addr: &str, encoding: Encoding) -> Result<Self, ClientError> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(ClientError::Connect)?;
+        let (reader, mut writer) = stream.into_split();
+        let (end, server) = channel_pair();
+        match encoding {
+            Encoding::Json => {
+                tokio::spawn(pipe_lines(reader, writer, server));
+            }
+            Encoding::Cbor => {
+                let mut reader = BufReader::new(reader);
+                negotiate_cbor(&mut reader, &mut writer)
+                    .await
+                    .map_err(ClientError::Connect)?;
+                tokio::spawn(pipe_cbor(reader, writer, server));
+            }
+        }
+        Ok(Self::new(end))
+    }
+
+    /// Sets how long to wait for the server to answer a request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends a request and waits for its result
+    pub async fn request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> Result<R, ClientError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, sender);
+
+        let mut request = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        let params = serde_json::to_value(params)?;
+        if !params.is_null() {
+            request["params"] = params;
+        }
+        debug!("Sending {} request {}", method, id);
+        if self.sender.send(request.to_string()).await.is_err() {
+            self.forget(id);
+            return Err(ClientError::Closed);
+        }
+
+        let result = tokio::time::timeout(self.timeout, receiver).await;
+        self.forget(id);
+        match result {
+            Ok(Ok(Ok(value))) => Ok(serde_json::from_value(value)?),
+            Ok(Ok(Err(e))) => Err(ClientError::Rpc(e)),
+            Ok(Err(_)) => Err(ClientError::Closed),
+            Err(_) => Err(ClientError::Timeout(method.to_string())),
+        }
+    }
+
+    /// Sends a notification, which the server doesn't answer
+    pub async fn notify(&self, method: &str, params: impl Serialize) -> Result<(), ClientError> {
+        let mut notification = json!({ "jsonrpc": "2.0", "method": method });
+        let params = serde_json::to_value(params)?;
+        if !params.is_null() {
+            notification["params"] = params;
+        }
+        self.sender
+            .send(notification.to_string())
+            .await
+            .map_err(|_| ClientError::Closed)
+    }
+
+    /// Completes the initialization handshake, offering the newest protocol version
+    pub async fn initialize(&self) -> Result<InitializeResult, ClientError> {
+        let params = InitializeRequestParams {
+            capabilities: ClientCapabilities::default(),
+            client_info: Implementation {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+        };
+        let result = self.request("initialize", params).await?;
+        self.notify("notifications/initialized", ()).await?;
+        Ok(result)
+    }
+
+    pub async fn ping(&self) -> Result<(), ClientError> {
+        self.request::<Value>("ping", ()).await.map(|_| ())
+    }
+
+    /// Every tool the server lists, across all pages
+    pub async fn list_tools(&self) -> Result<Vec<Tool>, ClientError> {
+        let mut tools = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page: ListToolsResult = self.request("tools/list", page(cursor)).await?;
+            tools.extend(page.tools);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(tools),
+            }
+        }
+    }
+
+    /// Calls the tool called `name`, whose failures are results with `is_error` set
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, ClientError> {
+        self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    /// Every resource the server lists, across all pages
+    pub async fn list_resources(&self) -> Result<Vec<Resource>, ClientError> {
+        let mut resources = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page: ListResourcesResult = self.request("resources/list", page(cursor)).await?;
+            resources.extend(page.resources);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(resources),
+            }
+        }
+    }
+
+    /// Every resource template the server lists, across all pages
+    pub async fn list_resource_templates(&self) -> Result<Vec<ResourceTemplate>, ClientError> {
+        let mut templates = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page: ListResourceTemplatesResult = self
+                .request("resources/templates/list", page(cursor))
+                .await?;
+            templates.extend(page.resource_templates);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(templates),
+            }
+        }
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, ClientError> {
+        self.request("resources/read", json!({ "uri": uri })).await
+    }
+
+    /// Every prompt the server lists, across all pages
+    pub async fn list_prompts(&self) -> Result<Vec<Prompt>, ClientError> {
+        let mut prompts = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page: ListPromptsResult = self.request("prompts/list", page(cursor)).await?;
+            prompts.extend(page.prompts);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(prompts),
+            }
+        }
+    }
+
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: BTreeMap<String, String>,
+    ) -> Result<GetPromptResult, ClientError> {
+        self.request(
+            "prompts/get",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    /// Values the server suggests for the argument `argument` of `reference`,
+    /// given the client typed `value` so far
+    pub async fn complete(
+        &self,
+        reference: CompletionRef,
+        argument: &str,
+        value: &str,
+    ) -> Result<CompleteResultCompletion, ClientError> {
+        let result: CompleteResult = self
+            .request(
+                "completion/complete",
+                json!({ "ref": reference, "argument": { "name": argument, "value": value } }),
+            )
+            .await?;
+        Ok(result.completion)
+    }
+
+    /// The next notification from the server, or `None` once the connection closed
+    pub async fn next_notification(&self) -> Option<Value> {
+        self.notifications.lock().await.recv().await
+    }
+
+    fn forget(&self, id: u64) {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+    }
+}
+
+/// The client's end of a connection, and the end that relays it to the server
+fn channel_pair() -> (ChannelEnd, ChannelEnd) {
+    let (to_server, from_client) = mpsc::channel(RELAY_CAPACITY);
+    let (to_client, from_server) = mpsc::channel(RELAY_CAPACITY);
+    let client = ChannelEnd {
+        sender: to_server,
+        receiver: from_server,
+    };
+    let server = ChannelEnd {
+        sender: to_client,
+        receiver: from_client,
+    };
+    (client, server)
+}
+
+/// Starts `command` with piped stdio, returning the end that talks to it one line at a time
+pub(crate) fn spawn_stdio(mut command: Command) -> io::Result<(ChannelEnd, Child)> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = command.spawn()?;
+    let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        return Err(io::Error::other("The server's stdio isn't piped"));
+    };
+
+    let (end, server) = channel_pair();
+    tokio::spawn(pipe_lines(stdout, stdin, server));
+    Ok((end, child))
+}
+
+/// Relays messages between `server`'s end and a process's stdio or a socket,
+/// one per line, until either side closes
+async fn pipe_lines<R, W>(reader: R, mut writer: W, mut server: ChannelEnd)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) if line.trim().is_empty() => {}
+                Ok(Some(line)) => {
+                    if server.sender.send(line).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Failed to read from the server: {}", e);
+                    break;
+                }
+            },
+            message = server.receiver.recv() => {
+                let Some(message) = message else {
+                    break;
+                };
+                let line = message + "\n";
+                if let Err(e) = writer.write_all(line.as_bytes()).await {
+                    error!("Failed to write to the server: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+    debug!("Server closed its end of the stream");
+}
+
+/// Asks the server for CBOR, waiting for it to echo [`CBOR_MAGIC`]
+async fn negotiate_cbor<R, W>(reader: &mut R, writer: &mut W) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    writer.write_all(&CBOR_MAGIC).await?;
+    writer.flush().await?;
+    let mut magic = [0; CBOR_MAGIC.len()];
+    tokio::time::timeout(DEFAULT_REQUEST_TIMEOUT, reader.read_exact(&mut magic))
+        .await
+        .map_err(|_| {
+            io::Error::new(io::ErrorKind::TimedOut, "The server didn't agree to CBOR")
+        })??;
+    if magic != CBOR_MAGIC {
+        return Err(io::Error::other("The server doesn't speak CBOR"));
+    }
+    Ok(())
+}
+
+/// Relays messages between `server`'s end and a socket speaking
+/// length-prefixed CBOR until either side closes
+///
+/// Reading a frame isn't cancel safe, so each direction runs in a loop of its own.
+async fn pipe_cbor<R, W>(mut reader: R, mut writer: W, server: ChannelEnd)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let ChannelEnd {
+        sender,
+        mut receiver,
+    } = server;
+    let incoming = async {
+        loop {
+            match read_cbor(&mut reader, DEFAULT_MAX_MESSAGE_SIZE).await {
+                Ok(Some(Frame::Message(message))) => {
+                    if sender.send(message).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(Some(Frame::TooLarge)) => {}
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Failed to read from the server: {}", e);
+                    break;
+                }
+            }
+        }
+    };
+    let outgoing = async {
+        while let Some(message) = receiver.recv().await {
+            let written = match to_cbor(&message) {
+                Ok(frame) => writer.write_all(&frame).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = written {
+                error!("Failed to write to the server: {}", e);
+                break;
+            }
+        }
+    };
+    tokio::select! {
+        _ = incoming => {}
+        _ = outgoing => {}
+    }
+    debug!("Server closed its end of the stream");
+}
+
+/// Relays messages between `server`'s end and a WebSocket until either side closes
+async fn pipe_websocket<S>(socket: WebSocketStream<S>, mut server: ChannelEnd)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut writer, mut reader) = socket.split();
+    loop {
+        tokio::select! {
+            frame = reader.next() => match frame {
+                Some(Ok(Message::Text(text))) => {
+                    if server.sender.send(text.to_string()).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    error!("WebSocket error: {}", e);
+                    break;
+                }
+            },
+            message = server.receiver.recv() => {
+                let Some(message) = message else {
+                    let _ = writer.send(Message::Close(None)).await;
+                    break;
+                };
+                if let Err(e) = writer.send(Message::Text(message.into())).await {
+                    error!("Failed to write to the server: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+    debug!("WebSocket connection to the server closed");
+}
+
+/// Parameters of a list request for the page at `cursor`
+fn page(cursor: Option<String>) -> Value {
+    match cursor {
+        Some(cursor) => json!({ "cursor": cursor }),
+        None => Value::Null,
+    }
+}
+
+/// Routes the server's messages until the connection closes, failing the
+/// requests still waiting then
+async fn read_messages(
+    mut receiver: mpsc::Receiver<String>,
+    sender: mpsc::WeakSender<String>,
+    pending: PendingRequests,
+    notifications: mpsc::UnboundedSender<Value>,
+) {
+    while let Some(message) = receiver.recv().await {
+        let messages = match serde_json::from_str(&message) {
+            Ok(Value::Array(messages)) => messages,
+            Ok(message) => vec![message],
+            Err(e) => {
+                error!("Server sent invalid JSON: {}", e);
+                continue;
+            }
+        };
+        for message in messages {
+            match (message.get("method"), message.get("id")) {
+                (Some(method), Some(id)) => {
+                    let reply = match method.as_str() {
+                        Some("ping") => json!({ "jsonrpc": "2.0", "id": id, "result": {} }),
+                        _ => json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": jsonrpc_core::Error::method_not_found(),
+                        }),
+                    };
+                    // Once every clone of the client is gone nobody is left to answer
+                    if let Some(sender) = sender.upgrade() {
+                        let _ = sender.send(reply.to_string()).await;
+                    }
+                }
+                (Some(_), None) => {
+                    let _ = notifications.send(message);
+                }
+                (None, Some(id)) => {
+                    let waiting = id.as_u64().and_then(|id| {
+                        pending
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .remove(&id)
+                    });
+                    let Some(waiting) = waiting else {
+                        error!("Response to unknown request {}", id);
+                        continue;
+                    };
+                    let result = match message.get("error") {
+                        Some(error) => Err(serde_json::from_value(error.clone())
+                            .unwrap_or_else(|_| jsonrpc_core::Error::internal_error())),
+                        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                    };
+                    let _ = waiting.send(result);
+                }
+                (None, None) => error!("Server sent a message that isn't JSON-RPC"),
+            }
+        }
+    }
+    debug!("Connection to the server closed");
+    pending.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::echo::Echo;
+    use crate::Server;
+
+    #[tokio::test]
+    async fn test_in_process_client() {
+        let (handle, client) = Server::builder().tool(Echo).build().spawn_in_process();
+
+        let initialized = client.initialize().await.unwrap();
+        assert_eq!(initialized.protocol_version, LATEST_PROTOCOL_VERSION);
+        client.ping().await.unwrap();
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "echo");
+        let result = client
+            .call_tool("echo", json!({ "message": "hi" }))
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].as_text(), Some("hi"));
+
+        // Unknown methods come back as JSON-RPC errors
+        match client.request::<Value>("tools/unknown", ()).await {
+            Err(ClientError::Rpc(e)) => assert_eq!(e.code.code(), -32601),
+            other => panic!("Expected a method not found error, got {:?}", other.err()),
+        }
+
+        handle
+            .send_notification("notifications/message", json!({ "text": "hello" }))
+            .unwrap();
+        let notification = client.next_notification().await.unwrap();
+        assert_eq!(notification["params"]["text"], "hello");
+
+        // Dropping the client closes the transport, stopping the server
+        drop(client);
+        tokio::time::timeout(Duration::from_secs(5), handle.wait())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_process_client() {
+        // Answers the first request, which the client makes with ID 0
+        let mut command = Command::new("sh");
+        command.args([
+            "-c",
+            r#"read request; echo '{"jsonrpc":"2.0","id":0,"result":{}}'; cat >/dev/null"#,
+        ]);
+        let (client, mut child) = Client::spawn_process(command).unwrap();
+        client.ping().await.unwrap();
+
+        // Dropping the client closes the process's stdin
+        drop(client);
+        let status = tokio::time::timeout(Duration::from_secs(5), child.wait())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[tokio::test]
+    async fn test_websocket_client() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let transport = crate::transport::TransportType::WebSocket(
+            crate::transport::WebSocketTransport::new(addr.clone()),
+        );
+        let handle = Server::builder().tool(Echo).build().spawn(transport);
+
+        let url = format!("ws://{}", addr);
+        let client = loop {
+            match Client::connect_websocket(&url).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        client.initialize().await.unwrap();
+        let result = client
+            .call_tool("echo", json!({ "message": "over the wire" }))
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].as_text(), Some("over the wire"));
+
+        handle.shutdown();
+        tokio::time::timeout(Duration::from_secs(5), handle.wait())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tcp_cbor_client() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let transport = crate::transport::TransportType::Tcp(
+            crate::transport::TcpTransport::new(addr.clone()).with_cbor(),
+        );
+        let handle = Server::builder().tool(Echo).build().spawn(transport);
+
+        let client = loop {
+            match Client::connect_tcp(&addr, Encoding::Cbor).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        client.initialize().await.unwrap();
+        let message = "x".repeat(100_000);
+        let result = client
+            .call_tool("echo", json!({ "message": message }))
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].as_text(), Some(message.as_str()));
+
+        // JSON clients are still served next to CBOR ones
+        let client = Client::connect_tcp(&addr, Encoding::Json).await.unwrap();
+        client.initialize().await.unwrap();
+        client.ping().await.unwrap();
+
+        handle.shutdown();
+        tokio::time::timeout(Duration::from_secs(5), handle.wait())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
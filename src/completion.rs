@@ -0,0 +1,116 @@
+use crate::schema::CompleteResultCompletion;
+use serde::{Deserialize, Serialize};
+
+/// Most values a `completion/complete` result may hold
+pub const MAX_VALUES: usize = 100;
+
+/// What a `completion/complete` request completes an argument of, its `ref`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum CompletionRef {
+    /// An argument of the prompt called `name`
+    #[serde(rename = "ref/prompt")]
+    Prompt { name: String },
+    /// A variable of the resource template `uri`
+    #[serde(rename = "ref/resource")]
+    Resource { uri: String },
+}
+
+/// The `candidates` that fuzzily match what the client typed, best first
+///
+/// Values starting with `value` come first, then those containing it and
+/// then those holding its characters in order, each ignoring case and the
+/// closer together the better. Candidates keep their order otherwise.
+/// At most `MAX_VALUES` are returned, with the total of those that matched.
+pub fn complete(
+    value: &str,
+    candidates: impl IntoIterator<Item = String>,
+) -> CompleteResultCompletion {
+    let needle: Vec<char> = value.to_lowercase().chars().collect();
+    let mut seen = std::collections::HashSet::new();
+    let mut matches: Vec<_> = candidates
+        .into_iter()
+        .filter(|candidate| seen.insert(candidate.clone()))
+        .enumerate()
+        .filter_map(|(index, candidate)| {
+            score(&needle, &candidate).map(|score| (score, index, candidate))
+        })
+        .collect();
+    matches.sort_by_key(|(score, index, _)| (*score, *index));
+
+    let total = matches.len();
+    CompleteResultCompletion {
+        has_more: Some(total > MAX_VALUES),
+        total: Some(total as i64),
+        values: matches
+            .into_iter()
+            .take(MAX_VALUES)
+            .map(|(_, _, candidate)| candidate)
+            .collect(),
+    }
+}
+
+/// How well `candidate` matches `needle`, lower being better, if at all
+fn score(needle: &[char], candidate: &str) -> Option<(u8, usize)> {
+    let haystack: Vec<char> = candidate.to_lowercase().chars().collect();
+    if haystack.starts_with(needle) {
+        return Some((0, 0));
+    }
+    if let Some(start) = haystack
+        .windows(needle.len().max(1))
+        .position(|window| window == needle)
+    {
+        return Some((1, start));
+    }
+
+    // The shortest stretch holding its characters in order
+    let span = |start: usize| {
+        let mut wanted = needle.iter().peekable();
+        for (i, c) in haystack[start..].iter().enumerate() {
+            if wanted.next_if_eq(&c).is_some() && wanted.peek().is_none() {
+                return Some(i + 1);
+            }
+        }
+        None
+    };
+    (0..haystack.len())
+        .filter_map(span)
+        .min()
+        .map(|span| (2, span))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn values(value: &str, candidates: &[&str]) -> Vec<String> {
+        complete(value, candidates.iter().map(|c| c.to_string())).values
+    }
+
+    #[test]
+    fn test_complete() {
+        let languages = ["python", "rust", "typescript", "ruby", "Rust"];
+        assert_eq!(values("", &languages), languages);
+        assert_eq!(values("ru", &languages), ["rust", "ruby", "Rust"]);
+        assert_eq!(values("script", &languages), ["typescript"]);
+        assert_eq!(values("tpst", &languages), ["typescript"]);
+        assert_eq!(values("yt", &languages), ["python", "typescript"]);
+        assert!(values("go", &languages).is_empty());
+
+        let many = complete("", (0..150).map(|i| format!("value-{}", i)));
+        assert_eq!(many.values.len(), MAX_VALUES);
+        assert_eq!(many.total, Some(150));
+        assert_eq!(many.has_more, Some(true));
+
+        let parse = |reference| serde_json::from_value::<CompletionRef>(reference);
+        assert_eq!(
+            parse(json!({ "type": "ref/prompt", "name": "review" })).unwrap(),
+            CompletionRef::Prompt {
+                name: "review".to_string()
+            }
+        );
+        assert!(parse(json!({ "type": "ref/resource" })).is_err());
+        assert!(parse(json!({ "type": "ref/tool", "name": "x" })).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use crate::error::{ConfigError, ResourceError};
+use crate::logging::parse_filter;
+use crate::policy::ToolPolicy;
+use crate::resources::{FileWatcher, ResourceWatcher};
+use crate::tools::command::CommandToolConfig;
+use crate::tools::fetch::FetchLimits;
+use crate::tools::overrides::ToolOverride;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{error, info};
+use url::Url;
+
+/// Time given to an editor to finish writing the file before it's reloaded
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Settings read from a TOML file that can change while the server runs
+///
+/// ```toml
+/// log_level = "info"
+/// enabled_tools = ["echo", "fetch", "time"]
+/// disabled_tools = ["time"]
+///
+/// [tools.fetch]
+/// name = "web.get"
+/// description = "Fetches a page from example.com"
+///
+/// [fetch]
+/// timeout_secs = 10
+/// max_response_bytes = 1048576
+///
+/// [policy.fetch]
+/// allowed_hosts = ["example.com"]
+///
+/// [command_tools.word_count]
+/// command = ["python3", "tools/word_count.py"]
+/// ```
+///
+/// Tools are named by their own names throughout, whatever `[tools]` renames
+/// them to. Command tools are only read at startup.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Filter of the log file in `RUST_LOG` syntax, such as `info` or
+    /// `info,bioma_tool::transport=debug`
+    pub log_level: Option<String>,
+    /// The only tools offered to clients, if not all
+    pub enabled_tools: Option<Vec<String>>,
+    /// Tools hidden from clients
+    pub disabled_tools: Vec<String>,
+    /// Names and descriptions tools are shown by instead of their own, by tool
+    pub tools: HashMap<String, ToolOverride>,
+    pub fetch: FetchSection,
+    /// Policies of tools, by name
+    pub policy: HashMap<String, ToolPolicy>,
+    /// External programs offered as tools, by tool name
+    pub command_tools: HashMap<String, CommandToolConfig>,
+}
+
+/// Limits of the fetch tool, each kept as it was at startup when unset
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FetchSection {
+    pub timeout_secs: Option<u64>,
+    pub max_response_bytes: Option<usize>,
+    pub allowed_content_types: Option<Vec<String>>,
+    pub min_request_interval_ms: Option<u64>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let config: Config = toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        config.log_level()?;
+        Ok(config)
+    }
+
+    /// The log filter, checked to parse
+    pub fn log_level(&self) -> Result<Option<&str>, ConfigError> {
+        match self.log_level.as_deref() {
+            Some(level) => parse_filter(level)
+                .map(|_| Some(level))
+                .map_err(ConfigError::LogLevel),
+            None => Ok(None),
+        }
+    }
+}
+
+impl FetchSection {
+    /// `defaults` with the limits this section sets replaced
+    pub fn apply(&self, defaults: &FetchLimits) -> FetchLimits {
+        FetchLimits {
+            timeout: self
+                .timeout_secs
+                .map_or(defaults.timeout, Duration::from_secs),
+            max_response_bytes: self
+                .max_response_bytes
+                .unwrap_or(defaults.max_response_bytes),
+            allowed_content_types: self
+                .allowed_content_types
+                .clone()
+                .unwrap_or_else(|| defaults.allowed_content_types.clone()),
+            min_request_interval: self
+                .min_request_interval_ms
+                .map(Duration::from_millis)
+                .or(defaults.min_request_interval),
+        }
+    }
+}
+
+/// Calls `apply` with the config at `path` each time the file changes
+///
+/// Versions that fail to load are logged and skipped, leaving the last good
+/// one in effect. Runs until the watcher stops.
+pub async fn watch(path: &Path, mut apply: impl FnMut(Config)) -> Result<(), ConfigError> {
+    // File events carry canonical paths
+    let path: PathBuf = path.canonicalize().map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let uri = Url::from_file_path(&path)
+        .map_err(|_| ResourceError::failed(format!("Invalid config path: {}", path.display())))?
+        .to_string();
+
+    let watcher = FileWatcher::new()?;
+    let mut updates = watcher.updates();
+    watcher.watch(&uri)?;
+    let mut current = Config::load(&path).ok();
+
+    loop {
+        match updates.recv().await {
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        }
+        // Editors often write a file in several steps
+        tokio::time::sleep(SETTLE_TIME).await;
+        while updates.try_recv().is_ok() {}
+
+        match Config::load(&path) {
+            Ok(config) if current.as_ref() != Some(&config) => {
+                info!("Reloading {}", path.display());
+                current = Some(config.clone());
+                apply(config);
+            }
+            Ok(_) => {}
+            Err(e) => error!("Keeping the previous config: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(
+            r#"
+            log_level = "warn"
+            enabled_tools = ["echo", "fetch"]
+            disabled_tools = ["fetch"]
+
+            [tools.fetch]
+            name = "web.get"
+
+            [fetch]
+            timeout_secs = 5
+            min_request_interval_ms = 250
+
+            [policy.fetch]
+            allowed_hosts = ["example.com"]
+            max_runtime_secs = 10
+            require_approval = true
+
+            [command_tools.word_count]
+            command = ["wc", "-w"]
+            timeout_secs = 5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.log_level().unwrap(), Some("warn"));
+        assert_eq!(config.disabled_tools, ["fetch"]);
+        assert_eq!(
+            config.enabled_tools.as_deref(),
+            Some(&["echo".to_string(), "fetch".to_string()][..])
+        );
+        assert_eq!(config.tools["fetch"].name.as_deref(), Some("web.get"));
+        assert_eq!(config.tools["fetch"].description, None);
+        let policy = &config.policy["fetch"];
+        assert_eq!(
+            policy.allowed_hosts.as_deref(),
+            Some(&["example.com".to_string()][..])
+        );
+        assert_eq!(policy.max_runtime(), Some(Duration::from_secs(10)));
+        assert!(policy.network);
+        assert_eq!(policy.require_approval, Some(true));
+        assert_eq!(config.command_tools["word_count"].command, ["wc", "-w"]);
+
+        let defaults = FetchLimits {
+            timeout: Duration::from_secs(30),
+            max_response_bytes: 1024,
+            allowed_content_types: vec!["text/".to_string()],
+            min_request_interval: None,
+        };
+        let limits = config.fetch.apply(&defaults);
+        assert_eq!(limits.timeout, Duration::from_secs(5));
+        assert_eq!(limits.max_response_bytes, 1024);
+        assert_eq!(limits.allowed_content_types, ["text/"]);
+        assert_eq!(
+            limits.min_request_interval,
+            Some(Duration::from_millis(250))
+        );
+
+        assert!(toml::from_str::<Config>("unknown = 1").is_err());
+        assert!(toml::from_str::<Config>("[tools.fetch]\nalias = \"get\"").is_err());
+        let config = Config {
+            log_level: Some("loud".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(config.log_level(), Err(ConfigError::LogLevel(_))));
+        let config = Config {
+            log_level: Some("info,bioma_tool::transport=debug".to_string()),
+            ..Default::default()
+        };
+        assert!(config.log_level().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_watch_config() {
+        let directory = std::env::temp_dir().join(format!("bioma-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("config.toml");
+        std::fs::write(&path, "disabled_tools = []").unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let watched = path.clone();
+        tokio::spawn(async move {
+            watch(&watched, |config| {
+                let _ = tx.send(config);
+            })
+            .await
+        });
+        // Give the watcher time to start
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        std::fs::write(&path, "not toml [").unwrap();
+        assert!(matches!(
+            Config::load(&path),
+            Err(ConfigError::Parse { .. })
+        ));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        std::fs::write(&path, r#"disabled_tools = ["echo"]"#).unwrap();
+
+        let config = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.disabled_tools, ["echo"]);
+        assert!(rx.try_recv().is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,520 @@
+use crate::message::JsonRpcMessage;
+use crate::schema::{CancelledNotificationParams, RequestId};
+use crate::transport::ConnectionId;
+use crate::ServerMetadata;
+use jsonrpc_core::futures_util::future::{
+    self, AbortHandle, AbortRegistration, Abortable, BoxFuture, FutureExt,
+};
+use jsonrpc_core::{Call, ErrorCode, MetaIoHandler, Middleware};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::{error, info, warn};
+
+/// Methods of the notification cancelling a request, the second being what
+/// clients sent before the spec named it
+pub(crate) const CANCELLED_METHODS: [&str; 2] = ["notifications/cancelled", "cancelled"];
+
+type InFlightKey = (ConnectionId, RequestId);
+
+/// IDs of the requests each session has in flight
+///
+/// A request reusing the ID of one still being handled is refused, since
+/// its response couldn't be told apart from the other's. Cancelling a
+/// request stops handling it and frees its ID right away.
+#[derive(Clone, Default)]
+pub(crate) struct InFlight {
+    ids: Arc<Mutex<HashMap<InFlightKey, (u64, AbortHandle)>>>,
+    /// Tells apart requests that reused the ID of a cancelled one
+    serial: Arc<AtomicU64>,
+}
+
+impl InFlight {
+    fn ids(&self) -> MutexGuard<'_, HashMap<InFlightKey, (u64, AbortHandle)>> {
+        self.ids.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Marks the request in flight until the guard is dropped, or returns
+    /// `None` if one with the same ID already is
+    fn start(
+        &self,
+        connection: &ConnectionId,
+        id: RequestId,
+    ) -> Option<(InFlightGuard, AbortRegistration)> {
+        let key = (connection.clone(), id);
+        let mut ids = self.ids();
+        if ids.contains_key(&key) {
+            return None;
+        }
+        let serial = self.serial.fetch_add(1, Ordering::Relaxed);
+        let (abort, registration) = AbortHandle::new_pair();
+        ids.insert(key.clone(), (serial, abort));
+        let guard = InFlightGuard {
+            in_flight: self.clone(),
+            key,
+            serial,
+        };
+        Some((guard, registration))
+    }
+
+    /// Stops handling the request `id` of `connection`, which then goes
+    /// unanswered, returning whether it was in flight
+    pub(crate) fn cancel(&self, connection: &ConnectionId, id: &RequestId) -> bool {
+        let key = (connection.clone(), id.clone());
+        match self.ids().remove(&key) {
+            Some((_, abort)) => {
+                abort.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.ids().len()
+    }
+}
+
+struct InFlightGuard {
+    in_flight: InFlight,
+    key: InFlightKey,
+    serial: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut ids = self.in_flight.ids();
+        // A cancelled request's ID may be in use by another request by now
+        if ids
+            .get(&self.key)
+            .is_some_and(|(serial, _)| *serial == self.serial)
+        {
+            ids.remove(&self.key);
+        }
+    }
+}
+
+/// A JSON-RPC error response, answering `id` or, when that couldn't be read, `null`
+fn error_response(id: Value, code: ErrorCode, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": code.code(), "message": message.into() },
+        "id": id,
+    })
+}
+
+/// The ID of `message` if it's one a request may have
+fn valid_id(message: &Value) -> Option<Value> {
+    let id = message.get("id")?;
+    serde_json::from_value::<RequestId>(id.clone()).ok()?;
+    Some(id.clone())
+}
+
+/// Handles a message from the client of `meta`, returning the response to
+/// send back, if any
+///
+/// Each message of a batch is handled on its own, and the batch answered
+/// with the responses to its requests. Responses from the client go to the
+/// requests awaiting them. Notifications are never answered, even when
+/// they're malformed, while malformed requests are answered with an error
+/// carrying their ID whenever it can be read.
+///
+/// Runs what handlers do synchronously before returning, so session state
+/// changes in the order messages arrive.
+pub(crate) fn dispatch<M: Middleware<ServerMetadata>>(
+    io_handler: &MetaIoHandler<ServerMetadata, M>,
+    message: &str,
+    meta: ServerMetadata,
+    in_flight: &InFlight,
+) -> BoxFuture<'static, Option<String>> {
+    let value: Value = match serde_json::from_str(message) {
+        Ok(value) => value,
+        Err(e) => {
+            let response = error_response(
+                Value::Null,
+                ErrorCode::ParseError,
+                format!("Parse error: {}", e),
+            );
+            return future::ready(Some(response.to_string())).boxed();
+        }
+    };
+
+    match value {
+        Value::Array(messages) if !messages.is_empty() => {
+            let handling: Vec<_> = messages
+                .into_iter()
+                .map(|message| dispatch_one(io_handler, message, meta.clone(), in_flight))
+                .collect();
+            async move {
+                let responses: Vec<Value> = future::join_all(handling)
+                    .await
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                (!responses.is_empty()).then(|| Value::Array(responses).to_string())
+            }
+            .boxed()
+        }
+        message => dispatch_one(io_handler, message, meta, in_flight)
+            .map(|response| response.map(|response| response.to_string()))
+            .boxed(),
+    }
+}
+
+fn dispatch_one<M: Middleware<ServerMetadata>>(
+    io_handler: &MetaIoHandler<ServerMetadata, M>,
+    message: Value,
+    meta: ServerMetadata,
+    in_flight: &InFlight,
+) -> BoxFuture<'static, Option<Value>> {
+    let invalid = |id: Value, message: String| {
+        future::ready(Some(error_response(id, ErrorCode::InvalidRequest, message))).boxed()
+    };
+
+    let id = match JsonRpcMessage::from_value(message.clone()) {
+        Err(e) => {
+            // Without an ID there's nothing to answer
+            if message.is_object() && message.get("id").is_none() && message.get("method").is_some()
+            {
+                warn!(
+                    "Ignored malformed notification from {}: {}",
+                    meta.session_id, e
+                );
+                return future::ready(None).boxed();
+            }
+            return invalid(valid_id(&message).unwrap_or(Value::Null), e);
+        }
+        Ok(JsonRpcMessage::Response(response)) => {
+            return match meta.router.handle_response(&meta.session_id, &response) {
+                Ok(()) => future::ready(None).boxed(),
+                Err(e) => {
+                    error!("Unexpected response from {}: {}", meta.session_id, e);
+                    // The ID is left out, so the client can't mistake this
+                    // for the answer to a request of its own
+                    let mut response = error_response(
+                        Value::Null,
+                        ErrorCode::InvalidRequest,
+                        format!("Unexpected response: {}", e),
+                    );
+                    response["error"]["data"] = json!({ "responseId": response_id(&message) });
+                    future::ready(Some(response)).boxed()
+                }
+            };
+        }
+        Ok(JsonRpcMessage::Notification { method }) => {
+            if CANCELLED_METHODS.contains(&method.as_str()) {
+                cancel(in_flight, &meta.session_id, &message);
+            }
+            let Ok(call) = serde_json::from_value::<Call>(message) else {
+                return future::ready(None).boxed();
+            };
+            return io_handler.handle_call(call, meta).map(|_| None).boxed();
+        }
+        Ok(JsonRpcMessage::Request { id, .. }) => id,
+        Ok(JsonRpcMessage::Batch(_)) => {
+            return invalid(Value::Null, "Batches can't be nested".to_string());
+        }
+    };
+
+    let Ok(request_id) = serde_json::from_value::<RequestId>(id.clone()) else {
+        return invalid(
+            Value::Null,
+            format!("Request ID must be a string or an integer, not {}", id),
+        );
+    };
+    let Some((guard, registration)) = in_flight.start(&meta.session_id, request_id.clone()) else {
+        warn!(
+            "Refused request {} from {} while another with its ID is in flight",
+            request_id, meta.session_id
+        );
+        return invalid(
+            id,
+            format!(
+                "Request ID {} is already in use by a pending request",
+                request_id
+            ),
+        );
+    };
+    let call = match serde_json::from_value::<Call>(message) {
+        Ok(Call::Invalid { .. }) | Err(_) => {
+            return invalid(id, "Invalid request".to_string());
+        }
+        Ok(call) => call,
+    };
+
+    let session_id = meta.session_id.clone();
+    Abortable::new(io_handler.handle_call(call, meta), registration)
+        .map(move |output| {
+            drop(guard);
+            match output {
+                Err(_) => {
+                    info!("Cancelled request {} from {}", request_id, session_id);
+                    None
+                }
+                Ok(Some(output)) => serde_json::to_value(output).ok(),
+                Ok(None) => {
+                    error!("Request {} got no response", id);
+                    Some(error_response(
+                        id,
+                        ErrorCode::InternalError,
+                        "Internal error",
+                    ))
+                }
+            }
+        })
+        .boxed()
+}
+
+/// Cancels the request a cancellation notification names, if it's in flight
+fn cancel(in_flight: &InFlight, connection: &ConnectionId, notification: &Value) {
+    let Some(params) = notification
+        .get("params")
+        .and_then(|params| CancelledNotificationParams::deserialize(params).ok())
+    else {
+        return;
+    };
+    if !in_flight.cancel(connection, &params.request_id) {
+        // It may have been answered already, or never been sent
+        info!(
+            "Ignored cancellation of request {} from {}, which isn't in flight",
+            params.request_id, connection
+        );
+    }
+}
+
+fn response_id(message: &Value) -> Value {
+    message.get("id").cloned().unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roots::RootsState;
+    use crate::router::RequestRouter;
+    use crate::session::Sessions;
+    use jsonrpc_core::futures_util::future::Either;
+    use jsonrpc_core::{Output, Params};
+    use tokio::sync::oneshot;
+
+    fn meta() -> ServerMetadata {
+        ServerMetadata {
+            session_id: ConnectionId::from("client"),
+            sessions: Sessions::default(),
+            router: RequestRouter::default(),
+            roots: RootsState::default(),
+        }
+    }
+
+    async fn answer(
+        io_handler: &MetaIoHandler<ServerMetadata>,
+        in_flight: &InFlight,
+        message: &str,
+    ) -> Option<Value> {
+        dispatch(io_handler, message, meta(), in_flight)
+            .await
+            .map(|response| serde_json::from_str(&response).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() {
+        let mut io_handler = MetaIoHandler::default();
+        io_handler.add_method("echo", |params: Params| async move {
+            Ok(params.parse::<Value>().unwrap_or_default())
+        });
+        io_handler.add_notification("notify", |_params| ());
+        let in_flight = InFlight::default();
+
+        let response = answer(
+            &io_handler,
+            &in_flight,
+            r#"{"jsonrpc":"2.0","id":"a","method":"echo","params":[1]}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["id"], "a");
+        assert_eq!(response["result"], json!([1]));
+
+        // Malformed requests are answered with their ID when it's valid
+        for (message, id, code) in [
+            ("not json", Value::Null, -32700),
+            ("[]", Value::Null, -32600),
+            (r#"{"jsonrpc":"2.0","id":7,"method":5}"#, json!(7), -32600),
+            (
+                r#"{"jsonrpc":"2.0","id":{"a":1},"method":"echo"}"#,
+                Value::Null,
+                -32600,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","id":null,"method":"echo"}"#,
+                Value::Null,
+                -32600,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","id":3.5,"method":"echo"}"#,
+                Value::Null,
+                -32600,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","id":8,"method":"echo","params":5}"#,
+                json!(8),
+                -32600,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","id":9,"method":"missing"}"#,
+                json!(9),
+                -32601,
+            ),
+        ] {
+            let response = answer(&io_handler, &in_flight, message).await.unwrap();
+            assert_eq!(response["id"], id, "{}", message);
+            assert_eq!(response["error"]["code"], code, "{}", message);
+        }
+
+        // Notifications are never answered
+        for message in [
+            r#"{"jsonrpc":"2.0","method":"notify"}"#,
+            r#"{"jsonrpc":"2.0","method":"notify","params":5}"#,
+            r#"{"jsonrpc":"2.0","method":"missing"}"#,
+            r#"{"jsonrpc":"2.0","method":5}"#,
+        ] {
+            assert_eq!(
+                answer(&io_handler, &in_flight, message).await,
+                None,
+                "{}",
+                message
+            );
+        }
+        let batch = answer(
+            &io_handler,
+            &in_flight,
+            r#"[{"jsonrpc":"2.0","method":"notify","params":5},{"jsonrpc":"2.0","id":1,"method":"echo"},1]"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(batch.as_array().unwrap().len(), 2);
+        assert_eq!(batch[0]["id"], 1);
+        assert_eq!(batch[1]["error"]["code"], -32600);
+
+        // Responses the server isn't waiting for are refused without an ID
+        let response = answer(
+            &io_handler,
+            &in_flight,
+            r#"{"jsonrpc":"2.0","id":4,"result":{}}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["data"]["responseId"], 4);
+        assert_eq!(in_flight.len(), 0);
+    }
+
+    /// Holds every call until it's released
+    struct Held(Arc<Mutex<Option<oneshot::Receiver<()>>>>);
+
+    impl Middleware<ServerMetadata> for Held {
+        type Future = jsonrpc_core::middleware::NoopFuture;
+        type CallFuture = BoxFuture<'static, Option<Output>>;
+
+        fn on_call<F, X>(
+            &self,
+            call: Call,
+            meta: ServerMetadata,
+            next: F,
+        ) -> Either<Self::CallFuture, X>
+        where
+            F: Fn(Call, ServerMetadata) -> X + Send + Sync,
+            X: std::future::Future<Output = Option<Output>> + Send + 'static,
+        {
+            let release = self.0.lock().unwrap().take();
+            let response = next(call, meta);
+            Either::Left(
+                async move {
+                    if let Some(release) = release {
+                        let _ = release.await;
+                    }
+                    response.await
+                }
+                .boxed(),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancellation() {
+        let mut io_handler = MetaIoHandler::default();
+        io_handler.add_method("wait", |_params| future::pending());
+        io_handler.add_method("ping", |_params| async { Ok(json!({})) });
+        let in_flight = InFlight::default();
+
+        let waiting = dispatch(
+            &io_handler,
+            r#"{"jsonrpc":"2.0","id":"w","method":"wait"}"#,
+            meta(),
+            &in_flight,
+        );
+        let cancelled =
+            r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":"w"}}"#;
+        assert_eq!(answer(&io_handler, &in_flight, cancelled).await, None);
+        // The cancelled request goes unanswered, and its ID is free at once
+        assert_eq!(in_flight.len(), 0);
+        let reused = answer(
+            &io_handler,
+            &in_flight,
+            r#"{"jsonrpc":"2.0","id":"w","method":"ping"}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(reused["result"], json!({}));
+        assert_eq!(waiting.await, None);
+
+        // The name clients used before the spec's still cancels
+        let waiting = dispatch(
+            &io_handler,
+            r#"{"jsonrpc":"2.0","id":1,"method":"wait"}"#,
+            meta(),
+            &in_flight,
+        );
+        let cancelled = r#"{"jsonrpc":"2.0","method":"cancelled","params":{"requestId":1}}"#;
+        answer(&io_handler, &in_flight, cancelled).await;
+        assert_eq!(waiting.await, None);
+        assert!(!in_flight.cancel(&ConnectionId::from("client"), &RequestId::Number(1)));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_request_ids() {
+        let (release, held) = oneshot::channel();
+        let mut io_handler = MetaIoHandler::with_middleware(Held(Arc::new(Mutex::new(Some(held)))));
+        io_handler.add_method("ping", |_params| async { Ok(json!({})) });
+        let in_flight = InFlight::default();
+        let ping = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+
+        let first = dispatch(&io_handler, ping, meta(), &in_flight);
+        let duplicate = dispatch(&io_handler, ping, meta(), &in_flight)
+            .await
+            .unwrap();
+        let duplicate: Value = serde_json::from_str(&duplicate).unwrap();
+        assert_eq!(duplicate["id"], 1);
+        assert_eq!(duplicate["error"]["code"], -32600);
+
+        // Other sessions may use the same ID
+        let other = ServerMetadata {
+            session_id: ConnectionId::from("other"),
+            ..meta()
+        };
+        assert!(dispatch(&io_handler, ping, other, &in_flight)
+            .await
+            .unwrap()
+            .contains("result"));
+
+        release.send(()).unwrap();
+        assert!(first.await.unwrap().contains("result"));
+        // Once answered, the ID is free again
+        assert!(dispatch(&io_handler, ping, meta(), &in_flight)
+            .await
+            .unwrap()
+            .contains("result"));
+    }
+}
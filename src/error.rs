@@ -0,0 +1,487 @@
+use crate::schema::RequestId;
+use crate::server::ToolCollision;
+use crate::tools::ToolError;
+use crate::transport::ConnectionId;
+use std::path::PathBuf;
+
+/// Why a server stopped before its transport closed or it was shut down
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    /// The transport failed to start or stopped with an error
+    #[error("Transport failed: {0}")]
+    Transport(#[source] TransportError),
+
+    /// A message couldn't be sent to a client
+    #[error("Failed to send response: {0}")]
+    Send(#[source] TransportError),
+
+    /// The server couldn't be built since tools added to it collide
+    #[error(transparent)]
+    Tools(#[from] ToolCollision),
+}
+
+/// Why a request a `Client` made of a server failed
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    /// The server couldn't be started or reached
+    #[error("Failed to connect to the server: {0}")]
+    Connect(#[source] std::io::Error),
+
+    /// The connection closed before the server answered
+    #[error("The connection to the server closed")]
+    Closed,
+
+    /// The server didn't answer in time
+    #[error("Timed out waiting for {0} response")]
+    Timeout(String),
+
+    /// The server answered with a JSON-RPC error
+    #[error("Server returned error {}: {}", .0.code.code(), .0.message)]
+    Rpc(jsonrpc_core::Error),
+
+    /// The result doesn't have the shape the request expects
+    #[error("Unexpected result: {0}")]
+    InvalidResult(#[from] serde_json::Error),
+}
+
+/// Errors of a `Transport`
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    /// The transport couldn't listen on its address
+    #[error("Failed to bind to {addr}: {source}")]
+    Bind {
+        addr: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Reading from or writing to the underlying stream failed
+    #[error("Transport I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A message couldn't be delivered to a connection
+    #[error("Failed to send to {connection}: {source}")]
+    Send {
+        connection: ConnectionId,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl TransportError {
+    pub(crate) fn bind(addr: impl Into<String>) -> impl FnOnce(std::io::Error) -> Self {
+        let addr = addr.into();
+        move |source| TransportError::Bind { addr, source }
+    }
+}
+
+/// Errors of a `ResourceProvider`
+#[derive(Debug, thiserror::Error)]
+pub enum ResourceError {
+    /// The provider has no resource at the URI
+    #[error("Resource not found: {0}")]
+    NotFound(String),
+
+    /// The provider can't report changes to the resource at the URI
+    #[error("Subscriptions to {0} aren't supported")]
+    Unsubscribable(String),
+
+    /// Listing, reading or watching resources failed
+    #[error(transparent)]
+    Failed(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl ResourceError {
+    pub fn failed(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        ResourceError::Failed(error.into())
+    }
+}
+
+/// Errors of a `PromptProvider`
+#[derive(Debug, thiserror::Error)]
+pub enum PromptError {
+    /// The provider has no prompt of that name
+    #[error("Prompt not found: {0}")]
+    NotFound(String),
+
+    /// A required argument of the prompt wasn't given
+    #[error("Missing required argument: {0}")]
+    MissingArgument(String),
+
+    /// Listing or filling in prompts failed
+    #[error(transparent)]
+    Failed(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl PromptError {
+    pub fn failed(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        PromptError::Failed(error.into())
+    }
+}
+
+/// Errors of a `PluginRuntime` and the plugins it runs
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    /// A module, its capabilities or the plugin directory couldn't be read
+    #[error("Failed to load {}: {source}", .path.display())]
+    Load {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// The module couldn't be compiled or instantiated
+    #[error("Failed to instantiate the plugin: {0}")]
+    Instantiate(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// The module trapped or broke the tool ABI
+    #[error("Plugin call failed: {0}")]
+    Failed(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// A call ran past the plugin's timeout and was stopped
+    #[error("Plugin call timed out after {0:?}")]
+    TimedOut(std::time::Duration),
+}
+
+impl PluginError {
+    pub fn instantiate(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        PluginError::Instantiate(error.into())
+    }
+
+    pub fn failed(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        PluginError::Failed(error.into())
+    }
+
+    pub(crate) fn load<E: Into<Box<dyn std::error::Error + Send + Sync>>>(
+        path: impl Into<PathBuf>,
+    ) -> impl FnOnce(E) -> Self {
+        let path = path.into();
+        move |source| PluginError::Load {
+            path,
+            source: source.into(),
+        }
+    }
+}
+
+/// Why a request the server sent to a client, or the response to one, failed
+#[derive(Debug, thiserror::Error)]
+pub enum RouterError {
+    /// The server stopped sending messages
+    #[error("Server is not accepting outgoing messages")]
+    Closed,
+
+    /// The client answered with a JSON-RPC error
+    #[error("Client returned error {}: {}", .0.code.code(), .0.message)]
+    Rpc(jsonrpc_core::Error),
+
+    /// The request was dropped before the client answered
+    #[error("Request {0} was dropped")]
+    Dropped(String),
+
+    /// The client didn't answer in time
+    #[error("Timed out waiting for {0} response")]
+    Timeout(String),
+
+    /// The result doesn't have the shape the request expects
+    #[error("Unexpected result: {0}")]
+    InvalidResult(#[from] serde_json::Error),
+
+    /// A response has no ID, or one that isn't a request ID
+    #[error("Response has no valid ID")]
+    InvalidId,
+
+    /// A response answers no request waiting on its connection
+    #[error("No pending request {id} for {connection}")]
+    NotPending {
+        id: RequestId,
+        connection: ConnectionId,
+    },
+}
+
+/// Why the cursor or params of a paginated list request were rejected
+#[derive(Debug, thiserror::Error)]
+pub enum PaginationError {
+    /// The params aren't those of a list request
+    #[error(transparent)]
+    Params(jsonrpc_core::Error),
+
+    /// The cursor wasn't made by this server
+    #[error("{0}")]
+    InvalidCursor(&'static str),
+
+    /// The cursor points after the last item
+    #[error("Cursor is past the end of the list")]
+    PastEnd,
+}
+
+/// Why a URI template couldn't be parsed, with the template
+#[derive(Debug, thiserror::Error)]
+pub enum UriTemplateError {
+    #[error("Unclosed expression in URI template: {0}")]
+    Unclosed(String),
+
+    #[error("Unexpected '}}' in URI template: {0}")]
+    UnexpectedClose(String),
+
+    #[error("Invalid prefix in URI template: {0}")]
+    InvalidPrefix(String),
+
+    #[error("Invalid variable name in URI template: {0}")]
+    InvalidVariable(String),
+}
+
+/// Why a config file couldn't be loaded or watched
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// The file couldn't be read
+    #[error("Failed to read {}: {source}", .path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The file isn't valid TOML or has unknown settings
+    #[error("Invalid config {}: {source}", .path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    /// A command tool was declared without a program to run
+    #[error("Command tool {0} has no command")]
+    EmptyCommand(String),
+
+    /// The log level isn't a filter in `RUST_LOG` syntax
+    #[error("Invalid log level: {0}")]
+    LogLevel(#[source] LogError),
+
+    /// The file couldn't be watched for changes
+    #[error(transparent)]
+    Watch(#[from] ResourceError),
+}
+
+/// Why a secrets file couldn't be read
+#[derive(Debug, thiserror::Error)]
+pub enum SecretsError {
+    #[error("Failed to read secrets file {}: {source}", .path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A line that isn't blank or a comment has no `=`
+    #[error("Expected NAME=value on line {line} of {}", .path.display())]
+    Syntax { path: PathBuf, line: usize },
+}
+
+/// Errors of a `MemoryStore`
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    /// Reading or writing a file of the store failed
+    #[error("Failed to access {}: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The store's file isn't valid JSON
+    #[error("Invalid memory file {}: {source}", .path.display())]
+    InvalidFile {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// A memory couldn't be encoded or decoded
+    #[error("Invalid memory: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The database failed
+    #[cfg(feature = "sqlite")]
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    /// Any other failure of the store
+    #[error(transparent)]
+    Failed(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl StoreError {
+    pub fn failed(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        StoreError::Failed(error.into())
+    }
+
+    #[cfg(feature = "tool-memory")]
+    pub(crate) fn io(path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.into();
+        move |source| StoreError::Io { path, source }
+    }
+}
+
+/// Errors of a `SearchProvider`
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    /// The engine's URL couldn't be built
+    #[error("Invalid search URL: {0}")]
+    Url(#[from] url::ParseError),
+
+    /// The request failed or the engine answered with an error status
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+
+    /// The engine's answer couldn't be decoded
+    #[error("Invalid {provider} response: {source}")]
+    InvalidResponse {
+        provider: &'static str,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// Any other failure of the engine
+    #[error(transparent)]
+    Failed(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl SearchError {
+    pub fn failed(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        SearchError::Failed(error.into())
+    }
+
+    #[cfg(feature = "tool-search")]
+    pub(crate) fn invalid_response(provider: &'static str) -> impl FnOnce(reqwest::Error) -> Self {
+        move |source| SearchError::InvalidResponse { provider, source }
+    }
+}
+
+/// Why a tool call couldn't be put to approval
+#[derive(Debug, thiserror::Error)]
+pub enum ApprovalError {
+    /// The webhook couldn't be reached
+    #[error("Failed to reach {url}: {source}")]
+    Unreachable {
+        url: url::Url,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// The webhook answered with an error status
+    #[error("{url} answered with {status}")]
+    Status {
+        url: url::Url,
+        status: reqwest::StatusCode,
+    },
+
+    /// The webhook's answer isn't an `ApprovalDecision`
+    #[error("{url} answered with an invalid decision: {source}")]
+    InvalidDecision {
+        url: url::Url,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// The client can't elicit and no approver is set
+    #[error("{0} needs approval, but the client can't be asked and no approver is set")]
+    NoApprover(String),
+
+    /// The call's arguments couldn't be shown to the user
+    #[error("Invalid arguments: {0}")]
+    Arguments(#[from] serde_json::Error),
+
+    /// Asking the client's user failed
+    #[error(transparent)]
+    Elicitation(#[from] ToolError),
+
+    /// Any other failure of an approver
+    #[error(transparent)]
+    Failed(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl ApprovalError {
+    pub fn failed(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        ApprovalError::Failed(error.into())
+    }
+}
+
+/// Why logging couldn't be set up or its filter changed
+#[derive(Debug, thiserror::Error)]
+pub enum LogError {
+    /// A directive without a target names no level
+    #[error("Unknown log level: {0}")]
+    UnknownLevel(String),
+
+    /// The directives aren't in `RUST_LOG` syntax
+    #[error("Invalid log filter {directives}: {source}")]
+    InvalidFilter {
+        directives: String,
+        #[source]
+        source: tracing_subscriber::filter::ParseError,
+    },
+
+    /// The log file or its directory couldn't be created or opened
+    #[error("Failed to open {}: {source}", .path.display())]
+    Open {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// The log file's path doesn't end in a file name
+    #[error("Invalid log file: {}", .0.display())]
+    InvalidPath(PathBuf),
+
+    /// The filter in effect couldn't be replaced
+    #[error("Failed to change the log filter: {0}")]
+    Reload(#[source] tracing_subscriber::reload::Error),
+
+    /// Another subscriber was installed first
+    #[error("Logging is already set up")]
+    AlreadySetUp(#[source] tracing_subscriber::util::TryInitError),
+}
+
+impl LogError {
+    pub(crate) fn open<E: Into<Box<dyn std::error::Error + Send + Sync>>>(
+        path: impl Into<PathBuf>,
+    ) -> impl FnOnce(E) -> Self {
+        let path = path.into();
+        move |source| LogError::Open {
+            path,
+            source: source.into(),
+        }
+    }
+}
+
+/// Why a conformance suite couldn't be loaded, or where a server failed it
+#[derive(Debug, thiserror::Error)]
+pub enum ConformanceError {
+    /// A suite file or directory couldn't be read
+    #[error("Failed to read suites in {}: {source}", .path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A suite file isn't a suite
+    #[error("Invalid suite {}: {source}", .path.display())]
+    Invalid {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// The server failed the handshake before the first step
+    #[error("Handshake: {0}")]
+    Handshake(String),
+
+    /// The server failed a step, counted from 1
+    #[error("Step {step}: {message}")]
+    Step { step: usize, message: String },
+
+    /// After the steps, the server stopped answering or had answered what it wasn't asked
+    #[error("{0}")]
+    Finish(String),
+}
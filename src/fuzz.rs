@@ -0,0 +1,225 @@
+//! Entry points for fuzzing the JSON-RPC message layer
+//!
+//! Each feeds arbitrary input to a fresh server session the way a client
+//! would, and panics if the server stops answering or answers with something
+//! that isn't JSON-RPC. The cargo-fuzz targets under `fuzz/` call these:
+//!
+//! ```text
+//! cargo +nightly fuzz run message
+//! ```
+
+use crate::message::JsonRpcMessage;
+use crate::protocol::LATEST_PROTOCOL_VERSION;
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolError};
+use crate::transport::{ChannelEnd, ChannelTransport, TransportType};
+use crate::{Server, ServerHandle};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// How long the server gets to answer before it counts as stuck
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// ID of the `ping` checking the server still answers, unlikely to clash with the input's
+const ALIVE_ID: &str = "fuzz-alive";
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    Fast,
+    Slow,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct FuzzProperties {
+    #[schemars(description = "Text to answer with", required = true)]
+    text: String,
+    count: Option<u32>,
+    ratio: Option<f64>,
+    tags: Option<Vec<String>>,
+    mode: Option<Mode>,
+    nested: Option<BTreeMap<String, Value>>,
+}
+
+/// A tool whose arguments take many shapes, for fuzzing their deserialization
+#[derive(Clone, PartialEq, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "fuzz",
+    description = "Answers with the text it was given",
+    properties = FuzzProperties
+)]
+pub struct FuzzTool;
+
+impl FuzzTool {
+    async fn run(
+        &self,
+        properties: FuzzProperties,
+        _context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        Ok(CallToolResult::text(properties.text))
+    }
+}
+
+/// Sends `data` as a message from a client that completed the handshake
+pub fn message(data: &[u8]) {
+    let text = String::from_utf8_lossy(data);
+    let _ = JsonRpcMessage::parse(&text);
+    run(|mut end| async move {
+        handshake(&mut end, initialize_params()).await;
+        send(&mut end, text.into_owned()).await;
+        end
+    });
+}
+
+/// Starts a session with `data` as the parameters of `initialize`
+pub fn initialize(data: &[u8]) {
+    let params = json_or_text(data);
+    run(|mut end| async move {
+        handshake(&mut end, params).await;
+        end
+    });
+}
+
+/// Calls a tool with `data` as its arguments
+pub fn tool_arguments(data: &[u8]) {
+    let arguments = json_or_text(data);
+    run(|mut end| async move {
+        handshake(&mut end, initialize_params()).await;
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": "fuzz-call",
+            "method": "tools/call",
+            "params": { "name": "fuzz", "arguments": arguments },
+        });
+        send(&mut end, call.to_string()).await;
+        answer(&mut end, "fuzz-call").await;
+        end
+    });
+}
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start the fuzzing runtime")
+    })
+}
+
+/// Runs `session` against a fresh server, then checks the server still answers
+fn run<F, Fut>(session: F)
+where
+    F: FnOnce(ChannelEnd) -> Fut,
+    Fut: std::future::Future<Output = ChannelEnd>,
+{
+    runtime().block_on(async {
+        let (transport, end) = ChannelTransport::new();
+        let handle = Server::builder()
+            .tool(FuzzTool)
+            .build()
+            .spawn(TransportType::Channel(transport));
+        let mut end = session(end).await;
+        let ping = json!({ "jsonrpc": "2.0", "id": ALIVE_ID, "method": "ping" });
+        send(&mut end, ping.to_string()).await;
+        answer(&mut end, ALIVE_ID).await;
+        stop(handle, end).await;
+    });
+}
+
+async fn stop(handle: ServerHandle, end: ChannelEnd) {
+    drop(end);
+    match tokio::time::timeout(ANSWER_TIMEOUT, handle.wait()).await {
+        Ok(_) => {}
+        Err(_) => panic!("The server didn't stop once its client left"),
+    }
+}
+
+fn initialize_params() -> Value {
+    json!({
+        "protocolVersion": LATEST_PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": { "name": "fuzz", "version": "0" },
+    })
+}
+
+/// Initializes the session with `params`, answered with a result or an error
+async fn handshake(end: &mut ChannelEnd, params: Value) {
+    let initialize = json!({
+        "jsonrpc": "2.0",
+        "id": "fuzz-init",
+        "method": "initialize",
+        "params": params,
+    });
+    send(end, initialize.to_string()).await;
+    answer(end, "fuzz-init").await;
+    let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    send(end, initialized.to_string()).await;
+}
+
+async fn send(end: &mut ChannelEnd, message: String) {
+    if end.sender.send(message).await.is_err() {
+        panic!("The server stopped reading");
+    }
+}
+
+/// Waits for the answer to request `id`, checking every message before it is JSON-RPC
+async fn answer(end: &mut ChannelEnd, id: &str) -> Value {
+    loop {
+        let message = match tokio::time::timeout(ANSWER_TIMEOUT, end.receiver.recv()).await {
+            Ok(Some(message)) => message,
+            Ok(None) => panic!("The server closed the connection before answering {}", id),
+            Err(_) => panic!("The server didn't answer {} in time", id),
+        };
+        let parsed = JsonRpcMessage::parse(&message)
+            .unwrap_or_else(|e| panic!("The server sent {}, which isn't JSON-RPC: {}", message, e));
+        if let Some(response) = parsed.responses().into_iter().find(|r| r["id"] == id) {
+            return response.clone();
+        }
+    }
+}
+
+/// `data` as JSON, or as a JSON string when it isn't
+fn json_or_text(data: &[u8]) -> Value {
+    serde_json::from_slice(data)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(data).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_malformed_input() {
+        for data in [
+            &b""[..],
+            b"\xff\xfe",
+            b"null",
+            b"[]",
+            b"[[]]",
+            b"{\"jsonrpc\":\"2.0\",\"id\":{},\"method\":\"ping\"}",
+            b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"tools/call\",\"params\":[1]}",
+            b"{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}",
+            b"{\"jsonrpc\":\"2.0\",\"method\":\"cancelled\",\"params\":null}",
+        ] {
+            message(data);
+        }
+        for data in [&b"null"[..], b"{}", b"{\"protocolVersion\":1}", b"\"x\""] {
+            initialize(data);
+        }
+        for data in [
+            &b"{}"[..],
+            b"[]",
+            b"{\"text\":1}",
+            b"{\"text\":\"hi\",\"count\":-1,\"mode\":\"other\"}",
+            b"{\"text\":\"hi\",\"ratio\":1e400}",
+        ] {
+            tool_arguments(data);
+        }
+    }
+}
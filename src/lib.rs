@@ -1,158 +1,724 @@
-use anyhow::{Context, Result};
+use approval::Approvals;
+use completion::CompletionRef;
 use jsonrpc_core::{MetaIoHandler, Metadata, Params};
-use tokio::sync::mpsc;
-use tools::ToolCallHandler;
-use tracing::{debug, error, info};
-use transport::{Transport, TransportType};
+use middleware::Middleware;
+use policy::ToolPolicy;
+use prompts::PromptProvider;
+use rate_limit::RateLimits;
+use resources::{MemoryResources, ResourceMounts};
+use roots::RootsState;
+use router::RequestRouter;
+use session::{SessionState, Sessions};
+use shutdown::Shutdown;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinSet;
+use tools::{ToolCallHandler, ToolCaller, ToolContext};
+use tracing::{debug, error, info, warn};
+use transport::{
+    ConnectionEvent, ConnectionId, QueueConfig, Transport, TransportMessage, TransportType,
+};
+
+// Lets code generated by `bioma-tool-derive` name this crate from inside it
+extern crate self as bioma_tool;
 
+pub mod aggregator;
+pub mod approval;
+pub mod bridge;
+pub mod client;
+pub mod completion;
+pub mod config;
+mod dispatch;
+pub mod error;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzz;
+pub mod logging;
+pub mod message;
+pub mod middleware;
+pub mod pagination;
+pub mod plugins;
+pub mod policy;
+pub mod prompts;
+pub mod protocol;
+pub mod rate_limit;
+pub mod resources;
+pub mod roots;
+pub mod router;
 pub mod schema;
+pub mod secrets;
+pub mod server;
+pub mod session;
+pub mod shutdown;
+pub mod testing;
 pub mod tools;
+mod trace;
 pub mod transport;
 
+pub use client::Client;
+pub use error::{
+    ApprovalError, ClientError, ConfigError, ConformanceError, LogError, PaginationError,
+    PluginError, PromptError, ResourceError, RouterError, SearchError, SecretsError, ServerError,
+    StoreError, TransportError, UriTemplateError,
+};
+pub use server::{
+    ListChanged, Server, ServerBuilder, ServerHandle, ToolCollision, ToolCollisions, ToolSwitches,
+};
+
 use schema::{
-    CallToolRequestParams, CancelledNotificationParams, Implementation, InitializeRequestParams,
-    InitializeResult, ListPromptsResult, ListResourcesResult, ListToolsResult, Prompt, Resource,
-    ServerCapabilities,
+    CallToolRequestParams, CancelledNotificationParams, CompleteRequestParams, CompleteResult,
+    EmptyResult, GetPromptRequestParams, Implementation, InitializeRequestParams, InitializeResult,
+    ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult,
+    LoggingMessageNotificationParams, Prompt, ProtocolVersion, ReadResourceRequestParams,
+    ResourceUpdatedNotificationParams, ServerCapabilities, SetLevelRequestParams,
+    SubscribeRequestParams, UnsubscribeRequestParams, Versioned,
 };
 
-#[derive(Default, Clone)]
-struct ServerMetadata;
+/// Per-request metadata identifying the session the request belongs to
+#[derive(Clone)]
+struct ServerMetadata {
+    session_id: ConnectionId,
+    sessions: Sessions,
+    router: RequestRouter,
+    roots: RootsState,
+}
 impl Metadata for ServerMetadata {}
 
+/// What a server offers its clients
+///
+/// `Server::builder()` assembles one from tools, resources and prompts, or
+/// this can be implemented for a type of your own.
 pub trait ModelContextProtocolServer: Send + Sync + 'static {
-    fn new() -> Self;
     fn get_capabilities(&self) -> ServerCapabilities;
-    fn get_resources(&self) -> &Vec<Resource>;
     fn get_prompts(&self) -> &Vec<Prompt>;
-    fn get_tools(&self) -> &Vec<Box<dyn ToolCallHandler>>;
+    fn get_tools(&self) -> Vec<std::sync::Arc<dyn ToolCallHandler>>;
+
+    /// Providers of prompts offered after those of `get_prompts`, none by default
+    fn get_prompt_providers(&self) -> &[std::sync::Arc<dyn PromptProvider>] {
+        &[]
+    }
+
+    /// Maximum number of items in each page of `tools/list`, `resources/list` and `prompts/list`
+    fn get_page_size(&self) -> usize {
+        pagination::DEFAULT_PAGE_SIZE
+    }
+
+    /// Resource providers, which `resources/*` requests are routed to by URI prefix
+    fn get_resource_mounts(&self) -> &ResourceMounts;
+
+    /// How long a `tools/call` may run, for tools that don't set their own timeout
+    fn get_tool_timeout(&self) -> std::time::Duration {
+        tools::DEFAULT_TOOL_TIMEOUT
+    }
+
+    /// Calls of a `tools/call_batch` run at once
+    fn get_batch_concurrency(&self) -> usize {
+        tools::batch::DEFAULT_BATCH_CONCURRENCY
+    }
+
+    /// Longest arguments of a tool call as JSON, beyond which the call is refused
+    fn get_max_arguments_size(&self) -> usize {
+        tools::DEFAULT_MAX_ARGUMENTS_SIZE
+    }
+
+    /// Limits on how often clients may send requests, none by default
+    fn get_rate_limits(&self) -> RateLimits {
+        RateLimits::default()
+    }
+
+    /// Sizes of the queues between the transport and the handlers, and what happens when they fill up
+    fn get_queue_config(&self) -> QueueConfig {
+        QueueConfig::default()
+    }
+
+    /// Hooks run on every request and response, in order
+    fn get_middleware(&self) -> &[std::sync::Arc<dyn Middleware>] {
+        &[]
+    }
+
+    /// Whether `tools/list` offers the tool and `tools/call` runs it
+    fn is_tool_enabled(&self, _name: &str) -> bool {
+        true
+    }
+
+    /// What the operator allows the tool called `name` to do, everything by default
+    fn get_tool_policy(&self, _name: &str) -> ToolPolicy {
+        ToolPolicy::default()
+    }
+
+    /// How calls of destructive tools get approved, which they can't be without an approver
+    /// or a client that supports elicitation
+    fn get_approvals(&self) -> Approvals {
+        Approvals::default()
+    }
+
+    /// Resources tools publish with `ToolContext::publish_resource`, which they can't without
+    fn get_published_resources(&self) -> Option<MemoryResources> {
+        None
+    }
+
+    /// Receives the lists that changed, which clients are then notified of
+    fn list_changes(&self) -> Option<broadcast::Receiver<ListChanged>> {
+        None
+    }
+
+    /// Receives notifications to send to every initialized client
+    fn notifications(&self) -> Option<broadcast::Receiver<serde_json::Value>> {
+        None
+    }
 }
 
+/// Runs the server until the transport closes or the process gets SIGINT or SIGTERM
 pub async fn start_server<T: ModelContextProtocolServer>(
+    server: T,
+    transport: TransportType,
+) -> Result<(), ServerError> {
+    start_server_with_shutdown(server, transport, Shutdown::new().on_signals()).await
+}
+
+/// Runs the server until the transport closes or `shutdown` is triggered
+///
+/// Requests still running when the shutdown starts get its drain timeout to
+/// finish, and their responses are sent before this returns.
+pub async fn start_server_with_shutdown<T: ModelContextProtocolServer>(
+    server: T,
+    transport: TransportType,
+    shutdown: Shutdown,
+) -> Result<(), ServerError> {
+    run_server(server, transport, shutdown, Sessions::default()).await
+}
+
+/// Runs the server like `start_server_with_shutdown`, keeping its clients in `sessions`
+pub(crate) async fn run_server<T: ModelContextProtocolServer>(
+    server: T,
     mut transport: TransportType,
-) -> Result<()> {
-    let server = T::new();
-    let mut io_handler = MetaIoHandler::default();
+    shutdown: Shutdown,
+    sessions: Sessions,
+) -> Result<(), ServerError> {
+    let queues = server.get_queue_config();
+    let metrics = queues.metrics();
+    metrics.set_capacities(queues.inbound_capacity(), queues.outbound_capacity());
+    let health = transport.health();
+    if let Some(health) = &health {
+        health.attach(sessions.clone());
+        health.attach_queues(metrics.clone());
+    }
+    let mut io_handler = MetaIoHandler::with_middleware((
+        trace::RequestSpans,
+        middleware::Hooks::new(server.get_middleware().to_vec()),
+        rate_limit::RateLimiter::new(server.get_rate_limits()),
+        protocol::HandshakeGate {
+            stateless: transport.is_stateless(),
+        },
+    ));
 
     let server = std::sync::Arc::new(server);
     let server_tools = server.clone();
     let server_resources = server.clone();
     let server_prompts = server.clone();
+    let server_get_prompt = server.clone();
+    let server_complete = server.clone();
     let server_call = server.clone();
+    let validator = std::sync::Arc::new(tools::ArgumentValidator::default());
+    let server_templates = server.clone();
+    let server_read = server.clone();
+    let server_subscribe = server.clone();
+    let server_unsubscribe = server.clone();
+    let server_updates = server.clone();
 
-    io_handler.add_method_with_meta(
-        "initialize",
-        move |params: Params, _meta: ServerMetadata| {
-            let server = server.clone();
-            debug!("Handling initialize request");
+    io_handler.add_method_with_meta("initialize", move |params: Params, meta: ServerMetadata| {
+        let server = server.clone();
+        debug!("Handling initialize request from {}", meta.session_id);
 
-            async move {
-                let init_params: InitializeRequestParams = params.parse().map_err(|e| {
-                    error!("Failed to parse initialize parameters: {}", e);
-                    jsonrpc_core::Error::invalid_params(e.to_string())
-                })?;
+        // Update the session before returning the future, so a notification
+        // dispatched right after this request can't be overwritten by it
+        let init_params = params
+            .parse::<InitializeRequestParams>()
+            .inspect(|init_params| {
+                let version = protocol::negotiate_version(&init_params.protocol_version);
+                meta.sessions.update(&meta.session_id, |session| {
+                    session.client_info = Some(init_params.client_info.clone());
+                    session.client_capabilities = init_params.capabilities.clone().for_version(
+                        ProtocolVersion::parse(version).unwrap_or(ProtocolVersion::LATEST),
+                    );
+                    session.protocol_version = Some(version.to_string());
+                    session.state = SessionState::Initializing;
+                });
+            });
 
-                let result = InitializeResult {
-                    capabilities: server.get_capabilities(),
-                    protocol_version: init_params.protocol_version,
-                    server_info: Implementation {
-                        name: "rust-mcp-server".to_string(),
-                        version: "0.1.0".to_string(),
-                    },
-                    instructions: Some("Basic MCP server with tool support".to_string()),
-                    meta: None,
-                };
+        async move {
+            let init_params = init_params.map_err(|e| {
+                error!("Failed to parse initialize parameters: {}", e);
+                jsonrpc_core::Error::invalid_params(e.to_string())
+            })?;
 
-                info!("Successfully handled initialize request");
-                Ok(serde_json::to_value(result).map_err(|e| {
-                    error!("Failed to serialize initialize result: {}", e);
-                    jsonrpc_core::Error::invalid_params(e.to_string())
-                })?)
+            let mut capabilities = server.get_capabilities();
+            if server.get_resource_mounts().subscribable() {
+                if let Some(resources) = capabilities.resources.as_mut() {
+                    resources.subscribe = Some(true);
+                }
+            }
+            if capabilities.tools.is_some() {
+                capabilities
+                    .experimental
+                    .get_or_insert_with(Default::default)
+                    .insert(
+                        tools::batch::CALL_BATCH_METHOD.to_string(),
+                        tools::batch::capability(server.get_batch_concurrency()),
+                    );
             }
-        },
-    );
+
+            let protocol_version = protocol::negotiate_version(&init_params.protocol_version);
+            if protocol_version != init_params.protocol_version {
+                info!(
+                    "{} asked for unsupported protocol version {}, offering {}",
+                    meta.session_id, init_params.protocol_version, protocol_version
+                );
+            }
+
+            let result = InitializeResult {
+                capabilities,
+                protocol_version: protocol_version.to_string(),
+                server_info: Implementation {
+                    name: "rust-mcp-server".to_string(),
+                    version: "0.1.0".to_string(),
+                },
+                instructions: Some("Basic MCP server with tool support".to_string()),
+                meta: None,
+            };
+
+            info!(
+                "Successfully handled initialize request from {}",
+                meta.session_id
+            );
+            let version =
+                ProtocolVersion::parse(protocol_version).unwrap_or(ProtocolVersion::LATEST);
+            result.to_wire(version).map_err(|e| {
+                error!("Failed to serialize initialize result: {}", e);
+                jsonrpc_core::Error::invalid_params(e.to_string())
+            })
+        }
+    });
 
     io_handler.add_notification_with_meta(
         "notifications/initialized",
-        |_params, _meta: ServerMetadata| {
-            info!("Received initialized notification");
+        |_params, meta: ServerMetadata| {
+            // Looked up rather than updated, so a notification arriving after
+            // its connection closed doesn't bring the session back
+            let state = meta
+                .sessions
+                .get(&meta.session_id)
+                .map_or(SessionState::Uninitialized, |session| session.state);
+            if state != SessionState::Initializing {
+                warn!(
+                    "Ignored initialized notification from {} in state {:?}",
+                    meta.session_id, state
+                );
+                return;
+            }
+            meta.sessions.update(&meta.session_id, |session| {
+                session.state = SessionState::Ready
+            });
+            info!("Received initialized notification from {}", meta.session_id);
+            meta.roots
+                .spawn_refresh(meta.session_id, meta.sessions, meta.router);
         },
     );
 
     io_handler.add_notification_with_meta(
-        "cancelled",
-        move |params: Params, _meta: ServerMetadata| match params
-            .parse::<CancelledNotificationParams>()
-        {
-            Ok(cancel_params) => {
-                info!(
-                    "Received cancellation for request {}: {}",
-                    cancel_params.request_id,
-                    cancel_params.reason.unwrap_or_default()
-                );
-            }
-            Err(e) => {
-                error!("Failed to parse cancellation params: {}", e);
-            }
+        "notifications/roots/list_changed",
+        |_params, meta: ServerMetadata| {
+            info!("Roots changed for {}", meta.session_id);
+            meta.roots
+                .spawn_refresh(meta.session_id, meta.sessions, meta.router);
+        },
+    );
+
+    // Dispatching stops the cancelled request before this runs
+    for method in dispatch::CANCELLED_METHODS {
+        io_handler.add_notification_with_meta(
+            method,
+            move |params: Params, _meta: ServerMetadata| match params
+                .parse::<CancelledNotificationParams>()
+            {
+                Ok(cancel_params) => {
+                    info!(
+                        "Received cancellation for request {}: {}",
+                        cancel_params.request_id,
+                        cancel_params.reason.unwrap_or_default()
+                    );
+                }
+                Err(e) => {
+                    error!("Failed to parse cancellation params: {}", e);
+                }
+            },
+        );
+    }
+
+    io_handler.add_method("ping", |_params| async move {
+        debug!("Handling ping request");
+        Ok(serde_json::to_value(EmptyResult::default()).unwrap_or_default())
+    });
+
+    io_handler.add_method_with_meta(
+        "logging/setLevel",
+        |params: Params, meta: ServerMetadata| {
+            let result = params
+                .parse::<SetLevelRequestParams>()
+                .map(|params| {
+                    info!(
+                        "Setting log level for {} to {:?}",
+                        meta.session_id, params.level
+                    );
+                    meta.sessions.update(&meta.session_id, |session| {
+                        session.log_level = Some(params.level);
+                    });
+                    serde_json::to_value(EmptyResult::default()).unwrap_or_default()
+                })
+                .map_err(|e| {
+                    error!("Failed to parse logging/setLevel parameters: {}", e);
+                    jsonrpc_core::Error::invalid_params(e.to_string())
+                });
+
+            async move { result }
         },
     );
 
-    io_handler.add_method("resources/list", move |_params| {
+    io_handler.add_method("resources/list", move |params: Params| {
         let server = server_resources.clone();
         debug!("Handling resources/list request");
 
         async move {
+            let cursor = pagination::cursor(params).map_err(|e| {
+                error!("Failed to parse list parameters: {}", e);
+                jsonrpc_core::Error::invalid_params(e.to_string())
+            })?;
+            let (resources, next_cursor) = server
+                .get_resource_mounts()
+                .list_page(cursor.as_deref(), server.get_page_size())
+                .await
+                .map_err(|e| {
+                    error!("Invalid cursor: {}", e);
+                    jsonrpc_core::Error::invalid_params(format!("Invalid cursor: {}", e))
+                })?;
             let response = ListResourcesResult {
-                next_cursor: None,
-                resources: server.get_resources().clone(),
+                next_cursor,
+                resources,
                 meta: None,
             };
 
             info!("Successfully handled resources/list request");
-            Ok(serde_json::to_value(response).unwrap_or_default())
+            serialized("resources/list", serde_json::to_value(response))
+        }
+    });
+
+    io_handler.add_method("resources/templates/list", move |_params| {
+        let server = server_templates.clone();
+        debug!("Handling resources/templates/list request");
+
+        async move {
+            let response = ListResourceTemplatesResult {
+                next_cursor: None,
+                resource_templates: server.get_resource_mounts().templates(),
+                meta: None,
+            };
+
+            info!("Successfully handled resources/templates/list request");
+            serialized("resources/templates/list", serde_json::to_value(response))
+        }
+    });
+
+    io_handler.add_method("resources/read", move |params: Params| {
+        let server = server_read.clone();
+        debug!("Handling resources/read request");
+
+        async move {
+            let params: ReadResourceRequestParams = params.parse().map_err(|e| {
+                error!("Failed to parse resources/read parameters: {}", e);
+                jsonrpc_core::Error::invalid_params(e.to_string())
+            })?;
+
+            let not_found = || {
+                error!("Unknown resource requested: {}", params.uri);
+                jsonrpc_core::Error {
+                    code: jsonrpc_core::ErrorCode::ServerError(-32002),
+                    message: "Resource not found".to_string(),
+                    data: Some(serde_json::json!({ "uri": params.uri })),
+                }
+            };
+            let provider = server
+                .get_resource_mounts()
+                .provider(&params.uri)
+                .ok_or_else(not_found)?;
+            let result = provider.read(&params.uri).await.map_err(|e| match e {
+                ResourceError::NotFound(_) => not_found(),
+                e => {
+                    error!("Failed to read resource {}: {}", params.uri, e);
+                    jsonrpc_core::Error::internal_error()
+                }
+            })?;
+
+            info!("Successfully handled resources/read for: {}", params.uri);
+            // Moved rather than serialized, which would copy every blob
+            let mut response = serde_json::Map::new();
+            if let Some(meta) = result.meta {
+                response.insert(
+                    "_meta".to_string(),
+                    serde_json::Value::Object(meta.into_iter().collect()),
+                );
+            }
+            response.insert(
+                "contents".to_string(),
+                serde_json::Value::Array(result.contents),
+            );
+            Ok(serde_json::Value::Object(response))
         }
     });
 
-    io_handler.add_method("prompts/list", move |_params| {
+    io_handler.add_method_with_meta(
+        "resources/subscribe",
+        move |params: Params, meta: ServerMetadata| {
+            let server = server_subscribe.clone();
+            debug!(
+                "Handling resources/subscribe request from {}",
+                meta.session_id
+            );
+
+            let subscribe = || {
+                let params: SubscribeRequestParams = params.parse().map_err(|e| {
+                    error!("Failed to parse resources/subscribe parameters: {}", e);
+                    jsonrpc_core::Error::invalid_params(e.to_string())
+                })?;
+
+                let mounts = server.get_resource_mounts();
+                if !mounts.subscribable() {
+                    error!("Resource subscriptions are not supported");
+                    return Err(jsonrpc_core::Error::method_not_found());
+                }
+                let provider = mounts.provider(&params.uri).ok_or_else(|| {
+                    error!("No provider for resource {}", params.uri);
+                    jsonrpc_core::Error::invalid_params(format!(
+                        "Resource not found: {}",
+                        params.uri
+                    ))
+                })?;
+
+                // Only the first subscriber starts watching the resource
+                if meta.sessions.subscribers(&params.uri).is_empty() {
+                    provider.subscribe(&params.uri).map_err(|e| {
+                        error!("Failed to watch resource {}: {}", params.uri, e);
+                        jsonrpc_core::Error::invalid_params(e.to_string())
+                    })?;
+                }
+                meta.sessions.update(&meta.session_id, |session| {
+                    session.subscriptions.insert(params.uri.clone());
+                });
+
+                info!("{} subscribed to {}", meta.session_id, params.uri);
+                Ok(serde_json::to_value(EmptyResult::default()).unwrap_or_default())
+            };
+
+            let result = subscribe();
+            async move { result }
+        },
+    );
+
+    io_handler.add_method_with_meta(
+        "resources/unsubscribe",
+        move |params: Params, meta: ServerMetadata| {
+            let server = server_unsubscribe.clone();
+            debug!(
+                "Handling resources/unsubscribe request from {}",
+                meta.session_id
+            );
+
+            let unsubscribe = || {
+                let params: UnsubscribeRequestParams = params.parse().map_err(|e| {
+                    error!("Failed to parse resources/unsubscribe parameters: {}", e);
+                    jsonrpc_core::Error::invalid_params(e.to_string())
+                })?;
+
+                let mounts = server.get_resource_mounts();
+                if !mounts.subscribable() {
+                    error!("Resource subscriptions are not supported");
+                    return Err(jsonrpc_core::Error::method_not_found());
+                }
+
+                let removed = meta.sessions.update(&meta.session_id, |session| {
+                    session.subscriptions.remove(&params.uri)
+                });
+
+                // Stop watching once the last subscriber is gone
+                if removed && meta.sessions.subscribers(&params.uri).is_empty() {
+                    let unwatched = mounts
+                        .provider(&params.uri)
+                        .map_or(Ok(()), |provider| provider.unsubscribe(&params.uri));
+                    if let Err(e) = unwatched {
+                        error!("Failed to unwatch resource {}: {}", params.uri, e);
+                    }
+                }
+
+                info!("{} unsubscribed from {}", meta.session_id, params.uri);
+                Ok(serde_json::to_value(EmptyResult::default()).unwrap_or_default())
+            };
+
+            let result = unsubscribe();
+            async move { result }
+        },
+    );
+
+    io_handler.add_method("prompts/list", move |params: Params| {
         let server = server_prompts.clone();
         debug!("Handling prompts/list request");
 
         async move {
+            let mut prompts = server.get_prompts().clone();
+            for provider in server.get_prompt_providers() {
+                match provider.list().await {
+                    Ok(provided) => prompts.extend(provided),
+                    Err(e) => error!("Failed to list prompts: {}", e),
+                }
+            }
+            let (prompts, next_cursor) = list_page(&prompts, params, server.get_page_size())?;
             let response = ListPromptsResult {
-                next_cursor: None,
-                prompts: server.get_prompts().clone(),
+                next_cursor,
+                prompts,
                 meta: None,
             };
 
             info!("Successfully handled prompts/list request");
-            Ok(serde_json::to_value(response).unwrap_or_default())
+            serialized("prompts/list", serde_json::to_value(response))
+        }
+    });
+
+    io_handler.add_method("prompts/get", move |params: Params| {
+        let server = server_get_prompt.clone();
+        debug!("Handling prompts/get request");
+
+        async move {
+            let params: GetPromptRequestParams = params.parse().map_err(|e| {
+                error!("Failed to parse prompts/get parameters: {}", e);
+                jsonrpc_core::Error::invalid_params(e.to_string())
+            })?;
+            let arguments = params.arguments.unwrap_or_default();
+
+            for provider in server.get_prompt_providers() {
+                match provider.get(&params.name, arguments.clone()).await {
+                    Ok(result) => {
+                        info!("Successfully handled prompts/get for: {}", params.name);
+                        return serde_json::to_value(result).map_err(|e| {
+                            error!("Failed to serialize prompts/get result: {}", e);
+                            jsonrpc_core::Error::internal_error()
+                        });
+                    }
+                    Err(PromptError::NotFound(_)) => continue,
+                    Err(e @ PromptError::MissingArgument(_)) => {
+                        return Err(jsonrpc_core::Error::invalid_params(e.to_string()));
+                    }
+                    Err(e) => {
+                        error!("Failed to get prompt {}: {}", params.name, e);
+                        return Err(jsonrpc_core::Error::internal_error());
+                    }
+                }
+            }
+
+            error!("Unknown prompt requested: {}", params.name);
+            Err(jsonrpc_core::Error::invalid_params(
+                PromptError::NotFound(params.name).to_string(),
+            ))
+        }
+    });
+
+    io_handler.add_method("completion/complete", move |params: Params| {
+        let server = server_complete.clone();
+        debug!("Handling completion/complete request");
+
+        async move {
+            let params: CompleteRequestParams = params.parse().map_err(|e| {
+                error!("Failed to parse completion/complete parameters: {}", e);
+                jsonrpc_core::Error::invalid_params(e.to_string())
+            })?;
+            let reference: CompletionRef = serde_json::from_value(params.ref_).map_err(|e| {
+                error!("Invalid completion ref: {}", e);
+                jsonrpc_core::Error::invalid_params(e.to_string())
+            })?;
+            let argument = params.argument;
+
+            let candidates = match &reference {
+                CompletionRef::Prompt { name } => {
+                    let mut known = server.get_prompts().iter().any(|p| &p.name == name);
+                    let mut values = Vec::new();
+                    for provider in server.get_prompt_providers() {
+                        match provider.list().await {
+                            Ok(prompts) if prompts.iter().any(|p| &p.name == name) => {
+                                known = true;
+                                values.extend(provider.argument_values(name, &argument.name));
+                            }
+                            Ok(_) => {}
+                            Err(e) => error!("Failed to list prompts: {}", e),
+                        }
+                    }
+                    known.then_some(values).ok_or_else(|| {
+                        jsonrpc_core::Error::invalid_params(
+                            PromptError::NotFound(name.clone()).to_string(),
+                        )
+                    })?
+                }
+                CompletionRef::Resource { uri } => server
+                    .get_resource_mounts()
+                    .complete(uri, &argument.name, &argument.value)
+                    .ok_or_else(|| {
+                        jsonrpc_core::Error::invalid_params(format!(
+                            "Resource template not found: {}",
+                            uri
+                        ))
+                    })?,
+            };
+            let response = CompleteResult {
+                meta: None,
+                completion: completion::complete(&argument.value, candidates),
+            };
+
+            info!("Successfully handled completion/complete request");
+            serialized("completion/complete", serde_json::to_value(response))
         }
     });
 
-    io_handler.add_method("tools/list", move |_params| {
+    io_handler.add_method_with_meta("tools/list", move |params: Params, meta: ServerMetadata| {
         let server = server_tools.clone();
         debug!("Handling tools/list request");
+        let version = protocol::session_version(&meta);
 
         let tools = server
             .get_tools()
             .iter()
             .map(|tool| tool.def())
+            .filter(|tool| server.is_tool_enabled(&tool.name))
             .collect::<Vec<_>>();
 
         async move {
+            let (tools, next_cursor) = list_page(&tools, params, server.get_page_size())?;
             let response = ListToolsResult {
-                next_cursor: None,
-                tools: tools,
+                next_cursor,
+                tools,
                 meta: None,
             };
 
             info!("Successfully handled tools/list request");
-            Ok(serde_json::to_value(response).unwrap_or_default())
+            serialized("tools/list", response.to_wire(version))
         }
     });
 
-    io_handler.add_method("tools/call", move |params: Params| {
-        let server = server_call.clone();
-        debug!("Handling tools/call request");
+    let batch_concurrency = server_call.get_batch_concurrency();
+    let published = server_call.get_published_resources();
+    let batch_published = published.clone();
+    let resources = server_call.get_resource_mounts().clone();
+    let batch_resources = resources.clone();
+    let caller = tool_caller(server_call, validator);
+    let batch_caller = caller.clone();
+    io_handler.add_method_with_meta("tools/call", move |params: Params, meta: ServerMetadata| {
+        let caller = caller.clone();
+        debug!("Handling tools/call request from {}", meta.session_id);
+        let version = protocol::session_version(&meta);
+        let context = tool_context(meta, &caller, &published, &resources);
 
         async move {
             let params: CallToolRequestParams = params.parse().map_err(|e| {
@@ -160,62 +726,485 @@ pub async fn start_server<T: ModelContextProtocolServer>(
                 jsonrpc_core::Error::invalid_params(e.to_string())
             })?;
 
-            // Find the requested tool
-            let tools = server.get_tools();
-            let tool = tools.iter().find(|t| t.def().name == params.name);
+            let result = caller(params.name.clone(), params.arguments, context).await?;
+            info!("Successfully handled tool call for: {}", params.name);
+            result.to_wire(version).map_err(|e| {
+                error!("Failed to serialize tool call result: {}", e);
+                jsonrpc_core::Error::invalid_params(e.to_string())
+            })
+        }
+    });
 
-            match tool {
-                Some(tool) => {
-                    let result = tool.call_boxed(params.arguments).await.map_err(|e| {
-                        error!("Tool execution failed: {}", e);
-                        jsonrpc_core::Error::internal_error()
-                    })?;
+    io_handler.add_method_with_meta(
+        tools::batch::CALL_BATCH_METHOD,
+        move |params: Params, meta: ServerMetadata| {
+            let caller = batch_caller.clone();
+            debug!("Handling tools/call_batch request from {}", meta.session_id);
+            let version = protocol::session_version(&meta);
+            let context = tool_context(meta, &caller, &batch_published, &batch_resources);
 
-                    info!("Successfully handled tool call for: {}", params.name);
-                    Ok(serde_json::to_value(result).map_err(|e| {
-                        error!("Failed to serialize tool call result: {}", e);
-                        jsonrpc_core::Error::invalid_params(e.to_string())
-                    })?)
+            async move {
+                let params: tools::batch::CallBatchParams = params.parse().map_err(|e| {
+                    error!("Failed to parse tools/call_batch parameters: {}", e);
+                    jsonrpc_core::Error::invalid_params(e.to_string())
+                })?;
+                let calls = params.calls.len();
+                let result =
+                    tools::batch::call_batch(&caller, context, params, batch_concurrency, version)
+                        .await?;
+                info!("Successfully handled a batch of {} tool calls", calls);
+                Ok(result)
+            }
+        },
+    );
+
+    // Messages from clients wait here until they're dispatched
+    let (tx, mut rx) = mpsc::channel(queues.inbound_capacity());
+    // Every message to a client goes through this channel: responses,
+    // notifications and requests the server makes of the client
+    let (outgoing_tx, mut outgoing_rx) =
+        mpsc::channel::<TransportMessage>(queues.outbound_capacity());
+    let request_router = RequestRouter::new(outgoing_tx.clone());
+    let roots = RootsState::default();
+    let in_flight = dispatch::InFlight::default();
+
+    // Spawn the transport reader, whose messages the relay moves into the
+    // inbound queue as the overflow policy allows
+    let (transport_tx, transport_rx) = mpsc::channel(1);
+    tokio::spawn(transport::relay(
+        transport_rx,
+        tx,
+        outgoing_tx.clone(),
+        queues.overflow_policy(),
+        metrics.clone(),
+    ));
+    let mut transport_reader = transport.clone();
+    let mut reader = tokio::spawn(async move { transport_reader.start(transport_tx).await });
+    let mut reader_running = true;
+
+    let mut log_rx = logging::subscribe();
+    let mut updates_rx = server_updates.get_resource_mounts().updates();
+    let mut list_changes_rx = server_updates.list_changes();
+    let mut notifications_rx = server_updates.notifications();
+    let mut connection_events_rx = transport.connection_events();
+    let mut requests = JoinSet::new();
+    let mut incoming_open = true;
+    let mut drain_deadline = None;
+
+    // Handle incoming messages and forward outgoing messages, log events,
+    // resource updates and list changes until the transport closes or the server shuts down,
+    // and pending requests finish
+    while incoming_open || !requests.is_empty() {
+        // Drain pending log events first so they are filtered by the level
+        // that was in effect when they were emitted
+        tokio::select! {
+            biased;
+            event = log_rx.recv() => {
+                match event {
+                    Ok(params) => forward_log(&mut transport, &sessions, params).await,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("Dropped {} log events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {}
+                }
+            }
+            update = async {
+                match updates_rx.as_mut() {
+                    Some(updates) => updates.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                match update {
+                    Ok(uri) => forward_resource_update(&mut transport, &sessions, uri).await,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("Dropped {} resource updates", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => updates_rx = None,
+                }
+            }
+            change = async {
+                match list_changes_rx.as_mut() {
+                    Some(changes) => changes.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                match change {
+                    Ok(list) => forward_list_changed(&mut transport, &sessions, list).await,
+                    // Not knowing what was missed, announce every list
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        for list in ListChanged::ALL {
+                            forward_list_changed(&mut transport, &sessions, list).await;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => list_changes_rx = None,
+                }
+            }
+            notification = async {
+                match notifications_rx.as_mut() {
+                    Some(notifications) => notifications.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                match notification {
+                    Ok(notification) => {
+                        forward_to_ready(&mut transport, &sessions, notification.to_string()).await
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Dropped {} notifications", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => notifications_rx = None,
+                }
+            }
+            event = async {
+                match connection_events_rx.as_mut() {
+                    Some(events) => events.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                match event {
+                    Ok(ConnectionEvent::Opened(connection)) => {
+                        debug!("Client connected: {}", connection);
+                    }
+                    // The session is kept, and what's sent to it meanwhile
+                    // waits in the transport for the client to come back
+                    Ok(ConnectionEvent::Suspended(connection)) => {
+                        info!("Suspended session {} until its client reconnects", connection);
+                    }
+                    Ok(ConnectionEvent::Resumed(connection)) => {
+                        info!("Resumed session {}", connection);
+                    }
+                    Ok(ConnectionEvent::Closed(connection)) => {
+                        end_session(
+                            &sessions,
+                            &request_router,
+                            &roots,
+                            server_updates.as_ref(),
+                            &connection,
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Missed {} connection events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => connection_events_rx = None,
+                }
+            }
+            Some(outgoing) = outgoing_rx.recv() => {
+                metrics.record_outbound(outgoing_rx.len());
+                send_outgoing(&mut transport, outgoing).await?;
+            }
+            finished = &mut reader, if reader_running => {
+                reader_running = false;
+                match finished {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => {
+                        error!("Transport error: {}", e);
+                        return Err(ServerError::Transport(e));
+                    }
+                    Err(e) => error!("Transport reader failed: {}", e),
+                }
+            }
+            Some(finished) = requests.join_next(), if !requests.is_empty() => {
+                if let Err(e) = finished {
+                    error!("Request handler failed: {}", e);
+                }
+            }
+            _ = shutdown.triggered(), if drain_deadline.is_none() => {
+                info!("Shutting down with {} requests in flight", requests.len());
+                // Messages that haven't been dispatched yet are dropped
+                incoming_open = false;
+                rx.close();
+                for id in sessions.ids() {
+                    sessions.update(&id, |session| session.state = SessionState::ShuttingDown);
                 }
-                None => {
-                    error!("Unknown tool requested: {}", params.name);
-                    Err(jsonrpc_core::Error::method_not_found())
+                if let Some(health) = &health {
+                    health.set_shutting_down();
                 }
+                drain_deadline = Some(tokio::time::Instant::now() + shutdown.drain_timeout());
+            }
+            _ = async {
+                match drain_deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                warn!(
+                    "Dropping {} requests still running after the drain timeout",
+                    requests.len()
+                );
+                requests.shutdown().await;
+                break;
+            }
+            message = rx.recv(), if incoming_open => {
+                let Some(message) = message else {
+                    incoming_open = false;
+                    continue;
+                };
+                metrics.record_inbound(rx.len());
+
+                let meta = ServerMetadata {
+                    session_id: message.connection.clone(),
+                    sessions: sessions.clone(),
+                    router: request_router.clone(),
+                    roots: roots.clone(),
+                };
+                // Dispatching runs the synchronous part of each handler, so
+                // session state changes in the order messages arrive. The rest
+                // runs concurrently, letting handlers wait on the client.
+                let handling = dispatch::dispatch(&io_handler, &message.message, meta, &in_flight);
+                let outgoing_tx = outgoing_tx.clone();
+                let metrics = metrics.clone();
+                requests.spawn(async move {
+                    if let Some(response) = handling.await {
+                        let response = TransportMessage {
+                            connection: message.connection,
+                            message: response,
+                        };
+                        if outgoing_tx.send(response).await.is_err() {
+                            error!("Failed to queue response");
+                        }
+                        metrics.record_outbound(transport::queue_depth(&outgoing_tx));
+                    }
+                });
             }
         }
-    });
+    }
 
-    let (tx, mut rx) = mpsc::channel(32);
+    // Flush whatever the finished requests left behind
+    while let Ok(outgoing) = outgoing_rx.try_recv() {
+        send_outgoing(&mut transport, outgoing).await?;
+    }
+    transport.flush().await.map_err(ServerError::Send)?;
 
-    // Spawn the transport reader
-    let mut transport_reader = transport.clone();
-    tokio::spawn(async move {
-        if let Err(e) = transport_reader.start(tx).await {
-            error!("Transport error: {}", e);
+    Ok(())
+}
+
+/// The result a handler of `method` answers with, or an internal error if
+/// it couldn't be serialized
+fn serialized(
+    method: &str,
+    result: serde_json::Result<serde_json::Value>,
+) -> Result<serde_json::Value, jsonrpc_core::Error> {
+    result.map_err(|e| {
+        error!("Failed to serialize {} result: {}", method, e);
+        jsonrpc_core::Error::internal_error()
+    })
+}
+
+/// Calls the server's tools by name, as `tools/call` does
+fn tool_caller<T: ModelContextProtocolServer>(
+    server: std::sync::Arc<T>,
+    validator: std::sync::Arc<tools::ArgumentValidator>,
+) -> ToolCaller {
+    std::sync::Arc::new(move |name, arguments, context| {
+        let server = server.clone();
+        let validator = validator.clone();
+        Box::pin(async move {
+            let tools = server.get_tools();
+            let Some(tool) = tools
+                .iter()
+                .find(|t| t.def().name == name)
+                .filter(|_| server.is_tool_enabled(&name))
+            else {
+                error!("Unknown tool requested: {}", name);
+                return Err(tools::unknown_tool(&name));
+            };
+            let limit = server.get_max_arguments_size();
+            let size = tools::arguments_size(arguments.as_ref());
+            if size > limit {
+                error!("Refused {} bytes of arguments for {}", size, name);
+                return Err(tools::arguments_too_large(&name, size, limit));
+            }
+
+            async {
+                // Catch bad arguments before they reach the tool
+                let def = tool.def();
+                validator.validate(&def, arguments.as_ref())?;
+                let policy = server.get_tool_policy(&name);
+                let approvals = server.get_approvals();
+                if let Err(refused) = approvals
+                    .check(&def, arguments.as_ref(), &context, &policy)
+                    .await
+                {
+                    return Ok(refused);
+                }
+                let result = policy::call_with_policy(
+                    tool.as_ref(),
+                    arguments,
+                    context,
+                    server.get_tool_timeout(),
+                    policy,
+                )
+                .await?;
+                // Don't hand the client output its schema says it can't get
+                validator.validate_output(&def, &result)?;
+                Ok::<_, tools::ToolError>(result)
+            }
+            .await
+            .or_else(|e| {
+                error!("Tool execution failed: {}", e);
+                e.into_response(&name)
+            })
+        })
+    })
+}
+
+/// Context of a tool called by the client `meta` belongs to
+fn tool_context(
+    meta: ServerMetadata,
+    caller: &ToolCaller,
+    published: &Option<MemoryResources>,
+    resources: &ResourceMounts,
+) -> ToolContext {
+    let context = ToolContext::new(meta.session_id, meta.sessions, meta.router, meta.roots)
+        .with_caller(caller.clone())
+        .with_resources(resources.clone());
+    match published {
+        Some(published) => context.with_published_resources(published.clone()),
+        None => context,
+    }
+}
+
+/// Returns the page of `items` a list request's cursor points at
+fn list_page<T: Clone>(
+    items: &[T],
+    params: Params,
+    page_size: usize,
+) -> jsonrpc_core::Result<(Vec<T>, Option<String>)> {
+    let cursor = pagination::cursor(params).map_err(|e| {
+        error!("Failed to parse list parameters: {}", e);
+        jsonrpc_core::Error::invalid_params(e.to_string())
+    })?;
+    pagination::paginate(items, cursor.as_deref(), page_size).map_err(|e| {
+        error!("Invalid cursor: {}", e);
+        jsonrpc_core::Error::invalid_params(format!("Invalid cursor: {}", e))
+    })
+}
+
+async fn send_outgoing(
+    transport: &mut TransportType,
+    outgoing: TransportMessage,
+) -> Result<(), ServerError> {
+    transport
+        .send_response(outgoing.connection, outgoing.message)
+        .await
+        .map_err(|e| {
+            error!("Failed to send response: {}", e);
+            ServerError::Send(e)
+        })
+}
+
+/// Forgets a client that disconnected, failing the requests the server is waiting on it for
+fn end_session<T: ModelContextProtocolServer>(
+    sessions: &Sessions,
+    router: &RequestRouter,
+    roots: &RootsState,
+    server: &T,
+    connection: &ConnectionId,
+) {
+    router.cancel(connection);
+    roots.remove(connection);
+    let Some(session) = sessions.remove(connection) else {
+        return;
+    };
+    info!("Ended session {}", connection);
+
+    // Stop watching resources nobody else subscribed to
+    let mounts = server.get_resource_mounts();
+    for uri in session.subscriptions {
+        if !sessions.subscribers(&uri).is_empty() {
+            continue;
         }
+        if let Some(provider) = mounts.provider(&uri) {
+            if let Err(e) = provider.unsubscribe(&uri) {
+                error!("Failed to unwatch resource {}: {}", uri, e);
+            }
+        }
+    }
+}
+
+/// Sends a log event to every session whose requested level it meets
+async fn forward_log(
+    transport: &mut TransportType,
+    sessions: &Sessions,
+    params: LoggingMessageNotificationParams,
+) {
+    let connections = sessions.ids().into_iter().filter(|id| {
+        sessions
+            .get(id)
+            .and_then(|session| session.log_level)
+            .is_some_and(|threshold| logging::is_enabled(&params.level, &threshold))
     });
 
-    // Handle incoming messages
-    while let Some(request) = rx.recv().await {
-        let response = io_handler
-            .handle_request(&request, ServerMetadata::default())
+    let notification = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/logging/message",
+        "params": params,
+    })
+    .to_string();
+
+    for connection in connections {
+        if let Err(e) = transport
+            .send_response(connection, notification.clone())
             .await
-            .unwrap_or_else(|| {
-                if !request.contains(r#""method":"notifications/"#) && 
-                   !request.contains(r#""method":"cancelled"#) {
-                    error!("Error handling request");
-                    return r#"{"jsonrpc": "2.0", "error": {"code": -32603, "message": "Internal error"}, "id": null}"#.to_string();
-                }
-                String::new()
-            });
+        {
+            debug!("Failed to forward log message: {}", e);
+        }
+    }
+}
 
-        if !response.is_empty() {
-            if let Err(e) = transport.send_response(response).await {
-                error!("Failed to send response: {}", e);
-                return Err(e).context("Failed to send response");
-            }
+/// Tells every initialized client that a list changed
+async fn forward_list_changed(
+    transport: &mut TransportType,
+    sessions: &Sessions,
+    list: ListChanged,
+) {
+    let notification = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": list.method(),
+    })
+    .to_string();
+    forward_to_ready(transport, sessions, notification).await;
+}
+
+/// Sends `notification` to every initialized client
+async fn forward_to_ready(
+    transport: &mut TransportType,
+    sessions: &Sessions,
+    notification: String,
+) {
+    for connection in sessions.ids() {
+        let ready = sessions
+            .get(&connection)
+            .is_some_and(|session| session.state == SessionState::Ready);
+        if !ready {
+            continue;
+        }
+        if let Err(e) = transport
+            .send_response(connection, notification.clone())
+            .await
+        {
+            debug!("Failed to forward notification: {}", e);
         }
     }
+}
 
-    Ok(())
+/// Notifies every session subscribed to `uri` that the resource changed
+async fn forward_resource_update(transport: &mut TransportType, sessions: &Sessions, uri: String) {
+    let connections = sessions.subscribers(&uri);
+
+    let notification = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/resources/updated",
+        "params": ResourceUpdatedNotificationParams { uri },
+    })
+    .to_string();
+
+    for connection in connections {
+        if let Err(e) = transport
+            .send_response(connection, notification.clone())
+            .await
+        {
+            debug!("Failed to forward resource update: {}", e);
+        }
+    }
 }
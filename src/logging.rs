@@ -0,0 +1,182 @@
+use crate::schema::{LoggingLevel, LoggingMessageNotificationParams};
+use crate::secrets::redact;
+use lazy_static::lazy_static;
+use serde_json::{Map, Value};
+use std::fmt;
+use tokio::sync::broadcast;
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+mod setup;
+
+pub use setup::{
+    init, parse_filter, JsonFormat, LogFilter, LogFormat, LogOptions, LogRotation,
+    SizeRotatingFile, DEFAULT_FILTER,
+};
+
+/// Targets that are never forwarded: sending a notification logs through the
+/// first two, and clients already know how their own requests went
+const EXCLUDED_TARGETS: &[&str] = &[
+    "bioma_tool::transport",
+    "bioma_tool::logging",
+    "bioma_tool::trace",
+];
+
+// Log events captured by `McpLogLayer`, waiting to be forwarded to clients
+lazy_static! {
+    static ref LOG_EVENTS: broadcast::Sender<LoggingMessageNotificationParams> =
+        broadcast::channel(256).0;
+}
+
+/// Subscribes to the log events captured by [`McpLogLayer`]
+pub fn subscribe() -> broadcast::Receiver<LoggingMessageNotificationParams> {
+    LOG_EVENTS.subscribe()
+}
+
+/// Severity rank of a level, following RFC 5424 (higher is more severe)
+pub fn severity(level: &LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+/// Whether a message at `level` passes a client's requested `threshold`
+pub fn is_enabled(level: &LoggingLevel, threshold: &LoggingLevel) -> bool {
+    severity(level) >= severity(threshold)
+}
+
+/// Maps a tracing level onto the closest MCP logging level
+pub fn from_tracing(level: &Level) -> LoggingLevel {
+    match *level {
+        Level::ERROR => LoggingLevel::Error,
+        Level::WARN => LoggingLevel::Warning,
+        Level::INFO => LoggingLevel::Info,
+        Level::DEBUG | Level::TRACE => LoggingLevel::Debug,
+    }
+}
+
+/// Tracing layer that forwards log events to clients as
+/// `notifications/logging/message`.
+///
+/// Events are only delivered to sessions that asked for them with
+/// `logging/setLevel`. `TRACE` events are never forwarded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct McpLogLayer;
+
+impl<S: Subscriber> Layer<S> for McpLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() == Level::TRACE
+            || EXCLUDED_TARGETS
+                .iter()
+                .any(|target| metadata.target().starts_with(target))
+            || LOG_EVENTS.receiver_count() == 0
+        {
+            return;
+        }
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        let _ = LOG_EVENTS.send(LoggingMessageNotificationParams {
+            data: visitor.into_data(),
+            level: from_tracing(metadata.level()),
+            logger: Some(metadata.target().to_string()),
+        });
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    fields: Map<String, Value>,
+}
+
+impl FieldVisitor {
+    /// A plain message becomes a string, anything richer an object of fields
+    fn into_data(mut self) -> Value {
+        if self.fields.len() == 1 {
+            if let Some(message) = self.fields.remove("message") {
+                return message;
+            }
+        }
+        Value::Object(self.fields)
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{:?}", value);
+        self.fields
+            .insert(field.name().to_string(), redact(&value).into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields
+            .insert(field.name().to_string(), redact(value).into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_level_threshold() {
+        assert!(is_enabled(&LoggingLevel::Error, &LoggingLevel::Warning));
+        assert!(is_enabled(&LoggingLevel::Warning, &LoggingLevel::Warning));
+        assert!(!is_enabled(&LoggingLevel::Info, &LoggingLevel::Warning));
+        assert!(is_enabled(&LoggingLevel::Emergency, &LoggingLevel::Debug));
+    }
+
+    #[test]
+    fn test_layer_captures_events() {
+        let mut events = subscribe();
+        let subscriber = tracing_subscriber::registry().with(McpLogLayer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "example", "plain message");
+            tracing::warn!(target: "example", key = 42, "with fields");
+            tracing::debug!(target: "bioma_tool::transport::stdio", "excluded");
+            tracing::trace!(target: "example", "too verbose");
+        });
+
+        let plain = events.try_recv().unwrap();
+        assert_eq!(plain.level, LoggingLevel::Info);
+        assert_eq!(plain.logger.as_deref(), Some("example"));
+        assert_eq!(plain.data, Value::from("plain message"));
+
+        let fields = events.try_recv().unwrap();
+        assert_eq!(fields.level, LoggingLevel::Warning);
+        assert_eq!(fields.data["key"], Value::from(42));
+        assert_eq!(fields.data["message"], Value::from("with fields"));
+
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -0,0 +1,487 @@
+use super::{FieldVisitor, McpLogLayer};
+use crate::error::LogError;
+use crate::secrets::Redacting;
+use serde_json::json;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{
+    filter::{EnvFilter, LevelFilter, Targets},
+    fmt::{
+        format::{FmtSpan, Writer},
+        writer::{BoxMakeWriter, MakeWriter},
+        FmtContext, FormatEvent, FormatFields,
+    },
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    reload,
+    util::SubscriberInitExt,
+    Layer, Registry,
+};
+
+/// Filter of the log file and stderr when neither `RUST_LOG` nor the options set one
+pub const DEFAULT_FILTER: &str = "debug";
+
+/// How each log line is written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format: {}", format)),
+        }
+    }
+}
+
+/// When the log file is replaced by a new one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogRotation {
+    #[default]
+    Never,
+    /// Starts a file named after the hour each hour
+    Hourly,
+    /// Starts a file named after the day each day
+    Daily,
+    /// Moves the file aside as `name.1` once it would grow past this many bytes
+    Size(u64),
+}
+
+impl std::str::FromStr for LogRotation {
+    type Err = String;
+
+    /// `never`, `hourly`, `daily` or a size such as `10MB`
+    fn from_str(rotation: &str) -> Result<Self, Self::Err> {
+        match rotation {
+            "never" => return Ok(LogRotation::Never),
+            "hourly" => return Ok(LogRotation::Hourly),
+            "daily" => return Ok(LogRotation::Daily),
+            _ => {}
+        }
+        let upper = rotation.to_ascii_uppercase();
+        let (number, unit) = match upper.find(|c: char| !c.is_ascii_digit()) {
+            Some(i) => upper.split_at(i),
+            None => (upper.as_str(), ""),
+        };
+        let multiplier = match unit.trim() {
+            "" | "B" => 1,
+            "KB" => 1024,
+            "MB" => 1024 * 1024,
+            "GB" => 1024 * 1024 * 1024,
+            _ => return Err(format!("Unknown log rotation: {}", rotation)),
+        };
+        match number.parse::<u64>() {
+            Ok(size) if size > 0 => Ok(LogRotation::Size(size * multiplier)),
+            _ => Err(format!("Unknown log rotation: {}", rotation)),
+        }
+    }
+}
+
+/// Where and how the process logs
+///
+/// Logs go to a file, and to stderr as well when mirrored, never to stdout,
+/// which the stdio transport speaks the protocol on.
+#[derive(Clone, Debug)]
+pub struct LogOptions {
+    path: PathBuf,
+    format: LogFormat,
+    rotation: LogRotation,
+    max_files: Option<usize>,
+    stderr: bool,
+    filter: Option<String>,
+}
+
+impl LogOptions {
+    /// Logs to the file at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: LogFormat::default(),
+            rotation: LogRotation::default(),
+            max_files: None,
+            stderr: false,
+            filter: None,
+        }
+    }
+
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: LogRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Keeps at most `max_files` rotated files, all of them if unset
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = Some(max_files);
+        self
+    }
+
+    /// Also writes every line to stderr
+    pub fn with_stderr(mut self, stderr: bool) -> Self {
+        self.stderr = stderr;
+        self
+    }
+
+    /// Filters with `directives` in `RUST_LOG` syntax, such as `info,bioma_tool::transport=debug`,
+    /// which `RUST_LOG` itself overrides
+    pub fn with_filter(mut self, directives: impl Into<String>) -> Self {
+        self.filter = Some(directives.into());
+        self
+    }
+}
+
+/// Filter of the log file and stderr, which can be replaced while the process runs
+#[derive(Clone, Debug)]
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Directives in effect at startup
+    initial: String,
+}
+
+impl LogFilter {
+    /// Filters with `directives`, or with the filter of startup if `None`
+    pub fn set(&self, directives: Option<&str>) -> Result<(), LogError> {
+        let filter = parse_filter(directives.unwrap_or(&self.initial))?;
+        self.handle.reload(filter).map_err(LogError::Reload)
+    }
+}
+
+/// Parses filter directives in `RUST_LOG` syntax
+///
+/// Unlike `RUST_LOG`, a directive has to name a level, so a misspelt level
+/// isn't mistaken for a module.
+pub fn parse_filter(directives: &str) -> Result<EnvFilter, LogError> {
+    for directive in directives.split(',').map(str::trim) {
+        if !directive.is_empty() && !directive.contains('=') {
+            directive
+                .parse::<LevelFilter>()
+                .map_err(|_| LogError::UnknownLevel(directive.to_string()))?;
+        }
+    }
+    EnvFilter::builder()
+        .parse(directives)
+        .map_err(|source| LogError::InvalidFilter {
+            directives: directives.to_string(),
+            source,
+        })
+}
+
+/// Installs the process's logging, returning the filter to change later
+///
+/// This crate's own events are also forwarded to the clients that asked
+/// for them, whatever the filter.
+pub fn init(options: LogOptions) -> Result<LogFilter, LogError> {
+    let initial = match std::env::var("RUST_LOG") {
+        Ok(directives) if !directives.is_empty() => directives,
+        _ => options
+            .filter
+            .clone()
+            .unwrap_or_else(|| DEFAULT_FILTER.to_string()),
+    };
+    let (filter, handle) = reload::Layer::new(parse_filter(&initial)?);
+
+    let mut layers = vec![format_layer(
+        options.format,
+        BoxMakeWriter::new(Redacting::new(file_writer(&options)?)),
+    )];
+    if options.stderr {
+        layers.push(format_layer(
+            options.format,
+            BoxMakeWriter::new(Redacting::new(io::stderr)),
+        ));
+    }
+
+    // Forward this crate's own log events to clients that request them
+    let client_layer =
+        McpLogLayer.with_filter(Targets::new().with_target("bioma_tool", Level::DEBUG));
+
+    tracing_subscriber::registry()
+        .with(layers.with_filter(filter))
+        .with(client_layer)
+        .try_init()
+        .map_err(LogError::AlreadySetUp)?;
+    Ok(LogFilter { handle, initial })
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn format_layer(format: LogFormat, writer: BoxMakeWriter) -> BoxedLayer {
+    let layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(writer);
+    match format {
+        LogFormat::Text => layer
+            .with_timer(tracing_subscriber::fmt::time::UtcTime::rfc_3339())
+            .with_level(true)
+            .with_target(true)
+            .with_thread_ids(true)
+            .with_file(true)
+            .with_line_number(true)
+            .boxed(),
+        LogFormat::Json => layer.event_format(JsonFormat).boxed(),
+    }
+}
+
+/// Writer of the log file, rotated as the options say
+fn file_writer(options: &LogOptions) -> Result<BoxMakeWriter, LogError> {
+    let directory = match options.path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(directory).map_err(LogError::open(directory))?;
+    let file_name = options
+        .path
+        .file_name()
+        .ok_or_else(|| LogError::InvalidPath(options.path.clone()))?;
+
+    let rotation = match options.rotation {
+        LogRotation::Never => Rotation::NEVER,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Size(max_size) => {
+            let file = SizeRotatingFile::new(&options.path, max_size, options.max_files)?;
+            return Ok(BoxMakeWriter::new(file));
+        }
+    };
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(file_name.to_string_lossy());
+    if let Some(max_files) = options.max_files {
+        builder = builder.max_log_files(max_files.max(1));
+    }
+    let appender = builder
+        .build(directory)
+        .map_err(LogError::open(&options.path))?;
+    Ok(BoxMakeWriter::new(appender))
+}
+
+/// A log file moved aside once it would grow past a size
+///
+/// The file before is renamed to `name.1`, the one before that to `name.2`
+/// and so on, up to the most files kept.
+#[derive(Clone, Debug)]
+pub struct SizeRotatingFile {
+    state: Arc<Mutex<SizeRotatingState>>,
+}
+
+#[derive(Debug)]
+struct SizeRotatingState {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: Option<usize>,
+}
+
+impl SizeRotatingFile {
+    pub fn new(path: &Path, max_size: u64, max_files: Option<usize>) -> Result<Self, LogError> {
+        let file = open(path).map_err(LogError::open(path))?;
+        let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        Ok(Self {
+            state: Arc::new(Mutex::new(SizeRotatingState {
+                path: path.to_path_buf(),
+                file,
+                size,
+                max_size,
+                max_files,
+            })),
+        })
+    }
+}
+
+impl SizeRotatingState {
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = |n: usize| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        match self.max_files {
+            Some(0) => {}
+            Some(max_files) => {
+                for n in (1..max_files).rev() {
+                    let from = rotated(n);
+                    if from.exists() {
+                        std::fs::rename(from, rotated(n + 1))?;
+                    }
+                }
+                std::fs::rename(&self.path, rotated(1))?;
+            }
+            None => {
+                let mut n = 1;
+                while rotated(n).exists() {
+                    n += 1;
+                }
+                for n in (1..n).rev() {
+                    std::fs::rename(rotated(n), rotated(n + 1))?;
+                }
+                std::fs::rename(&self.path, rotated(1))?;
+            }
+        }
+        // With no files to keep, the file starts over instead
+        self.file = match self.max_files {
+            Some(0) => File::create(&self.path)?,
+            _ => open(&self.path)?,
+        };
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl Write for SizeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.size > 0 && state.size + buf.len() as u64 > state.max_size {
+            state.rotate()?;
+        }
+        state.file.write_all(buf)?;
+        state.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .file
+            .flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for SizeRotatingFile {
+    type Writer = SizeRotatingFile;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Writes each event as a JSON object on a line of its own
+///
+/// Fields are redacted as they are for clients, and the names of the spans
+/// the event happened in are listed from the outermost.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let spans: Vec<&str> = ctx
+            .event_scope()
+            .map(|scope| scope.from_root().map(|span| span.name()).collect())
+            .unwrap_or_default();
+        let line = json!({
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+            "level": metadata.level().as_str(),
+            "target": metadata.target(),
+            "file": metadata.file(),
+            "line": metadata.line(),
+            "thread": format!("{:?}", std::thread::current().id()),
+            "spans": spans,
+            "fields": visitor.fields,
+        });
+        writeln!(writer, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("bioma-logs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert!("xml".parse::<LogFormat>().is_err());
+        assert_eq!("daily".parse(), Ok(LogRotation::Daily));
+        assert_eq!("10MB".parse(), Ok(LogRotation::Size(10 * 1024 * 1024)));
+        assert_eq!("512 kb".parse(), Ok(LogRotation::Size(512 * 1024)));
+        assert_eq!("4096".parse(), Ok(LogRotation::Size(4096)));
+        assert!("0".parse::<LogRotation>().is_err());
+        assert!("weekly".parse::<LogRotation>().is_err());
+
+        assert!(parse_filter("info,bioma_tool::transport=debug").is_ok());
+        assert!(parse_filter("warn").is_ok());
+        assert!(matches!(
+            parse_filter("loud"),
+            Err(LogError::UnknownLevel(level)) if level == "loud"
+        ));
+    }
+
+    #[test]
+    fn test_size_rotation() {
+        let directory = temp_dir();
+        let path = directory.join("server.log");
+        let mut file = SizeRotatingFile::new(&path, 10, Some(2)).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        let read = |name: &str| std::fs::read_to_string(directory.join(name)).unwrap();
+        assert_eq!(read("server.log"), "fourth\n");
+        assert_eq!(read("server.log.1"), "third\n");
+        assert_eq!(read("server.log.2"), "second\n");
+        assert!(!directory.join("server.log.3").exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_json_format() {
+        let directory = temp_dir();
+        let path = directory.join("server.log");
+        let file = SizeRotatingFile::new(&path, u64::MAX, None).unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(format_layer(LogFormat::Json, BoxMakeWriter::new(file)));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request");
+            let _entered = span.enter();
+            tracing::info!(tool = "echo", "Calling a tool");
+        });
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let line: serde_json::Value = serde_json::from_str(text.lines().next().unwrap()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["spans"], json!(["request"]));
+        assert_eq!(line["fields"]["message"], "Calling a tool");
+        assert_eq!(line["fields"]["tool"], "echo");
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
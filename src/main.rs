@@ -1,18 +1,45 @@
 use anyhow::{Context, Result};
+#[cfg(feature = "prompt-templates")]
+use bioma_tool::prompts::DirectoryPromptProvider;
 use bioma_tool::{
-    schema::{
-        Prompt, PromptArgument, Resource, ServerCapabilities, ServerCapabilitiesPrompts,
-        ServerCapabilitiesPromptsResources, ServerCapabilitiesPromptsResourcesTools,
+    aggregator::Aggregator,
+    approval::{self, Approvals, WebhookApprover},
+    bridge::Bridge,
+    config::{self, Config},
+    logging::{self, LogFilter, LogFormat, LogOptions, LogRotation},
+    policy::{Policies, ToolPolicy},
+    rate_limit::{RateLimit, RateLimits},
+    resources::{
+        DirectoryResourceProvider, FileWatcher, HttpResourceProvider, MemoryResources,
+        ResourceTemplateProvider,
     },
-    tools::{self, ToolCallHandler},
-    transport::{StdioTransport, TransportType, WebSocketTransport},
-    ModelContextProtocolServer,
+    schema::{Prompt, PromptArgument, ReadResourceResult, Resource, ResourceTemplate},
+    tools::{
+        command::CommandTool,
+        fetch::{Fetch, FetchLimits},
+        Bundle, DEFAULT_MAX_ARGUMENTS_SIZE,
+    },
+    transport::{
+        AuthConfig, HttpTransport, OriginPolicy, QueueConfig, SseTransport, StdioTransport,
+        TcpTransport, TlsIdentity, TransportType, WebSocketTransport, DEFAULT_MAX_MESSAGE_SIZE,
+        DEFAULT_QUEUE_CAPACITY, DEFAULT_REPLAY_CAPACITY, DEFAULT_RESUME_BACKLOG,
+    },
+    Client, ResourceError, Server, ServerBuilder, ToolSwitches,
 };
-use clap::Parser;
+#[cfg(feature = "tool-search")]
+use bioma_tool::{
+    secrets::SecretsProvider,
+    tools::search::{BraveSearch, Search},
+};
+use clap::{Parser, Subcommand};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Write;
 use std::path::PathBuf;
-use tracing::{info, Level};
-use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_subscriber::fmt::format::FmtSpan;
+use std::pin::Pin;
+use std::time::Duration;
+use tracing::{error, info};
+use url::Url;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -21,122 +48,720 @@ struct Args {
     #[arg(long, default_value = "mcp_server.log")]
     log_file: PathBuf,
 
-    /// Transport type (stdio or websocket)
+    /// Format of the log lines (text or json)
+    #[arg(long, default_value = "text")]
+    log_format: String,
+
+    /// When the log file is rotated (never, hourly, daily or a size such as 10MB)
+    #[arg(long, default_value = "never")]
+    log_rotation: String,
+
+    /// Most rotated log files kept (all if unset)
+    #[arg(long)]
+    log_max_files: Option<usize>,
+
+    /// Also write the logs to stderr, which stays clear of the stdio transport
+    #[arg(long)]
+    log_stderr: bool,
+
+    /// Log filter in RUST_LOG syntax, such as info,bioma_tool::transport=debug (RUST_LOG overrides it)
+    #[arg(long)]
+    log_filter: Option<String>,
+
+    /// TOML file with the log level, tools and fetch limits, reloaded when it changes
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Transport type (stdio, websocket, sse, http or tcp)
     #[arg(long, default_value = "stdio")]
     transport: String,
 
+    /// How messages are delimited on stdio (auto, newline or content-length)
+    #[arg(long, default_value = "auto")]
+    stdio_framing: String,
+
     /// WebSocket address (only used with websocket transport)
     #[arg(long, default_value = "127.0.0.1:8080")]
     ws_addr: String,
+
+    /// Seconds between keepalive pings to WebSocket clients (disabled if unset)
+    #[arg(long)]
+    ws_ping_interval: Option<u64>,
+
+    /// Seconds to wait for a keepalive reply before dropping a WebSocket client
+    #[arg(long, default_value = "10")]
+    ws_ping_timeout: u64,
+
+    /// Seconds a WebSocket client may stay silent before it is dropped (disabled if unset)
+    #[arg(long)]
+    ws_idle_timeout: Option<u64>,
+
+    /// Seconds a dropped WebSocket client has to reconnect with its session token and resume (disabled if unset)
+    #[arg(long)]
+    ws_resume_timeout: Option<u64>,
+
+    /// Send WebSocket messages longer than this many bytes as several frames
+    #[arg(long)]
+    ws_fragment_size: Option<usize>,
+
+    /// Most messages kept for a WebSocket client while it's away
+    #[arg(long, default_value_t = DEFAULT_RESUME_BACKLOG)]
+    ws_resume_backlog: usize,
+
+    /// TCP address, serving one JSON message per line (only used with tcp transport)
+    #[arg(long, default_value = "127.0.0.1:8110")]
+    tcp_addr: String,
+
+    /// PEM certificate chain, enabling TLS on the tcp transport
+    #[arg(long, requires = "tcp_tls_key")]
+    tcp_tls_cert: Option<PathBuf>,
+
+    /// PEM PKCS#8 private key of the TLS certificate
+    #[arg(long, requires = "tcp_tls_cert")]
+    tcp_tls_key: Option<PathBuf>,
+
+    /// Let TCP clients asking for it exchange length-prefixed CBOR instead of JSON lines
+    #[arg(long)]
+    tcp_cbor: bool,
+
+    /// SSE address (only used with sse transport)
+    #[arg(long, default_value = "127.0.0.1:8090")]
+    sse_addr: String,
+
+    /// Streamable HTTP address (only used with http transport)
+    #[arg(long, default_value = "127.0.0.1:8100")]
+    http_addr: String,
+
+    /// Streamable HTTP endpoint path (only used with http transport)
+    #[arg(long, default_value = "/mcp")]
+    http_path: String,
+
+    /// Disable session IDs on the http transport
+    #[arg(long)]
+    http_stateless: bool,
+
+    /// Messages kept per session for clients reopening their event stream with Last-Event-ID (sse and http transports, 0 disables)
+    #[arg(long, default_value_t = DEFAULT_REPLAY_CAPACITY)]
+    replay_capacity: usize,
+
+    /// Canonical URL of this server, enabling OAuth on the sse and http transports
+    #[arg(long, requires_all = ["auth_issuer", "auth_jwks_uri"])]
+    auth_resource: Option<Url>,
+
+    /// Authorization server that issues accepted tokens
+    #[arg(long)]
+    auth_issuer: Option<String>,
+
+    /// Where the authorization server publishes its signing keys
+    #[arg(long)]
+    auth_jwks_uri: Option<Url>,
+
+    /// Scope every token must grant (can be repeated)
+    #[arg(long = "auth-scope")]
+    auth_scopes: Vec<String>,
+
+    /// Web origin allowed to connect besides localhost (can be repeated)
+    #[arg(long = "allowed-origin")]
+    allowed_origins: Vec<String>,
+
+    /// Only allow the origins given with --allowed-origin, not localhost
+    #[arg(long)]
+    strict_origins: bool,
+
+    /// Longest message in bytes a client may send, answered with an error beyond it
+    #[arg(long, default_value_t = DEFAULT_MAX_MESSAGE_SIZE)]
+    max_message_size: usize,
+
+    /// Longest arguments of a tool call in bytes of JSON, refused beyond it
+    #[arg(long, default_value_t = DEFAULT_MAX_ARGUMENTS_SIZE)]
+    max_arguments_size: usize,
+
+    /// Compress messages of at least this many bytes for clients that support it, with
+    /// permessage-deflate on websocket and gzip or Brotli on http
+    #[arg(long)]
+    compress_above: Option<usize>,
+
+    /// Requests each session may send per second
+    #[arg(long)]
+    rate_limit: Option<u32>,
+
+    /// Tool calls each session may make per minute
+    #[arg(long)]
+    tool_call_limit: Option<u32>,
+
+    /// Messages held in each of the inbound and outbound queues
+    #[arg(long, default_value_t = DEFAULT_QUEUE_CAPACITY)]
+    queue_capacity: usize,
+
+    /// What happens to messages arriving at a full inbound queue (wait, reject or drop)
+    #[arg(long, default_value = "wait")]
+    queue_overflow: String,
+
+    /// URL asked to approve calls of destructive tools for clients that can't ask their user
+    #[arg(long)]
+    approval_webhook: Option<Url>,
+
+    /// Seconds a destructive tool call waits for approval before it's refused
+    #[arg(long, default_value_t = approval::DEFAULT_APPROVAL_TIMEOUT.as_secs())]
+    approval_timeout: u64,
+
+    /// File of NAME=value secrets for tools, overridden by environment variables
+    #[arg(long)]
+    secrets_file: Option<PathBuf>,
+
+    /// Search engine used by the search tool (duckduckgo or brave, which needs BRAVE_API_KEY)
+    #[arg(long, default_value = "duckduckgo")]
+    search_engine: String,
+
+    /// Serve another MCP server's tools, resources and prompts under a prefix, as
+    /// `prefix=ws://host:port` or `prefix=command args` (repeatable)
+    #[arg(long)]
+    mount: Vec<String>,
+
+    /// Serve the files under a directory as resources (repeatable)
+    #[arg(long)]
+    resource_dir: Vec<PathBuf>,
+
+    /// Serve an HTTP(S) URL as a resource, fetched when read (repeatable)
+    #[arg(long)]
+    resource_url: Vec<String>,
+
+    /// Serve the Markdown and YAML prompt files in a directory, reloaded when they change (repeatable)
+    #[arg(long)]
+    prompt_dir: Vec<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-struct McpServer {
-    tools: Vec<Box<dyn ToolCallHandler>>,
-    resources: Vec<Resource>,
-    prompts: Vec<Prompt>,
+#[derive(Subcommand)]
+enum Command {
+    /// Connect to an MCP server to list what it offers or call one of its tools
+    Client(ClientArgs),
+    /// Serve a stdio MCP server over the chosen transport, starting it once per client
+    Bridge(BridgeArgs),
 }
 
-impl ModelContextProtocolServer for McpServer {
-    fn new() -> Self {
-        let example_resource = Resource {
-            name: "example.txt".to_string(),
-            uri: "file:///example.txt".to_string(),
-            description: Some("An example text file".to_string()),
-            mime_type: Some("text/plain".to_string()),
-            annotations: None,
+#[derive(clap::Args)]
+struct ClientArgs {
+    /// Server to talk to: `stdio` to start the command given after the options, a ws:// URL
+    /// or a tcp://host:port address
+    #[arg(long)]
+    connect: String,
+
+    /// Encoding of messages to a tcp:// server (json or cbor)
+    #[arg(long, default_value = "json")]
+    encoding: String,
+
+    /// Tool to call instead of listing the server's tools, resources and prompts
+    #[arg(long)]
+    call: Option<String>,
+
+    /// JSON arguments of the tool call
+    #[arg(long, default_value = "{}")]
+    args: String,
+
+    /// Seconds to wait for each response
+    #[arg(long, default_value = "30")]
+    timeout: u64,
+
+    /// Command and arguments starting the server with `--connect stdio`
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+#[derive(clap::Args)]
+struct BridgeArgs {
+    /// Command and arguments starting the server
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+impl Args {
+    fn auth(&self) -> Option<AuthConfig> {
+        let config = AuthConfig::new(
+            self.auth_resource.clone()?,
+            self.auth_issuer.clone()?,
+            self.auth_jwks_uri.clone()?,
+        );
+        Some(config.with_scopes(self.auth_scopes.clone()))
+    }
+
+    fn origins(&self) -> OriginPolicy {
+        let origins = self.allowed_origins.iter().cloned();
+        if self.strict_origins {
+            OriginPolicy::strict(origins)
+        } else {
+            origins.fold(OriginPolicy::localhost(), OriginPolicy::allow)
+        }
+    }
+
+    fn rate_limits(&self) -> RateLimits {
+        let mut limits = RateLimits::new();
+        if let Some(requests) = self.rate_limit {
+            limits = limits.per_session(RateLimit::per_second(requests));
+        }
+        if let Some(calls) = self.tool_call_limit {
+            limits = limits.method("tools/call", RateLimit::per_minute(calls));
+        }
+        limits
+    }
+
+    fn queues(&self) -> Result<QueueConfig> {
+        let overflow = self.queue_overflow.parse().map_err(anyhow::Error::msg)?;
+        Ok(QueueConfig::new()
+            .inbound(self.queue_capacity)
+            .outbound(self.queue_capacity)
+            .overflow(overflow))
+    }
+
+    /// Connects to the servers given with `--mount`, returning the processes started for them
+    async fn mounts(&self) -> Result<(Aggregator, Vec<tokio::process::Child>)> {
+        let mut aggregator = Aggregator::new();
+        let mut children = Vec::new();
+        for mount in &self.mount {
+            let (prefix, target) = mount
+                .split_once('=')
+                .with_context(|| format!("Expected prefix=server to mount, got {}", mount))?;
+            let client = if target.starts_with("ws://") || target.starts_with("wss://") {
+                Client::connect_websocket(target).await?
+            } else {
+                let mut words = target.split_whitespace();
+                let program = words
+                    .next()
+                    .with_context(|| format!("No command to mount as {}", prefix))?;
+                let mut command = tokio::process::Command::new(program);
+                command.args(words);
+                let (client, child) = Client::spawn_process(command)?;
+                children.push(child);
+                client
+            };
+            aggregator = aggregator.mount(prefix, client);
+        }
+        Ok((aggregator, children))
+    }
+
+    /// The transport clients connect to this process through
+    fn transport(&self) -> Result<TransportType> {
+        let transport = match self.transport.as_str() {
+            "stdio" => {
+                let framing = self.stdio_framing.parse().map_err(anyhow::Error::msg)?;
+                TransportType::Stdio(
+                    StdioTransport::new()
+                        .with_framing(framing)
+                        .with_max_message_size(self.max_message_size),
+                )
+            }
+            "websocket" => {
+                let mut transport = WebSocketTransport::new(self.ws_addr.clone())
+                    .with_origins(self.origins())
+                    .with_max_message_size(self.max_message_size);
+                if let Some(timeout) = self.ws_idle_timeout {
+                    transport = transport.with_idle_timeout(Duration::from_secs(timeout));
+                }
+                if let Some(threshold) = self.compress_above {
+                    transport = transport.with_compression(threshold);
+                }
+                if let Some(bytes) = self.ws_fragment_size {
+                    transport = transport.with_fragment_size(bytes);
+                }
+                if let Some(timeout) = self.ws_resume_timeout {
+                    transport = transport
+                        .with_resumption(Duration::from_secs(timeout), self.ws_resume_backlog);
+                }
+                match self.ws_ping_interval {
+                    Some(interval) => TransportType::WebSocket(transport.with_keepalive(
+                        Duration::from_secs(interval),
+                        Duration::from_secs(self.ws_ping_timeout),
+                    )),
+                    None => TransportType::WebSocket(transport),
+                }
+            }
+            "sse" => {
+                let transport = SseTransport::new(self.sse_addr.clone())
+                    .with_origins(self.origins())
+                    .with_replay_capacity(self.replay_capacity)
+                    .with_max_message_size(self.max_message_size);
+                match self.auth() {
+                    Some(auth) => TransportType::Sse(transport.with_auth(auth)),
+                    None => TransportType::Sse(transport),
+                }
+            }
+            "http" => {
+                let mut transport =
+                    HttpTransport::new(self.http_addr.clone(), self.http_path.clone())
+                        .with_origins(self.origins())
+                        .with_replay_capacity(self.replay_capacity)
+                        .with_max_message_size(self.max_message_size);
+                if let Some(auth) = self.auth() {
+                    transport = transport.with_auth(auth);
+                }
+                if let Some(threshold) = self.compress_above {
+                    transport = transport.with_compression(threshold);
+                }
+                if self.http_stateless {
+                    TransportType::Http(transport.stateless())
+                } else {
+                    TransportType::Http(transport)
+                }
+            }
+            "tcp" => {
+                let mut transport = TcpTransport::new(self.tcp_addr.clone())
+                    .with_max_message_size(self.max_message_size);
+                if self.tcp_cbor {
+                    transport = transport.with_cbor();
+                }
+                match self.tls_identity()? {
+                    Some(identity) => TransportType::Tcp(transport.with_tls(identity)?),
+                    None => TransportType::Tcp(transport),
+                }
+            }
+            _ => return Err(anyhow::anyhow!("Invalid transport type")),
         };
+        Ok(transport)
+    }
 
-        let example_prompt = Prompt {
-            name: "greet".to_string(),
-            description: Some("A friendly greeting prompt".to_string()),
-            arguments: Some(vec![PromptArgument {
-                name: "name".to_string(),
-                description: Some("Name of the person to greet".to_string()),
-                required: Some(true),
-            }]),
+    fn approvals(&self) -> Approvals {
+        let approvals =
+            Approvals::default().with_timeout(Duration::from_secs(self.approval_timeout));
+        match &self.approval_webhook {
+            Some(url) => approvals.with_approver(WebhookApprover::new(url.clone())),
+            None => approvals,
+        }
+    }
+
+    fn tls_identity(&self) -> Result<Option<TlsIdentity>> {
+        let (Some(cert), Some(key)) = (&self.tcp_tls_cert, &self.tcp_tls_key) else {
+            return Ok(None);
         };
+        let cert = std::fs::read(cert)
+            .with_context(|| format!("Failed to read TLS certificate {}", cert.display()))?;
+        let key = std::fs::read(key)
+            .with_context(|| format!("Failed to read TLS key {}", key.display()))?;
+        let identity = TlsIdentity::from_pkcs8(&cert, &key).context("Invalid TLS certificate")?;
+        Ok(Some(identity))
+    }
 
-        Self {
-            tools: vec![
-                Box::new(tools::echo::Echo),
-                Box::new(tools::memory::Memory),
-                Box::new(tools::fetch::Fetch::default()),
-            ],
-            resources: vec![example_resource],
-            prompts: vec![example_prompt],
+    #[cfg(feature = "tool-search")]
+    fn secrets(&self) -> Result<SecretsProvider> {
+        let secrets = SecretsProvider::from_env();
+        match &self.secrets_file {
+            Some(path) => Ok(secrets.with_file(path)?),
+            None => Ok(secrets),
         }
     }
 
-    fn get_capabilities(&self) -> ServerCapabilities {
-        ServerCapabilities {
-            tools: Some(ServerCapabilitiesPromptsResourcesTools {
-                list_changed: Some(false),
-            }),
-            resources: Some(ServerCapabilitiesPromptsResources {
-                list_changed: Some(false),
-                subscribe: Some(false),
-            }),
-            prompts: Some(ServerCapabilitiesPrompts {
-                list_changed: Some(false),
-            }),
-            ..Default::default()
+    #[cfg(feature = "tool-search")]
+    fn search(&self, secrets: &SecretsProvider) -> Result<Search> {
+        match self.search_engine.as_str() {
+            "duckduckgo" => Ok(Search::default()),
+            "brave" => Ok(Search::new(BraveSearch::from_secrets(secrets)?)),
+            engine => Err(anyhow::anyhow!("Unknown search engine: {}", engine)),
         }
     }
+}
 
-    fn get_resources(&self) -> &Vec<Resource> {
-        &self.resources
+/// Example templated resource that greets whoever is named in the URI
+struct GreetingTemplate;
+
+impl ResourceTemplateProvider for GreetingTemplate {
+    fn template(&self) -> ResourceTemplate {
+        ResourceTemplate {
+            name: "greeting".to_string(),
+            uri_template: "greeting://{name}".to_string(),
+            description: Some("A greeting for the named person".to_string()),
+            mime_type: Some("text/plain".to_string()),
+            annotations: None,
+        }
     }
 
-    fn get_prompts(&self) -> &Vec<Prompt> {
-        &self.prompts
+    fn read<'a>(
+        &'a self,
+        uri: &'a str,
+        variables: HashMap<String, String>,
+    ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>> {
+        Box::pin(async move {
+            let name = variables.get("name").map(String::as_str).unwrap_or("world");
+            Ok(ReadResourceResult {
+                contents: vec![serde_json::json!({
+                    "uri": uri,
+                    "mimeType": "text/plain",
+                    "text": format!("Hello, {}!", name),
+                })],
+                meta: None,
+            })
+        })
     }
+}
+
+/// Applies each version of the config file to the running server
+struct Reloader {
+    log_filter: LogFilter,
+    tools: ToolSwitches,
+    fetch: Fetch,
+    /// Fetch limits from before any config was applied
+    fetch_defaults: FetchLimits,
+    policies: Policies,
+    /// Tool policies from before any config was applied
+    policy_defaults: HashMap<String, ToolPolicy>,
+}
 
-    fn get_tools(&self) -> &Vec<Box<dyn ToolCallHandler>> {
-        &self.tools
+impl Reloader {
+    fn apply(&self, config: Config) {
+        let level = config.log_level().ok().flatten().map(str::to_string);
+        if let Err(e) = self.log_filter.set(level.as_deref()) {
+            error!("{:#}", e);
+        }
+        self.tools.set_enabled_only(config.enabled_tools);
+        self.tools.set_disabled(config.disabled_tools);
+        self.tools.set_overrides(config.tools);
+        self.fetch
+            .set_limits(config.fetch.apply(&self.fetch_defaults));
+        let mut policies = self.policy_defaults.clone();
+        policies.extend(config.policy);
+        self.policies.set_all(policies);
+        info!(
+            "Applied config with log level {}",
+            level.as_deref().unwrap_or("of startup")
+        );
     }
 }
 
-fn setup_logging(log_path: PathBuf) -> Result<()> {
-    // Create parent directory if it doesn't exist
-    if let Some(parent) = log_path.parent() {
-        std::fs::create_dir_all(parent).context("Failed to create log directory")?;
-    }
-
-    // Create file appender
-    let file_appender = RollingFileAppender::new(
-        Rotation::NEVER,
-        log_path.parent().unwrap_or(&PathBuf::from(".")),
-        log_path.file_name().unwrap_or_default(),
-    );
-
-    // Initialize tracing subscriber with cleaner formatting
-    tracing_subscriber::fmt()
-        .with_timer(tracing_subscriber::fmt::time::UtcTime::rfc_3339())
-        .with_level(true)
-        .with_target(true)
-        .with_thread_ids(true)
-        .with_file(true)
-        .with_line_number(true)
-        .with_ansi(false) // Disable ANSI color codes
-        .with_span_events(FmtSpan::CLOSE)
-        .with_writer(file_appender)
-        .with_max_level(Level::DEBUG)
-        .init();
+/// The example server, offering every built-in tool compiled in
+fn example_server(tools: Bundle) -> ServerBuilder {
+    let example_resource = Resource {
+        name: "example.txt".to_string(),
+        uri: "file:///example.txt".to_string(),
+        description: Some("An example text file".to_string()),
+        mime_type: Some("text/plain".to_string()),
+        annotations: None,
+    };
+
+    let example_prompt = Prompt {
+        name: "greet".to_string(),
+        description: Some("A friendly greeting prompt".to_string()),
+        arguments: Some(vec![PromptArgument {
+            name: "name".to_string(),
+            description: Some("Name of the person to greet".to_string()),
+            required: Some(true),
+        }]),
+    };
+
+    let builder = Server::builder()
+        .tools(tools.into_tools())
+        .memory_resources(MemoryResources::new())
+        .resource(example_resource)
+        .resource_template(GreetingTemplate)
+        .prompt(example_prompt);
 
+    match FileWatcher::new() {
+        Ok(watcher) => builder.resource_watcher(watcher),
+        Err(e) => {
+            error!("Resource subscriptions disabled: {}", e);
+            builder
+        }
+    }
+}
+
+impl Args {
+    fn log_options(&self) -> Result<LogOptions> {
+        let mut options = LogOptions::new(&self.log_file)
+            .with_format(
+                self.log_format
+                    .parse::<LogFormat>()
+                    .map_err(anyhow::Error::msg)?,
+            )
+            .with_rotation(
+                self.log_rotation
+                    .parse::<LogRotation>()
+                    .map_err(anyhow::Error::msg)?,
+            )
+            .with_stderr(self.log_stderr);
+        if let Some(max_files) = self.log_max_files {
+            options = options.with_max_files(max_files);
+        }
+        if let Some(filter) = &self.log_filter {
+            options = options.with_filter(filter);
+        }
+        Ok(options)
+    }
+}
+
+fn main() -> Result<()> {
+    let mut args = Args::parse();
+    let log_filter = logging::init(args.log_options()?)?;
     info!("Logging system initialized");
+
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start the runtime")?;
+    let result = match args.command.take() {
+        Some(Command::Client(client)) => return runtime.block_on(run_client(client)),
+        Some(Command::Bridge(bridge)) => runtime.block_on(run_bridge(&args, bridge)),
+        None => runtime.block_on(run(args, log_filter)),
+    };
+    // Stdin is read on a blocking thread that only returns once a line
+    // arrives, so don't wait for it
+    runtime.shutdown_background();
+    info!("Server stopped");
+    result
+}
+
+async fn run(args: Args, log_filter: LogFilter) -> Result<()> {
+    let config_path = args.config.clone();
+    let rate_limits = args.rate_limits();
+    let queues = args.queues()?;
+    let fetch = Fetch::default();
+    let tools = Bundle::default().with_fetch(fetch.clone());
+    // Fail before listening if a tool is missing its secrets
+    #[cfg(feature = "tool-search")]
+    let tools = tools.with_search(args.search(&args.secrets()?)?);
+    let transport = args.transport()?;
+
+    // Command tools are added before the server is built, so the config
+    // has to load now rather than once the server runs
+    let config = config_path.as_deref().map(Config::load).transpose()?;
+    let (mounts, _children) = args.mounts().await?;
+
+    let mut builder = example_server(tools);
+    for directory in &args.resource_dir {
+        let mut provider = DirectoryResourceProvider::new(directory)?;
+        match FileWatcher::new() {
+            Ok(watcher) => provider = provider.with_watcher(watcher),
+            Err(e) => error!("Subscriptions to {} disabled: {}", directory.display(), e),
+        }
+        builder = builder.mount_resources(provider.root_uri().to_string(), provider);
+    }
+    #[cfg(not(feature = "prompt-templates"))]
+    if !args.prompt_dir.is_empty() {
+        anyhow::bail!("Serving prompt files needs the prompt-templates feature");
+    }
+    #[cfg(feature = "prompt-templates")]
+    for directory in &args.prompt_dir {
+        let provider = DirectoryPromptProvider::new(directory)?;
+        let provider = match provider.watch() {
+            Ok(provider) => provider,
+            Err(e) => {
+                error!(
+                    "Reloading prompts in {} disabled: {}",
+                    directory.display(),
+                    e
+                );
+                DirectoryPromptProvider::new(directory)?
+            }
+        };
+        builder = builder.prompt_provider(provider);
+    }
+    if !args.resource_url.is_empty() {
+        let mut provider = HttpResourceProvider::new(fetch.clone());
+        for url in &args.resource_url {
+            provider = provider.url(url)?;
+        }
+        builder = builder.mount_resources("http", provider);
+    }
+    let command_tools = config.iter().flat_map(|config| &config.command_tools);
+    for (name, command_tool) in command_tools {
+        builder = builder.tool(CommandTool::new(name, command_tool.clone())?);
+    }
+    let server = mounts
+        .add_to(builder)
+        .await?
+        .max_arguments_size(args.max_arguments_size)
+        .rate_limits(rate_limits)
+        .queues(queues)
+        .approvals(args.approvals())
+        .try_build()?;
+    if let (Some(path), Some(config)) = (config_path, config) {
+        let reloader = Reloader {
+            log_filter,
+            tools: server.tool_switches(),
+            fetch_defaults: fetch.limits(),
+            fetch,
+            policies: server.policies(),
+            policy_defaults: server.policies().snapshot(),
+        };
+        reloader.apply(config);
+        tokio::spawn(async move {
+            if let Err(e) = config::watch(&path, |config| reloader.apply(config)).await {
+                error!("Stopped watching the config file: {:#}", e);
+            }
+        });
+    }
+
+    bioma_tool::start_server(server, transport).await?;
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
-    setup_logging(args.log_file)?;
+/// Relays every client of the transport to a server process of its own
+async fn run_bridge(args: &Args, bridge: BridgeArgs) -> Result<()> {
+    let (program, rest) = bridge
+        .command
+        .split_first()
+        .context("The bridge needs the command starting the server")?;
+    Bridge::new(program, rest).serve(args.transport()?).await?;
+    Ok(())
+}
 
-    let transport = match args.transport.as_str() {
-        "stdio" => TransportType::Stdio(StdioTransport::new()),
-        "websocket" => TransportType::WebSocket(WebSocketTransport::new(args.ws_addr)),
-        _ => return Err(anyhow::anyhow!("Invalid transport type")),
+/// Initializes a session with a server and prints what it offers or what a tool call returned
+async fn run_client(args: ClientArgs) -> Result<()> {
+    let (client, _server) = match args.connect.as_str() {
+        "stdio" => {
+            let (program, rest) = args
+                .command
+                .split_first()
+                .context("--connect stdio needs the command starting the server")?;
+            let mut command = tokio::process::Command::new(program);
+            command.args(rest);
+            let (client, child) = Client::spawn_process(command)?;
+            (client, Some(child))
+        }
+        url if url.starts_with("ws://") || url.starts_with("wss://") => {
+            (Client::connect_websocket(url).await?, None)
+        }
+        url if url.starts_with("tcp://") => {
+            let encoding = args.encoding.parse().map_err(anyhow::Error::msg)?;
+            (
+                Client::connect_tcp(&url["tcp://".len()..], encoding).await?,
+                None,
+            )
+        }
+        other => return Err(anyhow::anyhow!("Unknown server to connect to: {}", other)),
     };
+    let client = client.with_timeout(Duration::from_secs(args.timeout));
+    let initialized = client.initialize().await?;
+
+    if let Some(tool) = args.call {
+        let arguments: serde_json::Value =
+            serde_json::from_str(&args.args).context("Tool arguments must be JSON")?;
+        let result = client.call_tool(&tool, arguments).await?;
+        print_json(&result)?;
+        if result.is_error == Some(true) {
+            return Err(anyhow::anyhow!("Tool {} failed", tool));
+        }
+        return Ok(());
+    }
+
+    // Only ask for the lists the server says it has
+    let capabilities = &initialized.capabilities;
+    let mut report = serde_json::json!({
+        "server": initialized.server_info,
+        "protocolVersion": initialized.protocol_version,
+        "instructions": initialized.instructions,
+    });
+    if capabilities.tools.is_some() {
+        report["tools"] = serde_json::to_value(client.list_tools().await?)?;
+    }
+    if capabilities.resources.is_some() {
+        report["resources"] = serde_json::to_value(client.list_resources().await?)?;
+    }
+    if capabilities.prompts.is_some() {
+        report["prompts"] = serde_json::to_value(client.list_prompts().await?)?;
+    }
+    print_json(&report)
+}
 
-    bioma_tool::start_server::<McpServer>(transport).await
+/// Writes `value` to stdout, failing instead of panicking if stdout is closed
+fn print_json(value: &impl serde::Serialize) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, value)?;
+    writeln!(stdout)?;
+    Ok(())
 }
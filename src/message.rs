@@ -0,0 +1,137 @@
+use serde_json::Value;
+
+/// A JSON-RPC message told apart by its structure
+///
+/// Only what routing needs is kept. Requests and notifications are still
+/// dispatched from their raw text, which carries their parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonRpcMessage {
+    /// Expects a response with the same ID
+    Request {
+        id: Value,
+        method: String,
+    },
+    /// Has no ID and never gets a response
+    Notification {
+        method: String,
+    },
+    /// Answers a request the other side sent
+    Response(Value),
+    Batch(Vec<JsonRpcMessage>),
+}
+
+impl JsonRpcMessage {
+    /// Parses a message, failing if it isn't valid JSON-RPC
+    pub fn parse(message: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(message).map_err(|e| e.to_string())?;
+        Self::from_value(value)
+    }
+
+    pub fn from_value(value: Value) -> Result<Self, String> {
+        let object = match value {
+            Value::Array(messages) if !messages.is_empty() => {
+                return messages
+                    .into_iter()
+                    .map(Self::from_value)
+                    .collect::<Result<_, _>>()
+                    .map(JsonRpcMessage::Batch);
+            }
+            Value::Object(object) => object,
+            _ => return Err("Expected a JSON-RPC object or a non-empty batch".to_string()),
+        };
+
+        match (object.get("method"), object.get("id")) {
+            (Some(Value::String(method)), Some(id)) => Ok(JsonRpcMessage::Request {
+                id: id.clone(),
+                method: method.clone(),
+            }),
+            (Some(Value::String(method)), None) => Ok(JsonRpcMessage::Notification {
+                method: method.clone(),
+            }),
+            (Some(_), _) => Err("Method must be a string".to_string()),
+            (None, Some(_)) if object.contains_key("result") || object.contains_key("error") => {
+                Ok(JsonRpcMessage::Response(Value::Object(object)))
+            }
+            (None, _) => Err("Message is neither a request nor a response".to_string()),
+        }
+    }
+
+    /// Whether the receiver must answer this message
+    pub fn expects_response(&self) -> bool {
+        match self {
+            JsonRpcMessage::Request { .. } => true,
+            JsonRpcMessage::Notification { .. } | JsonRpcMessage::Response(_) => false,
+            JsonRpcMessage::Batch(messages) => messages.iter().any(Self::expects_response),
+        }
+    }
+
+    /// The responses in this message
+    pub fn responses(&self) -> Vec<&Value> {
+        match self {
+            JsonRpcMessage::Response(response) => vec![response],
+            JsonRpcMessage::Batch(messages) => messages.iter().flat_map(Self::responses).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether this is a response or a batch of nothing but responses
+    pub fn is_response(&self) -> bool {
+        match self {
+            JsonRpcMessage::Response(_) => true,
+            JsonRpcMessage::Batch(messages) => messages.iter().all(Self::is_response),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_messages() {
+        let request = JsonRpcMessage::parse(
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"text":"\"method\":\"notifications/x\""}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            request,
+            JsonRpcMessage::Request {
+                id: json!(1),
+                method: "tools/call".to_string()
+            }
+        );
+        assert!(request.expects_response());
+
+        let notification =
+            JsonRpcMessage::parse(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+                .unwrap();
+        assert!(!notification.expects_response());
+
+        let response = JsonRpcMessage::parse(r#"{"jsonrpc":"2.0","id":"a","result":{}}"#).unwrap();
+        assert!(response.is_response());
+        assert!(!response.expects_response());
+
+        let batch = JsonRpcMessage::parse(
+            r#"[{"jsonrpc":"2.0","method":"cancelled"},{"jsonrpc":"2.0","id":2,"method":"ping"}]"#,
+        )
+        .unwrap();
+        assert!(batch.expects_response());
+        assert!(!batch.is_response());
+
+        for invalid in [
+            "not json",
+            "[]",
+            "1",
+            r#"{"jsonrpc":"2.0","method":5}"#,
+            "{}",
+        ] {
+            assert!(
+                JsonRpcMessage::parse(invalid).is_err(),
+                "{} is invalid",
+                invalid
+            );
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use crate::transport::ConnectionId;
+use crate::ServerMetadata;
+use jsonrpc_core::futures_util::future::{BoxFuture, Either, FutureExt};
+use jsonrpc_core::{Call, Failure, Id, Output};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::error;
+
+/// What a middleware knows about the message it's handling
+#[derive(Clone, Debug)]
+pub struct MessageContext {
+    pub session_id: ConnectionId,
+    /// Method the client called, empty for messages that aren't valid JSON-RPC
+    pub method: String,
+    /// When the message was dispatched
+    pub received: Instant,
+}
+
+/// Hooks run on every request and response, for concerns such as auth,
+/// logging, metrics and redaction
+///
+/// Added with `ServerBuilder::middleware`, and run in the order they were
+/// added. Each message of a batch is handled on its own.
+pub trait Middleware: Send + Sync + 'static {
+    /// Called with a request or notification from the client before it's dispatched
+    ///
+    /// Changes to `message` are what gets dispatched. Returning an error
+    /// answers a request with it instead, and drops a notification.
+    fn on_request(
+        &self,
+        _context: &MessageContext,
+        _message: &mut Value,
+    ) -> Result<(), jsonrpc_core::Error> {
+        Ok(())
+    }
+
+    /// Called with each response before it's sent to the client, including
+    /// errors returned by `on_request`
+    fn on_response(&self, _context: &MessageContext, _response: &mut Value) {}
+}
+
+/// Runs the middleware of a server around each call
+pub(crate) struct Hooks {
+    middleware: Arc<Vec<Arc<dyn Middleware>>>,
+}
+
+impl Hooks {
+    pub(crate) fn new(middleware: Vec<Arc<dyn Middleware>>) -> Self {
+        Self {
+            middleware: Arc::new(middleware),
+        }
+    }
+}
+
+impl jsonrpc_core::Middleware<ServerMetadata> for Hooks {
+    type Future = jsonrpc_core::middleware::NoopFuture;
+    type CallFuture = BoxFuture<'static, Option<Output>>;
+
+    fn on_call<F, X>(
+        &self,
+        call: Call,
+        meta: ServerMetadata,
+        next: F,
+    ) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, ServerMetadata) -> X + Send + Sync,
+        X: std::future::Future<Output = Option<Output>> + Send + 'static,
+    {
+        if self.middleware.is_empty() {
+            return Either::Right(next(call, meta));
+        }
+        let Ok(mut message) = serde_json::to_value(&call) else {
+            return Either::Right(next(call, meta));
+        };
+        let context = MessageContext {
+            session_id: meta.session_id.clone(),
+            method: match &call {
+                Call::MethodCall(request) => request.method.clone(),
+                Call::Notification(notification) => notification.method.clone(),
+                Call::Invalid { .. } => String::new(),
+            },
+            received: Instant::now(),
+        };
+        let middleware = self.middleware.clone();
+
+        for hook in middleware.iter() {
+            if let Err(error) = hook.on_request(&context, &mut message) {
+                let output = match &call {
+                    Call::MethodCall(request) => Some(Output::Failure(Failure {
+                        jsonrpc: request.jsonrpc,
+                        error,
+                        id: request.id.clone(),
+                    })),
+                    _ => None,
+                };
+                let response = async move { respond(&middleware, &context, output) };
+                return Either::Left(response.boxed());
+            }
+        }
+
+        let call = serde_json::from_value(message).unwrap_or_else(|e| {
+            error!("Middleware made the request invalid: {}", e);
+            Call::Invalid { id: Id::Null }
+        });
+        let response = next(call, meta);
+        Either::Left(
+            async move {
+                let output = response.await;
+                respond(&middleware, &context, output)
+            }
+            .boxed(),
+        )
+    }
+}
+
+/// Runs the `on_response` hooks on the response to a call, if it has one
+fn respond(
+    middleware: &[Arc<dyn Middleware>],
+    context: &MessageContext,
+    output: Option<Output>,
+) -> Option<Output> {
+    let output = output?;
+    let Ok(mut response) = serde_json::to_value(&output) else {
+        return Some(output);
+    };
+    for hook in middleware {
+        hook.on_response(context, &mut response);
+    }
+    match serde_json::from_value(response) {
+        Ok(response) => Some(response),
+        Err(e) => {
+            error!(
+                "Middleware made the response invalid, sending it unchanged: {}",
+                e
+            );
+            Some(output)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roots::RootsState;
+    use crate::router::RequestRouter;
+    use crate::session::Sessions;
+    use jsonrpc_core::{ErrorCode, MetaIoHandler, Params};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Hides secrets from tools and refuses to let anyone delete anything
+    struct Redact;
+
+    impl Middleware for Redact {
+        fn on_request(
+            &self,
+            context: &MessageContext,
+            message: &mut Value,
+        ) -> Result<(), jsonrpc_core::Error> {
+            if context.method == "delete" {
+                return Err(jsonrpc_core::Error::new(ErrorCode::InvalidRequest));
+            }
+            if let Some(secret) = message.pointer_mut("/params/secret") {
+                *secret = json!("[redacted]");
+            }
+            Ok(())
+        }
+
+        fn on_response(&self, _context: &MessageContext, response: &mut Value) {
+            if let Some(result) = response.get_mut("result").and_then(Value::as_object_mut) {
+                result.insert("redacted".to_string(), json!(true));
+            }
+        }
+    }
+
+    /// Records the methods it saw responses for
+    #[derive(Default)]
+    struct Record(Mutex<Vec<String>>);
+
+    impl Middleware for Arc<Record> {
+        fn on_response(&self, context: &MessageContext, _response: &mut Value) {
+            self.0.lock().unwrap().push(context.method.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_hooks() {
+        let record = Arc::new(Record::default());
+        let mut io_handler = MetaIoHandler::with_middleware(Hooks::new(vec![
+            Arc::new(Redact),
+            Arc::new(record.clone()),
+        ]));
+        io_handler.add_method("echo", |params: Params| async move {
+            let params: Value = params.parse()?;
+            Ok(json!({ "params": params }))
+        });
+        io_handler.add_method("delete", |_params| async { Ok(json!({})) });
+
+        let (outgoing_tx, _outgoing_rx) = tokio::sync::mpsc::channel(1);
+        let meta = ServerMetadata {
+            session_id: ConnectionId::from("client"),
+            sessions: Sessions::default(),
+            router: RequestRouter::new(outgoing_tx),
+            roots: RootsState::default(),
+        };
+        let request = json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "echo", "params": { "secret": "hunter2" } },
+            { "jsonrpc": "2.0", "id": 2, "method": "delete" },
+        ]);
+        let response = io_handler
+            .handle_request(&request.to_string(), meta)
+            .await
+            .unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+
+        assert_eq!(response[0]["result"]["params"]["secret"], "[redacted]");
+        assert_eq!(response[0]["result"]["redacted"], true);
+        assert_eq!(response[1]["error"]["code"], -32600);
+        let mut methods = record.0.lock().unwrap().clone();
+        methods.sort();
+        assert_eq!(methods, ["delete", "echo"]);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::error::PaginationError;
+use crate::schema::PaginatedRequestParams;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonrpc_core::Params;
+
+/// Number of items returned per page of a list request by default
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Prefix that keeps cursors opaque to clients while letting the server reject foreign ones
+const CURSOR_PREFIX: &str = "offset:";
+
+/// Encodes the offset of the next page as an opaque cursor
+pub fn encode_cursor(offset: usize) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}{}", CURSOR_PREFIX, offset))
+}
+
+/// Decodes a cursor created by [`encode_cursor`] back into an offset
+pub fn decode_cursor(cursor: &str) -> Result<usize, PaginationError> {
+    let decoded = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(|_| PaginationError::InvalidCursor("Cursor is not valid base64"))?;
+    let decoded = String::from_utf8(decoded)
+        .map_err(|_| PaginationError::InvalidCursor("Cursor is not valid UTF-8"))?;
+    decoded
+        .strip_prefix(CURSOR_PREFIX)
+        .ok_or(PaginationError::InvalidCursor("Unknown cursor format"))?
+        .parse()
+        .map_err(|_| PaginationError::InvalidCursor("Cursor offset is not a number"))
+}
+
+/// Reads the `cursor` of a paginated list request, which may have no params at all
+pub fn cursor(params: Params) -> Result<Option<String>, PaginationError> {
+    match params {
+        Params::None => Ok(None),
+        params => {
+            let params: PaginatedRequestParams = params.parse().map_err(PaginationError::Params)?;
+            Ok(params.cursor)
+        }
+    }
+}
+
+/// Returns the page of `items` starting at `cursor`, and the cursor of the page after it
+pub fn paginate<T: Clone>(
+    items: &[T],
+    cursor: Option<&str>,
+    page_size: usize,
+) -> Result<(Vec<T>, Option<String>), PaginationError> {
+    let offset = cursor.map(decode_cursor).transpose()?.unwrap_or(0);
+    if offset > items.len() {
+        return Err(PaginationError::PastEnd);
+    }
+
+    // A page size of zero would never make progress
+    let end = offset.saturating_add(page_size.max(1)).min(items.len());
+    let next_cursor = (end < items.len()).then(|| encode_cursor(end));
+    Ok((items[offset..end].to_vec(), next_cursor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        for offset in [0, 1, 50, 12345] {
+            assert_eq!(decode_cursor(&encode_cursor(offset)).unwrap(), offset);
+        }
+        assert!(decode_cursor("not a cursor!").is_err());
+        assert!(decode_cursor(&URL_SAFE_NO_PAD.encode("42")).is_err());
+    }
+
+    #[test]
+    fn test_paginate() {
+        let items: Vec<u32> = (0..7).collect();
+
+        let (page, next) = paginate(&items, None, 3).unwrap();
+        assert_eq!(page, vec![0, 1, 2]);
+        let (page, next) = paginate(&items, next.as_deref(), 3).unwrap();
+        assert_eq!(page, vec![3, 4, 5]);
+        let (page, next) = paginate(&items, next.as_deref(), 3).unwrap();
+        assert_eq!(page, vec![6]);
+        assert!(next.is_none());
+
+        let (page, next) = paginate(&items, None, 10).unwrap();
+        assert_eq!(page.len(), 7);
+        assert!(next.is_none());
+
+        assert!(paginate(&items, Some(&encode_cursor(8)), 3).is_err());
+    }
+
+    #[test]
+    fn test_cursor_params() {
+        assert_eq!(cursor(Params::None).unwrap(), None);
+
+        let params: Params = serde_json::from_str(r#"{"cursor":"abc"}"#).unwrap();
+        assert_eq!(cursor(params).unwrap().as_deref(), Some("abc"));
+
+        let params: Params = serde_json::from_str("{}").unwrap();
+        assert_eq!(cursor(params).unwrap(), None);
+    }
+}
@@ -0,0 +1,374 @@
+//! Tools shipped as WebAssembly modules, loaded without recompiling the server
+//!
+//! A plugin is a `.wasm` module exporting a small tool ABI, passing UTF-8
+//! JSON through its linear memory:
+//!
+//! - `memory`: the module's memory
+//! - `alloc(len: i32) -> i32`: reserves `len` bytes for the host to write into
+//! - `describe() -> i64`: the tool's definition, `{"name", "description", "inputSchema"}`
+//! - `call(ptr: i32, len: i32) -> i64`: runs the tool on the arguments object
+//!   at `ptr`, returning a `CallToolResult` or `{"error": "message"}`
+//!
+//! Strings returned are packed as `ptr << 32 | len`. What a module may do is
+//! set by its `PluginCapabilities`, which by default is no environment
+//! variables, no files, no network and no output. Capabilities are read from
+//! a TOML file next to the module with the same stem, such as `weather.toml`
+//! for `weather.wasm`:
+//!
+//! ```toml
+//! timeout_secs = 5
+//! max_memory_bytes = 16777216
+//!
+//! [env]
+//! UNITS = "metric"
+//!
+//! [[dirs]]
+//! host = "/srv/weather"
+//! guest = "/data"
+//! ```
+//!
+//! Modules are compiled and sandboxed by a `PluginRuntime`, which enforces
+//! the capabilities. With the `wasm` feature, `WasmtimeRuntime` runs them
+//! under wasmtime and WASI. Each plugin is added under its file stem as
+//! namespace:
+//!
+//! ```ignore
+//! let runtime = Arc::new(WasmtimeRuntime::new()?);
+//! for plugin in plugins::load_dir("./plugins", runtime)? {
+//!     builder = builder.tool_in(plugin.namespace().to_string(), plugin);
+//! }
+//! ```
+
+use crate::error::PluginError;
+use crate::schema::{self, CallToolResult};
+use crate::tools::{ToolCallHandler, ToolContext, ToolError};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "wasm")]
+pub use wasm::WasmtimeRuntime;
+
+/// Compiles plugin modules and runs them in a sandbox
+///
+/// Implementations are trusted to enforce every capability, as
+/// `WasmtimeRuntime` does.
+pub trait PluginRuntime: Send + Sync {
+    /// Instantiates `module` with WASI limited to `capabilities`
+    fn instantiate(
+        &self,
+        module: &[u8],
+        capabilities: &PluginCapabilities,
+    ) -> Result<Box<dyn PluginInstance>, PluginError>;
+}
+
+/// A running plugin module, called through the tool ABI
+pub trait PluginInstance: Send {
+    /// The JSON `describe` returns
+    fn describe(&mut self) -> Result<String, PluginError>;
+
+    /// The JSON `call` returns for the JSON `arguments`
+    fn call(&mut self, arguments: &str) -> Result<String, PluginError>;
+}
+
+/// What the WASI sandbox of a plugin lets it do, nothing by default
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PluginCapabilities {
+    /// Environment variables the plugin sees
+    pub env: BTreeMap<String, String>,
+    /// Host directories the plugin may open
+    pub dirs: Vec<PreopenedDir>,
+    /// Passes the plugin's stdout and stderr on to the server's stderr
+    pub inherit_stdio: bool,
+    /// Most linear memory the plugin may grow to
+    pub max_memory_bytes: Option<u64>,
+    /// How long a call may run before it's stopped
+    pub timeout_secs: Option<u64>,
+}
+
+/// A host directory a plugin sees at `guest`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PreopenedDir {
+    pub host: PathBuf,
+    pub guest: String,
+    /// Whether the plugin may change what's in it
+    #[serde(default)]
+    pub writable: bool,
+}
+
+impl PluginCapabilities {
+    /// Capabilities from the TOML file next to `module`, or none if there isn't one
+    pub fn for_module(module: &Path) -> Result<Self, PluginError> {
+        let path = module.with_extension("toml");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(&path).map_err(PluginError::load(&path))?;
+        toml::from_str(&text).map_err(PluginError::load(path))
+    }
+}
+
+/// A tool implemented by a plugin module
+///
+/// Calls run one at a time on a blocking thread. A call that fails in the
+/// module drops its instance, so the next call starts from a fresh one.
+pub struct PluginTool {
+    namespace: String,
+    def: schema::Tool,
+    sandbox: Arc<Sandbox>,
+}
+
+/// A plugin module and the instance of it calls run in
+struct Sandbox {
+    module: Vec<u8>,
+    capabilities: PluginCapabilities,
+    runtime: Arc<dyn PluginRuntime>,
+    instance: Mutex<Option<Box<dyn PluginInstance>>>,
+}
+
+impl Sandbox {
+    fn call(&self, arguments: &str) -> Result<String, PluginError> {
+        let mut instance = self.instance.lock().unwrap_or_else(|e| e.into_inner());
+        let running = match instance.as_mut() {
+            Some(running) => running,
+            None => instance.insert(self.runtime.instantiate(&self.module, &self.capabilities)?),
+        };
+        let output = running.call(arguments);
+        if output.is_err() {
+            *instance = None;
+        }
+        output
+    }
+}
+
+impl PluginTool {
+    /// Instantiates `module` and asks it to describe its tool
+    pub fn new(
+        namespace: impl Into<String>,
+        module: Vec<u8>,
+        capabilities: PluginCapabilities,
+        runtime: Arc<dyn PluginRuntime>,
+    ) -> Result<Self, PluginError> {
+        let namespace = namespace.into();
+        let mut instance = runtime.instantiate(&module, &capabilities)?;
+        let def: schema::Tool = serde_json::from_str(&instance.describe()?).map_err(|e| {
+            PluginError::failed(format!(
+                "Plugin {} described its tool wrongly: {}",
+                namespace, e
+            ))
+        })?;
+        Ok(Self {
+            namespace,
+            def,
+            sandbox: Arc::new(Sandbox {
+                module,
+                capabilities,
+                runtime,
+                instance: Mutex::new(Some(instance)),
+            }),
+        })
+    }
+
+    /// Name of the plugin, which its tool is namespaced under
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+}
+
+impl ToolCallHandler for PluginTool {
+    fn call_boxed<'a>(
+        &'a self,
+        args: Option<BTreeMap<String, Value>>,
+        _context: ToolContext,
+    ) -> Pin<Box<dyn Future<Output = Result<CallToolResult, ToolError>> + Send + 'a>> {
+        Box::pin(async move {
+            let arguments = Value::Object(args.unwrap_or_default().into_iter().collect());
+            let arguments = serde_json::to_string(&arguments).map_err(ToolError::ArgumentParse)?;
+            let sandbox = self.sandbox.clone();
+            let output = tokio::task::spawn_blocking(move || sandbox.call(&arguments))
+                .await
+                .map_err(|e| ToolError::Execution(format!("Plugin call panicked: {}", e)))?
+                .map_err(|e| ToolError::Execution(format!("Plugin {}: {}", self.namespace, e)))?;
+            parse_output(&output)
+                .map_err(|e| ToolError::Execution(format!("Plugin {} {}", self.namespace, e)))
+        })
+    }
+
+    fn def(&self) -> schema::Tool {
+        self.def.clone()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.sandbox
+            .capabilities
+            .timeout_secs
+            .map(Duration::from_secs)
+    }
+}
+
+/// The result a plugin's `call` returned, or the error it reported
+fn parse_output(output: &str) -> Result<CallToolResult, String> {
+    let value: Value =
+        serde_json::from_str(output).map_err(|e| format!("returned invalid JSON: {}", e))?;
+    if let Some(error) = value.get("error") {
+        let message = error
+            .as_str()
+            .map_or_else(|| error.to_string(), str::to_string);
+        return Ok(CallToolResult::error(message));
+    }
+    serde_json::from_value(value).map_err(|e| format!("returned an invalid result: {}", e))
+}
+
+/// Loads the plugin at `path`, with the capabilities in the TOML file next to it
+pub fn load(path: &Path, runtime: Arc<dyn PluginRuntime>) -> Result<PluginTool, PluginError> {
+    let namespace = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| PluginError::Load {
+            path: path.to_path_buf(),
+            source: "it has no name".into(),
+        })?;
+    let module = std::fs::read(path).map_err(PluginError::load(path))?;
+    let capabilities = PluginCapabilities::for_module(path)?;
+    let plugin = PluginTool::new(namespace, module, capabilities, runtime)?;
+    info!(
+        "Loaded plugin {} as tool {}",
+        path.display(),
+        plugin.def.name
+    );
+    Ok(plugin)
+}
+
+/// Loads every `.wasm` plugin in `directory`, ordered by file name
+///
+/// Plugins that fail to load are skipped with a warning.
+pub fn load_dir(
+    directory: impl AsRef<Path>,
+    runtime: Arc<dyn PluginRuntime>,
+) -> Result<Vec<PluginTool>, PluginError> {
+    let directory = directory.as_ref();
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(directory)
+        .map_err(PluginError::load(directory))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("wasm"))
+        .collect();
+    paths.sort();
+
+    let mut plugins = Vec::new();
+    for path in paths {
+        match load(&path, runtime.clone()) {
+            Ok(plugin) => plugins.push(plugin),
+            Err(e) => warn!("Skipped plugin {}: {}", path.display(), e),
+        }
+    }
+    Ok(plugins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_tool_error, assert_tool_text, TestServer};
+    use crate::Server;
+    use serde_json::json;
+
+    /// Runs "modules" holding the JSON their tool describes itself with, echoing arguments back
+    struct FakeRuntime;
+
+    struct FakeInstance {
+        description: String,
+        env: BTreeMap<String, String>,
+    }
+
+    impl PluginRuntime for FakeRuntime {
+        fn instantiate(
+            &self,
+            module: &[u8],
+            capabilities: &PluginCapabilities,
+        ) -> Result<Box<dyn PluginInstance>, PluginError> {
+            Ok(Box::new(FakeInstance {
+                description: String::from_utf8(module.to_vec())
+                    .map_err(PluginError::instantiate)?,
+                env: capabilities.env.clone(),
+            }))
+        }
+    }
+
+    impl PluginInstance for FakeInstance {
+        fn describe(&mut self) -> Result<String, PluginError> {
+            Ok(self.description.clone())
+        }
+
+        fn call(&mut self, arguments: &str) -> Result<String, PluginError> {
+            let arguments: Value = serde_json::from_str(arguments).map_err(PluginError::failed)?;
+            match arguments["mode"].as_str() {
+                Some("fail") => Ok(json!({ "error": "no forecast" }).to_string()),
+                Some("trap") => Err(PluginError::failed("unreachable executed")),
+                _ => {
+                    let units = self.env.get("UNITS").cloned().unwrap_or_default();
+                    let text = format!(
+                        "{} in {}",
+                        arguments["city"].as_str().unwrap_or_default(),
+                        units
+                    );
+                    Ok(json!({ "content": [{ "type": "text", "text": text }] }).to_string())
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plugins() {
+        let dir = std::env::temp_dir().join(format!("bioma-plugins-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let describe = json!({
+            "name": "forecast",
+            "description": "Forecasts the weather",
+            "inputSchema": { "type": "object", "properties": { "city": { "type": "string" } } }
+        });
+        std::fs::write(dir.join("weather.wasm"), describe.to_string()).unwrap();
+        std::fs::write(
+            dir.join("weather.toml"),
+            "timeout_secs = 5\n[env]\nUNITS = \"metric\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.wasm"), "not a tool").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let plugins = load_dir(&dir, Arc::new(FakeRuntime)).unwrap();
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].namespace(), "weather");
+        assert_eq!(plugins[0].timeout(), Some(Duration::from_secs(5)));
+
+        let mut builder = Server::builder();
+        for plugin in plugins {
+            builder = builder.tool_in(plugin.namespace().to_string(), plugin);
+        }
+        let server = TestServer::start(builder.build()).await;
+        assert_eq!(server.list_tools().await.unwrap()[0].name, "forecast");
+        let forecast = server.call("forecast", json!({ "city": "Lisbon" })).await;
+        assert_tool_text(&forecast, "Lisbon in metric");
+        let failed = server.call("forecast", json!({ "mode": "fail" })).await;
+        assert_eq!(assert_tool_error(&failed), "no forecast");
+        // A trap drops the instance, and the next call gets a new one
+        let trapped = server.call("forecast", json!({ "mode": "trap" })).await;
+        assert!(assert_tool_error(&trapped).contains("unreachable executed"));
+        let forecast = server.call("forecast", json!({ "city": "Porto" })).await;
+        assert_tool_text(&forecast, "Porto in metric");
+        server.shutdown().await;
+
+        assert!(toml::from_str::<PluginCapabilities>("network = true").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
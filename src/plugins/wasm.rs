@@ -0,0 +1,315 @@
+use super::{PluginCapabilities, PluginInstance, PluginRuntime};
+use crate::error::PluginError;
+use std::time::Duration;
+use wasmtime::{
+    Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Trap,
+    TypedFunc, UpdateDeadline,
+};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{DirPerms, FilePerms, WasiCtxBuilder};
+
+/// How often running calls are checked against their timeout
+const TICK: Duration = Duration::from_millis(100);
+
+/// Runs plugin modules under wasmtime, with WASI limited to their capabilities
+///
+/// Modules see no files, environment, network or output but what their
+/// capabilities grant. Their linear memory can't grow past
+/// `max_memory_bytes`, and a call running longer than `timeout_secs` is
+/// interrupted, dropping its instance.
+pub struct WasmtimeRuntime {
+    engine: Engine,
+    linker: Linker<State>,
+}
+
+/// What a plugin's store holds: its WASI context and limits
+struct State {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+impl WasmtimeRuntime {
+    pub fn new() -> Result<Self, PluginError> {
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config).map_err(PluginError::instantiate)?;
+        let mut linker = Linker::new(&engine);
+        preview1::add_to_linker_sync(&mut linker, |state: &mut State| &mut state.wasi)
+            .map_err(PluginError::instantiate)?;
+
+        // Advances the epoch calls are timed by, until the engine is dropped
+        let weak = engine.weak();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(TICK);
+            match weak.upgrade() {
+                Some(engine) => engine.increment_epoch(),
+                None => return,
+            }
+        });
+
+        Ok(Self { engine, linker })
+    }
+}
+
+/// The WASI context granting `capabilities` and nothing else
+fn wasi(capabilities: &PluginCapabilities) -> Result<WasiP1Ctx, PluginError> {
+    let mut builder = WasiCtxBuilder::new();
+    for (key, value) in &capabilities.env {
+        builder.env(key, value);
+    }
+    for dir in &capabilities.dirs {
+        let (dir_perms, file_perms) = if dir.writable {
+            (DirPerms::all(), FilePerms::all())
+        } else {
+            (DirPerms::READ, FilePerms::READ)
+        };
+        builder
+            .preopened_dir(&dir.host, &dir.guest, dir_perms, file_perms)
+            .map_err(|e| {
+                PluginError::instantiate(format!("Can't open {}: {}", dir.host.display(), e))
+            })?;
+    }
+    // Stdout may be the server's transport, so both go to stderr
+    if capabilities.inherit_stdio {
+        builder
+            .stdout(wasmtime_wasi::stderr())
+            .stderr(wasmtime_wasi::stderr());
+    }
+    Ok(builder.build_p1())
+}
+
+/// Epoch ticks a call may run for within `timeout`, at least one
+fn ticks(timeout: Duration) -> u64 {
+    (timeout.as_millis() / TICK.as_millis()).max(1) as u64
+}
+
+impl PluginRuntime for WasmtimeRuntime {
+    fn instantiate(
+        &self,
+        module: &[u8],
+        capabilities: &PluginCapabilities,
+    ) -> Result<Box<dyn PluginInstance>, PluginError> {
+        let module = Module::new(&self.engine, module).map_err(PluginError::instantiate)?;
+        let mut limits = StoreLimitsBuilder::new();
+        if let Some(bytes) = capabilities.max_memory_bytes {
+            limits = limits.memory_size(usize::try_from(bytes).unwrap_or(usize::MAX));
+        }
+        let state = State {
+            wasi: wasi(capabilities)?,
+            limits: limits.build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+
+        let timeout = capabilities.timeout_secs.map(Duration::from_secs);
+        match timeout {
+            Some(timeout) => store.set_epoch_deadline(ticks(timeout)),
+            None => {
+                store.set_epoch_deadline(1);
+                store.epoch_deadline_callback(|_| Ok(UpdateDeadline::Continue(1)));
+            }
+        }
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, &module)
+            .map_err(PluginError::instantiate)?;
+        // Reactor modules set themselves up before anything else is called
+        if let Ok(initialize) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+            initialize
+                .call(&mut store, ())
+                .map_err(PluginError::instantiate)?;
+        }
+        Ok(Box::new(WasmInstance::new(store, &instance, timeout)?))
+    }
+}
+
+/// A plugin module instantiated in its own store
+struct WasmInstance {
+    store: Store<State>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    describe: TypedFunc<(), i64>,
+    call: TypedFunc<(i32, i32), i64>,
+    timeout: Option<Duration>,
+}
+
+impl WasmInstance {
+    fn new(
+        mut store: Store<State>,
+        instance: &Instance,
+        timeout: Option<Duration>,
+    ) -> Result<Self, PluginError> {
+        let missing = |export: &str| {
+            PluginError::instantiate(format!("The module doesn't export {}", export))
+        };
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| missing("memory"))?;
+        let alloc = instance
+            .get_typed_func(&mut store, "alloc")
+            .map_err(|_| missing("alloc(i32) -> i32"))?;
+        let describe = instance
+            .get_typed_func(&mut store, "describe")
+            .map_err(|_| missing("describe() -> i64"))?;
+        let call = instance
+            .get_typed_func(&mut store, "call")
+            .map_err(|_| missing("call(i32, i32) -> i64"))?;
+        Ok(Self {
+            store,
+            memory,
+            alloc,
+            describe,
+            call,
+            timeout,
+        })
+    }
+
+    /// Gives the next call the whole timeout
+    fn reset_deadline(&mut self) {
+        if let Some(timeout) = self.timeout {
+            self.store.set_epoch_deadline(ticks(timeout));
+        }
+    }
+
+    /// The error a call that trapped or was interrupted fails with
+    fn failed(&self, error: wasmtime::Error) -> PluginError {
+        match (error.downcast_ref::<Trap>(), self.timeout) {
+            (Some(Trap::Interrupt), Some(timeout)) => PluginError::TimedOut(timeout),
+            _ => PluginError::failed(error),
+        }
+    }
+
+    /// The string packed as `ptr << 32 | len` in `packed`
+    fn string(&self, packed: i64) -> Result<String, PluginError> {
+        let ptr = (packed as u64 >> 32) as usize;
+        let len = (packed as u64 & 0xffff_ffff) as usize;
+        let bytes = self
+            .memory
+            .data(&self.store)
+            .get(ptr..ptr + len)
+            .ok_or_else(|| PluginError::failed("Returned a string outside its memory"))?;
+        String::from_utf8(bytes.to_vec()).map_err(PluginError::failed)
+    }
+}
+
+impl PluginInstance for WasmInstance {
+    fn describe(&mut self) -> Result<String, PluginError> {
+        self.reset_deadline();
+        let packed = self
+            .describe
+            .call(&mut self.store, ())
+            .map_err(|e| self.failed(e))?;
+        self.string(packed)
+    }
+
+    fn call(&mut self, arguments: &str) -> Result<String, PluginError> {
+        self.reset_deadline();
+        let len = i32::try_from(arguments.len())
+            .map_err(|_| PluginError::failed("Arguments too long for the module"))?;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|e| self.failed(e))?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, arguments.as_bytes())
+            .map_err(PluginError::failed)?;
+        let packed = self
+            .call
+            .call(&mut self.store, (ptr, len))
+            .map_err(|e| self.failed(e))?;
+        self.string(packed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::PreopenedDir;
+    use std::path::PathBuf;
+
+    const DESCRIBE: &str =
+        r#"{"name":"echo","description":"Echoes","inputSchema":{"type":"object"}}"#;
+
+    /// A module of `pages` pages of memory whose `call` runs `body`, echoing
+    /// its arguments back unless `body` never returns
+    fn module(pages: u32, body: &str) -> Vec<u8> {
+        format!(
+            r#"(module
+                (memory (export "memory") {pages})
+                (global $next (mut i32) (i32.const 1024))
+                (data (i32.const 0) "{describe}")
+                (func (export "alloc") (param $len i32) (result i32)
+                    (local $ptr i32)
+                    (local.set $ptr (global.get $next))
+                    (global.set $next (i32.add (global.get $next) (local.get $len)))
+                    (local.get $ptr))
+                (func (export "describe") (result i64)
+                    (i64.const {len}))
+                (func (export "call") (param $ptr i32) (param $len i32) (result i64)
+                    {body}
+                    (i64.or
+                        (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                        (i64.extend_i32_u (local.get $len)))))"#,
+            describe = DESCRIBE.replace('"', "\\\""),
+            len = DESCRIBE.len(),
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_wasmtime_runtime() {
+        let runtime = WasmtimeRuntime::new().unwrap();
+        let mut instance = runtime
+            .instantiate(&module(1, ""), &PluginCapabilities::default())
+            .unwrap();
+        assert_eq!(instance.describe().unwrap(), DESCRIBE);
+        assert_eq!(
+            instance.call(r#"{"message":"hi"}"#).unwrap(),
+            r#"{"message":"hi"}"#
+        );
+    }
+
+    #[test]
+    fn test_wasmtime_capabilities() {
+        let runtime = WasmtimeRuntime::new().unwrap();
+
+        // Memory can't grow past the limit, so modules needing more don't start
+        let capped = PluginCapabilities {
+            max_memory_bytes: Some(2 * 65536),
+            ..Default::default()
+        };
+        assert!(runtime.instantiate(&module(2, ""), &capped).is_ok());
+        assert!(matches!(
+            runtime.instantiate(&module(3, ""), &capped),
+            Err(PluginError::Instantiate(_))
+        ));
+        let grow = "(if (i32.ne (memory.grow (i32.const 1)) (i32.const -1)) (then unreachable))";
+        let mut instance = runtime.instantiate(&module(2, grow), &capped).unwrap();
+        assert!(instance.call("{}").is_ok());
+
+        // Calls running past the timeout are stopped
+        let timed = PluginCapabilities {
+            timeout_secs: Some(1),
+            ..Default::default()
+        };
+        let mut instance = runtime
+            .instantiate(&module(1, "(loop $forever (br $forever))"), &timed)
+            .unwrap();
+        assert!(matches!(
+            instance.call("{}"),
+            Err(PluginError::TimedOut(timeout)) if timeout == Duration::from_secs(1)
+        ));
+
+        // Only directories that exist are opened
+        let missing = PluginCapabilities {
+            dirs: vec![PreopenedDir {
+                host: PathBuf::from("/nonexistent/bioma-plugin"),
+                guest: "/data".to_string(),
+                writable: false,
+            }],
+            ..Default::default()
+        };
+        assert!(runtime.instantiate(&module(1, ""), &missing).is_err());
+    }
+}
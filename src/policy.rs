@@ -0,0 +1,343 @@
+use crate::schema::CallToolResult;
+use crate::tools::{self, ToolCallHandler, ToolContext, ToolError};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use url::Url;
+
+/// URL schemes whose arguments count as network access
+const NETWORK_SCHEMES: &[&str] = &["http", "https", "ws", "wss"];
+
+/// What a tool is allowed to do, declared by the operator
+///
+/// Checked against the arguments of each `tools/call` before the tool runs,
+/// and by the built-in tools against every URL and path they touch while
+/// running. The default allows everything.
+///
+/// ```toml
+/// [policy.fetch]
+/// allowed_hosts = ["example.com", "*.wikipedia.org"]
+/// max_runtime_secs = 10
+///
+/// [policy.filesystem]
+/// network = false
+/// filesystem_roots = ["/srv/data"]
+/// require_approval = true
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolPolicy {
+    /// Whether the tool may reach the network at all
+    pub network: bool,
+    /// Hosts the tool may reach, any if unset; `*.example.com` matches its subdomains
+    pub allowed_hosts: Option<Vec<String>>,
+    /// Directories the tool may touch, any if unset
+    pub filesystem_roots: Option<Vec<PathBuf>>,
+    /// Seconds a call may run, when shorter than the tool's own timeout
+    pub max_runtime_secs: Option<u64>,
+    /// Whether calls wait for a human to approve them, by default if the tool is destructive
+    pub require_approval: Option<bool>,
+}
+
+impl Default for ToolPolicy {
+    fn default() -> Self {
+        Self {
+            network: true,
+            allowed_hosts: None,
+            filesystem_roots: None,
+            max_runtime_secs: None,
+            require_approval: None,
+        }
+    }
+}
+
+/// Why a tool call was refused or stopped by its policy
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum PolicyViolation {
+    #[error("Network access is not allowed: {0}")]
+    NetworkDenied(String),
+
+    #[error("Host {0} is not allowed")]
+    HostNotAllowed(String),
+
+    #[error("{} is outside the allowed filesystem roots", .0.display())]
+    PathNotAllowed(PathBuf),
+
+    #[error("Exceeded the maximum runtime of {} seconds", .0.as_secs_f64())]
+    RuntimeExceeded(Duration),
+}
+
+impl PolicyViolation {
+    /// Short machine-readable name of the violation
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PolicyViolation::NetworkDenied(_) => "network_denied",
+            PolicyViolation::HostNotAllowed(_) => "host_not_allowed",
+            PolicyViolation::PathNotAllowed(_) => "path_not_allowed",
+            PolicyViolation::RuntimeExceeded(_) => "runtime_exceeded",
+        }
+    }
+
+    /// A failed result for a call of `tool`, with the details in `_meta.policyViolation`
+    pub fn into_result(self, tool: &str) -> CallToolResult {
+        let message = format!("Policy violation: {}", self);
+        let details = json!({
+            "tool": tool,
+            "kind": self.kind(),
+            "message": self.to_string(),
+        });
+        CallToolResult {
+            meta: Some(BTreeMap::from([("policyViolation".to_string(), details)])),
+            ..CallToolResult::error(message)
+        }
+    }
+}
+
+impl ToolPolicy {
+    pub fn max_runtime(&self) -> Option<Duration> {
+        self.max_runtime_secs.map(Duration::from_secs)
+    }
+
+    /// Whether the tool may send a request to `url`
+    pub fn check_url(&self, url: &Url) -> Result<(), PolicyViolation> {
+        if !self.network {
+            return Err(PolicyViolation::NetworkDenied(url.to_string()));
+        }
+        let Some(allowed) = &self.allowed_hosts else {
+            return Ok(());
+        };
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let matches = |pattern: &String| {
+            let pattern = pattern.to_ascii_lowercase();
+            match pattern.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.ends_with('.')),
+                None => host == pattern,
+            }
+        };
+        if allowed.iter().any(matches) {
+            Ok(())
+        } else {
+            Err(PolicyViolation::HostNotAllowed(host))
+        }
+    }
+
+    /// Whether the tool may reach the network without naming a URL
+    pub fn check_network(&self, target: &str) -> Result<(), PolicyViolation> {
+        if self.network {
+            Ok(())
+        } else {
+            Err(PolicyViolation::NetworkDenied(target.to_string()))
+        }
+    }
+
+    /// Whether the tool may touch `path`, judged after following symlinks
+    pub fn check_path(&self, path: &Path) -> Result<(), PolicyViolation> {
+        let Some(roots) = &self.filesystem_roots else {
+            return Ok(());
+        };
+        let resolved = canonical(path);
+        if roots
+            .iter()
+            .any(|root| resolved.starts_with(canonical(root)))
+        {
+            Ok(())
+        } else {
+            Err(PolicyViolation::PathNotAllowed(path.to_path_buf()))
+        }
+    }
+
+    /// Checks the URLs and absolute paths among the arguments of a call
+    pub fn check_arguments(&self, args: &BTreeMap<String, Value>) -> Result<(), PolicyViolation> {
+        args.values().try_for_each(|value| self.check_value(value))
+    }
+
+    fn check_value(&self, value: &Value) -> Result<(), PolicyViolation> {
+        match value {
+            Value::String(text) => match Url::parse(text) {
+                Ok(url) if NETWORK_SCHEMES.contains(&url.scheme()) => self.check_url(&url),
+                _ if Path::new(text).is_absolute() => self.check_path(Path::new(text)),
+                _ => Ok(()),
+            },
+            Value::Array(values) => values.iter().try_for_each(|value| self.check_value(value)),
+            Value::Object(map) => map.values().try_for_each(|value| self.check_value(value)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `path` with symlinks followed, through its parents for the parts that don't exist yet
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    let Some(parent) = path.parent() else {
+        return path.to_path_buf();
+    };
+    let mut resolved = canonical(parent);
+    match path.components().next_back() {
+        Some(Component::Normal(name)) => resolved.push(name),
+        Some(Component::ParentDir) => {
+            resolved.pop();
+        }
+        _ => {}
+    }
+    resolved
+}
+
+/// The policies of a server's tools, which can be replaced while it runs
+#[derive(Clone, Debug, Default)]
+pub struct Policies {
+    policies: Arc<RwLock<HashMap<String, ToolPolicy>>>,
+}
+
+impl Policies {
+    pub fn new(policies: HashMap<String, ToolPolicy>) -> Self {
+        Self {
+            policies: Arc::new(RwLock::new(policies)),
+        }
+    }
+
+    /// Policy of the tool called `name`, allowing everything if none was set
+    pub fn get(&self, name: &str) -> ToolPolicy {
+        self.policies
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set(&self, name: impl Into<String>, policy: ToolPolicy) {
+        self.write().insert(name.into(), policy);
+    }
+
+    /// Replaces every policy with `policies`
+    pub fn set_all(&self, policies: HashMap<String, ToolPolicy>) {
+        *self.write() = policies;
+    }
+
+    /// Copy of the current policies
+    pub fn snapshot(&self) -> HashMap<String, ToolPolicy> {
+        self.policies
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<String, ToolPolicy>> {
+        self.policies.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Calls a tool under `policy`
+///
+/// Calls whose arguments violate the policy are refused without running the
+/// tool. The tool gets the policy through its context to check what it does
+/// while running, and is stopped once it runs past the policy's maximum.
+pub async fn call_with_policy(
+    tool: &dyn ToolCallHandler,
+    args: Option<BTreeMap<String, Value>>,
+    context: ToolContext,
+    default_timeout: Duration,
+    policy: ToolPolicy,
+) -> Result<CallToolResult, ToolError> {
+    let name = tool.def().name;
+    if let Some(args) = &args {
+        if let Err(violation) = policy.check_arguments(args) {
+            return Ok(violation.into_result(&name));
+        }
+    }
+
+    let max_runtime = policy.max_runtime();
+    let context = context.with_policy(policy);
+    match max_runtime {
+        Some(max_runtime) if max_runtime < tool.timeout().unwrap_or(default_timeout) => {
+            match tokio::time::timeout(max_runtime, tool.call_boxed(args, context)).await {
+                Ok(result) => result,
+                Err(_) => Ok(PolicyViolation::RuntimeExceeded(max_runtime).into_result(&name)),
+            }
+        }
+        _ => tools::call_with_timeout(tool, args, context, default_timeout).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::echo::Echo;
+
+    #[test]
+    fn test_check_policy() {
+        let policy: ToolPolicy = toml::from_str(
+            r#"
+            allowed_hosts = ["example.com", "*.wikipedia.org"]
+            filesystem_roots = ["/srv/data"]
+            "#,
+        )
+        .unwrap();
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert!(policy.check_url(&url("https://example.com/a")).is_ok());
+        assert!(policy.check_url(&url("https://en.wikipedia.org/")).is_ok());
+        assert_eq!(
+            policy.check_url(&url("https://evilwikipedia.org/")),
+            Err(PolicyViolation::HostNotAllowed(
+                "evilwikipedia.org".to_string()
+            ))
+        );
+        assert!(policy.check_path(Path::new("/srv/data/report.txt")).is_ok());
+        assert!(policy.check_path(Path::new("/srv/data/../secret")).is_err());
+
+        let args = |value: Value| serde_json::from_value(value).unwrap();
+        assert!(policy
+            .check_arguments(&args(json!({ "urls": ["https://example.com"], "n": 1 })))
+            .is_ok());
+        let error = policy
+            .check_arguments(&args(json!({ "options": { "path": "/etc/passwd" } })))
+            .unwrap_err();
+        assert_eq!(error.kind(), "path_not_allowed");
+
+        let offline = ToolPolicy {
+            network: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            offline
+                .check_url(&url("https://example.com"))
+                .unwrap_err()
+                .kind(),
+            "network_denied"
+        );
+        assert!(ToolPolicy::default()
+            .check_arguments(&args(
+                json!({ "url": "https://anywhere.org", "path": "/etc" })
+            ))
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_call_with_policy() {
+        let policy = ToolPolicy {
+            allowed_hosts: Some(vec!["example.com".to_string()]),
+            ..Default::default()
+        };
+        let args = BTreeMap::from([("message".to_string(), json!("https://example.org"))]);
+        let result = call_with_policy(
+            &Echo,
+            Some(args),
+            ToolContext::default(),
+            Duration::from_secs(5),
+            policy,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.is_error, Some(true));
+        let violation = &result.meta.unwrap()["policyViolation"];
+        assert_eq!(violation["tool"], "echo");
+        assert_eq!(violation["kind"], "host_not_allowed");
+    }
+}
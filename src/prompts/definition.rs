@@ -0,0 +1,319 @@
+use super::template::{Escape, RenderOptions, Template, TemplateError};
+use super::PromptProvider;
+use crate::error::PromptError;
+use crate::schema::{
+    Content, GetPromptResult, Prompt, PromptArgument, PromptMessage, ResourceContents, Role,
+};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+
+/// A prompt and the messages it expands into
+///
+/// Where a `Prompt` only describes what clients can ask for, a definition
+/// also says what `prompts/get` answers: any number of messages from the
+/// user or the assistant, each text, an image or an embedded resource,
+/// filled in from the arguments. It serves itself as a `PromptProvider`.
+///
+/// ```ignore
+/// let review = PromptDefinition::new("review")
+///     .with_description("Reviews a change")
+///     .with_argument("diff", "The change to review", true)
+///     .with_message(MessageTemplate::text(Role::User, "Review this change:")?)
+///     .with_message(MessageTemplate::resource(Role::User, "diff://current", "{{diff}}")?
+///         .with_mime_type("text/x-diff"))
+///     .with_message(MessageTemplate::text(Role::Assistant, "I'll look for bugs first.")?);
+/// Server::builder().prompt_provider(review)
+/// ```
+#[derive(Clone, Debug)]
+pub struct PromptDefinition {
+    prompt: Prompt,
+    defaults: BTreeMap<String, String>,
+    /// Values arguments may take, suggested by `completion/complete`
+    values: BTreeMap<String, Vec<String>>,
+    messages: Vec<MessageTemplate>,
+    escape: Escape,
+    strict: bool,
+}
+
+/// A message of a prompt, whose text and URIs are templates
+#[derive(Clone, Debug)]
+pub struct MessageTemplate {
+    role: Role,
+    content: ContentTemplate,
+}
+
+#[derive(Clone, Debug)]
+enum ContentTemplate {
+    Text(Template),
+    Image {
+        data: String,
+        mime_type: String,
+    },
+    Resource {
+        uri: Template,
+        text: Template,
+        mime_type: Option<String>,
+    },
+}
+
+impl MessageTemplate {
+    /// A message of the text `template` renders
+    pub fn text(role: Role, template: &str) -> Result<Self, TemplateError> {
+        Ok(Self {
+            role,
+            content: ContentTemplate::Text(Template::parse(template)?),
+        })
+    }
+
+    /// A message of an image, as base64 `data`
+    pub fn image(role: Role, data: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: ContentTemplate::Image {
+                data: data.into(),
+                mime_type: mime_type.into(),
+            },
+        }
+    }
+
+    /// A message embedding the resource at `uri` with the text `text` renders
+    pub fn resource(role: Role, uri: &str, text: &str) -> Result<Self, TemplateError> {
+        Ok(Self {
+            role,
+            content: ContentTemplate::Resource {
+                uri: Template::parse(uri)?,
+                text: Template::parse(text)?,
+                mime_type: None,
+            },
+        })
+    }
+
+    /// Sets the MIME type of an embedded resource
+    pub fn with_mime_type(mut self, mime: impl Into<String>) -> Self {
+        if let ContentTemplate::Resource { mime_type, .. } = &mut self.content {
+            *mime_type = Some(mime.into());
+        }
+        self
+    }
+
+    fn render(
+        &self,
+        context: &serde_json::Value,
+        options: RenderOptions,
+    ) -> Result<PromptMessage, TemplateError> {
+        let content = match &self.content {
+            ContentTemplate::Text(template) => Content::text(template.render(context, options)?),
+            ContentTemplate::Image { data, mime_type } => Content::image(data, mime_type),
+            ContentTemplate::Resource {
+                uri,
+                text,
+                mime_type,
+            } => {
+                let mut resource = ResourceContents::text(
+                    uri.render(context, options)?,
+                    text.render(context, options)?,
+                );
+                if let Some(mime_type) = mime_type {
+                    resource = resource.with_mime_type(mime_type);
+                }
+                Content::resource(resource)
+            }
+        };
+        Ok(PromptMessage::new(self.role.clone(), content))
+    }
+}
+
+impl PromptDefinition {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            prompt: Prompt {
+                arguments: None,
+                description: None,
+                name: name.into(),
+            },
+            defaults: BTreeMap::new(),
+            values: BTreeMap::new(),
+            messages: Vec::new(),
+            escape: Escape::default(),
+            strict: false,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.prompt.description = Some(description.into());
+        self
+    }
+
+    pub fn with_argument(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        required: bool,
+    ) -> Self {
+        self.prompt
+            .arguments
+            .get_or_insert_with(Vec::new)
+            .push(PromptArgument {
+                description: Some(description.into()).filter(|d: &String| !d.is_empty()),
+                name: name.into(),
+                required: Some(required),
+            });
+        self
+    }
+
+    /// Uses `value` for the argument `name` when the client doesn't give one
+    pub fn with_default(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defaults.insert(name.into(), value.into());
+        self
+    }
+
+    /// Declares the values the argument `name` may take, which clients can complete
+    pub fn with_values(
+        mut self,
+        name: impl Into<String>,
+        values: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.values
+            .insert(name.into(), values.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Values declared for the argument `name`
+    pub fn values(&self, name: &str) -> &[String] {
+        self.values.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn with_message(mut self, message: MessageTemplate) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    /// Escapes the values inserted with `{{name}}`
+    pub fn with_escape(mut self, escape: Escape) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Fails on missing required arguments and undefined variables instead of rendering them empty
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// What `prompts/list` shows of the prompt
+    pub fn prompt(&self) -> &Prompt {
+        &self.prompt
+    }
+
+    /// Fills in the messages with `arguments`, with `partials` for `{{> name}}` to include
+    pub fn render(
+        &self,
+        arguments: &BTreeMap<String, String>,
+        partials: &BTreeMap<String, Template>,
+    ) -> Result<GetPromptResult, PromptError> {
+        let mut values = self.defaults.clone();
+        values.extend(arguments.clone());
+        if self.strict {
+            for argument in self.prompt.arguments.iter().flatten() {
+                if argument.required == Some(true) && !values.contains_key(&argument.name) {
+                    return Err(PromptError::MissingArgument(argument.name.clone()));
+                }
+            }
+        }
+
+        let context = serde_json::to_value(values).map_err(PromptError::failed)?;
+        let options = RenderOptions {
+            escape: self.escape,
+            strict: self.strict,
+            partials: Some(partials),
+        };
+        Ok(GetPromptResult {
+            meta: None,
+            description: self.prompt.description.clone(),
+            messages: self
+                .messages
+                .iter()
+                .map(|message| message.render(&context, options))
+                .collect::<Result<_, _>>()
+                .map_err(PromptError::failed)?,
+        })
+    }
+}
+
+impl PromptProvider for PromptDefinition {
+    fn list(&self) -> Pin<Box<dyn Future<Output = Result<Vec<Prompt>, PromptError>> + Send + '_>> {
+        Box::pin(async move { Ok(vec![self.prompt.clone()]) })
+    }
+
+    fn get<'a>(
+        &'a self,
+        name: &'a str,
+        arguments: BTreeMap<String, String>,
+    ) -> Pin<Box<dyn Future<Output = Result<GetPromptResult, PromptError>> + Send + 'a>> {
+        Box::pin(async move {
+            if name != self.prompt.name {
+                return Err(PromptError::NotFound(name.to_string()));
+            }
+            self.render(&arguments, &BTreeMap::new())
+        })
+    }
+
+    fn argument_values(&self, name: &str, argument: &str) -> Vec<String> {
+        if name != self.prompt.name {
+            return Vec::new();
+        }
+        self.values(argument).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_definition_messages() {
+        let review = PromptDefinition::new("review")
+            .with_description("Reviews a change")
+            .with_argument("diff", "The change to review", true)
+            .with_argument("file", "", false)
+            .with_default("file", "main.rs")
+            .with_values("file", ["main.rs", "lib.rs"])
+            .with_message(MessageTemplate::text(Role::User, "Review {{file}}:").unwrap())
+            .with_message(
+                MessageTemplate::resource(Role::User, "diff://{{file}}", "{{diff}}")
+                    .unwrap()
+                    .with_mime_type("text/x-diff"),
+            )
+            .with_message(MessageTemplate::image(Role::User, "iVBORw==", "image/png"))
+            .with_message(
+                MessageTemplate::text(Role::Assistant, "Looking for bugs first.").unwrap(),
+            );
+        assert_eq!(review.prompt().arguments.as_ref().unwrap().len(), 2);
+        assert_eq!(
+            review.argument_values("review", "file"),
+            ["main.rs", "lib.rs"]
+        );
+        assert!(review.argument_values("review", "diff").is_empty());
+
+        let arguments = BTreeMap::from([("diff".to_string(), "+fn main() {}".to_string())]);
+        let result = review.get("review", arguments).await.unwrap();
+        assert_eq!(result.messages.len(), 4);
+        assert_eq!(
+            result.messages[0].content.as_text(),
+            Some("Review main.rs:")
+        );
+        let resource = result.messages[1].content.as_resource().unwrap();
+        assert_eq!(resource.uri(), "diff://main.rs");
+        assert_eq!(resource.as_text(), Some("+fn main() {}"));
+        assert_eq!(resource.mime_type(), Some("text/x-diff"));
+        assert!(matches!(result.messages[2].content, Content::Image { .. }));
+        assert_eq!(result.messages[3].role, Role::Assistant);
+
+        let strict = review.clone().with_strict(true);
+        let missing = strict.get("review", BTreeMap::new()).await.unwrap_err();
+        assert!(matches!(missing, PromptError::MissingArgument(argument) if argument == "diff"));
+        let other = review.get("other", BTreeMap::new()).await.unwrap_err();
+        assert!(matches!(other, PromptError::NotFound(_)));
+    }
+}
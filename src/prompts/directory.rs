@@ -0,0 +1,678 @@
+use super::definition::{MessageTemplate, PromptDefinition};
+use super::template::{Escape, Template};
+use super::PromptProvider;
+use crate::error::PromptError;
+use crate::schema::{GetPromptResult, Prompt, Role};
+use crate::server::ListChanged;
+use crate::tools::fetch::document::image_mime_type;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use tokio::sync::broadcast;
+use tracing::{debug, error, warn};
+
+/// Directory under the prompts holding the partials templates can include
+const PARTIALS: &str = "partials";
+
+/// Why a prompt file was skipped
+type ParseError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Serves the prompt files in a directory
+///
+/// Markdown files (`.md`) hold the prompt's template as their body, with
+/// its name, description and arguments in YAML front matter between `---`
+/// lines, or TOML between `+++` lines. YAML files (`.yaml`, `.yml`) have the
+/// same fields and the template under `template`. The name defaults to the
+/// file's stem.
+///
+/// ```text
+/// ---
+/// description: Reviews a change
+/// arguments:
+///   - name: language
+///     required: true
+///   - name: tone
+///     default: friendly
+///     values: [friendly, blunt]
+/// ---
+/// Review this {{language}} change in a {{tone}} tone.
+/// ```
+///
+/// Templates are Handlebars, see [`Template`]: `{{name}}` is replaced by
+/// the argument's value, or its default when it wasn't given, and
+/// `{{> header}}` by the partial in `partials/header.md`. Front matter can set
+/// `escape` to `html` or `json` to escape the values inserted, and `strict`
+/// to fail on missing required arguments and undefined variables rather
+/// than rendering them empty. An argument's `values` list what it may be,
+/// which clients can complete.
+///
+/// Instead of a single template, `messages` can list what the prompt expands
+/// into, each from the `user` or `assistant` `role`, with `text`, an `image`
+/// file relative to the prompt or a `resource` URI embedded with `text` as
+/// its contents:
+///
+/// ```text
+/// messages:
+///   - text: Here's the screen that fails
+///   - image: screens/login.png
+///   - resource: "log://{{service}}"
+///     mime_type: text/plain
+///     text: "{{log}}"
+///   - role: assistant
+///     text: I'll compare the two.
+/// ```
+///
+/// Files that fail to parse are skipped with a warning.
+pub struct DirectoryPromptProvider {
+    inner: Arc<Inner>,
+    /// Reloads the prompts when the directory changes, for as long as it's kept
+    watcher: Option<Mutex<RecommendedWatcher>>,
+}
+
+struct Inner {
+    root: PathBuf,
+    prompts: RwLock<Vec<PromptDefinition>>,
+    /// Templates in `partials/`, by file stem
+    partials: RwLock<BTreeMap<String, Template>>,
+    /// Changes of the server the prompts are served by
+    changes: OnceLock<broadcast::Sender<ListChanged>>,
+}
+
+impl DirectoryPromptProvider {
+    /// Serves the prompt files in `root`, which must be an existing directory
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, PromptError> {
+        let root = root.as_ref();
+        let root = root.canonicalize().map_err(|e| {
+            PromptError::failed(format!(
+                "Can't serve missing directory {}: {}",
+                root.display(),
+                e
+            ))
+        })?;
+        if !root.is_dir() {
+            return Err(PromptError::failed(format!(
+                "{} isn't a directory",
+                root.display()
+            )));
+        }
+        let prompts = load(&root);
+        let partials = load_partials(&root.join(PARTIALS));
+        Ok(Self {
+            inner: Arc::new(Inner {
+                root,
+                prompts: RwLock::new(prompts),
+                partials: RwLock::new(partials),
+                changes: OnceLock::new(),
+            }),
+            watcher: None,
+        })
+    }
+
+    /// Reloads the prompts whenever a file in the directory or its partials changes
+    pub fn watch(mut self) -> Result<Self, PromptError> {
+        let inner = self.inner.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event)
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) =>
+                {
+                    inner.reload()
+                }
+                Ok(_) => {}
+                Err(e) => error!("Prompt watcher error: {}", e),
+            })
+            .map_err(|e| PromptError::failed(format!("Failed to create prompt watcher: {}", e)))?;
+        watcher
+            .watch(&self.inner.root, RecursiveMode::Recursive)
+            .map_err(|e| {
+                PromptError::failed(format!(
+                    "Failed to watch {}: {}",
+                    self.inner.root.display(),
+                    e
+                ))
+            })?;
+        self.watcher = Some(Mutex::new(watcher));
+        Ok(self)
+    }
+
+    /// Reads the prompt files again, announcing the change if the list differs
+    pub fn reload(&self) {
+        self.inner.reload();
+    }
+}
+
+impl Inner {
+    fn reload(&self) {
+        *self.partials.write().unwrap_or_else(|e| e.into_inner()) =
+            load_partials(&self.root.join(PARTIALS));
+        let prompts = load(&self.root);
+        let changed = {
+            let mut current = self.prompts.write().unwrap_or_else(|e| e.into_inner());
+            let changed = current
+                .iter()
+                .map(PromptDefinition::prompt)
+                .ne(prompts.iter().map(PromptDefinition::prompt));
+            *current = prompts;
+            changed
+        };
+        if changed {
+            debug!("Prompts in {} changed", self.root.display());
+            if let Some(changes) = self.changes.get() {
+                let _ = changes.send(ListChanged::Prompts);
+            }
+        }
+    }
+}
+
+impl PromptProvider for DirectoryPromptProvider {
+    fn list(&self) -> Pin<Box<dyn Future<Output = Result<Vec<Prompt>, PromptError>> + Send + '_>> {
+        let prompts = self
+            .inner
+            .prompts
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|definition| definition.prompt().clone())
+            .collect();
+        Box::pin(async move { Ok(prompts) })
+    }
+
+    fn get<'a>(
+        &'a self,
+        name: &'a str,
+        arguments: BTreeMap<String, String>,
+    ) -> Pin<Box<dyn Future<Output = Result<GetPromptResult, PromptError>> + Send + 'a>> {
+        let partials = self
+            .inner
+            .partials
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        let result = self
+            .inner
+            .prompts
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|definition| definition.prompt().name == name)
+            .ok_or_else(|| PromptError::NotFound(name.to_string()))
+            .and_then(|definition| definition.render(&arguments, &partials));
+        Box::pin(async move { result })
+    }
+
+    fn argument_values(&self, name: &str, argument: &str) -> Vec<String> {
+        self.inner
+            .prompts
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|definition| definition.prompt().name == name)
+            .map_or_else(Vec::new, |definition| definition.values(argument).to_vec())
+    }
+
+    fn announce_on(&self, changes: broadcast::Sender<ListChanged>) {
+        let _ = self.inner.changes.set(changes);
+    }
+}
+
+/// The fields of a prompt file, from its front matter or the YAML file itself
+#[derive(Default, Deserialize)]
+struct PromptSpec {
+    name: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    arguments: Vec<ArgumentSpec>,
+    template: Option<String>,
+    #[serde(default)]
+    messages: Vec<MessageSpec>,
+    #[serde(default)]
+    escape: Escape,
+    #[serde(default)]
+    strict: bool,
+}
+
+#[derive(Deserialize)]
+struct ArgumentSpec {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    required: bool,
+    default: Option<Value>,
+    /// Values the argument may take, which clients can complete
+    #[serde(default)]
+    values: Vec<String>,
+}
+
+/// A message of a prompt file, with one of `text`, `image` or `resource`
+#[derive(Deserialize)]
+struct MessageSpec {
+    #[serde(default = "user")]
+    role: Role,
+    text: Option<String>,
+    /// Path of an image, relative to the prompt file
+    image: Option<PathBuf>,
+    /// URI template of an embedded resource, whose contents are `text`
+    resource: Option<String>,
+    mime_type: Option<String>,
+}
+
+fn user() -> Role {
+    Role::User
+}
+
+impl MessageSpec {
+    fn parse(self, dir: &Path) -> Result<MessageTemplate, ParseError> {
+        match (self.text, self.image, self.resource) {
+            (Some(text), None, None) => Ok(MessageTemplate::text(self.role, text.trim())?),
+            (None, Some(image), None) => {
+                let path = dir.join(image);
+                let bytes = std::fs::read(&path)
+                    .map_err(|e| format!("Failed to read image {}: {}", path.display(), e))?;
+                let mime_type = match self.mime_type {
+                    Some(mime_type) => mime_type,
+                    None => image_mime_type("", &bytes).ok_or_else(|| {
+                        format!("{} isn't a PNG, JPEG, GIF or WebP image", path.display())
+                    })?,
+                };
+                Ok(MessageTemplate::image(
+                    self.role,
+                    STANDARD.encode(bytes),
+                    mime_type,
+                ))
+            }
+            (text, None, Some(uri)) => {
+                let text = text.ok_or_else(|| format!("Resource {} has no text", uri))?;
+                let message = MessageTemplate::resource(self.role, &uri, &text)?;
+                Ok(match self.mime_type {
+                    Some(mime_type) => message.with_mime_type(mime_type),
+                    None => message,
+                })
+            }
+            _ => Err("Each message needs one of text, image or resource".into()),
+        }
+    }
+}
+
+/// Reads the prompt in the file at `path`
+fn parse(path: &Path, text: &str) -> Result<PromptDefinition, ParseError> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let spec = match extension {
+        "yaml" | "yml" => serde_yaml::from_str(text)?,
+        _ => match split_front_matter(text) {
+            Some(("---", front, body)) => PromptSpec {
+                template: Some(body.to_string()),
+                ..serde_yaml::from_str(front)?
+            },
+            Some((_, front, body)) => PromptSpec {
+                template: Some(body.to_string()),
+                ..toml::from_str(front)?
+            },
+            None => PromptSpec {
+                template: Some(text.to_string()),
+                ..Default::default()
+            },
+        },
+    };
+
+    let name = match spec.name {
+        Some(name) => name,
+        None => path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| format!("{} has no name", path.display()))?
+            .to_string(),
+    };
+    let mut definition = PromptDefinition::new(name)
+        .with_escape(spec.escape)
+        .with_strict(spec.strict);
+    if let Some(description) = spec.description {
+        definition = definition.with_description(description);
+    }
+    for argument in spec.arguments {
+        match argument.default {
+            Some(Value::String(default)) => {
+                definition = definition.with_default(&argument.name, default);
+            }
+            Some(Value::Null) | None => {}
+            Some(default) => {
+                definition = definition.with_default(&argument.name, default.to_string());
+            }
+        }
+        if !argument.values.is_empty() {
+            definition = definition.with_values(&argument.name, argument.values);
+        }
+        definition = definition.with_argument(
+            argument.name,
+            argument.description.unwrap_or_default(),
+            argument.required,
+        );
+    }
+
+    // A Markdown body and a list of messages are two ways of writing the same thing
+    let messages = match (spec.template, spec.messages.is_empty()) {
+        (Some(template), true) => vec![MessageTemplate::text(Role::User, template.trim())?],
+        (Some(template), false) if !template.trim().is_empty() => {
+            return Err(format!("{} has both a template and messages", path.display()).into())
+        }
+        (_, false) => {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            spec.messages
+                .into_iter()
+                .map(|message| message.parse(dir))
+                .collect::<Result<_, _>>()?
+        }
+        (None, true) => return Err(format!("{} has no template", path.display()).into()),
+    };
+    Ok(messages
+        .into_iter()
+        .fold(definition, PromptDefinition::with_message))
+}
+
+/// Reads the prompt files in `root`, ordered by file name
+fn load(root: &Path) -> Vec<PromptDefinition> {
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to list prompts in {}: {}", root.display(), e);
+            return Vec::new();
+        }
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_none_or(|name| name.starts_with('.'));
+            let extension = path.extension().and_then(|e| e.to_str());
+            !hidden && path.is_file() && matches!(extension, Some("md" | "yaml" | "yml"))
+        })
+        .collect();
+    paths.sort();
+
+    let mut prompts: Vec<PromptDefinition> = Vec::new();
+    for path in paths {
+        let definition = std::fs::read_to_string(&path)
+            .map_err(ParseError::from)
+            .and_then(|text| parse(&path, &text));
+        match definition {
+            Ok(definition)
+                if prompts
+                    .iter()
+                    .any(|p| p.prompt().name == definition.prompt().name) =>
+            {
+                warn!(
+                    "Skipped {}: there's already a prompt called {}",
+                    path.display(),
+                    definition.prompt().name
+                );
+            }
+            Ok(definition) => prompts.push(definition),
+            Err(e) => warn!("Skipped prompt {}: {}", path.display(), e),
+        }
+    }
+    prompts
+}
+
+/// Reads the partials in `directory`, if there is one
+fn load_partials(directory: &Path) -> BTreeMap<String, Template> {
+    let mut partials = BTreeMap::new();
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return partials;
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path.extension().and_then(|e| e.to_str()) != Some("md") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let template = std::fs::read_to_string(&path)
+            .map_err(ParseError::from)
+            .and_then(|text| Ok(Template::parse(text.trim_end())?));
+        match template {
+            Ok(template) => {
+                partials.insert(name.to_string(), template);
+            }
+            Err(e) => warn!("Skipped partial {}: {}", path.display(), e),
+        }
+    }
+    partials
+}
+
+/// Splits Markdown into its front matter fence, the front matter and the body
+fn split_front_matter(text: &str) -> Option<(&'static str, &str, &str)> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let fence = ["---", "+++"]
+        .into_iter()
+        .find(|fence| text.lines().next().map(str::trim_end) == Some(*fence))?;
+    let start = text.find('\n')? + 1;
+    let mut offset = start;
+    for line in text[start..].split_inclusive('\n') {
+        if line.trim_end() == fence {
+            return Some((fence, &text[start..offset], &text[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::CompletionRef;
+    use crate::schema::Content;
+
+    fn write(dir: &Path, name: &str, text: &str) {
+        std::fs::write(dir.join(name), text).unwrap();
+    }
+
+    fn text(result: &GetPromptResult) -> &str {
+        match &result.messages[0].content {
+            Content::Text { text, .. } => text,
+            other => panic!("expected text, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_directory_prompts() {
+        let dir = std::env::temp_dir().join(format!("bioma-prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("partials")).unwrap();
+        write(&dir, "partials/intro.md", "Hi.\n");
+        write(
+            &dir,
+            "review.md",
+            "---\ndescription: Reviews a change\nstrict: true\narguments:\n  - name: language\n    required: true\n  - name: tone\n    default: friendly\n    values: [friendly, blunt]\n---\n{{> intro}} Review this {{language}} change in a {{ tone }} tone.\n",
+        );
+        write(
+            &dir,
+            "summary.yaml",
+            "name: summarize\narguments:\n- name: words\n  default: 50\n- name: focus\ntemplate: |\n  Summarize in {{words}} words{{#each focus}}, {{this}}{{/each}}.\n",
+        );
+        write(
+            &dir,
+            "plan.md",
+            "+++\ndescription = \"Plans work\"\n+++\nMake a plan.",
+        );
+        write(&dir, "broken.yaml", "name: [\n");
+        write(&dir, "notes.txt", "Not a prompt");
+
+        let provider = DirectoryPromptProvider::new(&dir).unwrap();
+        let prompts = provider.list().await.unwrap();
+        let names: Vec<_> = prompts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["plan", "review", "summarize"]);
+        assert_eq!(prompts[0].description.as_deref(), Some("Plans work"));
+        assert_eq!(prompts[1].arguments.as_ref().unwrap().len(), 2);
+
+        let arguments = BTreeMap::from([("language".to_string(), "Rust".to_string())]);
+        let review = provider.get("review", arguments).await.unwrap();
+        assert_eq!(
+            text(&review),
+            "Hi. Review this Rust change in a friendly tone."
+        );
+        assert_eq!(review.description.as_deref(), Some("Reviews a change"));
+        let summary = provider.get("summarize", BTreeMap::new()).await.unwrap();
+        assert_eq!(text(&summary), "Summarize in 50 words.");
+        let arguments = BTreeMap::from([("focus".to_string(), "risks\nnext steps".to_string())]);
+        let summary = provider.get("summarize", arguments).await.unwrap();
+        assert_eq!(text(&summary), "Summarize in 50 words, risks, next steps.");
+
+        assert_eq!(
+            provider.argument_values("review", "tone"),
+            ["friendly", "blunt"]
+        );
+        assert!(provider.argument_values("review", "language").is_empty());
+
+        let missing = provider.get("review", BTreeMap::new()).await.unwrap_err();
+        assert!(matches!(missing, PromptError::MissingArgument(_)));
+        let unknown = provider.get("other", BTreeMap::new()).await.unwrap_err();
+        assert!(matches!(unknown, PromptError::NotFound(_)));
+
+        // Reloading announces prompts that were added, but not unchanged ones
+        let (changes, mut changed) = broadcast::channel(4);
+        provider.announce_on(changes);
+        provider.reload();
+        assert!(changed.try_recv().is_err());
+        write(&dir, "greet.md", "Hello!");
+        provider.reload();
+        assert_eq!(changed.try_recv().unwrap(), ListChanged::Prompts);
+        assert_eq!(provider.list().await.unwrap().len(), 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_yaml_prompt_files() {
+        let dir = std::env::temp_dir().join(format!("bioma-prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write(
+            &dir,
+            "agent.yaml",
+            "name: 007\ndescription: \"Quoted: kept\" # not part of it\narguments:\n- name: tone\n  values: [plain, \"dry, wry\", 0x1]\ntemplate: Hi\n",
+        );
+
+        let provider = DirectoryPromptProvider::new(&dir).unwrap();
+        let prompts = provider.list().await.unwrap();
+        assert_eq!(prompts[0].name, "007");
+        assert_eq!(prompts[0].description.as_deref(), Some("Quoted: kept"));
+        assert_eq!(
+            provider.argument_values("007", "tone"),
+            ["plain", "dry, wry", "0x1"]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prompt_messages() {
+        let dir = std::env::temp_dir().join(format!("bioma-prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("screens")).unwrap();
+        std::fs::write(dir.join("screens/login.png"), b"\x89PNG\r\n\x1a\nrest").unwrap();
+        write(
+            &dir,
+            "debug.yaml",
+            "arguments:\n- name: service\n- name: log\nmessages:\n  - text: The {{service}} login fails\n  - image: screens/login.png\n  - resource: \"log://{{service}}\"\n    mime_type: text/plain\n    text: \"{{log}}\"\n  - role: assistant\n    text: I'll compare the two.\n",
+        );
+        write(&dir, "both.md", "---\nmessages:\n  - text: One\n---\nTwo\n");
+        write(&dir, "empty.yaml", "messages:\n  - role: user\n");
+
+        let provider = DirectoryPromptProvider::new(&dir).unwrap();
+        let names: Vec<_> = provider
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, ["debug"]);
+
+        let arguments = BTreeMap::from([
+            ("service".to_string(), "auth".to_string()),
+            ("log".to_string(), "401 Unauthorized".to_string()),
+        ]);
+        let debug = provider.get("debug", arguments).await.unwrap();
+        assert_eq!(debug.messages.len(), 4);
+        assert_eq!(text(&debug), "The auth login fails");
+        match &debug.messages[1].content {
+            Content::Image {
+                data, mime_type, ..
+            } => {
+                assert_eq!(mime_type, "image/png");
+                assert_eq!(
+                    STANDARD.decode(data).unwrap(),
+                    b"\x89PNG\r\n\x1a\nrest".to_vec()
+                );
+            }
+            other => panic!("expected an image, got {:?}", other),
+        }
+        let log = debug.messages[2].content.as_resource().unwrap();
+        assert_eq!(log.uri(), "log://auth");
+        assert_eq!(log.as_text(), Some("401 Unauthorized"));
+        assert_eq!(log.mime_type(), Some("text/plain"));
+        assert_eq!(debug.messages[3].role, Role::Assistant);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prompts_served() {
+        let dir = std::env::temp_dir().join(format!("bioma-prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write(
+            &dir,
+            "greet.md",
+            "---\nstrict: true\narguments:\n  - name: name\n    required: true\n    values: [Ada, Grace, Linus]\n---\nHello {{name}}!",
+        );
+        let greet = Prompt {
+            name: "static".to_string(),
+            description: None,
+            arguments: None,
+        };
+        let server = crate::Server::builder()
+            .prompt(greet)
+            .prompt_provider(DirectoryPromptProvider::new(&dir).unwrap())
+            .build();
+        let server = crate::testing::TestServer::start(server).await;
+        assert!(server.initialized.capabilities.prompts.is_some());
+
+        let prompts = server.list_prompts().await.unwrap();
+        let names: Vec<_> = prompts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["static", "greet"]);
+        let arguments = BTreeMap::from([("name".to_string(), "Ada".to_string())]);
+        let greeting = server.get_prompt("greet", arguments).await.unwrap();
+        assert_eq!(text(&greeting), "Hello Ada!");
+        assert!(server.get_prompt("greet", BTreeMap::new()).await.is_err());
+        assert!(server.get_prompt("missing", BTreeMap::new()).await.is_err());
+
+        assert!(server.initialized.capabilities.completions.is_some());
+        let greet = CompletionRef::Prompt {
+            name: "greet".to_string(),
+        };
+        let names = server.complete(greet.clone(), "name", "a").await.unwrap();
+        assert_eq!(names.values, ["Ada", "Grace"]);
+        assert_eq!(names.total, Some(2));
+        let missing = CompletionRef::Prompt {
+            name: "missing".to_string(),
+        };
+        assert!(server.complete(missing, "name", "").await.is_err());
+
+        server.shutdown().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
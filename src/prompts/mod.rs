@@ -0,0 +1,16 @@
+/// Modules containing prompt subsystems
+#[cfg(feature = "prompt-templates")]
+pub mod definition;
+#[cfg(feature = "prompt-templates")]
+pub mod directory;
+pub mod provider;
+#[cfg(feature = "prompt-templates")]
+pub mod template;
+
+#[cfg(feature = "prompt-templates")]
+pub use definition::{MessageTemplate, PromptDefinition};
+#[cfg(feature = "prompt-templates")]
+pub use directory::DirectoryPromptProvider;
+pub use provider::PromptProvider;
+#[cfg(feature = "prompt-templates")]
+pub use template::{Escape, RenderOptions, Template, TemplateError};
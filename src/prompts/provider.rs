@@ -0,0 +1,36 @@
+use crate::error::PromptError;
+use crate::schema::{GetPromptResult, Prompt};
+use crate::server::ListChanged;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use tokio::sync::broadcast;
+
+/// A source of prompts, offered next to the ones added to a server on their own
+///
+/// `prompts/list` lists what every provider offers, and `prompts/get` asks
+/// each in the order they were added until one has the prompt.
+pub trait PromptProvider: Send + Sync {
+    /// Prompts listed by `prompts/list`
+    fn list(&self) -> Pin<Box<dyn Future<Output = Result<Vec<Prompt>, PromptError>> + Send + '_>>;
+
+    /// Fills in the prompt called `name` with `arguments`
+    ///
+    /// Fails with `PromptError::NotFound` if the provider has no such prompt,
+    /// and with `PromptError::MissingArgument` if a required argument wasn't given.
+    fn get<'a>(
+        &'a self,
+        name: &'a str,
+        arguments: BTreeMap<String, String>,
+    ) -> Pin<Box<dyn Future<Output = Result<GetPromptResult, PromptError>> + Send + 'a>>;
+
+    /// Values the argument `argument` of the prompt `name` may take, which
+    /// `completion/complete` suggests; none by default
+    fn argument_values(&self, _name: &str, _argument: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Called once with the changes of the server the provider is added to,
+    /// for providers whose prompts change while it runs to announce them
+    fn announce_on(&self, _changes: broadcast::Sender<ListChanged>) {}
+}
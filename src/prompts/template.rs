@@ -0,0 +1,404 @@
+//! Handlebars templates for prompts, rendered by the handlebars crate
+//!
+//! ```text
+//! {{name}}                       the value of `name`, escaped as configured
+//! {{{name}}}                     the value of `name`, never escaped
+//! {{#if name}}..{{else}}..{{/if}} a section shown when `name` is truthy
+//! {{#unless name}}..{{/unless}}  a section shown when `name` isn't
+//! {{#each items}}..{{/each}}     a section repeated for each item, with
+//!                                `{{this}}`, `{{@index}}`, `{{@first}}` and `{{@last}}`
+//! {{> partial}}                  another template, rendered in place
+//! {{! comment }}                 nothing
+//! \{{                            a literal `{{`
+//! ```
+//!
+//! A `~` just inside the braces, as in `{{~#if name~}}`, removes the
+//! whitespace on that side of the tag.
+//!
+//! Inside `#each`, names are looked up in the item, and `{{../name}}` in the
+//! scope around it. Handlebars' `with`, `lookup`, `eq`, `ne`, `gt`, `gte`,
+//! `lt`, `lte`, `and`, `or`, `not` and `len` helpers work as usual, but `log`
+//! and `raw` aren't helpers, so arguments can have those names.
+//!
+//! Since prompt arguments are always strings, `#if`, `#unless` and `#each`
+//! differ from Handlebars' own. Values are falsy when missing, null, false,
+//! empty, zero, or the strings `"false"` and `"0"`. `#each` goes through
+//! arrays, the values of objects, and strings holding a JSON array or else
+//! one item per line.
+
+use handlebars::{
+    BlockContext, Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext,
+    RenderError, RenderErrorReason, Renderable, StringOutput,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Partials rendered inside partials before giving up, which stops a partial including itself
+const MAX_DEPTH: usize = 16;
+
+/// Errors parsing or rendering a template
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("Template syntax error: {0}")]
+    Syntax(String),
+
+    /// A variable that's not set, in strict mode
+    #[error("Undefined variable in template: {0}")]
+    Undefined(String),
+
+    #[error("Unknown partial: {0}")]
+    UnknownPartial(String),
+
+    #[error("Partials nested more than {MAX_DEPTH} deep")]
+    TooDeep,
+
+    #[error("Failed to render template: {0}")]
+    Render(String),
+}
+
+/// How values are escaped when they're inserted with `{{name}}`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Escape {
+    /// Inserted as they are, the default for prompts of plain text
+    #[default]
+    None,
+    /// Escaped for HTML or XML, so `<`, `>`, `&` and quotes can't start markup
+    Html,
+    /// Escaped for the inside of a JSON string
+    Json,
+}
+
+/// How a template is rendered
+#[derive(Clone, Copy, Default)]
+pub struct RenderOptions<'a> {
+    pub escape: Escape,
+    /// Fails on variables that aren't set instead of rendering them empty
+    pub strict: bool,
+    /// Templates `{{> name}}` can include
+    pub partials: Option<&'a BTreeMap<String, Template>>,
+}
+
+/// A parsed template
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    source: String,
+    compiled: handlebars::Template,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self, TemplateError> {
+        let compiled = handlebars::Template::compile(text)
+            .map_err(|e| TemplateError::Syntax(e.to_string()))?;
+        Ok(Self {
+            source: text.to_string(),
+            compiled,
+        })
+    }
+
+    /// Renders the template with the values of `context`, usually an object
+    pub fn render(&self, context: &Value, options: RenderOptions) -> Result<String, TemplateError> {
+        let mut registry = Handlebars::new();
+        registry.set_strict_mode(options.strict);
+        registry.set_prevent_indent(true);
+        match options.escape {
+            Escape::None => registry.register_escape_fn(handlebars::no_escape),
+            Escape::Html => registry.register_escape_fn(handlebars::html_escape),
+            Escape::Json => registry.register_escape_fn(escape_json),
+        }
+        registry.register_helper("if", Box::new(If { negate: false }));
+        registry.register_helper("unless", Box::new(If { negate: true }));
+        registry.register_helper("each", Box::new(Each));
+        registry.unregister_helper("log");
+        registry.unregister_helper("raw");
+        if let Some(partials) = options.partials {
+            self.check_depth(partials, 0)?;
+            for (name, partial) in partials {
+                registry.register_template(name, partial.compiled.clone());
+            }
+        }
+
+        let context = Context::wraps(context).map_err(render_error)?;
+        let mut output = StringOutput::new();
+        self.compiled
+            .render(
+                &registry,
+                &context,
+                &mut RenderContext::new(None),
+                &mut output,
+            )
+            .map_err(render_error)?;
+        output
+            .into_string()
+            .map_err(|e| TemplateError::Render(e.to_string()))
+    }
+
+    /// Fails if the partials this includes nest more than `MAX_DEPTH` deep,
+    /// which handlebars would follow until the stack overflows
+    fn check_depth(
+        &self,
+        partials: &BTreeMap<String, Template>,
+        depth: usize,
+    ) -> Result<(), TemplateError> {
+        for name in self.includes() {
+            if let Some(partial) = partials.get(name) {
+                if depth >= MAX_DEPTH {
+                    return Err(TemplateError::TooDeep);
+                }
+                partial.check_depth(partials, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the partials included with `{{> name}}`
+    fn includes(&self) -> BTreeSet<&str> {
+        self.source
+            .split("{{")
+            .skip(1)
+            .filter_map(|tag| {
+                let tag = tag.trim_start_matches(['~', '#']).strip_prefix('>')?;
+                tag.split(|c: char| c.is_whitespace() || c == '}' || c == '~')
+                    .find(|name| !name.is_empty())
+            })
+            .collect()
+    }
+}
+
+fn render_error(error: RenderError) -> TemplateError {
+    match error.reason() {
+        RenderErrorReason::MissingVariable(path) => {
+            TemplateError::Undefined(path.clone().unwrap_or_default())
+        }
+        RenderErrorReason::PartialNotFound(name) => TemplateError::UnknownPartial(name.clone()),
+        RenderErrorReason::CannotIncludeSelf => TemplateError::TooDeep,
+        _ => TemplateError::Render(error.to_string()),
+    }
+}
+
+/// `#if`, or `#unless` when `negate`, with truthiness as prompts see it
+struct If {
+    negate: bool,
+}
+
+impl HelperDef for If {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let truthy = h.param(0).is_some_and(|param| is_truthy(param.value()));
+        let branch = if truthy != self.negate {
+            h.template()
+        } else {
+            h.inverse()
+        };
+        match branch {
+            Some(template) => template.render(r, ctx, rc, out),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `#each`, going through the items `items` finds
+struct Each;
+
+impl HelperDef for Each {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let items = h
+            .param(0)
+            .map(|param| items(param.value().clone()))
+            .unwrap_or_default();
+        if items.is_empty() {
+            return match h.inverse() {
+                Some(template) => template.render(r, ctx, rc, out),
+                None => Ok(()),
+            };
+        }
+        let Some(body) = h.template() else {
+            return Ok(());
+        };
+        for (i, item) in items.iter().enumerate() {
+            let mut block = BlockContext::new();
+            block.set_base_value(item.clone());
+            block.set_local_var("index", i.into());
+            block.set_local_var("first", (i == 0).into());
+            block.set_local_var("last", (i + 1 == items.len()).into());
+            rc.push_block(block);
+            let rendered = body.render(r, ctx, rc, out);
+            rc.pop_block();
+            rendered?;
+        }
+        Ok(())
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !matches!(s.as_str(), "" | "false" | "0"),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+/// What `#each` goes through for `value`
+fn items(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(items) => items,
+        Value::Object(map) => map.into_iter().map(|(_, value)| value).collect(),
+        Value::String(text) => match serde_json::from_str(&text) {
+            Ok(Value::Array(items)) => items,
+            _ => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| Value::String(line.to_string()))
+                .collect(),
+        },
+        Value::Null => Vec::new(),
+        other => vec![other],
+    }
+}
+
+fn escape_json(text: &str) -> String {
+    let quoted = Value::String(text.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, context: Value) -> String {
+        Template::parse(template)
+            .unwrap()
+            .render(&context, RenderOptions::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_blocks() {
+        let template =
+            "{{#if urgent}}URGENT: {{/if}}{{title}}{{#unless notes}} (no notes){{/unless}}";
+        assert_eq!(
+            render(template, json!({ "urgent": "true", "title": "Fix" })),
+            "URGENT: Fix (no notes)"
+        );
+        assert_eq!(
+            render(
+                template,
+                json!({ "urgent": "false", "title": "Fix", "notes": "x" })
+            ),
+            "Fix"
+        );
+
+        let list = "{{#each files~}}\n{{@index}}. {{this}}{{#if @last}}.{{else}},{{/if}}{{/each}}";
+        assert_eq!(
+            render(list, json!({ "files": "a.rs\nb.rs\n" })),
+            "0. a.rs,1. b.rs."
+        );
+        assert_eq!(
+            render(
+                "{{#each people}}{{name}} ({{../team}}) {{/each}}{{#each none}}x{{else}}none{{/each}}",
+                json!({ "team": "core", "people": "[{\"name\": \"Ada\"}, {\"name\": \"Lin\"}]" })
+            ),
+            "Ada (core) Lin (core) none"
+        );
+        assert_eq!(
+            render("\\{{literal}} {{! hidden }}{{missing}}", json!({})),
+            "{{literal}} "
+        );
+        assert_eq!(
+            render(
+                "{{log}} {{#if (eq level \"high\")}}!{{/if}}",
+                json!({ "log": "401", "level": "high" })
+            ),
+            "401 !"
+        );
+    }
+
+    #[test]
+    fn test_escaping_and_partials() {
+        let context = json!({ "code": "<b>\"hi\"</b>" });
+        let template = Template::parse("{{code}} {{{code}}}").unwrap();
+        let html = RenderOptions {
+            escape: Escape::Html,
+            ..Default::default()
+        };
+        assert_eq!(
+            template.render(&context, html).unwrap(),
+            "&lt;b&gt;&quot;hi&quot;&lt;/b&gt; <b>\"hi\"</b>"
+        );
+        let json = RenderOptions {
+            escape: Escape::Json,
+            ..Default::default()
+        };
+        assert_eq!(
+            Template::parse("{{code}}")
+                .unwrap()
+                .render(&context, json)
+                .unwrap(),
+            "<b>\\\"hi\\\"</b>"
+        );
+
+        let strict = RenderOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let missing = Template::parse("{{missing}}")
+            .unwrap()
+            .render(&context, strict);
+        assert!(matches!(missing, Err(TemplateError::Undefined(_))));
+
+        let partials = BTreeMap::from([
+            ("sign".to_string(), Template::parse("-- {{who}}").unwrap()),
+            ("loop".to_string(), Template::parse("{{> loop}}").unwrap()),
+            (
+                "ping".to_string(),
+                Template::parse("{{#if a}}{{> pong}}{{/if}}").unwrap(),
+            ),
+            ("pong".to_string(), Template::parse("{{~> ping~}}").unwrap()),
+        ]);
+        let options = RenderOptions {
+            partials: Some(&partials),
+            ..Default::default()
+        };
+        let signed = Template::parse("Thanks\n{{> sign}}").unwrap();
+        assert_eq!(
+            signed.render(&json!({ "who": "Ada" }), options).unwrap(),
+            "Thanks\n-- Ada"
+        );
+        let looped = Template::parse("{{> loop}}")
+            .unwrap()
+            .render(&json!({}), options);
+        assert!(matches!(looped, Err(TemplateError::TooDeep)));
+        let looped = Template::parse("{{> ping}}")
+            .unwrap()
+            .render(&json!({ "a": true }), options);
+        assert!(matches!(looped, Err(TemplateError::TooDeep)));
+        let unknown = Template::parse("{{> other}}")
+            .unwrap()
+            .render(&json!({}), options);
+        assert!(matches!(unknown, Err(TemplateError::UnknownPartial(_))));
+
+        assert!(Template::parse("{{#if a}}open").is_err());
+        assert!(Template::parse("{{#if a}}{{/each}}").is_err());
+        assert!(Template::parse("{{/if}}").is_err());
+        assert!(Template::parse("{{name").is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::schema::ProtocolVersion;
+use crate::session::SessionState;
+use crate::ServerMetadata;
+use jsonrpc_core::futures_util::future::{self, Either, Ready};
+use jsonrpc_core::{Call, ErrorCode, Failure, Middleware, Output};
+use tracing::warn;
+
+/// Newest protocol version the server speaks, offered to clients asking for one it doesn't know
+pub const LATEST_PROTOCOL_VERSION: &str = ProtocolVersion::LATEST.as_str();
+
+/// Protocol versions the server can speak, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &[
+    ProtocolVersion::V2025_06_18.as_str(),
+    ProtocolVersion::V2025_03_26.as_str(),
+    ProtocolVersion::V2024_11_05.as_str(),
+];
+
+/// JSON-RPC error code for requests sent before the handshake finished
+pub const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// Picks the protocol version to answer `initialize` with
+///
+/// A supported version is accepted as is. Otherwise the server offers its
+/// latest version, which the client disconnects over if it can't speak it.
+pub fn negotiate_version(requested: &str) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| **version == requested)
+        .copied()
+        .unwrap_or(LATEST_PROTOCOL_VERSION)
+}
+
+/// Protocol version the session of `meta` agreed on, the latest for sessions that didn't
+///
+/// Decides the shapes the session's results are sent in.
+pub(crate) fn session_version(meta: &ServerMetadata) -> ProtocolVersion {
+    meta.sessions
+        .get(&meta.session_id)
+        .and_then(|session| session.protocol_version)
+        .and_then(|version| ProtocolVersion::parse(&version))
+        .unwrap_or(ProtocolVersion::LATEST)
+}
+
+/// Enforces the session lifecycle on every request
+///
+/// Only `initialize` and `ping` are accepted before the client sends
+/// `notifications/initialized`, and `initialize` only once. Runs while a
+/// message is dispatched, so it sees the session as every earlier message
+/// left it. Stateless transports let every request through, as each one
+/// comes from a new session.
+pub(crate) struct HandshakeGate {
+    pub(crate) stateless: bool,
+}
+
+impl Middleware<ServerMetadata> for HandshakeGate {
+    type Future = jsonrpc_core::middleware::NoopFuture;
+    type CallFuture = Ready<Option<Output>>;
+
+    fn on_call<F, X>(
+        &self,
+        call: Call,
+        meta: ServerMetadata,
+        next: F,
+    ) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, ServerMetadata) -> X + Send + Sync,
+        X: std::future::Future<Output = Option<Output>> + Send + 'static,
+    {
+        let Call::MethodCall(request) = &call else {
+            return Either::Right(next(call, meta));
+        };
+        let state = meta
+            .sessions
+            .get(&meta.session_id)
+            .map(|session| session.state)
+            .unwrap_or_default();
+        let rejection = match (state, request.method.as_str()) {
+            _ if self.stateless => None,
+            (SessionState::Uninitialized, "initialize") => None,
+            (_, "initialize") => {
+                Some((ErrorCode::InvalidRequest, "Session is already initialized"))
+            }
+            (SessionState::ShuttingDown, _) => {
+                Some((ErrorCode::InvalidRequest, "Server is shutting down"))
+            }
+            (_, "ping") | (SessionState::Ready, _) => None,
+            (SessionState::Uninitialized | SessionState::Initializing, _) => Some((
+                ErrorCode::ServerError(SERVER_NOT_INITIALIZED),
+                "Server not initialized",
+            )),
+        };
+        let Some((code, message)) = rejection else {
+            return Either::Right(next(call, meta));
+        };
+
+        warn!(
+            "Rejected {} from {} in state {:?}",
+            request.method, meta.session_id, state
+        );
+        Either::Left(future::ready(Some(Output::Failure(Failure {
+            jsonrpc: request.jsonrpc,
+            error: jsonrpc_core::Error {
+                code,
+                message: message.to_string(),
+                data: None,
+            },
+            id: request.id.clone(),
+        }))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roots::RootsState;
+    use crate::router::RequestRouter;
+    use crate::session::Sessions;
+    use crate::transport::ConnectionId;
+    use jsonrpc_core::MetaIoHandler;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_negotiate_version() {
+        assert_eq!(negotiate_version("2024-11-05"), "2024-11-05");
+        assert_eq!(negotiate_version("2025-03-26"), "2025-03-26");
+        assert_eq!(negotiate_version("2025-06-18"), "2025-06-18");
+        assert_eq!(negotiate_version("1999-01-01"), LATEST_PROTOCOL_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_gate() {
+        let mut io_handler = MetaIoHandler::with_middleware(HandshakeGate { stateless: false });
+        io_handler.add_method_with_meta("tools/list", |_params, _meta: ServerMetadata| async {
+            Ok(json!({ "tools": [] }))
+        });
+        io_handler.add_method("ping", |_params| async { Ok(json!({})) });
+
+        let (outgoing_tx, _outgoing_rx) = tokio::sync::mpsc::channel(1);
+        let meta = ServerMetadata {
+            session_id: ConnectionId::from("client"),
+            sessions: Sessions::default(),
+            router: RequestRouter::new(outgoing_tx),
+            roots: RootsState::default(),
+        };
+        let call = |method: &str| {
+            let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method }).to_string();
+            let response = io_handler.handle_request(&request, meta.clone());
+            async move {
+                let response: Value = serde_json::from_str(&response.await.unwrap()).unwrap();
+                response
+            }
+        };
+
+        let response = call("tools/list").await;
+        assert_eq!(response["error"]["code"], SERVER_NOT_INITIALIZED);
+        assert_eq!(response["id"], 1);
+        assert_eq!(call("ping").await["result"], json!({}));
+
+        let set_state = |state| {
+            meta.sessions
+                .update(&meta.session_id, |session| session.state = state)
+        };
+        set_state(SessionState::Initializing);
+        assert_eq!(
+            call("tools/list").await["error"]["code"],
+            SERVER_NOT_INITIALIZED
+        );
+        set_state(SessionState::Ready);
+        assert_eq!(call("tools/list").await["result"]["tools"], json!([]));
+        // A session is only initialized once
+        assert_eq!(call("initialize").await["error"]["code"], -32600);
+        set_state(SessionState::ShuttingDown);
+        assert_eq!(call("ping").await["error"]["code"], -32600);
+    }
+}
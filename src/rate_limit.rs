@@ -0,0 +1,289 @@
+use crate::transport::ConnectionId;
+use crate::ServerMetadata;
+use jsonrpc_core::futures_util::future::{self, Either, Ready};
+use jsonrpc_core::{Call, ErrorCode, Failure, Middleware, Output};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::warn;
+
+/// JSON-RPC error code for requests refused by a rate limit
+pub const RATE_LIMITED: i64 = -32029;
+
+/// Number of idle session buckets kept before the full ones are dropped
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// A token bucket: `burst` requests at once, refilled at a steady rate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    burst: f64,
+    per_second: f64,
+}
+
+impl RateLimit {
+    /// Allows `requests` a second, all of which may arrive at once
+    pub fn per_second(requests: u32) -> Self {
+        Self {
+            burst: requests.max(1) as f64,
+            per_second: requests as f64,
+        }
+    }
+
+    /// Allows `requests` a minute, all of which may arrive at once
+    pub fn per_minute(requests: u32) -> Self {
+        Self {
+            burst: requests.max(1) as f64,
+            per_second: requests as f64 / 60.0,
+        }
+    }
+
+    /// Sets how many requests may arrive at once
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1) as f64;
+        self
+    }
+}
+
+/// Rate limits applied while requests are dispatched
+///
+/// A request takes a token from the global bucket, its session's bucket and
+/// its session's bucket for the method, and is rejected with
+/// [`RATE_LIMITED`] if any of them is empty. Notifications are never
+/// limited. Stateless transports give every request a new session, so only
+/// the global limit applies to them.
+///
+/// ```ignore
+/// RateLimits::new()
+///     .per_session(RateLimit::per_second(20))
+///     .method("tools/call", RateLimit::per_minute(30));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RateLimits {
+    global: Option<RateLimit>,
+    session: Option<RateLimit>,
+    methods: HashMap<String, RateLimit>,
+}
+
+impl RateLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the requests of all sessions together
+    pub fn global(mut self, limit: RateLimit) -> Self {
+        self.global = Some(limit);
+        self
+    }
+
+    /// Limits the requests of each session
+    pub fn per_session(mut self, limit: RateLimit) -> Self {
+        self.session = Some(limit);
+        self
+    }
+
+    /// Limits the requests each session makes for `method`, on top of the other limits
+    pub fn method(mut self, method: impl Into<String>, limit: RateLimit) -> Self {
+        self.methods.insert(method.into(), limit);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.global.is_none() && self.session.is_none() && self.methods.is_empty()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst);
+        self.updated = now;
+    }
+
+    /// How long until the bucket has a token, zero if it has one now
+    fn wait(&self, limit: &RateLimit) -> Duration {
+        let missing = 1.0 - self.tokens;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else if limit.per_second <= 0.0 {
+            Duration::MAX
+        } else {
+            Duration::from_secs_f64(missing / limit.per_second)
+        }
+    }
+}
+
+/// Which bucket of a session a limit fills
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Session(ConnectionId),
+    Method(ConnectionId, String),
+}
+
+#[derive(Default)]
+struct Buckets {
+    global: Option<Bucket>,
+    sessions: HashMap<Key, Bucket>,
+}
+
+/// Middleware enforcing [`RateLimits`]
+pub(crate) struct RateLimiter {
+    limits: RateLimits,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            buckets: Mutex::new(Buckets::default()),
+        }
+    }
+
+    /// Takes a token for the request, or returns how long to wait for one
+    fn acquire(&self, session_id: &ConnectionId, method: &str) -> Result<(), Duration> {
+        if self.limits.is_empty() {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let Buckets { global, sessions } = &mut *buckets;
+
+        let mut keys = Vec::new();
+        if let Some(limit) = &self.limits.session {
+            keys.push((Key::Session(session_id.clone()), limit));
+        }
+        if let Some(limit) = self.limits.methods.get(method) {
+            keys.push((Key::Method(session_id.clone(), method.to_string()), limit));
+        }
+
+        // Only take tokens once every bucket has one, so a rejected request costs nothing
+        let mut wait = Duration::ZERO;
+        for (key, limit) in &keys {
+            let bucket = sessions
+                .entry(key.clone())
+                .or_insert_with(|| Bucket::full(limit, now));
+            bucket.refill(limit, now);
+            wait = wait.max(bucket.wait(limit));
+        }
+        if let Some(limit) = &self.limits.global {
+            let bucket = global.get_or_insert_with(|| Bucket::full(limit, now));
+            bucket.refill(limit, now);
+            wait = wait.max(bucket.wait(limit));
+        }
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        for (key, _) in &keys {
+            if let Some(bucket) = sessions.get_mut(key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        if let Some(bucket) = global {
+            bucket.tokens -= 1.0;
+        }
+
+        if sessions.len() > PRUNE_THRESHOLD {
+            let limits = &self.limits;
+            sessions.retain(|key, bucket| {
+                let limit = match key {
+                    Key::Session(_) => limits.session.as_ref(),
+                    Key::Method(_, method) => limits.methods.get(method),
+                };
+                limit.is_some_and(|limit| {
+                    bucket.refill(limit, now);
+                    bucket.tokens < limit.burst
+                })
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Middleware<ServerMetadata> for RateLimiter {
+    type Future = jsonrpc_core::middleware::NoopFuture;
+    type CallFuture = Ready<Option<Output>>;
+
+    fn on_call<F, X>(
+        &self,
+        call: Call,
+        meta: ServerMetadata,
+        next: F,
+    ) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, ServerMetadata) -> X + Send + Sync,
+        X: std::future::Future<Output = Option<Output>> + Send + 'static,
+    {
+        let Call::MethodCall(request) = &call else {
+            return Either::Right(next(call, meta));
+        };
+        let Err(wait) = self.acquire(&meta.session_id, &request.method) else {
+            return Either::Right(next(call, meta));
+        };
+
+        let retry_after_ms = u64::try_from(wait.as_millis().max(1)).unwrap_or(u64::MAX);
+        warn!(
+            "Rate limited {} from {}, retry in {}ms",
+            request.method, meta.session_id, retry_after_ms
+        );
+        Either::Left(future::ready(Some(Output::Failure(Failure {
+            jsonrpc: request.jsonrpc,
+            error: jsonrpc_core::Error {
+                code: ErrorCode::ServerError(RATE_LIMITED),
+                message: format!("Rate limit exceeded, retry in {}ms", retry_after_ms),
+                data: Some(json!({ "retryAfterMs": retry_after_ms })),
+            },
+            id: request.id.clone(),
+        }))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limits() {
+        tokio::time::pause();
+        let limiter = RateLimiter::new(
+            RateLimits::new()
+                .per_session(RateLimit::per_second(10).with_burst(3))
+                .method("tools/call", RateLimit::per_minute(1)),
+        );
+        let alice = ConnectionId::from("alice");
+        let bob = ConnectionId::from("bob");
+
+        assert!(limiter.acquire(&alice, "tools/call").is_ok());
+        let wait = limiter.acquire(&alice, "tools/call").unwrap_err();
+        assert!(wait > Duration::from_secs(59));
+        // The rejected call didn't take a token from the session bucket
+        assert!(limiter.acquire(&alice, "ping").is_ok());
+        assert!(limiter.acquire(&alice, "ping").is_ok());
+        let wait = limiter.acquire(&alice, "ping").unwrap_err();
+        assert_eq!(wait, Duration::from_millis(100));
+
+        // Sessions have buckets of their own
+        assert!(limiter.acquire(&bob, "tools/call").is_ok());
+
+        tokio::time::advance(Duration::from_millis(100)).await;
+        assert!(limiter.acquire(&alice, "ping").is_ok());
+
+        let global = RateLimiter::new(RateLimits::new().global(RateLimit::per_second(1)));
+        assert!(global.acquire(&alice, "ping").is_ok());
+        assert!(global.acquire(&bob, "ping").is_err());
+    }
+}
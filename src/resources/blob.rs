@@ -0,0 +1,123 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Bytes read at a time, a multiple of 3 so every chunk but the last
+/// encodes to base64 without padding
+const CHUNK_SIZE: usize = 3 * 16 * 1024;
+
+/// What a resource's bytes were read as
+pub(super) enum Contents {
+    Text(String),
+    /// Base64 of the bytes
+    Blob(String),
+}
+
+/// Reads `reader` to the end, as text when `may_be_text` and the bytes are
+/// UTF-8, and otherwise as base64 encoded a chunk at a time
+///
+/// Blobs are never held whole next to their encoding, which is sized from
+/// `size` up front. Reading fails once more than `max_size` bytes came in,
+/// in case the source grew since it was checked.
+pub(super) async fn read_contents<R: AsyncRead + Unpin>(
+    mut reader: R,
+    size: u64,
+    max_size: u64,
+    may_be_text: bool,
+) -> io::Result<Contents> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut read = fill(&mut reader, &mut chunk).await?;
+    let mut total = read as u64;
+
+    check_size(total, max_size)?;
+    if may_be_text && is_utf8_prefix(&chunk[..read]) {
+        let mut bytes = Vec::with_capacity(size.min(max_size) as usize);
+        bytes.extend_from_slice(&chunk[..read]);
+        drop(chunk);
+        (&mut reader)
+            .take(max_size.saturating_add(1) - total)
+            .read_to_end(&mut bytes)
+            .await?;
+        check_size(bytes.len() as u64, max_size)?;
+        return Ok(match String::from_utf8(bytes) {
+            Ok(text) => Contents::Text(text),
+            Err(e) => Contents::Blob(STANDARD.encode(e.as_bytes())),
+        });
+    }
+
+    let mut blob =
+        String::with_capacity(base64::encoded_len(size.min(max_size) as usize, true).unwrap_or(0));
+    loop {
+        check_size(total, max_size)?;
+        STANDARD.encode_string(&chunk[..read], &mut blob);
+        if read < CHUNK_SIZE {
+            return Ok(Contents::Blob(blob));
+        }
+        read = fill(&mut reader, &mut chunk).await?;
+        total += read as u64;
+    }
+}
+
+/// Reads until `chunk` is full or the end of `reader`, returning how much was read
+async fn fill<R: AsyncRead + Unpin>(reader: &mut R, chunk: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < chunk.len() {
+        match reader.read(&mut chunk[filled..]).await? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+fn check_size(size: u64, max_size: u64) -> io::Result<()> {
+    if size > max_size {
+        return Err(io::Error::other(format!("Larger than {} bytes", max_size)));
+    }
+    Ok(())
+}
+
+/// Whether `bytes` are UTF-8, but for a character cut off at the end
+fn is_utf8_prefix(bytes: &[u8]) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_contents() {
+        // Binary spanning several chunks encodes as it would in one go
+        let bytes: Vec<u8> = (0..CHUNK_SIZE * 2 + 7).map(|i| (i % 251) as u8).collect();
+        let size = bytes.len() as u64;
+        match read_contents(bytes.as_slice(), size, size, true)
+            .await
+            .unwrap()
+        {
+            Contents::Blob(blob) => assert_eq!(blob, STANDARD.encode(&bytes)),
+            Contents::Text(_) => panic!("Expected a blob"),
+        }
+
+        let text = "é".repeat(CHUNK_SIZE);
+        match read_contents(text.as_bytes(), 0, u64::MAX, true)
+            .await
+            .unwrap()
+        {
+            Contents::Text(read) => assert_eq!(read, text),
+            Contents::Blob(_) => panic!("Expected text"),
+        }
+        match read_contents(&b"text"[..], 4, 4, false).await.unwrap() {
+            Contents::Blob(blob) => assert_eq!(blob, "dGV4dA=="),
+            Contents::Text(_) => panic!("Expected a blob"),
+        }
+
+        // Sources that grew past the limit since they were checked aren't read whole
+        assert!(read_contents(bytes.as_slice(), 10, 10, true).await.is_err());
+        assert!(read_contents(text.as_bytes(), 10, 10, true).await.is_err());
+    }
+}
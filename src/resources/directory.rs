@@ -0,0 +1,483 @@
+use super::blob::{read_contents, Contents};
+use super::{ResourceProvider, ResourceWatcher};
+use crate::error::ResourceError;
+use crate::schema::{ReadResourceResult, Resource, ResourceTemplate};
+use serde_json::json;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::sync::broadcast;
+use tracing::warn;
+use url::Url;
+
+/// Largest file read, unless set with `with_max_file_size`
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Most files listed from one directory tree
+const MAX_LISTED: usize = 10_000;
+
+/// Serves the files under a directory as `file://` resources
+///
+/// Every file is listed by `resources/list` and read by `resources/read`,
+/// as text when it's UTF-8 and as a base64 blob otherwise, which is encoded
+/// as the file is read rather than after reading it whole. Glob patterns,
+/// matched against paths relative to the directory, narrow down which files
+/// are served: `*` matches within a path segment, `**` across segments and
+/// `?` a single character. Hidden files and directories are skipped.
+///
+/// With a `FileWatcher`, clients can subscribe to the files.
+///
+/// ```ignore
+/// let docs = DirectoryResourceProvider::new("docs")?
+///     .include("**/*.md")
+///     .exclude("drafts/**")
+///     .with_watcher(FileWatcher::new()?);
+/// Server::builder().mount_resources(docs.root_uri(), docs)
+/// ```
+pub struct DirectoryResourceProvider {
+    root: PathBuf,
+    root_uri: Url,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    max_file_size: u64,
+    watcher: Option<Box<dyn ResourceWatcher>>,
+}
+
+impl DirectoryResourceProvider {
+    /// Serves the files under `root`, which must be an existing directory
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, ResourceError> {
+        let root = root.as_ref();
+        let root = root.canonicalize().map_err(|e| {
+            ResourceError::failed(format!(
+                "Can't serve missing directory {}: {}",
+                root.display(),
+                e
+            ))
+        })?;
+        if !root.is_dir() {
+            return Err(ResourceError::failed(format!(
+                "{} isn't a directory",
+                root.display()
+            )));
+        }
+        let root_uri = Url::from_directory_path(&root).map_err(|_| {
+            ResourceError::failed(format!("{} can't be a file URI", root.display()))
+        })?;
+        Ok(Self {
+            root,
+            root_uri,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            watcher: None,
+        })
+    }
+
+    /// The `file://` URI of the directory, which every served file's URI starts with
+    pub fn root_uri(&self) -> &str {
+        self.root_uri.as_str()
+    }
+
+    /// Only serves files matching `pattern`, or one of the other included patterns
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Doesn't serve files matching `pattern`, even if they're included
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Refuses to read files larger than `bytes`
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Lets clients subscribe to the files, which `watcher` reports changes to
+    pub fn with_watcher(mut self, watcher: impl ResourceWatcher + 'static) -> Self {
+        self.watcher = Some(Box::new(watcher));
+        self
+    }
+
+    /// Whether the file at `relative`, with `/` separators, is served
+    fn serves(&self, relative: &str) -> bool {
+        let hidden = relative.split('/').any(|segment| segment.starts_with('.'));
+        !hidden
+            && (self.include.is_empty() || self.include.iter().any(|p| glob_match(p, relative)))
+            && !self.exclude.iter().any(|p| glob_match(p, relative))
+    }
+
+    /// Path relative to the root, with `/` separators, of a path under it
+    fn relative(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let segments: Option<Vec<&str>> = relative.iter().map(|s| s.to_str()).collect();
+        Some(segments?.join("/"))
+    }
+
+    /// Adds the served files under `directory` to `resources`
+    fn walk(&self, directory: &Path, resources: &mut Vec<Resource>) {
+        let entries = match std::fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to list {}: {}", directory.display(), e);
+                return;
+            }
+        };
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            if resources.len() >= MAX_LISTED {
+                warn!(
+                    "Listed only the first {} files of {}",
+                    MAX_LISTED,
+                    self.root.display()
+                );
+                return;
+            }
+            let path = entry.path();
+            let Some(relative) = self.relative(&path) else {
+                continue;
+            };
+            if relative.split('/').any(|segment| segment.starts_with('.')) {
+                continue;
+            }
+            // Symlinks aren't followed, so nothing outside the root is listed
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => self.walk(&path, resources),
+                Ok(file_type) if file_type.is_file() && self.serves(&relative) => {
+                    let Ok(uri) = Url::from_file_path(&path) else {
+                        continue;
+                    };
+                    resources.push(Resource {
+                        name: relative,
+                        uri: uri.to_string(),
+                        description: None,
+                        mime_type: Some(mime_type(&path).to_string()),
+                        annotations: None,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn uri_template(&self) -> String {
+        format!("{}{{+path}}", self.root_uri)
+    }
+
+    /// Paths of the served files and the directories in the directory `partial`
+    /// is in, directories ending with `/`
+    ///
+    /// Nothing outside the root is completed, whether through `..` or symlinks.
+    fn entries(&self, partial: &str) -> Vec<String> {
+        let directory = partial
+            .rsplit_once('/')
+            .map_or("", |(directory, _)| directory);
+        let segments = directory.split('/').filter(|segment| !segment.is_empty());
+        if segments.clone().any(|segment| segment.starts_with('.')) {
+            return Vec::new();
+        }
+        let Ok(directory) = self.root.join(segments.collect::<PathBuf>()).canonicalize() else {
+            return Vec::new();
+        };
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            return Vec::new();
+        };
+
+        let mut paths = Vec::new();
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let Some(relative) = self.relative(&entry.path()) else {
+                continue;
+            };
+            if relative.split('/').any(|segment| segment.starts_with('.')) {
+                continue;
+            }
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => paths.push(format!("{}/", relative)),
+                Ok(file_type) if file_type.is_file() && self.serves(&relative) => {
+                    paths.push(relative)
+                }
+                _ => {}
+            }
+        }
+        paths.sort();
+        paths
+    }
+
+    /// The served file `uri` points at
+    fn file(&self, uri: &str) -> Result<PathBuf, ResourceError> {
+        let not_found = || ResourceError::NotFound(uri.to_string());
+        let path = Url::parse(uri)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .ok_or_else(not_found)?;
+        // Resolves `..` and symlinks before checking the file is under the root
+        let path = path.canonicalize().map_err(|_| not_found())?;
+        match self.relative(&path) {
+            Some(relative) if path.is_file() && self.serves(&relative) => Ok(path),
+            _ => Err(not_found()),
+        }
+    }
+}
+
+impl ResourceProvider for DirectoryResourceProvider {
+    fn list(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Resource>, ResourceError>> + Send + '_>> {
+        Box::pin(async move {
+            let mut resources = Vec::new();
+            self.walk(&self.root, &mut resources);
+            Ok(resources)
+        })
+    }
+
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        vec![ResourceTemplate {
+            name: self.root.display().to_string(),
+            uri_template: self.uri_template(),
+            description: Some(format!("Files under {}", self.root.display())),
+            mime_type: None,
+            annotations: None,
+        }]
+    }
+
+    fn complete(&self, uri_template: &str, argument: &str, value: &str) -> Vec<String> {
+        if uri_template != self.uri_template() || argument != "path" {
+            return Vec::new();
+        }
+        self.entries(value)
+    }
+
+    fn read<'a>(
+        &'a self,
+        uri: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>> {
+        Box::pin(async move {
+            let path = self.file(uri)?;
+            let size = tokio::fs::metadata(&path)
+                .await
+                .map_err(ResourceError::failed)?
+                .len();
+            if size > self.max_file_size {
+                return Err(ResourceError::failed(format!(
+                    "{} is larger than {} bytes",
+                    uri, self.max_file_size
+                )));
+            }
+
+            let file = tokio::fs::File::open(&path)
+                .await
+                .map_err(ResourceError::failed)?;
+            let mime_type = mime_type(&path);
+            let may_be_text = !mime_type.starts_with("image/");
+            let contents = read_contents(file, size, self.max_file_size, may_be_text)
+                .await
+                .map_err(|e| ResourceError::failed(format!("Failed to read {}: {}", uri, e)))?;
+            let contents = match contents {
+                Contents::Text(text) => json!({ "uri": uri, "mimeType": mime_type, "text": text }),
+                Contents::Blob(blob) => json!({ "uri": uri, "mimeType": mime_type, "blob": blob }),
+            };
+            Ok(ReadResourceResult {
+                contents: vec![contents],
+                meta: None,
+            })
+        })
+    }
+
+    fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        let watcher = self
+            .watcher
+            .as_ref()
+            .ok_or_else(|| ResourceError::Unsubscribable(uri.to_string()))?;
+        self.file(uri)?;
+        watcher.watch(uri)
+    }
+
+    fn unsubscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        match &self.watcher {
+            Some(watcher) => watcher.unwatch(uri),
+            None => Ok(()),
+        }
+    }
+
+    fn updates(&self) -> Option<broadcast::Receiver<String>> {
+        self.watcher.as_ref().map(|watcher| watcher.updates())
+    }
+}
+
+#[cfg(feature = "s3")]
+pub(super) fn blob(uri: &str, mime_type: &str, bytes: &[u8]) -> serde_json::Value {
+    use base64::Engine;
+    let blob = base64::engine::general_purpose::STANDARD.encode(bytes);
+    json!({ "uri": uri, "mimeType": mime_type, "blob": blob })
+}
+
+/// MIME type of a file, guessed from its extension
+pub(super) fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "md" | "markdown" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "wasm" => "application/wasm",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "txt" | "log" | "rs" | "py" | "ts" | "go" | "c" | "h" | "cpp" | "java" | "sh" | "ini"
+        | "cfg" | "conf" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Whether `path` matches the glob `pattern`, both with `/` separators
+fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches(&pattern, &path)
+}
+
+fn matches(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directories at all
+            if let ['/', after @ ..] = rest {
+                if matches(after, path) {
+                    return true;
+                }
+            }
+            (0..=path.len()).any(|i| matches(rest, &path[i..]))
+        }
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| matches(rest, &path[i..])),
+        ['?', rest @ ..] => {
+            matches!(path.first(), Some(c) if *c != '/') && matches(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::CompletionRef;
+    use crate::testing::TestServer;
+    use crate::Server;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.md", "readme.md"));
+        assert!(!glob_match("*.md", "docs/readme.md"));
+        assert!(glob_match("**/*.md", "readme.md"));
+        assert!(glob_match("**/*.md", "docs/guide/readme.md"));
+        assert!(glob_match("docs/**", "docs/guide/readme.md"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(!glob_match("file?.txt", "file10.txt"));
+        assert!(!glob_match("docs/*", "docs/guide/readme.md"));
+    }
+
+    #[tokio::test]
+    async fn test_directory_resources() {
+        let root = std::env::temp_dir().join(format!("bioma-resources-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("docs/drafts")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join("docs/guide.md"), "# Guide").unwrap();
+        std::fs::write(root.join("docs/drafts/next.md"), "# Next").unwrap();
+        std::fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G', 0xff]).unwrap();
+        std::fs::write(root.join(".git/config"), "").unwrap();
+
+        let provider = DirectoryResourceProvider::new(&root).unwrap();
+        let names: Vec<_> = provider
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.name)
+            .collect();
+        assert_eq!(
+            names,
+            vec!["docs/drafts/next.md", "docs/guide.md", "logo.png"]
+        );
+
+        let provider = provider.include("**/*.md").exclude("**/drafts/**");
+        let resources = provider.list().await.unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].mime_type.as_deref(), Some("text/markdown"));
+
+        let guide = provider.read(&resources[0].uri).await.unwrap();
+        assert_eq!(guide.contents[0]["text"], "# Guide");
+        // Excluded files and paths escaping the root can't be read
+        let draft = Url::from_file_path(root.join("docs/drafts/next.md")).unwrap();
+        let draft = provider.read(draft.as_str()).await.unwrap_err();
+        assert!(matches!(draft, ResourceError::NotFound(_)));
+        let escape = format!("{}docs/../../etc/passwd", provider.root_uri);
+        assert!(provider.read(&escape).await.is_err());
+
+        let provider = DirectoryResourceProvider::new(&root).unwrap();
+        let logo = Url::from_file_path(root.canonicalize().unwrap().join("logo.png")).unwrap();
+        let logo = provider.read(logo.as_str()).await.unwrap();
+        assert_eq!(logo.contents[0]["mimeType"], "image/png");
+        assert!(logo.contents[0]["blob"].is_string());
+
+        // Mounted under its root, which reads of the listed URIs are routed to
+        let root_uri = provider.root_uri().to_string();
+        let server = Server::builder()
+            .mount_resources(root_uri.clone(), provider)
+            .build();
+        let server = TestServer::start(server).await;
+        let resources = server.list_resources().await.unwrap();
+        assert_eq!(resources.len(), 3);
+        let guide = server.read_resource(&resources[1].uri).await.unwrap();
+        assert_eq!(guide.contents[0]["text"], "# Guide");
+        let missing = format!("{}missing.md", root_uri);
+        assert!(server.read_resource(&missing).await.is_err());
+
+        // The path variable of the template completes to files and directories under the root
+        let template = CompletionRef::Resource {
+            uri: format!("{}{{+path}}", root_uri),
+        };
+        let paths = server.complete(template.clone(), "path", "").await.unwrap();
+        assert_eq!(paths.values, ["docs/", "logo.png"]);
+        let paths = server
+            .complete(template.clone(), "path", "docs/gd")
+            .await
+            .unwrap();
+        assert_eq!(paths.values, ["docs/guide.md"]);
+        let paths = server
+            .complete(template.clone(), "path", "../")
+            .await
+            .unwrap();
+        assert!(paths.values.is_empty());
+        let unknown = CompletionRef::Resource {
+            uri: "file:///{+path}".to_string(),
+        };
+        assert!(server.complete(unknown, "path", "").await.is_err());
+        server.shutdown().await;
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+use super::ResourceProvider;
+use crate::error::ResourceError;
+use crate::schema::{ReadResourceResult, Resource};
+use crate::tools::fetch::Fetch;
+use base64::Engine;
+use serde_json::json;
+use std::future::Future;
+use std::pin::Pin;
+use url::Url;
+
+/// Serves configured HTTP(S) URLs as resources
+///
+/// Reads go through a `Fetch`, so they're held to its host guard, robots.txt,
+/// limits and cache. Text comes back as it was sent, anything else as a
+/// base64 blob, both with the MIME type the site gave. Only the configured
+/// URLs can be read.
+///
+/// ```ignore
+/// Server::builder().mount_resources(
+///     "http",
+///     HttpResourceProvider::new(Fetch::default()).url("https://example.com/changelog.md")?,
+/// )
+/// ```
+pub struct HttpResourceProvider {
+    fetch: Fetch,
+    resources: Vec<Resource>,
+}
+
+impl HttpResourceProvider {
+    pub fn new(fetch: Fetch) -> Self {
+        Self {
+            fetch,
+            resources: Vec::new(),
+        }
+    }
+
+    /// Serves `resource`, whose URI must be an HTTP(S) URL
+    pub fn resource(mut self, resource: Resource) -> Result<Self, ResourceError> {
+        let url = Url::parse(&resource.uri).map_err(ResourceError::failed)?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ResourceError::failed(format!(
+                "{} isn't an HTTP URL",
+                resource.uri
+            )));
+        }
+        self.resources
+            .retain(|existing| existing.uri != resource.uri);
+        self.resources.push(resource);
+        Ok(self)
+    }
+
+    /// Serves `url`, named after itself
+    pub fn url(self, url: &str) -> Result<Self, ResourceError> {
+        self.resource(Resource {
+            name: url.to_string(),
+            uri: url.to_string(),
+            description: None,
+            mime_type: None,
+            annotations: None,
+        })
+    }
+}
+
+impl ResourceProvider for HttpResourceProvider {
+    fn list(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Resource>, ResourceError>> + Send + '_>> {
+        Box::pin(async move { Ok(self.resources.clone()) })
+    }
+
+    fn read<'a>(
+        &'a self,
+        uri: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>> {
+        Box::pin(async move {
+            let resource = self
+                .resources
+                .iter()
+                .find(|resource| resource.uri == uri)
+                .ok_or_else(|| ResourceError::NotFound(uri.to_string()))?;
+            let url = Url::parse(uri).map_err(ResourceError::failed)?;
+            let download = self
+                .fetch
+                .download(&url)
+                .await
+                .map_err(ResourceError::failed)?;
+
+            let mime_type = download
+                .mime_type()
+                .or_else(|| resource.mime_type.clone())
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            let contents = match String::from_utf8(download.body) {
+                Ok(text) if is_text(&mime_type) => {
+                    json!({ "uri": uri, "mimeType": mime_type, "text": text })
+                }
+                Ok(text) => blob(uri, &mime_type, text.as_bytes()),
+                Err(e) => blob(uri, &mime_type, e.as_bytes()),
+            };
+            Ok(ReadResourceResult {
+                contents: vec![contents],
+                meta: None,
+            })
+        })
+    }
+}
+
+fn blob(uri: &str, mime_type: &str, bytes: &[u8]) -> serde_json::Value {
+    let blob = base64::engine::general_purpose::STANDARD.encode(bytes);
+    json!({ "uri": uri, "mimeType": mime_type, "blob": blob })
+}
+
+/// Whether content of `mime_type` is text rather than binary
+fn is_text(mime_type: &str) -> bool {
+    mime_type.starts_with("text/")
+        || mime_type.ends_with("+json")
+        || mime_type.ends_with("+xml")
+        || matches!(
+            mime_type,
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/yaml"
+                | "application/toml"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::fetch::{CacheConfig, FetchConfig};
+
+    #[tokio::test]
+    async fn test_http_resources() {
+        let mut site = mockito::Server::new_async().await;
+        let _robots = site
+            .mock("GET", "/robots.txt")
+            .with_status(404)
+            .create_async()
+            .await;
+        let notes_mock = site
+            .mock("GET", "/notes.md")
+            .with_header("content-type", "text/markdown; charset=utf-8")
+            .with_header("cache-control", "max-age=60")
+            .with_body("# Notes")
+            .expect(1)
+            .create_async()
+            .await;
+        let _logo = site
+            .mock("GET", "/logo.png")
+            .with_header("content-type", "image/png")
+            .with_body([0x89, b'P', b'N', b'G', 0xff])
+            .create_async()
+            .await;
+
+        let fetch = Fetch::new(FetchConfig {
+            cache: Some(CacheConfig::default()),
+            ..Default::default()
+        });
+        let notes = format!("{}/notes.md", site.url());
+        let logo = format!("{}/logo.png", site.url());
+        let provider = HttpResourceProvider::new(fetch)
+            .url(&notes)
+            .unwrap()
+            .url(&logo)
+            .unwrap();
+        assert!(HttpResourceProvider::new(Fetch::default())
+            .url("file:///etc/passwd")
+            .is_err());
+        assert_eq!(provider.list().await.unwrap().len(), 2);
+
+        let read = provider.read(&notes).await.unwrap();
+        assert_eq!(read.contents[0]["text"], "# Notes");
+        assert_eq!(read.contents[0]["mimeType"], "text/markdown");
+        // Read again from the fetch cache
+        let read = provider.read(&notes).await.unwrap();
+        assert_eq!(read.contents[0]["text"], "# Notes");
+        notes_mock.assert_async().await;
+        let read = provider.read(&logo).await.unwrap();
+        assert_eq!(read.contents[0]["mimeType"], "image/png");
+        assert_eq!(read.contents[0]["blob"], "iVBOR/8=");
+
+        // Only the configured URLs are served
+        let other = format!("{}/other.md", site.url());
+        let missing = provider.read(&other).await.unwrap_err();
+        assert!(matches!(missing, ResourceError::NotFound(_)));
+    }
+}
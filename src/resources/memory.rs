@@ -0,0 +1,353 @@
+use super::ResourceProvider;
+use crate::error::ResourceError;
+use crate::schema::{ReadResourceResult, Resource};
+use crate::server::ListChanged;
+use base64::Engine;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use tokio::sync::broadcast;
+
+/// Scheme of the URIs of published resources
+pub const MEMORY_SCHEME: &str = "mem://";
+
+/// Resources kept before the oldest published is dropped, unless set with `with_max_resources`
+pub const DEFAULT_MAX_RESOURCES: usize = 1000;
+
+/// Changed URIs kept for a server whose forwarding falls behind
+const UPDATES_CAPACITY: usize = 64;
+
+/// What a published resource reads as
+#[derive(Clone, Debug, PartialEq)]
+pub enum MemoryContents {
+    Text { mime_type: String, text: String },
+    Blob { mime_type: String, bytes: Vec<u8> },
+}
+
+impl MemoryContents {
+    pub fn text(mime_type: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::Text {
+            mime_type: mime_type.into(),
+            text: text.into(),
+        }
+    }
+
+    pub fn blob(mime_type: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        Self::Blob {
+            mime_type: mime_type.into(),
+            bytes: bytes.into(),
+        }
+    }
+
+    fn mime_type(&self) -> &str {
+        match self {
+            Self::Text { mime_type, .. } | Self::Blob { mime_type, .. } => mime_type,
+        }
+    }
+
+    fn to_value(&self, uri: &str) -> Value {
+        match self {
+            Self::Text { mime_type, text } => {
+                json!({ "uri": uri, "mimeType": mime_type, "text": text })
+            }
+            Self::Blob { mime_type, bytes } => {
+                let blob = base64::engine::general_purpose::STANDARD.encode(bytes);
+                json!({ "uri": uri, "mimeType": mime_type, "blob": blob })
+            }
+        }
+    }
+}
+
+/// Resources published at runtime under `mem://` URIs, by tools or the embedding application
+///
+/// Publishing to a path gives a stable URI, `mem://path`: publishing to it
+/// again replaces the contents and tells subscribers the resource changed.
+/// New and dropped resources are announced with `resources/list_changed`.
+/// Clones share the same resources.
+///
+/// ```ignore
+/// let published = MemoryResources::new();
+/// let server = Server::builder().memory_resources(published.clone()).build();
+/// published.publish("reports/daily.md", MemoryContents::text("text/markdown", report));
+/// ```
+#[derive(Clone)]
+pub struct MemoryResources {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    /// Oldest published first
+    published: RwLock<Vec<(Resource, MemoryContents)>>,
+    max_resources: usize,
+    subscribed: Mutex<HashSet<String>>,
+    updates: broadcast::Sender<String>,
+    /// Changes of the server the resources are mounted on
+    changes: OnceLock<broadcast::Sender<ListChanged>>,
+}
+
+impl Default for MemoryResources {
+    fn default() -> Self {
+        Self::with_max_resources(DEFAULT_MAX_RESOURCES)
+    }
+}
+
+impl MemoryResources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps at most `max` resources, dropping the oldest published first
+    pub fn with_max_resources(max: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                published: RwLock::default(),
+                max_resources: max.max(1),
+                subscribed: Mutex::default(),
+                updates: broadcast::channel(UPDATES_CAPACITY).0,
+                changes: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Publishes `contents` at `mem://path`, returning the resource
+    pub fn publish(&self, path: &str, contents: MemoryContents) -> Resource {
+        let path = path.trim_start_matches('/');
+        let resource = Resource {
+            name: path.to_string(),
+            uri: format!("{}{}", MEMORY_SCHEME, path),
+            description: None,
+            mime_type: Some(contents.mime_type().to_string()),
+            annotations: None,
+        };
+
+        let (replaced, dropped) = {
+            let mut published = self
+                .inner
+                .published
+                .write()
+                .unwrap_or_else(|e| e.into_inner());
+            let before = published.len();
+            published.retain(|(existing, _)| existing.uri != resource.uri);
+            let replaced = published.len() < before;
+            published.push((resource.clone(), contents));
+            let excess = published.len().saturating_sub(self.inner.max_resources);
+            let dropped: Vec<Resource> = published
+                .drain(..excess)
+                .map(|(resource, _)| resource)
+                .collect();
+            (replaced, dropped)
+        };
+
+        if replaced && self.is_subscribed(&resource.uri) {
+            // Nobody listens until the server runs
+            let _ = self.inner.updates.send(resource.uri.clone());
+        }
+        for dropped in &dropped {
+            self.unsubscribe_all(&dropped.uri);
+        }
+        if !replaced || !dropped.is_empty() {
+            self.notify_changed();
+        }
+        resource
+    }
+
+    /// Removes the resource at `uri`, returning whether there was one
+    pub fn remove(&self, uri: &str) -> bool {
+        let removed = {
+            let mut published = self
+                .inner
+                .published
+                .write()
+                .unwrap_or_else(|e| e.into_inner());
+            let before = published.len();
+            published.retain(|(existing, _)| existing.uri != uri);
+            published.len() < before
+        };
+        if removed {
+            self.unsubscribe_all(uri);
+            self.notify_changed();
+        }
+        removed
+    }
+
+    /// Announces changes to the list on `changes`, those of the server mounting the resources
+    pub(crate) fn announce_on(&self, changes: broadcast::Sender<ListChanged>) {
+        let _ = self.inner.changes.set(changes);
+    }
+
+    fn notify_changed(&self) {
+        if let Some(changes) = self.inner.changes.get() {
+            let _ = changes.send(ListChanged::Resources);
+        }
+    }
+
+    fn is_subscribed(&self, uri: &str) -> bool {
+        self.inner
+            .subscribed
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(uri)
+    }
+
+    fn unsubscribe_all(&self, uri: &str) {
+        self.inner
+            .subscribed
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(uri);
+    }
+}
+
+impl ResourceProvider for MemoryResources {
+    fn list(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Resource>, ResourceError>> + Send + '_>> {
+        let resources = self
+            .inner
+            .published
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(resource, _)| resource.clone())
+            .collect();
+        Box::pin(async move { Ok(resources) })
+    }
+
+    fn read<'a>(
+        &'a self,
+        uri: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>> {
+        let contents = self
+            .inner
+            .published
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|(resource, _)| resource.uri == uri)
+            .map(|(_, contents)| contents.to_value(uri));
+        Box::pin(async move {
+            let contents = contents.ok_or_else(|| ResourceError::NotFound(uri.to_string()))?;
+            Ok(ReadResourceResult {
+                contents: vec![contents],
+                meta: None,
+            })
+        })
+    }
+
+    fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        self.inner
+            .subscribed
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(uri.to_string());
+        Ok(())
+    }
+
+    fn unsubscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        self.unsubscribe_all(uri);
+        Ok(())
+    }
+
+    fn updates(&self) -> Option<broadcast::Receiver<String>> {
+        Some(self.inner.updates.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::CallToolResult;
+    use crate::testing::TestServer;
+    use crate::tools::{McpTool, ToolContext, ToolError};
+    use crate::{ModelContextProtocolServer, Server};
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct ReportProperties {
+        title: String,
+    }
+
+    /// Publishes a report and answers with its URI
+    #[derive(Clone, Debug, Serialize, McpTool)]
+    #[mcp_tool(
+        name = "report",
+        description = "Writes a report",
+        properties = ReportProperties
+    )]
+    struct Report;
+
+    impl Report {
+        async fn run(
+            &self,
+            properties: ReportProperties,
+            context: ToolContext,
+        ) -> Result<CallToolResult, ToolError> {
+            let contents = MemoryContents::text("text/markdown", format!("# {}", properties.title));
+            let resource = context.publish_resource("reports/latest.md", contents)?;
+            Ok(CallToolResult::text(resource.uri))
+        }
+    }
+
+    #[test]
+    fn test_memory_resources_evict_oldest() {
+        let published = MemoryResources::with_max_resources(2);
+        for path in ["a", "b", "c"] {
+            published.publish(path, MemoryContents::blob("application/octet-stream", [1]));
+        }
+        let uris: Vec<_> = published
+            .inner
+            .published
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(resource, _)| resource.uri.clone())
+            .collect();
+        assert_eq!(uris, vec!["mem://b", "mem://c"]);
+        assert!(published.remove("mem://b"));
+        assert!(!published.remove("mem://b"));
+    }
+
+    #[tokio::test]
+    async fn test_tools_publish_resources() {
+        let published = MemoryResources::new();
+        let server = Server::builder()
+            .tool(Report)
+            .memory_resources(published.clone())
+            .build();
+        let mut changes = server.list_changes().unwrap();
+        let server = TestServer::start(server).await;
+        let subscribe = server.initialized.capabilities.resources.clone().unwrap();
+        assert_eq!(subscribe.subscribe, Some(true));
+
+        let result = server.call("report", json!({ "title": "Q1" })).await;
+        assert_eq!(result.content[0].as_text(), Some("mem://reports/latest.md"));
+        assert_eq!(changes.recv().await.unwrap(), ListChanged::Resources);
+
+        let resources = server.list_resources().await.unwrap();
+        assert_eq!(resources[0].uri, "mem://reports/latest.md");
+        let read = server
+            .read_resource("mem://reports/latest.md")
+            .await
+            .unwrap();
+        assert_eq!(read.contents[0]["text"], "# Q1");
+
+        // Publishing again keeps the URI and only updates the contents
+        published.publish(
+            "reports/latest.md",
+            MemoryContents::text("text/markdown", "# Q2"),
+        );
+        let read = server
+            .read_resource("mem://reports/latest.md")
+            .await
+            .unwrap();
+        assert_eq!(read.contents[0]["text"], "# Q2");
+        assert!(changes.try_recv().is_err());
+        assert!(server.read_resource("mem://missing").await.is_err());
+
+        server.shutdown().await;
+    }
+}
@@ -0,0 +1,19 @@
+/// Modules containing resource subsystems
+mod blob;
+pub mod directory;
+pub mod http;
+pub mod memory;
+pub mod provider;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod template;
+pub mod watcher;
+
+pub use directory::DirectoryResourceProvider;
+pub use http::HttpResourceProvider;
+pub use memory::{MemoryContents, MemoryResources};
+pub use provider::{ResourceMounts, ResourceProvider};
+#[cfg(feature = "s3")]
+pub use s3::{S3Config, S3ResourceProvider};
+pub use template::{ResourceTemplateProvider, UriTemplate};
+pub use watcher::{FileWatcher, ResourceWatcher};
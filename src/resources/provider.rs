@@ -0,0 +1,421 @@
+use super::{ResourceTemplateProvider, ResourceWatcher, UriTemplate};
+use crate::error::{PaginationError, ResourceError};
+use crate::pagination;
+use crate::schema::{ReadResourceResult, Resource, ResourceTemplate};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+use tracing::{debug, error};
+
+/// Changed URIs kept for a server whose forwarding falls behind
+const UPDATES_CAPACITY: usize = 64;
+
+/// Resources of a page of `resources/list`, and the cursor of the page after
+pub type ResourcePage = (Vec<Resource>, Option<String>);
+
+/// Prefix of the cursors of `ResourceMounts::list_page`, which also name the mount
+const CURSOR_PREFIX: &str = "mount:";
+
+/// A source of resources, mounted on a server under a URI prefix
+///
+/// The server sends every `resources/read`, `resources/subscribe` and
+/// `resources/unsubscribe` for a URI to the provider mounted under the
+/// longest prefix of it, and lists what all providers offer.
+pub trait ResourceProvider: Send + Sync {
+    /// Resources listed by `resources/list`
+    fn list(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Resource>, ResourceError>> + Send + '_>>;
+
+    /// Up to `limit` resources starting at `cursor`, and the cursor of the ones after
+    ///
+    /// Pages through `list` by default. Providers backed by a store that
+    /// lists in pages pass its continuation tokens as cursors instead.
+    fn list_page<'a>(
+        &'a self,
+        cursor: Option<&'a str>,
+        limit: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<ResourcePage, ResourceError>> + Send + 'a>> {
+        Box::pin(async move {
+            pagination::paginate(&self.list().await?, cursor, limit).map_err(ResourceError::failed)
+        })
+    }
+
+    /// Templates listed by `resources/templates/list`, none by default
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        Vec::new()
+    }
+
+    /// Values the variable `argument` of the template `uri_template` may take,
+    /// given what the client typed so far as `value`; none by default
+    ///
+    /// `completion/complete` fuzzily matches them against `value`, so they
+    /// only need narrowing down as far as is cheap.
+    fn complete(&self, _uri_template: &str, _argument: &str, _value: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Reads the resource at `uri`, failing with `ResourceError::NotFound` if there's none
+    fn read<'a>(
+        &'a self,
+        uri: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>>;
+
+    /// Starts reporting changes to `uri` on `updates`
+    ///
+    /// Called when the first client subscribes to the URI.
+    fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        Err(ResourceError::Unsubscribable(uri.to_string()))
+    }
+
+    /// Stops reporting changes to `uri`, once the last client unsubscribed
+    fn unsubscribe(&self, _uri: &str) -> Result<(), ResourceError> {
+        Ok(())
+    }
+
+    /// Receives the URIs of subscribed resources that change, if the
+    /// provider supports subscriptions
+    fn updates(&self) -> Option<broadcast::Receiver<String>> {
+        None
+    }
+}
+
+/// Resource providers mounted under URI prefixes, such as `file://` or `s3://bucket/`
+#[derive(Clone, Default)]
+pub struct ResourceMounts {
+    mounts: Vec<(String, Arc<dyn ResourceProvider>)>,
+}
+
+impl ResourceMounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends requests for URIs starting with `prefix` to `provider`
+    ///
+    /// A provider mounted under the same prefix before is replaced.
+    pub fn mount(&mut self, prefix: impl Into<String>, provider: Arc<dyn ResourceProvider>) {
+        let prefix = prefix.into();
+        self.mounts.retain(|(mounted, _)| *mounted != prefix);
+        self.mounts.push((prefix, provider));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mounts.is_empty()
+    }
+
+    /// The provider mounted under the longest prefix of `uri`
+    pub fn provider(&self, uri: &str) -> Option<&dyn ResourceProvider> {
+        self.mounts
+            .iter()
+            .filter(|(prefix, _)| uri.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, provider)| provider.as_ref())
+    }
+
+    /// Every provider's resources, skipping providers that fail to list theirs
+    pub async fn list(&self) -> Vec<Resource> {
+        let mut resources = Vec::new();
+        for (prefix, provider) in &self.mounts {
+            match provider.list().await {
+                Ok(listed) => resources.extend(listed),
+                Err(e) => error!("Failed to list the resources under {}: {}", prefix, e),
+            }
+        }
+        resources
+    }
+
+    /// Up to `page_size` resources starting at `cursor`, and the cursor of the ones after
+    ///
+    /// Fills the page from one provider after the other, skipping providers
+    /// that fail to list theirs. Fails only if the cursor is invalid.
+    pub async fn list_page(
+        &self,
+        cursor: Option<&str>,
+        page_size: usize,
+    ) -> Result<ResourcePage, PaginationError> {
+        let page_size = page_size.max(1);
+        let (mut index, mut inner) = match cursor {
+            Some(cursor) => {
+                let (index, inner) = decode_cursor(cursor)?;
+                if index >= self.mounts.len() {
+                    return Err(PaginationError::PastEnd);
+                }
+                (index, inner)
+            }
+            None => (0, None),
+        };
+
+        let mut resources = Vec::new();
+        while let Some((prefix, provider)) = self.mounts.get(index) {
+            let limit = page_size - resources.len();
+            match provider.list_page(inner.as_deref(), limit).await {
+                Ok((listed, Some(next))) => {
+                    resources.extend(listed);
+                    return Ok((resources, Some(encode_cursor(index, Some(&next)))));
+                }
+                Ok((listed, None)) => resources.extend(listed),
+                Err(e) => error!("Failed to list the resources under {}: {}", prefix, e),
+            }
+            index += 1;
+            inner = None;
+            if resources.len() >= page_size {
+                let next = (index < self.mounts.len()).then(|| encode_cursor(index, None));
+                return Ok((resources, next));
+            }
+        }
+        Ok((resources, None))
+    }
+
+    pub fn templates(&self) -> Vec<ResourceTemplate> {
+        self.mounts
+            .iter()
+            .flat_map(|(_, provider)| provider.templates())
+            .collect()
+    }
+
+    /// Values for the variable `argument` of `uri_template`, from the providers
+    /// listing the template, or `None` if none does
+    pub fn complete(&self, uri_template: &str, argument: &str, value: &str) -> Option<Vec<String>> {
+        let mut providers = self
+            .mounts
+            .iter()
+            .filter(|(_, provider)| {
+                provider
+                    .templates()
+                    .iter()
+                    .any(|template| template.uri_template == uri_template)
+            })
+            .peekable();
+        providers.peek()?;
+        Some(
+            providers
+                .flat_map(|(_, provider)| provider.complete(uri_template, argument, value))
+                .collect(),
+        )
+    }
+
+    /// Whether any provider supports subscriptions
+    pub fn subscribable(&self) -> bool {
+        self.mounts
+            .iter()
+            .any(|(_, provider)| provider.updates().is_some())
+    }
+
+    /// Receives the changed URIs of every provider that supports subscriptions
+    ///
+    /// Must be called from within a Tokio runtime, which forwards the updates.
+    pub fn updates(&self) -> Option<broadcast::Receiver<String>> {
+        let receivers: Vec<_> = self
+            .mounts
+            .iter()
+            .filter_map(|(_, provider)| provider.updates())
+            .collect();
+        if receivers.is_empty() {
+            return None;
+        }
+        let (sender, updates) = broadcast::channel(UPDATES_CAPACITY);
+        for mut receiver in receivers {
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(uri) => {
+                            if sender.send(uri).is_err() {
+                                return;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            debug!("Dropped {} resource updates", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    }
+                }
+            });
+        }
+        Some(updates)
+    }
+}
+
+/// Encodes where a page of the mounts starts: at the provider mounted `index`-th, with its own cursor
+fn encode_cursor(index: usize, inner: Option<&str>) -> String {
+    URL_SAFE_NO_PAD.encode(format!(
+        "{}{}:{}",
+        CURSOR_PREFIX,
+        index,
+        inner.unwrap_or_default()
+    ))
+}
+
+/// Decodes a cursor created by [`encode_cursor`]
+fn decode_cursor(cursor: &str) -> Result<(usize, Option<String>), PaginationError> {
+    let decoded = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(|_| PaginationError::InvalidCursor("Cursor is not valid base64"))?;
+    let decoded = String::from_utf8(decoded)
+        .map_err(|_| PaginationError::InvalidCursor("Cursor is not valid UTF-8"))?;
+    let (index, inner) = decoded
+        .strip_prefix(CURSOR_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .ok_or(PaginationError::InvalidCursor("Unknown cursor format"))?;
+    let index = index
+        .parse()
+        .map_err(|_| PaginationError::InvalidCursor("Cursor index is not a number"))?;
+    Ok((index, (!inner.is_empty()).then(|| inner.to_string())))
+}
+
+/// Resources added to a `ServerBuilder` one by one, with its templates and watcher
+///
+/// Mounted under the empty prefix, so it gets the URIs no other provider is
+/// mounted for. Static resources are only listed; reads go to the first
+/// template the URI expands from.
+pub(crate) struct TemplateResources {
+    pub(crate) resources: Arc<RwLock<Vec<Resource>>>,
+    pub(crate) templates: Vec<Box<dyn ResourceTemplateProvider>>,
+    pub(crate) watcher: Option<Box<dyn ResourceWatcher>>,
+}
+
+impl ResourceProvider for TemplateResources {
+    fn list(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Resource>, ResourceError>> + Send + '_>> {
+        let resources = self
+            .resources
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        Box::pin(async move { Ok(resources) })
+    }
+
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        self.templates
+            .iter()
+            .map(|provider| provider.template())
+            .collect()
+    }
+
+    fn read<'a>(
+        &'a self,
+        uri: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>> {
+        Box::pin(async move {
+            // Find the first template the URI expands from
+            let matched = self.templates.iter().find_map(|provider| {
+                let template = provider.template();
+                match UriTemplate::parse(&template.uri_template) {
+                    Ok(template) => template
+                        .match_uri(uri)
+                        .map(|variables| (provider, variables)),
+                    Err(e) => {
+                        error!("Invalid resource template {}: {}", template.name, e);
+                        None
+                    }
+                }
+            });
+            match matched {
+                Some((provider, variables)) => provider.read(uri, variables).await,
+                None => Err(ResourceError::NotFound(uri.to_string())),
+            }
+        })
+    }
+
+    fn subscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        match &self.watcher {
+            Some(watcher) => watcher.watch(uri),
+            None => Err(ResourceError::Unsubscribable(uri.to_string())),
+        }
+    }
+
+    fn unsubscribe(&self, uri: &str) -> Result<(), ResourceError> {
+        match &self.watcher {
+            Some(watcher) => watcher.unwatch(uri),
+            None => Ok(()),
+        }
+    }
+
+    fn updates(&self) -> Option<broadcast::Receiver<String>> {
+        self.watcher.as_ref().map(|watcher| watcher.updates())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Serves a single resource at its URI
+    struct Single(&'static str);
+
+    impl ResourceProvider for Single {
+        fn list(
+            &self,
+        ) -> Pin<Box<dyn Future<Output = Result<Vec<Resource>, ResourceError>> + Send + '_>>
+        {
+            let resource = Resource {
+                name: self.0.to_string(),
+                uri: self.0.to_string(),
+                description: None,
+                mime_type: None,
+                annotations: None,
+            };
+            Box::pin(async move { Ok(vec![resource]) })
+        }
+
+        fn read<'a>(
+            &'a self,
+            uri: &'a str,
+        ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>>
+        {
+            Box::pin(async move {
+                if uri != self.0 {
+                    return Err(ResourceError::NotFound(uri.to_string()));
+                }
+                Ok(ReadResourceResult {
+                    contents: vec![json!({ "uri": uri, "text": self.0 })],
+                    meta: None,
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mounts_route_by_prefix() {
+        let mut mounts = ResourceMounts::new();
+        mounts.mount("mem://", Arc::new(Single("mem://a")));
+        mounts.mount("mem://notes/", Arc::new(Single("mem://notes/b")));
+
+        let listed: Vec<_> = mounts.list().await.into_iter().map(|r| r.uri).collect();
+        assert_eq!(listed, vec!["mem://a", "mem://notes/b"]);
+
+        // The longest prefix wins
+        let notes = mounts.provider("mem://notes/b").unwrap();
+        assert!(notes.read("mem://notes/b").await.is_ok());
+        let other = mounts.provider("mem://a").unwrap();
+        let missing = other.read("mem://b").await.unwrap_err();
+        assert!(matches!(missing, ResourceError::NotFound(_)));
+        assert!(mounts.provider("s3://bucket/key").is_none());
+
+        assert!(!mounts.subscribable());
+        assert!(other.subscribe("mem://a").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mounts_list_pages() {
+        let mut mounts = ResourceMounts::new();
+        for uri in ["a://1", "b://1", "c://1"] {
+            mounts.mount(uri, Arc::new(Single(uri)));
+        }
+
+        let (page, next) = mounts.list_page(None, 2).await.unwrap();
+        assert_eq!(page.len(), 2);
+        let (page, next) = mounts.list_page(next.as_deref(), 2).await.unwrap();
+        assert_eq!(page[0].uri, "c://1");
+        assert!(next.is_none());
+
+        assert!(mounts.list_page(Some("bogus"), 2).await.is_err());
+        let past = encode_cursor(3, None);
+        assert!(mounts.list_page(Some(&past), 2).await.is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use crate::error::{ResourceError, UriTemplateError};
+use crate::schema::{ReadResourceResult, ResourceTemplate};
+use percent_encoding::percent_decode_str;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Characters RFC 6570 leaves unencoded in reserved (`+` and `#`) expansion
+const RESERVED: &str = ":/?#[]@!$&'()*+,;=";
+
+/// Supplies resources whose URIs are described by an RFC 6570 URI template
+///
+/// Listed by `resources/templates/list`. A `resources/read` for a URI that
+/// matches the template is passed to `read` along with the variables the URI
+/// was expanded from.
+pub trait ResourceTemplateProvider: Send + Sync {
+    /// Returns the template definition advertised to clients
+    fn template(&self) -> ResourceTemplate;
+
+    /// Reads the resource at `uri`
+    ///
+    /// # Arguments
+    /// * `uri` - The URI the client asked for
+    /// * `variables` - The template variables the URI was expanded from
+    fn read<'a>(
+        &'a self,
+        uri: &'a str,
+        variables: HashMap<String, String>,
+    ) -> Pin<Box<dyn Future<Output = Result<ReadResourceResult, ResourceError>> + Send + 'a>>;
+}
+
+/// Expression operators defined by RFC 6570, section 3.2
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Simple,
+    Reserved,
+    Fragment,
+    Label,
+    Path,
+    PathParameter,
+    Query,
+    QueryContinuation,
+}
+
+impl Operator {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(Self::Reserved),
+            '#' => Some(Self::Fragment),
+            '.' => Some(Self::Label),
+            '/' => Some(Self::Path),
+            ';' => Some(Self::PathParameter),
+            '?' => Some(Self::Query),
+            '&' => Some(Self::QueryContinuation),
+            _ => None,
+        }
+    }
+
+    /// Prefix written before the first defined variable
+    fn first(self) -> &'static str {
+        match self {
+            Self::Simple | Self::Reserved => "",
+            Self::Fragment => "#",
+            Self::Label => ".",
+            Self::Path => "/",
+            Self::PathParameter => ";",
+            Self::Query => "?",
+            Self::QueryContinuation => "&",
+        }
+    }
+
+    /// Separator written between defined variables
+    fn separator(self) -> char {
+        match self {
+            Self::Simple | Self::Reserved | Self::Fragment => ',',
+            Self::Label => '.',
+            Self::Path => '/',
+            Self::PathParameter => ';',
+            Self::Query | Self::QueryContinuation => '&',
+        }
+    }
+
+    /// Whether variables are written as `name=value` pairs
+    fn named(self) -> bool {
+        matches!(
+            self,
+            Self::PathParameter | Self::Query | Self::QueryContinuation
+        )
+    }
+
+    /// Whether reserved characters are written without encoding
+    fn allows_reserved(self) -> bool {
+        matches!(self, Self::Reserved | Self::Fragment)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct VarSpec {
+    name: String,
+    /// Maximum number of characters to expand, from a `:N` modifier
+    prefix: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Expression {
+        operator: Operator,
+        variables: Vec<VarSpec>,
+    },
+}
+
+/// A parsed RFC 6570 URI template
+///
+/// Supports every level 4 operator for string values. The explode modifier
+/// (`*`) is accepted but has no effect, since values are never lists.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UriTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+impl UriTemplate {
+    pub fn parse(template: &str) -> Result<Self, UriTemplateError> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while !rest.is_empty() {
+            match rest.find('{') {
+                Some(0) => {
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| UriTemplateError::Unclosed(template.to_string()))?;
+                    parts.push(parse_expression(&rest[1..end], template)?);
+                    rest = &rest[end + 1..];
+                }
+                Some(start) => {
+                    parts.push(parse_literal(&rest[..start], template)?);
+                    rest = &rest[start..];
+                }
+                None => {
+                    parts.push(parse_literal(rest, template)?);
+                    rest = "";
+                }
+            }
+        }
+
+        Ok(Self {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Names of every variable in the template, in order of appearance
+    pub fn variables(&self) -> Vec<&str> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Expression { variables, .. } => Some(variables),
+                Part::Literal(_) => None,
+            })
+            .flatten()
+            .map(|var| var.name.as_str())
+            .collect()
+    }
+
+    /// Expands the template, leaving out variables that aren't in `variables`
+    pub fn expand(&self, variables: &HashMap<String, String>) -> String {
+        let mut uri = String::new();
+
+        for part in &self.parts {
+            let (operator, specs) = match part {
+                Part::Literal(literal) => {
+                    uri.push_str(literal);
+                    continue;
+                }
+                Part::Expression {
+                    operator,
+                    variables,
+                } => (*operator, variables),
+            };
+
+            let defined = specs
+                .iter()
+                .filter_map(|spec| variables.get(&spec.name).map(|value| (spec, value)));
+            for (i, (spec, value)) in defined.enumerate() {
+                if i == 0 {
+                    uri.push_str(operator.first());
+                } else {
+                    uri.push(operator.separator());
+                }
+
+                let value = match spec.prefix {
+                    Some(length) => value.chars().take(length).collect(),
+                    None => value.clone(),
+                };
+                if operator.named() {
+                    uri.push_str(&spec.name);
+                    if value.is_empty() && operator == Operator::PathParameter {
+                        continue;
+                    }
+                    uri.push('=');
+                }
+                uri.push_str(&encode(&value, operator.allows_reserved()));
+            }
+        }
+
+        uri
+    }
+
+    /// Recovers the variables `uri` was expanded from, if it matches the template
+    ///
+    /// Matching is the best-effort inverse of [`UriTemplate::expand`]: each
+    /// expression takes everything up to the next literal or operator prefix,
+    /// so two simple expressions in a row can't be told apart.
+    pub fn match_uri(&self, uri: &str) -> Option<HashMap<String, String>> {
+        let mut variables = HashMap::new();
+        let mut rest = uri;
+
+        for (i, part) in self.parts.iter().enumerate() {
+            match part {
+                Part::Literal(literal) => {
+                    rest = rest.strip_prefix(literal.as_str())?;
+                }
+                Part::Expression {
+                    operator,
+                    variables: specs,
+                } => {
+                    let end = match self.parts.get(i + 1) {
+                        Some(Part::Literal(literal)) => rest.find(literal.as_str())?,
+                        Some(Part::Expression { operator, .. }) if !operator.first().is_empty() => {
+                            rest.find(operator.first()).unwrap_or(rest.len())
+                        }
+                        _ => rest.len(),
+                    };
+                    match_expression(*operator, specs, &rest[..end], &mut variables)?;
+                    rest = &rest[end..];
+                }
+            }
+        }
+
+        rest.is_empty().then_some(variables)
+    }
+}
+
+impl fmt::Display for UriTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+fn parse_literal(literal: &str, template: &str) -> Result<Part, UriTemplateError> {
+    if literal.contains('}') {
+        return Err(UriTemplateError::UnexpectedClose(template.to_string()));
+    }
+    Ok(Part::Literal(literal.to_string()))
+}
+
+fn parse_expression(expression: &str, template: &str) -> Result<Part, UriTemplateError> {
+    let mut chars = expression.chars();
+    let (operator, list) = match chars.next().and_then(Operator::from_char) {
+        Some(operator) => (operator, chars.as_str()),
+        None => (Operator::Simple, expression),
+    };
+
+    let variables = list
+        .split(',')
+        .map(|spec| {
+            let (name, prefix) = match spec.split_once(':') {
+                Some((name, length)) => {
+                    let length = length
+                        .parse()
+                        .map_err(|_| UriTemplateError::InvalidPrefix(template.to_string()))?;
+                    (name, Some(length))
+                }
+                None => (spec.strip_suffix('*').unwrap_or(spec), None),
+            };
+
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '%');
+            if !valid {
+                return Err(UriTemplateError::InvalidVariable(template.to_string()));
+            }
+
+            Ok(VarSpec {
+                name: name.to_string(),
+                prefix,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Part::Expression {
+        operator,
+        variables,
+    })
+}
+
+fn match_expression(
+    operator: Operator,
+    specs: &[VarSpec],
+    text: &str,
+    variables: &mut HashMap<String, String>,
+) -> Option<()> {
+    // Expressions whose variables are all undefined expand to nothing
+    if text.is_empty() {
+        return Some(());
+    }
+    let text = text.strip_prefix(operator.first())?;
+
+    // Without reserved expansion these would have been percent-encoded
+    let encoded = |c: char| "/?#".contains(c) && c != operator.separator();
+    if !operator.allows_reserved() && text.chars().any(encoded) {
+        return None;
+    }
+
+    let values: Vec<&str> = if specs.len() == 1 && !operator.named() {
+        vec![text]
+    } else {
+        text.split(operator.separator()).collect()
+    };
+
+    if operator.named() {
+        for value in values {
+            let (name, value) = value.split_once('=').unwrap_or((value, ""));
+            if specs.iter().any(|spec| spec.name == name) {
+                variables.insert(name.to_string(), decode(value)?);
+            }
+        }
+    } else {
+        if values.len() > specs.len() {
+            return None;
+        }
+        for (spec, value) in specs.iter().zip(values) {
+            variables.insert(spec.name.clone(), decode(value)?);
+        }
+    }
+
+    Some(())
+}
+
+fn encode(value: &str, allow_reserved: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    let bytes = value.as_bytes();
+
+    for (i, c) in value.char_indices() {
+        let unreserved = c.is_ascii_alphanumeric() || "-._~".contains(c);
+        let reserved = allow_reserved && RESERVED.contains(c);
+        // Reserved expansion keeps existing percent-encoded triplets intact
+        let triplet = allow_reserved
+            && c == '%'
+            && bytes.len() > i + 2
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit();
+
+        if unreserved || reserved || triplet {
+            encoded.push(c);
+        } else {
+            let mut buffer = [0; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+
+    encoded
+}
+
+fn decode(value: &str) -> Option<String> {
+    percent_decode_str(value)
+        .decode_utf8()
+        .ok()
+        .map(|value| value.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_operators() {
+        let vars = variables(&[
+            ("var", "value"),
+            ("hello", "Hello World!"),
+            ("path", "/foo/bar"),
+            ("x", "1024"),
+            ("y", "768"),
+            ("empty", ""),
+        ]);
+
+        let cases = [
+            ("{var}", "value"),
+            ("{hello}", "Hello%20World%21"),
+            ("{+hello}", "Hello%20World!"),
+            ("{+path}/here", "/foo/bar/here"),
+            ("{#path}", "#/foo/bar"),
+            ("{.x,y}", ".1024.768"),
+            ("{/var,x}/here", "/value/1024/here"),
+            ("{;x,y,empty}", ";x=1024;y=768;empty"),
+            ("{?x,y,empty}", "?x=1024&y=768&empty="),
+            ("?fixed=yes{&x}", "?fixed=yes&x=1024"),
+            ("{var:3}", "val"),
+            ("{?undefined}", ""),
+            ("file:///{+path}", "file:////foo/bar"),
+        ];
+
+        for (template, expected) in cases {
+            let template = UriTemplate::parse(template).unwrap();
+            assert_eq!(template.expand(&vars), expected, "{}", template);
+        }
+    }
+
+    #[test]
+    fn test_match_uri() {
+        let template = UriTemplate::parse("file:///{+path}").unwrap();
+        assert_eq!(
+            template.match_uri("file:///docs/readme%20first.md"),
+            Some(variables(&[("path", "docs/readme first.md")]))
+        );
+        assert_eq!(template.match_uri("https://example.com"), None);
+
+        let template = UriTemplate::parse("users://{id}/posts/{post}").unwrap();
+        assert_eq!(
+            template.match_uri("users://42/posts/7"),
+            Some(variables(&[("id", "42"), ("post", "7")]))
+        );
+        assert_eq!(template.match_uri("users://42/posts/7/extra"), None);
+        assert_eq!(template.match_uri("users://a/b/posts/7"), None);
+
+        let template = UriTemplate::parse("search://items{?q,limit}").unwrap();
+        assert_eq!(
+            template.match_uri("search://items?limit=5&q=rust%20mcp"),
+            Some(variables(&[("q", "rust mcp"), ("limit", "5")]))
+        );
+        assert_eq!(template.match_uri("search://items"), Some(HashMap::new()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let template = UriTemplate::parse("notes://{folder}/{name}{?tag}").unwrap();
+        let vars = variables(&[("folder", "work"), ("name", "a&b c"), ("tag", "x")]);
+        let uri = template.expand(&vars);
+        assert_eq!(uri, "notes://work/a%26b%20c?tag=x");
+        assert_eq!(template.match_uri(&uri), Some(vars));
+        assert_eq!(template.variables(), vec!["folder", "name", "tag"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(UriTemplate::parse("file:///{path").is_err());
+        assert!(UriTemplate::parse("file:///path}").is_err());
+        assert!(UriTemplate::parse("file:///{}").is_err());
+        assert!(UriTemplate::parse("file:///{path:abc}").is_err());
+    }
+}
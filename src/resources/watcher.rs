@@ -0,0 +1,203 @@
+use crate::error::ResourceError;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, error};
+use url::Url;
+
+/// Watches resources so clients subscribed to them can be told when they change
+///
+/// The server calls `watch` when the first client subscribes to a URI and
+/// `unwatch` when the last one unsubscribes. Changed URIs are reported on the
+/// channel returned by `updates`.
+pub trait ResourceWatcher: Send + Sync {
+    /// Starts watching the resource at `uri`
+    fn watch(&self, uri: &str) -> Result<(), ResourceError>;
+
+    /// Stops watching the resource at `uri`
+    fn unwatch(&self, uri: &str) -> Result<(), ResourceError>;
+
+    /// Receives the URI of every watched resource that changes
+    fn updates(&self) -> broadcast::Receiver<String>;
+}
+
+#[derive(Default)]
+struct WatchedFiles {
+    /// Watched file paths and the URI the client subscribed with
+    files: HashMap<PathBuf, String>,
+    /// Number of watched files in each watched directory
+    directories: HashMap<PathBuf, usize>,
+}
+
+/// Watches `file://` resources for modification using OS file notifications
+///
+/// The parent directory of each file is watched rather than the file itself,
+/// so files replaced by an atomic rename are still tracked.
+pub struct FileWatcher {
+    watcher: Mutex<RecommendedWatcher>,
+    watched: Arc<Mutex<WatchedFiles>>,
+    updates: broadcast::Sender<String>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Result<Self, ResourceError> {
+        let (updates, _) = broadcast::channel(64);
+        let watched = Arc::new(Mutex::new(WatchedFiles::default()));
+
+        let sender = updates.clone();
+        let files = watched.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    error!("File watcher error: {}", e);
+                    return;
+                }
+            };
+            if !matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                return;
+            }
+
+            let files = files.lock().unwrap_or_else(|e| e.into_inner());
+            for path in &event.paths {
+                if let Some(uri) = files.files.get(path) {
+                    debug!("Resource changed: {}", uri);
+                    let _ = sender.send(uri.clone());
+                }
+            }
+        })
+        .map_err(ResourceError::failed)?;
+
+        Ok(Self {
+            watcher: Mutex::new(watcher),
+            watched,
+            updates,
+        })
+    }
+}
+
+fn file_path(uri: &str) -> Result<PathBuf, ResourceError> {
+    let url = Url::parse(uri)
+        .map_err(|e| ResourceError::failed(format!("Invalid resource URI {}: {}", uri, e)))?;
+    if url.scheme() != "file" {
+        return Err(ResourceError::failed(format!(
+            "Only file:// resources can be watched: {}",
+            uri
+        )));
+    }
+    url.to_file_path()
+        .map_err(|_| ResourceError::failed(format!("Invalid file URI: {}", uri)))
+}
+
+fn parent_directory(path: &Path) -> PathBuf {
+    path.parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("/"))
+}
+
+impl ResourceWatcher for FileWatcher {
+    fn watch(&self, uri: &str) -> Result<(), ResourceError> {
+        let path = file_path(uri)?;
+        let directory = parent_directory(&path);
+
+        let mut watched = self.watched.lock().unwrap_or_else(|e| e.into_inner());
+        if watched.files.contains_key(&path) {
+            return Ok(());
+        }
+
+        if !watched.directories.contains_key(&directory) {
+            self.watcher
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .watch(&directory, RecursiveMode::NonRecursive)
+                .map_err(|e| {
+                    ResourceError::failed(format!("Failed to watch {}: {}", directory.display(), e))
+                })?;
+        }
+
+        *watched.directories.entry(directory).or_default() += 1;
+        watched.files.insert(path, uri.to_string());
+        debug!("Watching resource: {}", uri);
+        Ok(())
+    }
+
+    fn unwatch(&self, uri: &str) -> Result<(), ResourceError> {
+        let path = file_path(uri)?;
+        let directory = parent_directory(&path);
+
+        let mut watched = self.watched.lock().unwrap_or_else(|e| e.into_inner());
+        if watched.files.remove(&path).is_none() {
+            return Ok(());
+        }
+
+        let remaining = watched.directories.get_mut(&directory).map(|count| {
+            *count -= 1;
+            *count
+        });
+        if remaining == Some(0) {
+            watched.directories.remove(&directory);
+            self.watcher
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .unwatch(&directory)
+                .map_err(|e| {
+                    ResourceError::failed(format!(
+                        "Failed to unwatch {}: {}",
+                        directory.display(),
+                        e
+                    ))
+                })?;
+        }
+
+        debug!("Stopped watching resource: {}", uri);
+        Ok(())
+    }
+
+    fn updates(&self) -> broadcast::Receiver<String> {
+        self.updates.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_file_watcher_reports_changes() {
+        let directory = std::env::temp_dir().join(format!("bioma-watch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let directory = directory.canonicalize().unwrap();
+        let watched = directory.join("watched.txt");
+        let other = directory.join("other.txt");
+        std::fs::write(&watched, "one").unwrap();
+
+        let watcher = FileWatcher::new().unwrap();
+        let mut updates = watcher.updates();
+        let uri = Url::from_file_path(&watched).unwrap().to_string();
+        watcher.watch(&uri).unwrap();
+
+        std::fs::write(&other, "ignored").unwrap();
+        std::fs::write(&watched, "two").unwrap();
+
+        let updated = tokio::time::timeout(Duration::from_secs(5), updates.recv())
+            .await
+            .expect("Should be notified of the change")
+            .unwrap();
+        assert_eq!(updated, uri);
+
+        watcher.unwatch(&uri).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_file_watcher_rejects_other_schemes() {
+        let watcher = FileWatcher::new().unwrap();
+        assert!(watcher.watch("https://example.com/page").is_err());
+    }
+}
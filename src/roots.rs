@@ -0,0 +1,159 @@
+use crate::error::RouterError;
+use crate::router::RequestRouter;
+use crate::schema::{ListRootsResult, Root};
+use crate::session::Sessions;
+use crate::transport::ConnectionId;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, error, info};
+use url::Url;
+
+/// Roots each client has exposed to the server with `roots/list`
+///
+/// Refreshed when a client finishes initializing and whenever it sends
+/// `notifications/roots/list_changed`. Clients without the `roots`
+/// capability have no roots.
+#[derive(Clone, Default)]
+pub struct RootsState {
+    inner: Arc<RwLock<HashMap<ConnectionId, Vec<Root>>>>,
+}
+
+impl RootsState {
+    /// The roots last reported by the client on `connection`
+    pub fn get(&self, connection: &ConnectionId) -> Vec<Root> {
+        self.inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(connection)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Local directories of the client's `file://` roots
+    pub fn paths(&self, connection: &ConnectionId) -> Vec<PathBuf> {
+        self.get(connection)
+            .iter()
+            .filter_map(|root| Url::parse(&root.uri).ok()?.to_file_path().ok())
+            .collect()
+    }
+
+    pub fn set(&self, connection: &ConnectionId, roots: Vec<Root>) {
+        self.inner
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(connection.clone(), roots);
+    }
+
+    pub fn remove(&self, connection: &ConnectionId) -> Option<Vec<Root>> {
+        self.inner
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(connection)
+    }
+
+    /// Asks the client for its roots and caches them
+    ///
+    /// Does nothing if the client didn't advertise the `roots` capability.
+    pub async fn refresh(
+        &self,
+        connection: &ConnectionId,
+        sessions: &Sessions,
+        router: &RequestRouter,
+    ) -> Result<(), RouterError> {
+        let supported = sessions
+            .get(connection)
+            .is_some_and(|session| session.client_capabilities.roots.is_some());
+        if !supported {
+            debug!("Client {} does not expose roots", connection);
+            return Ok(());
+        }
+
+        let result = router
+            .request(connection, "roots/list", serde_json::json!({}))
+            .await?;
+        let result: ListRootsResult = serde_json::from_value(result)?;
+
+        info!("Client {} has {} roots", connection, result.roots.len());
+        self.set(connection, result.roots);
+        Ok(())
+    }
+
+    /// Refreshes the client's roots in the background
+    pub fn spawn_refresh(
+        &self,
+        connection: ConnectionId,
+        sessions: Sessions,
+        router: RequestRouter,
+    ) {
+        let roots = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = roots.refresh(&connection, &sessions, &router).await {
+                error!("Failed to list roots of {}: {}", connection, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ClientCapabilitiesRoots;
+    use serde_json::Value;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_refresh_roots() {
+        let (outgoing, mut outgoing_rx) = mpsc::channel(8);
+        let router = RequestRouter::new(outgoing);
+        let sessions = Sessions::default();
+        let roots = RootsState::default();
+        let connection = ConnectionId::from("client");
+
+        // Nothing is requested from clients without the capability
+        roots
+            .refresh(&connection, &sessions, &router)
+            .await
+            .unwrap();
+        assert!(outgoing_rx.try_recv().is_err());
+
+        sessions.update(&connection, |session| {
+            session.client_capabilities.roots = Some(ClientCapabilitiesRoots {
+                list_changed: Some(true),
+            });
+        });
+
+        let client = {
+            let router = router.clone();
+            tokio::spawn(async move {
+                let sent = outgoing_rx.recv().await.unwrap();
+                let request: Value = serde_json::from_str(&sent.message).unwrap();
+                assert_eq!(request["method"], "roots/list");
+                let reply = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": {
+                        "roots": [
+                            { "uri": "file:///home/user/project", "name": "project" },
+                            { "uri": "https://example.com/repo" },
+                        ],
+                    },
+                });
+                router.handle_response(&sent.connection, &reply).unwrap();
+            })
+        };
+
+        roots
+            .refresh(&connection, &sessions, &router)
+            .await
+            .unwrap();
+        client.await.unwrap();
+
+        assert_eq!(roots.get(&connection).len(), 2);
+        assert_eq!(
+            roots.paths(&connection),
+            vec![PathBuf::from("/home/user/project")]
+        );
+        assert!(roots.get(&ConnectionId::from("other")).is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::error::RouterError;
+use crate::message::JsonRpcMessage;
+use crate::schema::RequestId;
+use crate::transport::{ConnectionId, TransportMessage};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error};
+
+/// How long to wait for a client to answer a server-initiated request
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+type PendingRequests = Arc<
+    Mutex<HashMap<(ConnectionId, RequestId), oneshot::Sender<Result<Value, jsonrpc_core::Error>>>>,
+>;
+
+/// Routes requests the server sends to clients and the responses they send back
+///
+/// Outgoing requests are written to the same channel as every other server
+/// message. Responses are matched to the request they answer by connection
+/// and JSON-RPC ID.
+#[derive(Clone)]
+pub struct RequestRouter {
+    next_id: Arc<AtomicU64>,
+    pending: PendingRequests,
+    outgoing: mpsc::Sender<TransportMessage>,
+    timeout: Duration,
+}
+
+impl RequestRouter {
+    pub fn new(outgoing: mpsc::Sender<TransportMessage>) -> Self {
+        Self {
+            next_id: Arc::new(AtomicU64::new(0)),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            outgoing,
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends a request to the client on `connection` and waits for its result
+    pub async fn request(
+        &self,
+        connection: &ConnectionId,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, RouterError> {
+        let id = RequestId::Number(self.next_id.fetch_add(1, Ordering::Relaxed) as i64);
+        let key = (connection.clone(), id.clone());
+
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.clone(), sender);
+
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        debug!("Sending {} request {} to {}", method, id, connection);
+
+        let sent = self
+            .outgoing
+            .send(TransportMessage {
+                connection: connection.clone(),
+                message: request.to_string(),
+            })
+            .await;
+        if sent.is_err() {
+            self.forget(&key);
+            return Err(RouterError::Closed);
+        }
+
+        let result = tokio::time::timeout(self.timeout, receiver).await;
+        self.forget(&key);
+        match result {
+            Ok(Ok(Ok(value))) => Ok(value),
+            Ok(Ok(Err(e))) => Err(RouterError::Rpc(e)),
+            Ok(Err(_)) => Err(RouterError::Dropped(method.to_string())),
+            Err(_) => Err(RouterError::Timeout(method.to_string())),
+        }
+    }
+
+    /// Delivers a client response to the request awaiting it
+    pub fn handle_response(
+        &self,
+        connection: &ConnectionId,
+        message: &Value,
+    ) -> Result<(), RouterError> {
+        let id: RequestId = message
+            .get("id")
+            .and_then(|id| serde_json::from_value(id.clone()).ok())
+            .ok_or(RouterError::InvalidId)?;
+        let key = (connection.clone(), id.clone());
+
+        let sender = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&key)
+            .ok_or_else(|| RouterError::NotPending {
+                id,
+                connection: connection.clone(),
+            })?;
+
+        let result = match message.get("error") {
+            Some(error) => Err(serde_json::from_value(error.clone()).unwrap_or_else(|e| {
+                error!("Malformed error response: {}", e);
+                jsonrpc_core::Error::internal_error()
+            })),
+            None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+        };
+        let _ = sender.send(result);
+        Ok(())
+    }
+
+    /// Drops every request waiting on `connection`, failing them at once
+    pub fn cancel(&self, connection: &ConnectionId) {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|(pending, _), _| pending != connection);
+    }
+
+    fn forget(&self, key: &(ConnectionId, RequestId)) {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key);
+    }
+}
+
+impl Default for RequestRouter {
+    /// A router not attached to any server, whose requests always fail
+    fn default() -> Self {
+        Self::new(mpsc::channel(1).0)
+    }
+}
+
+/// Parses `message` if it is a JSON-RPC response rather than a request or notification
+pub fn as_response(message: &str) -> Option<Value> {
+    match JsonRpcMessage::parse(message) {
+        Ok(JsonRpcMessage::Response(response)) => Some(response),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_response() {
+        assert!(as_response(r#"{"jsonrpc":"2.0","id":1,"result":{}}"#).is_some());
+        assert!(
+            as_response(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-1,"message":"x"}}"#).is_some()
+        );
+        assert!(as_response(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).is_none());
+        assert!(as_response(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).is_none());
+        assert!(as_response("not json").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_request_round_trip() {
+        let (outgoing, mut outgoing_rx) = mpsc::channel(8);
+        let router = RequestRouter::new(outgoing);
+        let connection = ConnectionId::from("client");
+
+        let client = {
+            let router = router.clone();
+            tokio::spawn(async move {
+                let sent = outgoing_rx.recv().await.unwrap();
+                let request: Value = serde_json::from_str(&sent.message).unwrap();
+                assert_eq!(request["method"], "sampling/createMessage");
+
+                // A response on another connection doesn't answer it
+                let reply =
+                    serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": "ok" });
+                assert!(router
+                    .handle_response(&ConnectionId::from("other"), &reply)
+                    .is_err());
+                router.handle_response(&sent.connection, &reply).unwrap();
+
+                let sent = outgoing_rx.recv().await.unwrap();
+                let request: Value = serde_json::from_str(&sent.message).unwrap();
+                let reply = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": { "code": -32601, "message": "Method not found" },
+                });
+                router.handle_response(&sent.connection, &reply).unwrap();
+            })
+        };
+
+        let result = router
+            .request(&connection, "sampling/createMessage", Value::Null)
+            .await
+            .unwrap();
+        assert_eq!(result, "ok");
+
+        let error = router
+            .request(&connection, "roots/list", Value::Null)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Method not found"));
+        client.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let (outgoing, _outgoing_rx) = mpsc::channel(8);
+        let router = RequestRouter::new(outgoing).with_timeout(Duration::from_millis(10));
+        let error = router
+            .request(&ConnectionId::from("client"), "ping", Value::Null)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Timed out"));
+        assert!(router.pending.lock().unwrap().is_empty());
+
+        let detached = RequestRouter::default();
+        assert!(detached
+            .request(&ConnectionId::from("client"), "ping", Value::Null)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_on_disconnect() {
+        let (outgoing, mut outgoing_rx) = mpsc::channel(8);
+        let router = RequestRouter::new(outgoing);
+
+        let waiting = {
+            let router = router.clone();
+            tokio::spawn(async move {
+                router
+                    .request(&ConnectionId::from("gone"), "roots/list", Value::Null)
+                    .await
+            })
+        };
+        outgoing_rx.recv().await.unwrap();
+        router.cancel(&ConnectionId::from("gone"));
+
+        let error = waiting.await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("dropped"));
+        assert!(router.pending.lock().unwrap().is_empty());
+    }
+}
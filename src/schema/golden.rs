@@ -0,0 +1,264 @@
+//! Round trips of every wire type through serde against the JSON fixtures
+//! under `tests/fixtures/schema`, modeled on the examples of the MCP spec
+//!
+//! A fixture that no longer reads back as itself means a field was renamed
+//! or dropped when the types were regenerated. Which of its fields each type
+//! can't do without is kept in a golden file, so fields turning optional or
+//! required show up there; set `BIOMA_UPDATE_GOLDEN` to accept such changes.
+
+use super::{v2024_11_05, v2025_03_26, v2025_06_18};
+use crate::testing::assert_golden;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/schema")
+}
+
+/// Checks the fixture of `T` reads back as itself, returning the fields it can't do without
+fn round_trip<T: DeserializeOwned + Serialize>(version: &str, name: &str) -> Vec<String> {
+    let path = fixtures().join(version).join(format!("{}.json", name));
+    let fixture = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read fixture {}: {}", path.display(), e));
+    let fixture: Value = serde_json::from_str(&fixture)
+        .unwrap_or_else(|e| panic!("Fixture {} isn't JSON: {}", path.display(), e));
+
+    let value: T = serde_json::from_value(fixture.clone())
+        .unwrap_or_else(|e| panic!("{} {} doesn't read its fixture: {}", version, name, e));
+    let written = serde_json::to_value(&value).unwrap();
+    assert!(
+        normalize(&written) == normalize(&fixture),
+        "{} {} doesn't write its fixture back\nexpected: {}\nactual: {}",
+        version,
+        name,
+        fixture,
+        written
+    );
+
+    let Value::Object(object) = fixture else {
+        return Vec::new();
+    };
+    object
+        .keys()
+        .filter(|key| {
+            let mut without = object.clone();
+            without.remove(*key);
+            serde_json::from_value::<T>(Value::Object(without)).is_err()
+        })
+        .cloned()
+        .collect()
+}
+
+/// `value` with every number as a float, since `50` and `50.0` are the same on the wire
+fn normalize(value: &Value) -> Value {
+    match value {
+        Value::Number(number) => number
+            .as_f64()
+            .and_then(serde_json::Number::from_f64)
+            .map_or_else(|| value.clone(), Value::Number),
+        Value::Array(values) => Value::Array(values.iter().map(normalize).collect()),
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| (key.clone(), normalize(value)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Names of the types `source` declares
+fn declared(source: &str) -> BTreeSet<&str> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let rest = line
+                .strip_prefix("pub struct ")
+                .or_else(|| line.strip_prefix("pub enum "))?;
+            rest.split(|c: char| !c.is_alphanumeric() && c != '_')
+                .next()
+        })
+        .collect()
+}
+
+macro_rules! round_trips {
+    ($module:ident, $version:literal, [$($name:ident),* $(,)?]) => {{
+        let mut required = BTreeMap::new();
+        $(
+            required.insert(
+                stringify!($name),
+                round_trip::<$module::$name>($version, stringify!($name)),
+            );
+        )*
+        let declared = declared(include_str!(concat!(stringify!($module), ".rs")));
+        let checked: BTreeSet<&str> = required.keys().copied().collect();
+        assert_eq!(
+            declared.difference(&checked).collect::<Vec<_>>(),
+            Vec::<&&str>::new(),
+            "Types of {} without a round trip",
+            $version
+        );
+        assert_golden(&required, fixtures().join(concat!("required-", $version, ".json")));
+    }};
+}
+
+#[test]
+fn test_schema_round_trips() {
+    round_trips!(
+        v2025_06_18,
+        "2025-06-18",
+        [
+            AnnotatedAnnotations,
+            Annotated,
+            AudioContentAnnotations,
+            AudioContent,
+            BlobResourceContents,
+            CallToolRequestParams,
+            CallToolRequest,
+            CallToolResult,
+            CancelledNotificationParams,
+            CancelledNotification,
+            ClientCapabilitiesRoots,
+            ClientCapabilities,
+            CompleteRequestParamsArgument,
+            CompleteRequestParams,
+            CompleteRequest,
+            CompleteResultCompletion,
+            CompleteResult,
+            Content,
+            CreateMessageRequestParams,
+            CreateMessageRequest,
+            CreateMessageResult,
+            ElicitRequestParamsRequestedSchema,
+            ElicitRequestParams,
+            ElicitResultAction,
+            ElicitResult,
+            EmbeddedResourceAnnotations,
+            EmbeddedResource,
+            EmptyResult,
+            GetPromptRequestParams,
+            GetPromptRequest,
+            GetPromptResult,
+            ImageContentAnnotations,
+            ImageContent,
+            Implementation,
+            InitializeRequestParams,
+            InitializeRequest,
+            InitializeResult,
+            InitializedNotificationParams,
+            InitializedNotification,
+            JsonrpcerrorError,
+            Jsonrpcerror,
+            JsonrpcnotificationParams,
+            Jsonrpcnotification,
+            JsonrpcrequestParamsMeta,
+            JsonrpcrequestParams,
+            Jsonrpcrequest,
+            Jsonrpcresponse,
+            ListPromptsRequestParams,
+            ListPromptsRequest,
+            ListPromptsResult,
+            ListResourceTemplatesRequestParams,
+            ListResourceTemplatesRequest,
+            ListResourceTemplatesResult,
+            ListResourcesRequestParams,
+            ListResourcesRequest,
+            ListResourcesResult,
+            ListRootsRequestParamsMeta,
+            ListRootsRequestParams,
+            ListRootsRequest,
+            ListRootsResult,
+            ListToolsRequestParams,
+            ListToolsRequest,
+            ListToolsResult,
+            LoggingLevel,
+            LoggingMessageNotificationParams,
+            LoggingMessageNotification,
+            ModelHint,
+            ModelPreferences,
+            NotificationParams,
+            Notification,
+            PaginatedRequestParams,
+            PaginatedRequest,
+            PaginatedResult,
+            PingRequestParamsMeta,
+            PingRequestParams,
+            PingRequest,
+            ProgressNotificationParams,
+            ProgressNotification,
+            ProgressToken,
+            Prompt,
+            PromptArgument,
+            PromptListChangedNotificationParams,
+            PromptListChangedNotification,
+            PromptMessage,
+            PromptReference,
+            ReadResourceRequestParams,
+            ReadResourceRequest,
+            ReadResourceResult,
+            RequestParamsMeta,
+            RequestParams,
+            Request,
+            RequestId,
+            ResourceAnnotations,
+            Resource,
+            ResourceContents,
+            ResourceListChangedNotificationParams,
+            ResourceListChangedNotification,
+            ResourceReference,
+            ResourceTemplateAnnotations,
+            ResourceTemplate,
+            ResourceUpdatedNotificationParams,
+            ResourceUpdatedNotification,
+            Result,
+            Role,
+            Root,
+            RootsListChangedNotificationParams,
+            RootsListChangedNotification,
+            SamplingMessage,
+            SamplingContent,
+            ServerCapabilitiesPrompts,
+            ServerCapabilitiesPromptsResources,
+            ServerCapabilitiesPromptsResourcesTools,
+            ServerCapabilities,
+            SetLevelRequestParams,
+            SetLevelRequest,
+            SubscribeRequestParams,
+            SubscribeRequest,
+            TextContentAnnotations,
+            TextContent,
+            TextResourceContents,
+            ToolOutputSchema,
+            ToolInputSchema,
+            ToolAnnotations,
+            Tool,
+            ToolListChangedNotificationParams,
+            ToolListChangedNotification,
+            UnsubscribeRequestParams,
+            UnsubscribeRequest,
+        ]
+    );
+    round_trips!(
+        v2025_03_26,
+        "2025-03-26",
+        [CallToolResult, ListToolsResult, Tool]
+    );
+    round_trips!(
+        v2024_11_05,
+        "2024-11-05",
+        [
+            CallToolResult,
+            Content,
+            GetPromptResult,
+            InitializeResult,
+            ListToolsResult,
+            ProgressNotificationParams,
+            PromptMessage,
+            ServerCapabilities,
+            Tool,
+        ]
+    );
+}
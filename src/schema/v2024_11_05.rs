@@ -0,0 +1,278 @@
+//! Shapes of the 2024-11-05 revision that differ from the newest one
+//!
+//! Types the revisions share are re-exported from the newest. Each type here
+//! converts from its newer counterpart, dropping what the revision lacks.
+
+use super::v2025_06_18 as newer;
+use serde::{Deserialize, Serialize};
+
+pub use newer::{
+    EmbeddedResourceAnnotations, ImageContentAnnotations, Implementation, ProgressToken,
+    ResourceContents, Role, ServerCapabilitiesPrompts, ServerCapabilitiesPromptsResources,
+    ServerCapabilitiesPromptsResourcesTools, TextContentAnnotations, ToolInputSchema,
+};
+
+#[doc = " The server's response to a tool call."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct CallToolResult {
+    #[doc = " This result property is reserved by the protocol to allow clients and servers to attach "]
+    #[doc = " additional metadata to their responses."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "_meta")]
+    pub meta: Option<::std::collections::BTreeMap<String, serde_json::Value>>,
+    pub content: Vec<Content>,
+    #[doc = " Whether the tool call ended in an error."]
+    #[doc = " "]
+    #[doc = " If not set, this is assumed to be false (the call was successful)."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "isError")]
+    pub is_error: Option<bool>,
+}
+#[doc = " Content of a tool call result, tagged by its `type`."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum Content {
+    #[serde(rename = "text")]
+    Text {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        annotations: Option<TextContentAnnotations>,
+        #[doc = " The text content of the message."]
+        text: String,
+    },
+    #[serde(rename = "image")]
+    Image {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        annotations: Option<ImageContentAnnotations>,
+        #[doc = " The base64-encoded image data."]
+        data: String,
+        #[doc = " The MIME type of the image. Different providers may support different image types."]
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    #[serde(rename = "resource")]
+    Resource {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        annotations: Option<EmbeddedResourceAnnotations>,
+        resource: ResourceContents,
+    },
+}
+#[doc = " The server's response to a prompts/get request from the client."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct GetPromptResult {
+    #[doc = " This result property is reserved by the protocol to allow clients and servers to attach "]
+    #[doc = " additional metadata to their responses."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "_meta")]
+    pub meta: Option<::std::collections::BTreeMap<String, serde_json::Value>>,
+    #[doc = " An optional description for the prompt."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub messages: Vec<PromptMessage>,
+}
+#[doc = " After receiving an initialize request from the client, the server sends this response."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct InitializeResult {
+    #[doc = " This result property is reserved by the protocol to allow clients and servers to attach "]
+    #[doc = " additional metadata to their responses."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "_meta")]
+    pub meta: Option<::std::collections::BTreeMap<String, serde_json::Value>>,
+    pub capabilities: ServerCapabilities,
+    #[doc = " Instructions describing how to use the server and its features."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    #[doc = " The version of the Model Context Protocol that the server wants to use."]
+    #[serde(rename = "protocolVersion")]
+    pub protocol_version: String,
+    #[serde(rename = "serverInfo")]
+    pub server_info: Implementation,
+}
+#[doc = " The server's response to a tools/list request from the client."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct ListToolsResult {
+    #[doc = " This result property is reserved by the protocol to allow clients and servers to attach "]
+    #[doc = " additional metadata to their responses."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "_meta")]
+    pub meta: Option<::std::collections::BTreeMap<String, serde_json::Value>>,
+    #[doc = " An opaque token representing the pagination position after the last returned result."]
+    #[doc = " If present, there may be more results available."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+    pub tools: Vec<Tool>,
+}
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct ProgressNotificationParams {
+    #[doc = " The progress thus far. This should increase every time progress is made, even if the total "]
+    #[doc = " is unknown."]
+    pub progress: f64,
+    #[doc = " The progress token which was given in the initial request, used to associate this "]
+    #[doc = " notification with the request that is proceeding."]
+    #[serde(rename = "progressToken")]
+    pub progress_token: ProgressToken,
+    #[doc = " Total number of items to process (or total progress required), if known."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+}
+#[doc = " Describes a message returned as part of a prompt."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct PromptMessage {
+    pub content: Content,
+    pub role: Role,
+}
+#[doc = " Capabilities that a server may support."]
+#[derive(Clone, PartialEq, Debug, Default, Deserialize, Serialize)]
+pub struct ServerCapabilities {
+    #[doc = " Experimental, non-standard capabilities that the server supports."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<
+        ::std::collections::BTreeMap<
+            String,
+            ::std::collections::BTreeMap<String, serde_json::Value>,
+        >,
+    >,
+    #[doc = " Present if the server supports sending log messages to the client."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<::std::collections::BTreeMap<String, serde_json::Value>>,
+    #[doc = " Present if the server offers any prompt templates."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<ServerCapabilitiesPrompts>,
+    #[doc = " Present if the server offers any resources to read."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ServerCapabilitiesPromptsResources>,
+    #[doc = " Present if the server offers any tools to call."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<ServerCapabilitiesPromptsResourcesTools>,
+}
+#[doc = " Definition for a tool the client can call."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct Tool {
+    #[doc = " A human-readable description of the tool."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[doc = " A JSON Schema object defining the expected parameters for the tool."]
+    #[serde(rename = "inputSchema")]
+    pub input_schema: ToolInputSchema,
+    #[doc = " The name of the tool."]
+    pub name: String,
+}
+
+impl From<newer::Content> for Content {
+    /// Audio, which this revision can't carry, becomes a note saying so
+    fn from(content: newer::Content) -> Self {
+        match content {
+            newer::Content::Text { annotations, text } => Content::Text { annotations, text },
+            newer::Content::Image {
+                annotations,
+                data,
+                mime_type,
+            } => Content::Image {
+                annotations,
+                data,
+                mime_type,
+            },
+            newer::Content::Audio { mime_type, .. } => Content::Text {
+                annotations: None,
+                text: format!(
+                    "[{} audio omitted: not supported by protocol version 2024-11-05]",
+                    mime_type
+                ),
+            },
+            newer::Content::Resource {
+                annotations,
+                resource,
+            } => Content::Resource {
+                annotations,
+                resource,
+            },
+        }
+    }
+}
+
+impl From<newer::CallToolResult> for CallToolResult {
+    fn from(result: newer::CallToolResult) -> Self {
+        Self {
+            meta: result.meta,
+            content: result.content.into_iter().map(Content::from).collect(),
+            is_error: result.is_error,
+        }
+    }
+}
+
+impl From<newer::PromptMessage> for PromptMessage {
+    fn from(message: newer::PromptMessage) -> Self {
+        Self {
+            content: message.content.into(),
+            role: message.role,
+        }
+    }
+}
+
+impl From<newer::GetPromptResult> for GetPromptResult {
+    fn from(result: newer::GetPromptResult) -> Self {
+        Self {
+            meta: result.meta,
+            description: result.description,
+            messages: result
+                .messages
+                .into_iter()
+                .map(PromptMessage::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<newer::ServerCapabilities> for ServerCapabilities {
+    fn from(capabilities: newer::ServerCapabilities) -> Self {
+        Self {
+            experimental: capabilities.experimental,
+            logging: capabilities.logging,
+            prompts: capabilities.prompts,
+            resources: capabilities.resources,
+            tools: capabilities.tools,
+        }
+    }
+}
+
+impl From<newer::InitializeResult> for InitializeResult {
+    fn from(result: newer::InitializeResult) -> Self {
+        Self {
+            meta: result.meta,
+            capabilities: result.capabilities.into(),
+            instructions: result.instructions,
+            protocol_version: result.protocol_version,
+            server_info: result.server_info,
+        }
+    }
+}
+
+impl From<newer::Tool> for Tool {
+    fn from(tool: newer::Tool) -> Self {
+        Self {
+            description: tool.description,
+            input_schema: tool.input_schema,
+            name: tool.name,
+        }
+    }
+}
+
+impl From<newer::ListToolsResult> for ListToolsResult {
+    fn from(result: newer::ListToolsResult) -> Self {
+        Self {
+            meta: result.meta,
+            next_cursor: result.next_cursor,
+            tools: result.tools.into_iter().map(Tool::from).collect(),
+        }
+    }
+}
+
+impl From<newer::ProgressNotificationParams> for ProgressNotificationParams {
+    fn from(params: newer::ProgressNotificationParams) -> Self {
+        Self {
+            progress: params.progress,
+            progress_token: params.progress_token,
+            total: params.total,
+        }
+    }
+}
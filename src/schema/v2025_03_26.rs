@@ -0,0 +1,90 @@
+//! Shapes of the 2025-03-26 revision that differ from the newest one
+//!
+//! Types the revisions share are re-exported from the newest. Each type here
+//! converts from its newer counterpart, dropping what the revision lacks.
+
+use super::v2025_06_18 as newer;
+use serde::{Deserialize, Serialize};
+
+pub use newer::{
+    Content, GetPromptResult, InitializeResult, ProgressNotificationParams, ToolAnnotations,
+    ToolInputSchema,
+};
+
+#[doc = " The server's response to a tool call."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct CallToolResult {
+    #[doc = " This result property is reserved by the protocol to allow clients and servers to attach "]
+    #[doc = " additional metadata to their responses."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "_meta")]
+    pub meta: Option<::std::collections::BTreeMap<String, serde_json::Value>>,
+    pub content: Vec<Content>,
+    #[doc = " Whether the tool call ended in an error."]
+    #[doc = " "]
+    #[doc = " If not set, this is assumed to be false (the call was successful)."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "isError")]
+    pub is_error: Option<bool>,
+}
+#[doc = " The server's response to a tools/list request from the client."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct ListToolsResult {
+    #[doc = " This result property is reserved by the protocol to allow clients and servers to attach "]
+    #[doc = " additional metadata to their responses."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "_meta")]
+    pub meta: Option<::std::collections::BTreeMap<String, serde_json::Value>>,
+    #[doc = " An opaque token representing the pagination position after the last returned result."]
+    #[doc = " If present, there may be more results available."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+    pub tools: Vec<Tool>,
+}
+#[doc = " Definition for a tool the client can call."]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct Tool {
+    #[doc = " Optional additional tool information."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+    #[doc = " A human-readable description of the tool."]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[doc = " A JSON Schema object defining the expected parameters for the tool."]
+    #[serde(rename = "inputSchema")]
+    pub input_schema: ToolInputSchema,
+    #[doc = " The name of the tool."]
+    pub name: String,
+}
+
+impl From<newer::CallToolResult> for CallToolResult {
+    fn from(result: newer::CallToolResult) -> Self {
+        Self {
+            meta: result.meta,
+            content: result.content,
+            is_error: result.is_error,
+        }
+    }
+}
+
+impl From<newer::Tool> for Tool {
+    fn from(tool: newer::Tool) -> Self {
+        Self {
+            annotations: tool.annotations,
+            description: tool.description,
+            input_schema: tool.input_schema,
+            name: tool.name,
+        }
+    }
+}
+
+impl From<newer::ListToolsResult> for ListToolsResult {
+    fn from(result: newer::ListToolsResult) -> Self {
+        Self {
+            meta: result.meta,
+            next_cursor: result.next_cursor,
+            tools: result.tools.into_iter().map(Tool::from).collect(),
+        }
+    }
+}
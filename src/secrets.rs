@@ -0,0 +1,242 @@
+use crate::error::SecretsError;
+use lazy_static::lazy_static;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Secrets shorter than this aren't redacted, as they'd match ordinary text
+const MIN_REDACTED_LEN: usize = 4;
+
+// Every secret handed out so far, removed from what is logged
+lazy_static! {
+    static ref REDACTED: RwLock<Vec<Arc<str>>> = RwLock::new(Vec::new());
+}
+
+/// A secret such as an API key, kept out of `Debug` output
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(Arc<str>);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(Arc::from(value.into()))
+    }
+
+    /// The secret itself, for sending where it's needed
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([redacted])")
+    }
+}
+
+/// The required secrets that couldn't be found
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Missing required secrets: {} (set them as environment variables or in the secrets file)",
+    .0.join(", ")
+)]
+pub struct MissingSecrets(pub Vec<String>);
+
+/// Resolves the secrets tools need from environment variables or a secrets file
+///
+/// The file holds `NAME=value` lines, with `#` starting a comment line.
+/// Environment variables take precedence over it. Every secret resolved is
+/// redacted from the logs written through [`Redacting`] and from log
+/// messages sent to clients.
+#[derive(Clone, Debug, Default)]
+pub struct SecretsProvider {
+    secrets: HashMap<String, Secret>,
+    env: bool,
+}
+
+impl SecretsProvider {
+    /// Reads secrets from environment variables
+    pub fn from_env() -> Self {
+        Self {
+            secrets: HashMap::new(),
+            env: true,
+        }
+    }
+
+    /// Also reads the secrets in the file at `path`
+    pub fn with_file(mut self, path: &Path) -> Result<Self, SecretsError> {
+        let text = std::fs::read_to_string(path).map_err(|source| SecretsError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line.split_once('=').ok_or_else(|| SecretsError::Syntax {
+                path: path.to_path_buf(),
+                line: number + 1,
+            })?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            self.secrets
+                .insert(name.trim().to_string(), Secret::new(value));
+        }
+        Ok(self)
+    }
+
+    /// Adds a secret directly
+    pub fn with_secret(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.secrets.insert(name.into(), Secret::new(value));
+        self
+    }
+
+    /// Looks up the secret called `name`
+    pub fn get(&self, name: &str) -> Option<Secret> {
+        let secret = self
+            .env
+            .then(|| std::env::var(name).ok())
+            .flatten()
+            .filter(|value| !value.is_empty())
+            .map(Secret::new)
+            .or_else(|| self.secrets.get(name).cloned())?;
+        register(&secret);
+        Some(secret)
+    }
+
+    /// Looks up every secret in `names`, failing with the names of all that are missing
+    pub fn require(&self, names: &[&str]) -> Result<Vec<Secret>, MissingSecrets> {
+        let mut secrets = Vec::new();
+        let mut missing = Vec::new();
+        for name in names {
+            match self.get(name) {
+                Some(secret) => secrets.push(secret),
+                None => missing.push(name.to_string()),
+            }
+        }
+        if missing.is_empty() {
+            Ok(secrets)
+        } else {
+            Err(MissingSecrets(missing))
+        }
+    }
+}
+
+fn register(secret: &Secret) {
+    if secret.0.len() < MIN_REDACTED_LEN {
+        return;
+    }
+    let mut redacted = REDACTED.write().unwrap_or_else(|e| e.into_inner());
+    if !redacted.contains(&secret.0) {
+        redacted.push(secret.0.clone());
+    }
+}
+
+/// `text` with every secret resolved so far replaced by `[redacted]`
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let redacted = REDACTED.read().unwrap_or_else(|e| e.into_inner());
+    let mut text = Cow::Borrowed(text);
+    for secret in redacted.iter() {
+        if text.contains(&**secret) {
+            text = Cow::Owned(text.replace(&**secret, "[redacted]"));
+        }
+    }
+    text
+}
+
+/// Log writer that redacts secrets from everything written through it
+///
+/// Wraps the writer given to a `tracing_subscriber::fmt` layer.
+pub struct Redacting<W>(W);
+
+impl<W> Redacting<W> {
+    pub fn new(writer: W) -> Self {
+        Self(writer)
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = Redacting<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Redacting(self.0.make_writer())
+    }
+}
+
+impl<W: io::Write> io::Write for Redacting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(text) => self.0.write_all(redact(text).as_bytes())?,
+            Err(_) => self.0.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_resolve_secrets() {
+        let path = std::env::temp_dir().join(format!("bioma-secrets-{}", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "# Search\nBIOMA_TEST_SEARCH_KEY = \"s3cret-search\"\nBIOMA_TEST_OTHER=from-file\n",
+        )
+        .unwrap();
+        std::env::set_var("BIOMA_TEST_OTHER", "from-env");
+
+        let secrets = SecretsProvider::from_env().with_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            secrets.get("BIOMA_TEST_SEARCH_KEY").unwrap().expose(),
+            "s3cret-search"
+        );
+        assert_eq!(
+            secrets.get("BIOMA_TEST_OTHER").unwrap().expose(),
+            "from-env"
+        );
+        assert_eq!(
+            format!("{:?}", secrets.get("BIOMA_TEST_OTHER").unwrap()),
+            "Secret([redacted])"
+        );
+
+        let error = secrets
+            .require(&[
+                "BIOMA_TEST_SEARCH_KEY",
+                "BIOMA_TEST_MISSING",
+                "BIOMA_TEST_GONE",
+            ])
+            .unwrap_err();
+        assert_eq!(error.0, ["BIOMA_TEST_MISSING", "BIOMA_TEST_GONE"]);
+        assert!(error
+            .to_string()
+            .contains("BIOMA_TEST_MISSING, BIOMA_TEST_GONE"));
+    }
+
+    #[test]
+    fn test_redact_logs() {
+        let secrets = SecretsProvider::default().with_secret("KEY", "hunter2-key");
+        assert_eq!(redact("key is hunter2-key"), "key is hunter2-key");
+
+        secrets.get("KEY").unwrap();
+        assert_eq!(redact("key is hunter2-key"), "key is [redacted]");
+
+        let mut log = Redacting::new(Vec::new());
+        log.write_all(b"sending hunter2-key\n").unwrap();
+        assert_eq!(log.0, b"sending [redacted]\n");
+    }
+}
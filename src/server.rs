@@ -0,0 +1,1117 @@
+use crate::aggregator::namespaced;
+use crate::approval::Approvals;
+use crate::client::Client;
+use crate::error::ServerError;
+use crate::middleware::Middleware;
+use crate::policy::{Policies, ToolPolicy};
+use crate::prompts::PromptProvider;
+use crate::rate_limit::RateLimits;
+use crate::resources::memory::MEMORY_SCHEME;
+use crate::resources::provider::TemplateResources;
+use crate::resources::{
+    MemoryResources, ResourceMounts, ResourceProvider, ResourceTemplateProvider, ResourceWatcher,
+};
+use crate::schema::{
+    Prompt, Resource, ServerCapabilities, ServerCapabilitiesPrompts,
+    ServerCapabilitiesPromptsResources, ServerCapabilitiesPromptsResourcesTools,
+};
+use crate::session::{Session, Sessions};
+use crate::shutdown::Shutdown;
+use crate::tools::overrides::ToolOverride;
+use crate::tools::{self, ToolCallHandler};
+use crate::transport::{ChannelTransport, QueueConfig, QueueReport, TransportType};
+use crate::{pagination, ModelContextProtocolServer};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// The task running a spawned server, until someone waits for it
+type ServerTask = Arc<Mutex<Option<JoinHandle<Result<(), ServerError>>>>>;
+
+/// MCP server assembled from tools, resources and prompts with `Server::builder()`
+///
+/// ```ignore
+/// Server::builder()
+///     .tool(Echo)
+///     .resource(readme)
+///     .prompt(greet)
+///     .serve(TransportType::Stdio(StdioTransport::new()))
+///     .await?;
+/// ```
+pub struct Server {
+    tools: Arc<RwLock<Vec<Arc<dyn ToolCallHandler>>>>,
+    resources: Arc<RwLock<Vec<Resource>>>,
+    resource_mounts: ResourceMounts,
+    published: Option<MemoryResources>,
+    prompts: Vec<Prompt>,
+    prompt_providers: Vec<Arc<dyn PromptProvider>>,
+    capabilities: ServerCapabilities,
+    page_size: usize,
+    tool_timeout: Duration,
+    batch_concurrency: usize,
+    max_arguments_size: usize,
+    rate_limits: RateLimits,
+    queues: QueueConfig,
+    middleware: Vec<Arc<dyn Middleware>>,
+    approvals: Approvals,
+    tool_switches: ToolSwitches,
+    policies: Policies,
+    sessions: Sessions,
+    shutdown: Shutdown,
+    notifications: broadcast::Sender<Value>,
+    task: ServerTask,
+}
+
+impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::default()
+    }
+
+    /// Turns the server's tools on and off while it runs
+    pub fn tool_switches(&self) -> ToolSwitches {
+        self.tool_switches.clone()
+    }
+
+    /// Replaces the policies of the server's tools while it runs
+    pub fn policies(&self) -> Policies {
+        self.policies.clone()
+    }
+
+    /// Drives the server from the application embedding it while it runs
+    pub fn handle(&self) -> ServerHandle {
+        ServerHandle {
+            changes: self.tool_switches.changes.clone(),
+            notifications: self.notifications.clone(),
+            tools: self.tools.clone(),
+            resources: self.resources.clone(),
+            sessions: self.sessions.clone(),
+            shutdown: self.shutdown.clone(),
+            queues: self.queues.clone(),
+            task: self.task.clone(),
+        }
+    }
+
+    /// Runs the server in the background until the transport closes or it's shut down
+    ///
+    /// Unlike `serve`, the server doesn't stop on signals; the application
+    /// embedding it decides when with `ServerHandle::shutdown`. Must be called
+    /// from within a Tokio runtime.
+    pub fn spawn(self, transport: TransportType) -> ServerHandle {
+        let handle = self.handle();
+        let (shutdown, sessions) = (self.shutdown.clone(), self.sessions.clone());
+        let task = tokio::spawn(crate::run_server(self, transport, shutdown, sessions));
+        *handle.task.lock().unwrap_or_else(|e| e.into_inner()) = Some(task);
+        handle
+    }
+
+    /// Runs the server in the background like `spawn`, with a client in the same process
+    ///
+    /// The server stops once every clone of the client is dropped.
+    pub fn spawn_in_process(self) -> (ServerHandle, Client) {
+        let (transport, end) = ChannelTransport::new();
+        let handle = self.spawn(TransportType::Channel(transport));
+        (handle, Client::new(end))
+    }
+}
+
+/// Lets the application embedding a `Server` drive it while it runs
+///
+/// Tools and resources can be added and removed, which clients are told
+/// about. Clients don't learn of other changes behind the server's back
+/// unless told, which the `notify_*` methods do. Clones share the same
+/// server.
+#[derive(Clone)]
+pub struct ServerHandle {
+    changes: broadcast::Sender<ListChanged>,
+    notifications: broadcast::Sender<Value>,
+    tools: Arc<RwLock<Vec<Arc<dyn ToolCallHandler>>>>,
+    resources: Arc<RwLock<Vec<Resource>>>,
+    sessions: Sessions,
+    shutdown: Shutdown,
+    queues: QueueConfig,
+    task: ServerTask,
+}
+
+impl ServerHandle {
+    /// Adds `tool`, replacing the tool of the same name if there is one
+    pub fn add_tool(&self, tool: impl ToolCallHandler + 'static) {
+        let name = tool.def().name;
+        {
+            let mut tools = self.tools.write().unwrap_or_else(|e| e.into_inner());
+            tools.retain(|tool| tool.def().name != name);
+            tools.push(Arc::new(tool));
+        }
+        self.notify_tools_changed();
+    }
+
+    /// Removes the tool called `name`, returning whether there was one
+    pub fn remove_tool(&self, name: &str) -> bool {
+        let removed = remove_where(&self.tools, |tool| tool.def().name == name);
+        if removed {
+            self.notify_tools_changed();
+        }
+        removed
+    }
+
+    /// Adds `resource`, replacing the resource with the same URI if there is one
+    pub fn add_resource(&self, resource: Resource) {
+        {
+            let mut resources = self.resources.write().unwrap_or_else(|e| e.into_inner());
+            resources.retain(|existing| existing.uri != resource.uri);
+            resources.push(resource);
+        }
+        self.notify_resources_changed();
+    }
+
+    /// Removes the resource at `uri`, returning whether there was one
+    pub fn remove_resource(&self, uri: &str) -> bool {
+        let removed = remove_where(&self.resources, |resource| resource.uri == uri);
+        if removed {
+            self.notify_resources_changed();
+        }
+        removed
+    }
+
+    /// Sessions of the clients connected to the server
+    pub fn sessions(&self) -> Vec<Session> {
+        self.sessions
+            .ids()
+            .iter()
+            .filter_map(|id| self.sessions.get(id))
+            .collect()
+    }
+
+    /// Sends a notification to every initialized client
+    pub fn send_notification(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> Result<(), serde_json::Error> {
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": serde_json::to_value(params)?,
+        });
+        // Nobody listens until the server runs
+        let _ = self.notifications.send(notification);
+        Ok(())
+    }
+
+    /// How full the server's queues are, and what their overflow policy refused
+    pub fn queues(&self) -> QueueReport {
+        self.queues.metrics().report()
+    }
+
+    /// Starts shutting down a server run with `serve` or `spawn`
+    ///
+    /// Servers run with `serve_with_shutdown` stop on the `Shutdown` they were given.
+    pub fn shutdown(&self) {
+        self.shutdown.trigger();
+    }
+
+    /// Waits for a server started with `Server::spawn` to stop
+    ///
+    /// Only the first call waits; later ones, and calls on handles of
+    /// servers that weren't spawned, return right away.
+    pub async fn wait(&self) -> Result<(), ServerError> {
+        let task = self.task.lock().unwrap_or_else(|e| e.into_inner()).take();
+        match task {
+            Some(task) => match task.await {
+                Ok(result) => result,
+                Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                Err(_) => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Sends `notifications/tools/list_changed` to every initialized client
+    pub fn notify_tools_changed(&self) {
+        self.notify(ListChanged::Tools);
+    }
+
+    /// Sends `notifications/resources/list_changed` to every initialized client
+    pub fn notify_resources_changed(&self) {
+        self.notify(ListChanged::Resources);
+    }
+
+    /// Sends `notifications/prompts/list_changed` to every initialized client
+    pub fn notify_prompts_changed(&self) {
+        self.notify(ListChanged::Prompts);
+    }
+
+    fn notify(&self, list: ListChanged) {
+        // Nobody listens until the server runs
+        let _ = self.changes.send(list);
+    }
+}
+
+/// A list of the server's that clients are told about when it changes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListChanged {
+    Tools,
+    Resources,
+    Prompts,
+}
+
+impl ListChanged {
+    pub const ALL: [ListChanged; 3] = [
+        ListChanged::Tools,
+        ListChanged::Resources,
+        ListChanged::Prompts,
+    ];
+
+    /// Method of the notification that announces the change
+    pub fn method(&self) -> &'static str {
+        match self {
+            ListChanged::Tools => "notifications/tools/list_changed",
+            ListChanged::Resources => "notifications/resources/list_changed",
+            ListChanged::Prompts => "notifications/prompts/list_changed",
+        }
+    }
+}
+
+/// Removes the items matching `f`, returning whether there were any
+fn remove_where<T>(items: &RwLock<Vec<T>>, f: impl Fn(&T) -> bool) -> bool {
+    let mut items = items.write().unwrap_or_else(|e| e.into_inner());
+    let before = items.len();
+    items.retain(|item| !f(item));
+    items.len() != before
+}
+
+/// Turns the tools of a `Server` on and off, and renames them, while it runs
+///
+/// Disabled tools are left out of `tools/list` and can't be called, and
+/// when only some tools are enabled the others are left out too. Overrides
+/// change the name and description tools are listed and called by. Tools
+/// are always switched, overridden and given policies by their own names,
+/// whatever clients see them as. Clients are sent
+/// `notifications/tools/list_changed` whenever the set of tools they see
+/// changes.
+#[derive(Clone)]
+pub struct ToolSwitches {
+    state: Arc<RwLock<Switches>>,
+    changes: broadcast::Sender<ListChanged>,
+}
+
+#[derive(Default)]
+struct Switches {
+    disabled: HashSet<String>,
+    /// The only tools offered, if not all
+    enabled: Option<HashSet<String>>,
+    overrides: HashMap<String, ToolOverride>,
+}
+
+impl Switches {
+    fn own_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.overrides
+            .iter()
+            .find(|(_, tool_override)| tool_override.name.as_deref() == Some(name))
+            .map_or(name, |(own, _)| own)
+    }
+}
+
+impl Default for ToolSwitches {
+    fn default() -> Self {
+        Self {
+            state: Arc::default(),
+            changes: broadcast::channel(16).0,
+        }
+    }
+}
+
+impl ToolSwitches {
+    /// Whether the tool clients see as `name` wasn't disabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        !state.disabled.contains(state.own_name(name))
+    }
+
+    /// Name of the tool clients see as `name`, which it was renamed to or its own
+    pub fn own_name(&self, name: &str) -> String {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        state.own_name(name).to_string()
+    }
+
+    pub fn enable(&self, name: &str) {
+        self.update(|state| state.disabled.remove(name));
+    }
+
+    pub fn disable(&self, name: impl Into<String>) {
+        self.update(|state| state.disabled.insert(name.into()));
+    }
+
+    /// Disables the tools named in `names` and enables all others
+    pub fn set_disabled(&self, names: impl IntoIterator<Item = impl Into<String>>) {
+        let names: HashSet<String> = names.into_iter().map(Into::into).collect();
+        self.update(|state| {
+            let changed = state.disabled != names;
+            state.disabled = names;
+            changed
+        });
+    }
+
+    /// Offers only the tools named in `names`, or every tool when `None`
+    pub fn set_enabled_only(&self, names: Option<impl IntoIterator<Item = impl Into<String>>>) {
+        let names: Option<HashSet<String>> =
+            names.map(|names| names.into_iter().map(Into::into).collect());
+        self.update(|state| {
+            let changed = state.enabled != names;
+            state.enabled = names;
+            changed
+        });
+    }
+
+    /// Presents the tool called `name` as `tool_override` says
+    pub fn set_override(&self, name: impl Into<String>, tool_override: ToolOverride) {
+        let name = name.into();
+        self.update(|state| {
+            state.overrides.insert(name, tool_override.clone()) != Some(tool_override)
+        });
+    }
+
+    /// Replaces every override with `overrides`
+    pub fn set_overrides(&self, overrides: HashMap<String, ToolOverride>) {
+        self.update(|state| {
+            let changed = state.overrides != overrides;
+            state.overrides = overrides;
+            changed
+        });
+    }
+
+    /// The tools out of `tools` that were enabled, as clients see them
+    ///
+    /// Disabled tools are left for `is_enabled` to turn away.
+    pub fn curate(&self, tools: &[Arc<dyn ToolCallHandler>]) -> Vec<Arc<dyn ToolCallHandler>> {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        if state.enabled.is_none() && state.overrides.is_empty() {
+            return tools.to_vec();
+        }
+        tools
+            .iter()
+            .filter_map(|tool| {
+                let name = tool.def().name;
+                if let Some(enabled) = &state.enabled {
+                    if !enabled.contains(&name) {
+                        return None;
+                    }
+                }
+                Some(match state.overrides.get(&name) {
+                    Some(tool_override) => tool_override.apply(tool.clone()),
+                    None => tool.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Receives a message each time the set of enabled tools changes
+    pub fn changes(&self) -> broadcast::Receiver<ListChanged> {
+        self.changes.subscribe()
+    }
+
+    /// Applies `f`, announcing the change if it returns true
+    fn update(&self, f: impl FnOnce(&mut Switches) -> bool) {
+        let changed = f(&mut self.state.write().unwrap_or_else(|e| e.into_inner()));
+        if changed {
+            // Nobody listens until the server runs
+            let _ = self.changes.send(ListChanged::Tools);
+        }
+    }
+}
+
+impl ModelContextProtocolServer for Server {
+    fn get_capabilities(&self) -> ServerCapabilities {
+        self.capabilities.clone()
+    }
+
+    fn get_prompts(&self) -> &Vec<Prompt> {
+        &self.prompts
+    }
+
+    fn get_prompt_providers(&self) -> &[Arc<dyn PromptProvider>] {
+        &self.prompt_providers
+    }
+
+    fn get_tools(&self) -> Vec<Arc<dyn ToolCallHandler>> {
+        let tools = self.tools.read().unwrap_or_else(|e| e.into_inner());
+        self.tool_switches.curate(&tools)
+    }
+
+    fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn get_resource_mounts(&self) -> &ResourceMounts {
+        &self.resource_mounts
+    }
+
+    fn get_tool_timeout(&self) -> Duration {
+        self.tool_timeout
+    }
+
+    fn get_batch_concurrency(&self) -> usize {
+        self.batch_concurrency
+    }
+
+    fn get_max_arguments_size(&self) -> usize {
+        self.max_arguments_size
+    }
+
+    fn get_rate_limits(&self) -> RateLimits {
+        self.rate_limits.clone()
+    }
+
+    fn get_queue_config(&self) -> QueueConfig {
+        self.queues.clone()
+    }
+
+    fn get_middleware(&self) -> &[Arc<dyn Middleware>] {
+        &self.middleware
+    }
+
+    fn is_tool_enabled(&self, name: &str) -> bool {
+        self.tool_switches.is_enabled(name)
+    }
+
+    fn get_tool_policy(&self, name: &str) -> ToolPolicy {
+        self.policies.get(&self.tool_switches.own_name(name))
+    }
+
+    fn get_approvals(&self) -> Approvals {
+        self.approvals.clone()
+    }
+
+    fn get_published_resources(&self) -> Option<MemoryResources> {
+        self.published.clone()
+    }
+
+    fn list_changes(&self) -> Option<broadcast::Receiver<ListChanged>> {
+        Some(self.tool_switches.changes())
+    }
+
+    fn notifications(&self) -> Option<broadcast::Receiver<Value>> {
+        Some(self.notifications.subscribe())
+    }
+}
+
+/// What building a server does about tools added under the same name
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToolCollisions {
+    /// Fails to build, naming the tool
+    #[default]
+    Reject,
+    /// Renames the colliding tools that were added with a namespace to
+    /// `namespace.name`, failing if that doesn't tell them apart
+    Prefix,
+}
+
+/// Tools added to a `ServerBuilder` under the same name
+#[derive(Debug, thiserror::Error)]
+#[error("More than one tool is called {0}")]
+pub struct ToolCollision(pub String);
+
+/// Builds a `Server`
+///
+/// Unless set with `capabilities`, the server advertises tools, resources
+/// and prompts when it has any, and always advertises logging. Changes to
+/// every list are announced, since tools can be switched off at runtime and
+/// the `ServerHandle` announces changes to resources and prompts.
+///
+/// Tools from different sources, such as the built-in ones and plugins, can
+/// be added with `tool_in` under a namespace each. They keep their names
+/// unless two collide, which fails the build or prefixes them with their
+/// namespaces as `tool_collisions` says, so one never hides the other.
+pub struct ServerBuilder {
+    /// Tools with the namespace they were added under, if any
+    tools: Vec<(Option<String>, Box<dyn ToolCallHandler>)>,
+    tool_collisions: ToolCollisions,
+    resources: Vec<Resource>,
+    resource_templates: Vec<Box<dyn ResourceTemplateProvider>>,
+    resource_watcher: Option<Box<dyn ResourceWatcher>>,
+    resource_mounts: Vec<(String, Arc<dyn ResourceProvider>)>,
+    published: Option<MemoryResources>,
+    prompts: Vec<Prompt>,
+    prompt_providers: Vec<Arc<dyn PromptProvider>>,
+    capabilities: Option<ServerCapabilities>,
+    page_size: usize,
+    tool_timeout: Duration,
+    batch_concurrency: usize,
+    max_arguments_size: usize,
+    rate_limits: RateLimits,
+    queues: QueueConfig,
+    middleware: Vec<Arc<dyn Middleware>>,
+    policies: HashMap<String, ToolPolicy>,
+    approvals: Approvals,
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        Self {
+            tools: Vec::new(),
+            tool_collisions: ToolCollisions::default(),
+            resources: Vec::new(),
+            resource_templates: Vec::new(),
+            resource_watcher: None,
+            resource_mounts: Vec::new(),
+            published: None,
+            prompts: Vec::new(),
+            prompt_providers: Vec::new(),
+            capabilities: None,
+            page_size: pagination::DEFAULT_PAGE_SIZE,
+            tool_timeout: tools::DEFAULT_TOOL_TIMEOUT,
+            batch_concurrency: tools::batch::DEFAULT_BATCH_CONCURRENCY,
+            max_arguments_size: tools::DEFAULT_MAX_ARGUMENTS_SIZE,
+            rate_limits: RateLimits::default(),
+            queues: QueueConfig::default(),
+            middleware: Vec::new(),
+            policies: HashMap::new(),
+            approvals: Approvals::default(),
+        }
+    }
+}
+
+impl ServerBuilder {
+    pub fn tool(mut self, tool: impl ToolCallHandler + 'static) -> Self {
+        self.tools.push((None, Box::new(tool)));
+        self
+    }
+
+    /// Adds every tool in `tools`, such as `tools::all()`
+    pub fn tools(mut self, tools: impl IntoIterator<Item = Box<dyn ToolCallHandler>>) -> Self {
+        self.tools
+            .extend(tools.into_iter().map(|tool| (None, tool)));
+        self
+    }
+
+    /// Adds `tool` from the source called `namespace`, whose name it's
+    /// prefixed with if it collides under `ToolCollisions::Prefix`
+    pub fn tool_in(
+        mut self,
+        namespace: impl Into<String>,
+        tool: impl ToolCallHandler + 'static,
+    ) -> Self {
+        self.tools.push((Some(namespace.into()), Box::new(tool)));
+        self
+    }
+
+    /// What building does about tools added under the same name, rejecting them by default
+    pub fn tool_collisions(mut self, collisions: ToolCollisions) -> Self {
+        self.tool_collisions = collisions;
+        self
+    }
+
+    pub fn resource(mut self, resource: Resource) -> Self {
+        self.resources.push(resource);
+        self
+    }
+
+    pub fn resource_template(mut self, template: impl ResourceTemplateProvider + 'static) -> Self {
+        self.resource_templates.push(Box::new(template));
+        self
+    }
+
+    /// Lets clients subscribe to resources, which `watcher` reports changes to
+    pub fn resource_watcher(mut self, watcher: impl ResourceWatcher + 'static) -> Self {
+        self.resource_watcher = Some(Box::new(watcher));
+        self
+    }
+
+    /// Serves the resources whose URIs start with `prefix`, like `s3://bucket/`, from `provider`
+    ///
+    /// Requests for a URI go to the provider mounted under its longest
+    /// prefix. Resources, templates and watcher added on their own are
+    /// mounted under the empty prefix.
+    pub fn mount_resources(
+        mut self,
+        prefix: impl Into<String>,
+        provider: impl ResourceProvider + 'static,
+    ) -> Self {
+        self.resource_mounts
+            .push((prefix.into(), Arc::new(provider)));
+        self
+    }
+
+    /// Serves `published` under `mem://` and lets tools publish resources to it
+    pub fn memory_resources(mut self, published: MemoryResources) -> Self {
+        self.published = Some(published);
+        self
+    }
+
+    pub fn prompt(mut self, prompt: Prompt) -> Self {
+        self.prompts.push(prompt);
+        self
+    }
+
+    /// Also offers the prompts of `provider`, after those added on their own
+    pub fn prompt_provider(mut self, provider: impl PromptProvider + 'static) -> Self {
+        self.prompt_providers.push(Arc::new(provider));
+        self
+    }
+
+    /// Advertises `capabilities` instead of the ones worked out from what was added
+    pub fn capabilities(mut self, capabilities: ServerCapabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Maximum number of items in each page of the list requests
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// How long a tool call may run, for tools that don't set their own timeout
+    pub fn tool_timeout(mut self, timeout: Duration) -> Self {
+        self.tool_timeout = timeout;
+        self
+    }
+
+    /// How many calls of a `tools/call_batch` run at once
+    pub fn batch_concurrency(mut self, concurrency: usize) -> Self {
+        self.batch_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Refuses tool calls whose arguments are longer than `bytes` as JSON
+    pub fn max_arguments_size(mut self, bytes: usize) -> Self {
+        self.max_arguments_size = bytes;
+        self
+    }
+
+    /// Limits how often clients may send requests
+    pub fn rate_limits(mut self, limits: RateLimits) -> Self {
+        self.rate_limits = limits;
+        self
+    }
+
+    /// Sizes the queues between the transport and the handlers
+    pub fn queues(mut self, queues: QueueConfig) -> Self {
+        self.queues = queues;
+        self
+    }
+
+    /// Runs `middleware` on every request and response, after the middleware added before it
+    pub fn middleware(mut self, middleware: impl Middleware) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Restricts what the tool called `name` may do
+    pub fn policy(mut self, name: impl Into<String>, policy: ToolPolicy) -> Self {
+        self.policies.insert(name.into(), policy);
+        self
+    }
+
+    /// Gets calls of destructive tools approved through `approvals`
+    pub fn approvals(mut self, approvals: Approvals) -> Self {
+        self.approvals = approvals;
+        self
+    }
+
+    /// Builds the server
+    ///
+    /// # Panics
+    ///
+    /// If tools collide and `tool_collisions` can't tell them apart, which
+    /// `try_build` reports instead.
+    pub fn build(self) -> Server {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Builds the server, failing if tools collide and `tool_collisions` can't tell them apart
+    pub fn try_build(self) -> Result<Server, ToolCollision> {
+        let tools = resolve_tools(self.tools, self.tool_collisions)?;
+        let capabilities = match self.capabilities {
+            Some(capabilities) => capabilities,
+            None => {
+                let resources = !self.resources.is_empty()
+                    || !self.resource_templates.is_empty()
+                    || self.resource_watcher.is_some()
+                    || !self.resource_mounts.is_empty()
+                    || self.published.is_some();
+                let prompts = !self.prompts.is_empty() || !self.prompt_providers.is_empty();
+                ServerCapabilities {
+                    tools: (!tools.is_empty()).then_some(ServerCapabilitiesPromptsResourcesTools {
+                        list_changed: Some(true),
+                    }),
+                    resources: resources.then_some(ServerCapabilitiesPromptsResources {
+                        list_changed: Some(true),
+                        subscribe: Some(false),
+                    }),
+                    prompts: prompts.then_some(ServerCapabilitiesPrompts {
+                        list_changed: Some(true),
+                    }),
+                    // Prompt arguments and resource template variables can be completed
+                    completions: (resources || prompts).then(Default::default),
+                    logging: Some(Default::default()),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let resources = Arc::new(RwLock::new(self.resources));
+        let mut resource_mounts = ResourceMounts::new();
+        resource_mounts.mount(
+            "",
+            Arc::new(TemplateResources {
+                resources: resources.clone(),
+                templates: self.resource_templates,
+                watcher: self.resource_watcher,
+            }),
+        );
+        for (prefix, provider) in self.resource_mounts {
+            resource_mounts.mount(prefix, provider);
+        }
+        let tool_switches = ToolSwitches::default();
+        if let Some(published) = &self.published {
+            published.announce_on(tool_switches.changes.clone());
+            resource_mounts.mount(MEMORY_SCHEME, Arc::new(published.clone()));
+        }
+        for provider in &self.prompt_providers {
+            provider.announce_on(tool_switches.changes.clone());
+        }
+
+        Ok(Server {
+            tools: Arc::new(RwLock::new(tools)),
+            resources,
+            resource_mounts,
+            published: self.published,
+            prompts: self.prompts,
+            prompt_providers: self.prompt_providers,
+            capabilities,
+            page_size: self.page_size,
+            tool_timeout: self.tool_timeout,
+            batch_concurrency: self.batch_concurrency,
+            max_arguments_size: self.max_arguments_size,
+            rate_limits: self.rate_limits,
+            queues: self.queues,
+            middleware: self.middleware,
+            approvals: self.approvals,
+            tool_switches,
+            policies: Policies::new(self.policies),
+            sessions: Sessions::default(),
+            shutdown: Shutdown::new(),
+            notifications: broadcast::channel(16).0,
+            task: Arc::default(),
+        })
+    }
+
+    /// Builds the server and runs it until the transport closes or the process gets SIGINT or SIGTERM
+    pub async fn serve(self, transport: TransportType) -> Result<(), ServerError> {
+        let server = self.try_build()?;
+        let (shutdown, sessions) = (server.shutdown.clone(), server.sessions.clone());
+        crate::run_server(server, transport, shutdown.on_signals(), sessions).await
+    }
+
+    /// Builds the server and runs it until the transport closes or `shutdown` is triggered
+    pub async fn serve_with_shutdown(
+        self,
+        transport: TransportType,
+        shutdown: Shutdown,
+    ) -> Result<(), ServerError> {
+        let server = self.try_build()?;
+        let sessions = server.sessions.clone();
+        crate::run_server(server, transport, shutdown, sessions).await
+    }
+}
+
+/// The tools added to a builder, renamed where they collide as `collisions` says
+fn resolve_tools(
+    tools: Vec<(Option<String>, Box<dyn ToolCallHandler>)>,
+    collisions: ToolCollisions,
+) -> Result<Vec<Arc<dyn ToolCallHandler>>, ToolCollision> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (_, tool) in &tools {
+        *counts.entry(tool.def().name).or_default() += 1;
+    }
+
+    let mut names = HashSet::new();
+    let mut resolved = Vec::with_capacity(tools.len());
+    for (namespace, tool) in tools {
+        let tool: Arc<dyn ToolCallHandler> = Arc::from(tool);
+        let name = tool.def().name;
+        let tool = match namespace {
+            Some(namespace) if counts[&name] > 1 && collisions == ToolCollisions::Prefix => {
+                ToolOverride::default()
+                    .with_name(namespaced(&namespace, &name))
+                    .apply(tool)
+            }
+            _ => tool,
+        };
+        let name = tool.def().name;
+        if !names.insert(name.clone()) {
+            return Err(ToolCollision(name));
+        }
+        resolved.push(tool);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ClientError;
+    use crate::tools::echo::Echo;
+    use serde_json::json;
+
+    #[test]
+    fn test_builder_capabilities() {
+        let server = Server::builder()
+            .tool(Echo)
+            .tool_timeout(Duration::from_secs(5))
+            .build();
+        let capabilities = server.get_capabilities();
+        assert!(capabilities.tools.is_some());
+        assert!(capabilities.resources.is_none());
+        assert!(capabilities.prompts.is_none());
+        assert!(capabilities.logging.is_some());
+        assert_eq!(server.get_tools()[0].def().name, "echo");
+        assert_eq!(server.get_tool_timeout(), Duration::from_secs(5));
+        assert_eq!(server.get_page_size(), pagination::DEFAULT_PAGE_SIZE);
+
+        let prompt = Prompt {
+            name: "greet".to_string(),
+            description: None,
+            arguments: None,
+        };
+        let server = Server::builder()
+            .prompt(prompt)
+            .capabilities(ServerCapabilities::default())
+            .build();
+        assert_eq!(server.get_capabilities(), ServerCapabilities::default());
+        assert_eq!(server.get_prompts().len(), 1);
+    }
+
+    #[test]
+    fn test_tool_switches() {
+        let server = Server::builder().tool(Echo).build();
+        let switches = server.tool_switches();
+        let mut changes = server.list_changes().unwrap();
+        assert!(server.is_tool_enabled("echo"));
+
+        switches.disable("echo");
+        assert!(!server.is_tool_enabled("echo"));
+        assert_eq!(changes.try_recv().unwrap(), ListChanged::Tools);
+
+        // Only changes are announced
+        switches.set_disabled(["echo"]);
+        assert!(changes.try_recv().is_err());
+        switches.set_disabled(Vec::<String>::new());
+        assert!(server.is_tool_enabled("echo"));
+        assert_eq!(changes.try_recv().unwrap(), ListChanged::Tools);
+    }
+
+    #[tokio::test]
+    async fn test_tool_collisions() {
+        let names = |server: &Server| -> Vec<String> {
+            server.get_tools().iter().map(|t| t.def().name).collect()
+        };
+        let builder = || Server::builder().tool(Echo).tool_in("memory", Echo);
+        let collision = builder().try_build().err().unwrap();
+        assert_eq!(collision.to_string(), "More than one tool is called echo");
+        // Serving reports the collision instead of panicking
+        let (transport, _end) = ChannelTransport::new();
+        let served = builder()
+            .serve_with_shutdown(TransportType::Channel(transport), Shutdown::new())
+            .await;
+        assert!(served.is_err());
+
+        let server = builder()
+            .tool_in("kv", Echo)
+            .tool_collisions(ToolCollisions::Prefix)
+            .build();
+        assert_eq!(names(&server), ["echo", "memory.echo", "kv.echo"]);
+        let server = Server::builder().tool_in("kv", Echo).build();
+        assert_eq!(names(&server), ["echo"]);
+
+        // Tools without a namespace can't be told apart
+        let unnamespaced = Server::builder()
+            .tool(Echo)
+            .tool(Echo)
+            .tool_collisions(ToolCollisions::Prefix)
+            .try_build();
+        assert!(unnamespaced.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tool_overrides() {
+        let server = Server::builder().tool(Echo).build();
+        let switches = server.tool_switches();
+        switches.set_override(
+            "echo",
+            ToolOverride::default()
+                .with_name("say")
+                .with_description("Says it back"),
+        );
+        let server = crate::testing::TestServer::start(server).await;
+
+        let tools = server.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "say");
+        assert_eq!(tools[0].description.as_deref(), Some("Says it back"));
+        let said = server.call("say", json!({ "message": "hi" })).await;
+        crate::testing::assert_tool_text(&said, "hi");
+        assert!(server
+            .call_tool("echo", json!({ "message": "hi" }))
+            .await
+            .is_err());
+
+        // Switched under its own name, whatever clients see it as
+        switches.set_disabled(["say"]);
+        assert_eq!(server.list_tools().await.unwrap().len(), 1);
+        switches.set_disabled(["echo"]);
+        assert!(server.list_tools().await.unwrap().is_empty());
+        switches.set_disabled(Vec::<String>::new());
+        switches.set_enabled_only(Some(["say"]));
+        assert!(server.list_tools().await.unwrap().is_empty());
+        switches.set_enabled_only(Some(["echo"]));
+        assert_eq!(server.list_tools().await.unwrap().len(), 1);
+        switches.set_overrides(HashMap::new());
+        assert_eq!(server.list_tools().await.unwrap()[0].name, "echo");
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_max_arguments_size() {
+        let server = Server::builder().tool(Echo).max_arguments_size(32).build();
+        let server = crate::testing::TestServer::start(server).await;
+
+        let said = server.call("echo", json!({ "message": "hi" })).await;
+        crate::testing::assert_tool_text(&said, "hi");
+        let refused = server
+            .call_tool("echo", json!({ "message": "x".repeat(32) }))
+            .await;
+        match refused {
+            Err(ClientError::Rpc(error)) => {
+                assert_eq!(error.code, jsonrpc_core::ErrorCode::InvalidParams);
+                assert_eq!(error.data.unwrap()["limit"], 32);
+            }
+            other => panic!("Expected an invalid params error, got {:?}", other),
+        }
+
+        server.shutdown().await;
+    }
+
+    #[test]
+    fn test_notify_list_changed() {
+        let prompt = Prompt {
+            name: "greet".to_string(),
+            description: None,
+            arguments: None,
+        };
+        let server = Server::builder().tool(Echo).prompt(prompt).build();
+        let capabilities = server.get_capabilities();
+        assert_eq!(capabilities.prompts.unwrap().list_changed, Some(true));
+
+        let handle = server.handle();
+        let mut changes = server.list_changes().unwrap();
+        handle.notify_resources_changed();
+        handle.notify_prompts_changed();
+        server.tool_switches().disable("echo");
+        assert_eq!(changes.try_recv().unwrap(), ListChanged::Resources);
+        assert_eq!(changes.try_recv().unwrap(), ListChanged::Prompts);
+        assert_eq!(changes.try_recv().unwrap(), ListChanged::Tools);
+        assert_eq!(
+            ListChanged::Prompts.method(),
+            "notifications/prompts/list_changed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawn() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let transport =
+            TransportType::WebSocket(crate::transport::WebSocketTransport::new(addr.clone()));
+        let handle = Server::builder().tool(Echo).build().spawn(transport);
+
+        let url = format!("ws://{}", addr);
+        let mut client = loop {
+            match tokio_tungstenite::connect_async(&url).await {
+                Ok((client, _)) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": { "name": "test", "version": "1" },
+            },
+        });
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        for message in [initialize, initialized] {
+            client
+                .send(Message::Text(message.to_string().into()))
+                .await
+                .unwrap();
+        }
+        async fn next<S, E>(client: &mut S) -> Value
+        where
+            S: futures::Stream<Item = Result<Message, E>> + Unpin,
+            E: std::fmt::Debug,
+        {
+            let message = client.next().await.unwrap().unwrap();
+            serde_json::from_str(message.into_text().unwrap().as_str()).unwrap()
+        }
+        assert_eq!(next(&mut client).await["id"], 1);
+        while handle.sessions().first().map(|session| session.state)
+            != Some(crate::session::SessionState::Ready)
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(handle.remove_tool("echo"));
+        assert!(!handle.remove_tool("echo"));
+        assert_eq!(
+            next(&mut client).await["method"],
+            "notifications/tools/list_changed"
+        );
+        handle
+            .send_notification("notifications/message", json!({ "text": "hi" }))
+            .unwrap();
+        let notification = next(&mut client).await;
+        assert_eq!(notification["method"], "notifications/message");
+        assert_eq!(notification["params"]["text"], "hi");
+
+        handle.shutdown();
+        tokio::time::timeout(Duration::from_secs(5), handle.wait())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serve_bind_error() {
+        let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = taken.local_addr().unwrap().to_string();
+        let transport =
+            TransportType::WebSocket(crate::transport::WebSocketTransport::new(addr.clone()));
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            Server::builder().tool(Echo).serve(transport),
+        )
+        .await
+        .unwrap();
+        match result {
+            Err(ServerError::Transport(crate::transport::TransportError::Bind {
+                addr: failed,
+                ..
+            })) => assert_eq!(failed, addr),
+            other => panic!("Expected a bind error, got {:?}", other),
+        }
+    }
+}
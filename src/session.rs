@@ -0,0 +1,273 @@
+use crate::schema::{ClientCapabilities, Implementation, LoggingLevel};
+use crate::transport::ConnectionId;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+/// State negotiated with a single client connection
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub id: ConnectionId,
+    /// Client name and version sent with `initialize`
+    pub client_info: Option<Implementation>,
+    /// Capabilities the client advertised in `initialize`
+    pub client_capabilities: ClientCapabilities,
+    /// Protocol version agreed on during `initialize`
+    pub protocol_version: Option<String>,
+    /// How far the client got through the initialization handshake
+    pub state: SessionState,
+    /// Minimum level of log messages forwarded to the client, set with `logging/setLevel`
+    pub log_level: Option<LoggingLevel>,
+    /// Resource URIs the client subscribed to with `resources/subscribe`
+    pub subscriptions: HashSet<String>,
+    /// State tools keep for this client, shared by every snapshot of the session
+    pub store: SessionStore,
+}
+
+impl Session {
+    pub fn new(id: ConnectionId) -> Self {
+        Self {
+            id,
+            client_info: None,
+            client_capabilities: ClientCapabilities::default(),
+            protocol_version: None,
+            state: SessionState::Uninitialized,
+            log_level: None,
+            subscriptions: HashSet::new(),
+            store: SessionStore::default(),
+        }
+    }
+}
+
+/// Stage of a session's lifecycle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SessionState {
+    /// The client hasn't sent `initialize` yet
+    #[default]
+    Uninitialized,
+    /// The client sent `initialize` but not `notifications/initialized`
+    Initializing,
+    /// The handshake is done and the client may send any request
+    Ready,
+    /// The server is shutting down and takes no new requests
+    ShuttingDown,
+}
+
+/// Key-value store for tools that keep state per client session
+///
+/// Values can be of any type and are dropped with the session. Keys should
+/// start with the tool's name so tools don't overwrite each other's state.
+#[derive(Clone, Default)]
+pub struct SessionStore {
+    values: Arc<Mutex<HashMap<String, Box<dyn Any + Send>>>>,
+}
+
+impl SessionStore {
+    fn values(&self) -> MutexGuard<'_, HashMap<String, Box<dyn Any + Send>>> {
+        self.values.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns a copy of the value at `key`, if there is one of type `T`
+    pub fn get<T: Clone + 'static>(&self, key: &str) -> Option<T> {
+        self.values().get(key)?.downcast_ref::<T>().cloned()
+    }
+
+    pub fn insert<T: Send + 'static>(&self, key: impl Into<String>, value: T) {
+        self.values().insert(key.into(), Box::new(value));
+    }
+
+    /// Removes the value at `key`, returning whether there was one
+    pub fn remove(&self, key: &str) -> bool {
+        self.values().remove(key).is_some()
+    }
+
+    /// Applies `f` to the value at `key`, starting from `T::default()` if it is missing
+    ///
+    /// A value of another type at `key` is replaced. The store is locked
+    /// while `f` runs, so it must not use the store itself.
+    pub fn update<T: Default + Send + 'static, R>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> R {
+        let mut values = self.values();
+        let value = values
+            .entry(key.to_string())
+            .or_insert_with(|| Box::new(T::default()));
+        if !value.is::<T>() {
+            *value = Box::new(T::default());
+        }
+        let value = match value.downcast_mut::<T>() {
+            Some(value) => value,
+            None => unreachable!("value was just replaced with a T"),
+        };
+        f(value)
+    }
+
+    /// Keys of every stored value
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.values().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+}
+
+impl fmt::Debug for SessionStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionStore")
+            .field("keys", &self.keys())
+            .finish()
+    }
+}
+
+/// Registry of the sessions of every connected client
+#[derive(Clone, Default)]
+pub struct Sessions {
+    inner: Arc<RwLock<HashMap<ConnectionId, Session>>>,
+}
+
+impl Sessions {
+    /// Returns a snapshot of the session, if the client has one
+    pub fn get(&self, id: &ConnectionId) -> Option<Session> {
+        self.inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()
+    }
+
+    /// Applies `f` to the session, creating it first if needed
+    pub fn update<R>(&self, id: &ConnectionId, f: impl FnOnce(&mut Session) -> R) -> R {
+        let mut sessions = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let session = sessions
+            .entry(id.clone())
+            .or_insert_with(|| Session::new(id.clone()));
+        f(session)
+    }
+
+    pub fn remove(&self, id: &ConnectionId) -> Option<Session> {
+        self.inner
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id)
+    }
+
+    /// Number of sessions in `state`
+    pub fn count(&self, state: SessionState) -> usize {
+        self.inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|session| session.state == state)
+            .count()
+    }
+
+    /// IDs of every known session
+    pub fn ids(&self) -> Vec<ConnectionId> {
+        self.inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// IDs of the sessions subscribed to the resource at `uri`
+    pub fn subscribers(&self, uri: &str) -> Vec<ConnectionId> {
+        self.inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|session| session.subscriptions.contains(uri))
+            .map(|session| session.id.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_are_isolated() {
+        let sessions = Sessions::default();
+        let first = ConnectionId::from("first");
+        let second = ConnectionId::from("second");
+
+        sessions.update(&first, |session| {
+            session.protocol_version = Some("2024-11-05".to_string());
+            session.state = SessionState::Ready;
+        });
+        sessions.update(&second, |_| ());
+
+        let first = sessions.get(&first).unwrap();
+        assert_eq!(first.state, SessionState::Ready);
+        assert_eq!(first.protocol_version.as_deref(), Some("2024-11-05"));
+
+        let second_session = sessions.get(&second).unwrap();
+        assert_eq!(second_session.state, SessionState::Uninitialized);
+        assert!(second_session.protocol_version.is_none());
+
+        assert_eq!(sessions.len(), 2);
+        sessions.remove(&second);
+        assert_eq!(sessions.ids(), vec![ConnectionId::from("first")]);
+    }
+
+    #[test]
+    fn test_session_store() {
+        let sessions = Sessions::default();
+        let first = ConnectionId::from("first");
+        let second = ConnectionId::from("second");
+        let store = sessions.update(&first, |session| session.store.clone());
+
+        store.insert("counter", 1u32);
+        assert_eq!(store.get::<u32>("counter"), Some(1));
+        assert_eq!(store.get::<String>("counter"), None);
+        let count = store.update("counter", |count: &mut u32| {
+            *count += 1;
+            *count
+        });
+        assert_eq!(count, 2);
+        store.update("notes", |notes: &mut Vec<String>| {
+            notes.push("hi".to_string())
+        });
+
+        // Snapshots share the store, while other sessions have their own
+        let snapshot = sessions.get(&first).unwrap().store;
+        assert_eq!(snapshot.keys(), vec!["counter", "notes"]);
+        assert_eq!(snapshot.get::<Vec<String>>("notes").unwrap(), vec!["hi"]);
+        assert!(sessions
+            .update(&second, |session| session.store.keys())
+            .is_empty());
+
+        assert!(store.remove("counter"));
+        assert!(!snapshot.remove("counter"));
+    }
+
+    #[test]
+    fn test_subscribers() {
+        let sessions = Sessions::default();
+        let first = ConnectionId::from("first");
+        let second = ConnectionId::from("second");
+
+        sessions.update(&first, |session| {
+            session.subscriptions.insert("file:///a.txt".to_string());
+        });
+        sessions.update(&second, |session| {
+            session.subscriptions.insert("file:///b.txt".to_string());
+        });
+
+        assert_eq!(sessions.subscribers("file:///a.txt"), vec![first]);
+        assert_eq!(sessions.subscribers("file:///b.txt"), vec![second]);
+        assert!(sessions.subscribers("file:///c.txt").is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{error, info};
+
+/// How long in-flight requests get to finish once shutdown starts
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Handle that tells a running server to shut down
+///
+/// Once triggered the server stops accepting requests, waits up to the
+/// drain timeout for the ones in flight to finish, sends what they left
+/// behind and returns. Clones share the same state, so any of them can
+/// trigger the shutdown.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    triggered: Arc<watch::Sender<bool>>,
+    drain_timeout: Duration,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            triggered: Arc::new(watch::Sender::new(false)),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+        }
+    }
+
+    /// Sets how long in-flight requests get to finish before they are dropped
+    pub fn with_drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
+    /// Also triggers the shutdown on SIGINT or, on Unix, SIGTERM
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn on_signals(self) -> Self {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                signal = wait_for_signal() => match signal {
+                    Ok(name) => {
+                        info!("Received {}, shutting down", name);
+                        shutdown.trigger();
+                    }
+                    Err(e) => error!("Failed to listen for shutdown signals: {}", e),
+                },
+                _ = shutdown.triggered() => {}
+            }
+        });
+        self
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        self.drain_timeout
+    }
+
+    /// Starts shutting down the server
+    pub fn trigger(&self) {
+        self.triggered.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.triggered.borrow()
+    }
+
+    /// Waits until the shutdown is triggered
+    pub async fn triggered(&self) {
+        let mut triggered = self.triggered.subscribe();
+        // The sender lives in `self`, so this can't fail
+        let _ = triggered.wait_for(|triggered| *triggered).await;
+    }
+}
+
+/// Waits for a signal asking the process to stop and returns its name
+async fn wait_for_signal() -> std::io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT"),
+            _ = terminate.recv() => Ok("SIGTERM"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.map(|_| "Ctrl-C")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_trigger() {
+        let shutdown = Shutdown::new().with_drain_timeout(Duration::from_secs(5));
+        assert_eq!(shutdown.drain_timeout(), Duration::from_secs(5));
+        assert!(!shutdown.is_triggered());
+
+        let waiting = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.triggered().await }
+        });
+        shutdown.clone().trigger();
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(shutdown.is_triggered());
+
+        // Waiting after the fact returns right away
+        shutdown.triggered().await;
+    }
+}
@@ -0,0 +1,253 @@
+//! Helpers for testing servers end to end without a real MCP client
+//!
+//! A `TestServer` runs a server in the same process behind a client that has
+//! completed the handshake. The assertions compare results with JSON written
+//! in the test or kept in golden files.
+//!
+//! ```ignore
+//! let server = TestServer::start(Server::builder().tool(Echo).build()).await;
+//! let result = server.call("echo", json!({ "message": "hi" })).await;
+//! assert_tool_result(&result, json!({ "content": [{ "type": "text", "text": "hi" }] }));
+//! ```
+
+pub mod conformance;
+
+use crate::schema::{CallToolResult, InitializeResult};
+use crate::{Client, Server, ServerHandle};
+use serde::Serialize;
+use serde_json::Value;
+use std::ops::Deref;
+use std::path::Path;
+use std::time::Duration;
+
+/// Set to rewrite golden files with what tests produce instead of comparing them
+pub const UPDATE_GOLDEN_ENV: &str = "BIOMA_UPDATE_GOLDEN";
+
+/// How long `TestServer::shutdown` waits for the server to stop
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A server running in the same process, with a client that completed the handshake
+///
+/// Derefs to the `Client`, so any request can be made of the server. The
+/// helpers on it panic instead of returning errors, as tests want.
+pub struct TestServer {
+    pub handle: ServerHandle,
+    pub client: Client,
+    /// What the server answered to `initialize`
+    pub initialized: InitializeResult,
+}
+
+impl TestServer {
+    /// Spawns `server` and initializes a client of it, which must be called
+    /// from within a Tokio runtime
+    pub async fn start(server: Server) -> Self {
+        let (handle, client) = server.spawn_in_process();
+        let initialized = client
+            .initialize()
+            .await
+            .unwrap_or_else(|e| panic!("Initialization failed: {}", e));
+        Self {
+            handle,
+            client,
+            initialized,
+        }
+    }
+
+    /// Calls the tool called `name`, panicking if the request itself fails
+    ///
+    /// A tool that fails still returns its result, with `is_error` set.
+    pub async fn call(&self, name: &str, arguments: Value) -> CallToolResult {
+        self.client
+            .call_tool(name, arguments)
+            .await
+            .unwrap_or_else(|e| panic!("Calling {} failed: {}", name, e))
+    }
+
+    /// Shuts the server down and waits for it to stop, panicking if it failed
+    pub async fn shutdown(self) {
+        self.handle.shutdown();
+        match tokio::time::timeout(SHUTDOWN_TIMEOUT, self.handle.wait()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => panic!("Server failed: {}", e),
+            Err(_) => panic!("Server didn't stop within {:?}", SHUTDOWN_TIMEOUT),
+        }
+    }
+}
+
+impl Deref for TestServer {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+/// Asserts that a tool result matches `expected`, as `assert_json_matches` does
+pub fn assert_tool_result(result: &CallToolResult, expected: Value) {
+    assert_json_matches(result, &expected);
+}
+
+/// Asserts that a tool succeeded with `text` as its only content
+pub fn assert_tool_text(result: &CallToolResult, text: &str) {
+    assert!(
+        result.is_error != Some(true),
+        "Expected the tool to succeed, got {}",
+        to_pretty(result)
+    );
+    let texts: Vec<_> = result.content.iter().map(|c| c.as_text()).collect();
+    assert_eq!(texts, vec![Some(text)], "Unexpected tool content");
+}
+
+/// Asserts that a tool failed, returning the text of its error
+pub fn assert_tool_error(result: &CallToolResult) -> String {
+    assert!(
+        result.is_error == Some(true),
+        "Expected the tool to fail, got {}",
+        to_pretty(result)
+    );
+    result
+        .content
+        .iter()
+        .filter_map(|c| c.as_text())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Asserts that `actual` serializes to JSON matching `expected`
+///
+/// Objects in `expected` only need to list the fields a test cares about,
+/// while arrays and other values must match exactly.
+pub fn assert_json_matches(actual: &impl Serialize, expected: &Value) {
+    let actual = serde_json::to_value(actual).expect("Value should serialize to JSON");
+    if let Some(mismatch) = mismatch(&actual, expected, "$") {
+        panic!(
+            "JSON doesn't match at {}\nexpected: {}\nactual: {}",
+            mismatch,
+            to_pretty(expected),
+            to_pretty(&actual)
+        );
+    }
+}
+
+/// Asserts that `actual` serializes to the JSON in the golden file at `path`
+///
+/// A missing golden file is written from `actual`, as are all of them when
+/// `BIOMA_UPDATE_GOLDEN` is set, so they can be reviewed and committed.
+pub fn assert_golden(actual: &impl Serialize, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let actual = serde_json::to_value(actual).expect("Value should serialize to JSON");
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("Golden file directory should be writable");
+        }
+        std::fs::write(path, to_pretty(&actual) + "\n").expect("Golden file should be writable");
+        return;
+    }
+
+    let golden = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Failed to read golden file {}: {}", path.display(), e));
+    let golden: Value = serde_json::from_str(&golden)
+        .unwrap_or_else(|e| panic!("Golden file {} isn't JSON: {}", path.display(), e));
+    assert!(
+        actual == golden,
+        "JSON doesn't match golden file {} (set {} to update it)\nexpected: {}\nactual: {}",
+        path.display(),
+        UPDATE_GOLDEN_ENV,
+        to_pretty(&golden),
+        to_pretty(&actual)
+    );
+}
+
+/// Path of the first place `actual` differs from `expected`, if it does
+fn mismatch(actual: &Value, expected: &Value, path: &str) -> Option<String> {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => {
+            expected.iter().find_map(|(key, expected)| {
+                let path = format!("{}.{}", path, key);
+                match actual.get(key) {
+                    Some(actual) => mismatch(actual, expected, &path),
+                    None => Some(path),
+                }
+            })
+        }
+        (Value::Array(actual), Value::Array(expected)) => {
+            if actual.len() != expected.len() {
+                return Some(format!("{} (length)", path));
+            }
+            actual
+                .iter()
+                .zip(expected)
+                .enumerate()
+                .find_map(|(i, (actual, expected))| {
+                    mismatch(actual, expected, &format!("{}[{}]", path, i))
+                })
+        }
+        _ if actual == expected => None,
+        _ => Some(path.to_string()),
+    }
+}
+
+fn to_pretty(value: &impl Serialize) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::echo::Echo;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_harness() {
+        let server = TestServer::start(Server::builder().tool(Echo).build()).await;
+        assert_eq!(server.initialized.server_info.name, "rust-mcp-server");
+
+        let result = server.call("echo", json!({ "message": "hi" })).await;
+        assert_tool_text(&result, "hi");
+        assert_tool_result(
+            &result,
+            json!({ "content": [{ "type": "text", "text": "hi" }] }),
+        );
+        assert_eq!(assert_tool_error(&CallToolResult::error("boom")), "boom");
+        // Arguments that don't fit the schema are a protocol error, not a tool result
+        assert!(server.call_tool("echo", json!({})).await.is_err());
+
+        let tools = server.list_tools().await.unwrap();
+        let golden = std::env::temp_dir()
+            .join(format!("bioma-golden-{}", uuid::Uuid::new_v4()))
+            .join("tools.json");
+        // The first run writes the file, later ones compare with it
+        assert_golden(&tools, &golden);
+        assert_golden(&tools, &golden);
+        std::fs::remove_dir_all(golden.parent().unwrap()).unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[test]
+    fn test_json_matching() {
+        let actual = json!({ "a": 1, "b": { "c": [1, 2], "d": "x" } });
+        assert_eq!(mismatch(&actual, &json!({ "b": { "d": "x" } }), "$"), None);
+        assert_eq!(
+            mismatch(&actual, &json!({ "b": { "d": "y" } }), "$").as_deref(),
+            Some("$.b.d")
+        );
+        assert_eq!(
+            mismatch(&actual, &json!({ "b": { "c": [1] } }), "$").as_deref(),
+            Some("$.b.c (length)")
+        );
+        assert_eq!(
+            mismatch(&actual, &json!({ "e": null }), "$").as_deref(),
+            Some("$.e")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "JSON doesn't match at $.content[0].text")]
+    fn test_tool_result_mismatch() {
+        assert_tool_result(
+            &CallToolResult::text("hi"),
+            json!({ "content": [{ "text": "bye" }] }),
+        );
+    }
+}
@@ -0,0 +1,390 @@
+//! Scripted conformance suites, driving a server with raw JSON-RPC
+//!
+//! A suite is a JSON file listing what a client sends and what the server
+//! must answer, so any server built on this crate can be checked against the
+//! spec: the lifecycle, listing, calls, cancellation, subscriptions and error
+//! cases. The suites this crate passes are under `tests/fixtures/conformance`,
+//! and run against a server of your own with:
+//!
+//! ```ignore
+//! assert_conformance("tests/fixtures/conformance", || build_my_server()).await;
+//! ```
+//!
+//! Each step sends a message, a batch, or with `send_raw` text that isn't
+//! JSON. Its `expect` is the answer to the request it sent, or a list of
+//! answers, matched as `assert_json_matches` does. An expected answer with an
+//! `id` waits for the answer to that request, which may have been sent in an
+//! earlier step. Unless `initialize` is false, the session completed the
+//! handshake before the first step:
+//!
+//! ```json
+//! {
+//!   "description": "Unknown methods are rejected",
+//!   "steps": [
+//!     {
+//!       "send": { "jsonrpc": "2.0", "id": 1, "method": "unknown" },
+//!       "expect": { "error": { "code": -32601 } }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Once the steps are done the server must still answer a `ping`, and must
+//! not have answered anything but the requests sent, each at most once.
+
+use super::{mismatch, to_pretty};
+use crate::error::ConformanceError;
+use crate::message::JsonRpcMessage;
+use crate::protocol::LATEST_PROTOCOL_VERSION;
+use crate::transport::{ChannelEnd, ChannelTransport, TransportType};
+use crate::Server;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+
+/// How long the server gets to answer a request
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// ID of the requests the runner sends itself, unlikely to clash with a suite's
+const INITIALIZE_ID: &str = "conformance-initialize";
+const ALIVE_ID: &str = "conformance-alive";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    /// Name of the file the suite was loaded from
+    #[serde(skip)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Whether the session completes the handshake before the first step
+    #[serde(default = "initialize_by_default")]
+    pub initialize: bool,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// A message or a batch of them
+    pub send: Option<Value>,
+    /// Text sent as is, for what isn't JSON
+    pub send_raw: Option<String>,
+    /// The answer to the request sent, or a list of answers
+    pub expect: Option<Value>,
+}
+
+fn initialize_by_default() -> bool {
+    true
+}
+
+impl Suite {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConformanceError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|source| ConformanceError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut suite: Suite =
+            serde_json::from_str(&text).map_err(|source| ConformanceError::Invalid {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        suite.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(suite)
+    }
+
+    /// Loads the `.json` suites in `dir`, sorted by name
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<Self>, ConformanceError> {
+        let dir = dir.as_ref();
+        let read = |source| ConformanceError::Read {
+            path: dir.to_path_buf(),
+            source,
+        };
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(read)? {
+            let path = entry.map_err(read)?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        paths.into_iter().map(Self::load).collect()
+    }
+
+    /// Runs the suite against `server`, failing at the first step it doesn't pass
+    pub async fn run(&self, server: Server) -> Result<(), ConformanceError> {
+        let (transport, end) = ChannelTransport::new();
+        let handle = server.spawn(TransportType::Channel(transport));
+        let mut session = Session {
+            end,
+            requests: Vec::new(),
+            answers: Vec::new(),
+        };
+
+        let result = async {
+            if self.initialize {
+                session
+                    .handshake()
+                    .await
+                    .map_err(ConformanceError::Handshake)?;
+            }
+            for (i, step) in self.steps.iter().enumerate() {
+                session
+                    .step(step)
+                    .await
+                    .map_err(|message| ConformanceError::Step {
+                        step: i + 1,
+                        message,
+                    })?;
+            }
+            session.finish().await.map_err(ConformanceError::Finish)
+        }
+        .await;
+
+        drop(session);
+        handle.shutdown();
+        let _ = tokio::time::timeout(ANSWER_TIMEOUT, handle.wait()).await;
+        result
+    }
+}
+
+/// Runs every suite in `dir`, each against a fresh server from `server`,
+/// and panics listing those that failed
+///
+/// Must be called from within a Tokio runtime.
+pub async fn assert_conformance(dir: impl AsRef<Path>, server: impl Fn() -> Server) {
+    let suites = Suite::load_dir(dir).unwrap_or_else(|e| panic!("{}", e));
+    assert!(!suites.is_empty(), "No conformance suites to run");
+    let mut failures = Vec::new();
+    for suite in &suites {
+        if let Err(e) = suite.run(server()).await {
+            failures.push(format!("{} ({}): {}", suite.name, suite.description, e));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} conformance suites failed\n\n{}",
+        failures.len(),
+        suites.len(),
+        failures.join("\n\n")
+    );
+}
+
+/// The client side of a suite's connection
+struct Session {
+    end: ChannelEnd,
+    /// IDs of the requests sent and not answered yet
+    requests: Vec<Value>,
+    /// Answers received and not expected yet
+    answers: Vec<Value>,
+}
+
+/// What went wrong in a session, for the error of the suite
+type Failure = String;
+
+impl Session {
+    async fn handshake(&mut self) -> Result<(), Failure> {
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": INITIALIZE_ID,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": LATEST_PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": { "name": "conformance", "version": "0" },
+            },
+        });
+        self.send(initialize.to_string()).await?;
+        let answer = self.answer(&json!(INITIALIZE_ID)).await?;
+        if answer.get("result").is_none() {
+            return Err(format!("Initialization failed: {}", answer));
+        }
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        self.send(initialized.to_string()).await
+    }
+
+    async fn step(&mut self, step: &Step) -> Result<(), Failure> {
+        let sent = match (&step.send, &step.send_raw) {
+            (Some(_), Some(_)) => return Err("A step can't have both send and send_raw".into()),
+            (Some(message), None) => Some(message.to_string()),
+            (None, Some(text)) => Some(text.clone()),
+            (None, None) => None,
+        };
+        let mut sent_ids = Vec::new();
+        if let Some(text) = sent {
+            if let Ok(message) = JsonRpcMessage::parse(&text) {
+                request_ids(&message, &mut sent_ids);
+            }
+            self.send(text).await?;
+        }
+
+        let expected = match &step.expect {
+            None => return Ok(()),
+            Some(Value::Array(answers)) => answers.as_slice(),
+            Some(answer) => std::slice::from_ref(answer),
+        };
+        for expected in expected {
+            let id = match (expected.get("id"), sent_ids.as_slice()) {
+                (Some(id), _) => id.clone(),
+                (None, [id]) => id.clone(),
+                (None, _) => return Err(format!("Expected answer {} needs an id", expected)),
+            };
+            let answer = self.answer(&id).await?;
+            if let Some(mismatch) = mismatch(&answer, expected, "$") {
+                return Err(format!(
+                    "Answer doesn't match at {}\nexpected: {}\nactual: {}",
+                    mismatch,
+                    to_pretty(expected),
+                    to_pretty(&answer)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the server still answers, and answered nothing it wasn't asked
+    async fn finish(&mut self) -> Result<(), Failure> {
+        let ping = json!({ "jsonrpc": "2.0", "id": ALIVE_ID, "method": "ping" });
+        self.send(ping.to_string()).await?;
+        self.answer(&json!(ALIVE_ID))
+            .await
+            .map_err(|e| format!("The server stopped answering: {}", e))?;
+        for answer in std::mem::take(&mut self.answers) {
+            self.answered(&answer["id"])
+                .map_err(|e| format!("Unexpected answer {}: {}", answer, e))?;
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, text: String) -> Result<(), Failure> {
+        if let Ok(message) = JsonRpcMessage::parse(&text) {
+            request_ids(&message, &mut self.requests);
+        }
+        self.end
+            .sender
+            .send(text)
+            .await
+            .map_err(|_| "The server stopped reading".to_string())
+    }
+
+    /// Waits for the answer to the request `id`, keeping the others for later
+    async fn answer(&mut self, id: &Value) -> Result<Value, Failure> {
+        loop {
+            if let Some(i) = self.answers.iter().position(|answer| answer["id"] == *id) {
+                let answer = self.answers.remove(i);
+                self.answered(id)
+                    .map_err(|e| format!("Unexpected answer {}: {}", answer, e))?;
+                return Ok(answer);
+            }
+            let message = match tokio::time::timeout(ANSWER_TIMEOUT, self.end.receiver.recv()).await
+            {
+                Ok(Some(message)) => message,
+                Ok(None) => {
+                    return Err(format!(
+                        "The server closed the connection before answering {}",
+                        id
+                    ))
+                }
+                Err(_) => {
+                    return Err(format!(
+                        "The server didn't answer {} within {:?}",
+                        id, ANSWER_TIMEOUT
+                    ))
+                }
+            };
+            let parsed = JsonRpcMessage::parse(&message)
+                .map_err(|e| format!("The server sent {}, which isn't JSON-RPC: {}", message, e))?;
+            // Notifications and requests of the server are no answers
+            self.answers.extend(parsed.responses().into_iter().cloned());
+        }
+    }
+
+    /// Records the request `id` as answered, failing if it wasn't waiting for one
+    fn answered(&mut self, id: &Value) -> Result<(), Failure> {
+        // A request that couldn't be read is answered with a null ID
+        if id.is_null() {
+            return Ok(());
+        }
+        let i = self
+            .requests
+            .iter()
+            .position(|request| request == id)
+            .ok_or_else(|| format!("No request {} is waiting for an answer", id))?;
+        self.requests.remove(i);
+        Ok(())
+    }
+}
+
+/// Collects the IDs of the requests in `message`
+fn request_ids(message: &JsonRpcMessage, ids: &mut Vec<Value>) {
+    match message {
+        JsonRpcMessage::Request { id, .. } => ids.push(id.clone()),
+        JsonRpcMessage::Batch(messages) => {
+            for message in messages {
+                request_ids(message, ids);
+            }
+        }
+        JsonRpcMessage::Notification { .. } | JsonRpcMessage::Response(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::echo::Echo;
+
+    fn suite(json: Value) -> Suite {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_conformance_runner() {
+        let server = || Server::builder().tool(Echo).build();
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": "echo", "arguments": { "message": "hi" } },
+        });
+
+        // Answers are told apart by their ID, whichever step sent the request
+        let passing = suite(json!({
+            "steps": [
+                { "send": call },
+                {
+                    "send": { "jsonrpc": "2.0", "id": 2, "method": "ping" },
+                    "expect": [{ "id": 2, "result": {} }, { "id": 1, "result": {} }],
+                },
+            ],
+        }));
+        passing.run(server()).await.unwrap();
+
+        let failing = suite(json!({
+            "steps": [{ "send": call, "expect": { "result": { "isError": true } } }],
+        }));
+        match failing.run(server()).await {
+            Err(ConformanceError::Step { step: 1, message }) => {
+                assert!(message.contains("$.result.isError"), "{}", message)
+            }
+            other => panic!("Expected step 1 to fail, got {:?}", other),
+        }
+
+        // Without the handshake, requests are rejected
+        let uninitialized = suite(json!({
+            "initialize": false,
+            "steps": [{
+                "send": { "jsonrpc": "2.0", "id": 1, "method": "tools/list" },
+                "expect": { "result": {} },
+            }],
+        }));
+        assert!(uninitialized.run(server()).await.is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! The experimental `tools/call_batch` method
+//!
+//! A client sends several tool calls in one request and gets every result
+//! back in one response, in the order of the calls. Calls run concurrently,
+//! at most `maxConcurrency` at once, and each goes through the same checks
+//! as a `tools/call`. One call failing doesn't fail the others. Rate limits
+//! count a batch as a single `tools/call_batch` request.
+//!
+//! ```json
+//! { "calls": [
+//!     { "id": "a", "name": "echo", "arguments": { "message": "hi" } },
+//!     { "name": "calc", "arguments": { "expression": "1 + 1" } }
+//! ] }
+//! ```
+//!
+//! answers with `{ "results": [{ "id": "a", "result": ... }, { "id": 1, "error": ... }] }`,
+//! calls without an ID being known by their index.
+
+use crate::schema::{ProtocolVersion, Versioned};
+use crate::tools::{ToolCaller, ToolContext};
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Name of the method, and of the experimental capability advertising it
+pub const CALL_BATCH_METHOD: &str = "tools/call_batch";
+
+/// Calls of a batch run at once, unless the server sets its own limit
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
+/// Most calls a batch may have
+pub const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct CallBatchParams {
+    pub calls: Vec<BatchCall>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchCall {
+    /// Echoed in the call's result, which is known by its index without one
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Option<BTreeMap<String, Value>>,
+}
+
+/// What the experimental capability tells clients about batches
+pub fn capability(concurrency: usize) -> BTreeMap<String, Value> {
+    BTreeMap::from([
+        ("maxConcurrency".to_string(), json!(concurrency)),
+        ("maxCalls".to_string(), json!(MAX_BATCH_SIZE)),
+    ])
+}
+
+/// Runs the calls of a batch through `caller`, at most `concurrency` at once
+pub async fn call_batch(
+    caller: &ToolCaller,
+    context: ToolContext,
+    params: CallBatchParams,
+    concurrency: usize,
+    version: ProtocolVersion,
+) -> Result<Value, jsonrpc_core::Error> {
+    if params.calls.len() > MAX_BATCH_SIZE {
+        return Err(jsonrpc_core::Error::invalid_params(format!(
+            "A batch can't have more than {} calls",
+            MAX_BATCH_SIZE
+        )));
+    }
+
+    let calls = params.calls.into_iter().enumerate().map(|(i, call)| {
+        let context = context.clone();
+        async move {
+            let id = call.id.unwrap_or_else(|| json!(i));
+            let outcome = caller(call.name, call.arguments, context)
+                .await
+                .and_then(|result| {
+                    result
+                        .to_wire(version)
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+                });
+            match outcome {
+                Ok(result) => json!({ "id": id, "result": result }),
+                Err(error) => json!({ "id": id, "error": error }),
+            }
+        }
+    });
+    let results: Vec<Value> = futures::stream::iter(calls)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+    Ok(json!({ "results": results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TestServer;
+    use crate::tools::echo::Echo;
+    use crate::Server;
+    use serde_json::{json, Value};
+
+    #[tokio::test]
+    async fn test_call_batch() {
+        let server = TestServer::start(Server::builder().tool(Echo).build()).await;
+        let experimental = server
+            .initialized
+            .capabilities
+            .experimental
+            .clone()
+            .unwrap();
+        assert_eq!(experimental["tools/call_batch"]["maxConcurrency"], 8);
+
+        let response: Value = server
+            .request(
+                "tools/call_batch",
+                json!({ "calls": [
+                    { "id": "first", "name": "echo", "arguments": { "message": "one" } },
+                    { "name": "unknown" },
+                    { "name": "echo", "arguments": { "message": "three" } },
+                ] }),
+            )
+            .await
+            .unwrap();
+        let results = response["results"].as_array().unwrap();
+        assert_eq!(results[0]["id"], "first");
+        assert_eq!(results[0]["result"]["content"][0]["text"], "one");
+        assert_eq!(results[1]["id"], 1);
+        assert_eq!(results[1]["error"]["message"], "Unknown tool: unknown");
+        assert_eq!(results[2]["result"]["content"][0]["text"], "three");
+
+        let calls: Vec<_> = (0..101).map(|_| json!({ "name": "echo" })).collect();
+        let too_many = server
+            .request::<Value>("tools/call_batch", json!({ "calls": calls }))
+            .await;
+        assert!(too_many.is_err());
+
+        server.shutdown().await;
+    }
+}
@@ -0,0 +1,95 @@
+use super::fetch::Fetch;
+use super::ToolCallHandler;
+
+/// The built-in tools compiled into this build, each behind a `tool-*` feature
+///
+/// Tools that need settings take them from the bundle, and fall back to
+/// their defaults otherwise.
+///
+/// ```ignore
+/// let server = Server::builder()
+///     .tools(Bundle::default().with_fetch(fetch).into_tools())
+///     .build();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Bundle {
+    fetch: Fetch,
+    #[cfg(feature = "tool-search")]
+    search: Option<super::search::Search>,
+}
+
+impl Bundle {
+    /// Fetches with `fetch`, shared by the fetch and image tools
+    pub fn with_fetch(mut self, fetch: Fetch) -> Self {
+        self.fetch = fetch;
+        self
+    }
+
+    /// Searches with `search` instead of DuckDuckGo
+    #[cfg(feature = "tool-search")]
+    pub fn with_search(mut self, search: super::search::Search) -> Self {
+        self.search = Some(search);
+        self
+    }
+
+    /// The enabled tools, ready to be added to a server
+    #[allow(clippy::vec_init_then_push)]
+    pub fn into_tools(self) -> Vec<Box<dyn ToolCallHandler>> {
+        #[allow(unused_mut)]
+        let mut tools: Vec<Box<dyn ToolCallHandler>> = Vec::new();
+        #[cfg(feature = "tool-echo")]
+        tools.push(Box::new(super::echo::Echo));
+        #[cfg(feature = "tool-calc")]
+        tools.push(Box::new(super::calc::Calc));
+        #[cfg(feature = "tool-memory")]
+        tools.push(Box::new(super::memory::Memory::default()));
+        #[cfg(feature = "tool-image")]
+        tools.push(Box::new(super::image::Image::new(self.fetch.clone())));
+        #[cfg(feature = "tool-fetch")]
+        tools.push(Box::new(self.fetch));
+        #[cfg(feature = "tool-fs")]
+        tools.push(Box::new(super::filesystem::Filesystem::default()));
+        #[cfg(feature = "tool-search")]
+        tools.push(Box::new(self.search.unwrap_or_default()));
+        #[cfg(feature = "tool-time")]
+        tools.push(Box::new(super::time::Time::default()));
+        #[cfg(feature = "tool-thinking")]
+        tools.push(Box::new(super::thinking::Thinking));
+        #[cfg(feature = "tool-pipeline")]
+        tools.push(Box::new(super::pipeline::Pipeline));
+        #[cfg(feature = "tool-summarize")]
+        tools.push(Box::new(super::summarize::Summarize::default()));
+        tools
+    }
+}
+
+/// Every built-in tool compiled into this build, with default settings
+pub fn all() -> Vec<Box<dyn ToolCallHandler>> {
+    Bundle::default().into_tools()
+}
+
+#[cfg(all(test, feature = "all-tools"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_tools() {
+        let names: Vec<_> = all().iter().map(|tool| tool.def().name).collect();
+        assert_eq!(
+            names,
+            [
+                "echo",
+                "calc",
+                "memory",
+                "image",
+                "fetch",
+                "filesystem",
+                "search",
+                "time",
+                "sequentialthinking",
+                "pipeline",
+                "summarize"
+            ]
+        );
+    }
+}
@@ -0,0 +1,645 @@
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolError};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Longest expression accepted, in characters
+const MAX_EXPRESSION_LENGTH: usize = 1000;
+
+/// Size of an exact power above which it is computed as a float instead
+const MAX_EXACT_BITS: u64 = 100_000;
+
+/// Digits after the decimal point shown for fractions that don't terminate
+const DECIMAL_DIGITS: usize = 20;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CalcProperties {
+    #[schemars(
+        description = "Arithmetic expression using numbers, + - * / % ^, parentheses, the constants pi, e and tau, and the functions sqrt, abs, floor, ceil, round, exp, ln, log, sin, cos, tan, asin, acos, atan, min and max",
+        required = true
+    )]
+    expression: String,
+}
+
+/// The value of an expression
+#[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CalcOutput {
+    /// The expression as it was understood
+    pub expression: String,
+    /// The value, as a decimal
+    pub result: String,
+    /// Whether the value is exact rather than a floating point approximation
+    pub exact: bool,
+    /// The value as a fraction, for exact values that aren't integers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fraction: Option<String>,
+}
+
+/// Evaluates arithmetic expressions without running any code
+///
+/// Integers and fractions are kept exact with arbitrary precision, so large
+/// numbers don't lose digits. Functions like `sqrt` and `sin` switch to
+/// floating point.
+#[derive(Clone, Debug, Default, Serialize, McpTool)]
+#[mcp_tool(
+    name = "calc",
+    description = "Evaluates a math expression exactly, with support for very large numbers",
+    properties = CalcProperties,
+    output = CalcOutput
+)]
+pub struct Calc;
+
+impl Calc {
+    async fn run(
+        &self,
+        properties: CalcProperties,
+        _context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        if properties.expression.chars().count() > MAX_EXPRESSION_LENGTH {
+            return Ok(CallToolResult::error(format!(
+                "Expression is longer than {} characters",
+                MAX_EXPRESSION_LENGTH
+            )));
+        }
+
+        let expression = match Parser::new(&properties.expression).parse() {
+            Ok(expression) => expression,
+            Err(e) => return Ok(CallToolResult::error(format!("Invalid expression: {}", e))),
+        };
+        let value = match expression.eval() {
+            Ok(value) => value,
+            Err(e) => return Ok(CallToolResult::error(format!("Failed to evaluate: {}", e))),
+        };
+
+        let output = CalcOutput {
+            expression: expression.to_string(),
+            result: value.to_string(),
+            exact: matches!(value, Number::Exact(_)),
+            fraction: match &value {
+                Number::Exact(ratio) if !ratio.is_integer() => Some(ratio.to_string()),
+                _ => None,
+            },
+        };
+        CallToolResult::structured(output).map_err(ToolError::ResultSerialize)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+}
+
+impl Operator {
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Add | Operator::Subtract => 1,
+            Operator::Multiply | Operator::Divide | Operator::Remainder => 2,
+            Operator::Power => 4,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Remainder => "%",
+            Operator::Power => "^",
+        }
+    }
+}
+
+/// Precedence of negation, between multiplication and powers so `-2^2` is -4
+const NEGATE_PRECEDENCE: u8 = 3;
+
+#[derive(Debug, PartialEq)]
+enum Expr {
+    Number(String, BigRational),
+    Constant(&'static str),
+    Negate(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+    Call(&'static str, Vec<Expr>),
+}
+
+impl Expr {
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary(operator, ..) => operator.precedence(),
+            Expr::Negate(_) => NEGATE_PRECEDENCE,
+            _ => u8::MAX,
+        }
+    }
+
+    fn eval(&self) -> Result<Number, String> {
+        let value = match self {
+            Expr::Number(_, value) => Number::Exact(value.clone()),
+            Expr::Constant(name) => Number::Float(match *name {
+                "pi" => std::f64::consts::PI,
+                "tau" => std::f64::consts::TAU,
+                _ => std::f64::consts::E,
+            }),
+            Expr::Negate(inner) => match inner.eval()? {
+                Number::Exact(value) => Number::Exact(-value),
+                Number::Float(value) => Number::Float(-value),
+            },
+            Expr::Binary(operator, left, right) => binary(*operator, left.eval()?, right.eval()?)?,
+            Expr::Call(name, args) => {
+                let args = args.iter().map(Expr::eval).collect::<Result<Vec<_>, _>>()?;
+                call(name, args)?
+            }
+        };
+        match value {
+            Number::Float(value) if !value.is_finite() => {
+                Err("The result is not a finite number".to_string())
+            }
+            value => Ok(value),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Writes the expression with single spaces and only the parentheses it needs
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let wrap = |f: &mut fmt::Formatter<'_>, expr: &Expr, parens: bool| {
+            if parens {
+                write!(f, "({})", expr)
+            } else {
+                write!(f, "{}", expr)
+            }
+        };
+        match self {
+            Expr::Number(text, _) => write!(f, "{}", text),
+            Expr::Constant(name) => write!(f, "{}", name),
+            Expr::Negate(inner) => {
+                write!(f, "-")?;
+                wrap(f, inner, inner.precedence() < NEGATE_PRECEDENCE)
+            }
+            Expr::Binary(operator, left, right) => {
+                let precedence = operator.precedence();
+                // Powers group to the right, everything else to the left
+                let (left_parens, right_parens) = if *operator == Operator::Power {
+                    (
+                        left.precedence() <= precedence,
+                        right.precedence() < precedence,
+                    )
+                } else {
+                    (
+                        left.precedence() < precedence,
+                        right.precedence() <= precedence,
+                    )
+                };
+                wrap(f, left, left_parens)?;
+                if *operator == Operator::Power {
+                    write!(f, "^")?;
+                } else {
+                    write!(f, " {} ", operator.symbol())?;
+                }
+                wrap(f, right, right_parens)
+            }
+            Expr::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// A value that stays exact until an operation forces floating point
+#[derive(Clone, Debug, PartialEq)]
+enum Number {
+    Exact(BigRational),
+    Float(f64),
+}
+
+impl Number {
+    fn to_f64(&self) -> f64 {
+        match self {
+            Number::Exact(value) => value.to_f64().unwrap_or(f64::NAN),
+            Number::Float(value) => *value,
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Exact(value) if value.is_integer() => write!(f, "{}", value.to_integer()),
+            Number::Exact(value) => write!(f, "{}", decimal(value)),
+            Number::Float(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Writes a fraction as a decimal, cut off after `DECIMAL_DIGITS` digits
+fn decimal(value: &BigRational) -> String {
+    let sign = if value.is_negative() { "-" } else { "" };
+    let value = value.abs();
+    let denominator = value.denom();
+    let mut remainder = value.numer() % denominator;
+    let mut digits = String::new();
+    while !remainder.is_zero() && digits.len() < DECIMAL_DIGITS {
+        remainder *= 10;
+        digits.push_str(&(&remainder / denominator).to_string());
+        remainder %= denominator;
+    }
+    let ellipsis = if remainder.is_zero() { "" } else { "..." };
+    format!("{}{}.{}{}", sign, value.to_integer(), digits, ellipsis)
+}
+
+fn binary(operator: Operator, left: Number, right: Number) -> Result<Number, String> {
+    if let (Number::Exact(left), Number::Exact(right)) = (&left, &right) {
+        match operator {
+            Operator::Add => return Ok(Number::Exact(left + right)),
+            Operator::Subtract => return Ok(Number::Exact(left - right)),
+            Operator::Multiply => return Ok(Number::Exact(left * right)),
+            Operator::Divide | Operator::Remainder if right.is_zero() => {
+                return Err("Division by zero".to_string())
+            }
+            Operator::Divide => return Ok(Number::Exact(left / right)),
+            Operator::Remainder => return Ok(Number::Exact(left - right * (left / right).trunc())),
+            Operator::Power => {
+                if let Some(value) = exact_power(left, right) {
+                    return Ok(Number::Exact(value));
+                }
+            }
+        }
+    }
+
+    let (left, right) = (left.to_f64(), right.to_f64());
+    Ok(Number::Float(match operator {
+        Operator::Add => left + right,
+        Operator::Subtract => left - right,
+        Operator::Multiply => left * right,
+        Operator::Divide | Operator::Remainder if right == 0.0 => {
+            return Err("Division by zero".to_string())
+        }
+        Operator::Divide => left / right,
+        Operator::Remainder => left % right,
+        Operator::Power => left.powf(right),
+    }))
+}
+
+/// Raises to an integer power, unless the result would be huge
+fn exact_power(base: &BigRational, exponent: &BigRational) -> Option<BigRational> {
+    if !exponent.is_integer() {
+        return None;
+    }
+    let exponent = exponent.to_integer().to_i32()?;
+    if base.is_zero() && exponent < 0 {
+        return None;
+    }
+    let bits = base.numer().bits().max(base.denom().bits());
+    if bits.saturating_mul(exponent.unsigned_abs() as u64) > MAX_EXACT_BITS {
+        return None;
+    }
+    Some(num_traits::Pow::pow(base, exponent))
+}
+
+fn call(name: &str, args: Vec<Number>) -> Result<Number, String> {
+    // Rounding keeps exact values exact
+    if let [Number::Exact(value)] = args.as_slice() {
+        match name {
+            "abs" => return Ok(Number::Exact(value.abs())),
+            "floor" => return Ok(Number::Exact(value.floor())),
+            "ceil" => return Ok(Number::Exact(value.ceil())),
+            "round" => return Ok(Number::Exact(value.round())),
+            _ => {}
+        }
+    }
+    if name == "min" || name == "max" {
+        let mut args = args.into_iter();
+        let first = args
+            .next()
+            .ok_or(format!("{} needs at least one argument", name))?;
+        return Ok(args.fold(first, |best, arg| {
+            let better = match (&arg, &best) {
+                (Number::Exact(a), Number::Exact(b)) => (a < b) == (name == "min"),
+                (a, b) => (a.to_f64() < b.to_f64()) == (name == "min"),
+            };
+            if better {
+                arg
+            } else {
+                best
+            }
+        }));
+    }
+
+    let args: Vec<f64> = args.iter().map(Number::to_f64).collect();
+    let value = match (name, args.as_slice()) {
+        ("log", [value, base]) => value.log(*base),
+        (_, [value]) => match name {
+            "sqrt" if *value < 0.0 => return Err("Square root of a negative number".to_string()),
+            "sqrt" => value.sqrt(),
+            "abs" => value.abs(),
+            "floor" => value.floor(),
+            "ceil" => value.ceil(),
+            "round" => value.round(),
+            "exp" => value.exp(),
+            "ln" => value.ln(),
+            "log" => value.log10(),
+            "sin" => value.sin(),
+            "cos" => value.cos(),
+            "tan" => value.tan(),
+            "asin" => value.asin(),
+            "acos" => value.acos(),
+            "atan" => value.atan(),
+            _ => return Err(format!("Unknown function {}", name)),
+        },
+        _ => return Err(format!("Wrong number of arguments for {}", name)),
+    };
+    Ok(Number::Float(value))
+}
+
+const FUNCTIONS: &[&str] = &[
+    "sqrt", "abs", "floor", "ceil", "round", "exp", "ln", "log", "sin", "cos", "tan", "asin",
+    "acos", "atan", "min", "max",
+];
+
+const CONSTANTS: &[&str] = &["pi", "e", "tau"];
+
+/// Recursive descent parser for the expression grammar
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    fn parse(mut self) -> Result<Expr, String> {
+        let expr = self.expression()?;
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(expr),
+            Some(c) => Err(format!("unexpected '{}' at position {}", c, self.position)),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.position += c.len_utf8();
+        }
+    }
+
+    /// Consumes `token` if it comes next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.input[self.position..].starts_with(token) {
+            self.position += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let mut expr = self.term()?;
+        loop {
+            let operator = if self.eat("+") {
+                Operator::Add
+            } else if self.eat("-") {
+                Operator::Subtract
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(operator, Box::new(expr), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let operator = if self.eat("*") {
+                Operator::Multiply
+            } else if self.eat("/") {
+                Operator::Divide
+            } else if self.eat("%") {
+                Operator::Remainder
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(operator, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("-") {
+            Ok(Expr::Negate(Box::new(self.unary()?)))
+        } else if self.eat("+") {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.eat("^") || self.eat("**") {
+            let exponent = self.unary()?;
+            Ok(Expr::Binary(
+                Operator::Power,
+                Box::new(base),
+                Box::new(exponent),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.identifier(),
+            Some('(') => {
+                self.position += 1;
+                let expr = self.expression()?;
+                if !self.eat(")") {
+                    return Err(format!("expected ')' at position {}", self.position));
+                }
+                Ok(expr)
+            }
+            Some(c) => Err(format!("unexpected '{}' at position {}", c, self.position)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn number(&mut self) -> Result<Expr, String> {
+        let start = self.position;
+        let rest = &self.input[start..];
+        let mut end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        // Scientific notation such as 1.5e3 or 2E-4
+        let exponent = rest[end..]
+            .strip_prefix(['e', 'E'])
+            .map(|exponent| exponent.strip_prefix(['+', '-']).unwrap_or(exponent))
+            .map(|digits| digits.chars().take_while(char::is_ascii_digit).count());
+        if let Some(digits) = exponent.filter(|digits| *digits > 0) {
+            let sign = rest[end + 1..].starts_with(['+', '-']) as usize;
+            end += 1 + sign + digits;
+        }
+        let text = &rest[..end];
+        self.position += end;
+
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(split) => (&text[..split], &text[split + 1..]),
+            None => (text, "0"),
+        };
+        let invalid = || format!("invalid number {}", text);
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if (whole.is_empty() && fraction.is_empty()) || fraction.contains('.') {
+            return Err(invalid());
+        }
+        let digits: BigInt = format!("{}{}", whole, fraction)
+            .parse()
+            .map_err(|_| invalid())?;
+        let exponent: i32 = exponent.parse().map_err(|_| invalid())?;
+        let scale = exponent
+            .checked_sub(fraction.len() as i32)
+            .filter(|scale| scale.unsigned_abs() <= 10_000)
+            .ok_or_else(invalid)?;
+        let scale = num_traits::Pow::pow(BigRational::from_integer(10.into()), scale);
+        Ok(Expr::Number(
+            text.to_string(),
+            BigRational::from_integer(digits) * scale,
+        ))
+    }
+
+    fn identifier(&mut self) -> Result<Expr, String> {
+        let start = self.position;
+        let rest = &self.input[start..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let name = rest[..end].to_lowercase();
+        self.position += end;
+
+        if let Some(constant) = CONSTANTS.iter().find(|constant| **constant == name) {
+            return Ok(Expr::Constant(constant));
+        }
+        let Some(function) = FUNCTIONS.iter().find(|function| **function == name) else {
+            return Err(format!("unknown name {}", name));
+        };
+        if !self.eat("(") {
+            return Err(format!("expected '(' after {}", name));
+        }
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                args.push(self.expression()?);
+                if self.eat(")") {
+                    break;
+                }
+                if !self.eat(",") {
+                    return Err(format!("expected ',' or ')' at position {}", self.position));
+                }
+            }
+        }
+        Ok(Expr::Call(function, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolDef;
+    use serde_json::json;
+
+    fn eval(expression: &str) -> Result<(String, String), String> {
+        let expr = Parser::new(expression).parse()?;
+        let value = expr.eval()?;
+        Ok((expr.to_string(), value.to_string()))
+    }
+
+    #[test]
+    fn test_evaluation() {
+        let cases = [
+            ("1+2*3", "1 + 2 * 3", "7"),
+            ("(1+2)*3", "(1 + 2) * 3", "9"),
+            ("2^3^2", "2^3^2", "512"),
+            ("(2^3)^2", "(2^3)^2", "64"),
+            ("-2^2", "-2^2", "-4"),
+            ("(-2)**2", "(-2)^2", "4"),
+            ("10 - (4 - 1)", "10 - (4 - 1)", "7"),
+            ("1/3", "1 / 3", "0.33333333333333333333..."),
+            ("0.1 + 0.2", "0.1 + 0.2", "0.3"),
+            ("1.5e3 / 4", "1.5e3 / 4", "375"),
+            ("-7 % 3", "-7 % 3", "-1"),
+            ("2^-2", "2^(-2)", "0.25"),
+            ("round(7/2) + abs(-3)", "round(7 / 2) + abs(-3)", "7"),
+            ("max(1, 5/2, 2)", "max(1, 5 / 2, 2)", "2.5"),
+            ("sqrt(16)", "sqrt(16)", "4"),
+            ("log(100)", "log(100)", "2"),
+            ("2^128", "2^128", "340282366920938463463374607431768211456"),
+            (
+                "99999999999999999999 * 99999999999999999999",
+                "99999999999999999999 * 99999999999999999999",
+                "9999999999999999999800000000000000000001",
+            ),
+        ];
+        for (expression, normalized, result) in cases {
+            assert_eq!(
+                eval(expression),
+                Ok((normalized.to_string(), result.to_string())),
+                "{}",
+                expression
+            );
+        }
+
+        assert!(eval("sin(pi / 2)").unwrap().1.starts_with('1'));
+        for invalid in [
+            "1 +", "2 ** * 3", "foo(1)", "(1", "1 / 0", "sqrt(-1)", "1.2.3", "9^9^9",
+        ] {
+            assert!(eval(invalid).is_err(), "{} should fail", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calc_tool() {
+        let props = CalcProperties {
+            expression: "2/4 + 1".to_string(),
+        };
+        let result = Calc.call(props, ToolContext::default()).await.unwrap();
+        let expected =
+            json!({"expression": "2 / 4 + 1", "result": "1.5", "exact": true, "fraction": "3/2"});
+        assert_eq!(result.structured_content.as_ref(), Some(&expected));
+        let text: serde_json::Value =
+            serde_json::from_str(result.content[0].as_text().unwrap()).unwrap();
+        assert_eq!(text, expected);
+
+        let output_schema = Calc::def().output_schema.unwrap();
+        assert_eq!(
+            output_schema.required.unwrap(),
+            ["exact", "expression", "result"]
+        );
+
+        let props = CalcProperties {
+            expression: "1 +".to_string(),
+        };
+        let result = Calc.call(props, ToolContext::default()).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+}
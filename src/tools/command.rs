@@ -0,0 +1,251 @@
+use super::shell::{DEFAULT_COMMAND_TIMEOUT, INHERITED_ENV};
+use super::{ToolCallHandler, ToolContext, ToolError};
+use crate::error::ConfigError;
+use crate::schema::{self, CallToolResult, ToolInputSchema};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// An external program offered as a tool, as declared in the config
+///
+/// ```toml
+/// [command_tools.word_count]
+/// command = ["python3", "tools/word_count.py"]
+/// description = "Counts the words in a text"
+/// timeout_secs = 5
+///
+/// [command_tools.word_count.input_schema]
+/// type = "object"
+/// required = ["text"]
+/// properties.text = { type = "string" }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandToolConfig {
+    /// Program and the arguments it's started with, not interpreted by a shell
+    pub command: Vec<String>,
+    pub description: Option<String>,
+    /// Schema of the arguments, which accepts any object when unset
+    pub input_schema: Option<ToolInputSchema>,
+    pub timeout_secs: Option<u64>,
+    /// Variables set on top of the few inherited from the server
+    pub env: HashMap<String, String>,
+    pub cwd: Option<PathBuf>,
+}
+
+/// Runs a program once per call, in any language
+///
+/// The arguments are written to its stdin as a JSON object. What it prints
+/// is the result: a JSON `CallToolResult`, `{"error": "..."}` to report a
+/// failure, or otherwise plain text. Exiting with a non-zero status fails
+/// the call with whatever it wrote to stderr.
+#[derive(Clone, Debug)]
+pub struct CommandTool {
+    name: String,
+    config: CommandToolConfig,
+}
+
+impl CommandTool {
+    pub fn new(name: impl Into<String>, config: CommandToolConfig) -> Result<Self, ConfigError> {
+        let name = name.into();
+        if config.command.is_empty() {
+            return Err(ConfigError::EmptyCommand(name));
+        }
+        Ok(Self { name, config })
+    }
+
+    async fn run(&self, arguments: &[u8]) -> Result<CallToolResult, String> {
+        let (program, args) = self.config.command.split_first().expect("checked in new");
+        let mut command = tokio::process::Command::new(program);
+        command
+            .args(args)
+            .env_clear()
+            .envs(
+                INHERITED_ENV
+                    .iter()
+                    .filter_map(|key| Some((*key, std::env::var(key).ok()?))),
+            )
+            .envs(&self.config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(cwd) = &self.config.cwd {
+            command.current_dir(cwd);
+        }
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // Write while reading, so a program that answers before reading
+        // everything can't fill its stdout and stall
+        let write = async move {
+            // A program that doesn't want its arguments may exit without reading them
+            let _ = stdin.write_all(arguments).await;
+        };
+        let (_, output) = tokio::join!(write, child.wait_with_output());
+        let output = output.map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+        if !output.status.success() {
+            let exit_code = output
+                .status
+                .code()
+                .map_or_else(|| "killed by signal".to_string(), |code| code.to_string());
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Ok(CallToolResult::error(format!(
+                "{} exited with {}: {}",
+                self.name,
+                exit_code,
+                stderr.trim_end()
+            )));
+        }
+        Ok(parse_output(
+            String::from_utf8_lossy(&output.stdout).trim_end(),
+        ))
+    }
+}
+
+impl ToolCallHandler for CommandTool {
+    fn call_boxed<'a>(
+        &'a self,
+        args: Option<BTreeMap<String, Value>>,
+        _context: ToolContext,
+    ) -> Pin<Box<dyn Future<Output = Result<CallToolResult, ToolError>> + Send + 'a>> {
+        Box::pin(async move {
+            let arguments = Value::Object(args.unwrap_or_default().into_iter().collect());
+            let arguments = serde_json::to_vec(&arguments).map_err(ToolError::ArgumentParse)?;
+            // Killed on drop, so a call the server gives up on doesn't leave the program running
+            self.run(&arguments).await.map_err(ToolError::Execution)
+        })
+    }
+
+    fn def(&self) -> schema::Tool {
+        schema::Tool {
+            name: self.name.clone(),
+            description: self.config.description.clone(),
+            input_schema: self
+                .config
+                .input_schema
+                .clone()
+                .unwrap_or_else(|| ToolInputSchema {
+                    properties: None,
+                    required: None,
+                    type_: "object".to_string(),
+                }),
+            output_schema: None,
+            annotations: None,
+        }
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        Some(
+            self.config
+                .timeout_secs
+                .map_or(DEFAULT_COMMAND_TIMEOUT, Duration::from_secs),
+        )
+    }
+}
+
+/// The result a program printed, the error it reported, or its text
+fn parse_output(output: &str) -> CallToolResult {
+    let Ok(value) = serde_json::from_str::<Value>(output) else {
+        return CallToolResult::text(output);
+    };
+    if let Some(error) = value.as_object().and_then(|object| object.get("error")) {
+        let message = error
+            .as_str()
+            .map_or_else(|| error.to_string(), str::to_string);
+        return CallToolResult::error(message);
+    }
+    serde_json::from_value(value).unwrap_or_else(|_| CallToolResult::text(output))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn tool(script: &str) -> CommandTool {
+        let config = CommandToolConfig {
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            ..Default::default()
+        };
+        CommandTool::new("script", config).unwrap()
+    }
+
+    async fn call(tool: &CommandTool, args: Value) -> CallToolResult {
+        let args = serde_json::from_value(args).unwrap();
+        tool.call_boxed(Some(args), ToolContext::default())
+            .await
+            .unwrap()
+    }
+
+    fn text(result: &CallToolResult) -> String {
+        serde_json::to_value(&result.content[0]).unwrap()["text"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_command_tool() {
+        let echo = tool("cat");
+        let result = call(&echo, serde_json::json!({"text": "hi"})).await;
+        assert_ne!(result.is_error, Some(true));
+        assert_eq!(text(&result), r#"{"text":"hi"}"#);
+
+        let structured = tool(r#"echo '{"content": [{"type": "text", "text": "done"}]}'"#);
+        assert_eq!(
+            text(&call(&structured, serde_json::json!({})).await),
+            "done"
+        );
+
+        let reported = tool(r#"echo '{"error": "no such word"}'"#);
+        let result = call(&reported, serde_json::json!({})).await;
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(text(&result), "no such word");
+
+        let failing = tool("echo broken >&2; exit 3");
+        let result = call(&failing, serde_json::json!({})).await;
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(text(&result), "script exited with 3: broken");
+
+        let def = echo.def();
+        assert_eq!(def.name, "script");
+        assert_eq!(def.input_schema.type_, "object");
+        assert_eq!(echo.timeout(), Some(DEFAULT_COMMAND_TIMEOUT));
+        assert!(matches!(
+            CommandTool::new("empty", CommandToolConfig::default()),
+            Err(ConfigError::EmptyCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_command_tool_config() {
+        let config: CommandToolConfig = toml::from_str(
+            r#"
+            command = ["python3", "count.py"]
+            timeout_secs = 5
+            env = { LC_ALL = "C" }
+
+            [input_schema]
+            type = "object"
+            required = ["text"]
+            properties.text = { type = "string" }
+            "#,
+        )
+        .unwrap();
+        let tool = CommandTool::new("count", config).unwrap();
+        assert_eq!(tool.timeout(), Some(Duration::from_secs(5)));
+        let schema = tool.def().input_schema;
+        assert_eq!(schema.required.as_deref(), Some(&["text".to_string()][..]));
+        assert_eq!(schema.properties.unwrap()["text"]["type"], "string");
+        assert!(toml::from_str::<CommandToolConfig>("program = \"ls\"").is_err());
+    }
+}
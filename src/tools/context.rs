@@ -0,0 +1,383 @@
+use crate::policy::ToolPolicy;
+use crate::resources::{MemoryContents, MemoryResources, ResourceMounts};
+use crate::roots::RootsState;
+use crate::router::RequestRouter;
+use crate::schema::{
+    CallToolResult, CreateMessageRequestParams, CreateMessageResult, ElicitRequestParams,
+    ElicitRequestParamsRequestedSchema, ElicitResult, ReadResourceResult, Resource, Root,
+};
+use crate::session::{Session, SessionStore, Sessions};
+use crate::tools::ToolError;
+use crate::transport::ConnectionId;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Most tool calls that can be nested in one another through `ToolContext::call_tool`
+pub const MAX_CALL_DEPTH: usize = 8;
+
+/// Calls a tool of the server by name, checking it as `tools/call` does
+pub type ToolCaller = Arc<
+    dyn Fn(
+            String,
+            Option<BTreeMap<String, Value>>,
+            ToolContext,
+        )
+            -> Pin<Box<dyn Future<Output = Result<CallToolResult, jsonrpc_core::Error>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Context of the client session a tool is being called from
+///
+/// Lets tools send requests back to the calling client, such as
+/// `sampling/createMessage` and `elicitation/create`.
+#[derive(Clone)]
+pub struct ToolContext {
+    session_id: ConnectionId,
+    sessions: Sessions,
+    router: RequestRouter,
+    roots: RootsState,
+    policy: ToolPolicy,
+    caller: Option<ToolCaller>,
+    published: Option<MemoryResources>,
+    resources: Option<ResourceMounts>,
+    /// How many tool calls this one is nested in
+    depth: usize,
+}
+
+impl ToolContext {
+    pub fn new(
+        session_id: ConnectionId,
+        sessions: Sessions,
+        router: RequestRouter,
+        roots: RootsState,
+    ) -> Self {
+        Self {
+            session_id,
+            sessions,
+            router,
+            roots,
+            policy: ToolPolicy::default(),
+            caller: None,
+            published: None,
+            resources: None,
+            depth: 0,
+        }
+    }
+
+    /// Lets the tool call the server's other tools through `caller`
+    pub fn with_caller(mut self, caller: ToolCaller) -> Self {
+        self.caller = Some(caller);
+        self
+    }
+
+    /// Lets the tool publish resources to `published`
+    pub fn with_published_resources(mut self, published: MemoryResources) -> Self {
+        self.published = Some(published);
+        self
+    }
+
+    /// Lets the tool read the resources in `resources`
+    pub fn with_resources(mut self, resources: ResourceMounts) -> Self {
+        self.resources = Some(resources);
+        self
+    }
+
+    /// Runs the tool under `policy`
+    pub fn with_policy(mut self, policy: ToolPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// What the operator allows the tool to do, for it to check before touching anything
+    pub fn policy(&self) -> &ToolPolicy {
+        &self.policy
+    }
+
+    /// ID of the session the tool is being called from
+    pub fn session_id(&self) -> &ConnectionId {
+        &self.session_id
+    }
+
+    /// Snapshot of the calling session, if it has sent `initialize`
+    pub fn session(&self) -> Option<Session> {
+        self.sessions.get(&self.session_id)
+    }
+
+    /// State tools keep for the calling session
+    ///
+    /// Tools should use it instead of globals for anything one client must
+    /// not see of another's.
+    pub fn store(&self) -> SessionStore {
+        self.sessions
+            .update(&self.session_id, |session| session.store.clone())
+    }
+
+    /// Roots the calling client has exposed to the server
+    pub fn roots(&self) -> Vec<Root> {
+        self.roots.get(&self.session_id)
+    }
+
+    /// Local directories of the calling client's `file://` roots
+    pub fn root_paths(&self) -> Vec<PathBuf> {
+        self.roots.paths(&self.session_id)
+    }
+
+    /// Sends a request to the calling client and waits for its result
+    pub async fn request(&self, method: &str, params: impl Serialize) -> Result<Value, ToolError> {
+        let params = serde_json::to_value(params).map_err(ToolError::ResultSerialize)?;
+        self.router
+            .request(&self.session_id, method, params)
+            .await
+            .map_err(|e| ToolError::Execution(e.to_string()))
+    }
+
+    /// Calls another tool of the server on behalf of the same client
+    ///
+    /// The call goes through the same checks as one the client makes, and
+    /// is refused if it would nest deeper than `MAX_CALL_DEPTH`. Arguments
+    /// must be a JSON object, or null for none.
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, ToolError> {
+        let Some(caller) = &self.caller else {
+            return Err(ToolError::Execution(
+                "Tools can't be called from this context".to_string(),
+            ));
+        };
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(ToolError::Execution(format!(
+                "Tool calls can't be nested more than {} deep",
+                MAX_CALL_DEPTH
+            )));
+        }
+        let arguments = match arguments {
+            Value::Null => None,
+            Value::Object(map) => Some(map.into_iter().collect()),
+            _ => {
+                return Err(ToolError::Execution(format!(
+                    "Arguments of {} must be an object",
+                    name
+                )))
+            }
+        };
+
+        let context = Self {
+            depth: self.depth + 1,
+            ..self.clone()
+        };
+        caller(name.to_string(), arguments, context)
+            .await
+            .map_err(|e| ToolError::Execution(format!("{} failed: {}", name, e.message)))
+    }
+
+    /// Publishes `contents` as the resource `mem://path`, for clients to read
+    ///
+    /// Lets tools hand out what they produce by URI rather than inline.
+    /// Fails if the server has no `MemoryResources`.
+    pub fn publish_resource(
+        &self,
+        path: &str,
+        contents: MemoryContents,
+    ) -> Result<Resource, ToolError> {
+        match &self.published {
+            Some(published) => Ok(published.publish(path, contents)),
+            None => Err(ToolError::Execution(
+                "This server doesn't publish resources".to_string(),
+            )),
+        }
+    }
+
+    /// Reads one of the server's resources, as `resources/read` does
+    pub async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, ToolError> {
+        let provider = self
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.provider(uri))
+            .ok_or_else(|| ToolError::Execution(format!("Resource not found: {}", uri)))?;
+        provider
+            .read(uri)
+            .await
+            .map_err(|e| ToolError::Execution(format!("Failed to read {}: {}", uri, e)))
+    }
+
+    /// Asks the calling client to sample a message from its language model
+    ///
+    /// Fails if the client didn't advertise the `sampling` capability.
+    pub async fn create_message(
+        &self,
+        params: CreateMessageRequestParams,
+    ) -> Result<CreateMessageResult, ToolError> {
+        let supported = self
+            .session()
+            .is_some_and(|session| session.client_capabilities.sampling.is_some());
+        if !supported {
+            return Err(ToolError::Execution(
+                "Client does not support sampling".to_string(),
+            ));
+        }
+
+        let result = self.request("sampling/createMessage", params).await?;
+        serde_json::from_value(result).map_err(ToolError::ArgumentParse)
+    }
+
+    /// Asks the calling client's user to fill in a form shaped by `schema`
+    ///
+    /// Blocks the call until the user answers, which may be declining or
+    /// cancelling. Fails if the client didn't advertise the `elicitation`
+    /// capability.
+    pub async fn elicit(
+        &self,
+        schema: ElicitRequestParamsRequestedSchema,
+        message: impl Into<String>,
+    ) -> Result<ElicitResult, ToolError> {
+        let supported = self
+            .session()
+            .is_some_and(|session| session.client_capabilities.elicitation.is_some());
+        if !supported {
+            return Err(ToolError::Execution(
+                "Client does not support elicitation".to_string(),
+            ));
+        }
+
+        let params = ElicitRequestParams {
+            message: message.into(),
+            requested_schema: schema,
+        };
+        let result = self.request("elicitation/create", params).await?;
+        serde_json::from_value(result).map_err(ToolError::ArgumentParse)
+    }
+}
+
+impl Default for ToolContext {
+    /// A context not attached to any client, for calling tools directly
+    fn default() -> Self {
+        Self::new(
+            ConnectionId::new(),
+            Sessions::default(),
+            RequestRouter::default(),
+            RootsState::default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{ElicitResultAction, Role, SamplingMessage};
+    use std::collections::BTreeMap;
+    use tokio::sync::mpsc;
+
+    fn sampling_params() -> CreateMessageRequestParams {
+        CreateMessageRequestParams {
+            include_context: None,
+            max_tokens: 100,
+            messages: vec![SamplingMessage::text(Role::User, "Hello")],
+            metadata: None,
+            model_preferences: None,
+            stop_sequences: None,
+            system_prompt: None,
+            temperature: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_message() {
+        let (outgoing, mut outgoing_rx) = mpsc::channel(8);
+        let router = RequestRouter::new(outgoing);
+        let sessions = Sessions::default();
+        let session_id = ConnectionId::from("client");
+        let context = ToolContext::new(
+            session_id.clone(),
+            sessions.clone(),
+            router.clone(),
+            RootsState::default(),
+        );
+
+        // Sampling is refused until the client advertises it
+        assert!(context.create_message(sampling_params()).await.is_err());
+        sessions.update(&session_id, |session| {
+            session.client_capabilities.sampling = Some(BTreeMap::new());
+        });
+
+        tokio::spawn(async move {
+            let sent = outgoing_rx.recv().await.unwrap();
+            let request: Value = serde_json::from_str(&sent.message).unwrap();
+            assert_eq!(request["params"]["maxTokens"], 100);
+            let reply = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": {
+                    "role": "assistant",
+                    "content": { "type": "text", "text": "Hi there" },
+                    "model": "test-model",
+                },
+            });
+            router.handle_response(&sent.connection, &reply).unwrap();
+        });
+
+        let result = context.create_message(sampling_params()).await.unwrap();
+        assert_eq!(result.model, "test-model");
+        assert_eq!(result.content.as_text(), Some("Hi there"));
+    }
+
+    #[tokio::test]
+    async fn test_elicit() {
+        let (outgoing, mut outgoing_rx) = mpsc::channel(8);
+        let router = RequestRouter::new(outgoing);
+        let sessions = Sessions::default();
+        let session_id = ConnectionId::from("client");
+        let context = ToolContext::new(
+            session_id.clone(),
+            sessions.clone(),
+            router.clone(),
+            RootsState::default(),
+        );
+        let schema = ElicitRequestParamsRequestedSchema::object()
+            .with_property("name", serde_json::json!({ "type": "string" }), true)
+            .with_property("age", serde_json::json!({ "type": "integer" }), false);
+
+        assert!(context
+            .elicit(schema.clone(), "Who are you?")
+            .await
+            .is_err());
+        sessions.update(&session_id, |session| {
+            session.client_capabilities.elicitation = Some(BTreeMap::new());
+        });
+
+        tokio::spawn(async move {
+            let sent = outgoing_rx.recv().await.unwrap();
+            let request: Value = serde_json::from_str(&sent.message).unwrap();
+            assert_eq!(request["method"], "elicitation/create");
+            assert_eq!(request["params"]["message"], "Who are you?");
+            assert_eq!(
+                request["params"]["requestedSchema"],
+                serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "age": { "type": "integer" },
+                    },
+                    "required": ["name"],
+                })
+            );
+            let reply = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "action": "accept", "content": { "name": "Ada" } },
+            });
+            router.handle_response(&sent.connection, &reply).unwrap();
+        });
+
+        let result = context.elicit(schema, "Who are you?").await.unwrap();
+        assert_eq!(result.action, ElicitResultAction::Accept);
+        assert_eq!(result.content.unwrap()["name"], "Ada");
+    }
+}
@@ -1,53 +1,29 @@
-use crate::schema::{CallToolResult, TextContent, Tool, ToolInputSchema};
-use crate::tools::{ToolDef, ToolError};
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolError};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-pub const ECHO_SCHEMA: &str = r#"{
-    "type": "object",
-    "properties": {
-        "message": {
-            "description": "The message to echo",
-            "type": "string"
-        }
-    },
-    "required": ["message"]
-}"#;
-
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct EchoProperties {
     #[schemars(description = "The message to echo", required = true)]
     message: String,
 }
 
-#[derive(Clone, PartialEq, Debug, Serialize)]
+#[derive(Clone, PartialEq, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "echo",
+    description = "Echoes back the input message",
+    properties = EchoProperties
+)]
 pub struct Echo;
 
-impl ToolDef for Echo {
-    const NAME: &'static str = "echo";
-    const DESCRIPTION: &'static str = "Echoes back the input message";
-    type Properties = EchoProperties;
-
-    fn def() -> Tool {
-        let input_schema = serde_json::from_str::<ToolInputSchema>(ECHO_SCHEMA).unwrap();
-        Tool {
-            name: Self::NAME.to_string(),
-            description: Some(Self::DESCRIPTION.to_string()),
-            input_schema,
-        }
-    }
-
-    async fn call(&self, properties: Self::Properties) -> Result<CallToolResult, ToolError> {
-        Ok(CallToolResult {
-            content: vec![serde_json::to_value(TextContent {
-                type_: "text".to_string(),
-                text: properties.message,
-                annotations: None,
-            })
-            .map_err(ToolError::ResultSerialize)?],
-            is_error: Some(false),
-            meta: None,
-        })
+impl Echo {
+    async fn run(
+        &self,
+        properties: EchoProperties,
+        _context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        Ok(CallToolResult::text(properties.message))
     }
 }
 
@@ -63,8 +39,10 @@ mod tests {
             message: "hello".to_string(),
         };
 
-        let result = ToolDef::call(&tool, props).await.unwrap();
-        assert_eq!(result.content[0]["text"].as_str().unwrap(), "hello");
+        let result = ToolDef::call(&tool, props, ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].as_text().unwrap(), "hello");
         assert_eq!(result.is_error, Some(false));
     }
 
@@ -1,37 +1,46 @@
-use crate::schema::{CallToolResult, TextContent, Tool, ToolInputSchema};
-use crate::tools::{ToolDef, ToolError};
+use crate::resources::MemoryContents;
+use crate::schema::{CallToolResult, Content, ResourceContents};
+use crate::tools::{McpTool, ToolContext, ToolDef, ToolError};
+use base64::Engine;
+use chrono::Utc;
 use readability::ExtractOptions;
-use reqwest::header::CONTENT_TYPE;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use reqwest::StatusCode;
 use robotstxt::DefaultMatcher;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::time::Instant;
 use url::Url;
 
-const FETCH_SCHEMA: &str = r#"{
-    "type": "object",
-    "properties": {
-        "url": {
-            "description": "URL to fetch",
-            "type": "string"
-        },
-        "max_length": {
-            "description": "Maximum number of characters to return",
-            "type": "integer",
-            "default": 5000
-        },
-        "start_index": {
-            "description": "Start content from this character index",
-            "type": "integer",
-            "default": 0
-        },
-        "raw": {
-            "description": "Get raw content without markdown conversion",
-            "type": "boolean",
-            "default": false
-        }
-    },
-    "required": ["url"]
-}"#;
+pub mod cache;
+pub mod document;
+pub mod guard;
+
+pub use cache::{CacheConfig, CacheStatus};
+use cache::{CachedResponse, FetchCache};
+use document::{image_mime_type, DocumentFormat};
+use guard::GuardedResolver;
+pub use guard::HostGuard;
+
+/// Number of characters returned when the caller doesn't set `max_length`
+const DEFAULT_MAX_LENGTH: usize = 5000;
+
+/// How a fetched HTML page is turned into text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchMode {
+    /// Extract the main article with readability and convert it to markdown
+    #[default]
+    Readable,
+    /// Convert the whole page to markdown
+    Markdown,
+    /// Return the HTML unchanged
+    Raw,
+}
 
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct FetchProperties {
@@ -43,102 +52,425 @@ pub struct FetchProperties {
     start_index: Option<usize>,
     #[schemars(description = "Get raw content without markdown conversion")]
     raw: Option<bool>,
+    #[schemars(
+        description = "How HTML is returned: 'readable' for the main content as markdown (the default), 'markdown' for the whole page as markdown, or 'raw' for the unchanged HTML"
+    )]
+    mode: Option<FetchMode>,
+    #[schemars(
+        description = "Return the whole content as an embedded resource instead of a page of text"
+    )]
+    as_resource: Option<bool>,
+}
+
+impl FetchProperties {
+    /// The requested mode, where `raw` is kept as a shorthand for [`FetchMode::Raw`]
+    fn mode(&self) -> FetchMode {
+        if self.raw.unwrap_or(false) {
+            FetchMode::Raw
+        } else {
+            self.mode.unwrap_or_default()
+        }
+    }
 }
 
+/// Limits on what the fetch tool downloads
 #[derive(Clone, Debug, Serialize)]
+pub struct FetchConfig {
+    pub user_agent: String,
+    /// Time allowed for each request, including reading the body
+    pub timeout: Duration,
+    /// Redirects followed before giving up
+    pub max_redirects: usize,
+    /// Largest response body downloaded, in bytes
+    pub max_response_bytes: usize,
+    /// Largest image returned as image content, in bytes
+    pub max_image_bytes: usize,
+    /// MIME types that may be fetched, matched as prefixes such as `text/`; any if empty
+    pub allowed_content_types: Vec<String>,
+    /// Minimum time between requests to the same host, unlimited if unset
+    pub min_request_interval: Option<Duration>,
+    /// Hosts and networks that may not be fetched
+    pub guard: HostGuard,
+    /// Reuse earlier responses, revalidating them with their ETag or Last-Modified
+    pub cache: Option<CacheConfig>,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: "Bioma/1.0 (+https://github.com/BiomaAI/bioma)".to_string(),
+            timeout: Duration::from_secs(30),
+            max_redirects: 10,
+            max_response_bytes: 10 * 1024 * 1024,
+            max_image_bytes: 5 * 1024 * 1024,
+            allowed_content_types: Vec::new(),
+            min_request_interval: None,
+            guard: HostGuard::default(),
+            cache: None,
+        }
+    }
+}
+
+/// The limits of [`FetchConfig`] that can be changed while the tool runs
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FetchLimits {
+    pub timeout: Duration,
+    pub max_response_bytes: usize,
+    pub allowed_content_types: Vec<String>,
+    pub min_request_interval: Option<Duration>,
+}
+
+impl From<&FetchConfig> for FetchLimits {
+    fn from(config: &FetchConfig) -> Self {
+        Self {
+            timeout: config.timeout,
+            max_response_bytes: config.max_response_bytes,
+            allowed_content_types: config.allowed_content_types.clone(),
+            min_request_interval: config.min_request_interval,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "fetch",
+    description = "Fetches a URL from the internet and extracts its contents as markdown",
+    properties = FetchProperties
+)]
 pub struct Fetch {
     #[serde(skip)]
     client: reqwest::Client,
-    user_agent: String,
+    config: FetchConfig,
+    /// Shared by clones, so limits set on one apply to all
+    #[serde(skip)]
+    limits: Arc<RwLock<FetchLimits>>,
+    /// When the next request to each host may be sent
+    #[serde(skip)]
+    next_request: Arc<Mutex<HashMap<String, Instant>>>,
+    #[serde(skip)]
+    cache: Option<Arc<FetchCache>>,
 }
 
 impl Default for Fetch {
     fn default() -> Self {
-        Self {
-            client: reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
-                .build()
-                .unwrap_or_default(),
-            user_agent: "Bioma/1.0 (+https://github.com/BiomaAI/bioma)".to_string(),
-        }
+        Self::new(FetchConfig::default())
     }
 }
 
-impl ToolDef for Fetch {
-    const NAME: &'static str = "fetch";
-    const DESCRIPTION: &'static str =
-        "Fetches a URL from the internet and extracts its contents as markdown";
-    type Properties = FetchProperties;
+impl Fetch {
+    pub fn new(config: FetchConfig) -> Self {
+        let max_redirects = config.max_redirects;
+        let guard = Arc::new(config.guard.clone());
+        let redirect_guard = guard.clone();
+        let redirect = reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_redirects {
+                return attempt.error("too many redirects");
+            }
+            match redirect_guard.check_url_literal(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e),
+            }
+        });
+
+        let mut client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .redirect(redirect);
+        if !guard.is_open() {
+            client = client.dns_resolver(Arc::new(GuardedResolver(guard)));
+        }
 
-    fn def() -> Tool {
-        let input_schema = serde_json::from_str::<ToolInputSchema>(FETCH_SCHEMA).unwrap();
-        Tool {
-            name: Self::NAME.to_string(),
-            description: Some(Self::DESCRIPTION.to_string()),
-            input_schema,
+        Self {
+            client: client.build().unwrap_or_default(),
+            cache: config
+                .cache
+                .clone()
+                .map(|cache| Arc::new(FetchCache::new(cache))),
+            limits: Arc::new(RwLock::new(FetchLimits::from(&config))),
+            config,
+            next_request: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    async fn call(&self, properties: Self::Properties) -> Result<CallToolResult, ToolError> {
+    pub fn limits(&self) -> FetchLimits {
+        self.limits
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replaces the limits of this tool and its clones, starting with the next request
+    pub fn set_limits(&self, limits: FetchLimits) {
+        *self.limits.write().unwrap_or_else(|e| e.into_inner()) = limits;
+    }
+
+    async fn run(
+        &self,
+        properties: FetchProperties,
+        context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
         // Validate URL
         let url = Url::parse(&properties.url);
         let url = match url {
             Ok(url) => url,
-            Err(e) => return Ok(Self::error(format!("Invalid URL: {}", e))),
+            Err(e) => return Ok(CallToolResult::error(format!("Invalid URL: {}", e))),
         };
 
+        // Refuse internal hosts before sending anything to them
+        if let Err(e) = self.config.guard.check_url(&url).await {
+            return Ok(CallToolResult::error(format!("Access denied: {}", e)));
+        }
+        if let Err(violation) = context.policy().check_url(&url) {
+            return Ok(violation.into_result(Self::NAME));
+        }
+
         // Check robots.txt
         if let Err(e) = self.check_robots_txt(&url).await {
-            return Ok(Self::error(format!("Access denied by robots.txt: {}", e)));
+            return Ok(CallToolResult::error(format!(
+                "Access denied by robots.txt: {}",
+                e
+            )));
         }
 
-        // Fetch the webpage
-        let response = match self.fetch_url(&url).await {
-            Ok(r) => r,
-            Err(e) => return Ok(Self::error(format!("Failed to fetch URL: {}", e))),
+        // Fetch the webpage, unless the cached copy is still fresh
+        let cached = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(url.as_str()));
+        let (response, cache_status) = match cached {
+            Some(cached) if cached.is_fresh(Utc::now()) => (cached, CacheStatus::Hit),
+            cached => {
+                let response = match self.fetch_url(&url, cached.as_ref()).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        return Ok(CallToolResult::error(format!("Failed to fetch URL: {}", e)))
+                    }
+                };
+                // Redirects may have led somewhere the policy doesn't allow
+                if let Err(violation) = context.policy().check_url(response.url()) {
+                    return Ok(violation.into_result(Self::NAME));
+                }
+                match self.read_response(&url, response, cached).await {
+                    Ok(response) => response,
+                    Err(e) => {
+                        return Ok(CallToolResult::error(format!(
+                            "Failed to process content: {}",
+                            e
+                        )))
+                    }
+                }
+            }
         };
 
+        if response.format == DocumentFormat::Image {
+            return Ok(self.image_result(&response, cache_status));
+        }
+
         // Process content
-        let content = self.process_content(&url, response, &properties).await;
+        let format = response.format;
+        let content = self.process_content(&url, response, &properties);
         let content = match content {
             Ok(content) => content,
-            Err(e) => return Ok(Self::error(format!("Failed to process content: {}", e))),
+            Err(e) => {
+                return Ok(CallToolResult::error(format!(
+                    "Failed to process content: {}",
+                    e
+                )))
+            }
         };
 
+        let mime_type = content_mime_type(format, properties.mode());
+        if properties.as_resource.unwrap_or(false) {
+            // Published when the server allows it, so the client can read it again later
+            let uri = publish_page(&context, &url, mime_type, &content)
+                .unwrap_or_else(|| url.to_string());
+            let total_length = content.chars().count();
+            let mut result = CallToolResult::success(vec![Content::resource(
+                ResourceContents::text(uri, content).with_mime_type(mime_type),
+            )]);
+            let mut meta = BTreeMap::new();
+            meta.insert("totalLength".to_string(), total_length.into());
+            meta.insert("format".to_string(), format.as_str().into());
+            result.meta = Some(meta);
+            return Ok(result);
+        }
+
+        // Apply start_index and max_length
+        let start = properties.start_index.unwrap_or(0);
+        let max_length = properties.max_length.unwrap_or(DEFAULT_MAX_LENGTH);
+        let page = Page::new(&content, start, max_length);
+
         // Create result
-        let result = Self::success(&content);
+        let mut result = CallToolResult::text(&page.text);
+        let mut published = None;
+        if let Some(next) = page.next_start_index {
+            published = publish_page(&context, &url, mime_type, &content);
+            let rest = match &published {
+                Some(uri) => format!(
+                    "Call fetch again with start_index={} to continue, or read the whole content from {}",
+                    next, uri
+                ),
+                None => format!("Call fetch again with start_index={} to continue", next),
+            };
+            result.content.push(Content::text(format!(
+                "<content truncated: showing characters {}-{} of {}. {}>",
+                start, next, page.total_length, rest
+            )));
+        }
+        let mut meta = page.meta(start);
+        if let Some(uri) = published {
+            meta.insert("resourceUri".to_string(), uri.into());
+        }
+        meta.insert("format".to_string(), format.as_str().into());
+        if self.cache.is_some() {
+            meta.insert("cache".to_string(), cache_status.as_str().into());
+        }
+        result.meta = Some(meta);
 
         Ok(result)
     }
 }
 
+/// The MIME type of content processed from a page of `format` in `mode`
+fn content_mime_type(format: DocumentFormat, mode: FetchMode) -> &'static str {
+    match (format, mode) {
+        (DocumentFormat::Html, FetchMode::Raw) => "text/html",
+        (DocumentFormat::Html, _) => "text/markdown",
+        _ => "text/plain",
+    }
+}
+
+/// Publishes the whole `content` of `url` as a `mem://fetch/...` resource, if the server publishes resources
+fn publish_page(
+    context: &ToolContext,
+    url: &Url,
+    mime_type: &str,
+    content: &str,
+) -> Option<String> {
+    let path = format!(
+        "fetch/{}{}",
+        url.host_str().unwrap_or_default(),
+        url.path().trim_end_matches('/')
+    );
+    context
+        .publish_resource(&path, MemoryContents::text(mime_type, content))
+        .ok()
+        .map(|resource| resource.uri)
+}
+
+/// A body downloaded as the site sent it, by [`Fetch::download`]
+#[derive(Clone, Debug)]
+pub struct Download {
+    pub content_type: String,
+    pub body: Vec<u8>,
+    pub cache: CacheStatus,
+}
+
 impl Fetch {
-    fn error(error_message: impl Into<String>) -> CallToolResult {
-        CallToolResult {
-            content: vec![serde_json::to_value(TextContent {
-                type_: "text".to_string(),
-                text: error_message.into(),
-                annotations: None,
-            })
-            .unwrap()],
-            is_error: Some(true),
-            meta: None,
+    /// Downloads `url` unchanged, with the same checks and limits as the tool and through its cache
+    pub async fn download(&self, url: &Url) -> Result<Download, ToolError> {
+        if let Err(e) = self.config.guard.check_url(url).await {
+            return Err(ToolError::Custom(format!("Access denied: {}", e)));
+        }
+        self.check_robots_txt(url).await?;
+
+        // Cached copies are only of use if they kept the body
+        let cached = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(url.as_str()))
+            .filter(|cached| cached.bytes().is_some());
+        let fresh = cached.as_ref().filter(|cached| cached.is_fresh(Utc::now()));
+        if let Some(cached) = fresh {
+            return Ok(Download::cached(cached, CacheStatus::Hit));
+        }
+
+        let response = self
+            .fetch_url(url, cached.as_ref())
+            .await
+            .map_err(|e| ToolError::Custom(format!("Failed to fetch URL: {}", e)))?;
+        if let Some(mut cached) = cached.filter(|_| response.status() == StatusCode::NOT_MODIFIED) {
+            cached.update_headers(response.headers());
+            self.remember(&cached);
+            return Ok(Download::cached(&cached, CacheStatus::Revalidated));
+        }
+
+        let (content_type, headers, body) = self.read_new(response).await?;
+        // Cached like the tool would, unless its text can't be extracted
+        let format = DocumentFormat::detect(&content_type, &body);
+        if let Ok(text) = format.extract_text(body.clone()).await {
+            let mut response =
+                CachedResponse::new(url.as_str(), &headers, content_type.clone(), text);
+            response.format = format;
+            response.keep_bytes(&body);
+            self.remember(&response);
         }
+        Ok(Download {
+            content_type,
+            body,
+            cache: CacheStatus::Miss,
+        })
     }
+}
 
-    fn success(message: impl Into<String>) -> CallToolResult {
-        CallToolResult {
-            content: vec![serde_json::to_value(TextContent {
-                type_: "text".to_string(),
-                text: message.into(),
-                annotations: None,
-            })
-            .unwrap()],
-            is_error: Some(false),
-            meta: None,
+impl Download {
+    fn cached(response: &CachedResponse, cache: CacheStatus) -> Self {
+        Self {
+            content_type: response.content_type.clone(),
+            body: response.bytes().unwrap_or_default(),
+            cache,
         }
     }
 
+    /// The MIME type of the body if it's an image, going by its bytes before the site's word
+    pub fn image_mime_type(&self) -> Option<String> {
+        image_mime_type(&self.content_type, &self.body)
+    }
+
+    /// The MIME type the site sent, without parameters
+    pub fn mime_type(&self) -> Option<String> {
+        let mime = self.content_type.split(';').next()?.trim().to_lowercase();
+        (!mime.is_empty()).then_some(mime)
+    }
+}
+
+/// The part of the fetched content returned by one call
+struct Page {
+    text: String,
+    /// Length of the whole content in characters
+    total_length: usize,
+    /// Where the next page starts, if the content was truncated
+    next_start_index: Option<usize>,
+}
+
+impl Page {
+    /// Takes up to `max_length` characters starting at character `start`
+    fn new(content: &str, start: usize, max_length: usize) -> Self {
+        let total_length = content.chars().count();
+        let text: String = content.chars().skip(start).take(max_length).collect();
+        let end = start.saturating_add(text.chars().count());
+        Self {
+            text,
+            total_length,
+            next_start_index: (end < total_length).then_some(end),
+        }
+    }
+
+    fn meta(&self, start: usize) -> BTreeMap<String, Value> {
+        let mut meta = BTreeMap::new();
+        meta.insert("totalLength".to_string(), self.total_length.into());
+        meta.insert("startIndex".to_string(), start.into());
+        meta.insert(
+            "truncated".to_string(),
+            self.next_start_index.is_some().into(),
+        );
+        if let Some(next) = self.next_start_index {
+            meta.insert("nextStartIndex".to_string(), next.into());
+        }
+        meta
+    }
+}
+
+impl Fetch {
     async fn check_robots_txt(&self, url: &Url) -> Result<(), ToolError> {
         let robots_url = url
             .join("/robots.txt")
@@ -147,7 +479,8 @@ impl Fetch {
         let response = self
             .client
             .get(robots_url)
-            .header("User-Agent", &self.user_agent)
+            .timeout(self.limits().timeout)
+            .header("User-Agent", &self.config.user_agent)
             .send()
             .await;
 
@@ -165,7 +498,7 @@ impl Fetch {
                 let mut matcher = DefaultMatcher::default();
                 if !matcher.one_agent_allowed_by_robots(
                     &robots_content,
-                    &self.user_agent,
+                    &self.config.user_agent,
                     url.as_str(),
                 ) {
                     return Err(ToolError::Custom("Access denied by robots.txt".to_string()));
@@ -176,20 +509,64 @@ impl Fetch {
         }
     }
 
-    async fn fetch_url(&self, url: &Url) -> Result<reqwest::Response, reqwest::Error> {
-        self.client
+    /// Sends the request, made conditional on the validators of a cached copy
+    async fn fetch_url(
+        &self,
+        url: &Url,
+        cached: Option<&CachedResponse>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        self.wait_for_host(url).await;
+        let mut request = self
+            .client
             .get(url.as_str())
-            .header("User-Agent", &self.user_agent)
-            .send()
-            .await
+            .timeout(self.limits().timeout)
+            .header("User-Agent", &self.config.user_agent);
+        if let Some(cached) = cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        request.send().await?.error_for_status()
     }
 
-    async fn process_content(
+    /// Reads the response, or takes the cached copy if the site says it's unchanged
+    async fn read_response(
         &self,
         url: &Url,
         response: reqwest::Response,
-        properties: &FetchProperties,
-    ) -> Result<String, ToolError> {
+        cached: Option<CachedResponse>,
+    ) -> Result<(CachedResponse, CacheStatus), ToolError> {
+        let (response, status) = match cached {
+            Some(mut cached) if response.status() == StatusCode::NOT_MODIFIED => {
+                cached.update_headers(response.headers());
+                (cached, CacheStatus::Revalidated)
+            }
+            _ => {
+                let (content_type, headers, body) = self.read_new(response).await?;
+                let format = DocumentFormat::detect(&content_type, &body);
+                let text = format
+                    .extract_text(body.clone())
+                    .await
+                    .map_err(ToolError::Custom)?;
+                let mut response = CachedResponse::new(url.as_str(), &headers, content_type, text);
+                response.format = format;
+                response.keep_bytes(&body);
+                (response, CacheStatus::Miss)
+            }
+        };
+
+        self.remember(&response);
+        Ok((response, status))
+    }
+
+    /// Reads the body of a response that isn't a cached copy, checking its type and size
+    async fn read_new(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<(String, HeaderMap, Vec<u8>), ToolError> {
         let content_type = response
             .headers()
             .get(CONTENT_TYPE)
@@ -197,56 +574,155 @@ impl Fetch {
             .unwrap_or_default()
             .to_string();
 
-        let html = response
-            .text()
-            .await
-            .map_err(|e| ToolError::Custom(format!("Failed to get response text: {}", e)))?;
+        if !self.is_allowed_content_type(&content_type) {
+            return Err(ToolError::Custom(format!(
+                "Content type {} is not allowed",
+                content_type
+            )));
+        }
 
-        let is_html = html.trim().starts_with("<html") || content_type.contains("text/html");
+        let headers = response.headers().clone();
+        let body = self.read_body(response).await?;
+        Ok((content_type, headers, body))
+    }
 
-        let content = if properties.raw.unwrap_or(false) || !is_html {
-            html
-        } else {
-            // Convert the HTML string into a cursor that implements Read
-            let mut cursor = std::io::Cursor::new(html);
-
-            // Use readability for main content extraction
-            let readable = readability::extract(&mut cursor, url, ExtractOptions::default());
-            let readable = match readable {
-                Ok(readable) => readable,
-                Err(e) => {
-                    return Err(ToolError::Custom(format!(
-                        "Failed to extract content: {}",
-                        e
-                    )))
-                }
-            };
+    fn remember(&self, response: &CachedResponse) {
+        if let Some(cache) = &self.cache {
+            if response.is_cacheable() {
+                cache.insert(response.clone());
+            }
+        }
+    }
 
-            // Convert to markdown
-            html2md::parse_html(&readable.content)
+    /// A fetched image as image content, unless it's larger than `max_image_bytes`
+    fn image_result(&self, response: &CachedResponse, cache_status: CacheStatus) -> CallToolResult {
+        let Some(bytes) = response.bytes() else {
+            return CallToolResult::error("Failed to process content: the image wasn't kept");
         };
+        if bytes.len() > self.config.max_image_bytes {
+            return CallToolResult::error(format!(
+                "Image of {} bytes is larger than the {} bytes allowed",
+                bytes.len(),
+                self.config.max_image_bytes
+            ));
+        }
+        let mime_type = image_mime_type(&response.content_type, &bytes)
+            .unwrap_or_else(|| response.content_type.clone());
 
-        // Apply start_index and max_length
-        let start = properties.start_index.unwrap_or(0);
-        let content = if start < content.len() {
-            content[start..].to_string()
-        } else {
-            String::new()
-        };
+        let data = base64::engine::general_purpose::STANDARD.encode(&bytes);
+        let mut result = CallToolResult::image(data, mime_type);
+        let mut meta = BTreeMap::new();
+        meta.insert("format".to_string(), DocumentFormat::Image.as_str().into());
+        meta.insert("size".to_string(), bytes.len().into());
+        if self.cache.is_some() {
+            meta.insert("cache".to_string(), cache_status.as_str().into());
+        }
+        result.meta = Some(meta);
+        result
+    }
 
-        let content = if let Some(max_length) = properties.max_length {
-            content.chars().take(max_length).collect()
-        } else {
-            content.chars().take(5000).collect()
+    fn process_content(
+        &self,
+        url: &Url,
+        response: CachedResponse,
+        properties: &FetchProperties,
+    ) -> Result<String, ToolError> {
+        let is_html = response.format == DocumentFormat::Html;
+        let html = response.body;
+
+        let content = match properties.mode() {
+            _ if !is_html => html,
+            FetchMode::Raw => html,
+            FetchMode::Markdown => html2md::parse_html(&html),
+            FetchMode::Readable => {
+                // Convert the HTML string into a cursor that implements Read
+                let mut cursor = std::io::Cursor::new(html);
+
+                // Use readability for main content extraction
+                let readable = readability::extract(&mut cursor, url, ExtractOptions::default());
+                let readable = match readable {
+                    Ok(readable) => readable,
+                    Err(e) => {
+                        return Err(ToolError::Custom(format!(
+                            "Failed to extract content: {}",
+                            e
+                        )))
+                    }
+                };
+
+                // Convert to markdown
+                html2md::parse_html(&readable.content)
+            }
         };
 
         Ok(content)
     }
+
+    /// Waits until the configured interval since the last request to the host has passed
+    async fn wait_for_host(&self, url: &Url) {
+        let Some(interval) = self.limits().min_request_interval else {
+            return;
+        };
+        let host = url.host_str().unwrap_or_default().to_string();
+
+        // Reserve the next slot before sleeping so concurrent calls queue up
+        let slot = {
+            let mut next_request = self.next_request.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let slot = next_request.get(&host).map_or(now, |next| (*next).max(now));
+            next_request.insert(host, slot + interval);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    fn is_allowed_content_type(&self, content_type: &str) -> bool {
+        let allowed = self.limits().allowed_content_types;
+        // Servers that don't say what they send are handled by sniffing the body
+        if allowed.is_empty() || content_type.is_empty() {
+            return true;
+        }
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        allowed
+            .iter()
+            .any(|allowed| mime.starts_with(&allowed.to_lowercase()))
+    }
+
+    /// Reads the body, refusing responses larger than the configured limit
+    async fn read_body(&self, mut response: reqwest::Response) -> Result<Vec<u8>, ToolError> {
+        let limit = self.limits().max_response_bytes;
+        let too_large = || ToolError::Custom(format!("Response is larger than {} bytes", limit));
+        if response
+            .content_length()
+            .is_some_and(|length| length > limit as u64)
+        {
+            return Err(too_large());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ToolError::Custom(format!("Failed to get response text: {}", e)))?
+        {
+            if body.len() + chunk.len() > limit {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::resources::MemoryResources;
     use mockito;
 
     #[tokio::test]
@@ -280,9 +756,11 @@ mod tests {
             max_length: None,
             start_index: None,
             raw: None,
+            mode: None,
+            as_resource: None,
         };
 
-        let result = tool.call(props).await.unwrap();
+        let result = tool.call(props, ToolContext::default()).await.unwrap();
         assert_eq!(result.is_error, Some(false));
 
         // Test disallowed URL
@@ -291,9 +769,11 @@ mod tests {
             max_length: None,
             start_index: None,
             raw: None,
+            mode: None,
+            as_resource: None,
         };
 
-        let result = tool.call(props).await.unwrap();
+        let result = tool.call(props, ToolContext::default()).await.unwrap();
         assert_eq!(result.is_error, Some(true));
 
         // Clean up mocks
@@ -319,20 +799,96 @@ mod tests {
             max_length: None,
             start_index: None,
             raw: Some(true),
+            mode: None,
+            as_resource: None,
         };
 
-        let result = tool.call(props).await.unwrap();
+        let result = tool.call(props, ToolContext::default()).await.unwrap();
         assert_eq!(result.is_error, Some(false));
         assert!(result.content[0]
-            .get("text")
-            .unwrap()
-            .as_str()
+            .as_text()
             .unwrap()
             .contains("<html><body>"));
 
         html_mock.remove_async().await;
     }
 
+    #[tokio::test]
+    async fn test_fetch_modes() {
+        let mut server = mockito::Server::new_async().await;
+        let _page = server
+            .mock("GET", "/page")
+            .with_status(200)
+            .with_header("content-type", "text/html")
+            .with_body("<html><body><h1>Title</h1><p>Content</p></body></html>")
+            .create_async()
+            .await;
+
+        let tool = Fetch::default();
+        let fetch = |mode| {
+            let props = FetchProperties {
+                url: format!("{}/page", server.url()),
+                max_length: None,
+                start_index: None,
+                raw: None,
+                mode: Some(mode),
+                as_resource: None,
+            };
+            let tool = tool.clone();
+            async move {
+                let result = tool.call(props, ToolContext::default()).await.unwrap();
+                result.content[0].as_text().unwrap().to_string()
+            }
+        };
+
+        assert!(fetch(FetchMode::Raw).await.contains("<h1>Title</h1>"));
+        let markdown = fetch(FetchMode::Markdown).await;
+        assert!(markdown.contains("Title") && markdown.contains("Content"));
+        assert!(!markdown.contains("<h1>"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_documents() {
+        let mut server = mockito::Server::new_async().await;
+        let _text = server
+            .mock("GET", "/notes.txt")
+            .with_header("content-type", "text/plain")
+            .with_body("plain notes")
+            .create_async()
+            .await;
+        let _pdf = server
+            .mock("GET", "/broken.pdf")
+            .with_header("content-type", "application/pdf")
+            .with_body(b"%PDF-1.7\n\x00\xff not really a pdf")
+            .create_async()
+            .await;
+        let props = |path: &str| FetchProperties {
+            url: format!("{}{}", server.url(), path),
+            max_length: None,
+            start_index: None,
+            raw: None,
+            mode: None,
+            as_resource: None,
+        };
+
+        let tool = Fetch::default();
+        let result = tool
+            .call(props("/notes.txt"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].as_text(), Some("plain notes"));
+        assert_eq!(result.meta.unwrap()["format"], "text");
+
+        // Binary documents are never returned as garbled text
+        let result = tool
+            .call(props("/broken.pdf"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+        let text = result.content[0].as_text().unwrap().to_lowercase();
+        assert!(text.contains("pdf"), "{}", text);
+    }
+
     #[tokio::test]
     async fn test_fetch_with_length_limits() {
         let mut server = mockito::Server::new_async().await;
@@ -353,13 +909,20 @@ mod tests {
             max_length: Some(5),
             start_index: None,
             raw: Some(true),
+            mode: None,
+            as_resource: None,
         };
 
-        let result = tool.call(props).await.unwrap();
-        assert_eq!(
-            result.content[0].get("text").unwrap().as_str().unwrap(),
-            "12345"
-        );
+        let result = tool.call(props, ToolContext::default()).await.unwrap();
+        assert_eq!(result.content[0].as_text().unwrap(), "12345");
+        assert!(result.content[1]
+            .as_text()
+            .unwrap()
+            .contains("start_index=5"));
+        let meta = result.meta.unwrap();
+        assert_eq!(meta["totalLength"], 10);
+        assert_eq!(meta["nextStartIndex"], 5);
+        assert_eq!(meta["truncated"], true);
 
         // Test start_index
         let props = FetchProperties {
@@ -367,15 +930,337 @@ mod tests {
             max_length: None,
             start_index: Some(5),
             raw: Some(true),
+            mode: None,
+            as_resource: None,
+        };
+
+        let result = tool.call(props, ToolContext::default()).await.unwrap();
+        assert_eq!(result.content[0].as_text().unwrap(), "67890");
+        assert_eq!(result.content.len(), 1);
+        let meta = result.meta.unwrap();
+        assert_eq!(meta["truncated"], false);
+        assert!(!meta.contains_key("nextStartIndex"));
+
+        html_mock.remove_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fetch_images() {
+        let mut server = mockito::Server::new_async().await;
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let _png = server
+            .mock("GET", "/logo")
+            .with_header("content-type", "application/octet-stream")
+            .with_body(png)
+            .create_async()
+            .await;
+        let props = |path: &str| FetchProperties {
+            url: format!("{}{}", server.url(), path),
+            max_length: None,
+            start_index: None,
+            raw: None,
+            mode: None,
+            as_resource: None,
+        };
+
+        let tool = Fetch::default();
+        let result = tool
+            .call(props("/logo"), ToolContext::default())
+            .await
+            .unwrap();
+        match &result.content[0] {
+            Content::Image {
+                data, mime_type, ..
+            } => {
+                assert_eq!(mime_type, "image/png");
+                let decoded = base64::engine::general_purpose::STANDARD
+                    .decode(data)
+                    .unwrap();
+                assert_eq!(decoded, png);
+            }
+            other => panic!("expected an image, got {:?}", other),
+        }
+        let meta = result.meta.unwrap();
+        assert_eq!(meta["format"], "image");
+        assert_eq!(meta["size"], png.len());
+
+        let tool = Fetch::new(FetchConfig {
+            max_image_bytes: 4,
+            ..Default::default()
+        });
+        let result = tool
+            .call(props("/logo"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_as_resource() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/long.txt")
+            .with_header("content-type", "text/plain")
+            .with_body("1234567890")
+            .create_async()
+            .await;
+        let url = format!("{}/long.txt", server.url());
+        let props = |max_length, as_resource| FetchProperties {
+            url: url.clone(),
+            max_length,
+            start_index: None,
+            raw: None,
+            mode: None,
+            as_resource,
         };
+        let tool = Fetch::default();
+
+        // Without published resources the URL itself is the URI
+        let result = tool
+            .call(props(None, Some(true)), ToolContext::default())
+            .await
+            .unwrap();
+        let resource = result.content[0].as_resource().unwrap();
+        assert_eq!(resource.uri(), url);
+        assert_eq!(resource.as_text(), Some("1234567890"));
+        assert_eq!(resource.mime_type(), Some("text/plain"));
+
+        let published = MemoryResources::new();
+        let context = ToolContext::default().with_published_resources(published.clone());
+        let result = tool
+            .call(props(None, Some(true)), context.clone())
+            .await
+            .unwrap();
+        let uri = result.content[0].as_resource().unwrap().uri();
+        assert_eq!(uri, "mem://fetch/127.0.0.1/long.txt");
 
-        let result = tool.call(props).await.unwrap();
+        // A truncated page points at the whole content
+        let result = tool.call(props(Some(4), None), context).await.unwrap();
+        assert_eq!(result.content[0].as_text(), Some("1234"));
+        let notice = result.content[1].as_text().unwrap();
+        assert!(
+            notice.contains("mem://fetch/127.0.0.1/long.txt"),
+            "{}",
+            notice
+        );
         assert_eq!(
-            result.content[0].get("text").unwrap().as_str().unwrap(),
-            "67890"
+            result.meta.unwrap()["resourceUri"],
+            "mem://fetch/127.0.0.1/long.txt"
         );
+    }
 
-        html_mock.remove_async().await;
+    #[tokio::test]
+    async fn test_fetch_config_limits() {
+        let mut server = mockito::Server::new_async().await;
+        let _large = server
+            .mock("GET", "/large")
+            .with_status(200)
+            .with_header("content-type", "text/plain")
+            .with_body("x".repeat(100))
+            .create_async()
+            .await;
+        let _image = server
+            .mock("GET", "/image")
+            .with_status(200)
+            .with_header("content-type", "image/png")
+            .with_body("png")
+            .create_async()
+            .await;
+        let _redirect = server
+            .mock("GET", "/redirect")
+            .with_status(302)
+            .with_header("location", "/large")
+            .create_async()
+            .await;
+
+        let tool = Fetch::new(FetchConfig {
+            max_redirects: 0,
+            max_response_bytes: 50,
+            allowed_content_types: vec!["text/".to_string()],
+            ..Default::default()
+        });
+        let props = |path: &str| FetchProperties {
+            url: format!("{}{}", server.url(), path),
+            max_length: None,
+            start_index: None,
+            raw: Some(true),
+            mode: None,
+            as_resource: None,
+        };
+
+        for path in ["/large", "/image", "/redirect"] {
+            let result = tool
+                .call(props(path), ToolContext::default())
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true), "{} should be refused", path);
+        }
+        let result = tool
+            .call(props("/image"), ToolContext::default())
+            .await
+            .unwrap();
+        assert!(result.content[0]
+            .as_text()
+            .unwrap()
+            .contains("image/png is not allowed"));
+
+        let tool = Fetch::new(FetchConfig {
+            max_response_bytes: 100,
+            ..Default::default()
+        });
+        let result = tool
+            .call(props("/redirect"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(false));
+        // Limits set while the tool runs apply to every clone of it
+        let registered = tool.clone();
+        tool.set_limits(FetchLimits {
+            max_response_bytes: 10,
+            ..tool.limits()
+        });
+        let result = registered
+            .call(props("/large"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_blocks_private_networks() {
+        let mut server = mockito::Server::new_async().await;
+        let page = server
+            .mock("GET", "/page")
+            .with_status(200)
+            .with_body("secret")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let tool = Fetch::new(FetchConfig {
+            guard: HostGuard {
+                block_private_networks: true,
+                denied_hosts: Vec::new(),
+            },
+            ..Default::default()
+        });
+        for url in [
+            format!("{}/page", server.url()),
+            format!("http://localhost:{}/page", server.socket_address().port()),
+        ] {
+            let props = FetchProperties {
+                url,
+                max_length: None,
+                start_index: None,
+                raw: Some(true),
+                mode: None,
+                as_resource: None,
+            };
+            let result = tool.call(props, ToolContext::default()).await.unwrap();
+            assert_eq!(result.is_error, Some(true));
+            assert!(result.content[0]
+                .as_text()
+                .unwrap()
+                .starts_with("Access denied"));
+        }
+        page.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rate_limit() {
+        let tool = Fetch::new(FetchConfig {
+            min_request_interval: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        let url = Url::parse("https://example.com/page").unwrap();
+        let other = Url::parse("https://example.org/page").unwrap();
+
+        let start = Instant::now();
+        tool.wait_for_host(&url).await;
+        tool.wait_for_host(&other).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        tool.wait_for_host(&url).await;
+        tool.wait_for_host(&url).await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_page_offsets_are_characters() {
+        let page = Page::new("héllo wörld", 3, 4);
+        assert_eq!(page.text, "lo w");
+        assert_eq!(page.total_length, 11);
+        assert_eq!(page.next_start_index, Some(7));
+
+        let page = Page::new("héllo", 10, 4);
+        assert_eq!(page.text, "");
+        assert_eq!(page.next_start_index, None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_cache() {
+        let mut server = mockito::Server::new_async().await;
+        let page = server
+            .mock("GET", "/page")
+            .match_header("if-none-match", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_header("etag", "\"v1\"")
+            .with_body("cached body")
+            .expect(1)
+            .create_async()
+            .await;
+        let not_modified = server
+            .mock("GET", "/page")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .expect(1)
+            .create_async()
+            .await;
+        let fresh = server
+            .mock("GET", "/fresh")
+            .with_status(200)
+            .with_header("cache-control", "max-age=60")
+            .with_body("fresh body")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let tool = Fetch::new(FetchConfig {
+            cache: Some(CacheConfig::default()),
+            ..Default::default()
+        });
+        let props = |path: &str| FetchProperties {
+            url: format!("{}{}", server.url(), path),
+            max_length: None,
+            start_index: None,
+            raw: Some(true),
+            mode: None,
+            as_resource: None,
+        };
+
+        for (path, body, status) in [
+            ("/page", "cached body", "miss"),
+            ("/page", "cached body", "revalidated"),
+            ("/fresh", "fresh body", "miss"),
+            ("/fresh", "fresh body", "hit"),
+        ] {
+            let result = tool
+                .call(props(path), ToolContext::default())
+                .await
+                .unwrap();
+            assert_eq!(result.content[0].as_text(), Some(body));
+            assert_eq!(result.meta.unwrap()["cache"], status, "{}", path);
+        }
+        page.assert_async().await;
+        not_modified.assert_async().await;
+        fresh.assert_async().await;
+
+        // Without a cache nothing is reported
+        let result = Fetch::default()
+            .call(props("/fresh"), ToolContext::default())
+            .await
+            .unwrap();
+        assert!(!result.meta.unwrap().contains_key("cache"));
     }
 
     #[tokio::test]
@@ -395,9 +1280,11 @@ mod tests {
             max_length: None,
             start_index: None,
             raw: None,
+            mode: None,
+            as_resource: None,
         };
 
-        let result = tool.call(props).await.unwrap();
+        let result = tool.call(props, ToolContext::default()).await.unwrap();
         assert_eq!(result.is_error, Some(true));
 
         // Test invalid URL
@@ -406,9 +1293,11 @@ mod tests {
             max_length: None,
             start_index: None,
             raw: None,
+            mode: None,
+            as_resource: None,
         };
 
-        let result = tool.call(props).await.unwrap();
+        let result = tool.call(props, ToolContext::default()).await.unwrap();
         assert_eq!(result.is_error, Some(true));
 
         not_found_mock.remove_async().await;
@@ -0,0 +1,335 @@
+use super::document::DocumentFormat;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, CACHE_CONTROL, ETAG, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use tracing::{debug, warn};
+
+/// Settings of the fetch tool's response cache
+#[derive(Clone, Debug, Serialize)]
+pub struct CacheConfig {
+    /// Number of responses kept in memory
+    pub capacity: usize,
+    /// Directory responses are also saved to, so they survive restarts
+    pub directory: Option<PathBuf>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 100,
+            directory: None,
+        }
+    }
+}
+
+/// How a fetch was served
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// Downloaded from the remote site
+    Miss,
+    /// Served from the cache without contacting the site
+    Hit,
+    /// The site confirmed the cached copy is still current
+    Revalidated,
+}
+
+impl CacheStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheStatus::Miss => "miss",
+            CacheStatus::Hit => "hit",
+            CacheStatus::Revalidated => "revalidated",
+        }
+    }
+}
+
+/// A downloaded page and the validators needed to revalidate it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub content_type: String,
+    /// What the body was extracted from
+    #[serde(default)]
+    pub format: DocumentFormat,
+    /// Text of the body, already extracted from documents
+    pub body: String,
+    /// Base64 of the body as downloaded, kept when it isn't the text itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Until when the response may be used without asking the site, from `max-age`
+    pub fresh_until: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    no_store: bool,
+}
+
+impl CachedResponse {
+    pub fn new(url: &str, headers: &HeaderMap, content_type: String, body: String) -> Self {
+        let mut response = Self {
+            url: url.to_string(),
+            content_type,
+            format: DocumentFormat::default(),
+            body,
+            blob: None,
+            etag: None,
+            last_modified: None,
+            fresh_until: None,
+            no_store: false,
+        };
+        response.update_headers(headers);
+        response
+    }
+
+    /// Takes the validators and freshness of a new response for the same page
+    pub fn update_headers(&mut self, headers: &HeaderMap) {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        if let Some(etag) = header(ETAG) {
+            self.etag = Some(etag);
+        }
+        if let Some(last_modified) = header(LAST_MODIFIED) {
+            self.last_modified = Some(last_modified);
+        }
+
+        self.fresh_until = None;
+        self.no_store = false;
+        let cache_control = header(CACHE_CONTROL).unwrap_or_default().to_lowercase();
+        for directive in cache_control.split(',').map(str::trim) {
+            if directive == "no-store" {
+                self.no_store = true;
+            } else if let Some(max_age) = directive.strip_prefix("max-age=") {
+                self.fresh_until = max_age.trim_matches('"').parse().ok().and_then(|seconds| {
+                    Utc::now().checked_add_signed(chrono::Duration::seconds(seconds))
+                });
+            }
+        }
+        if cache_control.contains("no-cache") {
+            self.fresh_until = None;
+        }
+    }
+
+    /// Keeps `body`, the body as downloaded, unless it's the same as the text
+    pub fn keep_bytes(&mut self, body: &[u8]) {
+        let is_text = matches!(self.format, DocumentFormat::Html | DocumentFormat::Text)
+            && std::str::from_utf8(body).is_ok();
+        self.blob = (!is_text).then(|| base64::engine::general_purpose::STANDARD.encode(body));
+    }
+
+    /// The body as downloaded, unless only the text extracted from it was kept
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        match &self.blob {
+            Some(blob) => base64::engine::general_purpose::STANDARD.decode(blob).ok(),
+            None if matches!(self.format, DocumentFormat::Html | DocumentFormat::Text) => {
+                Some(self.body.clone().into_bytes())
+            }
+            None => None,
+        }
+    }
+
+    /// Whether the response can be stored and later reused or revalidated
+    pub fn is_cacheable(&self) -> bool {
+        !self.no_store
+            && (self.etag.is_some() || self.last_modified.is_some() || self.fresh_until.is_some())
+    }
+
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.fresh_until
+            .is_some_and(|fresh_until| now < fresh_until)
+    }
+}
+
+/// Recently fetched pages, in memory and optionally on disk
+///
+/// The in-memory copies are evicted least recently used first, while the
+/// ones on disk are kept until they are replaced.
+#[derive(Debug)]
+pub struct FetchCache {
+    config: CacheConfig,
+    entries: Mutex<Lru>,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    responses: HashMap<String, CachedResponse>,
+    /// URLs from least to most recently used
+    order: VecDeque<String>,
+}
+
+impl Lru {
+    fn touch(&mut self, url: &str) {
+        if let Some(position) = self.order.iter().position(|entry| entry == url) {
+            self.order.remove(position);
+        }
+        self.order.push_back(url.to_string());
+    }
+
+    fn insert(&mut self, response: CachedResponse, capacity: usize) {
+        self.touch(&response.url);
+        self.responses.insert(response.url.clone(), response);
+        while self.order.len() > capacity.max(1) {
+            if let Some(evicted) = self.order.pop_front() {
+                self.responses.remove(&evicted);
+            }
+        }
+    }
+}
+
+impl FetchCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(Lru::default()),
+        }
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Lru> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The cached response for `url`, loading it from disk if it isn't in memory
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries();
+        if let Some(response) = entries.responses.get(url).cloned() {
+            entries.touch(url);
+            return Some(response);
+        }
+
+        let path = self.path(url)?;
+        let contents = std::fs::read(&path).ok()?;
+        match serde_json::from_slice::<CachedResponse>(&contents) {
+            // Guard against hash collisions
+            Ok(response) if response.url == url => {
+                entries.insert(response.clone(), self.config.capacity);
+                Some(response)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Ignoring invalid cache file {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn insert(&self, response: CachedResponse) {
+        if let Some(path) = self.path(&response.url) {
+            if let Err(e) = Self::save(&path, &response) {
+                warn!("Failed to write cache file {}: {}", path.display(), e);
+            }
+        }
+        debug!("Cached {}", response.url);
+        self.entries().insert(response, self.config.capacity);
+    }
+
+    fn save(path: &PathBuf, response: &CachedResponse) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first so a crash can't leave a partial entry
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, serde_json::to_vec(response)?)?;
+        std::fs::rename(&temp, path)
+    }
+
+    fn path(&self, url: &str) -> Option<PathBuf> {
+        let directory = self.config.directory.as_ref()?;
+        let hash = Sha256::digest(url.as_bytes());
+        let name: String = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+        Some(directory.join(format!("{}.json", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn response(url: &str, headers: &[(reqwest::header::HeaderName, &str)]) -> CachedResponse {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(name.clone(), HeaderValue::from_str(value).unwrap());
+        }
+        CachedResponse::new(
+            url,
+            &map,
+            "text/plain".to_string(),
+            format!("body of {}", url),
+        )
+    }
+
+    #[test]
+    fn test_cache_policy() {
+        let etag = response("https://a.example/", &[(ETAG, "\"v1\"")]);
+        assert!(etag.is_cacheable());
+        assert!(!etag.is_fresh(Utc::now()));
+
+        let max_age = response(
+            "https://a.example/",
+            &[(CACHE_CONTROL, "public, max-age=60")],
+        );
+        assert!(max_age.is_cacheable());
+        assert!(max_age.is_fresh(Utc::now()));
+
+        let no_store = response(
+            "https://a.example/",
+            &[(ETAG, "\"v1\""), (CACHE_CONTROL, "no-store")],
+        );
+        assert!(!no_store.is_cacheable());
+        assert!(!response("https://a.example/", &[]).is_cacheable());
+    }
+
+    #[test]
+    fn test_cache_keeps_bytes() {
+        let mut text = response("https://a.example/", &[]);
+        text.keep_bytes(b"body of https://a.example/");
+        assert!(text.blob.is_none());
+        assert_eq!(text.bytes().unwrap(), b"body of https://a.example/");
+
+        let mut image = response("https://a.example/logo.png", &[]);
+        image.keep_bytes(&[0x89, b'P', b'N', b'G', 0xff]);
+        assert_eq!(image.bytes().unwrap(), [0x89, b'P', b'N', b'G', 0xff]);
+
+        // Only the text of documents cached without their bytes is known
+        let mut pdf = response("https://a.example/doc.pdf", &[]);
+        pdf.format = DocumentFormat::Pdf;
+        assert!(pdf.bytes().is_none());
+    }
+
+    #[test]
+    fn test_cache_eviction_and_disk() {
+        let directory = std::env::temp_dir().join(format!("bioma-cache-{}", uuid::Uuid::new_v4()));
+        let config = CacheConfig {
+            capacity: 2,
+            directory: Some(directory.clone()),
+        };
+        let cache = FetchCache::new(config.clone());
+        for url in [
+            "https://a.example/",
+            "https://b.example/",
+            "https://c.example/",
+        ] {
+            cache.insert(response(url, &[(ETAG, "\"v1\"")]));
+        }
+        assert_eq!(cache.entries().responses.len(), 2);
+        assert!(!cache.entries().responses.contains_key("https://a.example/"));
+
+        // Evicted and restarted entries are read back from disk
+        assert!(cache.get("https://a.example/").is_some());
+        let restarted = FetchCache::new(config);
+        let cached = restarted.get("https://b.example/").unwrap();
+        assert_eq!(cached.body, "body of https://b.example/");
+        assert_eq!(cached.etag.as_deref(), Some("\"v1\""));
+        assert!(restarted.get("https://missing.example/").is_none());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
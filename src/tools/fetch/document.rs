@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+
+/// MIME type of Word documents
+const DOCX_MIME: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+
+/// Format of a fetched body, which decides how it is turned into text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentFormat {
+    Html,
+    #[default]
+    Text,
+    Pdf,
+    Docx,
+    /// Returned as image content rather than text
+    Image,
+}
+
+/// The MIME type of an image body, sniffed from its magic bytes before trusting `content_type`
+///
+/// Bodies sent with a vague type such as `application/octet-stream` count
+/// as images only if their bytes say so.
+pub fn image_mime_type(content_type: &str, body: &[u8]) -> Option<String> {
+    let sniffed = if body.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if body.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if body.starts_with(b"GIF87a") || body.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if body.len() >= 12 && &body[..4] == b"RIFF" && &body[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    };
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    match sniffed {
+        Some(sniffed) if mime.is_empty() || mime == "application/octet-stream" => {
+            Some(sniffed.to_string())
+        }
+        Some(sniffed) if mime.starts_with("image/") => Some(sniffed.to_string()),
+        None if mime.starts_with("image/") => Some(mime),
+        _ => None,
+    }
+}
+
+impl DocumentFormat {
+    /// Works out the format from the `Content-Type` header, or the body if the header is vague
+    pub fn detect(content_type: &str, body: &[u8]) -> Self {
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if mime == "application/pdf" || body.starts_with(b"%PDF-") {
+            DocumentFormat::Pdf
+        } else if mime == DOCX_MIME {
+            DocumentFormat::Docx
+        } else if image_mime_type(&mime, body).is_some() {
+            DocumentFormat::Image
+        } else if mime.contains("text/html")
+            || String::from_utf8_lossy(body).trim().starts_with("<html")
+        {
+            DocumentFormat::Html
+        } else {
+            DocumentFormat::Text
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentFormat::Html => "html",
+            DocumentFormat::Text => "text",
+            DocumentFormat::Pdf => "pdf",
+            DocumentFormat::Docx => "docx",
+            DocumentFormat::Image => "image",
+        }
+    }
+
+    /// Extracts the text of a body in this format
+    ///
+    /// Documents are parsed on a blocking thread, which also contains any
+    /// panic from a malformed file.
+    pub async fn extract_text(self, body: Vec<u8>) -> Result<String, String> {
+        match self {
+            DocumentFormat::Html | DocumentFormat::Text => {
+                Ok(String::from_utf8_lossy(&body).into_owned())
+            }
+            // Images have no text, only their bytes are kept
+            DocumentFormat::Image => Ok(String::new()),
+            DocumentFormat::Pdf | DocumentFormat::Docx => {
+                tokio::task::spawn_blocking(move || self.extract_document(&body))
+                    .await
+                    .map_err(|_| format!("Failed to read {} document", self.as_str()))?
+            }
+        }
+    }
+
+    #[cfg(feature = "documents")]
+    fn extract_document(self, body: &[u8]) -> Result<String, String> {
+        match self {
+            DocumentFormat::Pdf => pdf_extract::extract_text_from_mem(body)
+                .map_err(|e| format!("Failed to read PDF document: {}", e)),
+            _ => docx_text(body).map_err(|e| format!("Failed to read DOCX document: {}", e)),
+        }
+    }
+
+    #[cfg(not(feature = "documents"))]
+    fn extract_document(self, _body: &[u8]) -> Result<String, String> {
+        Err(format!(
+            "Extracting text from {} documents needs the documents feature",
+            self.as_str()
+        ))
+    }
+}
+
+/// Reads the paragraphs of a Word document
+#[cfg(feature = "documents")]
+fn docx_text(body: &[u8]) -> zip::result::ZipResult<String> {
+    use std::io::Read;
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(body))?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")?
+        .read_to_string(&mut xml)?;
+    Ok(document_xml_text(&xml))
+}
+
+/// Collects the `<w:t>` runs of WordprocessingML, one line per paragraph
+#[cfg(feature = "documents")]
+fn document_xml_text(xml: &str) -> String {
+    let mut text = String::new();
+    let mut in_text = false;
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        if in_text {
+            text.push_str(&unescape(&rest[..start]));
+        }
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .find(|part| !part.is_empty())
+            .unwrap_or_default();
+        match (name, tag.starts_with('/'), tag.ends_with('/')) {
+            ("w:t", false, false) => in_text = true,
+            ("w:t", true, _) => in_text = false,
+            ("w:p", true, _) => text.push('\n'),
+            ("w:tab", _, _) => text.push('\t'),
+            ("w:br", _, _) => text.push('\n'),
+            _ => {}
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.trim_end().to_string()
+}
+
+#[cfg(feature = "documents")]
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            DocumentFormat::detect("application/pdf", b""),
+            DocumentFormat::Pdf
+        );
+        assert_eq!(
+            DocumentFormat::detect("application/octet-stream", b"%PDF-1.7"),
+            DocumentFormat::Pdf
+        );
+        assert_eq!(
+            DocumentFormat::detect(DOCX_MIME, b"PK"),
+            DocumentFormat::Docx
+        );
+        assert_eq!(
+            DocumentFormat::detect("text/html; charset=utf-8", b""),
+            DocumentFormat::Html
+        );
+        assert_eq!(
+            DocumentFormat::detect("", b"  <html><body></body></html>"),
+            DocumentFormat::Html
+        );
+        assert_eq!(
+            DocumentFormat::detect("text/plain", b"hi"),
+            DocumentFormat::Text
+        );
+        assert_eq!(
+            DocumentFormat::detect("image/svg+xml", b"<svg/>"),
+            DocumentFormat::Image
+        );
+    }
+
+    #[test]
+    fn test_image_mime_type() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0";
+        assert_eq!(image_mime_type("", png).as_deref(), Some("image/png"));
+        // The bytes win over a wrong header
+        assert_eq!(
+            image_mime_type("image/jpeg", png).as_deref(),
+            Some("image/png")
+        );
+        assert_eq!(
+            image_mime_type("application/octet-stream", b"GIF89a..").as_deref(),
+            Some("image/gif")
+        );
+        assert_eq!(
+            image_mime_type("image/avif", b"....").as_deref(),
+            Some("image/avif")
+        );
+        assert_eq!(image_mime_type("application/octet-stream", b"PK"), None);
+        assert_eq!(image_mime_type("text/plain", png), None);
+    }
+
+    #[cfg(feature = "documents")]
+    #[tokio::test]
+    async fn test_extract_docx() {
+        use std::io::Write;
+
+        let xml = r#"<?xml version="1.0"?><w:document><w:body>
+            <w:p><w:r><w:t>Hello</w:t></w:r><w:r><w:t xml:space="preserve"> &amp; welcome</w:t></w:r></w:p>
+            <w:p><w:r><w:t>Second</w:t><w:tab/><w:t>line</w:t></w:r></w:p>
+            </w:body></w:document>"#;
+        let mut docx = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        docx.start_file::<_, ()>("word/document.xml", Default::default())
+            .unwrap();
+        docx.write_all(xml.as_bytes()).unwrap();
+        let docx = docx.finish().unwrap().into_inner();
+
+        let text = DocumentFormat::Docx.extract_text(docx).await.unwrap();
+        assert_eq!(text, "Hello & welcome\nSecond\tline");
+        assert!(DocumentFormat::Pdf
+            .extract_text(b"%PDF-garbage".to_vec())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use url::{Host, Url};
+
+/// Keeps web tools from reaching internal services on behalf of the model
+///
+/// Hosts are checked against a denylist and, when private networks are
+/// blocked, every address they resolve to must be public. The guard is
+/// also installed as the HTTP client's resolver so redirects and DNS
+/// rebinding can't get around the first check.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct HostGuard {
+    /// Refuse loopback, private, link-local and other non-public addresses
+    pub block_private_networks: bool,
+    /// Hosts that may not be fetched, along with their subdomains
+    pub denied_hosts: Vec<String>,
+}
+
+impl HostGuard {
+    /// Whether the guard lets anything through unchecked
+    pub fn is_open(&self) -> bool {
+        !self.block_private_networks && self.denied_hosts.is_empty()
+    }
+
+    /// Checks the host of `url` and the addresses it resolves to
+    pub async fn check_url(&self, url: &Url) -> Result<(), String> {
+        match url.host() {
+            Some(Host::Domain(domain)) => {
+                self.check_host(domain)?;
+                if self.block_private_networks {
+                    let port = url.port_or_known_default().unwrap_or(0);
+                    self.resolve_checked(domain, port).await?;
+                }
+                Ok(())
+            }
+            Some(Host::Ipv4(ip)) => self.check_ip(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => self.check_ip(IpAddr::V6(ip)),
+            None => Err(format!("{} has no host", url)),
+        }
+    }
+
+    /// Checks a URL synchronously, without resolving domain names
+    ///
+    /// Used for redirects, whose domains are checked when they are resolved.
+    pub fn check_url_literal(&self, url: &Url) -> Result<(), String> {
+        match url.host() {
+            Some(Host::Domain(domain)) => self.check_host(domain),
+            Some(Host::Ipv4(ip)) => self.check_ip(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => self.check_ip(IpAddr::V6(ip)),
+            None => Err(format!("{} has no host", url)),
+        }
+    }
+
+    fn check_host(&self, host: &str) -> Result<(), String> {
+        let host = host.trim_end_matches('.').to_lowercase();
+        let denied = self.denied_hosts.iter().any(|denied| {
+            let denied = denied.trim_end_matches('.').to_lowercase();
+            host == denied || host.ends_with(&format!(".{}", denied))
+        });
+        if denied {
+            return Err(format!("Host {} is denied", host));
+        }
+        if let Ok(ip) = host.trim_matches(['[', ']']).parse::<IpAddr>() {
+            return self.check_ip(ip);
+        }
+        Ok(())
+    }
+
+    fn check_ip(&self, ip: IpAddr) -> Result<(), String> {
+        if self.block_private_networks && !is_public(ip) {
+            return Err(format!("Address {} is not public", ip));
+        }
+        if self.denied_hosts.iter().any(|denied| {
+            denied
+                .trim_matches(['[', ']'])
+                .parse::<IpAddr>()
+                .is_ok_and(|denied| denied == ip)
+        }) {
+            return Err(format!("Address {} is denied", ip));
+        }
+        Ok(())
+    }
+
+    /// Resolves `host`, failing if any of its addresses is refused
+    async fn resolve_checked(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+            .collect();
+        for addr in &addrs {
+            self.check_ip(addr.ip())
+                .map_err(|e| format!("{} resolves to a refused address: {}", host, e))?;
+        }
+        Ok(addrs)
+    }
+}
+
+/// Resolver that only hands the HTTP client addresses the guard allows
+#[derive(Clone)]
+pub(crate) struct GuardedResolver(pub(crate) Arc<HostGuard>);
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.0.clone();
+        Box::pin(async move {
+            guard.check_host(name.as_str())?;
+            let addrs = guard.resolve_checked(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "This network" 0.0.0.0/8
+        || a == 0
+        // Carrier-grade NAT 100.64.0.0/10
+        || (a == 100 && (b & 0xc0) == 64)
+        // Benchmarking 198.18.0.0/15
+        || (a == 198 && (b & 0xfe) == 18)
+        // Reserved 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local fe80::/10
+        || (first & 0xffc0) == 0xfe80
+        // Documentation 2001:db8::/32
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_addresses() {
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{} is public", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{} is not public", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_host_guard() {
+        let guard = HostGuard {
+            block_private_networks: true,
+            denied_hosts: vec!["internal.example.com".to_string()],
+        };
+        let check = |url: &str| guard.check_url_literal(&Url::parse(url).unwrap());
+
+        assert!(check("https://example.com/").is_ok());
+        assert!(check("https://internal.example.com/").is_err());
+        assert!(check("https://api.internal.example.com/").is_err());
+        assert!(check("https://notinternal.example.com/").is_ok());
+        assert!(check("http://127.0.0.1:8080/").is_err());
+        assert!(check("http://[::1]/").is_err());
+        assert!(check("http://169.254.169.254/latest/meta-data").is_err());
+
+        // Names are resolved before being allowed
+        let url = Url::parse("http://localhost/").unwrap();
+        assert!(guard.check_url(&url).await.is_err());
+        assert!(HostGuard::default().check_url(&url).await.is_ok());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolDef, ToolError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Maximum number of matches returned by a search
+const MAX_SEARCH_RESULTS: usize = 1000;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FilesystemAction {
+    ReadFile,
+    WriteFile,
+    ListDirectory,
+    Move,
+    Search,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct FilesystemProperties {
+    #[schemars(
+        description = "The operation to perform: 'read_file' to read a text file, 'write_file' to create or overwrite a file, 'list_directory' to list a directory, 'move' to move or rename a file or directory, or 'search' to recursively find files whose name contains a pattern"
+    )]
+    action: FilesystemAction,
+
+    #[schemars(
+        description = "The file or directory to operate on, absolute or relative to the first allowed directory"
+    )]
+    path: String,
+
+    #[schemars(description = "The text to write (only required for write_file)")]
+    content: Option<String>,
+
+    #[schemars(description = "Where to move the file or directory to (only required for move)")]
+    destination: Option<String>,
+
+    #[schemars(
+        description = "Case-insensitive text the file names must contain (only required for search)"
+    )]
+    pattern: Option<String>,
+}
+
+/// Reads, writes and lists files inside a set of allowed directories
+///
+/// The allowed directories are the ones the tool was configured with plus
+/// the `file://` roots exposed by the calling client.
+#[derive(Clone, Debug, Default, Serialize, McpTool)]
+#[mcp_tool(
+    name = "filesystem",
+    description = "Read, write, list, move and search files within the allowed directories",
+    properties = FilesystemProperties
+)]
+pub struct Filesystem {
+    allowed_directories: Vec<PathBuf>,
+}
+
+impl Filesystem {
+    /// A filesystem tool restricted to `allowed_directories` and the client's roots
+    pub fn new(allowed_directories: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self {
+            allowed_directories: allowed_directories.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    async fn run(
+        &self,
+        properties: FilesystemProperties,
+        context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        let allowed = self.allowed(&context);
+        if allowed.is_empty() {
+            return Ok(CallToolResult::error("No directories are allowed"));
+        }
+
+        let path = match resolve(&allowed, &properties.path) {
+            Ok(path) => path,
+            Err(e) => return Ok(CallToolResult::error(e)),
+        };
+        if let Err(violation) = context.policy().check_path(&path) {
+            return Ok(violation.into_result(Self::NAME));
+        }
+
+        let result = match properties.action {
+            FilesystemAction::ReadFile => tokio::fs::read_to_string(&path)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e)),
+            FilesystemAction::WriteFile => match properties.content {
+                Some(content) => tokio::fs::write(&path, &content)
+                    .await
+                    .map(|_| {
+                        format!(
+                            "Successfully wrote {} bytes to {}",
+                            content.len(),
+                            path.display()
+                        )
+                    })
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+                None => Err("Content is required for write_file action".to_string()),
+            },
+            FilesystemAction::ListDirectory => list_directory(&path).await,
+            FilesystemAction::Move => match properties.destination {
+                Some(destination) => match resolve(&allowed, &destination) {
+                    Ok(destination) => match context.policy().check_path(&destination) {
+                        Ok(()) => move_path(&path, &destination).await,
+                        Err(violation) => return Ok(violation.into_result(Self::NAME)),
+                    },
+                    Err(e) => Err(e),
+                },
+                None => Err("Destination is required for move action".to_string()),
+            },
+            FilesystemAction::Search => match properties.pattern {
+                Some(pattern) => search(&path, &pattern).await,
+                None => Err("Pattern is required for search action".to_string()),
+            },
+        };
+
+        Ok(match result {
+            Ok(text) => CallToolResult::text(text),
+            Err(e) => CallToolResult::error(e),
+        })
+    }
+
+    /// Canonical forms of the configured directories and the client's roots
+    fn allowed(&self, context: &ToolContext) -> Vec<PathBuf> {
+        self.allowed_directories
+            .iter()
+            .cloned()
+            .chain(context.root_paths())
+            .filter_map(|dir| dir.canonicalize().ok())
+            .collect()
+    }
+}
+
+/// Resolves `path` against the allowed directories, rejecting anything outside them
+///
+/// Paths that don't exist yet are resolved through their parent so they can
+/// be written to, and symlinks are followed before checking.
+fn resolve(allowed: &[PathBuf], path: &str) -> Result<PathBuf, String> {
+    let requested = Path::new(path);
+    let absolute = if requested.is_absolute() {
+        requested.to_path_buf()
+    } else {
+        allowed[0].join(requested)
+    };
+
+    let resolved = match absolute.canonicalize() {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            let name = match absolute.components().next_back() {
+                Some(Component::Normal(name)) => name,
+                _ => return Err(format!("Invalid path: {}", path)),
+            };
+            let parent = absolute
+                .parent()
+                .and_then(|parent| parent.canonicalize().ok())
+                .ok_or_else(|| format!("Parent directory of {} does not exist", path))?;
+            parent.join(name)
+        }
+    };
+
+    if allowed.iter().any(|dir| resolved.starts_with(dir)) {
+        Ok(resolved)
+    } else {
+        Err(format!(
+            "Access denied: {} is outside the allowed directories",
+            path
+        ))
+    }
+}
+
+async fn list_directory(path: &Path) -> Result<String, String> {
+    let error = |e: std::io::Error| format!("Failed to list {}: {}", path.display(), e);
+    let mut entries = tokio::fs::read_dir(path).await.map_err(error)?;
+
+    let mut listing = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(error)? {
+        let kind = match entry.file_type().await {
+            Ok(file_type) if file_type.is_dir() => "[DIR]",
+            _ => "[FILE]",
+        };
+        listing.push(format!("{} {}", kind, entry.file_name().to_string_lossy()));
+    }
+    listing.sort();
+
+    if listing.is_empty() {
+        Ok(format!("{} is empty", path.display()))
+    } else {
+        Ok(listing.join("\n"))
+    }
+}
+
+async fn move_path(source: &Path, destination: &Path) -> Result<String, String> {
+    if tokio::fs::try_exists(destination).await.unwrap_or(false) {
+        return Err(format!(
+            "Destination {} already exists",
+            destination.display()
+        ));
+    }
+    tokio::fs::rename(source, destination)
+        .await
+        .map(|_| {
+            format!(
+                "Successfully moved {} to {}",
+                source.display(),
+                destination.display()
+            )
+        })
+        .map_err(|e| format!("Failed to move {}: {}", source.display(), e))
+}
+
+/// Recursively finds entries under `path` whose name contains `pattern`
+///
+/// Symlinked directories are not descended into so the search can't escape
+/// the allowed directories.
+async fn search(path: &Path, pattern: &str) -> Result<String, String> {
+    let pattern = pattern.to_lowercase();
+    let mut matches = Vec::new();
+    let mut pending = vec![path.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if dir == path => {
+                return Err(format!("Failed to search {}: {}", path.display(), e))
+            }
+            Err(_) => continue,
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry
+                .file_name()
+                .to_string_lossy()
+                .to_lowercase()
+                .contains(&pattern)
+            {
+                matches.push(entry.path().display().to_string());
+                if matches.len() >= MAX_SEARCH_RESULTS {
+                    pending.clear();
+                    break;
+                }
+            }
+            if entry.file_type().await.is_ok_and(|t| t.is_dir()) {
+                pending.push(entry.path());
+            }
+        }
+    }
+    matches.sort();
+
+    if matches.is_empty() {
+        Ok("No matches found".to_string())
+    } else {
+        Ok(matches.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::ToolPolicy;
+
+    fn props(action: FilesystemAction, path: &str) -> FilesystemProperties {
+        FilesystemProperties {
+            action,
+            path: path.to_string(),
+            content: None,
+            destination: None,
+            pattern: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filesystem_operations() {
+        let dir = std::env::temp_dir().join(format!("bioma-fs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        let tool = Filesystem::new([&dir]);
+
+        // Write and read back, relative to the allowed directory
+        let mut write = props(FilesystemAction::WriteFile, "notes/todo.txt");
+        write.content = Some("buy milk".to_string());
+        let result = tool.call(write, ToolContext::default()).await.unwrap();
+        assert_eq!(result.is_error, Some(false));
+
+        let read = props(FilesystemAction::ReadFile, "notes/todo.txt");
+        let result = tool.call(read, ToolContext::default()).await.unwrap();
+        assert_eq!(result.content[0].as_text(), Some("buy milk"));
+
+        let list = props(FilesystemAction::ListDirectory, dir.to_str().unwrap());
+        let result = tool.call(list, ToolContext::default()).await.unwrap();
+        assert_eq!(result.content[0].as_text(), Some("[DIR] notes"));
+
+        let mut rename = props(FilesystemAction::Move, "notes/todo.txt");
+        rename.destination = Some("done.txt".to_string());
+        let result = tool.call(rename, ToolContext::default()).await.unwrap();
+        assert_eq!(result.is_error, Some(false));
+        assert!(dir.join("done.txt").exists());
+
+        let mut search = props(FilesystemAction::Search, ".");
+        search.pattern = Some("DONE".to_string());
+        let result = tool.call(search, ToolContext::default()).await.unwrap();
+        let expected = dir.canonicalize().unwrap().join("done.txt");
+        assert_eq!(
+            result.content[0].as_text(),
+            Some(expected.to_str().unwrap())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_filesystem_restricted() {
+        let dir = std::env::temp_dir().join(format!("bioma-fs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tool = Filesystem::new([&dir]);
+
+        for path in ["../outside.txt", "/etc/passwd"] {
+            let read = props(FilesystemAction::ReadFile, path);
+            let result = tool.call(read, ToolContext::default()).await.unwrap();
+            assert_eq!(result.is_error, Some(true), "{} should be denied", path);
+        }
+
+        let mut write = props(FilesystemAction::Move, "missing.txt");
+        write.destination = Some("../escaped.txt".to_string());
+        let result = tool.call(write, ToolContext::default()).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+
+        // Nothing is allowed without configured directories or client roots
+        let read = props(FilesystemAction::ReadFile, "/etc/passwd");
+        let result = Filesystem::default()
+            .call(read, ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_filesystem_policy() {
+        let dir = std::env::temp_dir().join(format!("bioma-fs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("public")).unwrap();
+        std::fs::write(dir.join("private.txt"), "secret").unwrap();
+        let tool = Filesystem::new([&dir]);
+        let context = ToolContext::default().with_policy(ToolPolicy {
+            filesystem_roots: Some(vec![dir.join("public")]),
+            ..Default::default()
+        });
+
+        // Relative paths are only checked once the tool resolves them
+        let read = props(FilesystemAction::ReadFile, "private.txt");
+        let result = tool.call(read, context.clone()).await.unwrap();
+        let violation = &result.meta.unwrap()["policyViolation"];
+        assert_eq!(violation["tool"], "filesystem");
+        assert_eq!(violation["kind"], "path_not_allowed");
+
+        let mut write = props(FilesystemAction::WriteFile, "public/notes.txt");
+        write.content = Some("hello".to_string());
+        let result = tool.call(write, context).await.unwrap();
+        assert_eq!(result.is_error, Some(false));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
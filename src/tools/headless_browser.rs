@@ -0,0 +1,301 @@
+use crate::schema::CallToolResult;
+use crate::tools::fetch::HostGuard;
+use crate::tools::{McpTool, ToolContext, ToolError};
+use crate::transport::ConnectionId;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption;
+use headless_chrome::{Browser, LaunchOptions, Tab};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use url::Url;
+
+/// Time the browser is kept running without any calls
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Maximum number of elements returned by `extract`
+const MAX_ELEMENTS: usize = 100;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadlessBrowserAction {
+    Navigate,
+    Screenshot,
+    ExtractText,
+    Click,
+    Extract,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct HeadlessBrowserProperties {
+    #[schemars(
+        description = "The action to perform: 'navigate' to load a URL, 'screenshot' to capture the page as an image, 'extract_text' to get the text of the page or an element, 'click' to click an element, or 'extract' to get the text of every element matching a selector"
+    )]
+    action: HeadlessBrowserAction,
+
+    #[schemars(description = "The URL to load (only required for navigate)")]
+    url: Option<String>,
+
+    #[schemars(
+        description = "CSS selector of the element(s) to use (required for click/extract, defaults to the whole page for extract_text)"
+    )]
+    selector: Option<String>,
+}
+
+/// Renders pages in headless Chrome, including their JavaScript
+///
+/// The browser is launched on the first call and each client session gets
+/// its own tab, so navigation carries over between calls from the same
+/// client.
+#[derive(Clone, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "headless_browser",
+    description = "Loads pages in a headless browser that runs JavaScript, then takes screenshots, clicks elements and extracts their text",
+    properties = HeadlessBrowserProperties
+)]
+pub struct HeadlessBrowser {
+    chrome_path: Option<PathBuf>,
+    window_size: (u32, u32),
+    timeout: Duration,
+    guard: HostGuard,
+    #[serde(skip)]
+    state: Arc<Mutex<BrowserState>>,
+}
+
+#[derive(Default)]
+struct BrowserState {
+    browser: Option<Browser>,
+    tabs: HashMap<ConnectionId, Arc<Tab>>,
+}
+
+impl std::fmt::Debug for BrowserState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BrowserState")
+            .field("running", &self.browser.is_some())
+            .field("tabs", &self.tabs.len())
+            .finish()
+    }
+}
+
+impl Default for HeadlessBrowser {
+    fn default() -> Self {
+        Self {
+            chrome_path: None,
+            window_size: (1280, 800),
+            timeout: Duration::from_secs(30),
+            guard: HostGuard::default(),
+            state: Arc::new(Mutex::new(BrowserState::default())),
+        }
+    }
+}
+
+impl HeadlessBrowser {
+    /// Uses the Chrome or Chromium executable at `path` instead of searching for one
+    pub fn with_chrome_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.chrome_path = Some(path.into());
+        self
+    }
+
+    /// Sets the size of the browser window, and so of screenshots
+    pub fn with_window_size(mut self, width: u32, height: u32) -> Self {
+        self.window_size = (width, height);
+        self
+    }
+
+    /// Time allowed for pages to load and elements to appear
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Hosts and networks that may not be navigated to
+    pub fn with_guard(mut self, guard: HostGuard) -> Self {
+        self.guard = guard;
+        self
+    }
+
+    async fn run(
+        &self,
+        properties: HeadlessBrowserProperties,
+        context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        if let HeadlessBrowserAction::Navigate = properties.action {
+            let Some(url) = &properties.url else {
+                return Ok(CallToolResult::error("URL is required for navigate action"));
+            };
+            let url = match Url::parse(url) {
+                Ok(url) => url,
+                Err(e) => return Ok(CallToolResult::error(format!("Invalid URL: {}", e))),
+            };
+            if let Err(e) = self.guard.check_url(&url).await {
+                return Ok(CallToolResult::error(format!("Access denied: {}", e)));
+            }
+        }
+
+        let tool = self.clone();
+        let session = context.session_id().clone();
+        // headless_chrome is blocking, so calls run off the runtime
+        let result = tokio::task::spawn_blocking(move || tool.run_blocking(&session, properties))
+            .await
+            .map_err(|e| ToolError::Execution(e.to_string()))?;
+
+        Ok(result.unwrap_or_else(CallToolResult::error))
+    }
+
+    fn run_blocking(
+        &self,
+        session: &ConnectionId,
+        properties: HeadlessBrowserProperties,
+    ) -> Result<CallToolResult, String> {
+        let tab = self
+            .tab(session)
+            .map_err(|e| format!("Failed to start browser: {}", e))?;
+
+        match properties.action {
+            HeadlessBrowserAction::Navigate => {
+                let url = properties.url.unwrap_or_default();
+                tab.navigate_to(&url)
+                    .and_then(|tab| tab.wait_until_navigated())
+                    .map_err(|e| format!("Failed to navigate to {}: {}", url, e))?;
+                self.check_current_url(&tab)?;
+                let title = tab.get_title().unwrap_or_default();
+                Ok(CallToolResult::text(format!(
+                    "Navigated to {}\n\nTitle: {}",
+                    tab.get_url(),
+                    title
+                )))
+            }
+            HeadlessBrowserAction::Screenshot => {
+                let png = tab
+                    .capture_screenshot(CaptureScreenshotFormatOption::Png, None, None, true)
+                    .map_err(|e| format!("Failed to capture screenshot: {}", e))?;
+                Ok(CallToolResult::image(STANDARD.encode(png), "image/png"))
+            }
+            HeadlessBrowserAction::ExtractText => {
+                let selector = properties.selector.as_deref().unwrap_or("body");
+                let text = tab
+                    .wait_for_element(selector)
+                    .and_then(|element| element.get_inner_text())
+                    .map_err(|e| format!("Failed to extract text from {}: {}", selector, e))?;
+                Ok(CallToolResult::text(text))
+            }
+            HeadlessBrowserAction::Click => {
+                let selector = properties
+                    .selector
+                    .ok_or("Selector is required for click action")?;
+                tab.wait_for_element(&selector)
+                    .and_then(|element| element.click().map(|_| ()))
+                    .map_err(|e| format!("Failed to click {}: {}", selector, e))?;
+                // Clicking a link may have navigated somewhere the guard refuses
+                let _ = tab.wait_until_navigated();
+                self.check_current_url(&tab)?;
+                Ok(CallToolResult::text(format!(
+                    "Clicked {}\n\nCurrent URL: {}",
+                    selector,
+                    tab.get_url()
+                )))
+            }
+            HeadlessBrowserAction::Extract => {
+                let selector = properties
+                    .selector
+                    .ok_or("Selector is required for extract action")?;
+                let elements = tab
+                    .wait_for_elements(&selector)
+                    .map_err(|e| format!("Failed to find {}: {}", selector, e))?;
+                let texts: Vec<String> = elements
+                    .iter()
+                    .take(MAX_ELEMENTS)
+                    .filter_map(|element| element.get_inner_text().ok())
+                    .collect();
+                let text = serde_json::to_string_pretty(&texts)
+                    .map_err(|e| format!("Failed to serialize elements: {}", e))?;
+                Ok(CallToolResult::text(text))
+            }
+        }
+    }
+
+    /// The calling session's tab, launching the browser if it isn't running
+    fn tab(&self, session: &ConnectionId) -> Result<Arc<Tab>, String> {
+        let mut state = self.state();
+        if let Some(tab) = state.tabs.get(session) {
+            return Ok(tab.clone());
+        }
+
+        let browser = match state.browser.take() {
+            Some(browser) => browser,
+            None => self.launch()?,
+        };
+        // A browser that exited since the last call is relaunched once
+        let (browser, tab) = match browser.new_tab() {
+            Ok(tab) => (browser, tab),
+            Err(_) => {
+                state.tabs.clear();
+                let browser = self.launch()?;
+                let tab = browser.new_tab().map_err(|e| e.to_string())?;
+                (browser, tab)
+            }
+        };
+        tab.set_default_timeout(self.timeout);
+        state.browser = Some(browser);
+        state.tabs.insert(session.clone(), tab.clone());
+        Ok(tab)
+    }
+
+    fn launch(&self) -> Result<Browser, String> {
+        let options = LaunchOptions::default_builder()
+            .headless(true)
+            .path(self.chrome_path.clone())
+            .window_size(Some(self.window_size))
+            .idle_browser_timeout(IDLE_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Browser::new(options).map_err(|e| e.to_string())
+    }
+
+    /// Leaves pages the guard refuses, such as ones reached through a redirect
+    fn check_current_url(&self, tab: &Tab) -> Result<(), String> {
+        let Ok(url) = Url::parse(&tab.get_url()) else {
+            return Ok(());
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            return Ok(());
+        }
+        if let Err(e) = self.guard.check_url_literal(&url) {
+            let _ = tab.navigate_to("about:blank");
+            return Err(format!("Access denied: {}", e));
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> MutexGuard<'_, BrowserState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolDef;
+
+    #[tokio::test]
+    async fn test_navigate_is_guarded() {
+        let tool = HeadlessBrowser::default().with_guard(HostGuard {
+            block_private_networks: true,
+            denied_hosts: Vec::new(),
+        });
+        for url in ["http://127.0.0.1:8080/", "not a url"] {
+            let props = HeadlessBrowserProperties {
+                action: HeadlessBrowserAction::Navigate,
+                url: Some(url.to_string()),
+                selector: None,
+            };
+            let result = tool.call(props, ToolContext::default()).await.unwrap();
+            assert_eq!(result.is_error, Some(true));
+        }
+        // Refused before a browser had to be launched
+        assert!(tool.state().browser.is_none());
+    }
+}
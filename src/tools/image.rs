@@ -0,0 +1,265 @@
+use crate::schema::CallToolResult;
+use crate::tools::fetch::Fetch;
+use crate::tools::{McpTool, ToolContext, ToolDef, ToolError};
+use base64::Engine;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Largest image returned, in bytes, unless set with `with_max_bytes`
+const DEFAULT_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+/// Format images are converted to
+#[cfg(feature = "image")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ImageProperties {
+    #[schemars(description = "URL of the image to download", required = true)]
+    url: String,
+    #[cfg(feature = "image")]
+    #[schemars(description = "Width to scale the image down to, keeping its aspect ratio")]
+    max_width: Option<u32>,
+    #[cfg(feature = "image")]
+    #[schemars(description = "Height to scale the image down to, keeping its aspect ratio")]
+    max_height: Option<u32>,
+    #[cfg(feature = "image")]
+    #[schemars(description = "Format to convert the image to: 'png' or 'jpeg'")]
+    format: Option<ImageFormat>,
+}
+
+/// Downloads images for the model to look at
+///
+/// Downloads go through a `Fetch`, so they're held to its host guard,
+/// robots.txt, limits and cache. The MIME type is taken from the image's
+/// bytes where they're recognised, since sites often send a vague one.
+/// With the `image` feature, images can be scaled down and converted to PNG
+/// or JPEG before they're returned.
+#[derive(Clone, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "image",
+    description = "Downloads an image from a URL and returns it as image content",
+    properties = ImageProperties
+)]
+pub struct Image {
+    fetch: Fetch,
+    max_bytes: usize,
+}
+
+impl Image {
+    pub fn new(fetch: Fetch) -> Self {
+        Self {
+            fetch,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    /// Refuses images larger than `max_bytes`
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    async fn run(
+        &self,
+        properties: ImageProperties,
+        context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        let url = match Url::parse(&properties.url) {
+            Ok(url) => url,
+            Err(e) => return Ok(CallToolResult::error(format!("Invalid URL: {}", e))),
+        };
+        if let Err(violation) = context.policy().check_url(&url) {
+            return Ok(violation.into_result(Self::NAME));
+        }
+
+        let download = match self.fetch.download(&url).await {
+            Ok(download) => download,
+            Err(e) => return Ok(CallToolResult::error(e.to_string())),
+        };
+        let Some(mime_type) = download.image_mime_type() else {
+            return Ok(CallToolResult::error(format!(
+                "{} isn't an image but {}",
+                url, download.content_type
+            )));
+        };
+        if download.body.len() > self.max_bytes {
+            return Ok(CallToolResult::error(format!(
+                "Image of {} bytes is larger than the {} bytes allowed",
+                download.body.len(),
+                self.max_bytes
+            )));
+        }
+
+        #[cfg(feature = "image")]
+        let (body, mime_type) = match convert(&download.body, mime_type, &properties) {
+            Ok(converted) => converted,
+            Err(e) => {
+                return Ok(CallToolResult::error(format!(
+                    "Can't convert {}: {}",
+                    url, e
+                )))
+            }
+        };
+        #[cfg(not(feature = "image"))]
+        let body = download.body;
+
+        let data = base64::engine::general_purpose::STANDARD.encode(&body);
+        let mut result = CallToolResult::image(data, mime_type);
+        let mut meta = BTreeMap::new();
+        meta.insert("size".to_string(), body.len().into());
+        meta.insert("cache".to_string(), download.cache.as_str().into());
+        result.meta = Some(meta);
+        Ok(result)
+    }
+}
+
+/// The image in `body` scaled down and converted as `properties` ask, with its
+/// MIME type; unchanged if they ask for neither
+#[cfg(feature = "image")]
+fn convert(
+    body: &[u8],
+    mime_type: String,
+    properties: &ImageProperties,
+) -> Result<(Vec<u8>, String), image::ImageError> {
+    let (max_width, max_height) = (properties.max_width, properties.max_height);
+    if max_width.is_none() && max_height.is_none() && properties.format.is_none() {
+        return Ok((body.to_vec(), mime_type));
+    }
+
+    let mut decoded = image::load_from_memory(body)?;
+    let width = max_width.unwrap_or(u32::MAX).max(1);
+    let height = max_height.unwrap_or(u32::MAX).max(1);
+    if decoded.width() > width || decoded.height() > height {
+        decoded = decoded.resize(width, height, image::imageops::FilterType::Lanczos3);
+    }
+    // Images already in a format that's kept stay in it, the others become PNG
+    let format = properties.format.unwrap_or(match mime_type.as_str() {
+        "image/jpeg" => ImageFormat::Jpeg,
+        _ => ImageFormat::Png,
+    });
+
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    match format {
+        ImageFormat::Png => {
+            decoded.write_to(&mut encoded, image::ImageFormat::Png)?;
+            Ok((encoded.into_inner(), "image/png".to_string()))
+        }
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => {
+            image::DynamicImage::ImageRgb8(decoded.to_rgb8())
+                .write_to(&mut encoded, image::ImageFormat::Jpeg)?;
+            Ok((encoded.into_inner(), "image/jpeg".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Content;
+
+    #[tokio::test]
+    async fn test_image() {
+        let mut server = mockito::Server::new_async().await;
+        let gif = b"GIF89a\x01\x00\x01\x00";
+        let _gif = server
+            .mock("GET", "/pixel.gif")
+            .with_header("content-type", "image/png")
+            .with_body(gif)
+            .create_async()
+            .await;
+        let _page = server
+            .mock("GET", "/page")
+            .with_header("content-type", "text/html")
+            .with_body("<html></html>")
+            .create_async()
+            .await;
+        let tool = Image::new(Fetch::default());
+        let props = |path: &str| -> ImageProperties {
+            serde_json::from_value(
+                serde_json::json!({ "url": format!("{}{}", server.url(), path) }),
+            )
+            .unwrap()
+        };
+
+        let result = tool
+            .call(props("/pixel.gif"), ToolContext::default())
+            .await
+            .unwrap();
+        let Content::Image { mime_type, .. } = &result.content[0] else {
+            panic!("expected an image, got {:?}", result.content[0]);
+        };
+        // The bytes win over the header
+        assert_eq!(mime_type, "image/gif");
+        assert_eq!(result.meta.unwrap()["size"], gif.len());
+
+        let result = tool
+            .call(props("/page"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+
+        let tool = tool.with_max_bytes(4);
+        let result = tool
+            .call(props("/pixel.gif"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[cfg(feature = "image")]
+    #[tokio::test]
+    async fn test_image_conversion() {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::from_pixel(40, 20, image::Rgba([255, 0, 0, 128]))
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let _png = server
+            .mock("GET", "/wide.png")
+            .with_body(png.into_inner())
+            .create_async()
+            .await;
+        let tool = Image::new(Fetch::default());
+        let call = |properties: serde_json::Value| {
+            let mut properties: ImageProperties = serde_json::from_value(properties).unwrap();
+            properties.url = format!("{}/wide.png", server.url());
+            tool.call(properties, ToolContext::default())
+        };
+        let decode = |result: &CallToolResult| {
+            let Content::Image {
+                data, mime_type, ..
+            } = &result.content[0]
+            else {
+                panic!("expected an image, got {:?}", result.content[0]);
+            };
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .unwrap();
+            (mime_type.clone(), image::load_from_memory(&bytes).unwrap())
+        };
+
+        // Scaled down to fit, keeping the aspect ratio
+        let result = call(serde_json::json!({ "url": "", "max_width": 10 }))
+            .await
+            .unwrap();
+        let (mime_type, scaled) = decode(&result);
+        assert_eq!(mime_type, "image/png");
+        assert_eq!((scaled.width(), scaled.height()), (10, 5));
+
+        // Never scaled up
+        let result = call(serde_json::json!({ "url": "", "max_height": 100, "format": "jpeg" }))
+            .await
+            .unwrap();
+        let (mime_type, converted) = decode(&result);
+        assert_eq!(mime_type, "image/jpeg");
+        assert_eq!((converted.width(), converted.height()), (40, 20));
+    }
+}
@@ -1,36 +1,26 @@
-use crate::schema::{CallToolResult, TextContent, Tool, ToolInputSchema};
-use crate::tools::{ToolDef, ToolError};
-use lazy_static::lazy_static;
+use crate::error::StoreError;
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolError};
+use chrono::Utc;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
-use std::sync::Mutex;
-
-pub const MEMORY_SCHEMA: &str = r#"{
-    "type": "object",
-    "properties": {
-        "action": {
-            "description": "The action to perform: 'store' to save a value, 'retrieve' to get a value, 'list' to see all keys, 'delete' to remove a key, or 'clear' to remove all keys",
-            "type": "string",
-            "enum": ["store", "retrieve", "list", "delete", "clear"]
-        },
-        "key": {
-            "description": "The key to store/retrieve/delete the memory under (not required for list/clear)",
-            "type": "string"
-        },
-        "value": {
-            "description": "The JSON value to store (only required for store action)",
-            "type": ["object", "null"]
-        }
-    },
-    "required": ["action"]
-}"#;
+use serde_json_path::JsonPath;
+use std::sync::{Arc, Once};
+use std::time::Duration;
+use tracing::{debug, error};
 
-// Global memory store
-lazy_static! {
-    static ref MEMORY_STORE: Mutex<HashMap<String, Value>> = Mutex::new(HashMap::new());
-}
+pub mod store;
+
+pub use store::{InMemoryStore, JsonFileStore, MemoryEntry, MemoryStore};
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
+
+/// Namespace used when the calling client doesn't identify itself
+const DEFAULT_NAMESPACE: &str = "default";
+
+/// How often expired memories are evicted from the store
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
@@ -40,15 +30,15 @@ pub enum MemoryAction {
     List,
     Delete,
     Clear,
+    Search,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct MemoryProperties {
     #[schemars(required = true)]
     #[schemars(
-        description = "The action to perform: 'store' to save a value, 'retrieve' to get a value, 'list' to see all keys, 'delete' to remove a key, or 'clear' to remove all keys"
+        description = "The action to perform: 'store' to save a value, 'retrieve' to get a value, 'list' to see all keys, 'delete' to remove a key, 'clear' to remove all keys, or 'search' to find memories matching a query"
     )]
-    #[schemars(with = "String")]
     action: MemoryAction,
 
     #[schemars(
@@ -57,109 +47,240 @@ pub struct MemoryProperties {
     key: Option<String>,
 
     #[schemars(description = "The JSON value to store (only required for store action)")]
-    #[schemars(with = "Value")]
     value: Option<Value>,
+
+    #[schemars(
+        description = "Text to find in keys and values, or a JSONPath expression starting with '$' that values must match (only required for search action)"
+    )]
+    query: Option<String>,
+
+    #[schemars(
+        description = "Seconds until the stored memory expires, kept forever if unset (only used by store action)"
+    )]
+    ttl_seconds: Option<u64>,
+
+    #[schemars(
+        description = "Namespace keeping these keys apart from others, defaults to one per client"
+    )]
+    namespace: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "memory",
+    description = "Store and retrieve JSON memories using string keys",
+    properties = MemoryProperties
+)]
+pub struct Memory {
+    #[serde(skip)]
+    store: Arc<dyn MemoryStore>,
+    #[serde(skip)]
+    sweeper: Arc<Once>,
+}
+
+impl Default for Memory {
+    /// Memories kept in process until the server exits
+    fn default() -> Self {
+        Self::new(InMemoryStore::default())
+    }
 }
 
-#[derive(Clone, Debug, Serialize)]
-pub struct Memory;
+impl Memory {
+    /// A memory tool backed by `store`, such as a [`JsonFileStore`] to persist across restarts
+    pub fn new(store: impl MemoryStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            sweeper: Arc::new(Once::new()),
+        }
+    }
 
-impl ToolDef for Memory {
-    const NAME: &'static str = "memory";
-    const DESCRIPTION: &'static str = "Store and retrieve JSON memories using string keys";
-    type Properties = MemoryProperties;
+    /// Evicts expired memories in the background until the tool is dropped
+    fn start_sweeper(&self) {
+        let store = Arc::downgrade(&self.store);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(store) = store.upgrade() else {
+                    break;
+                };
+                match store.remove_expired(Utc::now()) {
+                    Ok(0) => {}
+                    Ok(count) => debug!("Evicted {} expired memories", count),
+                    Err(e) => error!("Failed to evict expired memories: {}", e),
+                }
+            }
+        });
+    }
 
-    fn def() -> Tool {
-        let input_schema = serde_json::from_str::<ToolInputSchema>(MEMORY_SCHEMA).unwrap();
-        Tool {
-            name: Self::NAME.to_string(),
-            description: Some(Self::DESCRIPTION.to_string()),
-            input_schema,
+    /// The namespace requested by the call, or else the calling client's name
+    fn namespace(properties: &MemoryProperties, context: &ToolContext) -> String {
+        if let Some(namespace) = &properties.namespace {
+            return namespace.clone();
         }
+        context
+            .session()
+            .and_then(|session| session.client_info)
+            .map(|client| client.name)
+            .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string())
     }
+}
 
-    async fn call(&self, properties: Self::Properties) -> Result<CallToolResult, ToolError> {
-        let store_result = MEMORY_STORE.lock();
-        let mut store = match store_result {
-            Ok(store) => store,
-            Err(e) => return Ok(Self::error(e.to_string())),
-        };
+impl Memory {
+    async fn run(
+        &self,
+        properties: MemoryProperties,
+        context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        self.sweeper.call_once(|| self.start_sweeper());
+
+        let namespace = Self::namespace(&properties, &context);
+        let store = &self.store;
+        let now = Utc::now();
+        let storage_error =
+            |e: StoreError| ToolError::Execution(format!("Memory storage failed: {}", e));
 
         let result = match properties.action {
             MemoryAction::Store => {
                 let key = match properties.key {
                     Some(k) => k,
-                    None => return Ok(Self::error("Key is required for store action")),
+                    None => return Ok(CallToolResult::error("Key is required for store action")),
                 };
                 let value = match properties.value {
                     Some(v) => v,
-                    None => return Ok(Self::error("Value is required for store action")),
+                    None => return Ok(CallToolResult::error("Value is required for store action")),
                 };
-                store.insert(key.clone(), value);
-                format!("Successfully stored memory with key: {}", key)
+                let mut entry = MemoryEntry::new(value);
+                if let Some(ttl) = properties.ttl_seconds {
+                    entry = entry.with_ttl(Duration::from_secs(ttl));
+                }
+                let expires_at = entry.expires_at;
+                store.insert(&namespace, &key, entry).map_err(storage_error)?;
+                match expires_at {
+                    Some(expires_at) => format!(
+                        "Successfully stored memory with key: {} (expires at {})",
+                        key,
+                        expires_at.to_rfc3339()
+                    ),
+                    None => format!("Successfully stored memory with key: {}", key),
+                }
             }
             MemoryAction::Retrieve => {
                 let key = match properties.key {
                     Some(k) => k,
-                    None => return Ok(Self::error("Key is required for retrieve action")),
+                    None => return Ok(CallToolResult::error("Key is required for retrieve action")),
                 };
-                match store.get(&key) {
-                    Some(value) => serde_json::to_string_pretty(value)
-                        .map_err(|e| ToolError::ResultSerialize(e))?,
+                let entry = store.get(&namespace, &key).map_err(storage_error)?;
+                match entry.filter(|entry| !entry.is_expired(now)) {
+                    Some(entry) => {
+                        let value = serde_json::to_string_pretty(&entry.value)
+                            .map_err(ToolError::ResultSerialize)?;
+                        match entry.expires_at {
+                            Some(expires_at) => {
+                                format!("{}\n\nExpires at: {}", value, expires_at.to_rfc3339())
+                            }
+                            None => value,
+                        }
+                    }
                     None => format!("No memory found for key: {}", key),
                 }
             }
             MemoryAction::List => {
-                let keys: Vec<&String> = store.keys().collect();
+                let entries = store.entries(&namespace).map_err(storage_error)?;
+                let keys: Vec<Value> = entries
+                    .iter()
+                    .filter(|(_, entry)| !entry.is_expired(now))
+                    .map(|(key, entry)| match entry.expires_at {
+                        Some(expires_at) => serde_json::json!({
+                            "key": key,
+                            "expires_at": expires_at.to_rfc3339(),
+                        }),
+                        None => serde_json::json!({ "key": key }),
+                    })
+                    .collect();
                 match serde_json::to_string_pretty(&keys) {
                     Ok(json_str) => json_str,
-                    Err(e) => return Ok(Self::error(format!("Failed to serialize keys: {}", e))),
+                    Err(e) => return Ok(CallToolResult::error(format!("Failed to serialize keys: {}", e))),
                 }
             }
             MemoryAction::Delete => {
                 let key = match properties.key {
                     Some(k) => k,
-                    None => return Ok(Self::error("Key is required for delete action")),
+                    None => return Ok(CallToolResult::error("Key is required for delete action")),
                 };
-                match store.remove(&key) {
+                match store.remove(&namespace, &key).map_err(storage_error)? {
                     Some(_) => format!("Successfully deleted memory with key: {}", key),
                     None => format!("No memory found to delete for key: {}", key),
                 }
             }
             MemoryAction::Clear => {
-                store.clear();
+                store.clear(&namespace).map_err(storage_error)?;
                 "Successfully cleared all memories".to_string()
             }
+            MemoryAction::Search => {
+                let query = match properties.query {
+                    Some(q) => q,
+                    None => return Ok(CallToolResult::error("Query is required for search action")),
+                };
+                let matcher = match Matcher::parse(&query) {
+                    Ok(matcher) => matcher,
+                    Err(e) => return Ok(CallToolResult::error(format!("Invalid JSONPath query: {}", e))),
+                };
+                let entries = store.entries(&namespace).map_err(storage_error)?;
+                let matches: Vec<Value> = entries
+                    .into_iter()
+                    .filter(|(_, entry)| !entry.is_expired(now))
+                    .filter_map(|(key, entry)| {
+                        let value = matcher.matches(&key, &entry.value)?;
+                        let mut result = serde_json::json!({ "key": key, "value": value });
+                        if let Some(expires_at) = entry.expires_at {
+                            result["expires_at"] = expires_at.to_rfc3339().into();
+                        }
+                        Some(result)
+                    })
+                    .collect();
+                serde_json::to_string_pretty(&matches).map_err(ToolError::ResultSerialize)?
+            }
         };
 
-        Ok(Self::success(result))
+        Ok(CallToolResult::text(result))
     }
 }
 
-impl Memory {
-    fn error(error_message: impl Into<String>) -> CallToolResult {
-        CallToolResult {
-            content: vec![serde_json::to_value(TextContent {
-                type_: "text".to_string(),
-                text: error_message.into(),
-                annotations: None,
-            })
-            .unwrap_or_default()],
-            is_error: Some(true),
-            meta: None,
+/// How the `search` action selects memories
+enum Matcher {
+    /// Case-insensitive text contained in the key or the serialized value
+    Text(String),
+    /// JSONPath expression that must select at least one node of the value
+    Path(JsonPath),
+}
+
+impl Matcher {
+    fn parse(query: &str) -> Result<Self, serde_json_path::ParseError> {
+        if query.trim_start().starts_with('$') {
+            Ok(Matcher::Path(JsonPath::parse(query.trim())?))
+        } else {
+            Ok(Matcher::Text(query.to_lowercase()))
         }
     }
 
-    fn success(message: impl Into<String>) -> CallToolResult {
-        CallToolResult {
-            content: vec![serde_json::to_value(TextContent {
-                type_: "text".to_string(),
-                text: message.into(),
-                annotations: None,
-            })
-            .unwrap_or_default()],
-            is_error: Some(false),
-            meta: None,
+    /// The part of the memory that matched: the whole value for text
+    /// queries, or the selected nodes for JSONPath ones
+    fn matches(&self, key: &str, value: &Value) -> Option<Value> {
+        match self {
+            Matcher::Text(text) => {
+                let found = key.to_lowercase().contains(text)
+                    || value.to_string().to_lowercase().contains(text);
+                found.then(|| value.clone())
+            }
+            Matcher::Path(path) => {
+                let nodes = path.query(value).all();
+                match nodes.as_slice() {
+                    [] => None,
+                    [node] => Some((*node).clone()),
+                    nodes => Some(nodes.iter().map(|node| (*node).clone()).collect()),
+                }
+            }
         }
     }
 }
@@ -167,34 +288,40 @@ impl Memory {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tools::ToolCallHandler;
+    use crate::tools::{ToolCallHandler, ToolDef};
     use serde_json::json;
 
     async fn clear_memory() {
-        let tool = Memory;
+        let tool = Memory::default();
         let clear_props = MemoryProperties {
             action: MemoryAction::Clear,
             key: None,
             value: None,
+            query: None,
+            ttl_seconds: None,
+            namespace: None,
         };
-        tool.call(clear_props).await.unwrap();
+        tool.call(clear_props, ToolContext::default()).await.unwrap();
     }
 
     #[tokio::test]
     async fn test_memory_operations() {
         clear_memory().await;
 
-        let tool = Memory;
+        let tool = Memory::default();
 
         // Test storing
         let store_props = MemoryProperties {
             action: MemoryAction::Store,
             key: Some("test_key".to_string()),
             value: Some(json!({"test": "value"})),
+            query: None,
+            ttl_seconds: None,
+            namespace: None,
         };
-        let result = tool.call(store_props).await.unwrap();
-        assert!(result.content[0]["text"]
-            .as_str()
+        let result = tool.call(store_props, ToolContext::default()).await.unwrap();
+        assert!(result.content[0]
+            .as_text()
             .unwrap()
             .contains("Successfully stored"));
 
@@ -203,19 +330,25 @@ mod tests {
             action: MemoryAction::Retrieve,
             key: Some("test_key".to_string()),
             value: None,
+            query: None,
+            ttl_seconds: None,
+            namespace: None,
         };
-        let result = tool.call(retrieve_props).await.unwrap();
-        assert!(result.content[0]["text"].as_str().unwrap().contains("test"));
+        let result = tool.call(retrieve_props, ToolContext::default()).await.unwrap();
+        assert!(result.content[0].as_text().unwrap().contains("test"));
 
         // Test listing
         let list_props = MemoryProperties {
             action: MemoryAction::List,
             key: None,
             value: None,
+            query: None,
+            ttl_seconds: None,
+            namespace: None,
         };
-        let result = tool.call(list_props).await.unwrap();
-        assert!(result.content[0]["text"]
-            .as_str()
+        let result = tool.call(list_props, ToolContext::default()).await.unwrap();
+        assert!(result.content[0]
+            .as_text()
             .unwrap()
             .contains("test_key"));
 
@@ -224,10 +357,13 @@ mod tests {
             action: MemoryAction::Delete,
             key: Some("test_key".to_string()),
             value: None,
+            query: None,
+            ttl_seconds: None,
+            namespace: None,
         };
-        let result = tool.call(delete_props).await.unwrap();
-        assert!(result.content[0]["text"]
-            .as_str()
+        let result = tool.call(delete_props, ToolContext::default()).await.unwrap();
+        assert!(result.content[0]
+            .as_text()
             .unwrap()
             .contains("Successfully deleted"));
 
@@ -236,17 +372,23 @@ mod tests {
             action: MemoryAction::Store,
             key: Some("test_key2".to_string()),
             value: Some(json!({"test": "value"})),
+            query: None,
+            ttl_seconds: None,
+            namespace: None,
         };
-        tool.call(store_props).await.unwrap();
+        tool.call(store_props, ToolContext::default()).await.unwrap();
 
         let clear_props = MemoryProperties {
             action: MemoryAction::Clear,
             key: None,
             value: None,
+            query: None,
+            ttl_seconds: None,
+            namespace: None,
         };
-        let result = tool.call(clear_props).await.unwrap();
-        assert!(result.content[0]["text"]
-            .as_str()
+        let result = tool.call(clear_props, ToolContext::default()).await.unwrap();
+        assert!(result.content[0]
+            .as_text()
             .unwrap()
             .contains("Successfully cleared"));
 
@@ -255,16 +397,164 @@ mod tests {
             action: MemoryAction::List,
             key: None,
             value: None,
+            query: None,
+            ttl_seconds: None,
+            namespace: None,
+        };
+        let result = tool.call(list_props, ToolContext::default()).await.unwrap();
+        assert_eq!(result.content[0].as_text().unwrap(), "[]");
+    }
+
+    #[tokio::test]
+    async fn test_memory_namespaces() {
+        let tool = Memory::default();
+        let props = |action, namespace: Option<&str>| MemoryProperties {
+            action,
+            key: Some("color".to_string()),
+            value: Some(json!(namespace)),
+            query: None,
+            ttl_seconds: None,
+            namespace: namespace.map(String::from),
+        };
+
+        tool.call(props(MemoryAction::Store, Some("alice")), ToolContext::default())
+            .await
+            .unwrap();
+        tool.call(props(MemoryAction::Store, None), ToolContext::default())
+            .await
+            .unwrap();
+
+        let result = tool
+            .call(props(MemoryAction::Retrieve, Some("alice")), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].as_text().unwrap(), "\"alice\"");
+        let result = tool
+            .call(props(MemoryAction::Retrieve, None), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].as_text().unwrap(), "null");
+        let result = tool
+            .call(props(MemoryAction::Retrieve, Some("bob")), ToolContext::default())
+            .await
+            .unwrap();
+        assert!(result.content[0]
+            .as_text()
+            .unwrap()
+            .contains("No memory found"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_expiration() {
+        let tool = Memory::default();
+        let props = |action, key: &str, ttl_seconds| MemoryProperties {
+            action,
+            key: Some(key.to_string()),
+            value: Some(json!("value")),
+            query: None,
+            ttl_seconds,
+            namespace: None,
+        };
+
+        let result = tool
+            .call(props(MemoryAction::Store, "later", Some(3600)), ToolContext::default())
+            .await
+            .unwrap();
+        assert!(result.content[0].as_text().unwrap().contains("expires at"));
+        tool.call(props(MemoryAction::Store, "now", Some(0)), ToolContext::default())
+            .await
+            .unwrap();
+
+        // Expired memories are hidden even before the sweeper evicts them
+        let result = tool
+            .call(props(MemoryAction::Retrieve, "now", None), ToolContext::default())
+            .await
+            .unwrap();
+        assert!(result.content[0]
+            .as_text()
+            .unwrap()
+            .contains("No memory found"));
+
+        let result = tool
+            .call(props(MemoryAction::Retrieve, "later", None), ToolContext::default())
+            .await
+            .unwrap();
+        assert!(result.content[0]
+            .as_text()
+            .unwrap()
+            .contains("Expires at: "));
+
+        let result = tool
+            .call(props(MemoryAction::List, "", None), ToolContext::default())
+            .await
+            .unwrap();
+        let keys: Value = serde_json::from_str(result.content[0].as_text().unwrap()).unwrap();
+        assert_eq!(keys.as_array().unwrap().len(), 1);
+        assert_eq!(keys[0]["key"], "later");
+        assert!(keys[0]["expires_at"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_memory_search() {
+        let tool = Memory::default();
+        let store = |key: &str, value| MemoryProperties {
+            action: MemoryAction::Store,
+            key: Some(key.to_string()),
+            value: Some(value),
+            query: None,
+            ttl_seconds: None,
+            namespace: None,
+        };
+        let search = |query: &str| MemoryProperties {
+            action: MemoryAction::Search,
+            key: None,
+            value: None,
+            query: Some(query.to_string()),
+            ttl_seconds: None,
+            namespace: None,
+        };
+
+        for (key, value) in [
+            ("alice", json!({ "city": "Paris", "tags": ["admin"] })),
+            ("bob", json!({ "city": "Berlin", "tags": [] })),
+            ("notes", json!("Meeting in paris on Monday")),
+        ] {
+            tool.call(store(key, value), ToolContext::default())
+                .await
+                .unwrap();
+        }
+
+        let results = |result: CallToolResult| -> Vec<Value> {
+            serde_json::from_str(result.content[0].as_text().unwrap()).unwrap()
         };
-        let result = tool.call(list_props).await.unwrap();
-        assert_eq!(result.content[0]["text"].as_str().unwrap(), "[]");
+
+        // Text queries match keys and values case-insensitively
+        let found = results(tool.call(search("PARIS"), ToolContext::default()).await.unwrap());
+        let keys: Vec<&Value> = found.iter().map(|m| &m["key"]).collect();
+        assert_eq!(keys, vec!["alice", "notes"]);
+        let found = results(tool.call(search("bo"), ToolContext::default()).await.unwrap());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0]["value"]["city"], "Berlin");
+
+        // JSONPath queries return the selected nodes
+        let found = results(
+            tool.call(search("$.tags[?@ == 'admin']"), ToolContext::default())
+                .await
+                .unwrap(),
+        );
+        assert_eq!(found, vec![json!({ "key": "alice", "value": "admin" })]);
+        let found = results(tool.call(search("$.city"), ToolContext::default()).await.unwrap());
+        assert_eq!(found.len(), 2);
+
+        let result = tool.call(search("$[invalid"), ToolContext::default()).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
     }
 
     #[tokio::test]
     async fn test_memory_input_schema() {
         clear_memory().await;
 
-        let tool = Memory.def();
+        let tool = Memory::default().def();
         let input_schema = tool.input_schema;
 
         assert_eq!(input_schema.type_, "object");
@@ -312,7 +602,7 @@ mod tests {
 
     #[test]
     fn test_auto_generated_schema() {
-        let tool = Memory.def();
+        let tool = Memory::default().def();
         println!("Tool: {:?}", tool);
     }
 }
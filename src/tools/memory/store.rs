@@ -0,0 +1,449 @@
+use crate::error::StoreError;
+use chrono::{DateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// A stored memory and when it expires, if ever
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub value: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl MemoryEntry {
+    /// A memory that never expires
+    pub fn new(value: Value) -> Self {
+        Self {
+            value,
+            expires_at: None,
+        }
+    }
+
+    /// Expires the memory `ttl` from now, to the millisecond
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.expires_at = chrono::Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| Utc::now().trunc_subsecs(3).checked_add_signed(ttl));
+        self
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Storage for the memories of the `Memory` tool
+///
+/// Keys are grouped into namespaces so that different clients can use the
+/// same key without overwriting each other's values. Stores keep expired
+/// entries until [`MemoryStore::remove_expired`] is called.
+pub trait MemoryStore: Debug + Send + Sync {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<MemoryEntry>, StoreError>;
+
+    fn insert(&self, namespace: &str, key: &str, entry: MemoryEntry) -> Result<(), StoreError>;
+
+    fn remove(&self, namespace: &str, key: &str) -> Result<Option<MemoryEntry>, StoreError>;
+
+    /// All keys and entries of the namespace, ordered by key
+    fn entries(&self, namespace: &str) -> Result<Vec<(String, MemoryEntry)>, StoreError>;
+
+    fn clear(&self, namespace: &str) -> Result<(), StoreError>;
+
+    /// Deletes the entries of every namespace that expired by `now`, returning how many
+    fn remove_expired(&self, now: DateTime<Utc>) -> Result<usize, StoreError>;
+}
+
+type Namespaces = HashMap<String, BTreeMap<String, MemoryEntry>>;
+
+fn lock(namespaces: &Mutex<Namespaces>) -> MutexGuard<'_, Namespaces> {
+    namespaces.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn expired(namespaces: &Namespaces, now: DateTime<Utc>) -> usize {
+    namespaces
+        .values()
+        .flat_map(|entries| entries.values())
+        .filter(|entry| entry.is_expired(now))
+        .count()
+}
+
+fn retain_unexpired(namespaces: &mut Namespaces, now: DateTime<Utc>) {
+    for entries in namespaces.values_mut() {
+        entries.retain(|_, entry| !entry.is_expired(now));
+    }
+    namespaces.retain(|_, entries| !entries.is_empty());
+}
+
+/// Memories kept in process, lost when the server exits
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+    namespaces: Mutex<Namespaces>,
+}
+
+impl MemoryStore for InMemoryStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<MemoryEntry>, StoreError> {
+        Ok(lock(&self.namespaces)
+            .get(namespace)
+            .and_then(|entries| entries.get(key))
+            .cloned())
+    }
+
+    fn insert(&self, namespace: &str, key: &str, entry: MemoryEntry) -> Result<(), StoreError> {
+        lock(&self.namespaces)
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(key.to_string(), entry);
+        Ok(())
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> Result<Option<MemoryEntry>, StoreError> {
+        Ok(lock(&self.namespaces)
+            .get_mut(namespace)
+            .and_then(|entries| entries.remove(key)))
+    }
+
+    fn entries(&self, namespace: &str) -> Result<Vec<(String, MemoryEntry)>, StoreError> {
+        Ok(lock(&self.namespaces)
+            .get(namespace)
+            .map(|entries| entries.clone().into_iter().collect())
+            .unwrap_or_default())
+    }
+
+    fn clear(&self, namespace: &str) -> Result<(), StoreError> {
+        lock(&self.namespaces).remove(namespace);
+        Ok(())
+    }
+
+    fn remove_expired(&self, now: DateTime<Utc>) -> Result<usize, StoreError> {
+        let mut namespaces = lock(&self.namespaces);
+        let count = expired(&namespaces, now);
+        retain_unexpired(&mut namespaces, now);
+        Ok(count)
+    }
+}
+
+/// Memories persisted to a JSON file
+///
+/// The whole file is rewritten on every change, through a temporary file
+/// that replaces it atomically so a crash never leaves it half-written.
+#[derive(Debug)]
+pub struct JsonFileStore {
+    path: PathBuf,
+    namespaces: Mutex<Namespaces>,
+}
+
+impl JsonFileStore {
+    /// Loads the memories stored at `path`, starting empty if the file doesn't exist
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, StoreError> {
+        let path = path.into();
+        let namespaces = match std::fs::read(&path) {
+            Ok(contents) => {
+                serde_json::from_slice(&contents).map_err(|source| StoreError::InvalidFile {
+                    path: path.clone(),
+                    source,
+                })?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Namespaces::new(),
+            Err(e) => return Err(StoreError::io(path)(e)),
+        };
+        Ok(Self {
+            path,
+            namespaces: Mutex::new(namespaces),
+        })
+    }
+
+    /// Applies `f` to the memories and writes them to disk, keeping them unchanged if that fails
+    fn update<R>(&self, f: impl FnOnce(&mut Namespaces) -> R) -> Result<R, StoreError> {
+        let mut namespaces = lock(&self.namespaces);
+        let mut updated = namespaces.clone();
+        let result = f(&mut updated);
+        write_atomic(&self.path, &serde_json::to_vec_pretty(&updated)?)?;
+        *namespaces = updated;
+        Ok(result)
+    }
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), StoreError> {
+    use std::io::Write;
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let mut file = std::fs::File::create(&temp).map_err(StoreError::io(&temp))?;
+    file.write_all(contents).map_err(StoreError::io(&temp))?;
+    file.sync_all().map_err(StoreError::io(&temp))?;
+    std::fs::rename(&temp, path).map_err(StoreError::io(path))
+}
+
+impl MemoryStore for JsonFileStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<MemoryEntry>, StoreError> {
+        Ok(lock(&self.namespaces)
+            .get(namespace)
+            .and_then(|entries| entries.get(key))
+            .cloned())
+    }
+
+    fn insert(&self, namespace: &str, key: &str, entry: MemoryEntry) -> Result<(), StoreError> {
+        self.update(|namespaces| {
+            namespaces
+                .entry(namespace.to_string())
+                .or_default()
+                .insert(key.to_string(), entry);
+        })
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> Result<Option<MemoryEntry>, StoreError> {
+        if self.get(namespace, key)?.is_none() {
+            return Ok(None);
+        }
+        self.update(|namespaces| {
+            namespaces
+                .get_mut(namespace)
+                .and_then(|entries| entries.remove(key))
+        })
+    }
+
+    fn entries(&self, namespace: &str) -> Result<Vec<(String, MemoryEntry)>, StoreError> {
+        Ok(lock(&self.namespaces)
+            .get(namespace)
+            .map(|entries| entries.clone().into_iter().collect())
+            .unwrap_or_default())
+    }
+
+    fn clear(&self, namespace: &str) -> Result<(), StoreError> {
+        self.update(|namespaces| {
+            namespaces.remove(namespace);
+        })
+    }
+
+    fn remove_expired(&self, now: DateTime<Utc>) -> Result<usize, StoreError> {
+        // Avoid rewriting the file when nothing expired
+        if expired(&lock(&self.namespaces), now) == 0 {
+            return Ok(0);
+        }
+        self.update(|namespaces| {
+            let count = expired(namespaces, now);
+            retain_unexpired(namespaces, now);
+            count
+        })
+    }
+}
+
+/// Memories persisted to a SQLite database
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    /// Opens or creates the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let connection = rusqlite::Connection::open(path)?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS memories (
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                expires_at INTEGER,
+                PRIMARY KEY (namespace, key)
+            )",
+            [],
+        )?;
+
+        // Databases created before expiration was supported lack the column
+        let has_expiry: bool = connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('memories') WHERE name = 'expires_at')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_expiry {
+            connection.execute("ALTER TABLE memories ADD COLUMN expires_at INTEGER", [])?;
+        }
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> MutexGuard<'_, rusqlite::Connection> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn entry(value: String, expires_at: Option<i64>) -> Result<MemoryEntry, StoreError> {
+        Ok(MemoryEntry {
+            value: serde_json::from_str(&value)?,
+            expires_at: expires_at.and_then(DateTime::from_timestamp_millis),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl MemoryStore for SqliteStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<MemoryEntry>, StoreError> {
+        use rusqlite::OptionalExtension;
+
+        let row: Option<(String, Option<i64>)> = self
+            .connection()
+            .query_row(
+                "SELECT value, expires_at FROM memories WHERE namespace = ?1 AND key = ?2",
+                [namespace, key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        row.map(|(value, expires_at)| Self::entry(value, expires_at))
+            .transpose()
+    }
+
+    fn insert(&self, namespace: &str, key: &str, entry: MemoryEntry) -> Result<(), StoreError> {
+        self.connection().execute(
+            "INSERT OR REPLACE INTO memories (namespace, key, value, expires_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                namespace,
+                key,
+                entry.value.to_string(),
+                entry.expires_at.map(|expires_at| expires_at.timestamp_millis()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> Result<Option<MemoryEntry>, StoreError> {
+        let entry = self.get(namespace, key)?;
+        self.connection().execute(
+            "DELETE FROM memories WHERE namespace = ?1 AND key = ?2",
+            [namespace, key],
+        )?;
+        Ok(entry)
+    }
+
+    fn entries(&self, namespace: &str) -> Result<Vec<(String, MemoryEntry)>, StoreError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT key, value, expires_at FROM memories WHERE namespace = ?1 ORDER BY key",
+        )?;
+        let rows = statement.query_map([namespace], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<i64>>(2)?,
+            ))
+        })?;
+        rows.map(|row| {
+            let (key, value, expires_at) = row?;
+            Ok((key, Self::entry(value, expires_at)?))
+        })
+        .collect()
+    }
+
+    fn clear(&self, namespace: &str) -> Result<(), StoreError> {
+        self.connection()
+            .execute("DELETE FROM memories WHERE namespace = ?1", [namespace])?;
+        Ok(())
+    }
+
+    fn remove_expired(&self, now: DateTime<Utc>) -> Result<usize, StoreError> {
+        Ok(self.connection().execute(
+            "DELETE FROM memories WHERE expires_at IS NOT NULL AND expires_at <= ?1",
+            [now.timestamp_millis()],
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn value(store: &dyn MemoryStore, namespace: &str, key: &str) -> Option<Value> {
+        store.get(namespace, key).unwrap().map(|entry| entry.value)
+    }
+
+    fn exercise(store: &dyn MemoryStore) {
+        store
+            .insert("alice", "color", MemoryEntry::new(json!("blue")))
+            .unwrap();
+        store
+            .insert(
+                "bob",
+                "color",
+                MemoryEntry::new(json!({ "rgb": [255, 0, 0] })),
+            )
+            .unwrap();
+        store
+            .insert("alice", "age", MemoryEntry::new(json!(30)))
+            .unwrap();
+
+        // Namespaces don't see each other's keys
+        assert_eq!(value(store, "alice", "color"), Some(json!("blue")));
+        assert_eq!(
+            value(store, "bob", "color"),
+            Some(json!({ "rgb": [255, 0, 0] }))
+        );
+        let keys: Vec<String> = store
+            .entries("alice")
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec!["age", "color"]);
+
+        assert_eq!(
+            store.remove("alice", "age").unwrap(),
+            Some(MemoryEntry::new(json!(30)))
+        );
+        assert_eq!(store.remove("alice", "age").unwrap(), None);
+        store.clear("bob").unwrap();
+        assert!(store.entries("bob").unwrap().is_empty());
+        assert_eq!(store.entries("alice").unwrap().len(), 1);
+
+        // Only entries past their expiry are swept
+        let expiring = MemoryEntry::new(json!("soon")).with_ttl(Duration::from_secs(60));
+        let expires_at = expiring.expires_at.unwrap();
+        store.insert("bob", "soon", expiring).unwrap();
+        assert_eq!(
+            store.get("bob", "soon").unwrap().unwrap().expires_at,
+            Some(expires_at)
+        );
+        assert_eq!(store.remove_expired(Utc::now()).unwrap(), 0);
+        let later = expires_at + chrono::Duration::seconds(1);
+        assert_eq!(store.remove_expired(later).unwrap(), 1);
+        assert_eq!(value(store, "bob", "soon"), None);
+        assert_eq!(value(store, "alice", "color"), Some(json!("blue")));
+    }
+
+    #[test]
+    fn test_in_memory_store() {
+        exercise(&InMemoryStore::default());
+    }
+
+    #[test]
+    fn test_json_file_store() {
+        let path = std::env::temp_dir().join(format!("bioma-memory-{}.json", uuid::Uuid::new_v4()));
+        exercise(&JsonFileStore::open(&path).unwrap());
+
+        // Memories survive reopening the file
+        let store = JsonFileStore::open(&path).unwrap();
+        assert_eq!(value(&store, "alice", "color"), Some(json!("blue")));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store() {
+        let path = std::env::temp_dir().join(format!("bioma-memory-{}.db", uuid::Uuid::new_v4()));
+        exercise(&SqliteStore::open(&path).unwrap());
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(value(&store, "alice", "color"), Some(json!("blue")));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,15 +1,59 @@
-use crate::schema::{self, CallToolResult};
-use schemars::JsonSchema;
+use crate::schema::{self, CallToolResult, ToolInputSchema, ToolOutputSchema};
+use schemars::{gen::SchemaSettings, JsonSchema};
 use serde::Serialize;
 use serde_json::Value;
 use std::collections::BTreeMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 
 /// Modules containing tool implementations
+pub mod batch;
+pub mod bundle;
+#[cfg(feature = "tool-calc")]
+pub mod calc;
+pub mod command;
+pub mod context;
+// Other tools' tests call echo
+#[cfg(any(test, feature = "tool-echo"))]
 pub mod echo;
+// Resources, prompts and the config use it too
 pub mod fetch;
+#[cfg(feature = "tool-fs")]
+pub mod filesystem;
+#[cfg(feature = "headless-browser")]
+pub mod headless_browser;
+#[cfg(feature = "tool-image")]
+pub mod image;
+#[cfg(feature = "tool-memory")]
 pub mod memory;
+pub mod overrides;
+#[cfg(feature = "tool-pipeline")]
+pub mod pipeline;
+#[cfg(feature = "tool-search")]
+pub mod search;
+pub mod shell;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod subserver;
+#[cfg(feature = "tool-summarize")]
+pub mod summarize;
+#[cfg(feature = "tool-thinking")]
+pub mod thinking;
+#[cfg(feature = "tool-time")]
+pub mod time;
+pub mod validation;
+
+pub use bioma_tool_derive::McpTool;
+pub use bundle::{all, Bundle};
+pub use context::{ToolCaller, ToolContext};
+pub use validation::{ArgumentError, ArgumentValidator};
+
+/// Time a tool call may run before the client gets an error, unless the tool sets its own
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest arguments of a tool call as JSON, unless the server sets its own limit
+pub const DEFAULT_MAX_ARGUMENTS_SIZE: usize = 16 * 1024 * 1024;
 
 /// Errors that can occur during tool operations
 #[derive(Debug, thiserror::Error)]
@@ -29,6 +73,111 @@ pub enum ToolError {
     /// Error custom
     #[error("Custom error: {0}")]
     Custom(String),
+
+    /// Error when the arguments don't match the tool's input schema
+    #[error("Invalid tool arguments: {}", join_errors(.0))]
+    InvalidArguments(Vec<ArgumentError>),
+
+    /// Error when the structured content doesn't match the tool's output schema
+    #[error("Invalid tool output: {}", join_errors(.0))]
+    InvalidOutput(Vec<ArgumentError>),
+}
+
+fn join_errors(errors: &[ArgumentError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl ToolError {
+    /// The response to a call of `tool` that failed with this error
+    ///
+    /// Execution errors are results with `isError` set, so the model sees the
+    /// message. Arguments that don't fit the tool's schema are an invalid
+    /// params error, and anything else an internal error, with the tool and
+    /// what went wrong in the error's data.
+    pub fn into_response(self, tool: &str) -> Result<CallToolResult, jsonrpc_core::Error> {
+        match self {
+            ToolError::Execution(message) | ToolError::Custom(message) => {
+                Ok(CallToolResult::error(message))
+            }
+            ToolError::ArgumentParse(e) => Err(jsonrpc_core::Error {
+                code: jsonrpc_core::ErrorCode::InvalidParams,
+                message: format!("Invalid arguments for tool {}: {}", tool, e),
+                data: Some(serde_json::json!({ "tool": tool, "reason": e.to_string() })),
+            }),
+            ToolError::InvalidArguments(errors) => Err(jsonrpc_core::Error {
+                code: jsonrpc_core::ErrorCode::InvalidParams,
+                message: format!(
+                    "Invalid arguments for tool {}: {}",
+                    tool,
+                    join_errors(&errors)
+                ),
+                data: Some(serde_json::json!({ "tool": tool, "errors": errors })),
+            }),
+            ToolError::ResultSerialize(e) => Err(jsonrpc_core::Error {
+                code: jsonrpc_core::ErrorCode::InternalError,
+                message: format!("Tool {} returned a result that can't be sent", tool),
+                data: Some(serde_json::json!({ "tool": tool, "reason": e.to_string() })),
+            }),
+            ToolError::InvalidOutput(errors) => Err(jsonrpc_core::Error {
+                code: jsonrpc_core::ErrorCode::InternalError,
+                message: format!(
+                    "Tool {} returned output that doesn't match its schema: {}",
+                    tool,
+                    join_errors(&errors)
+                ),
+                data: Some(serde_json::json!({ "tool": tool, "errors": errors })),
+            }),
+        }
+    }
+}
+
+/// Error for a call of a tool the server doesn't offer
+pub fn unknown_tool(name: &str) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::InvalidParams,
+        message: format!("Unknown tool: {}", name),
+        data: Some(serde_json::json!({ "tool": name })),
+    }
+}
+
+/// Error for a call of `name` whose arguments are `size` bytes, more than `limit`
+pub fn arguments_too_large(name: &str, size: usize, limit: usize) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::InvalidParams,
+        message: format!(
+            "Arguments for tool {} are {} bytes, larger than the limit of {} bytes",
+            name, size, limit
+        ),
+        data: Some(serde_json::json!({ "tool": name, "size": size, "limit": limit })),
+    }
+}
+
+/// Length of `arguments` as JSON, worked out without writing it out
+pub fn arguments_size(arguments: Option<&BTreeMap<String, Value>>) -> usize {
+    struct Counter(usize);
+
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    match arguments {
+        Some(arguments) => serde_json::to_writer(&mut counter, arguments)
+            .map(|_| counter.0)
+            .unwrap_or(0),
+        None => 0,
+    }
 }
 
 /// Trait for handling tool calls with dynamic dispatch
@@ -40,16 +189,23 @@ pub trait ToolCallHandler: Send + Sync {
     ///
     /// # Arguments
     /// * `args` - Optional map of argument names to JSON values
+    /// * `context` - The client session the tool is called from
     ///
     /// # Returns
     /// A future that resolves to either a tool result or an error
     fn call_boxed<'a>(
         &'a self,
         args: Option<BTreeMap<String, Value>>,
+        context: ToolContext,
     ) -> Pin<Box<dyn Future<Output = Result<CallToolResult, ToolError>> + Send + 'a>>;
 
     /// Returns the tool's definition/schema
     fn def(&self) -> schema::Tool;
+
+    /// How long a call may run, if the tool needs something other than the server's default
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
 }
 
 /// Trait for defining a concrete tool implementation
@@ -66,6 +222,12 @@ pub trait ToolDef: Serialize {
     /// The type representing the tool's input properties
     type Properties: Serialize + JsonSchema + serde::de::DeserializeOwned;
 
+    /// The type of the tool's structured output, `()` for tools without one
+    ///
+    /// Set with `#[mcp_tool(output = ...)]`, which makes the definition carry
+    /// its `outputSchema`. Tools send it with `CallToolResult::structured`.
+    type Output: Serialize + JsonSchema;
+
     /// Generates the tool's schema definition
     ///
     /// This method creates a complete tool schema including name, description,
@@ -76,13 +238,20 @@ pub trait ToolDef: Serialize {
     ///
     /// # Arguments
     /// * `properties` - The typed input properties for the tool
+    /// * `context` - The client session the tool is called from
     ///
     /// # Returns
     /// A future that resolves to either a tool result or an error
     fn call<'a>(
         &'a self,
         properties: Self::Properties,
+        context: ToolContext,
     ) -> impl Future<Output = Result<CallToolResult, ToolError>> + Send + 'a;
+
+    /// How long a call may run, set with `#[mcp_tool(timeout_secs = ...)]`
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
 }
 
 /// Implementation of `ToolCallHandler` for any type implementing `ToolDef`
@@ -93,6 +262,7 @@ impl<T: ToolDef + Send + Sync> ToolCallHandler for T {
     fn call_boxed<'a>(
         &'a self,
         args: Option<BTreeMap<String, Value>>,
+        context: ToolContext,
     ) -> Pin<Box<dyn Future<Output = Result<CallToolResult, ToolError>> + Send + 'a>> {
         Box::pin(async move {
             let value = match args {
@@ -103,11 +273,336 @@ impl<T: ToolDef + Send + Sync> ToolCallHandler for T {
             let properties: T::Properties =
                 serde_json::from_value(value).map_err(ToolError::ArgumentParse)?;
 
-            self.call(properties).await
+            self.call(properties, context).await
         })
     }
 
     fn def(&self) -> schema::Tool {
         T::def()
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        ToolDef::timeout(self)
+    }
+}
+
+/// Calls a tool, giving up once it runs longer than its timeout
+///
+/// Uses `default_timeout` for tools that don't set their own. A call that
+/// times out is reported to the client as a failed tool call.
+pub async fn call_with_timeout(
+    tool: &dyn ToolCallHandler,
+    args: Option<BTreeMap<String, Value>>,
+    context: ToolContext,
+    default_timeout: Duration,
+) -> Result<CallToolResult, ToolError> {
+    let timeout = tool.timeout().unwrap_or(default_timeout);
+    match tokio::time::timeout(timeout, tool.call_boxed(args, context)).await {
+        Ok(result) => result,
+        Err(_) => Ok(CallToolResult::error(format!(
+            "Tool {} timed out after {} seconds",
+            tool.def().name,
+            timeout.as_secs_f64()
+        ))),
+    }
+}
+
+/// Builds a tool definition whose input schema is generated from `P`
+///
+/// Nested types are inlined, since a tool's input schema can't carry
+/// definitions for them to refer to. Used by `#[derive(McpTool)]`.
+pub fn tool_def<P: JsonSchema>(name: &str, description: &str) -> schema::Tool {
+    schema::Tool {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        input_schema: input_schema::<P>(),
+        output_schema: None,
+        annotations: None,
+    }
+}
+
+/// Builds a tool definition whose input schema is generated from `P` and
+/// output schema from `O`
+pub fn tool_def_with_output<P: JsonSchema, O: JsonSchema>(
+    name: &str,
+    description: &str,
+) -> schema::Tool {
+    schema::Tool {
+        output_schema: Some(output_schema::<O>()),
+        ..tool_def::<P>(name, description)
+    }
+}
+
+/// Generates the JSON schema of a tool's input properties
+pub fn input_schema<P: JsonSchema>() -> ToolInputSchema {
+    let (properties, required) = object_schema::<P>();
+    ToolInputSchema {
+        properties: Some(properties),
+        required,
+        type_: "object".to_string(),
+    }
+}
+
+/// Generates the JSON schema of a tool's structured output
+pub fn output_schema<O: JsonSchema>() -> ToolOutputSchema {
+    let (properties, required) = object_schema::<O>();
+    ToolOutputSchema {
+        properties: Some(properties),
+        required,
+        type_: "object".to_string(),
+    }
+}
+
+/// Properties of an object schema, and which of them are required
+type ObjectSchema = (
+    BTreeMap<String, BTreeMap<String, Value>>,
+    Option<Vec<String>>,
+);
+
+/// Properties and required properties of the object schema of `T`
+fn object_schema<T: JsonSchema>() -> ObjectSchema {
+    let generator = SchemaSettings::draft07()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    let root = generator.into_root_schema_for::<T>();
+    let object = root.schema.object.unwrap_or_default();
+
+    // Boolean schemas (such as `true` for any JSON value) become objects
+    let properties = object
+        .properties
+        .into_iter()
+        .map(|(name, schema)| {
+            let schema = match serde_json::to_value(schema) {
+                Ok(Value::Object(schema)) => schema.into_iter().collect(),
+                _ => BTreeMap::new(),
+            };
+            (name, schema)
+        })
+        .collect();
+    let required = object.required.into_iter().collect::<Vec<_>>();
+    (properties, (!required.is_empty()).then_some(required))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct GreetProperties {
+        #[schemars(description = "Who to greet")]
+        name: String,
+        #[schemars(description = "Greeting to use instead of hello")]
+        greeting: Option<String>,
+        style: GreetStyle,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum GreetStyle {
+        Formal,
+        Casual,
+    }
+
+    /// Greets someone by name
+    #[derive(Serialize, McpTool)]
+    #[mcp_tool(name = "greet", properties = GreetProperties)]
+    struct Greet;
+
+    impl Greet {
+        async fn run(
+            &self,
+            properties: GreetProperties,
+            _context: ToolContext,
+        ) -> Result<CallToolResult, ToolError> {
+            let greeting = properties.greeting.unwrap_or_else(|| "Hello".to_string());
+            Ok(CallToolResult::text(format!("{}, {}!", greeting, properties.name)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_derived_tool() {
+        let def = Greet.def();
+        assert_eq!(def.name, "greet");
+        assert_eq!(def.description.as_deref(), Some("Greets someone by name"));
+
+        let schema = def.input_schema;
+        let properties = schema.properties.unwrap();
+        assert_eq!(properties["name"]["description"], "Who to greet");
+        assert_eq!(properties["name"]["type"], "string");
+        // Nested types are inlined rather than referenced
+        assert_eq!(
+            properties["style"]["enum"],
+            serde_json::json!(["formal", "casual"])
+        );
+        assert_eq!(
+            schema.required.unwrap(),
+            vec!["name".to_string(), "style".to_string()]
+        );
+
+        let args = serde_json::json!({ "name": "Ada", "style": "casual" });
+        let args = serde_json::from_value(args).unwrap();
+        let result = Greet
+            .call_boxed(Some(args), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].as_text(), Some("Hello, Ada!"));
+    }
+
+    /// Never finishes
+    #[derive(Serialize, McpTool)]
+    #[mcp_tool(name = "stall", properties = serde_json::Value, timeout_secs = 120)]
+    struct Stall;
+
+    impl Stall {
+        async fn run(
+            &self,
+            _properties: Value,
+            _context: ToolContext,
+        ) -> Result<CallToolResult, ToolError> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_timeout() {
+        assert_eq!(
+            ToolCallHandler::timeout(&Stall),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(ToolCallHandler::timeout(&Greet), None);
+
+        let args = serde_json::json!({ "name": "Ada", "style": "formal" });
+        let result = call_with_timeout(
+            &Greet,
+            serde_json::from_value(args).unwrap(),
+            ToolContext::default(),
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.is_error, Some(false));
+
+        // A tool's own timeout wins over the server's default
+        tokio::time::pause();
+        let result = call_with_timeout(&Stall, None, ToolContext::default(), Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.content[0].as_text(),
+            Some("Tool stall timed out after 120 seconds")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_error_responses() {
+        let result = ToolError::Execution("disk full".to_string())
+            .into_response("greet")
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(result.content[0].as_text(), Some("disk full"));
+
+        let args = BTreeMap::from([("name".to_string(), Value::from(42))]);
+        let error = Greet
+            .call_boxed(Some(args), ToolContext::default())
+            .await
+            .err()
+            .unwrap()
+            .into_response("greet")
+            .unwrap_err();
+        assert_eq!(error.code, jsonrpc_core::ErrorCode::InvalidParams);
+        let data = error.data.unwrap();
+        assert_eq!(data["tool"], "greet");
+        assert!(data["reason"]
+            .as_str()
+            .unwrap()
+            .contains("expected a string"));
+
+        let error = unknown_tool("missing");
+        assert_eq!(error.code, jsonrpc_core::ErrorCode::InvalidParams);
+        assert_eq!(error.message, "Unknown tool: missing");
+        assert_eq!(error.data.unwrap()["tool"], "missing");
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct MeasureProperties {
+        text: String,
+        /// Return output that doesn't match the schema
+        lie: Option<bool>,
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    struct MeasureOutput {
+        chars: usize,
+    }
+
+    /// Counts the characters of some text
+    #[derive(Serialize, McpTool)]
+    #[mcp_tool(name = "measure", properties = MeasureProperties, output = MeasureOutput)]
+    struct Measure;
+
+    impl Measure {
+        async fn run(
+            &self,
+            properties: MeasureProperties,
+            _context: ToolContext,
+        ) -> Result<CallToolResult, ToolError> {
+            let output = MeasureOutput {
+                chars: properties.text.chars().count(),
+            };
+            let mut result =
+                CallToolResult::structured(output).map_err(ToolError::ResultSerialize)?;
+            if properties.lie == Some(true) {
+                result.structured_content = Some(serde_json::json!({ "chars": "many" }));
+            }
+            Ok(result)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_structured_output() {
+        let server =
+            crate::testing::TestServer::start(crate::Server::builder().tool(Measure).build()).await;
+
+        let listed: Value = server
+            .request("tools/list", serde_json::json!({}))
+            .await
+            .unwrap();
+        let tool = &listed["tools"][0];
+        assert_eq!(tool["name"], "measure");
+        assert_eq!(tool["outputSchema"]["type"], "object");
+        assert_eq!(
+            tool["outputSchema"]["required"],
+            serde_json::json!(["chars"])
+        );
+        assert!(tool["outputSchema"]["properties"]["chars"].is_object());
+
+        let called: Value = server
+            .request(
+                "tools/call",
+                serde_json::json!({ "name": "measure", "arguments": { "text": "héllo" } }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            called["structuredContent"],
+            serde_json::json!({ "chars": 5 })
+        );
+
+        let lied = server
+            .call_tool("measure", serde_json::json!({ "text": "hi", "lie": true }))
+            .await;
+        match lied {
+            Err(crate::ClientError::Rpc(error)) => {
+                assert_eq!(error.code, jsonrpc_core::ErrorCode::InternalError);
+                let data = error.data.unwrap();
+                assert_eq!(data["tool"], "measure");
+                assert_eq!(data["errors"][0]["path"], "/chars");
+            }
+            other => panic!("Expected an internal error, got {:?}", other),
+        }
+
+        server.shutdown().await;
+    }
 }
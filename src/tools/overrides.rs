@@ -0,0 +1,78 @@
+use super::{ToolCallHandler, ToolContext, ToolError};
+use crate::schema::{self, CallToolResult};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How a tool is presented to clients instead of how it describes itself
+///
+/// ```toml
+/// [tools.fetch]
+/// name = "web.get"
+/// description = "Fetches a page from the allowed hosts"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolOverride {
+    /// Name the tool is listed and called by
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+impl ToolOverride {
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// `tool` presented as this override says
+    pub fn apply(&self, tool: Arc<dyn ToolCallHandler>) -> Arc<dyn ToolCallHandler> {
+        if self.name.is_none() && self.description.is_none() {
+            return tool;
+        }
+        Arc::new(OverriddenTool {
+            tool,
+            overrides: self.clone(),
+        })
+    }
+}
+
+/// A tool under another name or description, which it's called as usual under
+struct OverriddenTool {
+    tool: Arc<dyn ToolCallHandler>,
+    overrides: ToolOverride,
+}
+
+impl ToolCallHandler for OverriddenTool {
+    fn call_boxed<'a>(
+        &'a self,
+        args: Option<BTreeMap<String, Value>>,
+        context: ToolContext,
+    ) -> Pin<Box<dyn Future<Output = Result<CallToolResult, ToolError>> + Send + 'a>> {
+        self.tool.call_boxed(args, context)
+    }
+
+    fn def(&self) -> schema::Tool {
+        let mut def = self.tool.def();
+        if let Some(name) = &self.overrides.name {
+            def.name = name.clone();
+        }
+        if let Some(description) = &self.overrides.description {
+            def.description = Some(description.clone());
+        }
+        def
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.tool.timeout()
+    }
+}
@@ -0,0 +1,225 @@
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Most steps a pipeline may have
+const MAX_STEPS: usize = 20;
+
+/// Stands for the text the step before returned
+const PREVIOUS: &str = "{{previous}}";
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PipelineProperties {
+    #[schemars(
+        description = "Tool calls to run in order. In string arguments, {{previous}} is replaced by the text the previous step returned and {{steps.N}} by the text step N returned, counting from 0",
+        required = true
+    )]
+    steps: Vec<PipelineStep>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PipelineStep {
+    #[schemars(description = "Name of the tool to call", required = true)]
+    tool: String,
+
+    #[schemars(description = "Arguments of the call")]
+    #[serde(default)]
+    arguments: Map<String, Value>,
+}
+
+/// What each step of a pipeline returned
+#[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PipelineOutput {
+    /// Results of the steps, in the order they ran
+    pub steps: Vec<StepOutput>,
+    /// Text the last step returned
+    pub output: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct StepOutput {
+    pub tool: String,
+    /// Text content of the step's result
+    pub text: String,
+}
+
+/// Runs several tool calls in one, feeding each step's output to the next
+///
+/// Every step is called like the client would call it, with the same
+/// checks and policies. The pipeline stops at the first step that fails.
+#[derive(Clone, Debug, Default, Serialize, McpTool)]
+#[mcp_tool(
+    name = "pipeline",
+    description = "Runs a sequence of tool calls server-side, passing the text each step returns into the arguments of later steps, and returns every step's result. Use it for multi-step workflows like fetching a page and storing it in memory.",
+    properties = PipelineProperties,
+    output = PipelineOutput
+)]
+pub struct Pipeline;
+
+impl Pipeline {
+    async fn run(
+        &self,
+        properties: PipelineProperties,
+        context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        if properties.steps.is_empty() {
+            return Ok(CallToolResult::error("A pipeline needs at least one step"));
+        }
+        if properties.steps.len() > MAX_STEPS {
+            return Ok(CallToolResult::error(format!(
+                "A pipeline can't have more than {} steps",
+                MAX_STEPS
+            )));
+        }
+
+        let mut steps: Vec<StepOutput> = Vec::new();
+        for (i, step) in properties.steps.into_iter().enumerate() {
+            let texts: Vec<&str> = steps.iter().map(|step| step.text.as_str()).collect();
+            let arguments = wire(Value::Object(step.arguments), &texts);
+            let result = match context.call_tool(&step.tool, arguments).await {
+                Ok(result) => result,
+                Err(e) => {
+                    return Ok(CallToolResult::error(format!(
+                        "Step {} ({}) failed: {}",
+                        i, step.tool, e
+                    )))
+                }
+            };
+            let text = text_of(&result);
+            if result.is_error == Some(true) {
+                return Ok(CallToolResult::error(format!(
+                    "Step {} ({}) failed: {}",
+                    i, step.tool, text
+                )));
+            }
+            steps.push(StepOutput {
+                tool: step.tool,
+                text,
+            });
+        }
+
+        let output = steps
+            .last()
+            .map(|step| step.text.clone())
+            .unwrap_or_default();
+        CallToolResult::structured(PipelineOutput { steps, output })
+            .map_err(ToolError::ResultSerialize)
+    }
+}
+
+/// The text contents of `result`, one per line
+fn text_of(result: &CallToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|content| content.as_text())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replaces the placeholders in every string of `value` with the texts of earlier steps
+fn wire(value: Value, texts: &[&str]) -> Value {
+    match value {
+        Value::String(s) => Value::String(substitute(&s, texts)),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| wire(v, texts)).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, v)| (key, wire(v, texts)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// `s` with `{{previous}}` and `{{steps.N}}` replaced, leaving unknown placeholders as they are
+fn substitute(s: &str, texts: &[&str]) -> String {
+    let mut s = match texts.last() {
+        Some(previous) => s.replace(PREVIOUS, previous),
+        None => s.to_string(),
+    };
+    // Later steps first, so {{steps.1}} doesn't match the start of {{steps.10}}
+    for (i, text) in texts.iter().enumerate().rev() {
+        s = s.replace(&format!("{{{{steps.{}}}}}", i), text);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_tool_error, TestServer};
+    use crate::tools::echo::Echo;
+    use crate::Server;
+    use serde_json::json;
+
+    #[test]
+    fn test_substitute() {
+        let texts = ["a", "b"];
+        assert_eq!(substitute("{{previous}}!", &texts), "b!");
+        assert_eq!(substitute("{{steps.0}}{{steps.1}}", &texts), "ab");
+        assert_eq!(substitute("{{steps.2}}", &texts), "{{steps.2}}");
+        assert_eq!(substitute("{{previous}}", &[]), "{{previous}}");
+        assert_eq!(
+            wire(json!({ "list": ["{{steps.0}}", 1] }), &texts),
+            json!({ "list": ["a", 1] })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipeline() {
+        let server = Server::builder().tool(Echo).tool(Pipeline).build();
+        let server = TestServer::start(server).await;
+
+        let result = server
+            .call(
+                "pipeline",
+                json!({ "steps": [
+                    { "tool": "echo", "arguments": { "message": "hi" } },
+                    { "tool": "echo", "arguments": { "message": "{{previous}} there" } },
+                    { "tool": "echo", "arguments": { "message": "{{steps.0}}, {{previous}}" } },
+                ] }),
+            )
+            .await;
+        let output: PipelineOutput =
+            serde_json::from_value(result.structured_content.unwrap()).unwrap();
+        assert_eq!(output.steps.len(), 3);
+        assert_eq!(output.steps[1].text, "hi there");
+        assert_eq!(output.output, "hi, hi there");
+
+        // A step with bad arguments stops the pipeline
+        let result = server
+            .call(
+                "pipeline",
+                json!({ "steps": [{ "tool": "echo" }, { "tool": "unknown" }] }),
+            )
+            .await;
+        assert!(assert_tool_error(&result).starts_with("Step 0 (echo) failed"));
+
+        // Pipelines nest, but only so deep
+        let mut steps = json!({ "steps": [{ "tool": "echo", "arguments": { "message": "x" } }] });
+        for _ in 0..10 {
+            steps = json!({ "steps": [{ "tool": "pipeline", "arguments": steps }] });
+        }
+        let result = server.call("pipeline", steps).await;
+        assert!(assert_tool_error(&result).contains("can't be nested"));
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_without_server() {
+        let properties = PipelineProperties {
+            steps: vec![PipelineStep {
+                tool: "echo".to_string(),
+                arguments: Map::new(),
+            }],
+        };
+        let result = Pipeline
+            .run(properties, ToolContext::default())
+            .await
+            .unwrap();
+        assert!(assert_tool_error(&result).contains("can't be called"));
+    }
+}
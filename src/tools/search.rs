@@ -0,0 +1,496 @@
+use crate::error::SearchError;
+use crate::schema::CallToolResult;
+use crate::secrets::{MissingSecrets, Secret, SecretsProvider};
+use crate::tools::{McpTool, ToolContext, ToolDef, ToolError};
+use schemars::JsonSchema;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+/// Number of results returned when the caller doesn't set `count`
+const DEFAULT_RESULT_COUNT: usize = 10;
+
+/// A single search hit, in ranking order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// A web search engine the search tool can query
+pub trait SearchProvider: fmt::Debug + Send + Sync {
+    /// Name of the engine, shown alongside the results
+    fn name(&self) -> &str;
+
+    /// Returns up to `count` results for `query`, best first
+    fn search<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        query: &'a str,
+        count: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SearchResult>, SearchError>> + Send + 'a>>;
+}
+
+/// A SearxNG instance, queried through its JSON API
+///
+/// The instance must have the `json` format enabled in its settings.
+#[derive(Clone, Debug)]
+pub struct SearxNg {
+    base_url: Url,
+}
+
+impl SearxNg {
+    pub fn new(base_url: Url) -> Self {
+        Self { base_url }
+    }
+}
+
+impl SearchProvider for SearxNg {
+    fn name(&self) -> &str {
+        "SearxNG"
+    }
+
+    fn search<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        query: &'a str,
+        count: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SearchResult>, SearchError>> + Send + 'a>> {
+        Box::pin(async move {
+            let url = self.base_url.join("search")?;
+            let response: Value = client
+                .get(url)
+                .query(&[("q", query), ("format", "json")])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .map_err(SearchError::invalid_response("SearxNG"))?;
+            Ok(json_results(&response["results"], "content", count))
+        })
+    }
+}
+
+/// The Brave Search API, which needs a subscription token
+#[derive(Clone)]
+pub struct BraveSearch {
+    api_key: Secret,
+    endpoint: Url,
+}
+
+impl BraveSearch {
+    /// Secret holding the subscription token
+    pub const API_KEY: &'static str = "BRAVE_API_KEY";
+
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_key(Secret::new(api_key))
+    }
+
+    /// Takes the subscription token from `secrets`
+    pub fn from_secrets(secrets: &SecretsProvider) -> Result<Self, MissingSecrets> {
+        let mut keys = secrets.require(&[Self::API_KEY])?;
+        Ok(Self::with_key(keys.remove(0)))
+    }
+
+    fn with_key(api_key: Secret) -> Self {
+        Self {
+            api_key,
+            endpoint: Url::parse("https://api.search.brave.com/res/v1/web/search")
+                .expect("valid Brave Search endpoint"),
+        }
+    }
+
+    /// Sends requests to `endpoint` instead of the public API
+    pub fn with_endpoint(mut self, endpoint: Url) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+}
+
+impl fmt::Debug for BraveSearch {
+    // Keeps the API key out of logs
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BraveSearch")
+            .field("endpoint", &self.endpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SearchProvider for BraveSearch {
+    fn name(&self) -> &str {
+        "Brave Search"
+    }
+
+    fn search<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        query: &'a str,
+        count: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SearchResult>, SearchError>> + Send + 'a>> {
+        Box::pin(async move {
+            let response: Value = client
+                .get(self.endpoint.clone())
+                .header("X-Subscription-Token", self.api_key.expose())
+                .header("Accept", "application/json")
+                .query(&[("q", query), ("count", &count.to_string())])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .map_err(SearchError::invalid_response("Brave Search"))?;
+            Ok(json_results(
+                &response["web"]["results"],
+                "description",
+                count,
+            ))
+        })
+    }
+}
+
+/// DuckDuckGo, scraped from its HTML-only results page
+///
+/// Needs no key, but the page layout may change without notice.
+#[derive(Clone, Debug)]
+pub struct DuckDuckGo {
+    endpoint: Url,
+}
+
+impl Default for DuckDuckGo {
+    fn default() -> Self {
+        Self {
+            endpoint: Url::parse("https://html.duckduckgo.com/html/")
+                .expect("valid DuckDuckGo endpoint"),
+        }
+    }
+}
+
+impl DuckDuckGo {
+    /// Sends requests to `endpoint` instead of the public site
+    pub fn with_endpoint(mut self, endpoint: Url) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+}
+
+impl SearchProvider for DuckDuckGo {
+    fn name(&self) -> &str {
+        "DuckDuckGo"
+    }
+
+    fn search<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        query: &'a str,
+        count: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SearchResult>, SearchError>> + Send + 'a>> {
+        Box::pin(async move {
+            let html = client
+                .get(self.endpoint.clone())
+                .query(&[("q", query)])
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            parse_duckduckgo(&html, count)
+        })
+    }
+}
+
+/// Reads `title`, `url` and the snippet field from an array of JSON results
+fn json_results(results: &Value, snippet: &str, count: usize) -> Vec<SearchResult> {
+    let field =
+        |result: &Value, name: &str| result[name].as_str().unwrap_or_default().trim().to_string();
+    results
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|result| SearchResult {
+            title: field(result, "title"),
+            url: field(result, "url"),
+            snippet: field(result, snippet),
+        })
+        .filter(|result| !result.url.is_empty())
+        .take(count)
+        .collect()
+}
+
+fn parse_duckduckgo(html: &str, count: usize) -> Result<Vec<SearchResult>, SearchError> {
+    let selector =
+        |selector| Selector::parse(selector).map_err(|e| SearchError::failed(e.to_string()));
+    let result_selector = selector(".result")?;
+    let link_selector = selector("a.result__a")?;
+    let snippet_selector = selector(".result__snippet")?;
+
+    let document = Html::parse_document(html);
+    let text = |element: scraper::ElementRef| {
+        element
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    Ok(document
+        .select(&result_selector)
+        .filter_map(|result| {
+            let link = result.select(&link_selector).next()?;
+            let url = duckduckgo_target(link.value().attr("href")?)?;
+            let snippet = result
+                .select(&snippet_selector)
+                .next()
+                .map(text)
+                .unwrap_or_default();
+            Some(SearchResult {
+                title: text(link),
+                url,
+                snippet,
+            })
+        })
+        .take(count)
+        .collect())
+}
+
+/// Unwraps the `//duckduckgo.com/l/?uddg=...` redirect links results point to
+fn duckduckgo_target(href: &str) -> Option<String> {
+    let url = Url::parse("https://duckduckgo.com")
+        .and_then(|base| base.join(href))
+        .ok()?;
+    if url.path() == "/l/" {
+        return url
+            .query_pairs()
+            .find(|(key, _)| key == "uddg")
+            .map(|(_, target)| target.into_owned());
+    }
+    // Ads are served from duckduckgo.com itself
+    (url.domain() != Some("duckduckgo.com")).then(|| url.to_string())
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SearchProperties {
+    #[schemars(description = "What to search the web for", required = true)]
+    query: String,
+    #[schemars(description = "Maximum number of results to return")]
+    count: Option<usize>,
+}
+
+/// Searches the web through a configurable search engine
+#[derive(Clone, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "search",
+    description = "Searches the web and returns the titles, URLs and snippets of the top results",
+    properties = SearchProperties
+)]
+pub struct Search {
+    #[serde(skip)]
+    provider: Arc<dyn SearchProvider>,
+    #[serde(skip)]
+    client: reqwest::Client,
+    max_results: usize,
+}
+
+impl Default for Search {
+    /// Searches DuckDuckGo, which needs no API key
+    fn default() -> Self {
+        Self::new(DuckDuckGo::default())
+    }
+}
+
+impl Search {
+    pub fn new(provider: impl SearchProvider + 'static) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent("Bioma/1.0 (+https://github.com/BiomaAI/bioma)")
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_default();
+        Self {
+            provider: Arc::new(provider),
+            client,
+            max_results: 20,
+        }
+    }
+
+    /// Caps the number of results a caller can ask for
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results;
+        self
+    }
+
+    async fn run(
+        &self,
+        properties: SearchProperties,
+        context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        let query = properties.query.trim();
+        if query.is_empty() {
+            return Ok(CallToolResult::error("Query must not be empty"));
+        }
+        if let Err(violation) = context.policy().check_network(self.provider.name()) {
+            return Ok(violation.into_result(Self::NAME));
+        }
+        let count = properties
+            .count
+            .unwrap_or(DEFAULT_RESULT_COUNT)
+            .clamp(1, self.max_results.max(1));
+
+        let results = match self.provider.search(&self.client, query, count).await {
+            Ok(results) => results,
+            Err(e) => {
+                return Ok(CallToolResult::error(format!(
+                    "{} search failed: {}",
+                    self.provider.name(),
+                    e
+                )))
+            }
+        };
+
+        Ok(CallToolResult::text(format_results(
+            self.provider.name(),
+            query,
+            &results,
+        )))
+    }
+}
+
+fn format_results(provider: &str, query: &str, results: &[SearchResult]) -> String {
+    if results.is_empty() {
+        return format!("No results found for \"{}\"", query);
+    }
+    let mut text = format!("## {} results for \"{}\"\n", provider, query);
+    for (rank, result) in results.iter().enumerate() {
+        let title = if result.title.is_empty() {
+            &result.url
+        } else {
+            &result.title
+        };
+        text.push_str(&format!("\n{}. [{}]({})", rank + 1, title, result.url));
+        if !result.snippet.is_empty() {
+            text.push_str(&format!("\n   {}", result.snippet));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn props(query: &str) -> SearchProperties {
+        SearchProperties {
+            query: query.to_string(),
+            count: Some(2),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_providers() {
+        let mut server = mockito::Server::new_async().await;
+        let _searxng = server
+            .mock("GET", "/search")
+            .match_query(mockito::Matcher::UrlEncoded("format".into(), "json".into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"results": [
+                    {"title": "Rust", "url": "https://www.rust-lang.org/", "content": "A language"},
+                    {"title": "Crates", "url": "https://crates.io/", "content": "Packages"},
+                    {"title": "Docs", "url": "https://docs.rs/", "content": "Documentation"}
+                ]}"#,
+            )
+            .create_async()
+            .await;
+        let _brave = server
+            .mock("GET", "/brave")
+            .match_header("x-subscription-token", "secret")
+            .match_query(mockito::Matcher::UrlEncoded("q".into(), "rust".into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"web": {"results": [
+                    {"title": "Rust", "url": "https://www.rust-lang.org/", "description": "A language"}
+                ]}}"#,
+            )
+            .create_async()
+            .await;
+
+        let base = Url::parse(&server.url()).unwrap();
+        let tool = Search::new(SearxNg::new(base.clone()));
+        let result = tool
+            .call(props("rust"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            result.content[0].as_text(),
+            Some(
+                "## SearxNG results for \"rust\"\n\n\
+                 1. [Rust](https://www.rust-lang.org/)\n   A language\n\
+                 2. [Crates](https://crates.io/)\n   Packages"
+            )
+        );
+
+        let brave = BraveSearch::new("secret").with_endpoint(base.join("brave").unwrap());
+        assert!(!format!("{:?}", brave).contains("secret"));
+        let result = Search::new(brave)
+            .call(props("rust"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(false));
+        assert!(result.content[0]
+            .as_text()
+            .unwrap()
+            .contains("1. [Rust](https://www.rust-lang.org/)\n   A language"));
+
+        let wrong_key = BraveSearch::new("wrong").with_endpoint(base.join("brave").unwrap());
+        let result = Search::new(wrong_key)
+            .call(props("rust"), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[test]
+    fn test_parse_duckduckgo() {
+        let html = r#"<html><body>
+            <div class="result results_links">
+                <h2><a class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust-lang.org%2F&amp;rut=abc">The <b>Rust</b> Language</a></h2>
+                <a class="result__snippet">A language empowering
+                    everyone.</a>
+            </div>
+            <div class="result result--ad">
+                <a class="result__a" href="https://duckduckgo.com/y.js?ad_provider=x">Ad</a>
+            </div>
+            <div class="result">
+                <a class="result__a" href="https://crates.io/">Crates</a>
+            </div>
+        </body></html>"#;
+
+        let results = parse_duckduckgo(html, 10).unwrap();
+        assert_eq!(
+            results,
+            vec![
+                SearchResult {
+                    title: "The Rust Language".to_string(),
+                    url: "https://www.rust-lang.org/".to_string(),
+                    snippet: "A language empowering everyone.".to_string(),
+                },
+                SearchResult {
+                    title: "Crates".to_string(),
+                    url: "https://crates.io/".to_string(),
+                    snippet: String::new(),
+                },
+            ]
+        );
+        assert_eq!(parse_duckduckgo(html, 1).unwrap().len(), 1);
+        assert_eq!(
+            format_results("DuckDuckGo", "nothing", &[]),
+            "No results found for \"nothing\""
+        );
+    }
+}
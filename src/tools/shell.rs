@@ -0,0 +1,264 @@
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+/// Time a command may run before it is killed by default
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Environment variables passed through to commands unless overridden
+pub(crate) const INHERITED_ENV: &[&str] = &["PATH", "HOME", "LANG"];
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ShellProperties {
+    #[schemars(description = "The program to run, which must be one of the allowed commands")]
+    command: String,
+
+    #[schemars(description = "Arguments passed to the program, not interpreted by a shell")]
+    args: Option<Vec<String>>,
+
+    #[schemars(description = "Directory to run in, relative to the server's working directory")]
+    cwd: Option<String>,
+}
+
+/// Runs allow-listed commands and returns their output
+///
+/// Commands are executed directly rather than through a shell, with a
+/// cleared environment and a timeout, so the model can only run what the
+/// server was configured to allow.
+#[derive(Clone, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "shell",
+    description = "Runs an allowed command and returns its exit code, stdout and stderr",
+    properties = ShellProperties
+)]
+pub struct Shell {
+    allowed_commands: Vec<String>,
+    working_directory: Option<PathBuf>,
+    env: HashMap<String, String>,
+    timeout: Duration,
+}
+
+impl Shell {
+    /// A shell tool that can only run the programs in `allowed_commands`
+    pub fn new(allowed_commands: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            allowed_commands: allowed_commands.into_iter().map(Into::into).collect(),
+            working_directory: None,
+            env: HashMap::new(),
+            timeout: DEFAULT_COMMAND_TIMEOUT,
+        }
+    }
+
+    /// Runs commands in `directory`, and keeps their `cwd` inside it
+    pub fn with_working_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.working_directory = Some(directory.into());
+        self
+    }
+
+    /// Sets an environment variable for every command
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Kills commands that run longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn run(
+        &self,
+        properties: ShellProperties,
+        _context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        if !self.is_allowed(&properties.command) {
+            return Ok(CallToolResult::error(format!(
+                "Command not allowed: {}",
+                properties.command
+            )));
+        }
+
+        let cwd = match self.cwd(properties.cwd.as_deref()) {
+            Ok(cwd) => cwd,
+            Err(e) => return Ok(CallToolResult::error(e)),
+        };
+
+        let mut command = tokio::process::Command::new(&properties.command);
+        command
+            .args(properties.args.unwrap_or_default())
+            .env_clear()
+            .envs(
+                INHERITED_ENV
+                    .iter()
+                    .filter_map(|key| Some((*key, std::env::var(key).ok()?))),
+            )
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        if let Some(cwd) = cwd {
+            command.current_dir(cwd);
+        }
+
+        let output = match tokio::time::timeout(self.timeout, command.output()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                return Ok(CallToolResult::error(format!(
+                    "Failed to run {}: {}",
+                    properties.command, e
+                )))
+            }
+            Err(_) => {
+                return Ok(CallToolResult::error(format!(
+                    "Command timed out after {} seconds",
+                    self.timeout.as_secs_f64()
+                )))
+            }
+        };
+
+        let exit_code = output
+            .status
+            .code()
+            .map_or_else(|| "killed by signal".to_string(), |code| code.to_string());
+        let mut text = format!("Exit code: {}", exit_code);
+        for (name, stream) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+            if !stream.is_empty() {
+                text.push_str(&format!(
+                    "\n\n{}:\n{}",
+                    name,
+                    String::from_utf8_lossy(stream).trim_end()
+                ));
+            }
+        }
+
+        Ok(if output.status.success() {
+            CallToolResult::text(text)
+        } else {
+            CallToolResult::error(text)
+        })
+    }
+
+    /// Whether `command` is exactly one of the allowed commands
+    fn is_allowed(&self, command: &str) -> bool {
+        self.allowed_commands
+            .iter()
+            .any(|allowed| allowed == command)
+    }
+
+    /// Resolves the requested directory, which must stay inside the working directory
+    fn cwd(&self, requested: Option<&str>) -> Result<Option<PathBuf>, String> {
+        let Some(working_directory) = &self.working_directory else {
+            return match requested {
+                Some(_) => Err("No working directory is configured".to_string()),
+                None => Ok(None),
+            };
+        };
+
+        let root = working_directory
+            .canonicalize()
+            .map_err(|e| format!("Invalid working directory: {}", e))?;
+        let Some(requested) = requested else {
+            return Ok(Some(root));
+        };
+
+        let cwd = root
+            .join(Path::new(requested))
+            .canonicalize()
+            .map_err(|e| format!("Invalid directory {}: {}", requested, e))?;
+        if cwd.starts_with(&root) {
+            Ok(Some(cwd))
+        } else {
+            Err(format!(
+                "Access denied: {} is outside the working directory",
+                requested
+            ))
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::tools::ToolDef;
+
+    fn props(command: &str, args: &[&str]) -> ShellProperties {
+        ShellProperties {
+            command: command.to_string(),
+            args: Some(args.iter().map(|arg| arg.to_string()).collect()),
+            cwd: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shell_output() {
+        let tool = Shell::new(["echo", "sh", "pwd"]).with_env("GREETING", "hi");
+
+        let result = tool
+            .call(props("echo", &["hello", "world"]), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(
+            result.content[0].as_text(),
+            Some("Exit code: 0\n\nstdout:\nhello world")
+        );
+
+        // Arguments aren't interpreted by a shell unless it is allowed explicitly
+        let script = "echo $GREETING; echo oops >&2; exit 3";
+        let result = tool
+            .call(props("sh", &["-c", script]), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.content[0].as_text(),
+            Some("Exit code: 3\n\nstdout:\nhi\n\nstderr:\noops")
+        );
+
+        let dir = std::env::temp_dir().join(format!("bioma-shell-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let tool = tool.with_working_directory(&dir);
+        let mut pwd = props("pwd", &[]);
+        pwd.cwd = Some("sub".to_string());
+        let result = tool.call(pwd, ToolContext::default()).await.unwrap();
+        let expected = dir.canonicalize().unwrap().join("sub");
+        assert!(result.content[0]
+            .as_text()
+            .unwrap()
+            .contains(expected.to_str().unwrap()));
+
+        let mut escape = props("pwd", &[]);
+        escape.cwd = Some("..".to_string());
+        let result = tool.call(escape, ToolContext::default()).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shell_restrictions() {
+        let tool = Shell::new(["sleep"]).with_timeout(Duration::from_millis(100));
+
+        let result = tool
+            .call(
+                props("rm", &["-rf", "/tmp/nothing"]),
+                ToolContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(result.content[0].as_text(), Some("Command not allowed: rm"));
+
+        let result = tool
+            .call(props("sleep", &["5"]), ToolContext::default())
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert!(result.content[0].as_text().unwrap().contains("timed out"));
+    }
+}
@@ -0,0 +1,289 @@
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolError};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params_from_iter, Connection, OpenFlags};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Maximum number of rows returned by a query
+const MAX_ROWS: usize = 1000;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SqliteAction {
+    Query,
+    Execute,
+    ListTables,
+    DescribeTable,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SqliteProperties {
+    #[schemars(
+        description = "The action to perform: 'query' to run a read-only SELECT, 'execute' to run a statement that modifies the database, 'list_tables' to see all tables, or 'describe_table' to see a table's columns"
+    )]
+    action: SqliteAction,
+
+    #[schemars(description = "The SQL statement to run (only required for query/execute)")]
+    sql: Option<String>,
+
+    #[schemars(description = "Values bound to the statement's ? placeholders, in order")]
+    params: Option<Vec<Value>>,
+
+    #[schemars(description = "The table to describe (only required for describe_table)")]
+    table: Option<String>,
+}
+
+/// Queries and modifies a SQLite database file
+#[derive(Clone, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "sqlite",
+    description = "Run SQL queries against a SQLite database and inspect its tables",
+    properties = SqliteProperties
+)]
+pub struct Sqlite {
+    path: PathBuf,
+    read_only: bool,
+}
+
+impl Sqlite {
+    /// A tool for the database at `path`, which is created if it doesn't exist
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            read_only: false,
+        }
+    }
+
+    /// Rejects the `execute` action and opens the database read-only
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    async fn run(
+        &self,
+        properties: SqliteProperties,
+        _context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        let tool = self.clone();
+        // rusqlite is blocking, so each call gets its own connection off the runtime
+        let result = tokio::task::spawn_blocking(move || tool.run_blocking(properties))
+            .await
+            .map_err(|e| ToolError::Execution(e.to_string()))?;
+
+        Ok(match result {
+            Ok(text) => CallToolResult::text(text),
+            Err(e) => CallToolResult::error(e),
+        })
+    }
+
+    fn run_blocking(&self, properties: SqliteProperties) -> Result<String, String> {
+        let connection = self
+            .open()
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+        let params = properties.params.unwrap_or_default();
+
+        match properties.action {
+            SqliteAction::Query => {
+                let sql = properties
+                    .sql
+                    .ok_or("SQL is required for query action")?;
+                query(&connection, &sql, &params)
+            }
+            SqliteAction::Execute => {
+                if self.read_only {
+                    return Err("The database is read-only".to_string());
+                }
+                let sql = properties
+                    .sql
+                    .ok_or("SQL is required for execute action")?;
+                let changed = connection
+                    .execute(&sql, params_from_iter(params.iter().map(to_sql)))
+                    .map_err(|e| format!("Failed to execute statement: {}", e))?;
+                Ok(format!("Statement executed, {} rows affected", changed))
+            }
+            SqliteAction::ListTables => query(
+                &connection,
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+                &[],
+            ),
+            SqliteAction::DescribeTable => {
+                let table = properties
+                    .table
+                    .ok_or("Table is required for describe_table action")?;
+                let exists: bool = connection
+                    .query_row(
+                        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                        [&table],
+                        |row| row.get(0),
+                    )
+                    .map_err(|e| format!("Failed to describe table: {}", e))?;
+                if !exists {
+                    return Err(format!("No table named {}", table));
+                }
+                query(
+                    &connection,
+                    "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1)",
+                    &[Value::String(table)],
+                )
+            }
+        }
+    }
+
+    fn open(&self) -> rusqlite::Result<Connection> {
+        let flags = if self.read_only {
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+        };
+        Connection::open_with_flags(&self.path, flags | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+    }
+}
+
+/// Runs a read-only statement and formats its rows as a markdown table
+fn query(connection: &Connection, sql: &str, params: &[Value]) -> Result<String, String> {
+    let mut statement = connection
+        .prepare(sql)
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    if !statement.readonly() {
+        return Err("Only read-only statements can be run with query, use execute".to_string());
+    }
+
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+    let mut rows = statement
+        .query(params_from_iter(params.iter().map(to_sql)))
+        .map_err(|e| format!("Failed to run query: {}", e))?;
+
+    let mut table = vec![
+        format!("| {} |", columns.join(" | ")),
+        format!("|{}", " --- |".repeat(columns.len())),
+    ];
+    let mut count = 0;
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| format!("Failed to read row: {}", e))?
+    {
+        if count == MAX_ROWS {
+            table.push(format!("\n(truncated to {} rows)", MAX_ROWS));
+            break;
+        }
+        let cells: Vec<String> = (0..columns.len())
+            .map(|i| row.get_ref(i).map(format_cell).unwrap_or_default())
+            .collect();
+        table.push(format!("| {} |", cells.join(" | ")));
+        count += 1;
+    }
+
+    if count == 0 {
+        Ok("No rows returned".to_string())
+    } else {
+        Ok(table.join("\n"))
+    }
+}
+
+fn format_cell(value: ValueRef) -> String {
+    let text = match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+        ValueRef::Blob(blob) => format!("<{} byte blob>", blob.len()),
+    };
+    // Keep each row on one line of the table
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn to_sql(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        value => SqlValue::Text(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolDef;
+    use serde_json::json;
+
+    fn props(action: SqliteAction, sql: Option<&str>) -> SqliteProperties {
+        SqliteProperties {
+            action,
+            sql: sql.map(String::from),
+            params: None,
+            table: None,
+        }
+    }
+
+    async fn call(tool: &Sqlite, properties: SqliteProperties) -> CallToolResult {
+        tool.call(properties, ToolContext::default()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_operations() {
+        let path = std::env::temp_dir().join(format!("bioma-{}.db", uuid::Uuid::new_v4()));
+        let tool = Sqlite::new(&path);
+
+        let create = "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT NOT NULL)";
+        let result = call(&tool, props(SqliteAction::Execute, Some(create))).await;
+        assert_eq!(result.is_error, Some(false));
+
+        let mut insert = props(
+            SqliteAction::Execute,
+            Some("INSERT INTO notes (body) VALUES (?), (?)"),
+        );
+        insert.params = Some(vec![json!("first"), json!("a | b")]);
+        let result = call(&tool, insert).await;
+        assert_eq!(
+            result.content[0].as_text(),
+            Some("Statement executed, 2 rows affected")
+        );
+
+        let select = props(SqliteAction::Query, Some("SELECT * FROM notes ORDER BY id"));
+        let result = call(&tool, select).await;
+        assert_eq!(
+            result.content[0].as_text(),
+            Some("| id | body |\n| --- | --- |\n| 1 | first |\n| 2 | a \\| b |")
+        );
+
+        let result = call(&tool, props(SqliteAction::ListTables, None)).await;
+        assert_eq!(
+            result.content[0].as_text(),
+            Some("| name |\n| --- |\n| notes |")
+        );
+
+        let mut describe = props(SqliteAction::DescribeTable, None);
+        describe.table = Some("notes".to_string());
+        let result = call(&tool, describe).await;
+        assert!(result.content[0]
+            .as_text()
+            .unwrap()
+            .contains("| body | TEXT | 1 | NULL | 0 |"));
+
+        // Writes are refused through query and on read-only databases
+        let delete = props(SqliteAction::Query, Some("DELETE FROM notes"));
+        assert_eq!(call(&tool, delete).await.is_error, Some(true));
+        let delete = props(SqliteAction::Execute, Some("DELETE FROM notes"));
+        let result = call(&tool.clone().read_only(), delete).await;
+        assert_eq!(result.is_error, Some(true));
+
+        let mut describe = props(SqliteAction::DescribeTable, None);
+        describe.table = Some("missing".to_string());
+        assert_eq!(call(&tool, describe).await.is_error, Some(true));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
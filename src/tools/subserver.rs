@@ -0,0 +1,219 @@
+use crate::aggregator::namespaced;
+use crate::error::ClientError;
+use crate::schema::{self, CallToolResult};
+use crate::tools::{ToolCallHandler, ToolContext, ToolError};
+use crate::Client;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// An MCP server run as a child process, whose tools are served as this server's
+///
+/// The server is started and initialized by `start`, which lists its tools.
+/// Arguments are passed through untouched. If the process exits, the call
+/// that was running fails and the next call starts it again.
+///
+/// ```ignore
+/// let mut builder = Server::builder();
+/// for tool in Subserver::new("github-mcp-server", ["stdio"]).with_prefix("github").start().await? {
+///     builder = builder.tool(tool);
+/// }
+/// ```
+pub struct Subserver {
+    program: OsString,
+    args: Vec<OsString>,
+    prefix: Option<String>,
+}
+
+/// A tool of a `Subserver`, which starts its process again if it exited
+pub struct SubserverTool {
+    /// What the child server calls the tool
+    name: String,
+    def: schema::Tool,
+    process: Arc<Process>,
+}
+
+/// The child process shared by a subserver's tools
+struct Process {
+    program: OsString,
+    args: Vec<OsString>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    running: Option<(Client, Child)>,
+    /// Counts the processes started, telling each apart from the one before
+    generation: u64,
+}
+
+impl Subserver {
+    pub fn new(
+        program: impl Into<OsString>,
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            prefix: None,
+        }
+    }
+
+    /// Serves the child's tools as `prefix.name` rather than under their own names
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Starts the child server, returning a tool for each tool it lists
+    pub async fn start(self) -> Result<Vec<SubserverTool>, ClientError> {
+        let process = Arc::new(Process {
+            program: self.program,
+            args: self.args,
+            state: Mutex::default(),
+        });
+        let (client, _) = process.client().await?;
+        let tools = client.list_tools().await?;
+        Ok(tools
+            .into_iter()
+            .map(|def| SubserverTool {
+                name: def.name.clone(),
+                def: schema::Tool {
+                    name: match &self.prefix {
+                        Some(prefix) => namespaced(prefix, &def.name),
+                        None => def.name.clone(),
+                    },
+                    ..def
+                },
+                process: process.clone(),
+            })
+            .collect())
+    }
+}
+
+impl Process {
+    /// A client of the running child, which is started first if it isn't running
+    async fn client(&self) -> Result<(Client, u64), ClientError> {
+        let mut state = self.state.lock().await;
+        if let Some((client, child)) = state.running.as_mut() {
+            match child.try_wait() {
+                Ok(None) => return Ok((client.clone(), state.generation)),
+                Ok(Some(status)) => {
+                    warn!("{:?} exited with {}, restarting it", self.program, status)
+                }
+                Err(e) => warn!(
+                    "Failed to check on {:?}, restarting it: {}",
+                    self.program, e
+                ),
+            }
+        }
+        // Dropping the previous process kills it if it's still around
+        state.running = None;
+
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        let (client, child) = Client::spawn_process(command)?;
+        client.initialize().await?;
+        info!("Started {:?}", self.program);
+        state.running = Some((client.clone(), child));
+        state.generation += 1;
+        Ok((client, state.generation))
+    }
+
+    /// Kills the child started as `generation` once it stopped answering,
+    /// so the next call starts another
+    async fn stopped(&self, generation: u64) {
+        let mut state = self.state.lock().await;
+        if state.generation == generation {
+            state.running = None;
+        }
+    }
+}
+
+impl ToolCallHandler for SubserverTool {
+    fn call_boxed<'a>(
+        &'a self,
+        args: Option<BTreeMap<String, Value>>,
+        _context: ToolContext,
+    ) -> Pin<Box<dyn Future<Output = Result<CallToolResult, ToolError>> + Send + 'a>> {
+        Box::pin(async move {
+            let (client, generation) = self.process.client().await.map_err(|e| {
+                ToolError::Execution(format!("Failed to start {}: {}", self.def.name, e))
+            })?;
+            let arguments = Value::Object(args.unwrap_or_default().into_iter().collect());
+            match client.call_tool(&self.name, arguments).await {
+                Ok(result) => Ok(result),
+                Err(ClientError::Closed) => {
+                    self.process.stopped(generation).await;
+                    Err(ToolError::Execution(format!(
+                        "The server of {} exited during the call",
+                        self.def.name
+                    )))
+                }
+                Err(e) => Err(ToolError::Execution(format!(
+                    "{} failed: {}",
+                    self.def.name, e
+                ))),
+            }
+        })
+    }
+
+    fn def(&self) -> schema::Tool {
+        self.def.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{assert_tool_error, TestServer};
+    use crate::Server;
+    use serde_json::json;
+
+    /// A server with a `pid` tool answering with its process ID, which exits
+    /// when called with `crash`
+    const FAKE_SERVER: &str = r#"while read line; do
+        id=$(echo "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+        case "$line" in
+            *'"crash"'*) exit 1 ;;
+            *'"initialize"'*) echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"protocolVersion\":\"2025-03-26\",\"capabilities\":{\"tools\":{}},\"serverInfo\":{\"name\":\"fake\",\"version\":\"1\"}}}" ;;
+            *'"tools/list"'*) echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"tools\":[{\"name\":\"pid\",\"inputSchema\":{\"type\":\"object\"}}]}}" ;;
+            *'"tools/call"'*) echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"content\":[{\"type\":\"text\",\"text\":\"$$\"}]}}" ;;
+        esac
+    done"#;
+
+    fn text(result: &CallToolResult) -> String {
+        result.content[0].as_text().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_subserver_restarts_after_crash() {
+        let tools = Subserver::new("sh", ["-c", FAKE_SERVER])
+            .with_prefix("fake")
+            .start()
+            .await
+            .unwrap();
+        let mut builder = Server::builder();
+        for tool in tools {
+            builder = builder.tool(tool);
+        }
+        let server = TestServer::start(builder.build()).await;
+        assert_eq!(server.list_tools().await.unwrap()[0].name, "fake.pid");
+
+        let first = text(&server.call("fake.pid", json!({})).await);
+        assert_eq!(text(&server.call("fake.pid", json!({})).await), first);
+
+        let result = server.call("fake.pid", json!({ "mode": "crash" })).await;
+        assert!(assert_tool_error(&result).contains("exited during the call"));
+        let restarted = text(&server.call("fake.pid", json!({})).await);
+        assert_ne!(restarted, first);
+
+        server.shutdown().await;
+    }
+}
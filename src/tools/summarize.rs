@@ -0,0 +1,371 @@
+use crate::schema::{
+    CallToolResult, CreateMessageRequestParams, ModelHint, ModelPreferences, Role, SamplingMessage,
+};
+use crate::tools::{McpTool, ToolContext, ToolError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Characters of text summarized by one sampling request, unless set with `with_chunk_size`
+const DEFAULT_CHUNK_SIZE: usize = 8000;
+
+/// Tokens the client's model may use per summary when the caller doesn't say
+const DEFAULT_MAX_TOKENS: i64 = 500;
+
+const SYSTEM_PROMPT: &str =
+    "You summarize text faithfully and concisely, keeping names, numbers and conclusions.";
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SummarizeProperties {
+    #[schemars(description = "Text to summarize, unless uri is given")]
+    text: Option<String>,
+
+    #[schemars(description = "URI of a resource of this server to summarize instead of text")]
+    uri: Option<String>,
+
+    #[schemars(description = "What the summary should concentrate on")]
+    focus: Option<String>,
+
+    #[schemars(description = "Most tokens the model may use for each summary")]
+    max_tokens: Option<i64>,
+
+    #[schemars(description = "Name, or part of a name, of the model to prefer")]
+    model: Option<String>,
+
+    #[schemars(description = "How much to prefer a cheap model, from 0 to 1")]
+    cost_priority: Option<f64>,
+
+    #[schemars(description = "How much to prefer a fast model, from 0 to 1")]
+    speed_priority: Option<f64>,
+
+    #[schemars(description = "How much to prefer a capable model, from 0 to 1")]
+    intelligence_priority: Option<f64>,
+}
+
+/// Summarizes text with the calling client's own model
+///
+/// Long text is split into chunks that are summarized one by one through
+/// `sampling/createMessage`, and the partial summaries then combined in a
+/// last request. Fails if the client doesn't support sampling.
+#[derive(Clone, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "summarize",
+    description = "Summarizes text or a resource using the client's language model",
+    properties = SummarizeProperties
+)]
+pub struct Summarize {
+    chunk_size: usize,
+}
+
+impl Default for Summarize {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
+
+impl Summarize {
+    /// Summarizes at most `chunk_size` characters per sampling request
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    async fn run(
+        &self,
+        properties: SummarizeProperties,
+        context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        let text = match (&properties.text, &properties.uri) {
+            (Some(text), None) => text.clone(),
+            (None, Some(uri)) => match read_text(&context, uri).await {
+                Ok(text) => text,
+                Err(e) => return Ok(CallToolResult::error(e.to_string())),
+            },
+            _ => {
+                return Ok(CallToolResult::error(
+                    "Give either text or uri to summarize",
+                ))
+            }
+        };
+        if text.trim().is_empty() {
+            return Ok(CallToolResult::error("There's no text to summarize"));
+        }
+
+        let focus = properties
+            .focus
+            .as_ref()
+            .map(|focus| format!(", concentrating on {}", focus))
+            .unwrap_or_default();
+        let chunks = chunks(&text, self.chunk_size);
+        let (summary, model) = if let [chunk] = chunks.as_slice() {
+            let prompt = format!("Summarize the following text{}:\n\n{}", focus, chunk);
+            sample(&context, &properties, prompt).await?
+        } else {
+            let mut summaries = Vec::with_capacity(chunks.len());
+            for (i, chunk) in chunks.iter().enumerate() {
+                let prompt = format!(
+                    "Summarize part {} of {} of a longer text{}:\n\n{}",
+                    i + 1,
+                    chunks.len(),
+                    focus,
+                    chunk
+                );
+                summaries.push(sample(&context, &properties, prompt).await?.0);
+            }
+            let prompt = format!(
+                "Combine these summaries of consecutive parts of one text into a single summary{}:\n\n{}",
+                focus,
+                summaries.join("\n\n")
+            );
+            sample(&context, &properties, prompt).await?
+        };
+
+        let mut result = CallToolResult::text(summary);
+        let mut meta = BTreeMap::new();
+        meta.insert("model".to_string(), model.into());
+        meta.insert("chunks".to_string(), chunks.len().into());
+        result.meta = Some(meta);
+        Ok(result)
+    }
+}
+
+/// The text of the resource at `uri`, refusing binary contents
+async fn read_text(context: &ToolContext, uri: &str) -> Result<String, ToolError> {
+    let result = context.read_resource(uri).await?;
+    let texts = result
+        .contents
+        .iter()
+        .map(|contents| {
+            contents["text"].as_str().ok_or_else(|| {
+                ToolError::Execution(format!("{} has contents that aren't text", uri))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(texts.join("\n\n"))
+}
+
+/// Asks the client's model to answer `prompt`, returning the text and the model used
+async fn sample(
+    context: &ToolContext,
+    properties: &SummarizeProperties,
+    prompt: String,
+) -> Result<(String, String), ToolError> {
+    let params = CreateMessageRequestParams {
+        include_context: None,
+        max_tokens: properties.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        messages: vec![SamplingMessage::text(Role::User, prompt)],
+        metadata: None,
+        model_preferences: model_preferences(properties),
+        stop_sequences: None,
+        system_prompt: Some(SYSTEM_PROMPT.to_string()),
+        temperature: None,
+    };
+    let result = context.create_message(params).await?;
+    let text = result.content.as_text().ok_or_else(|| {
+        ToolError::Execution("The client's model didn't answer with text".to_string())
+    })?;
+    Ok((text.trim().to_string(), result.model))
+}
+
+fn model_preferences(properties: &SummarizeProperties) -> Option<ModelPreferences> {
+    if properties.model.is_none()
+        && properties.cost_priority.is_none()
+        && properties.speed_priority.is_none()
+        && properties.intelligence_priority.is_none()
+    {
+        return None;
+    }
+    Some(ModelPreferences {
+        cost_priority: properties.cost_priority,
+        hints: properties
+            .model
+            .clone()
+            .map(|name| vec![ModelHint { name: Some(name) }]),
+        intelligence_priority: properties.intelligence_priority,
+        speed_priority: properties.speed_priority,
+    })
+}
+
+/// Splits `text` into chunks of at most `size` characters
+///
+/// Chunks end between paragraphs where they can, otherwise between words,
+/// and only split a word longer than a whole chunk.
+fn chunks(text: &str, size: usize) -> Vec<String> {
+    let pieces = text.split_inclusive("\n\n").flat_map(|paragraph| {
+        if paragraph.chars().count() > size {
+            paragraph.split_inclusive(char::is_whitespace).collect()
+        } else {
+            vec![paragraph]
+        }
+    });
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut length = 0;
+    for piece in pieces {
+        let piece_length = piece.chars().count();
+        if length > 0 && length + piece_length > size {
+            chunks.push(std::mem::take(&mut current));
+            length = 0;
+        }
+        current.push_str(piece);
+        length += piece_length;
+        while length > size {
+            let split = current
+                .char_indices()
+                .nth(size)
+                .map_or(current.len(), |(i, _)| i);
+            let rest = current.split_off(split);
+            chunks.push(std::mem::replace(&mut current, rest));
+            length -= size;
+        }
+    }
+    chunks.push(current);
+    chunks
+        .into_iter()
+        .map(|chunk| chunk.trim().to_string())
+        .filter(|chunk| !chunk.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{MemoryContents, MemoryResources, ResourceMounts};
+    use crate::roots::RootsState;
+    use crate::router::RequestRouter;
+    use crate::session::Sessions;
+    use crate::tools::ToolDef;
+    use crate::transport::ConnectionId;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    fn props(text: &str) -> SummarizeProperties {
+        SummarizeProperties {
+            text: Some(text.to_string()),
+            uri: None,
+            focus: None,
+            max_tokens: Some(50),
+            model: Some("claude".to_string()),
+            cost_priority: None,
+            speed_priority: None,
+            intelligence_priority: None,
+        }
+    }
+
+    /// A context whose client answers each sampling request with the prompt's last line
+    fn sampling_context(
+        published: &MemoryResources,
+    ) -> (ToolContext, mpsc::UnboundedReceiver<Value>) {
+        let (outgoing, mut outgoing_rx) = mpsc::channel(8);
+        let router = RequestRouter::new(outgoing);
+        let sessions = Sessions::default();
+        let session_id = ConnectionId::from("client");
+        sessions.update(&session_id, |session| {
+            session.client_capabilities.sampling = Some(BTreeMap::new());
+        });
+        let mut resources = ResourceMounts::new();
+        resources.mount("mem://", Arc::new(published.clone()));
+        let context = ToolContext::new(session_id, sessions, router.clone(), RootsState::default())
+            .with_resources(resources);
+
+        let (requests, requests_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(sent) = outgoing_rx.recv().await {
+                let request: Value = serde_json::from_str(&sent.message).unwrap();
+                let prompt = request["params"]["messages"][0]["content"]["text"]
+                    .as_str()
+                    .unwrap();
+                let reply = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": {
+                        "role": "assistant",
+                        "content": { "type": "text", "text": prompt.lines().last() },
+                        "model": "test-model",
+                    },
+                });
+                requests.send(request).unwrap();
+                router.handle_response(&sent.connection, &reply).unwrap();
+            }
+        });
+        (context, requests_rx)
+    }
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(chunks("one two\n\nthree", 100), ["one two\n\nthree"]);
+        assert_eq!(chunks("one two\n\nthree", 9), ["one two", "three"]);
+        assert_eq!(chunks("one two three", 8), ["one two", "three"]);
+        assert_eq!(chunks("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert!(chunks("  \n\n ", 4).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_summarize() {
+        let published = MemoryResources::new();
+        let (context, mut requests) = sampling_context(&published);
+        let tool = Summarize::default();
+
+        let result = tool
+            .call(props("A short text."), context.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].as_text(), Some("A short text."));
+        let meta = result.meta.unwrap();
+        assert_eq!(meta["model"], "test-model");
+        assert_eq!(meta["chunks"], 1);
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request["method"], "sampling/createMessage");
+        assert_eq!(request["params"]["maxTokens"], 50);
+        assert_eq!(
+            request["params"]["modelPreferences"]["hints"][0]["name"],
+            "claude"
+        );
+
+        // Each part is summarized before the summaries are combined
+        let tool = tool.with_chunk_size(12);
+        let result = tool
+            .call(props("First part\n\nSecond one"), context.clone())
+            .await
+            .unwrap();
+        assert_eq!(result.meta.unwrap()["chunks"], 2);
+        let prompts: Vec<_> = (0..3)
+            .map(|_| {
+                requests.try_recv().unwrap()["params"]["messages"][0]["content"]["text"].clone()
+            })
+            .collect();
+        assert!(prompts[0]
+            .as_str()
+            .unwrap()
+            .starts_with("Summarize part 1 of 2"));
+        assert!(prompts[2]
+            .as_str()
+            .unwrap()
+            .ends_with("First part\n\nSecond one"));
+
+        published.publish("notes.txt", MemoryContents::text("text/plain", "Notes"));
+        let by_uri = SummarizeProperties {
+            text: None,
+            uri: Some("mem://notes.txt".to_string()),
+            ..props("")
+        };
+        let result = tool.call(by_uri, context.clone()).await.unwrap();
+        assert_eq!(result.content[0].as_text(), Some("Notes"));
+
+        let missing = SummarizeProperties {
+            text: None,
+            uri: Some("mem://missing.txt".to_string()),
+            ..props("")
+        };
+        let result = tool.call(missing, context).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+
+        // Clients that can't sample fail the call
+        let result = tool.call(props("Text"), ToolContext::default()).await;
+        assert!(result.is_err());
+    }
+}
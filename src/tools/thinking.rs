@@ -0,0 +1,252 @@
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Most thoughts kept for a session before new ones are refused
+const MAX_THOUGHTS: usize = 1000;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ThinkingProperties {
+    #[schemars(description = "Your current thinking step", required = true)]
+    thought: String,
+
+    #[schemars(description = "Number of this thought, starting at 1", required = true)]
+    thought_number: u32,
+
+    #[schemars(
+        description = "Current estimate of how many thoughts are needed, which can be changed as you go",
+        required = true
+    )]
+    total_thoughts: u32,
+
+    #[schemars(
+        description = "Whether another thought is needed after this one",
+        required = true
+    )]
+    next_thought_needed: bool,
+
+    #[schemars(description = "Whether this thought revises an earlier one")]
+    is_revision: Option<bool>,
+
+    #[schemars(
+        description = "Number of the thought being reconsidered (only used with is_revision)"
+    )]
+    revises_thought: Option<u32>,
+
+    #[schemars(description = "Number of the thought this one branches off from")]
+    branch_from_thought: Option<u32>,
+
+    #[schemars(description = "Name of the branch (required with branch_from_thought)")]
+    branch_id: Option<String>,
+
+    #[schemars(description = "Set when you realize more thoughts are needed than estimated")]
+    needs_more_thoughts: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Thought {
+    thought_number: u32,
+    thought: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revises_thought: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch_from_thought: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch_id: Option<String>,
+}
+
+/// Thoughts recorded in one client session
+#[derive(Debug, Default)]
+struct ThoughtChain {
+    thoughts: Vec<Thought>,
+    branches: BTreeMap<String, Vec<u32>>,
+}
+
+/// Scratchpad for step-by-step reasoning, following the sequential thinking pattern
+///
+/// Each client session has its own chain of thoughts, which can be revised
+/// and branched as the model's understanding changes. A first thought that
+/// isn't a revision or a branch starts a new chain.
+#[derive(Clone, Debug, Default, Serialize, McpTool)]
+#[mcp_tool(
+    name = "sequentialthinking",
+    description = "Think through a problem one step at a time. Each call records a thought, which can revise or branch from earlier ones, and returns the reasoning so far. Estimate how many thoughts you need, adjust as you go, and set next_thought_needed to false once you have an answer.",
+    properties = ThinkingProperties
+)]
+pub struct Thinking;
+
+impl Thinking {
+    async fn run(
+        &self,
+        properties: ThinkingProperties,
+        context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        let state = context
+            .store()
+            .update("sequentialthinking", |chain: &mut ThoughtChain| {
+                chain.record(properties)
+            });
+        let state = match state {
+            Ok(state) => state,
+            Err(e) => return Ok(CallToolResult::error(e)),
+        };
+        let text = serde_json::to_string_pretty(&state).map_err(ToolError::ResultSerialize)?;
+        Ok(CallToolResult::text(text))
+    }
+}
+
+impl ThoughtChain {
+    /// Adds a thought and returns the state of the reasoning after it
+    fn record(&mut self, properties: ThinkingProperties) -> Result<serde_json::Value, String> {
+        let number = properties.thought_number;
+        if number == 0 {
+            return Err("thought_number starts at 1".to_string());
+        }
+        if properties.thought.trim().is_empty() {
+            return Err("thought must not be empty".to_string());
+        }
+
+        let is_revision = properties.is_revision.unwrap_or(false);
+        let revises_thought = match (is_revision, properties.revises_thought) {
+            (true, Some(revised)) if revised >= number => {
+                return Err("revises_thought must be an earlier thought".to_string())
+            }
+            (true, revised) => revised,
+            (false, _) => None,
+        };
+        let branch = match (properties.branch_from_thought, properties.branch_id) {
+            (Some(from), Some(id)) if from < number => Some((from, id)),
+            (Some(_), Some(_)) => {
+                return Err("branch_from_thought must be an earlier thought".to_string())
+            }
+            (Some(_), None) => {
+                return Err("branch_id is required with branch_from_thought".to_string())
+            }
+            (None, _) => None,
+        };
+
+        if number == 1 && !is_revision && branch.is_none() {
+            *self = ThoughtChain::default();
+        }
+        if self.thoughts.len() >= MAX_THOUGHTS {
+            return Err(format!(
+                "This session has reached the limit of {} thoughts, start a new chain with thought_number 1",
+                MAX_THOUGHTS
+            ));
+        }
+
+        if let Some((_, id)) = &branch {
+            self.branches.entry(id.clone()).or_default().push(number);
+        }
+        let (branch_from_thought, branch_id) = branch.unzip();
+        self.thoughts.push(Thought {
+            thought_number: number,
+            thought: properties.thought,
+            revises_thought,
+            branch_from_thought,
+            branch_id,
+        });
+
+        // The estimate grows to cover thoughts past the end
+        let total_thoughts = properties.total_thoughts.max(number);
+        Ok(json!({
+            "thought_number": number,
+            "total_thoughts": total_thoughts,
+            "next_thought_needed": properties.next_thought_needed
+                || properties.needs_more_thoughts.unwrap_or(false),
+            "branches": self.branches.keys().collect::<Vec<_>>(),
+            "thought_history_length": self.thoughts.len(),
+            "thoughts": self.thoughts,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolDef;
+    use serde_json::Value;
+
+    fn thought(text: &str, number: u32, next: bool) -> ThinkingProperties {
+        ThinkingProperties {
+            thought: text.to_string(),
+            thought_number: number,
+            total_thoughts: 3,
+            next_thought_needed: next,
+            is_revision: None,
+            revises_thought: None,
+            branch_from_thought: None,
+            branch_id: None,
+            needs_more_thoughts: None,
+        }
+    }
+
+    async fn call(tool: &Thinking, properties: ThinkingProperties, context: &ToolContext) -> Value {
+        let result = tool.call(properties, context.clone()).await.unwrap();
+        assert_eq!(result.is_error, Some(false));
+        serde_json::from_str(result.content[0].as_text().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_thought_chain() {
+        let tool = Thinking;
+        let context = ToolContext::default();
+
+        call(&tool, thought("Understand the problem", 1, true), &context).await;
+        let mut revision = thought("Actually, the problem is different", 2, true);
+        revision.is_revision = Some(true);
+        revision.revises_thought = Some(1);
+        call(&tool, revision, &context).await;
+        let mut branch = thought("Try another approach", 3, true);
+        branch.branch_from_thought = Some(1);
+        branch.branch_id = Some("alternative".to_string());
+        let state = call(&tool, branch, &context).await;
+        assert_eq!(state["thought_history_length"], 3);
+        assert_eq!(state["branches"], json!(["alternative"]));
+        assert_eq!(state["thoughts"][1]["revises_thought"], 1);
+
+        let state = call(
+            &tool,
+            thought("Going past the estimate", 5, false),
+            &context,
+        )
+        .await;
+        assert_eq!(state["total_thoughts"], 5);
+        assert_eq!(state["next_thought_needed"], false);
+
+        // Other sessions have their own chain, and a new first thought starts over
+        let state = call(
+            &tool,
+            thought("Unrelated", 1, true),
+            &ToolContext::default(),
+        )
+        .await;
+        assert_eq!(state["thought_history_length"], 1);
+        let state = call(&tool, thought("Next problem", 1, true), &context).await;
+        assert_eq!(state["thought_history_length"], 1);
+        assert_eq!(state["branches"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_thoughts() {
+        let tool = Thinking;
+        let mut revision = thought("Revise a later thought", 2, true);
+        revision.is_revision = Some(true);
+        revision.revises_thought = Some(4);
+        let mut branch = thought("Branch without a name", 2, true);
+        branch.branch_from_thought = Some(1);
+
+        for properties in [
+            thought("Zero", 0, true),
+            thought(" ", 1, true),
+            revision,
+            branch,
+        ] {
+            let result = tool.call(properties, ToolContext::default()).await.unwrap();
+            assert_eq!(result.is_error, Some(true));
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use crate::schema::CallToolResult;
+use crate::tools::{McpTool, ToolContext, ToolError};
+use chrono::{DateTime, Duration, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, Tz};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeAction {
+    CurrentTime,
+    ConvertTime,
+    ConvertTimestamp,
+    AddDuration,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TimeProperties {
+    #[schemars(
+        description = "The action to perform: 'current_time' to get the time in a timezone, 'convert_time' to convert an HH:MM time between timezones, 'convert_timestamp' to show a Unix timestamp or RFC 3339 date in a timezone, or 'add_duration' to add a duration to a date"
+    )]
+    action: TimeAction,
+
+    #[schemars(
+        description = "IANA timezone name such as 'Europe/Warsaw' used for the result (defaults to the server's timezone)"
+    )]
+    timezone: Option<String>,
+
+    #[schemars(description = "Timezone the time is given in (only used by convert_time)")]
+    source_timezone: Option<String>,
+
+    #[schemars(description = "Timezone to convert to (only required for convert_time)")]
+    target_timezone: Option<String>,
+
+    #[schemars(
+        description = "The time to use: HH:MM for convert_time, or a Unix timestamp in seconds or an RFC 3339 date for convert_timestamp and add_duration (defaults to now for add_duration)"
+    )]
+    time: Option<String>,
+
+    #[schemars(
+        description = "Duration to add such as '1w 2d', '3h30m' or '-45s', in weeks, days, hours, minutes and seconds (only required for add_duration)"
+    )]
+    duration: Option<String>,
+}
+
+/// Tells the time in any timezone and does date arithmetic
+///
+/// Mirrors the reference MCP time server, so clients don't need to run it
+/// separately.
+#[derive(Clone, Debug, Serialize, McpTool)]
+#[mcp_tool(
+    name = "time",
+    description = "Gets the current time in a timezone, converts times and timestamps between timezones, and adds durations to dates",
+    properties = TimeProperties
+)]
+pub struct Time {
+    #[serde(serialize_with = "serialize_timezone")]
+    local_timezone: Tz,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new(Tz::UTC)
+    }
+}
+
+impl Time {
+    /// A time tool that uses `local_timezone` when the caller doesn't name one
+    pub fn new(local_timezone: Tz) -> Self {
+        Self { local_timezone }
+    }
+
+    async fn run(
+        &self,
+        properties: TimeProperties,
+        _context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        let result = match self.run_action(properties, Utc::now()) {
+            Ok(result) => result,
+            Err(e) => return Ok(CallToolResult::error(e)),
+        };
+        let text = serde_json::to_string_pretty(&result).map_err(ToolError::ResultSerialize)?;
+        Ok(CallToolResult::text(text))
+    }
+
+    fn run_action(&self, properties: TimeProperties, now: DateTime<Utc>) -> Result<Value, String> {
+        let timezone = self.timezone(properties.timezone.as_deref())?;
+
+        match properties.action {
+            TimeAction::CurrentTime => Ok(describe(&now.with_timezone(&timezone))),
+            TimeAction::ConvertTime => {
+                let source = self.timezone(properties.source_timezone.as_deref())?;
+                let target = properties
+                    .target_timezone
+                    .ok_or("Target timezone is required for convert_time action")?;
+                let target = self.timezone(Some(&target))?;
+                let time = properties
+                    .time
+                    .ok_or("Time is required for convert_time action")?;
+                let time = NaiveTime::parse_from_str(&time, "%H:%M")
+                    .map_err(|_| format!("Invalid time {}, expected HH:MM", time))?;
+
+                // The time is taken to be on today's date in the source timezone
+                let date = now.with_timezone(&source).date_naive();
+                let source_time = source
+                    .from_local_datetime(&date.and_time(time))
+                    .earliest()
+                    .ok_or_else(|| format!("{} does not exist in {}", time, source))?;
+                let target_time = source_time.with_timezone(&target);
+
+                let difference = offset_seconds(&target_time) - offset_seconds(&source_time);
+                Ok(json!({
+                    "source": describe(&source_time),
+                    "target": describe(&target_time),
+                    "time_difference": format_hours(difference),
+                }))
+            }
+            TimeAction::ConvertTimestamp => {
+                let time = properties
+                    .time
+                    .ok_or("Time is required for convert_timestamp action")?;
+                let time = parse_time(&time)?;
+                let mut result = describe(&time.with_timezone(&timezone));
+                result["timestamp"] = time.timestamp().into();
+                Ok(result)
+            }
+            TimeAction::AddDuration => {
+                let duration = properties
+                    .duration
+                    .ok_or("Duration is required for add_duration action")?;
+                let duration = parse_duration(&duration)?;
+                let time = match properties.time {
+                    Some(time) => parse_time(&time)?,
+                    None => now,
+                };
+                let result = time
+                    .checked_add_signed(duration)
+                    .ok_or("The resulting date is out of range")?;
+                Ok(describe(&result.with_timezone(&timezone)))
+            }
+        }
+    }
+
+    fn timezone(&self, name: Option<&str>) -> Result<Tz, String> {
+        match name {
+            Some(name) => name
+                .parse()
+                .map_err(|_| format!("Unknown timezone {}, use an IANA name", name)),
+            None => Ok(self.local_timezone),
+        }
+    }
+}
+
+fn serialize_timezone<S: serde::Serializer>(
+    timezone: &Tz,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(timezone.name())
+}
+
+/// The fields the reference time server reports for a point in time
+fn describe(time: &DateTime<Tz>) -> Value {
+    json!({
+        "timezone": time.timezone().name(),
+        "datetime": time.to_rfc3339(),
+        "is_dst": !time.offset().dst_offset().is_zero(),
+    })
+}
+
+fn offset_seconds(time: &DateTime<Tz>) -> i64 {
+    time.offset().fix().local_minus_utc() as i64
+}
+
+/// Formats an offset difference like `+5.5h`
+fn format_hours(seconds: i64) -> String {
+    let hours = seconds as f64 / 3600.0;
+    let sign = if seconds >= 0 { "+" } else { "" };
+    if seconds % 3600 == 0 {
+        format!("{}{:.1}h", sign, hours)
+    } else {
+        format!("{}{}h", sign, hours)
+    }
+}
+
+/// Parses a Unix timestamp in seconds or an RFC 3339 date
+fn parse_time(time: &str) -> Result<DateTime<Utc>, String> {
+    let time = time.trim();
+    if let Ok(timestamp) = time.parse::<i64>() {
+        return DateTime::from_timestamp(timestamp, 0)
+            .ok_or_else(|| format!("Timestamp {} is out of range", timestamp));
+    }
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| {
+            format!(
+                "Invalid time {}, expected a Unix timestamp or RFC 3339 date",
+                time
+            )
+        })
+}
+
+/// Parses durations like `1w 2d`, `3h30m` or `-45s`
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "Invalid duration {}, expected something like 2h30m",
+            duration
+        )
+    };
+    let trimmed = duration.trim();
+    let (negative, rest) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut parts = 0;
+    for c in rest.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: i64 = number.parse().map_err(|_| invalid())?;
+        let part = match c {
+            'w' => Duration::try_weeks(value),
+            'd' => Duration::try_days(value),
+            'h' => Duration::try_hours(value),
+            'm' => Duration::try_minutes(value),
+            's' => Duration::try_seconds(value),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+        total = total.checked_add(&part).ok_or_else(invalid)?;
+        number.clear();
+        parts += 1;
+    }
+    if parts == 0 || !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(if negative { -total } else { total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolDef;
+
+    fn props(action: TimeAction) -> TimeProperties {
+        TimeProperties {
+            action,
+            timezone: None,
+            source_timezone: None,
+            target_timezone: None,
+            time: None,
+            duration: None,
+        }
+    }
+
+    #[test]
+    fn test_time_actions() {
+        let tool = Time::new(chrono_tz::Europe::Warsaw);
+        let now = DateTime::parse_from_rfc3339("2024-07-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let result = tool
+            .run_action(props(TimeAction::CurrentTime), now)
+            .unwrap();
+        assert_eq!(
+            result,
+            json!({"timezone": "Europe/Warsaw", "datetime": "2024-07-01T12:00:00+02:00", "is_dst": true})
+        );
+
+        let mut convert = props(TimeAction::ConvertTime);
+        convert.source_timezone = Some("America/New_York".to_string());
+        convert.target_timezone = Some("Asia/Kolkata".to_string());
+        convert.time = Some("09:30".to_string());
+        let result = tool.run_action(convert, now).unwrap();
+        assert_eq!(result["source"]["datetime"], "2024-07-01T09:30:00-04:00");
+        assert_eq!(result["target"]["datetime"], "2024-07-01T19:00:00+05:30");
+        assert_eq!(result["target"]["is_dst"], false);
+        assert_eq!(result["time_difference"], "+9.5h");
+
+        let mut timestamp = props(TimeAction::ConvertTimestamp);
+        timestamp.timezone = Some("UTC".to_string());
+        timestamp.time = Some("0".to_string());
+        let result = tool.run_action(timestamp, now).unwrap();
+        assert_eq!(result["datetime"], "1970-01-01T00:00:00+00:00");
+        assert_eq!(result["timestamp"], 0);
+
+        let mut add = props(TimeAction::AddDuration);
+        add.time = Some("2024-03-30T12:00:00+01:00".to_string());
+        add.duration = Some("1d 30m".to_string());
+        let result = tool.run_action(add, now).unwrap();
+        // Crosses the switch to summer time
+        assert_eq!(result["datetime"], "2024-03-31T13:30:00+02:00");
+
+        let mut unknown = props(TimeAction::CurrentTime);
+        unknown.timezone = Some("Mars/Olympus".to_string());
+        assert!(tool.run_action(unknown, now).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("2h30m"), Ok(Duration::minutes(150)));
+        assert_eq!(parse_duration("1w 2d"), Ok(Duration::days(9)));
+        assert_eq!(parse_duration("-45s"), Ok(Duration::seconds(-45)));
+        for invalid in ["", "5", "3x", "h", "-"] {
+            assert!(parse_duration(invalid).is_err(), "{} is invalid", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_time_tool() {
+        let result = Time::default()
+            .call(props(TimeAction::CurrentTime), ToolContext::default())
+            .await
+            .unwrap();
+        let result: Value = serde_json::from_str(result.content[0].as_text().unwrap()).unwrap();
+        assert_eq!(result["timezone"], "UTC");
+    }
+}
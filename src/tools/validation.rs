@@ -0,0 +1,213 @@
+use crate::schema;
+use crate::tools::ToolError;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::error;
+
+/// Where the arguments of a call break the tool's input schema, and how
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ArgumentError {
+    /// JSON pointer to the offending value, empty for the arguments as a whole
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+type Validators = Mutex<HashMap<String, Option<Arc<jsonschema::Validator>>>>;
+
+/// Checks the arguments of tool calls against the tools' input schemas, and
+/// their structured content against the output schemas
+///
+/// Each tool's schema is compiled the first time it's called. Tools whose
+/// schema can't be compiled are called without checking their arguments.
+#[derive(Default)]
+pub struct ArgumentValidator {
+    validators: Validators,
+    output_validators: Validators,
+}
+
+impl ArgumentValidator {
+    /// Fails with every way `args` breaks the input schema of `tool`
+    pub fn validate(
+        &self,
+        tool: &schema::Tool,
+        args: Option<&BTreeMap<String, Value>>,
+    ) -> Result<(), ToolError> {
+        let Some(validator) = validator(&self.validators, tool, &tool.input_schema) else {
+            return Ok(());
+        };
+        let args = Value::Object(args.cloned().unwrap_or_default().into_iter().collect());
+        let errors = check(&validator, &args);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ToolError::InvalidArguments(errors))
+        }
+    }
+
+    /// Fails with every way the structured content of `result` breaks the
+    /// output schema of `tool`
+    ///
+    /// Results of tools without an output schema, and error results, aren't
+    /// checked.
+    pub fn validate_output(
+        &self,
+        tool: &schema::Tool,
+        result: &schema::CallToolResult,
+    ) -> Result<(), ToolError> {
+        let Some(output_schema) = &tool.output_schema else {
+            return Ok(());
+        };
+        if result.is_error == Some(true) {
+            return Ok(());
+        }
+        let Some(content) = &result.structured_content else {
+            return Err(ToolError::InvalidOutput(vec![ArgumentError {
+                path: String::new(),
+                message: "structured content is missing".to_string(),
+            }]));
+        };
+        let Some(validator) = validator(&self.output_validators, tool, output_schema) else {
+            return Ok(());
+        };
+        let errors = check(&validator, content);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ToolError::InvalidOutput(errors))
+        }
+    }
+}
+
+/// The compiled `schema` of `tool`, compiling it on first use
+fn validator(
+    validators: &Validators,
+    tool: &schema::Tool,
+    schema: &impl Serialize,
+) -> Option<Arc<jsonschema::Validator>> {
+    let mut validators = validators.lock().unwrap_or_else(|e| e.into_inner());
+    validators
+        .entry(tool.name.clone())
+        .or_insert_with(|| {
+            let schema = serde_json::to_value(schema).ok()?;
+            match jsonschema::validator_for(&schema) {
+                Ok(validator) => Some(Arc::new(validator)),
+                Err(e) => {
+                    error!("Not checking the schema of {}: {}", tool.name, e);
+                    None
+                }
+            }
+        })
+        .clone()
+}
+
+fn check(validator: &jsonschema::Validator, instance: &Value) -> Vec<ArgumentError> {
+    validator
+        .iter_errors(instance)
+        .map(|e| ArgumentError {
+            path: e.instance_path().to_string(),
+            message: e.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{tool_def, tool_def_with_output};
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct OrderProperties {
+        item: String,
+        quantity: Option<u32>,
+        size: Size,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    #[allow(dead_code)]
+    enum Size {
+        Small,
+        Large,
+    }
+
+    fn args(value: Value) -> BTreeMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_validate_arguments() {
+        let tool = tool_def::<OrderProperties>("order", "Orders an item");
+        let validator = ArgumentValidator::default();
+
+        let valid = args(json!({ "item": "tea", "size": "large" }));
+        assert!(validator.validate(&tool, Some(&valid)).is_ok());
+
+        let invalid = args(json!({ "quantity": "two", "size": "medium" }));
+        let Err(ToolError::InvalidArguments(errors)) = validator.validate(&tool, Some(&invalid))
+        else {
+            panic!("Expected invalid arguments");
+        };
+        let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors.contains(&"\"item\" is a required property".to_string()));
+        assert!(errors.iter().any(|e| e.starts_with("/quantity: \"two\"")));
+        assert!(errors.iter().any(|e| e.starts_with("/size: \"medium\"")));
+
+        let Err(ToolError::InvalidArguments(errors)) = validator.validate(&tool, None) else {
+            panic!("Expected missing arguments");
+        };
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    struct Receipt {
+        total: u32,
+    }
+
+    #[test]
+    fn test_validate_output() {
+        let tool = tool_def_with_output::<OrderProperties, Receipt>("order", "Orders an item");
+        let validator = ArgumentValidator::default();
+
+        let valid = schema::CallToolResult::structured(Receipt { total: 3 }).unwrap();
+        assert!(validator.validate_output(&tool, &valid).is_ok());
+
+        let mut invalid = valid.clone();
+        invalid.structured_content = Some(json!({ "total": -1 }));
+        let Err(ToolError::InvalidOutput(errors)) = validator.validate_output(&tool, &invalid)
+        else {
+            panic!("Expected invalid output");
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "/total");
+
+        let missing = schema::CallToolResult::text("3");
+        assert!(matches!(
+            validator.validate_output(&tool, &missing),
+            Err(ToolError::InvalidOutput(_))
+        ));
+
+        // Failures and tools without an output schema aren't checked
+        let failed = schema::CallToolResult::error("out of stock");
+        assert!(validator.validate_output(&tool, &failed).is_ok());
+        let untyped = tool_def::<OrderProperties>("order", "Orders an item");
+        assert!(validator.validate_output(&untyped, &invalid).is_ok());
+    }
+}
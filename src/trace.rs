@@ -0,0 +1,171 @@
+use crate::ServerMetadata;
+use jsonrpc_core::futures_util::future::{BoxFuture, Either, FutureExt};
+use jsonrpc_core::{Call, Id, Output};
+use std::time::Instant;
+use tracing::{field, info, info_span, Instrument};
+
+/// Runs every dispatched call in a `request` span and logs how it went
+///
+/// The span carries the method, request ID and session ID, so everything a
+/// handler logs can be traced back to the request. Once the call finishes,
+/// a summary event records its outcome and how many milliseconds it took.
+pub(crate) struct RequestSpans;
+
+impl jsonrpc_core::Middleware<ServerMetadata> for RequestSpans {
+    type Future = jsonrpc_core::middleware::NoopFuture;
+    type CallFuture = BoxFuture<'static, Option<Output>>;
+
+    fn on_call<F, X>(
+        &self,
+        call: Call,
+        meta: ServerMetadata,
+        next: F,
+    ) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, ServerMetadata) -> X + Send + Sync,
+        X: std::future::Future<Output = Option<Output>> + Send + 'static,
+    {
+        let span = match &call {
+            Call::MethodCall(request) => info_span!(
+                "request",
+                method = %request.method,
+                request_id = %display_id(&request.id),
+                session_id = %meta.session_id,
+            ),
+            Call::Notification(notification) => info_span!(
+                "request",
+                method = %notification.method,
+                request_id = field::Empty,
+                session_id = %meta.session_id,
+            ),
+            Call::Invalid { .. } => return Either::Right(next(call, meta)),
+        };
+
+        let started = Instant::now();
+        // Handlers start running while the call is dispatched, so enter the span for that too
+        let response = span.in_scope(|| next(call, meta));
+        let response = async move {
+            let output = response.await;
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+            match &output {
+                Some(Output::Success(success)) if success.result["isError"] == true => {
+                    info!(outcome = "tool_error", elapsed_ms, "Request finished");
+                }
+                Some(Output::Success(_)) => {
+                    info!(outcome = "success", elapsed_ms, "Request finished");
+                }
+                Some(Output::Failure(failure)) => info!(
+                    outcome = "error",
+                    elapsed_ms,
+                    error_code = failure.error.code.code(),
+                    error = %failure.error.message,
+                    "Request finished"
+                ),
+                None => info!(outcome = "notification", elapsed_ms, "Request finished"),
+            }
+            output
+        };
+        Either::Left(response.instrument(span).boxed())
+    }
+}
+
+fn display_id(id: &Id) -> String {
+    match id {
+        Id::Null => "null".to_string(),
+        Id::Num(id) => id.to_string(),
+        Id::Str(id) => id.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roots::RootsState;
+    use crate::router::RequestRouter;
+    use crate::session::Sessions;
+    use crate::transport::ConnectionId;
+    use jsonrpc_core::MetaIoHandler;
+    use serde_json::json;
+    use std::fmt::Write;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id as SpanId};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    /// Writes each event as `span fields | event fields`
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<String>>>);
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            let _ = write!(self.0, "{}={:?} ", field.name(), value);
+        }
+    }
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Capture {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &SpanId, ctx: Context<'_, S>) {
+            let mut fields = Fields(String::new());
+            attrs.record(&mut fields);
+            ctx.span(id).unwrap().extensions_mut().insert(fields);
+        }
+
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+            let span = ctx
+                .event_span(event)
+                .and_then(|span| span.extensions().get::<Fields>().map(|f| f.0.clone()))
+                .unwrap_or_default();
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}| {}", span, fields.0));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_spans() {
+        let capture = Capture::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+
+        let mut io_handler = MetaIoHandler::with_middleware(RequestSpans);
+        io_handler.add_method("tools/call", |_params| async {
+            tracing::info!("Calling tool");
+            Ok(json!({ "content": [], "isError": true }))
+        });
+        let (outgoing_tx, _outgoing_rx) = tokio::sync::mpsc::channel(1);
+        let meta = ServerMetadata {
+            session_id: ConnectionId::from("client"),
+            sessions: Sessions::default(),
+            router: RequestRouter::new(outgoing_tx),
+            roots: RootsState::default(),
+        };
+        let request = json!([
+            { "jsonrpc": "2.0", "id": "call-1", "method": "tools/call" },
+            { "jsonrpc": "2.0", "id": 2, "method": "missing" },
+        ]);
+        io_handler
+            .handle_request(&request.to_string(), meta)
+            .await
+            .unwrap();
+
+        let events = capture.0.lock().unwrap().clone();
+        assert_eq!(events.len(), 3, "{:?}", events);
+        let tool_call = "method=tools/call request_id=call-1 session_id=client |";
+        assert!(events[0].starts_with(tool_call), "{}", events[0]);
+        assert!(events[0].contains("Calling tool"));
+        let summaries: Vec<_> = events[1..].iter().collect();
+        assert!(summaries.iter().any(|event| event.starts_with(tool_call)
+            && event.contains("outcome=\"tool_error\"")
+            && event.contains("elapsed_ms=")));
+        assert!(summaries.iter().any(|event| event.contains("request_id=2")
+            && event.contains("outcome=\"error\"")
+            && event.contains("error_code=-32601")));
+    }
+}
@@ -0,0 +1,451 @@
+use axum::{
+    extract::{Request, State},
+    http::{header::WWW_AUTHENTICATE, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{DecodingKey, Validation};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{debug, error};
+use url::Url;
+
+/// Well-known path of the OAuth protected resource metadata (RFC 9728)
+pub const PROTECTED_RESOURCE_METADATA_PATH: &str = "/.well-known/oauth-protected-resource";
+
+/// How long fetched signing keys are used before they are fetched again
+const JWKS_TTL: Duration = Duration::from_secs(600);
+
+/// Shortest time between fetches of the signing keys when a token names an unknown key
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+/// OAuth 2.1 authorization of the HTTP transports, following the MCP authorization spec
+///
+/// Clients must send a bearer token issued by `issuer` for this server. The
+/// token is a JWT verified against the keys the issuer publishes at
+/// `jwks_uri`, and must name the server as its audience.
+#[derive(Clone, Debug)]
+pub struct AuthConfig {
+    resource: Url,
+    issuer: String,
+    jwks_uri: Url,
+    audience: Option<String>,
+    scopes: Vec<String>,
+}
+
+impl AuthConfig {
+    /// Protects the server at the canonical URL `resource`
+    pub fn new(resource: Url, issuer: impl Into<String>, jwks_uri: Url) -> Self {
+        Self {
+            resource,
+            issuer: issuer.into(),
+            jwks_uri,
+            audience: None,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Audience tokens must name, instead of the resource URL
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    /// Scopes every token must grant, which are also advertised to clients
+    pub fn with_scopes(mut self, scopes: Vec<String>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Where the protected resource metadata is served
+    pub fn metadata_url(&self) -> String {
+        let mut url = self.resource.clone();
+        url.set_path(&self.metadata_path());
+        url.set_query(None);
+        url.set_fragment(None);
+        url.to_string()
+    }
+
+    fn metadata_path(&self) -> String {
+        let path = self.resource.path().trim_end_matches('/');
+        format!("{}{}", PROTECTED_RESOURCE_METADATA_PATH, path)
+    }
+}
+
+/// Claims of a validated access token
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenClaims {
+    /// Who the token was issued to
+    pub sub: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default)]
+    scp: Option<Vec<String>>,
+}
+
+impl TokenClaims {
+    /// Scopes the token grants, from either the `scope` or the `scp` claim
+    pub fn scopes(&self) -> Vec<&str> {
+        match (&self.scope, &self.scp) {
+            (Some(scope), _) => scope.split_whitespace().collect(),
+            (None, Some(scp)) => scp.iter().map(String::as_str).collect(),
+            (None, None) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("No bearer token provided")]
+    MissingToken,
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+    #[error("Token lacks the required scopes")]
+    InsufficientScope,
+    #[error("Failed to fetch the signing keys: {0}")]
+    Jwks(String),
+}
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Validates bearer tokens against an `AuthConfig`
+pub struct Authenticator {
+    config: AuthConfig,
+    client: reqwest::Client,
+    keys: RwLock<Option<CachedKeys>>,
+}
+
+impl Authenticator {
+    pub fn new(config: AuthConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            keys: RwLock::new(None),
+        }
+    }
+
+    /// Checks a token and returns its claims
+    pub async fn validate(&self, token: &str) -> Result<TokenClaims, AuthError> {
+        let invalid = |e: jsonwebtoken::errors::Error| AuthError::InvalidToken(e.to_string());
+        let header = jsonwebtoken::decode_header(token).map_err(invalid)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| AuthError::InvalidToken("Token has no key ID".to_string()))?;
+        let key = self.key(&kid).await?;
+
+        // The key's family must match the algorithm, so a token can't pick a weaker one
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.config.issuer]);
+        let audience = match &self.config.audience {
+            Some(audience) => audience.clone(),
+            None => self.config.resource.to_string(),
+        };
+        validation.set_audience(&[audience.as_str(), audience.trim_end_matches('/')]);
+        let claims = jsonwebtoken::decode::<TokenClaims>(token, &key, &validation)
+            .map_err(invalid)?
+            .claims;
+
+        let granted = claims.scopes();
+        if !self
+            .config
+            .scopes
+            .iter()
+            .all(|scope| granted.contains(&scope.as_str()))
+        {
+            return Err(AuthError::InsufficientScope);
+        }
+        Ok(claims)
+    }
+
+    /// Finds the signing key `kid`, fetching the issuer's keys if needed
+    async fn key(&self, kid: &str) -> Result<DecodingKey, AuthError> {
+        let stale = {
+            let keys = self.keys.read().await;
+            match keys.as_ref() {
+                Some(cached) if cached.fetched_at.elapsed() < JWKS_TTL => {
+                    if let Some(jwk) = cached.keys.find(kid) {
+                        return decoding_key(jwk);
+                    }
+                    cached.fetched_at.elapsed() >= JWKS_MIN_REFRESH
+                }
+                _ => true,
+            }
+        };
+        if !stale {
+            return Err(AuthError::InvalidToken(format!("Unknown key {}", kid)));
+        }
+
+        let fetched = self.fetch_keys().await?;
+        let key = fetched.find(kid).map(decoding_key);
+        *self.keys.write().await = Some(CachedKeys {
+            keys: fetched,
+            fetched_at: Instant::now(),
+        });
+        key.unwrap_or_else(|| Err(AuthError::InvalidToken(format!("Unknown key {}", kid))))
+    }
+
+    async fn fetch_keys(&self) -> Result<JwkSet, AuthError> {
+        debug!("Fetching signing keys from {}", self.config.jwks_uri);
+        let response = self
+            .client
+            .get(self.config.jwks_uri.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AuthError::Jwks(e.to_string()))?;
+        response
+            .json()
+            .await
+            .map_err(|e| AuthError::Jwks(e.to_string()))
+    }
+
+    /// The response telling the client how to authorize
+    fn challenge(&self, error: &AuthError) -> Response {
+        let mut challenge = format!(
+            "Bearer resource_metadata=\"{}\"",
+            self.config.metadata_url()
+        );
+        let status = match error {
+            AuthError::MissingToken => StatusCode::UNAUTHORIZED,
+            AuthError::InvalidToken(description) => {
+                challenge.push_str(&format!(
+                    ", error=\"invalid_token\", error_description=\"{}\"",
+                    description.replace(['"', '\\'], "'")
+                ));
+                StatusCode::UNAUTHORIZED
+            }
+            AuthError::InsufficientScope => {
+                challenge.push_str(&format!(
+                    ", error=\"insufficient_scope\", scope=\"{}\"",
+                    self.config.scopes.join(" ")
+                ));
+                StatusCode::FORBIDDEN
+            }
+            AuthError::Jwks(_) => return StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        };
+
+        let mut response = status.into_response();
+        if let Ok(value) = HeaderValue::from_str(&challenge) {
+            response.headers_mut().insert(WWW_AUTHENTICATE, value);
+        }
+        response
+    }
+
+    fn metadata(&self) -> serde_json::Value {
+        let mut metadata = serde_json::json!({
+            "resource": self.config.resource.as_str(),
+            "authorization_servers": [self.config.issuer],
+            "bearer_methods_supported": ["header"],
+        });
+        if !self.config.scopes.is_empty() {
+            metadata["scopes_supported"] = self.config.scopes.clone().into();
+        }
+        metadata
+    }
+}
+
+/// Requires a valid bearer token on every route of `router` and serves the
+/// protected resource metadata alongside
+pub(crate) fn protect(router: Router, auth: Arc<Authenticator>) -> Router {
+    let metadata_path = auth.config.metadata_path();
+    let metadata = Json(auth.metadata());
+    let router = router.route_layer(axum::middleware::from_fn_with_state(auth, require_token));
+
+    let mut metadata_routes = Router::new().route(&metadata_path, get(metadata.clone()));
+    if metadata_path != PROTECTED_RESOURCE_METADATA_PATH {
+        metadata_routes = metadata_routes.route(PROTECTED_RESOURCE_METADATA_PATH, get(metadata));
+    }
+    router.merge(metadata_routes)
+}
+
+async fn require_token(
+    State(auth): State<Arc<Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let result = match bearer_token(request.headers()) {
+        Some(token) => auth.validate(token).await,
+        None => Err(AuthError::MissingToken),
+    };
+    match result {
+        Ok(claims) => {
+            request.extensions_mut().insert(claims);
+            next.run(request).await
+        }
+        Err(e) => {
+            match &e {
+                AuthError::Jwks(_) => error!("Rejected request: {}", e),
+                _ => debug!("Rejected request: {}", e),
+            }
+            auth.challenge(&e)
+        }
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers
+        .get(axum::http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+fn decoding_key(jwk: &Jwk) -> Result<DecodingKey, AuthError> {
+    DecodingKey::from_jwk(jwk)
+        .map_err(|e| AuthError::InvalidToken(format!("Unusable signing key: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use jsonwebtoken::{EncodingKey, Header};
+    use tokio::net::TcpListener;
+
+    const SECRET: &[u8] = b"test signing secret";
+
+    fn token(claims: serde_json::Value) -> String {
+        let mut header = Header::new(jsonwebtoken::Algorithm::HS256);
+        header.kid = Some("test-key".to_string());
+        jsonwebtoken::encode(&header, &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    }
+
+    fn claims(scope: &str) -> serde_json::Value {
+        serde_json::json!({
+            "iss": "https://auth.example.com",
+            "aud": "https://mcp.example.com/mcp",
+            "sub": "user-1",
+            "scope": scope,
+            "exp": chrono::Utc::now().timestamp() + 600,
+        })
+    }
+
+    async fn authenticator(
+        server: &mut mockito::ServerGuard,
+    ) -> (Arc<Authenticator>, mockito::Mock) {
+        let secret = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(SECRET);
+        let jwks = server
+            .mock("GET", "/jwks.json")
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "keys": [{ "kty": "oct", "kid": "test-key", "alg": "HS256", "k": secret }]
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let config = AuthConfig::new(
+            Url::parse("https://mcp.example.com/mcp").unwrap(),
+            "https://auth.example.com",
+            Url::parse(&format!("{}/jwks.json", server.url())).unwrap(),
+        )
+        .with_scopes(vec!["mcp:tools".to_string()]);
+        (Arc::new(Authenticator::new(config)), jwks)
+    }
+
+    #[tokio::test]
+    async fn test_validate_token() {
+        let mut server = mockito::Server::new_async().await;
+        let (auth, jwks) = authenticator(&mut server).await;
+
+        let validated = auth
+            .validate(&token(claims("mcp:tools other")))
+            .await
+            .unwrap();
+        assert_eq!(validated.sub.as_deref(), Some("user-1"));
+
+        assert!(matches!(
+            auth.validate(&token(claims_with("iss", "https://evil.example.com")))
+                .await,
+            Err(AuthError::InvalidToken(_))
+        ));
+        assert!(matches!(
+            auth.validate(&token(claims_with("aud", "https://other.example.com")))
+                .await,
+            Err(AuthError::InvalidToken(_))
+        ));
+        assert!(matches!(
+            auth.validate(&token(claims("other"))).await,
+            Err(AuthError::InsufficientScope)
+        ));
+        assert!(matches!(
+            auth.validate("not a token").await,
+            Err(AuthError::InvalidToken(_))
+        ));
+        // The keys are fetched once and reused
+        jwks.assert_async().await;
+    }
+
+    fn claims_with(claim: &str, value: &str) -> serde_json::Value {
+        let mut claims = claims("mcp:tools");
+        claims[claim] = value.into();
+        claims
+    }
+
+    #[tokio::test]
+    async fn test_protected_router() {
+        let mut server = mockito::Server::new_async().await;
+        let (auth, _jwks) = authenticator(&mut server).await;
+        let router = protect(Router::new().route("/mcp", get(|| async { "ok" })), auth);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let client = reqwest::Client::new();
+
+        let response = client.get(format!("{}/mcp", url)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[WWW_AUTHENTICATE],
+            "Bearer resource_metadata=\"https://mcp.example.com/.well-known/oauth-protected-resource/mcp\""
+        );
+
+        let response = client
+            .get(format!("{}/mcp", url))
+            .bearer_auth(token(claims("mcp:tools")))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+
+        let response = client
+            .get(format!("{}/mcp", url))
+            .bearer_auth(token(claims("other")))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let metadata: serde_json::Value = client
+            .get(format!("{}{}/mcp", url, PROTECTED_RESOURCE_METADATA_PATH))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(metadata["resource"], "https://mcp.example.com/mcp");
+        assert_eq!(
+            metadata["authorization_servers"],
+            serde_json::json!(["https://auth.example.com"])
+        );
+        assert_eq!(
+            metadata["scopes_supported"],
+            serde_json::json!(["mcp:tools"])
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use super::{ConnectionId, Transport, TransportError, TransportMessage};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+
+/// Connection ID of the single client talking over a channel
+pub const CHANNEL_CONNECTION: &str = "channel";
+
+/// Messages either side of a channel can queue before the sender waits
+const CHANNEL_CAPACITY: usize = 32;
+
+/// Serves a single client in the same process over a pair of channels
+///
+/// Nothing is serialized beyond the JSON-RPC text itself, which makes it
+/// the transport for tests and for applications embedding both ends. With
+/// no wire in between there's no encoding to pick; embedders talking to a
+/// server in another process can use `TcpTransport` with CBOR instead. The
+/// transport closes once the client's end is dropped.
+#[derive(Clone)]
+pub struct ChannelTransport {
+    incoming: Arc<Mutex<Option<mpsc::Receiver<String>>>>,
+    outgoing: mpsc::Sender<String>,
+}
+
+/// The client's end of a `ChannelTransport`
+pub struct ChannelEnd {
+    /// Messages for the server
+    pub sender: mpsc::Sender<String>,
+    /// Messages from the server
+    pub receiver: mpsc::Receiver<String>,
+}
+
+impl ChannelTransport {
+    /// Creates the transport and the end a client talks to it through
+    pub fn new() -> (Self, ChannelEnd) {
+        let (sender, incoming) = mpsc::channel(CHANNEL_CAPACITY);
+        let (outgoing, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let transport = Self {
+            incoming: Arc::new(Mutex::new(Some(incoming))),
+            outgoing,
+        };
+        (transport, ChannelEnd { sender, receiver })
+    }
+}
+
+impl Transport for ChannelTransport {
+    fn start(
+        &mut self,
+        request_tx: mpsc::Sender<TransportMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let incoming = self
+            .incoming
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        Box::pin(async move {
+            let Some(mut incoming) = incoming else {
+                return Err(TransportError::Io(io::Error::other(
+                    "Channel transport was already started",
+                )));
+            };
+            while let Some(message) = incoming.recv().await {
+                debug!("Received [channel]: {}", message);
+                let message = TransportMessage {
+                    connection: ConnectionId::from(CHANNEL_CONNECTION),
+                    message,
+                };
+                if request_tx.send(message).await.is_err() {
+                    error!("Failed to send request through channel");
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn send_response(
+        &mut self,
+        _connection: ConnectionId,
+        response: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        Box::pin(async move {
+            if response.is_empty() {
+                return Ok(());
+            }
+            debug!("Sending [channel]: {}", response);
+            // The client is gone once its end is dropped, like a closed connection
+            if self.outgoing.send(response).await.is_err() {
+                debug!("Dropped a message for the closed channel");
+            }
+            Ok(())
+        })
+    }
+
+    fn broadcast(
+        &mut self,
+        message: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        self.send_response(ConnectionId::from(CHANNEL_CONNECTION), message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_channel_transport() {
+        let (mut transport, mut client) = ChannelTransport::new();
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        let reader = tokio::spawn(async move { server.start(request_tx).await });
+
+        client.sender.send("hello".to_string()).await.unwrap();
+        let received = request_rx.recv().await.unwrap();
+        assert_eq!(received.message, "hello");
+        assert_eq!(received.connection.as_str(), CHANNEL_CONNECTION);
+
+        transport
+            .send_response(received.connection, "reply".to_string())
+            .await
+            .unwrap();
+        transport.broadcast(String::new()).await.unwrap();
+        assert_eq!(client.receiver.recv().await.unwrap(), "reply");
+
+        // Dropping the client's end closes the transport, which only starts once
+        drop(client);
+        reader.await.unwrap().unwrap();
+        let (request_tx, _request_rx) = mpsc::channel(32);
+        assert!(transport.start(request_tx).await.is_err());
+    }
+}
@@ -0,0 +1,524 @@
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use std::io::{self, Cursor};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+use tokio_tungstenite::tungstenite::protocol::frame::FrameHeader;
+
+/// WebSocket extension compressing each message with DEFLATE, RFC 7692
+const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+/// The extension as the server accepts it, without keeping either side's
+/// compression window from one message to the next
+pub(crate) const ACCEPTED: &str =
+    "permessage-deflate; server_no_context_takeover; client_no_context_takeover";
+
+/// Ends every message deflated with a sync flush, and is left off on the wire
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Bytes read from the connection at a time
+const READ_CHUNK: usize = 16 * 1024;
+
+/// Whether a client's `Sec-WebSocket-Extensions` offers permessage-deflate
+/// with parameters the server can honour
+pub(crate) fn offered(extensions: &str) -> bool {
+    extensions.split(',').any(|offer| {
+        let mut params = offer.split(';').map(str::trim);
+        params.next() == Some(PERMESSAGE_DEFLATE)
+            && params.all(|param| {
+                let (name, value) = match param.split_once('=') {
+                    Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                    None => (param, None),
+                };
+                match name {
+                    "server_no_context_takeover" | "client_no_context_takeover" => value.is_none(),
+                    // Messages are deflated with the largest window only
+                    "server_max_window_bits" => value == Some("15"),
+                    "client_max_window_bits" => {
+                        value.is_none_or(|bits| matches!(bits.parse(), Ok(8..=15)))
+                    }
+                    _ => false,
+                }
+            })
+    })
+}
+
+/// How the frames of a connection are compressed once the extension is on
+struct Codec {
+    threshold: usize,
+    max_size: usize,
+    /// Opcode and deflated payload so far of the message being read
+    message: Option<(OpCode, Vec<u8>)>,
+    /// The client wrote a frame that can't be inflated, so the rest is passed
+    /// on for tungstenite to fail on
+    failed: bool,
+    /// The server is writing a message in fragments, which stay as they are
+    fragmented: bool,
+}
+
+/// permessage-deflate beneath tungstenite, which doesn't do extensions
+///
+/// Bytes pass through untouched until `enable` is called after the
+/// handshake. From then on, frames the client deflated are inflated before
+/// tungstenite reads them, and messages the server writes of at least the
+/// threshold are deflated on their way out. A message that inflates to more
+/// than the maximum size becomes a frame announcing that much, so tungstenite
+/// refuses it like any other message too large, without it being inflated.
+pub(crate) struct Deflate<S> {
+    inner: S,
+    codec: Option<Codec>,
+    /// Read from the client and not decoded yet, from `read_pos`
+    read: Vec<u8>,
+    read_pos: usize,
+    /// Decoded for tungstenite and not read by it yet, from `decoded_pos`
+    decoded: Vec<u8>,
+    decoded_pos: usize,
+    /// Written by tungstenite and not encoded yet, from `written_pos`
+    written: Vec<u8>,
+    written_pos: usize,
+    /// Encoded for the client and not written yet, from `encoded_pos`
+    encoded: Vec<u8>,
+    encoded_pos: usize,
+    eof: bool,
+}
+
+impl<S> Deflate<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self {
+            inner,
+            codec: None,
+            read: Vec::new(),
+            read_pos: 0,
+            decoded: Vec::new(),
+            decoded_pos: 0,
+            written: Vec::new(),
+            written_pos: 0,
+            encoded: Vec::new(),
+            encoded_pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Starts compressing messages of at least `threshold` bytes, and
+    /// inflating those of the client up to `max_size`
+    pub(crate) fn enable(&mut self, threshold: usize, max_size: usize) {
+        self.codec = Some(Codec {
+            threshold,
+            max_size,
+            message: None,
+            failed: false,
+            fragmented: false,
+        });
+    }
+
+    /// Decodes the next whole frame read, returning whether there was one
+    fn decode(&mut self) -> bool {
+        let Some(codec) = self.codec.as_mut() else {
+            return false;
+        };
+        let pending = &self.read[self.read_pos..];
+        if pending.is_empty() {
+            return false;
+        }
+        if codec.failed {
+            self.decoded.extend_from_slice(pending);
+            self.read_pos = self.read.len();
+            return true;
+        }
+
+        let mut cursor = Cursor::new(pending);
+        let (header, length) = match FrameHeader::parse(&mut cursor) {
+            Ok(Some(parsed)) => parsed,
+            Ok(None) => return false,
+            Err(_) => {
+                codec.failed = true;
+                return self.decode();
+            }
+        };
+        if length > codec.max_size as u64 {
+            // tungstenite refuses the frame from its header alone
+            codec.failed = true;
+            return self.decode();
+        }
+        let start = cursor.position() as usize;
+        let end = start + length as usize;
+        if pending.len() < end {
+            return false;
+        }
+
+        let payload = unmask(&pending[start..end], header.mask);
+        match (header.opcode, header.rsv1, codec.message.as_mut()) {
+            (OpCode::Data(Data::Text | Data::Binary), true, None) => {
+                codec.message = Some((header.opcode, payload));
+            }
+            (OpCode::Data(Data::Continue), false, Some((_, deflated))) => {
+                deflated.extend_from_slice(&payload);
+            }
+            _ => self.decoded.extend_from_slice(&pending[..end]),
+        }
+        self.read_pos += end;
+        if self.read_pos == self.read.len() {
+            self.read.clear();
+            self.read_pos = 0;
+        }
+
+        let oversized = codec
+            .message
+            .as_ref()
+            .is_some_and(|(_, deflated)| deflated.len() > codec.max_size);
+        if oversized || (header.is_final && codec.message.is_some()) {
+            let (opcode, deflated) = codec.message.take().unwrap_or((header.opcode, Vec::new()));
+            let inflated = if oversized {
+                Inflated::TooLarge
+            } else {
+                inflate(deflated, codec.max_size)
+            };
+            let mut header = FrameHeader {
+                opcode,
+                // Frames from clients must be masked, and a zero mask keeps the payload as it is
+                mask: Some([0; 4]),
+                ..FrameHeader::default()
+            };
+            let _ = match inflated {
+                Inflated::Message(message) => header
+                    .format(message.len() as u64, &mut self.decoded)
+                    .map(|_| self.decoded.extend_from_slice(&message)),
+                Inflated::TooLarge => {
+                    codec.failed = true;
+                    header.format(codec.max_size as u64 + 1, &mut self.decoded)
+                }
+                Inflated::Corrupt => {
+                    codec.failed = true;
+                    header.rsv1 = true;
+                    header.format(0, &mut self.decoded)
+                }
+            };
+        }
+        true
+    }
+
+    /// Encodes the whole frames written so far
+    fn encode(&mut self) {
+        let Some(codec) = self.codec.as_mut() else {
+            return;
+        };
+        loop {
+            let pending = &self.written[self.written_pos..];
+            let mut cursor = Cursor::new(pending);
+            let (mut header, length) = match FrameHeader::parse(&mut cursor) {
+                Ok(Some(parsed)) => parsed,
+                Ok(None) => break,
+                Err(_) => {
+                    self.encoded.extend_from_slice(pending);
+                    self.written_pos = self.written.len();
+                    break;
+                }
+            };
+            let start = cursor.position() as usize;
+            let end = start + length as usize;
+            if pending.len() < end {
+                break;
+            }
+
+            let payload = &pending[start..end];
+            let whole = matches!(header.opcode, OpCode::Data(Data::Text | Data::Binary))
+                && header.is_final
+                && !codec.fragmented;
+            let deflated = (whole && !header.rsv1 && payload.len() >= codec.threshold)
+                .then(|| deflate(payload))
+                .filter(|deflated| deflated.len() < payload.len());
+            match deflated {
+                Some(deflated) => {
+                    header.rsv1 = true;
+                    let _ = header.format(deflated.len() as u64, &mut self.encoded);
+                    self.encoded.extend_from_slice(&deflated);
+                }
+                None => self.encoded.extend_from_slice(&pending[..end]),
+            }
+            match header.opcode {
+                OpCode::Data(Data::Text | Data::Binary) => codec.fragmented = !header.is_final,
+                OpCode::Data(Data::Continue) if header.is_final => codec.fragmented = false,
+                _ => {}
+            }
+            self.written_pos += end;
+        }
+        if self.written_pos == self.written.len() {
+            self.written.clear();
+            self.written_pos = 0;
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> Deflate<S> {
+    /// Writes out everything encoded so far
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.encoded_pos < self.encoded.len() {
+            let pending = &self.encoded[self.encoded_pos..];
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.encoded_pos += written;
+        }
+        self.encoded.clear();
+        self.encoded_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Deflate<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.codec.is_none() {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+        loop {
+            let decoded = &this.decoded[this.decoded_pos..];
+            if !decoded.is_empty() {
+                let len = decoded.len().min(buf.remaining());
+                buf.put_slice(&decoded[..len]);
+                this.decoded_pos += len;
+                if this.decoded_pos == this.decoded.len() {
+                    this.decoded.clear();
+                    this.decoded_pos = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+            if this.decode() {
+                continue;
+            }
+            if this.eof {
+                // A frame cut short is passed on for tungstenite to notice
+                this.decoded.extend_from_slice(&this.read[this.read_pos..]);
+                this.read.clear();
+                this.read_pos = 0;
+                if this.decoded.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                continue;
+            }
+
+            let mut chunk = [0; READ_CHUNK];
+            let mut chunk = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk))?;
+            if chunk.filled().is_empty() {
+                this.eof = true;
+            }
+            this.read.extend_from_slice(chunk.filled());
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Deflate<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.codec.is_none() {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+        ready!(this.poll_drain(cx))?;
+        this.written.extend_from_slice(buf);
+        this.encode();
+        // Flushing finishes what can't be written now
+        if let Poll::Ready(Err(e)) = this.poll_drain(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+fn unmask(payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    match mask {
+        Some(mask) => payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4])
+            .collect(),
+        None => payload.to_vec(),
+    }
+}
+
+/// A message as the client deflated it, without the trailer
+fn deflate(payload: &[u8]) -> Vec<u8> {
+    let mut compress = Compress::new(Compression::default(), false);
+    let mut output = Vec::with_capacity(payload.len() / 2 + 64);
+    loop {
+        let input = &payload[compress.total_in() as usize..];
+        if compress
+            .compress_vec(input, &mut output, FlushCompress::Sync)
+            .is_err()
+        {
+            // Sent as it is instead, since it's no shorter
+            return payload.to_vec();
+        }
+        if compress.total_in() as usize == payload.len() && output.len() < output.capacity() {
+            break;
+        }
+        output.reserve(output.capacity());
+    }
+    if output.ends_with(&TRAILER) {
+        output.truncate(output.len() - TRAILER.len());
+    }
+    output
+}
+
+enum Inflated {
+    Message(Vec<u8>),
+    TooLarge,
+    Corrupt,
+}
+
+/// Inflates a message, giving up once it's longer than `max_size`
+fn inflate(mut deflated: Vec<u8>, max_size: usize) -> Inflated {
+    deflated.extend_from_slice(&TRAILER);
+    let mut decompress = Decompress::new(false);
+    let mut output = Vec::with_capacity((deflated.len() * 4).min(max_size + 1));
+    loop {
+        if output.len() > max_size {
+            return Inflated::TooLarge;
+        }
+        if output.len() == output.capacity() {
+            output.reserve(output.capacity().clamp(1024, max_size + 1));
+        }
+        let (read, wrote) = (decompress.total_in(), decompress.total_out());
+        let input = &deflated[read as usize..];
+        match decompress.decompress_vec(input, &mut output, FlushDecompress::Sync) {
+            Ok(Status::StreamEnd) => break,
+            Ok(_)
+                if decompress.total_in() as usize == deflated.len()
+                    && output.len() < output.capacity() =>
+            {
+                break
+            }
+            Ok(_) if decompress.total_in() == read && decompress.total_out() == wrote => {
+                return Inflated::Corrupt
+            }
+            Ok(_) => {}
+            Err(_) => return Inflated::Corrupt,
+        }
+    }
+    if output.len() > max_size {
+        return Inflated::TooLarge;
+    }
+    Inflated::Message(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_tungstenite::tungstenite::protocol::{Role, WebSocketConfig};
+    use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+    use tokio_tungstenite::WebSocketStream;
+
+    /// A text frame as a client writes it, deflated if `deflated`
+    fn client_frame(text: &str, deflated: bool) -> Vec<u8> {
+        let payload = if deflated {
+            deflate(text.as_bytes())
+        } else {
+            text.as_bytes().to_vec()
+        };
+        let mask = [1, 2, 3, 4];
+        let header = FrameHeader {
+            rsv1: deflated,
+            opcode: OpCode::Data(Data::Text),
+            mask: Some(mask),
+            ..FrameHeader::default()
+        };
+        let mut frame = Vec::new();
+        header.format(payload.len() as u64, &mut frame).unwrap();
+        frame.extend(unmask(&payload, Some(mask)));
+        frame
+    }
+
+    #[test]
+    fn test_negotiation() {
+        assert!(offered("permessage-deflate"));
+        assert!(offered(
+            "x-webkit-deflate-frame, permessage-deflate; client_max_window_bits"
+        ));
+        assert!(offered(
+            "permessage-deflate; server_no_context_takeover; client_max_window_bits=10"
+        ));
+        assert!(!offered("permessage-deflate; server_max_window_bits=10"));
+        assert!(!offered("permessage-deflate; unknown"));
+        assert!(!offered("x-webkit-deflate-frame"));
+    }
+
+    #[tokio::test]
+    async fn test_permessage_deflate() {
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let mut server = Deflate::new(server);
+        server.enable(64, 1024);
+        let config = WebSocketConfig::default()
+            .max_message_size(Some(1024))
+            .max_frame_size(Some(1024));
+        let mut server = WebSocketStream::from_raw_socket(server, Role::Server, Some(config)).await;
+
+        // Deflated and plain messages from the client both arrive as text
+        let long = "compressible ".repeat(50);
+        client.write_all(&client_frame(&long, true)).await.unwrap();
+        client
+            .write_all(&client_frame("plain", false))
+            .await
+            .unwrap();
+        let received = server.next().await.unwrap().unwrap();
+        assert_eq!(received.to_text().unwrap(), long);
+        let received = server.next().await.unwrap().unwrap();
+        assert_eq!(received.to_text().unwrap(), "plain");
+
+        // Messages over the threshold are deflated
+        server
+            .send(Message::Text(long.clone().into()))
+            .await
+            .unwrap();
+        server.send(Message::Text("short".into())).await.unwrap();
+        let mut written = vec![0; 1024];
+        let len = client.read(&mut written).await.unwrap();
+        let mut cursor = Cursor::new(&written[..len]);
+        let (header, length) = FrameHeader::parse(&mut cursor).unwrap().unwrap();
+        assert!(header.rsv1);
+        let start = cursor.position() as usize;
+        let payload = written[start..start + length as usize].to_vec();
+        match inflate(payload, 1024) {
+            Inflated::Message(message) => assert_eq!(message, long.as_bytes()),
+            _ => panic!("Expected a deflated message"),
+        }
+        let rest = &written[start + length as usize..len];
+        let mut cursor = Cursor::new(rest);
+        let (header, _) = FrameHeader::parse(&mut cursor).unwrap().unwrap();
+        assert!(!header.rsv1);
+        assert!(rest.ends_with(b"short"));
+
+        // A message inflating past the limit is refused
+        client
+            .write_all(&client_frame(&"x".repeat(2048), true))
+            .await
+            .unwrap();
+        assert!(matches!(
+            server.next().await.unwrap(),
+            Err(WsError::Capacity(_))
+        ));
+    }
+}
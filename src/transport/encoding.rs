@@ -0,0 +1,132 @@
+use super::stdio::Frame;
+use serde_json::Value;
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt};
+use tracing::error;
+
+/// Sent first by a client asking for CBOR, and echoed by a server agreeing
+/// to it: the tag of self-described CBOR, which JSON text can't start with
+pub const CBOR_MAGIC: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+/// How JSON-RPC messages are written on a byte stream
+///
+/// JSON is what the spec asks for and what every client speaks. CBOR spares
+/// embedders exchanging large payloads the JSON text on the wire; each
+/// message is then its length as 4 big-endian bytes followed by the message
+/// encoded as CBOR.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// One JSON message per line
+    #[default]
+    Json,
+    /// Length-prefixed CBOR, once both sides agreed to it with [`CBOR_MAGIC`]
+    Cbor,
+}
+
+impl std::str::FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(encoding: &str) -> Result<Self, Self::Err> {
+        match encoding {
+            "json" => Ok(Encoding::Json),
+            "cbor" => Ok(Encoding::Cbor),
+            _ => Err(format!("Unknown encoding: {}", encoding)),
+        }
+    }
+}
+
+/// Consumes [`CBOR_MAGIC`] if the stream starts with it, returning whether it did
+pub(crate) async fn asks_for_cbor<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<bool> {
+    let buffer = reader.fill_buf().await?;
+    let len = buffer.len().min(CBOR_MAGIC.len());
+    if len == 0 || buffer[..len] != CBOR_MAGIC[..len] {
+        return Ok(false);
+    }
+    // The magic may arrive split across reads, and what starts like it isn't JSON anyway
+    let mut magic = [0; CBOR_MAGIC.len()];
+    reader.read_exact(&mut magic).await?;
+    Ok(magic == CBOR_MAGIC)
+}
+
+/// `message` as a length-prefixed CBOR frame
+pub(crate) fn to_cbor(message: &str) -> io::Result<Vec<u8>> {
+    let value: Value = serde_json::from_str(message)?;
+    let mut frame = vec![0; 4];
+    ciborium::into_writer(&value, &mut frame).map_err(io::Error::other)?;
+    let len = u32::try_from(frame.len() - 4).map_err(io::Error::other)?;
+    frame[..4].copy_from_slice(&len.to_be_bytes());
+    Ok(frame)
+}
+
+/// The JSON text of a CBOR message
+pub(crate) fn from_cbor(body: &[u8]) -> io::Result<String> {
+    let value: Value = ciborium::from_reader(body).map_err(io::Error::other)?;
+    Ok(value.to_string())
+}
+
+/// The next length-prefixed CBOR message, or `None` at the end of the stream
+///
+/// Messages longer than `max_message_size` are skipped without being kept,
+/// and those that aren't valid CBOR are skipped.
+pub(crate) async fn read_cbor<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_message_size: usize,
+) -> io::Result<Option<Frame>> {
+    loop {
+        let mut len = [0; 4];
+        match reader.read_exact(&mut len).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > max_message_size {
+            error!(
+                "Skipped a message of {} bytes, longer than {} bytes",
+                len, max_message_size
+            );
+            let mut body = reader.take(len as u64);
+            tokio::io::copy(&mut body, &mut tokio::io::sink()).await?;
+            return Ok(Some(Frame::TooLarge));
+        }
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).await?;
+        match from_cbor(&body) {
+            Ok(message) => return Ok(Some(Frame::Message(message))),
+            Err(e) => error!("Skipped a message that isn't valid CBOR: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    #[tokio::test]
+    async fn test_cbor_encoding() {
+        let message = r#"{"id":1,"jsonrpc":"2.0","result":{"data":[1,2.5,null,true]}}"#;
+        let mut stream = CBOR_MAGIC.to_vec();
+        stream.extend(to_cbor(message).unwrap());
+        stream.extend(to_cbor(&format!(r#"{{"big":"{}"}}"#, "x".repeat(64))).unwrap());
+        stream.extend(to_cbor("{}").unwrap());
+
+        let mut reader = BufReader::new(stream.as_slice());
+        assert!(asks_for_cbor(&mut reader).await.unwrap());
+        let mut frames = Vec::new();
+        while let Some(frame) = read_cbor(&mut reader, 64).await.unwrap() {
+            frames.push(match frame {
+                Frame::Message(message) => serde_json::from_str(&message).unwrap(),
+                Frame::TooLarge => Value::Null,
+            });
+        }
+        let expected: Value = serde_json::from_str(message).unwrap();
+        assert_eq!(frames, [expected, Value::Null, serde_json::json!({})]);
+
+        // JSON lines are left for the line reader
+        let mut reader = BufReader::new(&b"{\"jsonrpc\":\"2.0\"}\n"[..]);
+        assert!(!asks_for_cbor(&mut reader).await.unwrap());
+        assert_eq!(reader.fill_buf().await.unwrap()[0], b'{');
+        assert_eq!("cbor".parse(), Ok(Encoding::Cbor));
+    }
+}
@@ -0,0 +1,217 @@
+use super::{QueueMetrics, QueueReport};
+use crate::session::{SessionState, Sessions};
+use axum::{http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::debug;
+
+/// Liveness probe, answered with 200 for as long as the process runs
+pub const HEALTH_PATH: &str = "/healthz";
+
+/// Readiness probe, answered with 200 once the transport listens and 503 while it shuts down
+pub const READY_PATH: &str = "/readyz";
+
+/// Largest probe request read before it's answered
+const MAX_PROBE_REQUEST: usize = 8 * 1024;
+
+/// Status of a network transport, served on its health endpoints
+#[derive(Clone, Default)]
+pub struct Health {
+    inner: Arc<HealthState>,
+}
+
+#[derive(Default)]
+struct HealthState {
+    listening: AtomicBool,
+    shutting_down: AtomicBool,
+    sessions: OnceLock<Sessions>,
+    queues: OnceLock<QueueMetrics>,
+}
+
+/// Body of the health endpoints
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    pub status: &'static str,
+    pub transport: &'static str,
+    pub listening: bool,
+    pub shutting_down: bool,
+    /// Sessions the server knows of
+    pub sessions: usize,
+    /// Sessions that finished the initialization handshake
+    pub initialized_sessions: usize,
+    /// How full the server's queues are
+    pub queues: QueueReport,
+}
+
+impl Health {
+    /// Lets the report count the server's sessions
+    pub(crate) fn attach(&self, sessions: Sessions) {
+        let _ = self.inner.sessions.set(sessions);
+    }
+
+    /// Lets the report show the depths of the server's queues
+    pub(crate) fn attach_queues(&self, metrics: QueueMetrics) {
+        let _ = self.inner.queues.set(metrics);
+    }
+
+    pub(crate) fn set_listening(&self) {
+        self.inner.listening.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_shutting_down(&self) {
+        self.inner.shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the transport accepts new clients
+    pub fn is_ready(&self) -> bool {
+        self.inner.listening.load(Ordering::Relaxed)
+            && !self.inner.shutting_down.load(Ordering::Relaxed)
+    }
+
+    pub fn report(&self, transport: &'static str) -> HealthReport {
+        let (sessions, initialized_sessions) = match self.inner.sessions.get() {
+            Some(sessions) => (sessions.len(), sessions.count(SessionState::Ready)),
+            None => (0, 0),
+        };
+        HealthReport {
+            status: if self.is_ready() { "ok" } else { "unavailable" },
+            transport,
+            listening: self.inner.listening.load(Ordering::Relaxed),
+            shutting_down: self.inner.shutting_down.load(Ordering::Relaxed),
+            sessions,
+            initialized_sessions,
+            queues: self
+                .inner
+                .queues
+                .get()
+                .map(QueueMetrics::report)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Status code and report for a probe of `path`, if it's a health endpoint
+    fn probe(&self, path: &str, transport: &'static str) -> Option<(StatusCode, HealthReport)> {
+        let status = match path {
+            HEALTH_PATH => StatusCode::OK,
+            READY_PATH if self.is_ready() => StatusCode::OK,
+            READY_PATH => StatusCode::SERVICE_UNAVAILABLE,
+            _ => return None,
+        };
+        Some((status, self.report(transport)))
+    }
+}
+
+/// Routes serving the health endpoints of an axum transport
+pub(crate) fn routes(health: Health, transport: &'static str) -> Router {
+    let probe = |path: &'static str| {
+        let health = health.clone();
+        get(move || {
+            let health = health.clone();
+            async move {
+                match health.probe(path, transport) {
+                    Some((status, report)) => (status, Json(report)).into_response(),
+                    None => StatusCode::NOT_FOUND.into_response(),
+                }
+            }
+        })
+    };
+    Router::new()
+        .route(HEALTH_PATH, probe(HEALTH_PATH))
+        .route(READY_PATH, probe(READY_PATH))
+}
+
+/// The health endpoint a raw connection asks for, judged from the first bytes it sent
+///
+/// Lets a transport that doesn't speak HTTP itself answer probes on its port.
+pub(crate) async fn probed_path(stream: &TcpStream) -> Option<&'static str> {
+    let mut start = [0u8; 16];
+    let read = stream.peek(&mut start).await.ok()?;
+    let start = &start[..read];
+    [HEALTH_PATH, READY_PATH].into_iter().find(|path| {
+        start
+            .strip_prefix(b"GET ")
+            .and_then(|rest| rest.strip_prefix(path.as_bytes()))
+            .is_some_and(|rest| matches!(rest.first(), Some(b' ' | b'?')))
+    })
+}
+
+/// Answers a probe of `path` on a raw connection and closes it
+pub(crate) async fn answer_probe(
+    mut stream: TcpStream,
+    path: &str,
+    health: &Health,
+    transport: &'static str,
+) {
+    // Read the whole request so closing the connection doesn't reset it
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_PROBE_REQUEST {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => request.extend_from_slice(&buf[..read]),
+        }
+    }
+
+    let Some((status, report)) = health.probe(path, transport) else {
+        return;
+    };
+    let body = serde_json::to_string(&report).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        debug!("Failed to answer health probe: {}", e);
+    }
+    let _ = stream.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::ConnectionId;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_health_routes() {
+        let health = Health::default();
+        let sessions = Sessions::default();
+        health.attach(sessions.clone());
+        sessions.update(&ConnectionId::from("a"), |session| {
+            session.state = SessionState::Ready
+        });
+        sessions.update(&ConnectionId::from("b"), |_| {});
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let router = routes(health.clone(), "http");
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let response = reqwest::get(format!("{}/readyz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+        health.set_listening();
+        let response = reqwest::get(format!("{}/readyz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let report: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(report["status"], "ok");
+        assert_eq!(report["transport"], "http");
+        assert_eq!(report["sessions"], 2);
+        assert_eq!(report["initializedSessions"], 1);
+        assert_eq!(report["queues"]["inbound"]["depth"], 0);
+
+        health.set_shutting_down();
+        let response = reqwest::get(format!("{}/readyz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let response = reqwest::get(format!("{}/healthz", url)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let report: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(report["shuttingDown"], true);
+    }
+}
@@ -0,0 +1,943 @@
+use super::auth::{self, AuthConfig, Authenticator};
+use super::health::{self, Health};
+use super::limit::{self, DEFAULT_MAX_MESSAGE_SIZE};
+use super::origin::{self, OriginPolicy};
+use super::replay::{Replay, DEFAULT_REPLAY_CAPACITY};
+use super::{ConnectionEvent, ConnectionId, Transport, TransportError, TransportMessage};
+use axum::{
+    body::HttpBody,
+    extract::{rejection::StringRejection, DefaultBodyLimit, State},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+    },
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Router,
+};
+use futures::StreamExt;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::{
+    net::TcpListener,
+    sync::{
+        broadcast,
+        mpsc::{self, error::TrySendError},
+        oneshot, Mutex,
+    },
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tower_http::compression::predicate::{NotForContentType, Predicate};
+use tower_http::compression::CompressionLayer;
+use tracing::{debug, error, warn};
+
+/// Header carrying the session ID assigned during initialization
+pub const SESSION_ID_HEADER: HeaderName = HeaderName::from_static("mcp-session-id");
+
+/// Header a client reopening an event stream names the last event it received with
+pub(crate) const LAST_EVENT_ID_HEADER: HeaderName = HeaderName::from_static("last-event-id");
+
+/// Events queued for an event stream before its client counts as stalled
+const EVENT_STREAM_CAPACITY: usize = 32;
+
+/// Connection events kept for subscribers that fall behind
+const EVENTS_CAPACITY: usize = 256;
+
+#[derive(Default)]
+struct HttpShared {
+    /// Sessions handed out by `initialize` and not yet terminated
+    sessions: Mutex<HashSet<ConnectionId>>,
+    /// POSTs waiting for the response to their (first) request, keyed by JSON-RPC id
+    pending: Mutex<HashMap<(ConnectionId, String), oneshot::Sender<String>>>,
+    /// Streams opened with GET, used for messages that don't answer a POST
+    streams: Mutex<HashMap<ConnectionId, mpsc::Sender<Event>>>,
+}
+
+#[derive(Clone)]
+struct HttpState {
+    shared: Arc<HttpShared>,
+    sessions: bool,
+    replay: Replay,
+    max_message_size: usize,
+    events: broadcast::Sender<ConnectionEvent>,
+    request_tx: mpsc::Sender<TransportMessage>,
+}
+
+/// MCP Streamable HTTP transport.
+///
+/// A single endpoint accepts JSON-RPC messages by POST and answers each
+/// request either as `application/json` or, when the client accepts it, as a
+/// `text/event-stream`. A GET on the same endpoint opens a stream for server
+/// messages that aren't responses. Each session is one connection, closed
+/// when the client deletes it; without sessions every POST and GET is a
+/// connection of its own, and a POST's ends once it's answered. Responses
+/// are correlated to their POST by JSON-RPC id.
+///
+/// Messages on a session's GET stream carry event IDs, and the latest are
+/// kept so a client reopening the stream with `Last-Event-ID` gets the ones
+/// it missed, including responses whose POST went away.
+#[derive(Clone)]
+pub struct HttpTransport {
+    addr: String,
+    path: String,
+    sessions: bool,
+    replay: Replay,
+    max_message_size: usize,
+    /// Responses at least this long are compressed for clients that accept it
+    compression: Option<usize>,
+    shared: Arc<HttpShared>,
+    events: broadcast::Sender<ConnectionEvent>,
+    auth: Option<Arc<Authenticator>>,
+    origins: Arc<OriginPolicy>,
+    health: Health,
+}
+
+impl HttpTransport {
+    pub fn new(addr: String, path: String) -> Self {
+        Self {
+            addr,
+            path,
+            sessions: true,
+            replay: Replay::new(DEFAULT_REPLAY_CAPACITY),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            compression: None,
+            shared: Arc::new(HttpShared::default()),
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+            auth: None,
+            origins: Arc::new(OriginPolicy::default()),
+            health: Health::default(),
+        }
+    }
+
+    /// Requires OAuth bearer tokens on every request
+    pub fn with_auth(mut self, config: AuthConfig) -> Self {
+        self.auth = Some(Arc::new(Authenticator::new(config)));
+        self
+    }
+
+    /// Sets which web pages may send requests, allowing localhost by default
+    pub fn with_origins(mut self, origins: OriginPolicy) -> Self {
+        self.origins = Arc::new(origins);
+        self
+    }
+
+    /// Keeps the latest `capacity` messages of each session's event stream
+    /// for replay, or none if 0
+    pub fn with_replay_capacity(mut self, capacity: usize) -> Self {
+        self.replay = Replay::new(capacity);
+        self
+    }
+
+    /// Refuses bodies longer than `bytes` with a
+    /// [`MESSAGE_TOO_LARGE`](super::MESSAGE_TOO_LARGE) error
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Compresses responses of at least `threshold` bytes with gzip or
+    /// Brotli, for clients whose `Accept-Encoding` takes either
+    ///
+    /// Event streams are left alone, so their events aren't held back.
+    pub fn with_compression(mut self, threshold: usize) -> Self {
+        self.compression = Some(threshold);
+        self
+    }
+
+    /// Disables `Mcp-Session-Id` assignment and validation
+    pub fn stateless(mut self) -> Self {
+        self.sessions = false;
+        self
+    }
+
+    fn router(&self, request_tx: mpsc::Sender<TransportMessage>) -> Router {
+        let state = HttpState {
+            shared: self.shared.clone(),
+            sessions: self.sessions,
+            replay: self.replay.clone(),
+            max_message_size: self.max_message_size,
+            events: self.events.clone(),
+            request_tx,
+        };
+
+        let router = Router::new()
+            .route(
+                &self.path,
+                get(handle_get).post(handle_post).delete(handle_delete),
+            )
+            .layer(DefaultBodyLimit::max(self.max_message_size))
+            .with_state(state);
+        let router = match self.compression {
+            Some(threshold) => router.layer(
+                CompressionLayer::new()
+                    .compress_when(AtLeast(threshold).and(NotForContentType::SSE)),
+            ),
+            None => router,
+        };
+        let router = match &self.auth {
+            Some(authenticator) => auth::protect(router, authenticator.clone()),
+            None => router,
+        };
+        let router = router.merge(health::routes(self.health.clone(), "http"));
+        origin::apply(router, self.origins.clone())
+    }
+}
+
+impl HttpState {
+    /// Returns the caller's session, or the error response to send back
+    async fn check_session(&self, headers: &HeaderMap) -> Result<Option<ConnectionId>, Response> {
+        if !self.sessions {
+            return Ok(None);
+        }
+
+        let session_id = headers
+            .get(SESSION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(ConnectionId::from)
+            .ok_or_else(|| {
+                jsonrpc_error(
+                    StatusCode::BAD_REQUEST,
+                    -32000,
+                    "Bad Request: No valid session ID provided",
+                )
+            })?;
+
+        if !self.shared.sessions.lock().await.contains(&session_id) {
+            return Err(jsonrpc_error(
+                StatusCode::NOT_FOUND,
+                -32001,
+                "Session not found",
+            ));
+        }
+
+        Ok(Some(session_id))
+    }
+}
+
+fn jsonrpc_error(status: StatusCode, code: i64, message: &str) -> Response {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": { "code": code, "message": message },
+        "id": null,
+    });
+    (
+        status,
+        [(CONTENT_TYPE, "application/json")],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+/// Compresses responses whose length is known to be at least this many bytes
+#[derive(Clone, Copy)]
+struct AtLeast(usize);
+
+impl Predicate for AtLeast {
+    fn should_compress<B: HttpBody>(&self, response: &axum::http::Response<B>) -> bool {
+        response
+            .body()
+            .size_hint()
+            .exact()
+            .is_some_and(|size| size >= self.0 as u64)
+    }
+}
+
+/// The response to a body that couldn't be read, a JSON-RPC error if it was too long
+pub(super) fn body_rejection(rejection: StringRejection, limit: usize) -> Response {
+    if rejection.status() != StatusCode::PAYLOAD_TOO_LARGE {
+        return rejection.into_response();
+    }
+    error!("Refused a message longer than {} bytes", limit);
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        [(CONTENT_TYPE, "application/json")],
+        limit::too_large(limit),
+    )
+        .into_response()
+}
+
+/// Queues `event` on the stream of `connection` without waiting, returning
+/// false if the stream closed or its client stalled and it must be dropped
+///
+/// Streams are sent to under a lock, so one stalled client can't hold up the
+/// others. A dropped client gets what it missed by reopening its stream
+/// with `Last-Event-ID`.
+pub(super) fn queue_event(
+    connection: &ConnectionId,
+    sender: &mpsc::Sender<Event>,
+    event: Event,
+) -> bool {
+    match sender.try_send(event) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            warn!("Dropped the event stream of {}, which stalled", connection);
+            false
+        }
+        Err(TrySendError::Closed(_)) => {
+            debug!("Event stream closed: {}", connection);
+            false
+        }
+    }
+}
+
+/// A server message on an event stream, with its event ID if it's kept for replay
+fn message_event(id: Option<u64>, message: String) -> Event {
+    let event = Event::default().event("message").data(message);
+    match id {
+        Some(id) => event.id(id.to_string()),
+        None => event,
+    }
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"))
+}
+
+fn messages(payload: &Value) -> Vec<&Value> {
+    match payload {
+        Value::Array(batch) => batch.iter().collect(),
+        message => vec![message],
+    }
+}
+
+/// Ids of the requests (messages with both `method` and `id`) in a payload
+fn request_ids(payload: &Value) -> Vec<String> {
+    messages(payload)
+        .into_iter()
+        .filter(|m| m.get("method").is_some())
+        .filter_map(|m| m.get("id"))
+        .map(|id| id.to_string())
+        .collect()
+}
+
+fn is_initialize(payload: &Value) -> bool {
+    messages(payload)
+        .into_iter()
+        .any(|m| m.get("method").and_then(Value::as_str) == Some("initialize"))
+}
+
+/// Id a response payload answers; batches are keyed by their first response
+fn response_id(response: &str) -> Option<String> {
+    let payload: Value = serde_json::from_str(response).ok()?;
+    let first = messages(&payload).into_iter().next()?;
+    if first.get("method").is_some() {
+        return None;
+    }
+    first.get("id").map(|id| id.to_string())
+}
+
+async fn handle_post(
+    State(state): State<HttpState>,
+    headers: HeaderMap,
+    body: Result<String, StringRejection>,
+) -> Response {
+    let body = match body {
+        Ok(body) => body,
+        Err(rejection) => return body_rejection(rejection, state.max_message_size),
+    };
+    let payload: Value = match serde_json::from_str(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return jsonrpc_error(
+                StatusCode::BAD_REQUEST,
+                -32700,
+                &format!("Parse error: {}", e),
+            )
+        }
+    };
+
+    let new_session = state.sessions && is_initialize(&payload);
+    let connection = if new_session {
+        let session_id = ConnectionId::new();
+        state
+            .shared
+            .sessions
+            .lock()
+            .await
+            .insert(session_id.clone());
+        state.replay.open(&session_id);
+        debug!("New HTTP session: {}", session_id);
+        let _ = state
+            .events
+            .send(ConnectionEvent::Opened(session_id.clone()));
+        session_id
+    } else {
+        match state.check_session(&headers).await {
+            Ok(Some(session_id)) => session_id,
+            Ok(None) => {
+                let connection = ConnectionId::new();
+                let _ = state
+                    .events
+                    .send(ConnectionEvent::Opened(connection.clone()));
+                connection
+            }
+            Err(response) => return response,
+        }
+    };
+
+    let receiver = match request_ids(&payload).into_iter().next() {
+        Some(id) => {
+            let (sender, receiver) = oneshot::channel();
+            state
+                .shared
+                .pending
+                .lock()
+                .await
+                .insert((connection.clone(), id), sender);
+            Some(receiver)
+        }
+        None => None,
+    };
+
+    debug!("Received [http {}]: {}", connection, body);
+    let message = TransportMessage {
+        connection: connection.clone(),
+        message: body,
+    };
+    if state.request_tx.send(message).await.is_err() {
+        error!("Failed to send request through channel");
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    // Without sessions, a POST of notifications has nothing more coming;
+    // one with a request ends once it's answered
+    if !state.sessions && receiver.is_none() {
+        let _ = state
+            .events
+            .send(ConnectionEvent::Closed(connection.clone()));
+    }
+
+    let mut response = match receiver {
+        None => StatusCode::ACCEPTED.into_response(),
+        Some(receiver) if accepts_event_stream(&headers) => {
+            let stream = futures::stream::once(receiver).filter_map(|response| async move {
+                response
+                    .ok()
+                    .map(|data| Ok::<_, Infallible>(Event::default().event("message").data(data)))
+            });
+            Sse::new(stream).into_response()
+        }
+        Some(receiver) => match receiver.await {
+            Ok(data) => ([(CONTENT_TYPE, "application/json")], data).into_response(),
+            Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        },
+    };
+
+    if new_session {
+        if let Ok(value) = HeaderValue::from_str(connection.as_str()) {
+            response.headers_mut().insert(SESSION_ID_HEADER, value);
+        }
+    }
+    response
+}
+
+async fn handle_get(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    if !accepts_event_stream(&headers) {
+        return StatusCode::NOT_ACCEPTABLE.into_response();
+    }
+    let connection = match state.check_session(&headers).await {
+        Ok(session_id) => session_id.unwrap_or_default(),
+        Err(response) => return response,
+    };
+
+    // Recording and sending happen under the streams lock, so nothing
+    // recorded after the replay is missed by the new stream
+    let mut streams = state.shared.streams.lock().await;
+    let missed = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|id| id.parse().ok())
+        .map(|last| state.replay.since(&connection, last))
+        .unwrap_or_default();
+    let (sender, receiver) = mpsc::channel(EVENT_STREAM_CAPACITY + missed.len());
+    if !missed.is_empty() {
+        debug!("Replaying {} events to {}", missed.len(), connection);
+    }
+    for (id, message) in missed {
+        // The channel has room for every missed event
+        let _ = sender.try_send(message_event(Some(id), message));
+    }
+    debug!("New HTTP event stream: {}", connection);
+    streams.insert(connection, sender);
+    drop(streams);
+
+    Sse::new(ReceiverStream::new(receiver).map(Ok::<_, Infallible>))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+async fn handle_delete(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    if !state.sessions {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+
+    match state.check_session(&headers).await {
+        Ok(Some(session_id)) => {
+            state.shared.sessions.lock().await.remove(&session_id);
+            state.shared.streams.lock().await.remove(&session_id);
+            state.replay.close(&session_id);
+            debug!("Terminated HTTP session: {}", session_id);
+            let _ = state.events.send(ConnectionEvent::Closed(session_id));
+            StatusCode::OK.into_response()
+        }
+        Ok(None) => StatusCode::BAD_REQUEST.into_response(),
+        Err(response) => response,
+    }
+}
+
+impl Transport for HttpTransport {
+    fn start(
+        &mut self,
+        request_tx: mpsc::Sender<TransportMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let addr = self.addr.clone();
+        let path = self.path.clone();
+        let router = self.router(request_tx);
+        let health = self.health.clone();
+
+        Box::pin(async move {
+            let listener = TcpListener::bind(&addr)
+                .await
+                .map_err(TransportError::bind(&addr))?;
+            debug!("HTTP server listening on: {}{}", addr, path);
+            health.set_listening();
+
+            axum::serve(listener, router).await?;
+            Ok(())
+        })
+    }
+
+    fn send_response(
+        &mut self,
+        connection: ConnectionId,
+        response: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let shared = self.shared.clone();
+        let replay = self.replay.clone();
+        let sessions = self.sessions;
+        let events = self.events.clone();
+        Box::pin(async move {
+            if response.is_empty() {
+                return Ok(());
+            }
+
+            debug!("Sending [http {}]: {}", connection, response);
+            let mut response = response;
+            if let Some(id) = response_id(&response) {
+                let key = (connection.clone(), id);
+                if let Some(sender) = shared.pending.lock().await.remove(&key) {
+                    if !sessions {
+                        let _ = events.send(ConnectionEvent::Closed(connection.clone()));
+                    }
+                    match sender.send(response) {
+                        Ok(()) => return Ok(()),
+                        // Kept on the event stream, where a client resuming it still gets it
+                        Err(unsent) => {
+                            debug!("HTTP client went away before its response");
+                            response = unsent;
+                        }
+                    }
+                }
+            }
+
+            let mut streams = shared.streams.lock().await;
+            let id = replay.record(&connection, &response);
+            if let Some(sender) = streams.get(&connection) {
+                if !queue_event(&connection, sender, message_event(id, response)) {
+                    streams.remove(&connection);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn broadcast(
+        &mut self,
+        message: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let shared = self.shared.clone();
+        let replay = self.replay.clone();
+        Box::pin(async move {
+            if !message.is_empty() {
+                let mut streams = shared.streams.lock().await;
+                debug!("Broadcasting [http] to {}: {}", streams.len(), message);
+                streams.retain(|connection, sender| {
+                    let id = replay.record(connection, &message);
+                    queue_event(connection, sender, message_event(id, message.clone()))
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn is_stateless(&self) -> bool {
+        !self.sessions
+    }
+
+    fn health(&self) -> Option<Health> {
+        Some(self.health.clone())
+    }
+
+    fn connection_events(&self) -> Option<broadcast::Receiver<ConnectionEvent>> {
+        Some(self.events.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INITIALIZE: &str = r#"{"jsonrpc":"2.0","method":"initialize","id":1}"#;
+
+    async fn serve(transport: &HttpTransport) -> (String, mpsc::Receiver<TransportMessage>) {
+        let (request_tx, request_rx) = mpsc::channel(32);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let router = transport.router(request_tx);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        (url, request_rx)
+    }
+
+    #[tokio::test]
+    async fn test_http_session_lifecycle() {
+        let mut transport = HttpTransport::new(String::new(), "/mcp".to_string());
+        let (url, mut request_rx) = serve(&transport).await;
+        let client = reqwest::Client::new();
+
+        let post = tokio::spawn({
+            let client = client.clone();
+            let url = url.clone();
+            async move { client.post(url).body(INITIALIZE).send().await.unwrap() }
+        });
+        let request = request_rx.recv().await.unwrap();
+        assert!(request.message.contains("initialize"));
+        transport
+            .send_response(
+                request.connection,
+                r#"{"jsonrpc":"2.0","result":{},"id":1}"#.to_string(),
+            )
+            .await
+            .unwrap();
+
+        let response = post.await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let session_id = response
+            .headers()
+            .get("mcp-session-id")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            response.text().await.unwrap(),
+            r#"{"jsonrpc":"2.0","result":{},"id":1}"#
+        );
+
+        // Notifications are accepted without a body
+        let response = client
+            .post(&url)
+            .header("mcp-session-id", &session_id)
+            .body(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+
+        // Requests without a session are rejected
+        let response = client
+            .post(&url)
+            .body(r#"{"jsonrpc":"2.0","method":"ping","id":2}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        // Terminated sessions are gone
+        let response = client
+            .delete(&url)
+            .header("mcp-session-id", &session_id)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let response = client
+            .post(&url)
+            .header("mcp-session-id", &session_id)
+            .body(r#"{"jsonrpc":"2.0","method":"ping","id":3}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_http_message_size_limit() {
+        let transport = HttpTransport::new(String::new(), "/mcp".to_string())
+            .stateless()
+            .with_max_message_size(64);
+        let (url, _request_rx) = serve(&transport).await;
+
+        let response = reqwest::Client::new()
+            .post(url)
+            .body(format!(
+                r#"{{"jsonrpc":"2.0","method":"ping","id":"{}"}}"#,
+                "x".repeat(64)
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+        let error: Value = response.json().await.unwrap();
+        assert_eq!(error["error"]["code"], super::super::MESSAGE_TOO_LARGE);
+        assert_eq!(error["error"]["data"]["limit"], 64);
+    }
+
+    #[tokio::test]
+    async fn test_http_compression() {
+        let mut transport = HttpTransport::new(String::new(), "/mcp".to_string())
+            .stateless()
+            .with_compression(64);
+        let (url, mut request_rx) = serve(&transport).await;
+
+        let long = format!(
+            r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#,
+            "a".repeat(256)
+        );
+        let short = r#"{"jsonrpc":"2.0","result":{},"id":1}"#.to_string();
+        for (response, compressed) in [(long, true), (short, false)] {
+            let post = tokio::spawn({
+                let url = url.clone();
+                async move {
+                    reqwest::Client::new()
+                        .post(url)
+                        .header("accept-encoding", "gzip")
+                        .body(r#"{"jsonrpc":"2.0","method":"ping","id":1}"#)
+                        .send()
+                        .await
+                        .unwrap()
+                }
+            });
+            let request = request_rx.recv().await.unwrap();
+            transport
+                .send_response(request.connection, response)
+                .await
+                .unwrap();
+            let encoding = post
+                .await
+                .unwrap()
+                .headers()
+                .get("content-encoding")
+                .cloned();
+            assert_eq!(encoding.is_some_and(|e| e == "gzip"), compressed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_event_stream_response() {
+        let mut transport = HttpTransport::new(String::new(), "/mcp".to_string()).stateless();
+        let (url, mut request_rx) = serve(&transport).await;
+
+        let post = tokio::spawn(async move {
+            reqwest::Client::new()
+                .post(url)
+                .header("accept", "application/json, text/event-stream")
+                .body(r#"{"jsonrpc":"2.0","method":"ping","id":"abc"}"#)
+                .send()
+                .await
+                .unwrap()
+        });
+        let request = request_rx.recv().await.unwrap();
+        transport
+            .send_response(
+                request.connection,
+                r#"{"jsonrpc":"2.0","result":{},"id":"abc"}"#.to_string(),
+            )
+            .await
+            .unwrap();
+
+        let response = post.await.unwrap();
+        assert!(response.headers().get("mcp-session-id").is_none());
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/event-stream"
+        );
+        let body = response.text().await.unwrap();
+        assert!(body.contains("event: message"));
+        assert!(body.contains(r#""id":"abc""#));
+    }
+
+    #[tokio::test]
+    async fn test_http_event_replay() {
+        let mut transport = HttpTransport::new(String::new(), "/mcp".to_string());
+        let (url, mut request_rx) = serve(&transport).await;
+        let client = reqwest::Client::new();
+
+        let post = tokio::spawn({
+            let client = client.clone();
+            let url = url.clone();
+            async move { client.post(url).body(INITIALIZE).send().await.unwrap() }
+        });
+        let connection = request_rx.recv().await.unwrap().connection;
+        transport
+            .send_response(
+                connection.clone(),
+                r#"{"jsonrpc":"2.0","result":{},"id":1}"#.to_string(),
+            )
+            .await
+            .unwrap();
+        let session_id = post.await.unwrap().headers()["mcp-session-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let mut stream = client
+            .get(&url)
+            .header("accept", "text/event-stream")
+            .header("mcp-session-id", &session_id)
+            .send()
+            .await
+            .unwrap();
+        transport
+            .send_response(connection.clone(), "first".to_string())
+            .await
+            .unwrap();
+        let chunk = stream.chunk().await.unwrap().unwrap();
+        let chunk = String::from_utf8_lossy(&chunk).to_string();
+        assert!(chunk.contains("id: 0"));
+        assert!(chunk.contains("data: first"));
+
+        // What's sent while the stream is down is replayed when it reopens
+        drop(stream);
+        transport
+            .send_response(connection.clone(), "second".to_string())
+            .await
+            .unwrap();
+        let mut stream = client
+            .get(&url)
+            .header("accept", "text/event-stream")
+            .header("mcp-session-id", &session_id)
+            .header("last-event-id", "0")
+            .send()
+            .await
+            .unwrap();
+        let chunk = stream.chunk().await.unwrap().unwrap();
+        let chunk = String::from_utf8_lossy(&chunk).to_string();
+        assert!(chunk.contains("id: 1"));
+        assert!(chunk.contains("data: second"));
+        assert!(!chunk.contains("first"));
+    }
+
+    #[tokio::test]
+    async fn test_http_connection_events() {
+        let mut transport = HttpTransport::new(String::new(), "/mcp".to_string());
+        let mut events = transport.connection_events().unwrap();
+        let (url, mut request_rx) = serve(&transport).await;
+        let client = reqwest::Client::new();
+
+        // A session is a connection from initialization until it's deleted
+        let post = tokio::spawn({
+            let client = client.clone();
+            let url = url.clone();
+            async move { client.post(url).body(INITIALIZE).send().await.unwrap() }
+        });
+        let connection = request_rx.recv().await.unwrap().connection;
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Opened(connection.clone())
+        );
+        transport
+            .send_response(
+                connection.clone(),
+                r#"{"jsonrpc":"2.0","result":{},"id":1}"#.to_string(),
+            )
+            .await
+            .unwrap();
+        let session_id = post.await.unwrap().headers()["mcp-session-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        client
+            .delete(&url)
+            .header("mcp-session-id", &session_id)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Closed(connection)
+        );
+
+        // Without sessions, each POST is one, ending once it's answered
+        let mut transport = HttpTransport::new(String::new(), "/mcp".to_string()).stateless();
+        let mut events = transport.connection_events().unwrap();
+        let (url, mut request_rx) = serve(&transport).await;
+        let post = tokio::spawn({
+            let client = client.clone();
+            let url = url.clone();
+            async move {
+                client
+                    .post(url)
+                    .body(r#"{"jsonrpc":"2.0","method":"ping","id":1}"#)
+                    .send()
+                    .await
+                    .unwrap()
+            }
+        });
+        let connection = request_rx.recv().await.unwrap().connection;
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Opened(connection.clone())
+        );
+        transport
+            .send_response(
+                connection.clone(),
+                r#"{"jsonrpc":"2.0","result":{},"id":1}"#.to_string(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Closed(connection)
+        );
+        assert_eq!(post.await.unwrap().status(), reqwest::StatusCode::OK);
+
+        let response = client
+            .post(&url)
+            .body(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+        let connection = request_rx.recv().await.unwrap().connection;
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Opened(connection.clone())
+        );
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Closed(connection)
+        );
+    }
+
+    #[test]
+    fn test_response_correlation() {
+        assert_eq!(
+            response_id(r#"{"result":{},"id":7}"#),
+            Some("7".to_string())
+        );
+        assert_eq!(
+            response_id(r#"[{"result":{},"id":"a"},{"result":{},"id":"b"}]"#),
+            Some(r#""a""#.to_string())
+        );
+        assert_eq!(response_id(r#"{"method":"notifications/message"}"#), None);
+    }
+}
@@ -0,0 +1,23 @@
+use serde_json::json;
+
+/// Largest message a transport reads unless set with `with_max_message_size`
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// JSON-RPC error code for messages refused because they're larger than the transport accepts
+pub const MESSAGE_TOO_LARGE: i64 = -32031;
+
+/// The error answering a message longer than `limit` bytes
+///
+/// The message isn't read, so its ID isn't known and the error's is null.
+pub(crate) fn too_large(limit: usize) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": MESSAGE_TOO_LARGE,
+            "message": format!("Message larger than the limit of {} bytes", limit),
+            "data": { "limit": limit },
+        },
+        "id": null,
+    })
+    .to_string()
+}
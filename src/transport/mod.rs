@@ -0,0 +1,244 @@
+pub use crate::error::TransportError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use tokio::sync::{broadcast, mpsc};
+
+mod auth;
+mod channel;
+mod deflate;
+mod encoding;
+mod health;
+mod http;
+mod limit;
+mod origin;
+mod queue;
+mod replay;
+mod resume;
+mod sse;
+mod stdio;
+mod tcp;
+mod writer;
+mod ws;
+
+pub use auth::{
+    AuthConfig, AuthError, Authenticator, TokenClaims, PROTECTED_RESOURCE_METADATA_PATH,
+};
+pub use channel::{ChannelEnd, ChannelTransport, CHANNEL_CONNECTION};
+pub(crate) use encoding::{read_cbor, to_cbor};
+pub use encoding::{Encoding, CBOR_MAGIC};
+pub use health::{Health, HealthReport, HEALTH_PATH, READY_PATH};
+pub use http::{HttpTransport, SESSION_ID_HEADER};
+pub use limit::{DEFAULT_MAX_MESSAGE_SIZE, MESSAGE_TOO_LARGE};
+pub use origin::OriginPolicy;
+pub(crate) use queue::{depth as queue_depth, relay};
+pub use queue::{
+    GaugeReport, Overflow, QueueConfig, QueueMetrics, QueueReport, DEFAULT_QUEUE_CAPACITY,
+    SERVER_OVERLOADED,
+};
+pub use replay::DEFAULT_REPLAY_CAPACITY;
+pub use resume::{ResumeConfig, DEFAULT_RESUME_BACKLOG};
+pub use sse::{SseTransport, DEFAULT_RECONNECT_GRACE};
+pub(crate) use stdio::Frame;
+pub use stdio::{StdioFraming, StdioTransport, STDIO_CONNECTION};
+pub use tcp::{TcpTransport, TlsIdentity};
+pub use ws::{KeepAliveConfig, WebSocketTransport};
+
+/// Identifies a single client connection on a transport
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ConnectionId(String);
+
+impl ConnectionId {
+    /// Creates a new, unique connection ID
+    pub fn new() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for ConnectionId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&str> for ConnectionId {
+    fn from(id: &str) -> Self {
+        Self(id.to_string())
+    }
+}
+
+impl From<String> for ConnectionId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl fmt::Display for ConnectionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A raw JSON-RPC message received from a client connection
+#[derive(Clone, Debug)]
+pub struct TransportMessage {
+    pub connection: ConnectionId,
+    pub message: String,
+}
+
+/// A client connecting to or leaving a transport
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    Opened(ConnectionId),
+    /// The connection is gone, so whatever the server keeps for it can be dropped
+    Closed(ConnectionId),
+    /// The connection dropped, but the client may reconnect and resume its
+    /// session until the transport's grace period passes and `Closed` follows
+    Suspended(ConnectionId),
+    /// The client of a suspended connection reconnected
+    Resumed(ConnectionId),
+}
+
+pub trait Transport {
+    fn start(
+        &mut self,
+        request_tx: mpsc::Sender<TransportMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>>;
+
+    /// Sends a message to the connection it is addressed to
+    fn send_response(
+        &mut self,
+        connection: ConnectionId,
+        response: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>>;
+
+    /// Sends a message to every connected client
+    fn broadcast(
+        &mut self,
+        message: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>>;
+
+    /// Waits until every message handed to the transport so far is written
+    fn flush(&mut self) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Whether every message arrives on a connection of its own, so clients
+    /// can't complete the initialization handshake
+    fn is_stateless(&self) -> bool {
+        false
+    }
+
+    /// Status served on the transport's `/healthz` and `/readyz` endpoints, if it has them
+    fn health(&self) -> Option<Health> {
+        None
+    }
+
+    /// Subscribes to connections opening and closing, if the transport reports them
+    fn connection_events(&self) -> Option<broadcast::Receiver<ConnectionEvent>> {
+        None
+    }
+}
+
+#[derive(Clone)]
+pub enum TransportType {
+    Stdio(StdioTransport),
+    WebSocket(WebSocketTransport),
+    Sse(SseTransport),
+    Http(HttpTransport),
+    Tcp(TcpTransport),
+    Channel(ChannelTransport),
+}
+
+impl Transport for TransportType {
+    fn start(
+        &mut self,
+        request_tx: mpsc::Sender<TransportMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        match self {
+            TransportType::Stdio(t) => t.start(request_tx),
+            TransportType::WebSocket(t) => t.start(request_tx),
+            TransportType::Sse(t) => t.start(request_tx),
+            TransportType::Http(t) => t.start(request_tx),
+            TransportType::Tcp(t) => t.start(request_tx),
+            TransportType::Channel(t) => t.start(request_tx),
+        }
+    }
+
+    fn send_response(
+        &mut self,
+        connection: ConnectionId,
+        response: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        match self {
+            TransportType::Stdio(t) => t.send_response(connection, response),
+            TransportType::WebSocket(t) => t.send_response(connection, response),
+            TransportType::Sse(t) => t.send_response(connection, response),
+            TransportType::Http(t) => t.send_response(connection, response),
+            TransportType::Tcp(t) => t.send_response(connection, response),
+            TransportType::Channel(t) => t.send_response(connection, response),
+        }
+    }
+
+    fn broadcast(
+        &mut self,
+        message: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        match self {
+            TransportType::Stdio(t) => t.broadcast(message),
+            TransportType::WebSocket(t) => t.broadcast(message),
+            TransportType::Sse(t) => t.broadcast(message),
+            TransportType::Http(t) => t.broadcast(message),
+            TransportType::Tcp(t) => t.broadcast(message),
+            TransportType::Channel(t) => t.broadcast(message),
+        }
+    }
+
+    fn flush(&mut self) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        match self {
+            TransportType::Stdio(t) => t.flush(),
+            TransportType::WebSocket(t) => t.flush(),
+            TransportType::Sse(t) => t.flush(),
+            TransportType::Http(t) => t.flush(),
+            TransportType::Tcp(t) => t.flush(),
+            TransportType::Channel(t) => t.flush(),
+        }
+    }
+
+    fn is_stateless(&self) -> bool {
+        match self {
+            TransportType::Stdio(t) => t.is_stateless(),
+            TransportType::WebSocket(t) => t.is_stateless(),
+            TransportType::Sse(t) => t.is_stateless(),
+            TransportType::Http(t) => t.is_stateless(),
+            TransportType::Tcp(t) => t.is_stateless(),
+            TransportType::Channel(t) => t.is_stateless(),
+        }
+    }
+
+    fn health(&self) -> Option<Health> {
+        match self {
+            TransportType::Stdio(t) => t.health(),
+            TransportType::WebSocket(t) => t.health(),
+            TransportType::Sse(t) => t.health(),
+            TransportType::Http(t) => t.health(),
+            TransportType::Tcp(t) => t.health(),
+            TransportType::Channel(t) => t.health(),
+        }
+    }
+
+    fn connection_events(&self) -> Option<broadcast::Receiver<ConnectionEvent>> {
+        match self {
+            TransportType::Stdio(t) => t.connection_events(),
+            TransportType::WebSocket(t) => t.connection_events(),
+            TransportType::Sse(t) => t.connection_events(),
+            TransportType::Http(t) => t.connection_events(),
+            TransportType::Tcp(t) => t.connection_events(),
+            TransportType::Channel(t) => t.connection_events(),
+        }
+    }
+}
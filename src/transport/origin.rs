@@ -0,0 +1,255 @@
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{
+            ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+            ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+            ORIGIN, VARY,
+        },
+        HeaderMap, HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::sync::Arc;
+use tracing::warn;
+use url::Url;
+
+/// Request headers browsers may send cross-origin
+const ALLOWED_HEADERS: &str = "content-type, accept, authorization, mcp-session-id, last-event-id";
+
+/// Response headers browsers let cross-origin pages read
+const EXPOSED_HEADERS: &str = "mcp-session-id, www-authenticate";
+
+/// Which web pages may talk to the server
+///
+/// Browsers send an `Origin` header with cross-origin requests and WebSocket
+/// upgrades, so checking it stops pages on other sites, including ones that
+/// rebind their DNS name to a local address, from reaching a server on the
+/// user's machine. Requests without an `Origin` don't come from a web page
+/// and are always allowed.
+#[derive(Clone, Debug)]
+pub struct OriginPolicy {
+    origins: Vec<String>,
+    localhost: bool,
+    any: bool,
+}
+
+impl Default for OriginPolicy {
+    fn default() -> Self {
+        Self::localhost()
+    }
+}
+
+impl OriginPolicy {
+    /// Allows pages served from localhost
+    pub fn localhost() -> Self {
+        Self {
+            origins: Vec::new(),
+            localhost: true,
+            any: false,
+        }
+    }
+
+    /// Allows only the listed origins, such as `https://app.example.com`
+    pub fn strict(origins: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            origins: Vec::new(),
+            localhost: false,
+            any: false,
+        }
+        .allow_all(origins)
+    }
+
+    /// Allows every origin, for servers that other checks already protect
+    pub fn any() -> Self {
+        Self {
+            origins: Vec::new(),
+            localhost: false,
+            any: true,
+        }
+    }
+
+    /// Also allows `origin`
+    pub fn allow(mut self, origin: impl Into<String>) -> Self {
+        let origin = origin.into();
+        self.origins
+            .push(origin.trim_end_matches('/').to_ascii_lowercase());
+        self
+    }
+
+    fn allow_all(self, origins: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        origins.into_iter().fold(self, Self::allow)
+    }
+
+    /// Whether a request with this `Origin` header is allowed
+    pub fn is_allowed(&self, origin: Option<&str>) -> bool {
+        let Some(origin) = origin else {
+            return true;
+        };
+        if self.any {
+            return true;
+        }
+        let origin = origin.trim_end_matches('/').to_ascii_lowercase();
+        if self.origins.contains(&origin) {
+            return true;
+        }
+        self.localhost && is_localhost(&origin)
+    }
+
+    /// Checks the `Origin` header of a request
+    pub(crate) fn allows(&self, headers: &HeaderMap) -> bool {
+        let origin = headers.get(ORIGIN);
+        let allowed = match origin {
+            Some(origin) => origin
+                .to_str()
+                .is_ok_and(|origin| self.is_allowed(Some(origin))),
+            None => true,
+        };
+        if !allowed {
+            warn!("Rejected request from origin {:?}", origin);
+        }
+        allowed
+    }
+}
+
+fn is_localhost(origin: &str) -> bool {
+    let Ok(url) = Url::parse(origin) else {
+        return false;
+    };
+    matches!(url.scheme(), "http" | "https")
+        && url.host_str().is_some_and(|host| {
+            matches!(host, "localhost" | "127.0.0.1" | "[::1]") || host.ends_with(".localhost")
+        })
+}
+
+/// Rejects requests from disallowed origins and adds CORS headers for the allowed ones
+pub(crate) fn apply(router: Router, policy: Arc<OriginPolicy>) -> Router {
+    router.layer(axum::middleware::from_fn_with_state(policy, check_origin))
+}
+
+async fn check_origin(
+    State(policy): State<Arc<OriginPolicy>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !policy.allows(request.headers()) {
+        return (StatusCode::FORBIDDEN, "Origin not allowed").into_response();
+    }
+
+    let Some(origin) = request.headers().get(ORIGIN).cloned() else {
+        return next.run(request).await;
+    };
+    // Preflights are answered here, before routing or authorization
+    let mut response = if request.method() == Method::OPTIONS {
+        let mut response = StatusCode::NO_CONTENT.into_response();
+        let headers = response.headers_mut();
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static("GET, POST, DELETE, OPTIONS"),
+        );
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_HEADERS,
+            HeaderValue::from_static(ALLOWED_HEADERS),
+        );
+        headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static("86400"));
+        response
+    } else {
+        next.run(request).await
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.insert(
+        ACCESS_CONTROL_EXPOSE_HEADERS,
+        HeaderValue::from_static(EXPOSED_HEADERS),
+    );
+    headers.append(VARY, HeaderValue::from_static("origin"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_origin_policy() {
+        let localhost = OriginPolicy::default();
+        assert!(localhost.is_allowed(None));
+        assert!(localhost.is_allowed(Some("http://localhost:3000")));
+        assert!(localhost.is_allowed(Some("http://127.0.0.1")));
+        assert!(localhost.is_allowed(Some("http://[::1]:8080")));
+        assert!(localhost.is_allowed(Some("http://app.localhost")));
+        assert!(!localhost.is_allowed(Some("http://localhost.evil.com")));
+        assert!(!localhost.is_allowed(Some("null")));
+
+        let strict = OriginPolicy::strict(["https://app.example.com/"]);
+        assert!(strict.is_allowed(Some("https://APP.example.com")));
+        assert!(!strict.is_allowed(Some("http://localhost:3000")));
+        assert!(!strict.is_allowed(Some("https://example.com")));
+        assert!(strict.is_allowed(None));
+
+        assert!(OriginPolicy::any().is_allowed(Some("https://anywhere.example")));
+        assert!(OriginPolicy::localhost()
+            .allow("https://app.example.com")
+            .is_allowed(Some("https://app.example.com")));
+    }
+
+    #[tokio::test]
+    async fn test_cors_headers() {
+        let router = apply(
+            Router::new().route("/mcp", post(|| async { "ok" })),
+            Arc::new(OriginPolicy::default()),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(&url)
+            .header("origin", "http://evil.example.com")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let response = client
+            .request(reqwest::Method::OPTIONS, &url)
+            .header("origin", "http://localhost:3000")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+        assert_eq!(
+            response.headers()["access-control-allow-origin"],
+            "http://localhost:3000"
+        );
+        assert!(response.headers()["access-control-allow-headers"]
+            .to_str()
+            .unwrap()
+            .contains("mcp-session-id"));
+
+        let response = client
+            .post(&url)
+            .header("origin", "http://localhost:3000")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()["access-control-allow-origin"],
+            "http://localhost:3000"
+        );
+        assert_eq!(response.text().await.unwrap(), "ok");
+
+        // Clients that aren't browsers get no CORS headers
+        let response = client.post(&url).send().await.unwrap();
+        assert!(response
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none());
+    }
+}
@@ -0,0 +1,349 @@
+use crate::message::JsonRpcMessage;
+use crate::transport::TransportMessage;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// JSON-RPC error code for requests refused because the server's inbound queue is full
+pub const SERVER_OVERLOADED: i64 = -32030;
+
+/// Capacity of each queue unless configured otherwise
+pub const DEFAULT_QUEUE_CAPACITY: usize = 32;
+
+/// What happens to a message that arrives while the inbound queue is full
+///
+/// Responses to the server's own requests always wait for room, since
+/// dropping one would leave the request that sent it waiting until it
+/// times out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// The transport waits for room, so it stops reading from its clients
+    #[default]
+    Wait,
+    /// Requests are answered with [`SERVER_OVERLOADED`] and notifications dropped
+    Reject,
+    /// The message is dropped without an answer
+    Drop,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wait" => Ok(Overflow::Wait),
+            "reject" => Ok(Overflow::Reject),
+            "drop" => Ok(Overflow::Drop),
+            _ => Err(format!(
+                "Unknown overflow policy {:?}, expected wait, reject or drop",
+                s
+            )),
+        }
+    }
+}
+
+/// Sizes of the queues between the transport and the request handlers
+///
+/// Messages from clients wait in the inbound queue until they're
+/// dispatched, which happens one at a time; answers and notifications for
+/// clients wait in the outbound queue until the transport sends them. A
+/// handler whose answer finds the outbound queue full waits for room.
+/// Clones share the same metrics.
+///
+/// ```ignore
+/// QueueConfig::new().inbound(256).overflow(Overflow::Reject);
+/// ```
+#[derive(Clone, Debug)]
+pub struct QueueConfig {
+    inbound: usize,
+    outbound: usize,
+    overflow: Overflow,
+    metrics: QueueMetrics,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            inbound: DEFAULT_QUEUE_CAPACITY,
+            outbound: DEFAULT_QUEUE_CAPACITY,
+            overflow: Overflow::default(),
+            metrics: QueueMetrics::default(),
+        }
+    }
+}
+
+impl QueueConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of messages from clients held before the overflow policy applies
+    pub fn inbound(mut self, capacity: usize) -> Self {
+        self.inbound = capacity.max(1);
+        self
+    }
+
+    /// Number of messages for clients held before handlers wait to queue theirs
+    pub fn outbound(mut self, capacity: usize) -> Self {
+        self.outbound = capacity.max(1);
+        self
+    }
+
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    pub fn inbound_capacity(&self) -> usize {
+        self.inbound
+    }
+
+    pub fn outbound_capacity(&self) -> usize {
+        self.outbound
+    }
+
+    pub fn overflow_policy(&self) -> Overflow {
+        self.overflow
+    }
+
+    /// Depths of the queues while the server runs
+    pub fn metrics(&self) -> QueueMetrics {
+        self.metrics.clone()
+    }
+}
+
+/// Depths of a server's queues and what their overflow policy refused
+#[derive(Clone, Debug, Default)]
+pub struct QueueMetrics {
+    inner: Arc<MetricsState>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    inbound: Gauge,
+    outbound: Gauge,
+    rejected: AtomicU64,
+    dropped: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Gauge {
+    capacity: AtomicUsize,
+    depth: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl Gauge {
+    fn record(&self, depth: usize) {
+        self.depth.store(depth, Ordering::Relaxed);
+        self.peak.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    fn report(&self) -> GaugeReport {
+        GaugeReport {
+            capacity: self.capacity.load(Ordering::Relaxed),
+            depth: self.depth.load(Ordering::Relaxed),
+            peak: self.peak.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// How full a queue is
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct GaugeReport {
+    pub capacity: usize,
+    /// Messages waiting when last looked at
+    pub depth: usize,
+    /// Most messages seen waiting at once
+    pub peak: usize,
+}
+
+/// Snapshot of a server's queue metrics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct QueueReport {
+    pub inbound: GaugeReport,
+    pub outbound: GaugeReport,
+    /// Requests answered with [`SERVER_OVERLOADED`]
+    pub rejected: u64,
+    /// Messages dropped without an answer
+    pub dropped: u64,
+}
+
+impl QueueMetrics {
+    pub fn report(&self) -> QueueReport {
+        QueueReport {
+            inbound: self.inner.inbound.report(),
+            outbound: self.inner.outbound.report(),
+            rejected: self.inner.rejected.load(Ordering::Relaxed),
+            dropped: self.inner.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn set_capacities(&self, inbound: usize, outbound: usize) {
+        self.inner
+            .inbound
+            .capacity
+            .store(inbound, Ordering::Relaxed);
+        self.inner
+            .outbound
+            .capacity
+            .store(outbound, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_inbound(&self, depth: usize) {
+        self.inner.inbound.record(depth);
+    }
+
+    pub(crate) fn record_outbound(&self, depth: usize) {
+        self.inner.outbound.record(depth);
+    }
+}
+
+/// Number of messages waiting in the queue `sender` feeds
+pub(crate) fn depth<T>(sender: &mpsc::Sender<T>) -> usize {
+    sender.max_capacity() - sender.capacity()
+}
+
+/// Moves messages from the transport into the inbound queue until the transport stops
+///
+/// A message that finds the queue full is handled by the overflow policy;
+/// the answers to rejected requests go out through `outgoing`.
+pub(crate) async fn relay(
+    mut incoming: mpsc::Receiver<TransportMessage>,
+    queue: mpsc::Sender<TransportMessage>,
+    outgoing: mpsc::Sender<TransportMessage>,
+    overflow: Overflow,
+    metrics: QueueMetrics,
+) {
+    while let Some(message) = incoming.recv().await {
+        let message = match queue.try_send(message) {
+            Ok(()) => {
+                metrics.record_inbound(depth(&queue));
+                continue;
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => return,
+            Err(mpsc::error::TrySendError::Full(message)) => message,
+        };
+
+        let parsed = JsonRpcMessage::parse(&message.message);
+        let is_response = parsed.as_ref().is_ok_and(JsonRpcMessage::is_response);
+        if overflow == Overflow::Wait || is_response {
+            if queue.send(message).await.is_err() {
+                return;
+            }
+            metrics.record_inbound(depth(&queue));
+            continue;
+        }
+
+        let rejection = match (&parsed, overflow) {
+            (Ok(parsed), Overflow::Reject) => rejection(parsed),
+            _ => None,
+        };
+        match rejection {
+            Some(rejection) => {
+                warn!(
+                    "Inbound queue full, rejecting a request from {}",
+                    message.connection
+                );
+                metrics.inner.rejected.fetch_add(1, Ordering::Relaxed);
+                let answer = TransportMessage {
+                    connection: message.connection,
+                    message: rejection.to_string(),
+                };
+                if outgoing.send(answer).await.is_err() {
+                    return;
+                }
+            }
+            None => {
+                warn!(
+                    "Inbound queue full, dropping a message from {}",
+                    message.connection
+                );
+                metrics.inner.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// The error answering each request in `message`, if it has any
+fn rejection(message: &JsonRpcMessage) -> Option<Value> {
+    match message {
+        JsonRpcMessage::Request { id, .. } => Some(json!({
+            "jsonrpc": "2.0",
+            "error": { "code": SERVER_OVERLOADED, "message": "Server overloaded" },
+            "id": id,
+        })),
+        JsonRpcMessage::Batch(messages) => {
+            let errors: Vec<_> = messages.iter().filter_map(rejection).collect();
+            (!errors.is_empty()).then_some(Value::Array(errors))
+        }
+        JsonRpcMessage::Notification { .. } | JsonRpcMessage::Response(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str) -> TransportMessage {
+        TransportMessage {
+            connection: "a".into(),
+            message: text.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overflow() {
+        let config = QueueConfig::new().inbound(1).overflow(Overflow::Reject);
+        let metrics = config.metrics();
+        let (incoming_tx, incoming_rx) = mpsc::channel(1);
+        let (queue_tx, mut queue_rx) = mpsc::channel(config.inbound_capacity());
+        let (outgoing_tx, mut outgoing_rx) = mpsc::channel(8);
+        let relay = tokio::spawn(relay(
+            incoming_rx,
+            queue_tx,
+            outgoing_tx,
+            config.overflow_policy(),
+            metrics.clone(),
+        ));
+
+        let request = |id: u32| format!(r#"{{"jsonrpc":"2.0","id":{},"method":"ping"}}"#, id);
+        incoming_tx.send(message(&request(1))).await.unwrap();
+        incoming_tx.send(message(&request(2))).await.unwrap();
+        incoming_tx
+            .send(message(
+                r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            ))
+            .await
+            .unwrap();
+
+        // The queue holds the first request; the second is refused
+        let rejected = outgoing_rx.recv().await.unwrap();
+        let rejected: Value = serde_json::from_str(&rejected.message).unwrap();
+        assert_eq!(rejected["id"], 2);
+        assert_eq!(rejected["error"]["code"], SERVER_OVERLOADED);
+
+        // A response waits for room instead of being refused
+        incoming_tx
+            .send(message(r#"{"jsonrpc":"2.0","id":"s-1","result":{}}"#))
+            .await
+            .unwrap();
+        assert_eq!(queue_rx.recv().await.unwrap().message, request(1));
+        assert!(queue_rx.recv().await.unwrap().message.contains("s-1"));
+
+        drop(incoming_tx);
+        relay.await.unwrap();
+        let report = metrics.report();
+        assert_eq!(report.rejected, 1);
+        assert_eq!(report.dropped, 1);
+        assert_eq!(report.inbound.peak, 1);
+        assert!(outgoing_rx.try_recv().is_err());
+
+        assert_eq!("drop".parse(), Ok(Overflow::Drop));
+        assert!("spill".parse::<Overflow>().is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use super::ConnectionId;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Messages kept per session for replay unless set otherwise
+pub const DEFAULT_REPLAY_CAPACITY: usize = 128;
+
+#[derive(Default)]
+struct Log {
+    next_id: u64,
+    events: VecDeque<(u64, String)>,
+}
+
+/// The latest messages sent to each session's event stream, numbered so a
+/// client reconnecting with `Last-Event-ID` gets the ones it missed
+///
+/// Only sessions that were opened are recorded, and each keeps at most
+/// `capacity` messages, forgetting the oldest first.
+#[derive(Clone)]
+pub(crate) struct Replay {
+    capacity: usize,
+    logs: Arc<Mutex<HashMap<ConnectionId, Log>>>,
+}
+
+impl Replay {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            logs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn logs(&self) -> MutexGuard<'_, HashMap<ConnectionId, Log>> {
+        self.logs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts recording the messages of `connection`
+    pub(crate) fn open(&self, connection: &ConnectionId) {
+        if self.capacity > 0 {
+            self.logs().entry(connection.clone()).or_default();
+        }
+    }
+
+    pub(crate) fn close(&self, connection: &ConnectionId) {
+        self.logs().remove(connection);
+    }
+
+    pub(crate) fn is_open(&self, connection: &ConnectionId) -> bool {
+        self.logs().contains_key(connection)
+    }
+
+    /// Records `message`, returning its event ID if `connection` is recorded
+    pub(crate) fn record(&self, connection: &ConnectionId, message: &str) -> Option<u64> {
+        let mut logs = self.logs();
+        let log = logs.get_mut(connection)?;
+        let id = log.next_id;
+        log.next_id += 1;
+        if log.events.len() >= self.capacity {
+            log.events.pop_front();
+        }
+        log.events.push_back((id, message.to_string()));
+        Some(id)
+    }
+
+    /// Messages recorded after the one with ID `last`, those still kept if
+    /// some were already forgotten
+    pub(crate) fn since(&self, connection: &ConnectionId, last: u64) -> Vec<(u64, String)> {
+        self.logs()
+            .get(connection)
+            .map(|log| {
+                log.events
+                    .iter()
+                    .filter(|(id, _)| *id > last)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay() {
+        let replay = Replay::new(2);
+        let connection = ConnectionId::from("client");
+        assert_eq!(replay.record(&connection, "unrecorded"), None);
+
+        replay.open(&connection);
+        assert_eq!(replay.record(&connection, "zero"), Some(0));
+        assert_eq!(replay.record(&connection, "one"), Some(1));
+        assert_eq!(replay.record(&connection, "two"), Some(2));
+        assert_eq!(replay.since(&connection, 1), [(2, "two".to_string())]);
+        // What was forgotten can't be replayed
+        assert_eq!(
+            replay.since(&connection, 0),
+            [(1, "one".to_string()), (2, "two".to_string())]
+        );
+
+        replay.close(&connection);
+        assert!(!replay.is_open(&connection));
+        assert!(replay.since(&connection, 0).is_empty());
+        let disabled = Replay::new(0);
+        disabled.open(&connection);
+        assert_eq!(disabled.record(&connection, "zero"), None);
+    }
+}
@@ -0,0 +1,216 @@
+use super::{ConnectionEvent, ConnectionId};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// Messages kept for a suspended session unless set otherwise
+pub const DEFAULT_RESUME_BACKLOG: usize = 256;
+
+/// How long a dropped client has to reconnect, and how much it may miss meanwhile
+#[derive(Clone, Copy, Debug)]
+pub struct ResumeConfig {
+    pub grace: Duration,
+    /// Most messages kept for the client, dropping the oldest beyond it
+    pub backlog: usize,
+}
+
+struct Suspension {
+    expires: Instant,
+    backlog: VecDeque<String>,
+    /// Messages dropped from a full backlog
+    dropped: usize,
+    /// The client reconnected and its backlog is being written
+    resuming: bool,
+}
+
+/// Connections that dropped but whose sessions the clients may still resume
+///
+/// Messages for a suspended connection wait in its backlog, and are written
+/// in order when the client reconnects with its session token. Once the
+/// grace period passes without that, the connection is closed for good.
+#[derive(Clone, Default)]
+pub(crate) struct Suspended {
+    inner: Arc<Mutex<HashMap<ConnectionId, Suspension>>>,
+}
+
+impl Suspended {
+    fn lock(&self) -> MutexGuard<'_, HashMap<ConnectionId, Suspension>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Keeps the session of `connection` for the grace period, announcing it
+    /// suspended now and closed once the period passes unresumed
+    pub(crate) fn suspend(
+        &self,
+        connection: ConnectionId,
+        config: ResumeConfig,
+        events: broadcast::Sender<ConnectionEvent>,
+    ) {
+        self.suspend_then(connection, config, events, || ());
+    }
+
+    /// Suspends `connection` as `suspend` does, calling `close` if it's
+    /// closed before announcing it
+    pub(crate) fn suspend_then(
+        &self,
+        connection: ConnectionId,
+        config: ResumeConfig,
+        events: broadcast::Sender<ConnectionEvent>,
+        close: impl FnOnce() + Send + 'static,
+    ) {
+        let expires = Instant::now() + config.grace;
+        self.lock().insert(
+            connection.clone(),
+            Suspension {
+                expires,
+                backlog: VecDeque::new(),
+                dropped: 0,
+                resuming: false,
+            },
+        );
+        debug!("Suspended {} for {:?}", connection, config.grace);
+        let _ = events.send(ConnectionEvent::Suspended(connection.clone()));
+
+        let suspended = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep_until(expires).await;
+            if suspended.expire(&connection) {
+                debug!("{} wasn't resumed in time", connection);
+                close();
+                let _ = events.send(ConnectionEvent::Closed(connection));
+            }
+        });
+    }
+
+    /// Forgets `connection` if its grace period passed, returning whether it did
+    fn expire(&self, connection: &ConnectionId) -> bool {
+        let mut suspended = self.lock();
+        let expired = suspended
+            .get(connection)
+            .is_some_and(|suspension| !suspension.resuming && suspension.expires <= Instant::now());
+        if expired {
+            suspended.remove(connection);
+        }
+        expired
+    }
+
+    /// Claims `connection` for a client reconnecting with its token,
+    /// returning whether it can still be resumed
+    pub(crate) fn claim(&self, connection: &ConnectionId) -> bool {
+        match self.lock().get_mut(connection) {
+            Some(suspension) if !suspension.resuming => {
+                suspension.resuming = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Gives back a claim whose client didn't finish reconnecting, returning
+    /// whether the grace period passed meanwhile and the connection is gone
+    pub(crate) fn release(&self, connection: &ConnectionId) -> bool {
+        if let Some(suspension) = self.lock().get_mut(connection) {
+            suspension.resuming = false;
+        }
+        self.expire(connection)
+    }
+
+    /// Takes the messages buffered so far for a claimed connection
+    ///
+    /// Once nothing is left, `attach` is called and the connection stops
+    /// being suspended before the lock is released, so no message is lost
+    /// between the backlog and the new connection.
+    pub(crate) fn drain(&self, connection: &ConnectionId, attach: impl FnOnce()) -> Vec<String> {
+        let mut suspended = self.lock();
+        let Some(suspension) = suspended.get_mut(connection) else {
+            attach();
+            return Vec::new();
+        };
+        if suspension.dropped > 0 {
+            warn!(
+                "{} missed {} messages while it was away",
+                connection, suspension.dropped
+            );
+            suspension.dropped = 0;
+        }
+        if suspension.backlog.is_empty() {
+            suspended.remove(connection);
+            attach();
+            return Vec::new();
+        }
+        suspension.backlog.drain(..).collect()
+    }
+
+    /// Buffers `message` for `connection`, returning false if it isn't suspended
+    pub(crate) fn buffer(
+        &self,
+        connection: &ConnectionId,
+        message: String,
+        capacity: usize,
+    ) -> bool {
+        let mut suspended = self.lock();
+        let Some(suspension) = suspended.get_mut(connection) else {
+            return false;
+        };
+        if suspension.backlog.len() >= capacity {
+            suspension.backlog.pop_front();
+            suspension.dropped += 1;
+        }
+        if capacity > 0 {
+            suspension.backlog.push_back(message);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_suspended_connections() {
+        let suspended = Suspended::default();
+        let (events, mut events_rx) = broadcast::channel(8);
+        let config = ResumeConfig {
+            grace: Duration::from_millis(50),
+            backlog: 2,
+        };
+        let connection = ConnectionId::from("client");
+        assert!(!suspended.buffer(&connection, "lost".to_string(), 2));
+
+        suspended.suspend(connection.clone(), config, events.clone());
+        assert_eq!(
+            events_rx.recv().await.unwrap(),
+            ConnectionEvent::Suspended(connection.clone())
+        );
+        for message in ["one", "two", "three"] {
+            assert!(suspended.buffer(&connection, message.to_string(), 2));
+        }
+        assert!(suspended.claim(&connection));
+        assert!(!suspended.claim(&connection));
+        let mut attached = false;
+        assert_eq!(
+            suspended.drain(&connection, || attached = true),
+            ["two", "three"]
+        );
+        assert!(!attached);
+        assert!(suspended.drain(&connection, || attached = true).is_empty());
+        assert!(attached);
+        assert!(!suspended.buffer(&connection, "sent".to_string(), 2));
+
+        // Sessions not resumed in time are closed
+        suspended.suspend(connection.clone(), config, events);
+        assert_eq!(
+            events_rx.recv().await.unwrap(),
+            ConnectionEvent::Suspended(connection.clone())
+        );
+        assert_eq!(
+            events_rx.recv().await.unwrap(),
+            ConnectionEvent::Closed(connection.clone())
+        );
+        assert!(!suspended.claim(&connection));
+    }
+}
@@ -0,0 +1,605 @@
+use super::auth::{self, AuthConfig, Authenticator};
+use super::health::{self, Health};
+use super::http::{self, LAST_EVENT_ID_HEADER};
+use super::limit::DEFAULT_MAX_MESSAGE_SIZE;
+use super::origin::{self, OriginPolicy};
+use super::replay::{Replay, DEFAULT_REPLAY_CAPACITY};
+use super::resume::{ResumeConfig, Suspended};
+use super::{ConnectionEvent, ConnectionId, Transport, TransportError, TransportMessage};
+use axum::{
+    extract::{rejection::StringRejection, DefaultBodyLimit, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Router,
+};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc, Mutex},
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error};
+
+/// Path clients connect to for the server-to-client event stream
+pub const SSE_PATH: &str = "/sse";
+
+/// Path clients POST JSON-RPC messages to
+pub const MESSAGE_PATH: &str = "/message";
+
+/// Events queued for a stream before its client counts as stalled
+const EVENT_STREAM_CAPACITY: usize = 32;
+
+/// Connection events kept for subscribers that fall behind
+const EVENTS_CAPACITY: usize = 256;
+
+/// How long a client whose stream dropped has to reopen it, unless set otherwise
+pub const DEFAULT_RECONNECT_GRACE: Duration = Duration::from_secs(30);
+
+type SseSessions = Arc<Mutex<HashMap<ConnectionId, mpsc::Sender<Event>>>>;
+
+#[derive(Deserialize)]
+struct MessageQuery {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+#[derive(Clone)]
+struct SseState {
+    sessions: SseSessions,
+    replay: Replay,
+    suspended: Suspended,
+    reconnect_grace: Duration,
+    max_message_size: usize,
+    events: broadcast::Sender<ConnectionEvent>,
+    request_tx: mpsc::Sender<TransportMessage>,
+}
+
+/// MCP HTTP+SSE transport.
+///
+/// Clients open an event stream on [`SSE_PATH`], receive an `endpoint` event
+/// with the URI to POST messages to, and get every server message back as a
+/// `message` event on that stream. Each stream is its own connection.
+///
+/// Messages carry event IDs naming their session, and the latest are kept,
+/// so a client whose stream drops can reopen it with `Last-Event-ID` to get
+/// the same session back along with the messages it missed. The session is
+/// closed if the stream isn't reopened within the reconnect grace period.
+#[derive(Clone)]
+pub struct SseTransport {
+    addr: String,
+    sessions: SseSessions,
+    replay: Replay,
+    suspended: Suspended,
+    reconnect_grace: Duration,
+    max_message_size: usize,
+    events: broadcast::Sender<ConnectionEvent>,
+    auth: Option<Arc<Authenticator>>,
+    origins: Arc<OriginPolicy>,
+    health: Health,
+}
+
+impl SseTransport {
+    pub fn new(addr: String) -> Self {
+        Self {
+            addr,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            replay: Replay::new(DEFAULT_REPLAY_CAPACITY),
+            suspended: Suspended::default(),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+            auth: None,
+            origins: Arc::new(OriginPolicy::default()),
+            health: Health::default(),
+        }
+    }
+
+    /// Requires OAuth bearer tokens on the event stream and on every message
+    pub fn with_auth(mut self, config: AuthConfig) -> Self {
+        self.auth = Some(Arc::new(Authenticator::new(config)));
+        self
+    }
+
+    /// Sets which web pages may connect, allowing localhost by default
+    pub fn with_origins(mut self, origins: OriginPolicy) -> Self {
+        self.origins = Arc::new(origins);
+        self
+    }
+
+    /// Keeps the latest `capacity` messages of each session for replay, or none if 0
+    pub fn with_replay_capacity(mut self, capacity: usize) -> Self {
+        self.replay = Replay::new(capacity);
+        self
+    }
+
+    /// Keeps the session of a dropped stream for `grace`, for the client to
+    /// reopen it with `Last-Event-ID`
+    pub fn with_reconnect_grace(mut self, grace: Duration) -> Self {
+        self.reconnect_grace = grace;
+        self
+    }
+
+    /// Refuses messages longer than `bytes` with a
+    /// [`MESSAGE_TOO_LARGE`](super::MESSAGE_TOO_LARGE) error
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    fn router(&self, request_tx: mpsc::Sender<TransportMessage>) -> Router {
+        let state = SseState {
+            sessions: self.sessions.clone(),
+            replay: self.replay.clone(),
+            suspended: self.suspended.clone(),
+            reconnect_grace: self.reconnect_grace,
+            max_message_size: self.max_message_size,
+            events: self.events.clone(),
+            request_tx,
+        };
+
+        let router = Router::new()
+            .route(SSE_PATH, get(handle_sse))
+            .route(MESSAGE_PATH, post(handle_message))
+            .layer(DefaultBodyLimit::max(self.max_message_size))
+            .with_state(state);
+        let router = match &self.auth {
+            Some(authenticator) => auth::protect(router, authenticator.clone()),
+            None => router,
+        };
+        let router = router.merge(health::routes(self.health.clone(), "sse"));
+        origin::apply(router, self.origins.clone())
+    }
+}
+
+/// A server message, with an event ID naming its session if it's kept for replay
+fn message_event(connection: &ConnectionId, id: Option<u64>, message: String) -> Event {
+    let event = Event::default().event("message").data(message);
+    match id {
+        Some(id) => event.id(format!("{}/{}", connection, id)),
+        None => event,
+    }
+}
+
+/// Session and event a reconnecting client last received, from its `Last-Event-ID`
+fn last_event(headers: &HeaderMap) -> Option<(ConnectionId, u64)> {
+    let last_event_id = headers.get(LAST_EVENT_ID_HEADER)?.to_str().ok()?;
+    let (session_id, id) = last_event_id.rsplit_once('/')?;
+    Some((ConnectionId::from(session_id), id.parse().ok()?))
+}
+
+/// Lives as long as the event stream of `connection`, to notice it dropping
+struct StreamGuard {
+    state: SseState,
+    connection: ConnectionId,
+    sender: mpsc::WeakSender<Event>,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let state = self.state.clone();
+        let connection = self.connection.clone();
+        let sender = self.sender.clone();
+        runtime.spawn(async move { disconnect(&state, connection, &sender).await });
+    }
+}
+
+/// Suspends the session of a stream that dropped, unless a newer stream took
+/// it over, closing it if the client can't resume it
+async fn disconnect(state: &SseState, connection: ConnectionId, stream: &mpsc::WeakSender<Event>) {
+    // Suspending happens under the sessions lock, so a client reopening
+    // the stream meanwhile either finds it suspended or still open
+    let mut sessions = state.sessions.lock().await;
+    if let Some(sender) = sessions.get(&connection) {
+        if !stream
+            .upgrade()
+            .is_some_and(|stream| sender.same_channel(&stream))
+        {
+            return;
+        }
+        sessions.remove(&connection);
+    }
+    debug!("SSE stream closed: {}", connection);
+    if state.replay.is_open(&connection) {
+        let config = ResumeConfig {
+            grace: state.reconnect_grace,
+            backlog: 0,
+        };
+        // The replay log goes with the session, once it can't be resumed
+        let replay = state.replay.clone();
+        let closed = connection.clone();
+        state
+            .suspended
+            .suspend_then(connection, config, state.events.clone(), move || {
+                replay.close(&closed)
+            });
+    } else {
+        let _ = state.events.send(ConnectionEvent::Closed(connection));
+    }
+}
+
+async fn handle_sse(
+    State(state): State<SseState>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // Recording and sending happen under the sessions lock, so nothing
+    // recorded after the replay is missed by the new stream
+    let mut sessions = state.sessions.lock().await;
+    let (session_id, missed) = match last_event(&headers) {
+        Some((session_id, last)) if state.replay.is_open(&session_id) => {
+            let missed = state.replay.since(&session_id, last);
+            // What it missed is in the replay log, not in a backlog
+            if state.suspended.claim(&session_id) {
+                state.suspended.drain(&session_id, || ());
+                let _ = state
+                    .events
+                    .send(ConnectionEvent::Resumed(session_id.clone()));
+            }
+            debug!(
+                "Resumed SSE session {}, replaying {} events",
+                session_id,
+                missed.len()
+            );
+            (session_id, missed)
+        }
+        _ => {
+            let session_id = ConnectionId::new();
+            state.replay.open(&session_id);
+            debug!("New SSE connection: {}", session_id);
+            let _ = state
+                .events
+                .send(ConnectionEvent::Opened(session_id.clone()));
+            (session_id, Vec::new())
+        }
+    };
+    let (sender, receiver) = mpsc::channel(EVENT_STREAM_CAPACITY + missed.len() + 1);
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("{}?sessionId={}", MESSAGE_PATH, session_id));
+    // The channel has room for the endpoint and every missed event
+    let _ = sender.try_send(endpoint);
+    for (id, message) in missed {
+        let _ = sender.try_send(message_event(&session_id, Some(id), message));
+    }
+    let guard = StreamGuard {
+        state: state.clone(),
+        connection: session_id.clone(),
+        sender: sender.downgrade(),
+    };
+    sessions.insert(session_id, sender);
+
+    let stream = ReceiverStream::new(receiver).map(move |event| {
+        let _ = &guard;
+        Ok(event)
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn handle_message(
+    State(state): State<SseState>,
+    Query(query): Query<MessageQuery>,
+    body: Result<String, StringRejection>,
+) -> Response {
+    let connection = ConnectionId::from(query.session_id);
+    if !state.sessions.lock().await.contains_key(&connection) {
+        error!("Message for unknown SSE session: {}", connection);
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let body = match body {
+        Ok(body) => body,
+        Err(rejection) => return http::body_rejection(rejection, state.max_message_size),
+    };
+
+    debug!("Received [sse {}]: {}", connection, body);
+    let message = TransportMessage {
+        connection,
+        message: body,
+    };
+    if state.request_tx.send(message).await.is_err() {
+        error!("Failed to send request through channel");
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
+    StatusCode::ACCEPTED.into_response()
+}
+
+impl Transport for SseTransport {
+    fn start(
+        &mut self,
+        request_tx: mpsc::Sender<TransportMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let addr = self.addr.clone();
+        let router = self.router(request_tx);
+        let health = self.health.clone();
+
+        Box::pin(async move {
+            let listener = TcpListener::bind(&addr)
+                .await
+                .map_err(TransportError::bind(&addr))?;
+            debug!("SSE server listening on: {}", addr);
+            health.set_listening();
+
+            axum::serve(listener, router).await?;
+            Ok(())
+        })
+    }
+
+    fn send_response(
+        &mut self,
+        connection: ConnectionId,
+        response: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let sessions = self.sessions.clone();
+        let replay = self.replay.clone();
+        Box::pin(async move {
+            if !response.is_empty() {
+                let mut sessions = sessions.lock().await;
+                // Kept while the stream is down, for the client to resume it
+                let id = replay.record(&connection, &response);
+                if let Some(sender) = sessions.get(&connection) {
+                    debug!("Sending [sse {}]: {}", connection, response);
+                    let event = message_event(&connection, id, response);
+                    if !http::queue_event(&connection, sender, event) {
+                        sessions.remove(&connection);
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn broadcast(
+        &mut self,
+        message: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let sessions = self.sessions.clone();
+        let replay = self.replay.clone();
+        Box::pin(async move {
+            if !message.is_empty() {
+                let mut sessions = sessions.lock().await;
+                debug!("Broadcasting [sse] to {}: {}", sessions.len(), message);
+                sessions.retain(|connection, sender| {
+                    let id = replay.record(connection, &message);
+                    let event = message_event(connection, id, message.clone());
+                    http::queue_event(connection, sender, event)
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn health(&self) -> Option<Health> {
+        Some(self.health.clone())
+    }
+
+    fn connection_events(&self) -> Option<broadcast::Receiver<ConnectionEvent>> {
+        Some(self.events.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sse_round_trip() {
+        let mut transport = SseTransport::new(String::new());
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let router = transport.router(request_tx);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let client = reqwest::Client::new();
+        let mut stream = client
+            .get(format!("{}{}", base, SSE_PATH))
+            .send()
+            .await
+            .unwrap();
+
+        let chunk = stream.chunk().await.unwrap().unwrap();
+        let chunk = String::from_utf8_lossy(&chunk).to_string();
+        assert!(chunk.contains("event: endpoint"));
+        let endpoint = chunk
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .unwrap()
+            .to_string();
+
+        let status = client
+            .post(format!("{}{}", base, endpoint))
+            .body(r#"{"jsonrpc":"2.0","method":"ping","id":1}"#)
+            .send()
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(status, reqwest::StatusCode::ACCEPTED);
+        let request = request_rx.recv().await.unwrap();
+        assert!(request.message.contains("ping"));
+
+        transport
+            .send_response(
+                request.connection,
+                r#"{"jsonrpc":"2.0","result":{},"id":1}"#.to_string(),
+            )
+            .await
+            .unwrap();
+        let chunk = stream.chunk().await.unwrap().unwrap();
+        let chunk = String::from_utf8_lossy(&chunk).to_string();
+        assert!(chunk.contains("event: message"));
+        assert!(chunk.contains(r#""result":{}"#));
+
+        let status = client
+            .post(format!("{}{}?sessionId=unknown", base, MESSAGE_PATH))
+            .body("{}")
+            .send()
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_sse_client() {
+        let mut transport = SseTransport::new(String::new());
+        let stalled = ConnectionId::from("stalled");
+        let (sender, mut receiver) = mpsc::channel(1);
+        transport
+            .sessions
+            .lock()
+            .await
+            .insert(stalled.clone(), sender);
+        transport.replay.open(&stalled);
+
+        // Sending never waits on a client that stopped reading, which is
+        // dropped and can resume its stream from the replay log
+        for message in ["first", "second"] {
+            tokio::time::timeout(
+                std::time::Duration::from_secs(1),
+                transport.broadcast(message.to_string()),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        }
+        assert!(!transport.sessions.lock().await.contains_key(&stalled));
+        assert!(receiver.recv().await.is_some());
+        assert!(receiver.recv().await.is_none());
+        assert_eq!(transport.replay.since(&stalled, 0).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sse_resume_with_last_event_id() {
+        let mut transport = SseTransport::new(String::new());
+        let (request_tx, _request_rx) = mpsc::channel(32);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}{}", listener.local_addr().unwrap(), SSE_PATH);
+        let router = transport.router(request_tx);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let client = reqwest::Client::new();
+        let mut stream = client.get(&url).send().await.unwrap();
+        let chunk = stream.chunk().await.unwrap().unwrap();
+        let endpoint = String::from_utf8_lossy(&chunk).to_string();
+        let session_id = endpoint.split("sessionId=").nth(1).unwrap().trim();
+        let connection = ConnectionId::from(session_id);
+
+        transport
+            .send_response(connection.clone(), "first".to_string())
+            .await
+            .unwrap();
+        let chunk = stream.chunk().await.unwrap().unwrap();
+        let chunk = String::from_utf8_lossy(&chunk).to_string();
+        assert!(chunk.contains(&format!("id: {}/0", session_id)));
+
+        drop(stream);
+        transport
+            .send_response(connection.clone(), "second".to_string())
+            .await
+            .unwrap();
+
+        // The same session comes back, with what it missed
+        let mut stream = client
+            .get(&url)
+            .header("last-event-id", format!("{}/0", session_id))
+            .send()
+            .await
+            .unwrap();
+        let mut received = String::new();
+        while !received.contains("second") {
+            let chunk = stream.chunk().await.unwrap().unwrap();
+            received.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        assert!(received.contains(&format!("sessionId={}", session_id)));
+        assert!(received.contains(&format!("id: {}/1", session_id)));
+        assert!(!received.contains("first"));
+
+        // Unknown sessions get a new one
+        let mut stream = client
+            .get(&url)
+            .header("last-event-id", "unknown/3")
+            .send()
+            .await
+            .unwrap();
+        let chunk = stream.chunk().await.unwrap().unwrap();
+        assert!(!String::from_utf8_lossy(&chunk).contains(session_id));
+    }
+
+    #[tokio::test]
+    async fn test_sse_connection_events() {
+        let mut transport =
+            SseTransport::new(String::new()).with_reconnect_grace(Duration::from_millis(100));
+        let mut events = transport.connection_events().unwrap();
+        let (request_tx, _request_rx) = mpsc::channel(32);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}{}", listener.local_addr().unwrap(), SSE_PATH);
+        let router = transport.router(request_tx);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let client = reqwest::Client::new();
+        let stream = client.get(&url).send().await.unwrap();
+        let ConnectionEvent::Opened(connection) = events.recv().await.unwrap() else {
+            panic!("Expected the stream to open a connection");
+        };
+        transport
+            .send_response(connection.clone(), "first".to_string())
+            .await
+            .unwrap();
+
+        // A stream reopened within the grace period resumes its session
+        drop(stream);
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Suspended(connection.clone())
+        );
+        let stream = client
+            .get(&url)
+            .header("last-event-id", format!("{}/0", connection))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Resumed(connection.clone())
+        );
+
+        // One that isn't ends it
+        drop(stream);
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Suspended(connection.clone())
+        );
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Closed(connection.clone())
+        );
+        assert!(!transport.replay.is_open(&connection));
+
+        // And the client can't get it back
+        let mut stream = client
+            .get(&url)
+            .header("last-event-id", format!("{}/0", connection))
+            .send()
+            .await
+            .unwrap();
+        let chunk = stream.chunk().await.unwrap().unwrap();
+        assert!(!String::from_utf8_lossy(&chunk).contains(connection.as_str()));
+    }
+}
@@ -0,0 +1,467 @@
+use super::limit::{self, DEFAULT_MAX_MESSAGE_SIZE};
+use super::writer::Writer;
+use super::{ConnectionId, Transport, TransportError, TransportMessage};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc,
+};
+use tracing::{debug, error, info};
+
+/// Connection ID of the single client talking over stdio
+pub const STDIO_CONNECTION: &str = "stdio";
+
+/// Longest header line read from stdin, whatever the maximum message size
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+/// How messages are delimited on stdin and stdout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StdioFraming {
+    /// Worked out from the first message, with responses framed the same way
+    #[default]
+    Auto,
+    /// One JSON message per line
+    Newline,
+    /// A `Content-Length` header and a blank line before each message, as in LSP
+    ContentLength,
+}
+
+impl std::str::FromStr for StdioFraming {
+    type Err = String;
+
+    fn from_str(framing: &str) -> Result<Self, Self::Err> {
+        match framing {
+            "auto" => Ok(StdioFraming::Auto),
+            "newline" => Ok(StdioFraming::Newline),
+            "content-length" => Ok(StdioFraming::ContentLength),
+            _ => Err(format!("Unknown stdio framing: {}", framing)),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StdioTransport {
+    /// Writes to stdout, started by the first message sent
+    stdout: Arc<OnceLock<Writer<Vec<u8>>>>,
+    framing: Arc<std::sync::Mutex<StdioFraming>>,
+    max_message_size: usize,
+}
+
+impl StdioTransport {
+    pub fn new() -> Self {
+        Self {
+            stdout: Arc::default(),
+            framing: Arc::default(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// Frames messages as `framing` says instead of detecting it
+    pub fn with_framing(self, framing: StdioFraming) -> Self {
+        *self.framing.lock().unwrap_or_else(|e| e.into_inner()) = framing;
+        self
+    }
+
+    /// Skips messages longer than `bytes` instead of reading them, answering
+    /// them with a [`MESSAGE_TOO_LARGE`](super::MESSAGE_TOO_LARGE) error
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Framing in use, `Auto` until the first message is read when detecting it
+    pub fn framing(&self) -> StdioFraming {
+        *self.framing.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn writer(&self) -> Writer<Vec<u8>> {
+        let connection = ConnectionId::from(STDIO_CONNECTION);
+        self.stdout
+            .get_or_init(|| {
+                let stdout = futures::sink::unfold(
+                    tokio::io::stdout(),
+                    |mut stdout, frame: Vec<u8>| async move {
+                        stdout.write_all(&frame).await?;
+                        stdout.flush().await?;
+                        Ok::<_, io::Error>(stdout)
+                    },
+                );
+                Writer::spawn(connection, Box::pin(stdout))
+            })
+            .clone()
+    }
+}
+
+impl Default for StdioTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for StdioTransport {
+    fn start(
+        &mut self,
+        request_tx: mpsc::Sender<TransportMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        Box::pin(async move {
+            let mut reader = FrameReader {
+                reader: BufReader::new(tokio::io::stdin()),
+                framing: self.framing.clone(),
+                max_message_size: self.max_message_size,
+            };
+
+            while let Some(frame) = reader.next_message().await? {
+                let message = match frame {
+                    Frame::Message(message) => message,
+                    Frame::TooLarge => {
+                        let error = limit::too_large(self.max_message_size);
+                        let connection = ConnectionId::from(STDIO_CONNECTION);
+                        self.send_response(connection, error).await?;
+                        continue;
+                    }
+                };
+                debug!("Received [stdio]: {}", message);
+                let message = TransportMessage {
+                    connection: ConnectionId::from(STDIO_CONNECTION),
+                    message,
+                };
+                if request_tx.send(message).await.is_err() {
+                    error!("Failed to send request through channel");
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn send_response(
+        &mut self,
+        _connection: ConnectionId,
+        response: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let framing = self.framing();
+        Box::pin(async move {
+            if !response.is_empty() {
+                debug!("Sending [stdio]: {}", response);
+                self.writer()
+                    .send(frame(&response, framing))
+                    .await
+                    .map_err(|e| TransportError::Send {
+                        connection: ConnectionId::from(STDIO_CONNECTION),
+                        source: e.into(),
+                    })?;
+            }
+            Ok(())
+        })
+    }
+
+    fn broadcast(
+        &mut self,
+        message: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        self.send_response(ConnectionId::from(STDIO_CONNECTION), message)
+    }
+
+    fn flush(&mut self) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        Box::pin(async move {
+            if let Some(writer) = self.stdout.get() {
+                writer.flush().await.map_err(|e| TransportError::Send {
+                    connection: ConnectionId::from(STDIO_CONNECTION),
+                    source: e.into(),
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// `message` as written to stdout with `framing`
+fn frame(message: &str, framing: StdioFraming) -> Vec<u8> {
+    match framing {
+        StdioFraming::ContentLength => {
+            format!("Content-Length: {}\r\n\r\n{}", message.len(), message).into_bytes()
+        }
+        StdioFraming::Auto | StdioFraming::Newline => format!("{}\n", message).into_bytes(),
+    }
+}
+
+/// A message read from a stream
+pub(crate) enum Frame {
+    Message(String),
+    /// A message longer than the maximum, which was skipped
+    TooLarge,
+}
+
+/// A line of input, unless it was too long to keep
+enum Line {
+    Text(Vec<u8>),
+    TooLong,
+}
+
+/// The body announced by a message's headers, unless it was too long to keep
+enum Body {
+    Read(Vec<u8>),
+    TooLarge,
+}
+
+/// Reads the messages of a stream in the framing it uses
+///
+/// With `Auto`, the first message settles the framing: a `Content-Length`
+/// header means header framing and anything else one message per line.
+/// Messages that are too long or aren't UTF-8 are skipped, the former
+/// reported so the client can be told.
+pub(super) struct FrameReader<R> {
+    reader: R,
+    framing: Arc<std::sync::Mutex<StdioFraming>>,
+    max_message_size: usize,
+}
+
+impl<R: AsyncBufRead + Unpin> FrameReader<R> {
+    /// Reads one message per line, skipping those longer than `max_message_size`
+    pub(super) fn newline(reader: R, max_message_size: usize) -> Self {
+        Self {
+            reader,
+            framing: Arc::new(std::sync::Mutex::new(StdioFraming::Newline)),
+            max_message_size,
+        }
+    }
+
+    /// The next message, or `None` at the end of the stream
+    pub(super) async fn next_message(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            let Some(line) = self.read_line().await? else {
+                return Ok(None);
+            };
+            let line = match line {
+                Line::Text(line) => line,
+                Line::TooLong => {
+                    error!(
+                        "Skipped a message longer than {} bytes",
+                        self.max_message_size
+                    );
+                    return Ok(Some(Frame::TooLarge));
+                }
+            };
+            let line = trim_line_end(&line);
+            if line.is_empty() {
+                continue;
+            }
+
+            let framing = self.settle_framing(is_header(line));
+            let body = match framing {
+                StdioFraming::ContentLength if is_header(line) => {
+                    match self.read_body(line).await? {
+                        Some(Body::Read(body)) => body,
+                        Some(Body::TooLarge) => return Ok(Some(Frame::TooLarge)),
+                        None => continue,
+                    }
+                }
+                StdioFraming::ContentLength => {
+                    error!("Skipped input without a Content-Length header");
+                    continue;
+                }
+                StdioFraming::Auto | StdioFraming::Newline
+                    if line.len() > self.max_message_size =>
+                {
+                    error!(
+                        "Skipped a message longer than {} bytes",
+                        self.max_message_size
+                    );
+                    return Ok(Some(Frame::TooLarge));
+                }
+                StdioFraming::Auto | StdioFraming::Newline => line.to_vec(),
+            };
+            match String::from_utf8(body) {
+                Ok(message) => return Ok(Some(Frame::Message(message))),
+                Err(e) => error!("Skipped a message that isn't UTF-8: {}", e),
+            }
+        }
+    }
+
+    /// Detects the framing from a message starting with `header` if it's still `Auto`
+    fn settle_framing(&self, header: bool) -> StdioFraming {
+        let mut framing = self.framing.lock().unwrap_or_else(|e| e.into_inner());
+        if *framing == StdioFraming::Auto {
+            *framing = if header {
+                StdioFraming::ContentLength
+            } else {
+                StdioFraming::Newline
+            };
+            info!("Detected {:?} framing on stdio", *framing);
+        }
+        *framing
+    }
+
+    /// Reads the rest of the headers starting with `first` and the body they announce
+    async fn read_body(&mut self, first: &[u8]) -> io::Result<Option<Body>> {
+        let mut length = content_length(first);
+        loop {
+            match self.read_line().await? {
+                None => return Ok(None),
+                Some(Line::TooLong) => {}
+                Some(Line::Text(line)) => {
+                    let line = trim_line_end(&line);
+                    if line.is_empty() {
+                        break;
+                    }
+                    length = length.or_else(|| content_length(line));
+                }
+            }
+        }
+
+        let Some(length) = length else {
+            error!("Skipped a message without a valid Content-Length");
+            return Ok(None);
+        };
+        if length > self.max_message_size {
+            error!(
+                "Skipped a message of {} bytes, longer than {} bytes",
+                length, self.max_message_size
+            );
+            let mut body = (&mut self.reader).take(length as u64);
+            tokio::io::copy(&mut body, &mut tokio::io::sink()).await?;
+            return Ok(Some(Body::TooLarge));
+        }
+        let mut body = vec![0; length];
+        self.reader.read_exact(&mut body).await?;
+        Ok(Some(Body::Read(body)))
+    }
+
+    /// Reads up to the next newline, giving up on keeping lines too long to be
+    /// a message or a header
+    async fn read_line(&mut self) -> io::Result<Option<Line>> {
+        let max = self.max_message_size.max(MAX_HEADER_SIZE);
+        let limit = max as u64 + 1;
+        let mut line = Vec::new();
+        let read = (&mut self.reader)
+            .take(limit)
+            .read_until(b'\n', &mut line)
+            .await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if line.len() <= max || line.ends_with(b"\n") {
+            return Ok(Some(Line::Text(line)));
+        }
+
+        // Drop the rest of the line without holding on to it
+        loop {
+            let buffer = self.reader.fill_buf().await?;
+            if buffer.is_empty() {
+                break;
+            }
+            match buffer.iter().position(|byte| *byte == b'\n') {
+                Some(end) => {
+                    self.reader.consume(end + 1);
+                    break;
+                }
+                None => {
+                    let len = buffer.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+        Ok(Some(Line::TooLong))
+    }
+}
+
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn is_header(line: &[u8]) -> bool {
+    let lower = line.to_ascii_lowercase();
+    lower.starts_with(b"content-length:") || lower.starts_with(b"content-type:")
+}
+
+fn content_length(header: &[u8]) -> Option<usize> {
+    let header = std::str::from_utf8(header).ok()?;
+    let (name, value) = header.split_once(':')?;
+    if !name.trim().eq_ignore_ascii_case("content-length") {
+        return None;
+    }
+    value.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(input: &[u8], framing: StdioFraming, max: usize) -> FrameReader<&[u8]> {
+        FrameReader {
+            reader: input,
+            framing: Arc::new(std::sync::Mutex::new(framing)),
+            max_message_size: max,
+        }
+    }
+
+    /// Stands in for messages skipped for being too large
+    const TOO_LARGE: &str = "<too large>";
+
+    async fn read_all(reader: &mut FrameReader<&[u8]>) -> Vec<String> {
+        let mut messages = Vec::new();
+        while let Some(frame) = reader.next_message().await.unwrap() {
+            messages.push(match frame {
+                Frame::Message(message) => message,
+                Frame::TooLarge => TOO_LARGE.to_string(),
+            });
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_stdio_framing() {
+        // Header framing is detected, and bodies may span lines
+        let input = b"Content-Length: 8\r\nContent-Type: application/json\r\n\r\n{\"a\":\n1}Content-Length: 2\r\n\r\n{}";
+        let mut headers = reader(input, StdioFraming::Auto, DEFAULT_MAX_MESSAGE_SIZE);
+        assert_eq!(read_all(&mut headers).await, ["{\"a\":\n1}", "{}"]);
+        assert_eq!(
+            *headers.framing.lock().unwrap(),
+            StdioFraming::ContentLength
+        );
+
+        let input = b"{\"a\":1}\r\n\n{\"b\":2}";
+        let mut lines = reader(input, StdioFraming::Auto, DEFAULT_MAX_MESSAGE_SIZE);
+        assert_eq!(read_all(&mut lines).await, ["{\"a\":1}", "{\"b\":2}"]);
+        assert_eq!(*lines.framing.lock().unwrap(), StdioFraming::Newline);
+
+        assert_eq!(
+            frame("{}", StdioFraming::ContentLength),
+            b"Content-Length: 2\r\n\r\n{}"
+        );
+        assert_eq!(frame("{}", StdioFraming::Newline), b"{}\n");
+    }
+
+    #[tokio::test]
+    async fn test_stdio_large_messages() {
+        let large = format!("{{\"data\":\"{}\"}}", "x".repeat(4 * 1024 * 1024));
+        let input = format!("{}\n{{}}\n", large);
+        let mut lines = reader(
+            input.as_bytes(),
+            StdioFraming::Newline,
+            DEFAULT_MAX_MESSAGE_SIZE,
+        );
+        assert_eq!(read_all(&mut lines).await, [large.as_str(), "{}"]);
+
+        // Messages over the limit are skipped without losing the ones after them
+        let input = format!("{}\n{{}}\n", "x".repeat(100));
+        let mut lines = reader(input.as_bytes(), StdioFraming::Newline, 10);
+        assert_eq!(read_all(&mut lines).await, [TOO_LARGE, "{}"]);
+
+        let input = format!(
+            "Content-Length: 100\r\n\r\n{}Content-Length: 2\r\n\r\n{{}}",
+            "x".repeat(100)
+        );
+        let mut headers = reader(input.as_bytes(), StdioFraming::ContentLength, 10);
+        assert_eq!(read_all(&mut headers).await, [TOO_LARGE, "{}"]);
+
+        let error: serde_json::Value = serde_json::from_str(&limit::too_large(10)).unwrap();
+        assert_eq!(error["error"]["code"], super::super::MESSAGE_TOO_LARGE);
+        assert_eq!(error["error"]["data"]["limit"], 10);
+        assert!(error["id"].is_null());
+    }
+}
@@ -0,0 +1,364 @@
+use super::encoding::{self, Encoding, CBOR_MAGIC};
+use super::limit::{self, DEFAULT_MAX_MESSAGE_SIZE};
+use super::stdio::{Frame, FrameReader};
+use super::writer::Writer;
+use super::{ConnectionEvent, ConnectionId, Transport, TransportError, TransportMessage};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio_native_tls::native_tls;
+use tokio_native_tls::TlsAcceptor;
+use tracing::{debug, error};
+
+/// Certificate chain and private key a TLS listener presents to clients
+pub use native_tls::Identity as TlsIdentity;
+
+type TcpWriters = Arc<Mutex<HashMap<ConnectionId, Writer<String>>>>;
+
+/// Connection events kept for subscribers that fall behind
+const EVENTS_CAPACITY: usize = 256;
+
+/// How long to wait before accepting again after the listener fails
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Serves newline-delimited JSON-RPC on plain TCP connections, optionally over TLS
+///
+/// Every connection is a client of its own, with messages written one per
+/// line in both directions, or as CBOR for clients asking for it once
+/// enabled with `with_cbor`.
+#[derive(Clone)]
+pub struct TcpTransport {
+    addr: String,
+    writers: TcpWriters,
+    tls: Option<TlsAcceptor>,
+    max_message_size: usize,
+    cbor: bool,
+    events: broadcast::Sender<ConnectionEvent>,
+}
+
+impl TcpTransport {
+    pub fn new(addr: String) -> Self {
+        Self {
+            addr,
+            writers: Arc::new(Mutex::new(HashMap::new())),
+            tls: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            cbor: false,
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+        }
+    }
+
+    /// Requires clients to connect over TLS, presenting `identity`
+    pub fn with_tls(mut self, identity: TlsIdentity) -> Result<Self, native_tls::Error> {
+        let acceptor = native_tls::TlsAcceptor::new(identity)?;
+        self.tls = Some(TlsAcceptor::from(acceptor));
+        Ok(self)
+    }
+
+    /// Skips messages longer than `bytes` instead of reading them, answering
+    /// them with a [`MESSAGE_TOO_LARGE`](super::MESSAGE_TOO_LARGE) error
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Lets clients that open with [`CBOR_MAGIC`] exchange length-prefixed
+    /// CBOR instead of JSON lines, echoing the magic to agree
+    pub fn with_cbor(mut self) -> Self {
+        self.cbor = true;
+        self
+    }
+
+    /// Completes the TLS handshake if there is one and serves the connection
+    async fn accept(self, stream: TcpStream, request_tx: mpsc::Sender<TransportMessage>) {
+        match self.tls.clone() {
+            Some(tls) => match tls.accept(stream).await {
+                Ok(stream) => self.serve(stream, request_tx).await,
+                Err(e) => error!("TLS handshake failed: {}", e),
+            },
+            None => self.serve(stream, request_tx).await,
+        }
+    }
+
+    /// Reads messages from `stream` until the client disconnects
+    async fn serve<S>(self, stream: S, request_tx: mpsc::Sender<TransportMessage>)
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let connection = ConnectionId::new();
+        debug!("New TCP connection: {}", connection);
+
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+        let encoding = match self.negotiate(&mut reader, &mut writer).await {
+            Ok(encoding) => encoding,
+            Err(e) => {
+                error!("TCP error on {}: {}", connection, e);
+                return;
+            }
+        };
+        let messages =
+            futures::sink::unfold(writer, move |mut writer, message: String| async move {
+                match encoding {
+                    Encoding::Json => {
+                        writer.write_all(message.as_bytes()).await?;
+                        writer.write_all(b"\n").await?;
+                    }
+                    Encoding::Cbor => writer.write_all(&encoding::to_cbor(&message)?).await?,
+                }
+                writer.flush().await?;
+                Ok::<_, io::Error>(writer)
+            });
+        let writer = Writer::spawn(connection.clone(), Box::pin(messages));
+        self.writers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(connection.clone(), writer.clone());
+        let _ = self
+            .events
+            .send(ConnectionEvent::Opened(connection.clone()));
+
+        let mut reader = match encoding {
+            Encoding::Json => Incoming::Json(FrameReader::newline(reader, self.max_message_size)),
+            Encoding::Cbor => Incoming::Cbor(reader),
+        };
+        loop {
+            let message = match reader.next_message(self.max_message_size).await {
+                Ok(Some(Frame::Message(message))) => message,
+                Ok(Some(Frame::TooLarge)) => {
+                    if writer
+                        .send(limit::too_large(self.max_message_size))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                    continue;
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    error!("TCP error on {}: {}", connection, e);
+                    break;
+                }
+            };
+            debug!("Received [tcp {}]: {}", connection, message);
+            let message = TransportMessage {
+                connection: connection.clone(),
+                message,
+            };
+            if request_tx.send(message).await.is_err() {
+                error!("Failed to send request through channel");
+                break;
+            }
+        }
+
+        let removed = self
+            .writers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&connection);
+        if removed.is_some() {
+            debug!("TCP connection closed: {}", connection);
+            let _ = self.events.send(ConnectionEvent::Closed(connection));
+        }
+    }
+
+    /// The encoding the client asks for, echoing [`CBOR_MAGIC`] when it's CBOR
+    ///
+    /// Without CBOR enabled nothing is read, so clients speaking first aren't
+    /// waited for.
+    async fn negotiate<R, W>(&self, reader: &mut R, writer: &mut W) -> io::Result<Encoding>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        if !self.cbor || !encoding::asks_for_cbor(reader).await? {
+            return Ok(Encoding::Json);
+        }
+        writer.write_all(&CBOR_MAGIC).await?;
+        writer.flush().await?;
+        Ok(Encoding::Cbor)
+    }
+
+    /// The writer of `connection`, if it's still open
+    fn writer(&self, connection: &ConnectionId) -> Option<Writer<String>> {
+        let writers = self.writers.lock().unwrap_or_else(|e| e.into_inner());
+        writers.get(connection).cloned()
+    }
+}
+
+/// Reads a connection's messages in the encoding agreed on
+enum Incoming<R> {
+    Json(FrameReader<R>),
+    Cbor(R),
+}
+
+impl<R: AsyncBufRead + Unpin> Incoming<R> {
+    async fn next_message(&mut self, max_message_size: usize) -> io::Result<Option<Frame>> {
+        match self {
+            Incoming::Json(reader) => reader.next_message().await,
+            Incoming::Cbor(reader) => encoding::read_cbor(reader, max_message_size).await,
+        }
+    }
+}
+
+impl Transport for TcpTransport {
+    fn start(
+        &mut self,
+        request_tx: mpsc::Sender<TransportMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let transport = self.clone();
+
+        Box::pin(async move {
+            let addr = &transport.addr;
+            let listener = TcpListener::bind(addr)
+                .await
+                .map_err(TransportError::bind(addr))?;
+            debug!("TCP server listening on: {}", addr);
+
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(transport.clone().accept(stream, request_tx.clone()));
+                    }
+                    Err(e) => {
+                        error!("Failed to accept TCP connection: {}", e);
+                        tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                    }
+                }
+            }
+        })
+    }
+
+    fn send_response(
+        &mut self,
+        connection: ConnectionId,
+        response: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        Box::pin(async move {
+            if response.is_empty() {
+                return Ok(());
+            }
+            let Some(writer) = self.writer(&connection) else {
+                return Ok(());
+            };
+            debug!("Sending [tcp {}]: {}", connection, response);
+            if writer.send(response).await.is_err() {
+                debug!("Dropped a message for closed connection {}", connection);
+            }
+            Ok(())
+        })
+    }
+
+    fn broadcast(
+        &mut self,
+        message: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        Box::pin(async move {
+            if !message.is_empty() {
+                let writers: Vec<_> = {
+                    let writers = self.writers.lock().unwrap_or_else(|e| e.into_inner());
+                    writers
+                        .iter()
+                        .map(|(id, w)| (id.clone(), w.clone()))
+                        .collect()
+                };
+                debug!("Broadcasting [tcp] to {}: {}", writers.len(), message);
+                for (connection, writer) in writers {
+                    if writer.send(message.clone()).await.is_err() {
+                        debug!("Not broadcasting to closed connection {}", connection);
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn flush(&mut self) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        Box::pin(async move {
+            let writers: Vec<_> = {
+                let writers = self.writers.lock().unwrap_or_else(|e| e.into_inner());
+                writers.values().cloned().collect()
+            };
+            for writer in writers {
+                let _ = writer.flush().await;
+            }
+            Ok(())
+        })
+    }
+
+    fn connection_events(&self) -> Option<broadcast::Receiver<ConnectionEvent>> {
+        Some(self.events.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncBufReadExt;
+
+    #[tokio::test]
+    async fn test_tcp_routes_per_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let mut transport = TcpTransport::new(addr.clone());
+        let mut events = transport.connection_events().unwrap();
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        let first = loop {
+            match TcpStream::connect(&addr).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let second = TcpStream::connect(&addr).await.unwrap();
+        let (first_read, mut first_write) = first.into_split();
+        let (second_read, mut second_write) = second.into_split();
+        let mut first_read = BufReader::new(first_read).lines();
+        let mut second_read = BufReader::new(second_read).lines();
+
+        // Messages split across writes still arrive whole
+        first_write.write_all(b"{\"id\":").await.unwrap();
+        first_write.write_all(b"1}\n").await.unwrap();
+        let from_first = request_rx.recv().await.unwrap();
+        second_write.write_all(b"{\"id\":2}\n").await.unwrap();
+        let from_second = request_rx.recv().await.unwrap();
+        assert_eq!(from_first.message, "{\"id\":1}");
+        assert_eq!(from_second.message, "{\"id\":2}");
+        assert_ne!(from_first.connection, from_second.connection);
+
+        transport
+            .send_response(from_second.connection.clone(), "reply".to_string())
+            .await
+            .unwrap();
+        assert_eq!(second_read.next_line().await.unwrap().unwrap(), "reply");
+
+        transport.broadcast("all".to_string()).await.unwrap();
+        assert_eq!(first_read.next_line().await.unwrap().unwrap(), "all");
+        assert_eq!(second_read.next_line().await.unwrap().unwrap(), "all");
+
+        // Disconnecting is announced once the connection's reader sees it
+        for _ in 0..2 {
+            assert!(matches!(
+                events.recv().await.unwrap(),
+                ConnectionEvent::Opened(_)
+            ));
+        }
+        drop(second_write);
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Closed(from_second.connection)
+        );
+        assert_eq!(transport.writers.lock().unwrap().len(), 1);
+    }
+}
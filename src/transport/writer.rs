@@ -0,0 +1,138 @@
+use super::ConnectionId;
+use futures::{Sink, SinkExt};
+use std::fmt;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error};
+
+/// Messages queued for a single connection before senders wait for room
+pub(crate) const WRITER_CAPACITY: usize = 32;
+
+enum Outbound<T> {
+    Message(T),
+    /// Answered once every message queued before it is written
+    Flush(oneshot::Sender<()>),
+}
+
+/// Queue in front of a task that owns one connection's sink
+///
+/// Messages are written whole and in the order they were queued, whichever
+/// task queued them, and a slow connection only holds up its own senders.
+/// The task stops when the sink fails or every clone is dropped.
+pub(crate) struct Writer<T> {
+    queue: mpsc::Sender<Outbound<T>>,
+}
+
+impl<T> Clone for Writer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+/// The connection's writer has stopped, so the message wasn't sent
+#[derive(Debug)]
+pub(crate) struct WriterClosed;
+
+impl fmt::Display for WriterClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The connection's writer has stopped")
+    }
+}
+
+impl std::error::Error for WriterClosed {}
+
+impl<T: Send + 'static> Writer<T> {
+    /// Starts the task writing to `sink`, which must be called from within a Tokio runtime
+    pub(crate) fn spawn<S>(connection: ConnectionId, mut sink: S) -> Self
+    where
+        S: Sink<T> + Send + Unpin + 'static,
+        S::Error: fmt::Display,
+    {
+        let (queue, mut outbound) = mpsc::channel(WRITER_CAPACITY);
+        tokio::spawn(async move {
+            while let Some(item) = outbound.recv().await {
+                match item {
+                    Outbound::Message(message) => {
+                        if let Err(e) = sink.send(message).await {
+                            error!("Failed to write to {}: {}", connection, e);
+                            return;
+                        }
+                    }
+                    Outbound::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+            debug!("Writer for {} stopped", connection);
+            let _ = sink.close().await;
+        });
+        Self { queue }
+    }
+
+    /// Queues `message`, waiting while the connection's queue is full
+    pub(crate) async fn send(&self, message: T) -> Result<(), WriterClosed> {
+        self.queue
+            .send(Outbound::Message(message))
+            .await
+            .map_err(|_| WriterClosed)
+    }
+
+    /// Waits until every message queued so far is written
+    pub(crate) async fn flush(&self) -> Result<(), WriterClosed> {
+        let (done, written) = oneshot::channel();
+        self.queue
+            .send(Outbound::Flush(done))
+            .await
+            .map_err(|_| WriterClosed)?;
+        written.await.map_err(|_| WriterClosed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc as sink;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_writer_order() {
+        let (sink_tx, sink_rx) = sink::unbounded::<String>();
+        let writer = Writer::spawn(ConnectionId::from("a"), sink_tx);
+
+        // Handlers racing to answer still get their messages written whole, in queue order
+        let mut senders = Vec::new();
+        for task in 0..4 {
+            let writer = writer.clone();
+            senders.push(tokio::spawn(async move {
+                for i in 0..50 {
+                    writer.send(format!("{}-{}", task, i)).await.unwrap();
+                }
+            }));
+        }
+        for sender in senders {
+            sender.await.unwrap();
+        }
+        writer.flush().await.unwrap();
+        drop(writer);
+
+        let written: Vec<String> = sink_rx.collect().await;
+        assert_eq!(written.len(), 200);
+        for task in 0..4 {
+            let prefix = format!("{}-", task);
+            let sequence: Vec<u32> = written
+                .iter()
+                .filter_map(|m| m.strip_prefix(&prefix))
+                .map(|i| i.parse().unwrap())
+                .collect();
+            assert_eq!(sequence, (0..50).collect::<Vec<_>>());
+        }
+
+        // A writer whose sink failed refuses further messages
+        let (sink_tx, sink_rx) = sink::unbounded::<String>();
+        drop(sink_rx);
+        let writer = Writer::spawn(ConnectionId::from("b"), sink_tx);
+        let _ = writer.send("lost".to_string()).await;
+        assert!(writer.flush().await.is_err());
+    }
+}
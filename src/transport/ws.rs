@@ -0,0 +1,1022 @@
+use super::deflate::{self, Deflate};
+use super::health::{self, Health};
+use super::limit::{self, DEFAULT_MAX_MESSAGE_SIZE};
+use super::origin::OriginPolicy;
+use super::resume::{ResumeConfig, Suspended};
+use super::writer::Writer;
+use super::{
+    ConnectionEvent, ConnectionId, Transport, TransportError, TransportMessage, SESSION_ID_HEADER,
+};
+use futures::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::{broadcast, mpsc, Notify},
+    task::AbortHandle,
+};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_EXTENSIONS;
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{CloseCode, Data, OpCode};
+use tokio_tungstenite::tungstenite::protocol::frame::Frame;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
+use tokio_tungstenite::tungstenite::{Bytes, Error as WsError};
+use tokio_tungstenite::{accept_hdr_async_with_config, tungstenite::Message, WebSocketStream};
+use tracing::{debug, error};
+
+type WsStream = WebSocketStream<Deflate<TcpStream>>;
+
+/// How long a connection closed for a message too large stays open for the
+/// client to read the error
+const CLOSE_LINGER: Duration = Duration::from_secs(1);
+type WsWriters = Arc<Mutex<HashMap<ConnectionId, Writer<Message>>>>;
+
+/// The writer of `connection`, if it's still open
+fn writer(writers: &WsWriters, connection: &ConnectionId) -> Option<Writer<Message>> {
+    let writers = writers.lock().unwrap_or_else(|e| e.into_inner());
+    writers.get(connection).cloned()
+}
+
+/// Forgets `connection`, announcing it closed unless it already was
+///
+/// With resumption on, a connection that dropped is only suspended, unless
+/// `end` says the server itself ends its session.
+fn close(
+    transport: &WebSocketTransport,
+    connection: &ConnectionId,
+    end: bool,
+) -> Option<Writer<Message>> {
+    let writer = transport
+        .writers
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(connection)?;
+    debug!("WebSocket connection closed: {}", connection);
+    match transport.resumption {
+        Some(config) if !end => {
+            transport
+                .suspended
+                .suspend(connection.clone(), config, transport.events.clone());
+        }
+        _ => {
+            let _ = transport
+                .events
+                .send(ConnectionEvent::Closed(connection.clone()));
+        }
+    }
+    Some(writer)
+}
+
+/// Session token a reconnecting client sent, in the `Mcp-Session-Id`
+/// header or, for browsers that can't set headers, the `session` query parameter
+fn session_token(request: &Request) -> Option<ConnectionId> {
+    if let Some(token) = request
+        .headers()
+        .get(SESSION_ID_HEADER.as_str())
+        .and_then(|v| v.to_str().ok())
+    {
+        return Some(ConnectionId::from(token));
+    }
+    let query = request.uri().query()?;
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == "session")
+        .map(|(_, token)| ConnectionId::from(token.into_owned()))
+}
+
+/// Connection events kept for subscribers that fall behind
+const EVENTS_CAPACITY: usize = 256;
+
+/// How long to wait before accepting again after the listener fails, such as
+/// when the process is out of file descriptors
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// `message` as frames of at most `size` bytes, if it's text or binary longer than that
+fn fragments(message: Message, size: usize) -> Vec<Message> {
+    let (payload, opcode) = match message {
+        Message::Text(text) if text.len() > size => (Bytes::from(text), Data::Text),
+        Message::Binary(data) if data.len() > size => (data, Data::Binary),
+        message => return vec![message],
+    };
+    let count = payload.len().div_ceil(size);
+    (0..count)
+        .map(|i| {
+            let chunk = payload.slice(i * size..payload.len().min((i + 1) * size));
+            let opcode = if i == 0 { opcode } else { Data::Continue };
+            Message::Frame(Frame::message(chunk, OpCode::Data(opcode), i + 1 == count))
+        })
+        .collect()
+}
+
+/// How often the server pings idle clients, and how long it waits for a reply
+#[derive(Clone, Copy, Debug)]
+pub struct KeepAliveConfig {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+#[derive(Clone)]
+pub struct WebSocketTransport {
+    addr: String,
+    writers: WsWriters,
+    keepalive: Option<KeepAliveConfig>,
+    idle_timeout: Option<Duration>,
+    resumption: Option<ResumeConfig>,
+    suspended: Suspended,
+    max_message_size: usize,
+    /// Messages at least this long are compressed for clients that support it
+    compression: Option<usize>,
+    /// Messages longer than this are sent as frames of at most this many bytes
+    fragment_size: Option<usize>,
+    origins: Arc<OriginPolicy>,
+    health: Health,
+    events: broadcast::Sender<ConnectionEvent>,
+}
+
+impl WebSocketTransport {
+    pub fn new(addr: String) -> Self {
+        Self {
+            addr,
+            writers: Arc::new(Mutex::new(HashMap::new())),
+            keepalive: None,
+            idle_timeout: None,
+            resumption: None,
+            suspended: Suspended::default(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            compression: None,
+            fragment_size: None,
+            origins: Arc::new(OriginPolicy::default()),
+            health: Health::default(),
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+        }
+    }
+
+    /// Sets which web pages may connect, allowing localhost by default
+    pub fn with_origins(mut self, origins: OriginPolicy) -> Self {
+        self.origins = Arc::new(origins);
+        self
+    }
+
+    /// Sends a `ping` request to every client each `interval`, dropping
+    /// connections that don't answer within `timeout`
+    pub fn with_keepalive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.keepalive = Some(KeepAliveConfig { interval, timeout });
+        self
+    }
+
+    /// Closes connections that send no messages for `timeout`
+    ///
+    /// Replies to keepalive pings don't count, so a client that only answers
+    /// pings is still dropped.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Keeps the session of a client that drops for `grace`, so it can
+    /// reconnect and pick up where it left off
+    ///
+    /// Each upgrade is answered with the session's token in the
+    /// `Mcp-Session-Id` header. A client reconnecting with it, in the same
+    /// header or the `session` query parameter, keeps its negotiated
+    /// capabilities and subscriptions, and first receives up to `backlog`
+    /// messages sent while it was away. Clients dropped by the idle timeout
+    /// can't resume.
+    pub fn with_resumption(mut self, grace: Duration, backlog: usize) -> Self {
+        self.resumption = Some(ResumeConfig { grace, backlog });
+        self
+    }
+
+    /// Closes connections that send a message longer than `bytes`, answering
+    /// it first with a [`MESSAGE_TOO_LARGE`](super::MESSAGE_TOO_LARGE) error
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Negotiates permessage-deflate with clients that offer it, compressing
+    /// the messages sent to them of at least `threshold` bytes
+    pub fn with_compression(mut self, threshold: usize) -> Self {
+        self.compression = Some(threshold);
+        self
+    }
+
+    /// Sends messages longer than `bytes` as several frames of at most that
+    /// size, so clients with a frame limit below their message limit can
+    /// still receive large resources
+    ///
+    /// Fragmented messages aren't compressed.
+    pub fn with_fragment_size(mut self, bytes: usize) -> Self {
+        self.fragment_size = Some(bytes.max(1));
+        self
+    }
+
+    /// Upgrades a client's stream and serves it until the connection closes
+    async fn accept(self, stream: TcpStream, request_tx: mpsc::Sender<TransportMessage>) {
+        // Health probes are plain HTTP requests on the same port
+        if let Some(path) = health::probed_path(&stream).await {
+            health::answer_probe(stream, path, &self.health, "websocket").await;
+            return;
+        }
+        let mut connection = ConnectionId::new();
+        let mut resumed = false;
+        let mut compressed = false;
+        // The handshake callback's signature is set by tungstenite
+        #[allow(clippy::result_large_err)]
+        let check_origin = |request: &Request, mut response: Response| {
+            if !self.origins.allows(request.headers()) {
+                let mut rejection = ErrorResponse::new(Some("Origin not allowed".to_string()));
+                *rejection.status_mut() = StatusCode::FORBIDDEN;
+                return Err(rejection);
+            }
+            if self.resumption.is_some() {
+                if let Some(token) = session_token(request) {
+                    resumed = self.suspended.claim(&token);
+                    if resumed {
+                        connection = token;
+                    } else {
+                        debug!("Can't resume unknown or expired session {}", token);
+                    }
+                }
+                if let Ok(value) = connection.as_str().parse() {
+                    response.headers_mut().insert(SESSION_ID_HEADER, value);
+                }
+            }
+            if self.compression.is_some() {
+                compressed = request
+                    .headers()
+                    .get_all(SEC_WEBSOCKET_EXTENSIONS)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .any(deflate::offered);
+                if compressed {
+                    response.headers_mut().insert(
+                        SEC_WEBSOCKET_EXTENSIONS,
+                        HeaderValue::from_static(deflate::ACCEPTED),
+                    );
+                }
+            }
+            Ok(response)
+        };
+        let config = WebSocketConfig::default()
+            .max_message_size(Some(self.max_message_size))
+            .max_frame_size(Some(self.max_message_size));
+        let stream = Deflate::new(stream);
+        let mut ws_stream =
+            match accept_hdr_async_with_config(stream, check_origin, Some(config)).await {
+                Ok(ws_stream) => ws_stream,
+                Err(e) => {
+                    error!("Failed to accept WebSocket connection: {}", e);
+                    if resumed && self.suspended.release(&connection) {
+                        let _ = self.events.send(ConnectionEvent::Closed(connection));
+                    }
+                    return;
+                }
+            };
+        if let Some(threshold) = self.compression.filter(|_| compressed) {
+            debug!(
+                "Compressing messages of {} with permessage-deflate",
+                connection
+            );
+            ws_stream.get_mut().enable(threshold, self.max_message_size);
+        }
+
+        let (ws_writer, ws_reader) = ws_stream.split();
+        let writer = match self.fragment_size {
+            Some(size) => {
+                let ws_writer = ws_writer.with_flat_map(move |message| {
+                    futures::stream::iter(fragments(message, size).into_iter().map(Ok))
+                });
+                Writer::spawn(connection.clone(), ws_writer)
+            }
+            None => Writer::spawn(connection.clone(), ws_writer),
+        };
+        if resumed {
+            debug!("Resumed WebSocket session: {}", connection);
+            self.resume(&connection, writer).await;
+            let _ = self
+                .events
+                .send(ConnectionEvent::Resumed(connection.clone()));
+        } else {
+            debug!("New WebSocket connection: {}", connection);
+            self.writers
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(connection.clone(), writer);
+            let _ = self
+                .events
+                .send(ConnectionEvent::Opened(connection.clone()));
+        }
+
+        let state = self.keepalive.map(|_| Arc::new(KeepAliveState::default()));
+        let reader = tokio::spawn(read_connection(
+            connection.clone(),
+            ws_reader,
+            self.clone(),
+            request_tx,
+            state.clone(),
+        ));
+
+        if let (Some(config), Some(state)) = (self.keepalive, state) {
+            tokio::spawn(keep_alive(
+                connection,
+                config,
+                state,
+                self.clone(),
+                reader.abort_handle(),
+            ));
+        }
+    }
+
+    /// Writes what a resumed connection missed, then sends it messages directly
+    async fn resume(&self, connection: &ConnectionId, writer: Writer<Message>) {
+        loop {
+            let backlog = self.suspended.drain(connection, || {
+                self.writers
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(connection.clone(), writer.clone());
+            });
+            if backlog.is_empty() {
+                return;
+            }
+            debug!("Replaying {} messages to {}", backlog.len(), connection);
+            for message in backlog {
+                // A writer that stopped has lost the connection again, which the reader notices
+                let _ = writer.send(Message::Text(message.into())).await;
+            }
+        }
+    }
+}
+
+/// The keepalive ping awaiting a reply on a single connection
+#[derive(Default)]
+struct KeepAliveState {
+    pending: std::sync::Mutex<Option<String>>,
+    answered: Notify,
+}
+
+impl KeepAliveState {
+    /// Consumes `text` if it answers the pending ping, so it never reaches the server
+    fn take_reply(&self, text: &str) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(id) = pending.as_deref() else {
+            return false;
+        };
+
+        let is_reply = serde_json::from_str::<serde_json::Value>(text).is_ok_and(|message| {
+            message.get("method").is_none()
+                && message.get("id").and_then(|v| v.as_str()) == Some(id)
+        });
+        if is_reply {
+            *pending = None;
+            self.answered.notify_one();
+        }
+        is_reply
+    }
+}
+
+async fn keep_alive(
+    connection: ConnectionId,
+    config: KeepAliveConfig,
+    state: Arc<KeepAliveState>,
+    transport: WebSocketTransport,
+    reader: AbortHandle,
+) {
+    let mut ticker = tokio::time::interval(config.interval);
+    // The first tick completes immediately
+    ticker.tick().await;
+
+    for sequence in 0u64.. {
+        ticker.tick().await;
+
+        let id = format!("keepalive-{}", sequence);
+        *state.pending.lock().unwrap_or_else(|e| e.into_inner()) = Some(id.clone());
+        let ping = serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": "ping" }).to_string();
+
+        let Some(writer) = writer(&transport.writers, &connection) else {
+            // The connection is already gone
+            return;
+        };
+        debug!("Sending [websocket {}]: {}", connection, ping);
+        if writer.send(Message::Text(ping.into())).await.is_err() {
+            return;
+        }
+
+        if tokio::time::timeout(config.timeout, state.answered.notified())
+            .await
+            .is_err()
+        {
+            error!("WebSocket keepalive timed out, dropping {}", connection);
+            reader.abort();
+            if let Some(writer) = close(&transport, &connection, false) {
+                let _ = writer.send(Message::Close(None)).await;
+            }
+            return;
+        }
+    }
+}
+
+async fn read_connection(
+    connection: ConnectionId,
+    mut ws_reader: futures::stream::SplitStream<WsStream>,
+    transport: WebSocketTransport,
+    request_tx: mpsc::Sender<TransportMessage>,
+    keepalive: Option<Arc<KeepAliveState>>,
+) {
+    let idle = tokio::time::sleep(transport.idle_timeout.unwrap_or(Duration::MAX));
+    tokio::pin!(idle);
+
+    loop {
+        let msg = tokio::select! {
+            msg = ws_reader.next() => msg,
+            _ = &mut idle, if transport.idle_timeout.is_some() => {
+                debug!("Dropping idle WebSocket connection {}", connection);
+                if let Some(writer) = close(&transport, &connection, true) {
+                    let _ = writer.send(Message::Close(None)).await;
+                }
+                return;
+            }
+        };
+        let Some(msg) = msg else {
+            break;
+        };
+        match msg {
+            Ok(Message::Text(text)) => {
+                debug!("Received [websocket {}]: {}", connection, text);
+                if keepalive
+                    .as_ref()
+                    .is_some_and(|state| state.take_reply(&text))
+                {
+                    continue;
+                }
+                if let Some(timeout) = transport.idle_timeout {
+                    idle.as_mut().reset(tokio::time::Instant::now() + timeout);
+                }
+                let message = TransportMessage {
+                    connection: connection.clone(),
+                    message: text.to_string(),
+                };
+                if request_tx.send(message).await.is_err() {
+                    error!("Failed to send request through channel");
+                    break;
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Err(WsError::Capacity(e)) => {
+                error!("Closing WebSocket connection {}: {}", connection, e);
+                if let Some(writer) = close(&transport, &connection, true) {
+                    let error = limit::too_large(transport.max_message_size);
+                    let _ = writer.send(Message::Text(error.into())).await;
+                    let frame = CloseFrame {
+                        code: CloseCode::Size,
+                        reason: "Message too large".into(),
+                    };
+                    let _ = writer.send(Message::Close(Some(frame))).await;
+                    let _ = writer.flush().await;
+                    // The rest of the message can't be read, and closing the
+                    // socket on it right away would reset the connection
+                    // before the client reads why
+                    tokio::time::sleep(CLOSE_LINGER).await;
+                }
+                return;
+            }
+            Err(e) => {
+                error!("WebSocket error on {}: {}", connection, e);
+                break;
+            }
+            _ => continue,
+        }
+    }
+    close(&transport, &connection, false);
+}
+
+impl Transport for WebSocketTransport {
+    fn start(
+        &mut self,
+        request_tx: mpsc::Sender<TransportMessage>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let transport = self.clone();
+
+        Box::pin(async move {
+            let addr = &transport.addr;
+            let listener = TcpListener::bind(addr)
+                .await
+                .map_err(TransportError::bind(addr))?;
+            debug!("WebSocket server listening on: {}", addr);
+            transport.health.set_listening();
+
+            // A connection that fails only affects itself, and a slow
+            // handshake doesn't hold up the ones behind it
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(transport.clone().accept(stream, request_tx.clone()));
+                    }
+                    Err(e) => {
+                        error!("Failed to accept WebSocket connection: {}", e);
+                        tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                    }
+                }
+            }
+        })
+    }
+
+    fn send_response(
+        &mut self,
+        connection: ConnectionId,
+        response: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let writers = self.writers.clone();
+        let suspended = self.suspended.clone();
+        let backlog = self.resumption.map_or(0, |config| config.backlog);
+        Box::pin(async move {
+            if response.is_empty() {
+                return Ok(());
+            }
+            let writer = match writer(&writers, &connection) {
+                Some(writer) => writer,
+                // Kept for the client to receive when it resumes
+                None if suspended.buffer(&connection, response.clone(), backlog) => return Ok(()),
+                // The connection may have resumed since it was looked up
+                None => match writer(&writers, &connection) {
+                    Some(writer) => writer,
+                    None => return Ok(()),
+                },
+            };
+            debug!("Sending [websocket {}]: {}", connection, response);
+            // A writer that stopped has lost its connection, which the reader cleans up after
+            if writer.send(Message::Text(response.into())).await.is_err() {
+                debug!("Dropped a message for closed connection {}", connection);
+            }
+            Ok(())
+        })
+    }
+
+    fn broadcast(
+        &mut self,
+        message: String,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let writers = self.writers.clone();
+        Box::pin(async move {
+            if !message.is_empty() {
+                let writers: Vec<_> = {
+                    let writers = writers.lock().unwrap_or_else(|e| e.into_inner());
+                    writers
+                        .iter()
+                        .map(|(id, w)| (id.clone(), w.clone()))
+                        .collect()
+                };
+                debug!("Broadcasting [websocket] to {}: {}", writers.len(), message);
+                for (connection, writer) in writers {
+                    if writer
+                        .send(Message::Text(message.clone().into()))
+                        .await
+                        .is_err()
+                    {
+                        debug!("Not broadcasting to closed connection {}", connection);
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn flush(&mut self) -> Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + '_>> {
+        let writers = self.writers.clone();
+        Box::pin(async move {
+            let writers: Vec<_> = {
+                let writers = writers.lock().unwrap_or_else(|e| e.into_inner());
+                writers.values().cloned().collect()
+            };
+            for writer in writers {
+                let _ = writer.flush().await;
+            }
+            Ok(())
+        })
+    }
+
+    fn health(&self) -> Option<Health> {
+        Some(self.health.clone())
+    }
+
+    fn connection_events(&self) -> Option<broadcast::Receiver<ConnectionEvent>> {
+        Some(self.events.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::SinkExt;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::{connect_async, connect_async_with_config};
+
+    #[tokio::test]
+    async fn test_websocket_routes_per_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let mut transport = WebSocketTransport::new(addr.clone());
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        let url = format!("ws://{}", addr);
+        let mut first = loop {
+            match connect_async(&url).await {
+                Ok((client, _)) => break client,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        let (mut second, _) = connect_async(&url).await.unwrap();
+
+        first.send(Message::Text("one".into())).await.unwrap();
+        let from_first = request_rx.recv().await.unwrap();
+        second.send(Message::Text("two".into())).await.unwrap();
+        let from_second = request_rx.recv().await.unwrap();
+        assert_eq!(from_first.message, "one");
+        assert_eq!(from_second.message, "two");
+        assert_ne!(from_first.connection, from_second.connection);
+
+        transport
+            .send_response(from_second.connection, "reply".to_string())
+            .await
+            .unwrap();
+        let reply = second.next().await.unwrap().unwrap();
+        assert_eq!(reply.into_text().unwrap().as_str(), "reply");
+
+        transport.broadcast("all".to_string()).await.unwrap();
+        for client in [&mut first, &mut second] {
+            let message = client.next().await.unwrap().unwrap();
+            assert_eq!(message.into_text().unwrap().as_str(), "all");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_websocket_message_size_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let transport = WebSocketTransport::new(addr.clone()).with_max_message_size(64);
+        let mut events = transport.events.subscribe();
+        let (request_tx, _request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        let url = format!("ws://{}", addr);
+        let mut client = loop {
+            match connect_async(&url).await {
+                Ok((client, _)) => break client,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        let _ = client.send(Message::Text("x".repeat(100).into())).await;
+
+        // The client is told why before the connection closes
+        let error = client.next().await.unwrap().unwrap();
+        let error: serde_json::Value = serde_json::from_str(error.to_text().unwrap()).unwrap();
+        assert_eq!(error["error"]["code"], super::super::MESSAGE_TOO_LARGE);
+        match client.next().await.unwrap().unwrap() {
+            Message::Close(Some(frame)) => assert_eq!(frame.code, CloseCode::Size),
+            other => panic!("Expected a close frame, got {:?}", other),
+        }
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Opened(_)
+        ));
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Closed(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_websocket_compression_negotiation() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let transport = WebSocketTransport::new(addr.clone()).with_compression(1024);
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        let url = format!("ws://{}", addr);
+        let offer = || {
+            let mut request = url.as_str().into_client_request().unwrap();
+            request.headers_mut().insert(
+                SEC_WEBSOCKET_EXTENSIONS,
+                HeaderValue::from_static("permessage-deflate; client_max_window_bits"),
+            );
+            request
+        };
+        let (mut client, response) = loop {
+            match connect_async(offer()).await {
+                Ok(connected) => break connected,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        assert_eq!(
+            response.headers()[SEC_WEBSOCKET_EXTENSIONS],
+            deflate::ACCEPTED
+        );
+        // Messages under the threshold go as they are, readable without the extension
+        client.send(Message::Text("hi".into())).await.unwrap();
+        let request = request_rx.recv().await.unwrap();
+        let mut transport = transport;
+        transport
+            .send_response(request.connection, "hello".to_string())
+            .await
+            .unwrap();
+        let reply = client.next().await.unwrap().unwrap();
+        assert_eq!(reply.into_text().unwrap().as_str(), "hello");
+
+        // Clients that don't offer it don't get it
+        let (_client, response) = connect_async(&url).await.unwrap();
+        assert!(response.headers().get(SEC_WEBSOCKET_EXTENSIONS).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_websocket_fragmentation() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let transport = WebSocketTransport::new(addr.clone()).with_fragment_size(64);
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        // The client refuses frames longer than the fragments
+        let config = WebSocketConfig::default().max_frame_size(Some(64));
+        let url = format!("ws://{}", addr);
+        let mut client = loop {
+            match connect_async_with_config(&url, Some(config), false).await {
+                Ok((client, _)) => break client,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        client.send(Message::Text("hi".into())).await.unwrap();
+        let request = request_rx.recv().await.unwrap();
+        let mut transport = transport;
+        let long = "é".repeat(100);
+        transport
+            .send_response(request.connection, long.clone())
+            .await
+            .unwrap();
+        let reply = client.next().await.unwrap().unwrap();
+        assert_eq!(reply.into_text().unwrap().as_str(), long);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_keepalive() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let transport = WebSocketTransport::new(addr.clone())
+            .with_keepalive(Duration::from_millis(50), Duration::from_millis(100));
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        let url = format!("ws://{}", addr);
+        let mut responsive = loop {
+            match connect_async(&url).await {
+                Ok((client, _)) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let (mut silent, _) = connect_async(&url).await.unwrap();
+
+        // Answer a few pings on one connection and ignore them on the other
+        for _ in 0..3 {
+            let ping = responsive.next().await.unwrap().unwrap();
+            let ping: serde_json::Value =
+                serde_json::from_str(ping.into_text().unwrap().as_str()).unwrap();
+            assert_eq!(ping["method"], "ping");
+            let pong = serde_json::json!({ "jsonrpc": "2.0", "id": ping["id"], "result": {} });
+            responsive
+                .send(Message::Text(pong.to_string().into()))
+                .await
+                .unwrap();
+        }
+
+        let closed = tokio::time::timeout(Duration::from_secs(2), async {
+            while let Some(Ok(message)) = silent.next().await {
+                if message.is_close() {
+                    break;
+                }
+            }
+        })
+        .await;
+        assert!(closed.is_ok(), "Unresponsive connection should be dropped");
+
+        // Replies to pings are consumed by the transport
+        assert!(request_rx.try_recv().is_err());
+        assert_eq!(transport.writers.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_origin_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let transport = WebSocketTransport::new(addr.clone());
+        let (request_tx, _request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        let url = format!("ws://{}", addr);
+        let with_origin = |origin: &'static str| {
+            let mut request = url.as_str().into_client_request().unwrap();
+            request
+                .headers_mut()
+                .insert("origin", origin.parse().unwrap());
+            request
+        };
+        loop {
+            match connect_async(with_origin("http://localhost:3000")).await {
+                Ok(_) => break,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        }
+
+        // A rejected page doesn't stop the server from accepting others
+        assert!(connect_async(with_origin("http://evil.example.com"))
+            .await
+            .is_err());
+        assert!(connect_async(&url).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_websocket_health_probes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let transport = WebSocketTransport::new(addr.clone());
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        let url = format!("ws://{}", addr);
+        let mut client = loop {
+            match connect_async(&url).await {
+                Ok((client, _)) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        let response = reqwest::get(format!("http://{}/readyz", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let report: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(report["transport"], "websocket");
+        assert_eq!(report["listening"], true);
+
+        // Upgrades still work after a probe
+        client
+            .send(Message::Text("still here".into()))
+            .await
+            .unwrap();
+        assert_eq!(request_rx.recv().await.unwrap().message, "still here");
+    }
+
+    #[tokio::test]
+    async fn test_websocket_resumption() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let mut transport =
+            WebSocketTransport::new(addr.clone()).with_resumption(Duration::from_millis(300), 8);
+        let mut events = transport.connection_events().unwrap();
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        let url = format!("ws://{}", addr);
+        let (mut client, response) = loop {
+            match connect_async(&url).await {
+                Ok(connected) => break connected,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let token = response.headers()["mcp-session-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        client.send(Message::Text("hi".into())).await.unwrap();
+        let connection = request_rx.recv().await.unwrap().connection;
+        assert_eq!(connection.as_str(), token);
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Opened(connection.clone())
+        );
+
+        // Messages sent while the client is away wait for it to come back
+        client.close(None).await.unwrap();
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Suspended(connection.clone())
+        );
+        transport
+            .send_response(connection.clone(), "missed".to_string())
+            .await
+            .unwrap();
+        let resume_url = format!("{}/?session={}", url, token);
+        let (mut client, response) = connect_async(&resume_url).await.unwrap();
+        assert_eq!(response.headers()["mcp-session-id"], token.as_str());
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Resumed(connection.clone())
+        );
+        let missed = client.next().await.unwrap().unwrap();
+        assert_eq!(missed.into_text().unwrap().as_str(), "missed");
+        client.send(Message::Text("back".into())).await.unwrap();
+        assert_eq!(request_rx.recv().await.unwrap().connection, connection);
+
+        // Once the grace period passes, the session is gone
+        drop(client);
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Suspended(connection.clone())
+        );
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Closed(connection.clone())
+        );
+        let mut request = url.as_str().into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("mcp-session-id", token.parse().unwrap());
+        let (_client, response) = connect_async(request).await.unwrap();
+        assert_ne!(response.headers()["mcp-session-id"], token.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_websocket_connection_events() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let transport =
+            WebSocketTransport::new(addr.clone()).with_idle_timeout(Duration::from_millis(200));
+        let mut events = transport.connection_events().unwrap();
+        let (request_tx, mut request_rx) = mpsc::channel(32);
+        let mut server = transport.clone();
+        tokio::spawn(async move { server.start(request_tx).await });
+
+        let url = format!("ws://{}", addr);
+        let mut idle = loop {
+            match connect_async(&url).await {
+                Ok((client, _)) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let ConnectionEvent::Opened(idle_connection) = events.recv().await.unwrap() else {
+            panic!("Expected the connection to open");
+        };
+
+        // A client that drops mid-handshake doesn't stop the listener
+        let stalled = TcpStream::connect(&addr).await.unwrap();
+        drop(stalled);
+
+        let (mut leaving, _) = connect_async(&url).await.unwrap();
+        let ConnectionEvent::Opened(leaving_connection) = events.recv().await.unwrap() else {
+            panic!("Expected the connection to open");
+        };
+        leaving.send(Message::Text("hi".into())).await.unwrap();
+        assert_eq!(request_rx.recv().await.unwrap().message, "hi");
+        leaving.close(None).await.unwrap();
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Closed(leaving_connection)
+        );
+
+        // Clients that stay silent are dropped once the idle timeout passes
+        let closed = tokio::time::timeout(Duration::from_secs(2), async {
+            while let Some(Ok(message)) = idle.next().await {
+                if message.is_close() {
+                    break;
+                }
+            }
+        })
+        .await;
+        assert!(closed.is_ok(), "Idle connection should be dropped");
+        assert_eq!(
+            events.recv().await.unwrap(),
+            ConnectionEvent::Closed(idle_connection)
+        );
+        assert!(transport.writers.lock().unwrap().is_empty());
+    }
+}
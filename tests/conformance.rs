@@ -0,0 +1,69 @@
+//! Runs the conformance suites under `tests/fixtures/conformance` against a
+//! server with tools, a prompt and a subscribable resource
+//!
+//! Servers of your own can be checked the same way with
+//! `bioma_tool::testing::conformance::assert_conformance`.
+
+use bioma_tool::resources::memory::{MemoryContents, MemoryResources};
+use bioma_tool::schema::{CallToolResult, Prompt, PromptArgument};
+use bioma_tool::testing::conformance::assert_conformance;
+use bioma_tool::tools::echo::Echo;
+use bioma_tool::tools::{McpTool, ToolContext, ToolError};
+use bioma_tool::Server;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct WaitProperties {
+    #[schemars(description = "How long to wait", required = true)]
+    milliseconds: u64,
+}
+
+/// A tool that takes a while, for cancelling calls in flight
+#[derive(Clone, Serialize, McpTool)]
+#[mcp_tool(
+    name = "wait",
+    description = "Waits before answering",
+    properties = WaitProperties
+)]
+struct Wait;
+
+impl Wait {
+    async fn run(
+        &self,
+        properties: WaitProperties,
+        _context: ToolContext,
+    ) -> Result<CallToolResult, ToolError> {
+        tokio::time::sleep(Duration::from_millis(properties.milliseconds)).await;
+        Ok(CallToolResult::text("done"))
+    }
+}
+
+fn server() -> Server {
+    let published = MemoryResources::new();
+    published.publish("greeting", MemoryContents::text("text/plain", "Hello"));
+    Server::builder()
+        .tool(Echo)
+        .tool(Wait)
+        .prompt(Prompt {
+            name: "greet".to_string(),
+            description: Some("Greets someone".to_string()),
+            arguments: Some(vec![PromptArgument {
+                name: "name".to_string(),
+                description: None,
+                required: Some(true),
+            }]),
+        })
+        .memory_resources(published)
+        .build()
+}
+
+#[tokio::test]
+async fn test_conformance() {
+    assert_conformance(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/conformance"),
+        server,
+    )
+    .await;
+}